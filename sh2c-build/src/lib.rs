@@ -0,0 +1,247 @@
+//! Compile `.sh2` scripts from a Cargo build script, so a Rust project that ships
+//! shell tooling can embed the generated scripts with `include_str!`.
+//!
+//! ```no_run
+//! // build.rs
+//! fn main() {
+//!     sh2c_build::compile("scripts/*.sh2")
+//!         .out_dir(std::env::var("OUT_DIR").unwrap())
+//!         .run()
+//!         .unwrap();
+//! }
+//! ```
+//!
+//! ```ignore
+//! // src/main.rs
+//! const DEPLOY: &str = include_str!(concat!(env!("OUT_DIR"), "/deploy.sh"));
+//! ```
+//!
+//! Each input becomes `<out_dir>/<name>.sh`. Inside a build script (when Cargo
+//! sets `OUT_DIR`) every input and every file it imports is reported with
+//! `cargo:rerun-if-changed`, and warnings are passed on as `cargo:warning`.
+
+use sh2c::codegen::TargetShell;
+use sh2c::driver::{self, CompileOptions};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub use sh2c::driver::Artifact;
+
+/// Starts a build of the files matching `pattern`. `*` and `?` match within the
+/// last path component only (`scripts/*.sh2`); a pattern without them names a
+/// single file.
+pub fn compile(pattern: &str) -> Build {
+    Build::new().input(pattern)
+}
+
+/// Inputs and options for one build; see [`compile`].
+#[derive(Debug, Clone)]
+pub struct Build {
+    patterns: Vec<String>,
+    out_dir: Option<PathBuf>,
+    options: CompileOptions,
+    cargo: Option<bool>,
+}
+
+impl Default for Build {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Build {
+    pub fn new() -> Self {
+        Build {
+            patterns: Vec::new(),
+            out_dir: None,
+            // No chmod +x: embedded scripts are read as text, not run from OUT_DIR.
+            options: CompileOptions::default(),
+            cargo: None,
+        }
+    }
+
+    /// Adds more files, with the same pattern rules as [`compile`].
+    pub fn input(mut self, pattern: &str) -> Self {
+        self.patterns.push(pattern.to_string());
+        self
+    }
+
+    /// Where the scripts are written; defaults to `$OUT_DIR`.
+    pub fn out_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.out_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn target(mut self, target: TargetShell) -> Self {
+        self.options.target = target;
+        self
+    }
+
+    /// Leave out error-location reporting and its traps (`sh2c --no-diagnostics`).
+    pub fn diagnostics(mut self, on: bool) -> Self {
+        self.options.include_diagnostics = on;
+        self
+    }
+
+    /// Shrink the scripts (`sh2c --minify`).
+    pub fn minify(mut self, on: bool) -> Self {
+        self.options.minify = on;
+        self
+    }
+
+    /// Fail the build when a script is larger than `bytes` (`sh2c --max-size`).
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.options.max_size = Some(bytes);
+        self
+    }
+
+    /// Print `cargo:` directives. On by default when `OUT_DIR` is set.
+    pub fn cargo_metadata(mut self, on: bool) -> Self {
+        self.cargo = Some(on);
+        self
+    }
+
+    /// Compiles every input. All inputs are attempted; the error lists each failure.
+    pub fn run(self) -> Result<Vec<Artifact>, Error> {
+        let out_dir = match self.out_dir {
+            Some(dir) => dir,
+            None => std::env::var_os("OUT_DIR").map(PathBuf::from).ok_or(Error::NoOutDir)?,
+        };
+        let cargo = self.cargo.unwrap_or_else(|| std::env::var_os("OUT_DIR").is_some());
+
+        let mut inputs = Vec::new();
+        for pattern in &self.patterns {
+            let matched = expand(pattern).map_err(|e| Error::Pattern(pattern.clone(), e))?;
+            if matched.is_empty() {
+                return Err(Error::Pattern(pattern.clone(), "no files match".to_string()));
+            }
+            if cargo && is_pattern(pattern) {
+                // New files appearing in the directory should trigger a rebuild too.
+                let dir = Path::new(pattern).parent().filter(|p| !p.as_os_str().is_empty());
+                println!("cargo:rerun-if-changed={}", dir.unwrap_or(Path::new(".")).display());
+            }
+            inputs.extend(matched);
+        }
+
+        let options = CompileOptions { out_dir: Some(out_dir), ..self.options };
+        let artifacts = driver::compile_project(&inputs, &options);
+
+        if cargo {
+            for artifact in &artifacts {
+                println!("cargo:rerun-if-changed={}", artifact.entry.display());
+                for import in &artifact.imports {
+                    println!("cargo:rerun-if-changed={}", import.display());
+                }
+                for w in &artifact.warnings {
+                    // A cargo:warning directive is a single line.
+                    println!("cargo:warning={}", w.replace('\n', " "));
+                }
+            }
+        }
+
+        let failures: Vec<String> = artifacts
+            .iter()
+            .filter_map(|a| a.error.as_ref().map(|e| e.msg.clone()))
+            .collect();
+        if failures.is_empty() { Ok(artifacts) } else { Err(Error::Compile(failures)) }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// No `out_dir` was given and `OUT_DIR` is not set (not running under Cargo).
+    NoOutDir,
+    /// A pattern matched nothing or its directory could not be read.
+    Pattern(String, String),
+    /// The compiler's messages, one per failed input.
+    Compile(Vec<String>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoOutDir => write!(f, "sh2c-build: no out_dir given and OUT_DIR is not set"),
+            Error::Pattern(pattern, msg) => write!(f, "sh2c-build: {}: {}", pattern, msg),
+            Error::Compile(msgs) => {
+                write!(f, "sh2c-build: {} script(s) failed to compile", msgs.len())?;
+                for msg in msgs {
+                    write!(f, "\n{}", msg)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn is_pattern(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+/// Files matching `pattern`, sorted by name.
+fn expand(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(pattern);
+    if !is_pattern(pattern) {
+        return Ok(if path.is_file() { vec![path.to_path_buf()] } else { Vec::new() });
+    }
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if is_pattern(&dir.to_string_lossy()) {
+        return Err("wildcards are only supported in the file name".to_string());
+    }
+    let name_pattern = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("cannot read {}: {}", dir.display(), e))?;
+    let mut matched: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter(|entry| wildcard_match(&name_pattern, &entry.file_name().to_string_lossy()))
+        .map(|entry| dir.join(entry.file_name()))
+        .collect();
+    matched.sort();
+    Ok(matched)
+}
+
+/// `*` matches any run of characters, `?` exactly one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // Position after the last `*` and the name index it was tried at, for backtracking.
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi + 1, ni));
+                pi += 1;
+            }
+            Some(&c) if c == '?' || c == n[ni] => {
+                pi += 1;
+                ni += 1;
+            }
+            _ => match star {
+                Some((after, tried)) => {
+                    pi = after;
+                    ni = tried + 1;
+                    star = Some((after, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wildcard_match;
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*.sh2", "deploy.sh2"));
+        assert!(wildcard_match("d?ploy*", "deploy.sh2"));
+        assert!(wildcard_match("*a*b", "xaxxab"));
+        assert!(!wildcard_match("*.sh2", "deploy.sh"));
+        assert!(!wildcard_match("?", ""));
+    }
+}
@@ -0,0 +1,63 @@
+use sh2c::codegen::TargetShell;
+use std::fs;
+
+fn scripts() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let scripts = dir.path().join("scripts");
+    fs::create_dir_all(scripts.join("lib")).unwrap();
+    fs::write(scripts.join("lib/util.sh2"), "func shout(s) {\n    print(s & \"!\")\n}\n").unwrap();
+    fs::write(scripts.join("deploy.sh2"), "import \"lib/util.sh2\" as u\nfunc main() {\n    u.shout(\"deploy\")\n}\n").unwrap();
+    fs::write(scripts.join("backup.sh2"), "func main() {\n    print(\"backup\")\n}\n").unwrap();
+    fs::write(scripts.join("README.md"), "not a script\n").unwrap();
+    dir
+}
+
+#[test]
+fn compiles_matching_scripts_into_out_dir() {
+    let dir = scripts();
+    let out = dir.path().join("out");
+    let pattern = format!("{}/scripts/*.sh2", dir.path().display());
+    let artifacts = sh2c_build::compile(&pattern)
+        .out_dir(&out)
+        .target(TargetShell::Posix)
+        .cargo_metadata(false)
+        .run()
+        .unwrap();
+
+    let names: Vec<_> = artifacts.iter().map(|a| a.output.clone().unwrap()).collect();
+    assert_eq!(names, [out.join("backup.sh"), out.join("deploy.sh")]);
+    let deploy = fs::read_to_string(out.join("deploy.sh")).unwrap();
+    assert!(deploy.starts_with("#!/bin/sh\n"), "{}", deploy);
+    assert_eq!(artifacts[1].imports.len(), 1);
+
+    let run = std::process::Command::new("sh").arg(out.join("deploy.sh")).output().unwrap();
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), "deploy!\n");
+}
+
+#[test]
+fn failures_are_collected() {
+    let dir = scripts();
+    fs::write(dir.path().join("scripts/broken.sh2"), "func main() {\n    print(x)\n}\n").unwrap();
+    let err = sh2c_build::compile(&format!("{}/scripts/*.sh2", dir.path().display()))
+        .out_dir(dir.path().join("out"))
+        .cargo_metadata(false)
+        .run()
+        .unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.starts_with("sh2c-build: 1 script(s) failed to compile\n"), "{}", msg);
+    assert!(msg.contains("undefined variable 'x'"), "{}", msg);
+}
+
+#[test]
+fn patterns_must_match_something() {
+    let dir = scripts();
+    let err = sh2c_build::compile(&format!("{}/scripts/*.bash", dir.path().display()))
+        .out_dir(dir.path().join("out"))
+        .cargo_metadata(false)
+        .run()
+        .unwrap_err();
+    assert!(err.to_string().ends_with("/scripts/*.bash: no files match"), "{}", err);
+
+    let err = sh2c_build::compile("scripts/*/x.sh2").out_dir(dir.path()).run().unwrap_err();
+    assert!(err.to_string().contains("wildcards are only supported in the file name"), "{}", err);
+}
@@ -0,0 +1,157 @@
+//! `include_sh2!`: compile an `.sh2` file while the Rust crate compiles and use
+//! the generated script as a `&'static str`, for crates that ship scripts to run
+//! elsewhere (over SSH, in containers, as cloud-init user data).
+//!
+//! ```ignore
+//! use sh2c_macros::include_sh2;
+//!
+//! const DEPLOY: &str = include_sh2!("scripts/deploy.sh2");
+//! const DEPLOY_POSIX: &str = include_sh2!("scripts/deploy.sh2", target = "posix");
+//! ```
+//!
+//! Paths are relative to the crate root (`CARGO_MANIFEST_DIR`). Compile errors
+//! in the script become Rust compile errors at the macro call. The script and
+//! everything it imports are tracked, so editing any of them recompiles the crate.
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use sh2c::codegen::TargetShell;
+use sh2c::driver::{self, CompileOptions};
+use std::path::{Path, PathBuf};
+
+#[proc_macro]
+pub fn include_sh2(input: TokenStream) -> TokenStream {
+    let (path, target, span) = match parse_args(input) {
+        Ok(args) => args,
+        Err((msg, span)) => return compile_error(&msg, span),
+    };
+    let root = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default();
+    match compile(&root.join(path), target) {
+        Ok((script, sources)) => expansion(&script, &sources, span),
+        Err(msg) => compile_error(&msg, span),
+    }
+}
+
+type Args = (String, TargetShell, Span);
+
+/// `"path"` optionally followed by `, target = "bash" | "posix"`.
+fn parse_args(input: TokenStream) -> Result<Args, (String, Span)> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let usage = "expected include_sh2!(\"path/to/script.sh2\") or include_sh2!(\"...\", target = \"posix\")";
+
+    let (path, span) = match tokens.first() {
+        Some(TokenTree::Literal(lit)) => match string_value(&lit.to_string()) {
+            Some(path) => (path, lit.span()),
+            None => return Err((usage.to_string(), lit.span())),
+        },
+        Some(other) => return Err((usage.to_string(), other.span())),
+        None => return Err((usage.to_string(), Span::call_site())),
+    };
+
+    let mut target = TargetShell::Bash;
+    match &tokens[1..] {
+        [] | [TokenTree::Punct(_)] => {}
+        [TokenTree::Punct(comma), TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Literal(value), rest @ ..]
+            if comma.as_char() == ',' && key.to_string() == "target" && eq.as_char() == '=' && rest.len() <= 1 =>
+        {
+            target = match string_value(&value.to_string()).as_deref() {
+                Some("bash") => TargetShell::Bash,
+                Some("posix") => TargetShell::Posix,
+                _ => return Err(("target must be \"bash\" or \"posix\"".to_string(), value.span())),
+            };
+        }
+        [other, ..] => return Err((usage.to_string(), other.span())),
+    }
+    Ok((path, target, span))
+}
+
+/// The value of a string literal token, or `None` if it is not a plain or raw string.
+fn string_value(token: &str) -> Option<String> {
+    if let Some(raw) = token.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body = raw.get(hashes..raw.len().checked_sub(hashes)?)?;
+        return body.strip_prefix('"')?.strip_suffix('"').map(str::to_string);
+    }
+    let body = token.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            '0' => out.push('\0'),
+            c @ ('\\' | '"' | '\'') => out.push(c),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+/// The script and every source file it was built from.
+fn compile(path: &Path, target: TargetShell) -> Result<(String, Vec<PathBuf>), String> {
+    let options = CompileOptions { target, ..Default::default() };
+    let artifact = driver::compile_project(&[path.to_path_buf()], &options).remove(0);
+    if let Some(e) = artifact.error {
+        return Err(e.msg);
+    }
+    let mut sources = vec![std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())];
+    sources.extend(artifact.imports);
+    Ok((artifact.script.unwrap_or_default(), sources))
+}
+
+/// `{ const _: &[u8] = include_bytes!("<source>"); ... "<script>" }`. The
+/// `include_bytes!` items make rustc record each source as a dependency.
+fn expansion(script: &str, sources: &[PathBuf], span: Span) -> TokenStream {
+    let mut body: Vec<TokenTree> = Vec::new();
+    for source in sources {
+        body.extend([
+            TokenTree::Ident(Ident::new("const", span)),
+            TokenTree::Ident(Ident::new("_", span)),
+            TokenTree::Punct(Punct::new(':', Spacing::Alone)),
+            TokenTree::Punct(Punct::new('&', Spacing::Alone)),
+            TokenTree::Group(Group::new(
+                Delimiter::Bracket,
+                TokenStream::from(TokenTree::Ident(Ident::new("u8", span))),
+            )),
+            TokenTree::Punct(Punct::new('=', Spacing::Alone)),
+        ]);
+        body.extend(macro_call("include_bytes", &source.to_string_lossy(), span));
+        body.push(TokenTree::Punct(Punct::new(';', Spacing::Alone)));
+    }
+    let mut lit = Literal::string(script);
+    lit.set_span(span);
+    body.push(TokenTree::Literal(lit));
+    TokenStream::from(TokenTree::Group(Group::new(Delimiter::Brace, body.into_iter().collect())))
+}
+
+fn compile_error(msg: &str, span: Span) -> TokenStream {
+    macro_call("compile_error", msg, span).into_iter().collect()
+}
+
+/// `name!("arg")` with every token at `span`.
+fn macro_call(name: &str, arg: &str, span: Span) -> [TokenTree; 3] {
+    let mut lit = Literal::string(arg);
+    lit.set_span(span);
+    let mut bang = Punct::new('!', Spacing::Alone);
+    bang.set_span(span);
+    let mut group = Group::new(Delimiter::Parenthesis, TokenStream::from(TokenTree::Literal(lit)));
+    group.set_span(span);
+    [TokenTree::Ident(Ident::new(name, span)), TokenTree::Punct(bang), TokenTree::Group(group)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::string_value;
+
+    #[test]
+    fn string_literals() {
+        assert_eq!(string_value(r#""a/b.sh2""#).as_deref(), Some("a/b.sh2"));
+        assert_eq!(string_value(r#""a\"b\\c""#).as_deref(), Some("a\"b\\c"));
+        assert_eq!(string_value(r###"r#"x"y"#"###).as_deref(), Some("x\"y"));
+        assert_eq!(string_value("42"), None);
+        assert_eq!(string_value(r#"b"x""#), None);
+    }
+}
@@ -0,0 +1,26 @@
+use sh2c_macros::include_sh2;
+use std::process::Command;
+
+const DEPLOY: &str = include_sh2!("tests/scripts/deploy.sh2");
+const DEPLOY_POSIX: &str = include_sh2!("tests/scripts/deploy.sh2", target = "posix");
+
+fn run(shell: &str, script: &str) -> String {
+    let out = Command::new(shell).arg("-c").arg(script).arg("deploy.sh").arg("prod").output().unwrap();
+    assert!(out.status.success(), "{}: {}", shell, String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn expands_to_the_compiled_script() {
+    assert!(DEPLOY.starts_with("#!/usr/bin/env bash\n"), "{}", DEPLOY);
+    assert!(DEPLOY_POSIX.starts_with("#!/bin/sh\n"), "{}", DEPLOY_POSIX);
+    assert_eq!(run("bash", DEPLOY), "deploy prod!\n");
+    assert_eq!(run("sh", DEPLOY_POSIX), "deploy prod!\n");
+}
+
+#[test]
+fn matches_the_compiler_output() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/scripts/deploy.sh2");
+    let expected = sh2c::driver::compile_file(std::path::Path::new(path), Default::default()).ok().unwrap();
+    assert_eq!(DEPLOY, expected);
+}
@@ -0,0 +1,90 @@
+//! Per-stage compiler benchmarks: `cargo bench -p sh2c`.
+//!
+//! Each input size is measured through lex, parse, lower and codegen separately,
+//! plus the full driver, so a regression can be traced to the stage that caused it.
+//! Save a baseline before a change (`-- --save-baseline main`) and compare after it
+//! (`-- --baseline main`).
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use sh2c::codegen::{self, CodegenOptions};
+use sh2c::driver::{self, CompileOptions};
+use sh2c::lower::{self, LowerOptions};
+use sh2c::span::SourceMap;
+use sh2c::{lexer, loader, parser};
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+
+const SMALL: &str = include_str!("fixtures/small.sh2");
+
+/// Functions defined in `small.sh2`; copies get a numeric suffix.
+const FUNCS: &[&str] = &["log_step", "collect_sources", "build_release", "main"];
+
+/// `copies` renamed copies of the small fixture, driven from a new `main`.
+fn scaled(copies: usize) -> String {
+    let mut src = String::new();
+    for i in 0..copies {
+        let mut unit = SMALL.to_string();
+        for name in FUNCS {
+            unit = unit.replace(&format!("{}(", name), &format!("{}_{}(", name, i));
+        }
+        src.push_str(&unit);
+        src.push('\n');
+    }
+    src.push_str("func main() {\n");
+    for i in 0..copies {
+        src.push_str(&format!("    main_{}()\n", i));
+    }
+    src.push_str("}\n");
+    src
+}
+
+fn inputs(dir: &Path) -> Vec<(&'static str, PathBuf, String)> {
+    [("small", SMALL.to_string()), ("medium", scaled(25)), ("large", scaled(250))]
+        .into_iter()
+        .map(|(name, src)| {
+            let path = dir.join(format!("{}.sh2", name));
+            std::fs::write(&path, &src).unwrap();
+            (name, path, src)
+        })
+        .collect()
+}
+
+fn stages(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let lower_opts = LowerOptions::default();
+    let codegen_opts = CodegenOptions::default();
+
+    for (name, path, src) in inputs(dir.path()) {
+        let file = path.to_string_lossy().to_string();
+        let sm = SourceMap::new(src.clone());
+        let tokens = lexer::lex(&sm, &file).unwrap();
+        let load = || loader::load(&path).unwrap();
+        let ir = lower::lower_with_options(load(), &lower_opts).unwrap();
+
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Bytes(src.len() as u64));
+        if name == "large" {
+            group.sample_size(20);
+        }
+        group.bench_function("lex", |b| b.iter(|| lexer::lex(black_box(&sm), &file).unwrap()));
+        group.bench_function("parse", |b| {
+            b.iter(|| parser::parse(black_box(&tokens), &sm, &file).unwrap())
+        });
+        group.bench_function("lower", |b| {
+            b.iter_batched(load, |ast| lower::lower_with_options(ast, &lower_opts).unwrap(), BatchSize::LargeInput)
+        });
+        group.bench_function("codegen", |b| {
+            b.iter(|| codegen::emit_with_options(black_box(&ir), codegen_opts).unwrap())
+        });
+        group.bench_function("full", |b| {
+            b.iter(|| {
+                driver::compile_file(black_box(&path), CompileOptions::default())
+                    .unwrap_or_else(|e| panic!("{}", e.msg))
+            })
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, stages);
+criterion_main!(benches);
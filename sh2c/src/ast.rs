@@ -55,23 +55,79 @@ pub struct Import {
     pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Program {
     pub imports: Vec<Import>,
     pub functions: Vec<Function>,
+    /// `extern func` declarations: shell functions defined outside the program.
+    pub externs: Vec<ExternFunc>,
+    /// `on_enter(...)` / `on_exit(...)` blocks; woven into their functions by
+    /// `hooks::weave` and empty afterwards.
+    pub hooks: Vec<Hook>,
+    /// Functions holding `init { ... }` blocks, in the order they run before the
+    /// entry function: imported modules before the files importing them.
+    pub inits: Vec<String>,
+    /// Statements outside any function in a script-style file; they become the
+    /// body of an implicit `main`.
+    pub top_level: Vec<Stmt>,
 
     pub span: Span,
     pub source_maps: HashMap<String, SourceMap>,
     pub entry_file: String,
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HookKind {
+    Enter,
+    Exit,
+}
+
+/// `on_enter(f, g) { ... }`: statements run at the start (or, for `on_exit`,
+/// before every return) of the named functions of the same file. A target of
+/// `*` means every function in the file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hook {
+    pub kind: HookKind,
+    pub targets: Vec<String>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+    pub file: String,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Function {
+    /// `///` lines directly above the function (or its attributes).
+    pub doc: Vec<String>,
     pub name: String,
     pub params: Vec<String>,
+    /// Declared `-> bool`: the result is the function's exit status.
+    pub returns_bool: bool,
     pub body: Vec<Stmt>,
     pub span: Span,
     pub file: String,
+    /// `@name(...)` annotations written before `func`.
+    pub attrs: Vec<Attribute>,
+}
+
+/// `extern func name(params)`: a shell function that exists at runtime (for
+/// example from a sourced file). Calls to it are checked against `params` and
+/// emitted as plain invocations.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExternFunc {
+    /// `///` lines directly above the declaration.
+    pub doc: Vec<String>,
+    pub name: String,
+    pub params: Vec<String>,
+    pub span: Span,
+    pub file: String,
+}
+
+/// A function annotation such as `@deprecated("use new_fn")`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<String>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -93,6 +149,13 @@ pub enum CompareOp {
     Ge,
 }
 
+impl CompareOp {
+    /// `<`, `<=`, `>` and `>=`: the operators that chain, as in `0 <= x < 10`.
+    pub fn is_ordering(&self) -> bool {
+        matches!(self, CompareOp::Lt | CompareOp::Le | CompareOp::Gt | CompareOp::Ge)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum LValue {
     Var(Spanned<String>),
@@ -107,12 +170,17 @@ pub enum ExprKind {
     Var(String),
     Command(Vec<Expr>),
     CommandPipe(Vec<Vec<Expr>>),
+    /// Statement-form pipeline bound by `let` (e.g. `let out = run("a") | run("b")`).
+    /// Segments keep their per-segment options and status follows statement-pipe rules.
+    Pipeline(Vec<Spanned<PipeSegment>>),
     Concat(Box<Expr>, Box<Expr>),
     Arith {
         left: Box<Expr>,
         op: ArithOp,
         right: Box<Expr>,
     },
+    /// An ordering `Compare` whose `left` is another ordering `Compare` is a
+    /// chain: `a < b <= c` means `a < b && b <= c`, with `b` evaluated once.
     Compare {
         left: Box<Expr>,
         op: CompareOp,
@@ -121,6 +189,8 @@ pub enum ExprKind {
     And(Box<Expr>, Box<Expr>),
     Or(Box<Expr>, Box<Expr>),
     Not(Box<Expr>),
+    /// `...xs`: the items of a list as separate call arguments or list items.
+    Spread(Box<Expr>),
     Exists(Box<Expr>),
     IsDir(Box<Expr>),
     IsFile(Box<Expr>),
@@ -135,6 +205,8 @@ pub enum ExprKind {
     Index {
         list: Box<Expr>,
         index: Box<Expr>,
+        /// `xs?[i]`: a missing element reads as empty instead of failing under `set -u`.
+        optional: bool,
     },
     Field {
         base: Box<Expr>,
@@ -144,9 +216,18 @@ pub enum ExprKind {
         list: Box<Expr>,
         sep: Box<Expr>,
     },
+    /// `a ?? b`: `a` unless it is empty, else `b`.
+    Coalesce(Box<Expr>, Box<Expr>),
+    /// `if cond { a } else { b }` in expression position; `elif` nests
+    /// another `IfElse` in `else_expr`.
+    IfElse {
+        cond: Box<Expr>,
+        then_expr: Box<Expr>,
+        else_expr: Box<Expr>,
+    },
     Count(Box<Expr>),
     Bool(bool),
-    Number(u32),
+    Number(u64),
     List(Vec<Expr>),
     Args,
     Status,
@@ -191,9 +272,11 @@ pub enum ExprKind {
     },
     Run(RunCall),
     MapLiteral(Vec<(String, Expr)>),
+    /// `m["key"]`, or `m?["key"]` / `m?.key` when `optional` is set.
     MapIndex {
         map: String,
         key: String,
+        optional: bool,
     },
     Capture {
         expr: Box<Expr>,
@@ -224,6 +307,8 @@ pub struct CallOption {
 pub struct RunCall {
     pub args: Vec<Expr>,
     pub options: Vec<CallOption>,
+    /// Per-segment `stderr=<target>` suffix; only set for run/sudo pipeline segments.
+    pub stderr: Option<Box<Spanned<RedirectOutputTarget>>>,
 }
 
 
@@ -233,7 +318,8 @@ pub enum PipeSegment {
     Run(RunCall),
     Sudo(RunCall),
     Block(Vec<Stmt>),
-    EachLine(Spanned<String>, Vec<Stmt>),
+    /// `each_line var { ... }`, or `each_line(fd=N) var` to read the lines on fd N.
+    EachLine(Spanned<String>, Option<u8>, Vec<Stmt>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -290,6 +376,16 @@ pub enum StmtKind {
     Break,
     Continue,
     Return(Option<Expr>),
+    /// `return a, b`: two or more values, read back with `let x, y = f(...)`.
+    ReturnValues(Vec<Expr>),
+    /// `let x, y = f(...)`: binds each value of a `return a, b` function.
+    /// With `tuple`, written `let (x, y) = ...`, the value may also be a list
+    /// or `split_once(...)`, destructured into exactly that many names.
+    LetValues {
+        names: Vec<Spanned<String>>,
+        value: Expr,
+        tuple: bool,
+    },
     Exit(Option<Expr>),
     WithLog {
         path: Expr,
@@ -312,14 +408,40 @@ pub enum StmtKind {
         path: Expr,
         body: Vec<Stmt>,
     },
+    /// `with deadline("5m") { ... }`: the body and everything it starts are killed
+    /// once the duration has passed.
+    WithDeadline {
+        duration: Expr,
+        body: Vec<Stmt>,
+    },
+    /// `with umask(022) { ... }`: the body runs with that file creation mask, and
+    /// the previous mask is restored afterwards.
+    WithUmask {
+        mask: Expr,
+        body: Vec<Stmt>,
+    },
+    /// `with nice(10)`, `with ionice("idle")` or `with ulimit(nofile=1024)`: the
+    /// body runs in a subshell at that priority or under those limits.
+    WithLimits {
+        limit: Limit,
+        body: Vec<Stmt>,
+    },
     Cd {
         path: Expr,
     },
     Sh(Expr),
-    ShBlock(Vec<String>),
+    /// `sh { "line", ... }`. With `use(...)` / `export(...)` the block declares
+    /// which sh2 variables it reads and which shell variables it hands back, and
+    /// the checker holds it to that; without them it is unchecked.
+    ShBlock {
+        lines: Vec<String>,
+        bridge: Option<ShBridge>,
+    },
     Call {
         name: String,
         args: Vec<Expr>,
+        /// Named arguments; only accepted by statement builtins such as `wait_for_port`.
+        options: Vec<CallOption>,
     },
     QualifiedCall {
         ns: String,
@@ -336,6 +458,11 @@ pub enum StmtKind {
     Group {
         body: Vec<Stmt>,
     },
+    /// `unchecked { ... }`: a failing statement in the body does not abort;
+    /// `status()` still reports it.
+    Unchecked {
+        body: Vec<Stmt>,
+    },
     WithRedirect {
         stdout: Option<Vec<Spanned<RedirectOutputTarget>>>,
         stderr: Option<Vec<Spanned<RedirectOutputTarget>>>,
@@ -380,11 +507,29 @@ pub enum RedirectOutputTarget {
 pub enum RedirectInputTarget {
     File { path: Expr },
     HereDoc { content: String },
+    /// `text(expr)`: feed a string (plus trailing newline) without a temp file.
+    Text { value: Expr },
 }
 
+/// The checked setting of a `with nice/ionice/ulimit` block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Limit {
+    /// Niceness, -20 to 19.
+    Nice(i64),
+    /// I/O class `"realtime"`, `"best-effort"` or `"idle"`, and a level 0-7.
+    Ionice { class: String, level: Option<i64> },
+    /// `ulimit` settings by name, in source order; `None` is `"unlimited"`.
+    /// `fsize`, `core`, `stack` and `vmem` are in bytes.
+    Ulimit(Vec<(String, Option<i64>)>),
+}
+
+/// `ulimit` names accepted by `with ulimit(...)`, with their flag.
+pub const ULIMITS: &[(&str, char)] =
+    &[("nofile", 'n'), ("cpu", 't'), ("fsize", 'f'), ("core", 'c'), ("stack", 's'), ("vmem", 'v')];
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CaseArm {
-    pub patterns: Vec<Pattern>,
+    pub patterns: Vec<Spanned<Pattern>>,
     pub body: Vec<Stmt>,
 }
 
@@ -395,6 +540,12 @@ pub enum Pattern {
     Wildcard,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShBridge {
+    pub uses: Vec<String>,
+    pub exports: Vec<String>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Elif {
     pub cond: Expr,
@@ -419,14 +570,36 @@ impl Program {
         for func in &mut self.functions {
             func.strip_spans();
         }
+        for ext in &mut self.externs {
+            ext.span = Span::new(0, 0);
+            ext.file.clear();
+        }
+        self.inits.clear();
+        for s in &mut self.top_level {
+            s.strip_spans();
+        }
+        for hook in &mut self.hooks {
+            hook.span = Span::new(0, 0);
+            hook.file.clear();
+            for s in &mut hook.body {
+                s.strip_spans();
+            }
+        }
 
     }
 }
 
 impl Function {
+    pub fn attr(&self, name: &str) -> Option<&Attribute> {
+        self.attrs.iter().find(|a| a.name == name)
+    }
+
     pub fn strip_spans(&mut self) {
         self.span = Span::new(0, 0);
         self.file.clear(); // Clear for deterministic snapshot
+        for a in &mut self.attrs {
+            a.span = Span::new(0, 0);
+        }
         for s in &mut self.body {
             s.strip_spans();
         }
@@ -502,23 +675,23 @@ impl StmtKind {
             StmtKind::Pipe(segments) => {
                 for seg in segments {
                     seg.span = Span::new(0, 0);
-                    match &mut seg.node {
-                        PipeSegment::Run(call) => call.strip_spans(),
-                        PipeSegment::Sudo(call) => call.strip_spans(),
-                        PipeSegment::Block(stmts) => for s in stmts { s.strip_spans(); },
-                        PipeSegment::EachLine(ident, body) => {
-                            ident.span = Span::new(0, 0);
-                            for s in body { s.strip_spans(); }
-                        }
-                    }
+                    seg.node.strip_spans();
                 }
             }
             StmtKind::Return(Some(e)) => e.strip_spans(),
+            StmtKind::ReturnValues(values) => for v in values { v.strip_spans(); },
+            StmtKind::LetValues { names, value, .. } => {
+                for n in names { n.span = Span::new(0, 0); }
+                value.strip_spans();
+            }
             StmtKind::Exit(Some(e)) => e.strip_spans(),
             StmtKind::Cd { path } => path.strip_spans(),
             StmtKind::Export { value: Some(v), .. } => v.strip_spans(),
             StmtKind::Source { path } => path.strip_spans(),
-            StmtKind::Call { args, .. } => for a in args { a.strip_spans(); },
+            StmtKind::Call { args, options, .. } => {
+                for a in args { a.strip_spans(); }
+                for o in options { o.strip_spans(); }
+            }
             StmtKind::QualifiedCall { ns_span, name_span, args, resolved_path, resolved_mangled, .. } => {
                 *ns_span = Span::new(0, 0);
                 *name_span = Span::new(0, 0);
@@ -542,6 +715,17 @@ impl StmtKind {
                  path.strip_spans();
                  for s in body { s.strip_spans(); }
             }
+            StmtKind::WithDeadline { duration, body } => {
+                 duration.strip_spans();
+                 for s in body { s.strip_spans(); }
+            }
+            StmtKind::WithUmask { mask, body } => {
+                 mask.strip_spans();
+                 for s in body { s.strip_spans(); }
+            }
+            StmtKind::WithLimits { body, .. } => {
+                 for s in body { s.strip_spans(); }
+            }
             StmtKind::WithLog { path, body, .. } => {
                  path.strip_spans();
                  for s in body { s.strip_spans(); }
@@ -565,7 +749,7 @@ impl StmtKind {
             StmtKind::Subshell { body } => {
                  for s in body { s.strip_spans(); }
             }
-            StmtKind::Group { body } => {
+            StmtKind::Group { body } | StmtKind::Unchecked { body } => {
                  for s in body { s.strip_spans(); }
             }
             StmtKind::Spawn { stmt } => stmt.strip_spans(),
@@ -577,6 +761,7 @@ impl StmtKind {
             StmtKind::Case { expr, arms } => {
                 expr.strip_spans();
                 for arm in arms {
+                    for p in &mut arm.patterns { p.span = Span::new(0, 0); }
                     for s in &mut arm.body { s.strip_spans(); }
                 }
             },
@@ -598,12 +783,18 @@ impl ExprKind {
         match self {
             ExprKind::Command(args) => for a in args { a.strip_spans(); },
             ExprKind::CommandPipe(segs) => for s in segs { for a in s { a.strip_spans(); } },
+            ExprKind::Pipeline(segs) => for seg in segs {
+                seg.span = Span::new(0, 0);
+                seg.node.strip_spans();
+            },
             ExprKind::Concat(l, r) => { l.strip_spans(); r.strip_spans(); },
+            ExprKind::Coalesce(l, r) => { l.strip_spans(); r.strip_spans(); },
             ExprKind::Arith { left, right, .. } => { left.strip_spans(); right.strip_spans(); },
             ExprKind::Compare { left, right, .. } => { left.strip_spans(); right.strip_spans(); },
             ExprKind::And(l, r) => { l.strip_spans(); r.strip_spans(); },
             ExprKind::Or(l, r) => { l.strip_spans(); r.strip_spans(); },
             ExprKind::Not(e) => e.strip_spans(),
+            ExprKind::Spread(e) => e.strip_spans(),
             ExprKind::Exists(e) => e.strip_spans(),
             ExprKind::IsDir(e) => e.strip_spans(),
             ExprKind::IsFile(e) => e.strip_spans(),
@@ -614,9 +805,10 @@ impl ExprKind {
             ExprKind::IsNonEmpty(e) => e.strip_spans(),
             ExprKind::BoolStr(e) => e.strip_spans(),
             ExprKind::Len(e) => e.strip_spans(),
-            ExprKind::Index { list, index } => { list.strip_spans(); index.strip_spans(); },
+            ExprKind::Index { list, index, .. } => { list.strip_spans(); index.strip_spans(); },
             ExprKind::Field { base, .. } => base.strip_spans(),
             ExprKind::Join { list, sep } => { list.strip_spans(); sep.strip_spans(); },
+            ExprKind::IfElse { cond, then_expr, else_expr } => { cond.strip_spans(); then_expr.strip_spans(); else_expr.strip_spans(); },
             ExprKind::Count(e) => e.strip_spans(),
             ExprKind::List(items) => for i in items { i.strip_spans(); },
             ExprKind::Env(e) => e.strip_spans(),
@@ -666,6 +858,24 @@ impl RunCall {
     pub fn strip_spans(&mut self) {
          for a in &mut self.args { a.strip_spans(); }
          for o in &mut self.options { o.strip_spans(); }
+         if let Some(t) = &mut self.stderr {
+             t.span = Span::new(0, 0);
+             t.node.strip_spans();
+         }
+    }
+}
+
+impl PipeSegment {
+    pub fn strip_spans(&mut self) {
+        match self {
+            PipeSegment::Run(call) => call.strip_spans(),
+            PipeSegment::Sudo(call) => call.strip_spans(),
+            PipeSegment::Block(stmts) => for s in stmts { s.strip_spans(); },
+            PipeSegment::EachLine(ident, _, body) => {
+                ident.span = Span::new(0, 0);
+                for s in body { s.strip_spans(); }
+            }
+        }
     }
 }
 
@@ -679,8 +889,9 @@ impl RedirectOutputTarget {
 
 impl RedirectInputTarget {
     pub fn strip_spans(&mut self) {
-        if let RedirectInputTarget::File { path } = self {
-            path.strip_spans();
+        match self {
+            RedirectInputTarget::File { path } | RedirectInputTarget::Text { value: path } => path.strip_spans(),
+            RedirectInputTarget::HereDoc { .. } => {}
         }
     }
 }
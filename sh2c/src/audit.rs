@@ -0,0 +1,384 @@
+//! Quoting audit (`sh2c --audit-quoting`)
+//!
+//! Lists every place where a value leaves sh2's always-quoted world:
+//! - `sh(...)` / `sh { ... }` text that the shell parses as code
+//! - `raw_arg(...)` expansions that undergo word splitting and globbing
+//! - `glob(...)` patterns and `source(...)` paths taken from runtime values
+//! - `eval` lines in the generated script (prelude helpers and runtime glue)
+//!
+//! The audit is informational: it never fails compilation, it only reports
+//! sites for review.
+
+use crate::ast::{self, ExprKind, Program, StmtKind};
+use crate::span::{SourceMap, Span};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// Short category tag: `sh`, `sh-block`, `raw_arg`, `glob`, `source`, `eval`.
+    pub kind: &'static str,
+    /// `file:line:col` for source findings, `<generated>:line` for `eval`.
+    pub location: String,
+    pub message: String,
+}
+
+struct Auditor<'a> {
+    sm: &'a SourceMap,
+    file: String,
+    findings: Vec<Finding>,
+}
+
+impl Auditor<'_> {
+    fn report(&mut self, kind: &'static str, span: Span, message: String) {
+        let (line, col) = self.sm.line_col(span.start);
+        self.findings.push(Finding {
+            kind,
+            location: format!("{}:{}:{}", self.file, line, col),
+            message,
+        });
+    }
+}
+
+/// Audits the loaded program; spans are reported relative to `base`.
+pub fn audit_program(program: &Program, base: Option<&Path>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for func in &program.functions {
+        let Some(sm) = program.source_maps.get(&func.file) else { continue };
+        let mut auditor = Auditor {
+            sm,
+            file: crate::diag_path::display_path(&func.file, base),
+            findings: Vec::new(),
+        };
+        for stmt in &func.body {
+            audit_stmt(stmt, &mut auditor);
+        }
+        findings.extend(auditor.findings);
+    }
+    findings
+}
+
+/// Renders findings one per line, followed by a summary line.
+pub fn format_report(findings: &[Finding]) -> String {
+    let mut out = String::new();
+    for f in findings {
+        out.push_str(&format!("{}: {}: {}\n", f.location, f.kind, f.message));
+    }
+    match findings.len() {
+        0 => out.push_str("no quoting-sensitive sites found"),
+        1 => out.push_str("1 quoting-sensitive site found"),
+        n => out.push_str(&format!("{} quoting-sensitive sites found", n)),
+    }
+    out
+}
+
+/// True when the expression is a compile-time constant string.
+fn is_literal(expr: &ast::Expr) -> bool {
+    match &expr.node {
+        ExprKind::Literal(_) | ExprKind::Number(_) | ExprKind::Bool(_) => true,
+        ExprKind::Concat(a, b) | ExprKind::Coalesce(a, b) => is_literal(a) && is_literal(b),
+        ExprKind::IfElse { then_expr, else_expr, .. } => is_literal(then_expr) && is_literal(else_expr),
+        _ => false,
+    }
+}
+
+fn audit_block(stmts: &[ast::Stmt], a: &mut Auditor) {
+    for s in stmts {
+        audit_stmt(s, a);
+    }
+}
+
+// SYNC WITH: resolver::resolve_in_stmt (traversal shape).
+fn audit_stmt(stmt: &ast::Stmt, a: &mut Auditor) {
+    match &stmt.node {
+        StmtKind::ShBlock { lines, .. } => {
+            a.report(
+                "sh-block",
+                stmt.span,
+                format!("sh {{ ... }} pastes {} line(s) into the script verbatim", lines.len()),
+            );
+        }
+        StmtKind::Source { path } => {
+            if !is_literal(path) {
+                a.report("source", path.span, "source() executes a file whose path is computed at runtime".to_string());
+            }
+            audit_expr(path, a);
+        }
+        StmtKind::Let { value, .. } | StmtKind::Set { value, .. } | StmtKind::LetValues { value, .. } => audit_expr(value, a),
+        StmtKind::ReturnValues(values) => {
+            for v in values {
+                audit_expr(v, a);
+            }
+        }
+        StmtKind::Run(call) => audit_run_call(call, a),
+        StmtKind::Print(e)
+        | StmtKind::PrintErr(e)
+        | StmtKind::Exit(Some(e))
+        | StmtKind::Return(Some(e))
+        | StmtKind::Wait(Some(e))
+        | StmtKind::Sh(e)
+        | StmtKind::Cd { path: e }
+        | StmtKind::Export { value: Some(e), .. } => audit_expr(e, a),
+        StmtKind::Exec(args) => {
+            for e in args {
+                audit_expr(e, a);
+            }
+        }
+        StmtKind::Call { args, options, .. } => {
+            for e in args {
+                audit_expr(e, a);
+            }
+            for o in options {
+                audit_expr(&o.value, a);
+            }
+        }
+        StmtKind::QualifiedCall { args, .. } => {
+            for e in args {
+                audit_expr(e, a);
+            }
+        }
+        StmtKind::If { cond, then_body, elifs, else_body } => {
+            audit_expr(cond, a);
+            audit_block(then_body, a);
+            for e in elifs {
+                audit_expr(&e.cond, a);
+                audit_block(&e.body, a);
+            }
+            if let Some(body) = else_body {
+                audit_block(body, a);
+            }
+        }
+        StmtKind::While { cond, body } => {
+            audit_expr(cond, a);
+            audit_block(body, a);
+        }
+        StmtKind::For { iterable, body, .. } => {
+            match iterable {
+                ast::ForIterable::List(items) => {
+                    for e in items {
+                        audit_expr(e, a);
+                    }
+                }
+                ast::ForIterable::Range(start, end) => {
+                    audit_expr(start, a);
+                    audit_expr(end, a);
+                }
+                ast::ForIterable::Find0(spec) => {
+                    for e in [&spec.dir, &spec.name, &spec.type_filter, &spec.maxdepth].into_iter().flatten() {
+                        audit_expr(e, a);
+                    }
+                }
+                ast::ForIterable::StdinLines => {}
+            }
+            audit_block(body, a);
+        }
+        StmtKind::ForMap { body, .. }
+        | StmtKind::Subshell { body }
+        | StmtKind::Group { body }
+        | StmtKind::Unchecked { body }
+        | StmtKind::WithLimits { body, .. } => audit_block(body, a),
+        StmtKind::TryCatch { try_body, catch_body } => {
+            audit_block(try_body, a);
+            audit_block(catch_body, a);
+        }
+        StmtKind::AndThen { left, right } | StmtKind::OrElse { left, right } => {
+            audit_block(left, a);
+            audit_block(right, a);
+        }
+        StmtKind::Pipe(segments) => audit_pipe_segments(segments, a),
+        StmtKind::WithEnv { bindings, body } => {
+            for (_, v) in bindings {
+                audit_expr(v, a);
+            }
+            audit_block(body, a);
+        }
+        StmtKind::WithCwd { path, body }
+        | StmtKind::WithLog { path, body, .. }
+        | StmtKind::WithDeadline { duration: path, body } | StmtKind::WithUmask { mask: path, body } => {
+            audit_expr(path, a);
+            audit_block(body, a);
+        }
+        StmtKind::WithRedirect { stdout, stderr, stdin, body } => {
+            for t in stdout.iter().chain(stderr.iter()).flatten() {
+                if let ast::RedirectOutputTarget::File { path, .. } = &t.node {
+                    audit_expr(path, a);
+                }
+            }
+            if let Some(ast::RedirectInputTarget::File { path } | ast::RedirectInputTarget::Text { value: path }) = stdin {
+                audit_expr(path, a);
+            }
+            audit_block(body, a);
+        }
+        StmtKind::Spawn { stmt } => audit_stmt(stmt, a),
+        StmtKind::Case { expr, arms } => {
+            audit_expr(expr, a);
+            for arm in arms {
+                audit_block(&arm.body, a);
+            }
+        }
+        StmtKind::Exit(None)
+        | StmtKind::Return(None)
+        | StmtKind::Wait(None)
+        | StmtKind::Export { value: None, .. }
+        | StmtKind::Unset { .. }
+        | StmtKind::Break
+        | StmtKind::Continue => {}
+    }
+}
+
+fn audit_run_call(call: &ast::RunCall, a: &mut Auditor) {
+    for e in &call.args {
+        audit_expr(e, a);
+    }
+    for o in &call.options {
+        audit_expr(&o.value, a);
+    }
+    if let Some(ast::RedirectOutputTarget::File { path, .. }) = call.stderr.as_deref().map(|t| &t.node) {
+        audit_expr(path, a);
+    }
+}
+
+fn audit_pipe_segments(segments: &[ast::Spanned<ast::PipeSegment>], a: &mut Auditor) {
+    for seg in segments {
+        match &seg.node {
+            ast::PipeSegment::Run(call) | ast::PipeSegment::Sudo(call) => audit_run_call(call, a),
+            ast::PipeSegment::Block(body) | ast::PipeSegment::EachLine(_, _, body) => audit_block(body, a),
+        }
+    }
+}
+
+fn audit_expr(expr: &ast::Expr, a: &mut Auditor) {
+    match &expr.node {
+        ExprKind::Sh { cmd, options } => {
+            let msg = if is_literal(cmd) {
+                "sh() parses a literal command string as shell code".to_string()
+            } else {
+                "sh() command string is built from runtime values, which are parsed as shell code; pass data via args= instead of interpolation".to_string()
+            };
+            a.report("sh", expr.span, msg);
+            audit_expr(cmd, a);
+            for o in options {
+                audit_expr(&o.value, a);
+            }
+        }
+        ExprKind::Call { name, args, options } => {
+            match name.as_str() {
+                "raw_arg" => a.report(
+                    "raw_arg",
+                    expr.span,
+                    "raw_arg() expands unquoted; the value is word-split and glob-expanded".to_string(),
+                ),
+                "glob" if args.first().is_some_and(|p| !is_literal(p)) => a.report(
+                    "glob",
+                    expr.span,
+                    "glob() pattern is computed at runtime; metacharacters in it (* ? [) are expanded".to_string(),
+                ),
+                _ => {}
+            }
+            for e in args {
+                audit_expr(e, a);
+            }
+            for o in options {
+                audit_expr(&o.value, a);
+            }
+        }
+        ExprKind::QualifiedCall { args, .. } | ExprKind::Command(args) | ExprKind::List(args) | ExprKind::Sudo { args, .. } => {
+            for e in args {
+                audit_expr(e, a);
+            }
+            if let ExprKind::Sudo { options, .. } = &expr.node {
+                for o in options {
+                    audit_expr(&o.value, a);
+                }
+            }
+        }
+        ExprKind::CommandPipe(pipeline) => {
+            for e in pipeline.iter().flatten() {
+                audit_expr(e, a);
+            }
+        }
+        ExprKind::Pipeline(segments) => audit_pipe_segments(segments, a),
+        ExprKind::Run(call) => audit_run_call(call, a),
+        ExprKind::Concat(l, r)
+        | ExprKind::Coalesce(l, r)
+        | ExprKind::And(l, r)
+        | ExprKind::Or(l, r)
+        | ExprKind::Join { list: l, sep: r }
+        | ExprKind::Index { list: l, index: r, .. }
+        | ExprKind::Arith { left: l, right: r, .. }
+        | ExprKind::Compare { left: l, right: r, .. } => {
+            audit_expr(l, a);
+            audit_expr(r, a);
+        }
+        ExprKind::IfElse { cond, then_expr, else_expr } => {
+            audit_expr(cond, a);
+            audit_expr(then_expr, a);
+            audit_expr(else_expr, a);
+        }
+        ExprKind::Not(e)
+        | ExprKind::Spread(e)
+        | ExprKind::Exists(e)
+        | ExprKind::IsDir(e)
+        | ExprKind::IsFile(e)
+        | ExprKind::IsSymlink(e)
+        | ExprKind::IsExec(e)
+        | ExprKind::IsReadable(e)
+        | ExprKind::IsWritable(e)
+        | ExprKind::IsNonEmpty(e)
+        | ExprKind::BoolStr(e)
+        | ExprKind::Len(e)
+        | ExprKind::Count(e)
+        | ExprKind::Arg(e)
+        | ExprKind::Env(e)
+        | ExprKind::Input(e)
+        | ExprKind::Field { base: e, .. } => audit_expr(e, a),
+        ExprKind::MapLiteral(entries) => {
+            for (_, v) in entries {
+                audit_expr(v, a);
+            }
+        }
+        ExprKind::Capture { expr: inner, options } => {
+            audit_expr(inner, a);
+            for o in options {
+                audit_expr(&o.value, a);
+            }
+        }
+        ExprKind::Confirm { prompt, default } => {
+            audit_expr(prompt, a);
+            if let Some(d) = default {
+                audit_expr(d, a);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reports each generated line that runs `eval`, naming the enclosing shell
+/// function when there is one.
+pub fn audit_generated(generated: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut current_func: Option<&str> = None;
+    for (idx, line) in generated.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(pos) = trimmed.find("() {")
+            && !line.starts_with(' ')
+            && trimmed[..pos].chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            current_func = Some(&trimmed[..pos]);
+        }
+        let uses_eval = !trimmed.starts_with('#')
+            && trimmed
+                .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .any(|w| w == "eval");
+        if uses_eval {
+            let message = match current_func {
+                Some(f) => format!("generated function {}() uses eval", f),
+                None => "generated top-level code uses eval".to_string(),
+            };
+            findings.push(Finding { kind: "eval", location: format!("<generated>:{}", idx + 1), message });
+        }
+        if line == "}" || (current_func.is_some() && !line.starts_with(' ') && trimmed.ends_with('}')) {
+            current_func = None;
+        }
+    }
+    findings
+}
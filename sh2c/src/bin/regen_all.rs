@@ -25,6 +25,8 @@ fn main() {
             include_diagnostics: true,
             diag_base_dir: Some(fs::canonicalize(PathBuf::from(env!("CARGO_MANIFEST_DIR"))).unwrap()),
             target: TargetShell::Bash,  // Use Bash for regeneration (permissive)
+            debug: false,
+            profile: false,
         };
         
         // Lowering (might panic, but we deal with it mostly working for valid fixtures)
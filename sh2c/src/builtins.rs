@@ -22,24 +22,44 @@ pub const EXPR_BUILTINS: &[&str] = &[
     "load_envfile",
     "json_kv",
     "which",
+    "have",
+    "service_start",
+    "service_stop",
+    "service_running",
+    "wait_for_port",
+    "breakpoint",
+    "dump_vars",
+    "input_list",
+    "lines0",
+    "split0",
     "try_run",
     "require",
     "read_file",
     "write_file",
     "append_file",
+    "replace_in_file",
+    "append_line_if_missing",
     "log_info",
     "log_warn",
     "log_error",
     "home",
+    "last_command",
+    "last_stderr",
     "path_join",
     "lines",
     "split", // lowers to ir::Val::Split
+    "split_once", // `let (a, b) = split_once(...)` only
+    "awk",   // lowers to ir::Val::Awk
     "save_envfile",
     "glob",
+    "raw_arg",    // lowers to ir::Val::RawArg (run/sudo argument only)
     "find_files", // lowers to ir::Val::FindFiles (bash-only)
     "spawn",      // lowers to ir::Val::Spawn (job control)
     "wait",       // lowers to ir::Val::Wait (job control)
     "wait_all",   // lowers to loop IR (job control)
+    "wait_any",   // lowers to ir::Val::WaitAny (job control)
+    "render",     // lowers to ir::Val::Render (bash-only)
+    "bytes_len",  // lowers to ir::Val::BytesLen
 ];
 
 /// Prelude helper functions that pass through to ir::Val::Call.
@@ -77,6 +97,19 @@ pub fn is_prelude_helper(name: &str) -> bool {
     PRELUDE_HELPERS.contains(&name)
 }
 
+/// The closest known function name to `name`, for "did you mean" hints on calls
+/// to unknown functions. Candidates are the user's functions and every builtin.
+pub fn suggest_function<'a>(name: &str, user_funcs: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut candidates: Vec<&str> = user_funcs
+        .into_iter()
+        .chain(crate::lang_spec::BUILTINS.iter().copied())
+        .chain(ALL_BUILTINS.iter().copied())
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+    crate::suggest::suggest(name, &candidates)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,4 +125,11 @@ mod tests {
             overlap
         );
     }
+
+    #[test]
+    fn suggests_builtins_and_user_functions() {
+        assert_eq!(suggest_function("prnt", []), Some("print".to_string()));
+        assert_eq!(suggest_function("deploy_ap", ["deploy_app"]), Some("deploy_app".to_string()));
+        assert_eq!(suggest_function("nothing_like_it", ["deploy_app"]), None);
+    }
 }
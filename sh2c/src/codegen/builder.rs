@@ -0,0 +1,118 @@
+//! Output buffer for generated scripts.
+//!
+//! `ScriptBuilder` owns the script text and the current indentation, so emitters
+//! write lines with `emitln!` instead of threading a `pad` string through every call
+//! and allocating a `format!` temporary per line. Formatting goes straight into the
+//! buffer; the indentation string is cached and only rebuilt when the depth changes.
+
+use std::fmt::{self, Write as _};
+
+/// Spaces per indentation level in generated shell code.
+const INDENT_STEP: usize = 2;
+
+pub(super) struct ScriptBuilder {
+    buf: String,
+    pad: String,
+}
+
+impl ScriptBuilder {
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buf: String::with_capacity(capacity),
+            pad: String::new(),
+        }
+    }
+
+    /// An empty builder at the same indentation, for fragments that are spliced
+    /// into a line of the parent (pipeline stages, command substitutions).
+    pub(super) fn fragment(&self) -> Self {
+        Self {
+            buf: String::new(),
+            pad: self.pad.clone(),
+        }
+    }
+
+    /// The current indentation, for the few emitters that repeat it mid-line.
+    pub(super) fn indentation(&self) -> &str {
+        &self.pad
+    }
+
+    pub(super) fn indent(&mut self) {
+        self.pad.extend(std::iter::repeat_n(' ', INDENT_STEP));
+    }
+
+    pub(super) fn dedent(&mut self) {
+        let depth = self.pad.len().saturating_sub(INDENT_STEP);
+        self.pad.truncate(depth);
+    }
+
+    /// Writes the current indentation.
+    pub(super) fn pad(&mut self) {
+        self.buf.push_str(&self.pad);
+    }
+
+    /// Writes `content` as one indented line.
+    pub(super) fn line(&mut self, content: &str) {
+        self.pad();
+        self.buf.push_str(content);
+        self.buf.push('\n');
+    }
+
+    pub(super) fn push_str(&mut self, s: &str) {
+        self.buf.push_str(s);
+    }
+
+    pub(super) fn push(&mut self, c: char) {
+        self.buf.push(c);
+    }
+
+    /// Target of `write!`/`writeln!`. Writing into a `String` cannot fail, so this
+    /// shadows `fmt::Write::write_fmt` and returns nothing for callers to discard.
+    pub(super) fn write_fmt(&mut self, args: fmt::Arguments<'_>) {
+        let _ = self.buf.write_fmt(args);
+    }
+
+    pub(super) fn finish(self) -> String {
+        self.buf
+    }
+}
+
+/// Writes one indented, newline-terminated line: `emitln!(out, "fi")`.
+macro_rules! emitln {
+    ($out:ident, $($arg:tt)*) => {{
+        $out.pad();
+        writeln!($out, $($arg)*);
+    }};
+}
+pub(super) use emitln;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_indentation_across_lines() {
+        let mut out = ScriptBuilder::with_capacity(0);
+        out.line("main() {");
+        out.indent();
+        emitln!(out, "echo {}", 1);
+        out.indent();
+        out.line(":");
+        out.dedent();
+        out.dedent();
+        out.dedent();
+        out.line("}");
+        assert_eq!(out.finish(), "main() {\n  echo 1\n    :\n}\n");
+    }
+
+    #[test]
+    fn fragment_keeps_indentation_but_not_text() {
+        let mut out = ScriptBuilder::with_capacity(0);
+        out.indent();
+        out.push_str("x");
+        let mut frag = out.fragment();
+        frag.line("y");
+        assert_eq!(frag.finish(), "  y\n");
+        assert_eq!(out.indentation(), "  ");
+    }
+}
@@ -1,203 +1,271 @@
 
 use super::emit_val::{emit_val, emit_word, emit_cond, emit_cmd_body_raw, emit_cmd_pipe_body_raw};
-use super::helpers::{sh_single_quote, is_boolean_val, is_boolean_expr, emit_case_glob_pattern};
+use super::helpers::{sh_single_quote, is_boolean_val, is_static_val, is_boolean_expr, emit_case_glob_pattern};
+use super::builder::{ScriptBuilder, emitln};
 use super::{CodegenContext, CodegenOptions, TargetShell};
 use super::{emit_status_capture, emit_status_check, emit_status_check_ctx, emit_status_check_only};
 use crate::error::CompileError;
-use crate::ir::{Cmd, Val, RedirectOutputTarget, RedirectInputTarget, LogLevel};
+use crate::ir::{Cmd, Val, RedirectOutputTarget, RedirectInputTarget, LogLevel, FsOp, Limit};
+
+/// Emits `body` one indentation level deeper than the current line.
+fn emit_block(
+    body: &[Cmd],
+    out: &mut ScriptBuilder,
+    opts: CodegenOptions,
+    in_cond_ctx: bool,
+    ctx: &mut CodegenContext,
+) -> Result<(), CompileError> {
+    out.indent();
+    emit_cmds(body, out, opts, in_cond_ctx, ctx)?;
+    out.dedent();
+    Ok(())
+}
+
+/// Emits `body` in order. Assignments that cannot fail (see `is_static_val`)
+/// get no `__sh2_loc`/`__sh2_check` lines, and a run of them shares a single
+/// `__sh2_status=0` so `status()` still reads 0 afterwards.
+pub(super) fn emit_cmds(
+    body: &[Cmd],
+    out: &mut ScriptBuilder,
+    opts: CodegenOptions,
+    in_cond_ctx: bool,
+    ctx: &mut CodegenContext,
+) -> Result<(), CompileError> {
+    let mut status_pending = false;
+    for cmd in body {
+        if let Cmd::Assign(name, val, _) = cmd
+            && is_static_val(val)
+        {
+            emitln!(out, "{}={}", name, emit_val(val, opts.target)?);
+            status_pending = true;
+            continue;
+        }
+        if std::mem::take(&mut status_pending) {
+            out.line("__sh2_status=0");
+        }
+        emit_cmd(cmd, out, opts, in_cond_ctx, ctx)?;
+    }
+    if status_pending {
+        out.line("__sh2_status=0");
+    }
+    Ok(())
+}
+
 pub(super) fn emit_cmd(
     cmd: &Cmd,
-    out: &mut String,
-    indent: usize,
+    out: &mut ScriptBuilder,
     opts: CodegenOptions,
     in_cond_ctx: bool,
     ctx: &mut CodegenContext,
 ) -> Result<(), CompileError> {
-    let pad = " ".repeat(indent);
     let target = opts.target;
 
     match cmd {
         Cmd::Assign(name, val, loc) => {
             if let Some(l) = loc {
-                out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                emitln!(out, "__sh2_loc=\"{}\"", l);
             }
             if let Val::Lines(inner) = val {
                 if target == TargetShell::Posix {
                     return Err(CompileError::unsupported("lines() not supported in POSIX", target));
                 }
-                out.push_str(&pad);
-                out.push_str(&format!("__sh2_lines {} {}\n", emit_val(inner, target)?, name));
-                emit_status_check(&pad, out);
+                emitln!(out, "__sh2_lines {} {}", emit_val(inner, target)?, name);
+                emit_status_check(out);
                 return Ok(());
             }
             if let Val::Glob(inner) = val {
                 if target == TargetShell::Posix {
                     return Err(CompileError::unsupported("glob() requires bash target", target));
                 }
-                out.push_str(&pad);
-                out.push_str(&format!("__sh2_glob {} {}\n", name, emit_val(inner, target)?));
-                emit_status_check(&pad, out);
+                emitln!(out, "__sh2_glob {} {}", name, emit_val(inner, target)?);
+                emit_status_check(out);
                 ctx.known_lists.insert(name.to_string());
                 return Ok(());
             }
             if let Val::Split { s, delim } = val {
                 match target {
                     TargetShell::Bash => {
-                         out.push_str(&pad);
-                         out.push_str(&format!("__sh2_split {} {} {}\n", name, emit_val(s, target)?, emit_val(delim, target)?));
-                         emit_status_check(&pad, out);
+                         emitln!(out, "__sh2_split {} {} {}", name, emit_val(s, target)?, emit_val(delim, target)?);
+                         emit_status_check(out);
                          return Ok(());
                     }
                     TargetShell::Posix => {
-                         out.push_str(&pad);
-                         out.push_str(&format!("{}=\"$(__sh2_tmpfile)\"\n", name));
-                         out.push_str(&pad);
-                         out.push_str(&format!("__sh2_split {} {} > \"${}\"\n", emit_val(s, target)?, emit_val(delim, target)?, name));
-                         emit_status_check(&pad, out);
+                         emitln!(out, "{}=\"$(__sh2_tmpfile)\"", name);
+                         emitln!(out, "__sh2_split {} {} > \"${}\"", emit_val(s, target)?, emit_val(delim, target)?, name);
+                         emit_status_check(out);
                          ctx.known_lists.insert(name.to_string());
                          return Ok(());
                     }
                 }
             }
+            if let Val::InputList(prompt) = val {
+                out.pad();
+                match target {
+                    TargetShell::Bash => {
+                        writeln!(out, "__sh2_input_list {} {}", name, emit_val(prompt, target)?);
+                    }
+                    TargetShell::Posix => {
+                        writeln!(out, "{}=\"$(__sh2_tmpfile)\"", name);
+                        emitln!(out, "__sh2_input_list {} > \"${}\"", emit_val(prompt, target)?, name);
+                        ctx.known_lists.insert(name.to_string());
+                    }
+                }
+                emit_status_check(out);
+                return Ok(());
+            }
+            if let Val::Lines0(argv) = val {
+                if target == TargetShell::Posix {
+                    return Err(CompileError::unsupported("lines0()/split0() (NUL-delimited read)", target));
+                }
+                emitln!(out, "__sh2_lines0 {} {}", name, emit_words(argv, target)?);
+                emit_status_check(out);
+                return Ok(());
+            }
+            if let Val::Find { .. } = val {
+                let args = emit_find_args(val, target)?;
+                out.pad();
+                match target {
+                    TargetShell::Bash => {
+                        writeln!(out, "__sh2_find {} {}", name, args);
+                    }
+                    TargetShell::Posix => {
+                        writeln!(out, "{}=\"$(__sh2_tmpfile)\"", name);
+                        emitln!(out, "__sh2_find {} > \"${}\"", args, name);
+                        ctx.known_lists.insert(name.to_string());
+                    }
+                }
+                emit_status_check(out);
+                return Ok(());
+            }
             if let Val::FindFiles { dir, name: pattern } = val {
                 if target == TargetShell::Posix {
                     return Err(CompileError::unsupported("find_files() is only supported in Bash (requires NUL-delimited read)", target));
                 }
-                out.push_str(&pad);
-                out.push_str(&format!("__sh2_find_files {} {} {}\n", name, emit_val(dir, target)?, emit_val(pattern, target)?));
-                emit_status_check(&pad, out);
+                emitln!(out, "__sh2_find_files {} {} {}", name, emit_val(dir, target)?, emit_val(pattern, target)?);
+                emit_status_check(out);
                 return Ok(());
             }
             if let Val::Spawn { args, loc } = val {
                 // Spawn command in background and capture PID
                 // Emit: cmd args & ; varname=$!
                 if let Some(l) = loc {
-                    out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                    emitln!(out, "__sh2_loc=\"{}\"", l);
                 }
                 let shell_cmd = args
                     .iter()
                     .map(|a| emit_word(a, target))
                     .collect::<Result<Vec<_>, _>>()?
                     .join(" ");
-                out.push_str(&pad);
-                out.push_str(&format!("{} &\n", shell_cmd));
-                emit_status_capture(&pad, out);
-                out.push_str(&format!("{}{}=$!\n", pad, name));
+                emitln!(out, "{} &", shell_cmd);
+                emit_status_capture(out);
+                emitln!(out, "{}=$!", name);
+                emitln!(out, "__sh2_spawned=\"${{__sh2_spawned:-}} $!\"");
                 // Check if spawn itself failed (rare, e.g., command not found is async)
-                emit_status_check_only(&pad, out);
+                emit_status_check_only(out);
                 return Ok(());
             }
             if let Val::Wait { pid, allow_fail, loc } = val {
                 // Wait for PID and capture exit code
                 if let Some(l) = loc {
-                    out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                    emitln!(out, "__sh2_loc=\"{}\"", l);
                 }
-                out.push_str(&pad);
+                out.pad();
                 
                 if *allow_fail && target == TargetShell::Bash {
                      out.push_str("__sh2_suppress_err_depth=$((${__sh2_suppress_err_depth:-0}+1))\n");
-                     out.push_str(&format!("{}wait {}\n", pad, emit_word(pid, target)?));
-                     emit_status_capture(&pad, out);
-                     out.push_str(&format!("{}__sh2_suppress_err_depth=$((${{__sh2_suppress_err_depth:-0}}-1))\n", pad));
+                     emitln!(out, "wait {}", emit_word(pid, target)?);
+                     emit_status_capture(out);
+                     emitln!(out, "__sh2_suppress_err_depth=$((${{__sh2_suppress_err_depth:-0}}-1))");
                 } else {
-                     out.push_str(&format!("wait {}\n", emit_word(pid, target)?));
-                     emit_status_capture(&pad, out);
+                     writeln!(out, "wait {}", emit_word(pid, target)?);
+                     emit_status_capture(out);
                 }
 
                 if !allow_fail {
-                    emit_status_check_only(&pad, out);
+                    emit_status_check_only(out);
                 }
                 // Assign exit code to variable
-                out.push_str(&format!("{}{}=$__sh2_status\n", pad, name));
+                emitln!(out, "{}=$__sh2_status", name);
                 return Ok(());
             }
             if let Val::WaitAll { pids, allow_fail, loc } = val {
                 // Wait for all PIDs and return first non-zero exit code (in list order)
                 if let Some(l) = loc {
-                    out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                    emitln!(out, "__sh2_loc=\"{}\"", l);
                 }
-                
-                // Use internal temp (no user variable collision)
-                out.push_str(&format!("{}__sh2_wait_all_first=0\n", pad));
-                
-                match target {
-                    TargetShell::Bash => {
-                        if *allow_fail {
-                             out.push_str(&format!("{}__sh2_suppress_err_depth=$((${{__sh2_suppress_err_depth:-0}}+1))\n", pad));
-                        }
-
-                        // Handle list literals and variables correctly
-                        match pids.as_ref() {
-                            Val::List(elements) => {
-                                // Emit elements directly as space-separated values (no parentheses)
-                                let items: Vec<String> = elements.iter()
-                                    .map(|e| emit_word(e, target))
-                                    .collect::<Result<Vec<_>, _>>()?;
-                                let iter_expr = items.join(" ");
-                                out.push_str(&format!("{}for __sh2_pid in {}; do\n", pad, iter_expr));
-                            }
-                            Val::Var(v) => {
-                                // Variable is a Bash array - use proper expansion
-                                out.push_str(&format!("{}for __sh2_pid in \"${{{}[@]}}\"; do\n", pad, v));
-                            }
-                            _ => {
-                                // Other expressions - shouldn't reach here with current lowering
-                                return Err(CompileError::unsupported(
-                                    "wait_all() argument must be a list literal or list variable",
-                                    target,
-                                ));
-                            }
-                        }
+                let pid_words = emit_pid_list(pids, "wait_all", target)?;
 
-                        out.push_str(&format!("{}  wait \"$__sh2_pid\"\n", pad));
-                        out.push_str(&format!("{}  __sh2_status=$?\n", pad));
-                        out.push_str(&format!("{}  if [ \"$__sh2_status\" -ne 0 ] && [ \"$__sh2_wait_all_first\" -eq 0 ]; then\n", pad));
-                        out.push_str(&format!("{}    __sh2_wait_all_first=$__sh2_status\n", pad));
-                        out.push_str(&format!("{}  fi\n", pad));
-                        out.push_str(&format!("{}done\n", pad));
-
-                        if *allow_fail {
-                            out.push_str(&format!("{}__sh2_suppress_err_depth=$((${{__sh2_suppress_err_depth:-0}}-1))\n", pad));
-                        }
-                    }
-                    TargetShell::Posix => {
-                        // POSIX only supports list literals (enforced in lowering)
-                        match pids.as_ref() {
-                            Val::List(elements) => {
-                                // Emit elements as space-separated quoted values
-                                let items: Vec<String> = elements.iter()
-                                    .map(|e| emit_word(e, target))
-                                    .collect::<Result<Vec<_>, _>>()?;
-                                let iter_expr = items.join(" ");
-                                out.push_str(&format!("{}for __sh2_pid in {}; do\n", pad, iter_expr));
-                            }
-                            _ => {
-                                // Should be caught in lowering, but safety net
-                                return Err(CompileError::unsupported(
-                                    "wait_all() requires a list literal on --target posix",
-                                    target,
-                                ));
-                            }
-                        }
-                        out.push_str(&format!("{}  wait \"$__sh2_pid\"\n", pad));
-                        out.push_str(&format!("{}  __sh2_status=$?\n", pad));
-                        out.push_str(&format!("{}  if [ \"$__sh2_status\" -ne 0 ] && [ \"$__sh2_wait_all_first\" -eq 0 ]; then\n", pad));
-                        out.push_str(&format!("{}    __sh2_wait_all_first=$__sh2_status\n", pad));
-                        out.push_str(&format!("{}  fi\n", pad));
-                        out.push_str(&format!("{}done\n", pad));
-                    }
+                // Use internal temp (no user variable collision)
+                emitln!(out, "__sh2_wait_all_first=0");
+                if !allow_fail {
+                    emitln!(out, "__sh2_wait_all_statuses=\"\"");
+                    emitln!(out, "__sh2_wait_all_failed=0");
                 }
-                
+                if *allow_fail && target == TargetShell::Bash {
+                    emitln!(out, "__sh2_suppress_err_depth=$((${{__sh2_suppress_err_depth:-0}}+1))");
+                }
+                emitln!(out, "for __sh2_pid in {}; do", pid_words);
+                emitln!(out, "  wait \"$__sh2_pid\"");
+                emitln!(out, "  __sh2_status=$?");
+                if !allow_fail {
+                    emitln!(out, "  __sh2_wait_all_statuses=\"$__sh2_wait_all_statuses $__sh2_status\"");
+                    emitln!(out, "  if [ \"$__sh2_status\" -ne 0 ]; then __sh2_wait_all_failed=$((__sh2_wait_all_failed + 1)); fi");
+                }
+                emitln!(out, "  if [ \"$__sh2_status\" -ne 0 ] && [ \"$__sh2_wait_all_first\" -eq 0 ]; then");
+                emitln!(out, "    __sh2_wait_all_first=$__sh2_status");
+                emitln!(out, "  fi");
+                emitln!(out, "done");
+                if *allow_fail && target == TargetShell::Bash {
+                    emitln!(out, "__sh2_suppress_err_depth=$((${{__sh2_suppress_err_depth:-0}}-1))");
+                }
+
                 // Set final status
-                out.push_str(&format!("{}__sh2_status=$__sh2_wait_all_first\n", pad));
-                
-                // Check for failure if allow_fail is false
+                emitln!(out, "__sh2_status=$__sh2_wait_all_first");
+
+                // Report every job's status, then abort, if allow_fail is false
                 if !allow_fail {
-                    out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"\n", pad));
+                    emitln!(out, "if [ \"$__sh2_status\" -ne 0 ]; then");
+                    emitln!(out, "  printf 'wait_all: %s job(s) failed; exit statuses in list order:%s\\n' \"$__sh2_wait_all_failed\" \"$__sh2_wait_all_statuses\" >&2");
+                    emitln!(out, "fi");
+                    emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"");
                 }
-                
+
                 // Assign result to LHS variable
-                out.push_str(&format!("{}{}=$__sh2_wait_all_first\n", pad, name));
+                emitln!(out, "{}=$__sh2_wait_all_first", name);
+                return Ok(());
+            }
+            if let Val::WaitAny { pids, allow_fail, loc } = val {
+                // Poll until one PID has finished, reap it, then kill and reap the others.
+                // `kill -0` fails once the shell has reaped a finished job; its status stays
+                // available to `wait`.
+                if let Some(l) = loc {
+                    emitln!(out, "__sh2_loc=\"{}\"", l);
+                }
+                let pid_words = emit_pid_list(pids, "wait_any", target)?;
+                emitln!(out, "__sh2_wait_any_pid=\"\"");
+                emitln!(out, "while :; do");
+                emitln!(out, "  __sh2_wait_any_n=0");
+                emitln!(out, "  for __sh2_pid in {}; do", pid_words);
+                emitln!(out, "    __sh2_wait_any_n=1");
+                emitln!(out, "    if ! kill -0 \"$__sh2_pid\" 2>/dev/null; then __sh2_wait_any_pid=$__sh2_pid; break; fi");
+                emitln!(out, "  done");
+                emitln!(out, "  if [ -n \"$__sh2_wait_any_pid\" ] || [ \"$__sh2_wait_any_n\" -eq 0 ]; then break; fi");
+                emitln!(out, "  sleep 0.1 2>/dev/null || sleep 1");
+                emitln!(out, "done");
+                emitln!(out, "__sh2_status=0");
+                emitln!(out, "if [ -n \"$__sh2_wait_any_pid\" ]; then");
+                emitln!(out, "  if wait \"$__sh2_wait_any_pid\"; then :; else __sh2_status=$?; fi");
+                emitln!(out, "fi");
+                emitln!(out, "for __sh2_pid in {}; do", pid_words);
+                emitln!(out, "  if [ \"$__sh2_pid\" != \"$__sh2_wait_any_pid\" ]; then");
+                emitln!(out, "    kill \"$__sh2_pid\" 2>/dev/null || :");
+                emitln!(out, "    wait \"$__sh2_pid\" 2>/dev/null || :");
+                emitln!(out, "  fi");
+                emitln!(out, "done");
+                if !allow_fail {
+                    emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"");
+                }
+                emitln!(out, "{}=$__sh2_status", name);
                 return Ok(());
             }
             if target == TargetShell::Posix {
@@ -209,7 +277,7 @@ pub(super) fn emit_cmd(
                 }
             } else if let Val::MapLiteral(entries) = val {
                 // Bash Map Assignment
-                out.push_str(&pad);
+                out.pad();
 
                 // 1. Emit associative array
                 // local -A map=( ['k']="v" ... )
@@ -223,7 +291,7 @@ pub(super) fn emit_cmd(
 
                 for (key, value) in entries {
                     out.push(' ');
-                    out.push_str(&format!("[{}]=", sh_single_quote(key)));
+                    write!(out, "[{}]=", sh_single_quote(key));
                     out.push_str(&emit_word(value, target)?);
 
                     if !keys_seen.contains(key) {
@@ -234,19 +302,19 @@ pub(super) fn emit_cmd(
                 out.push_str(" )\n");
 
                 // 2. Emit keys array for deterministic iteration
-                out.push_str(&pad);
-                out.push_str(&format!("local -a __sh2_keys_{}=(", name));
+                out.pad();
+                write!(out, "local -a __sh2_keys_{}=(", name);
                 for key in ordered_keys {
                     out.push(' ');
                     out.push_str(&sh_single_quote(key));
                 }
                 out.push_str(" )\n");
-                emit_status_check(&pad, out);
+                emit_status_check(out);
                 return Ok(());
             }
 
             // Normal assignment
-            out.push_str(&pad);
+            out.pad();
             if let Val::List(elems) = val {
                 out.push_str(name);
                 out.push_str("=(");
@@ -264,49 +332,22 @@ pub(super) fn emit_cmd(
                     .collect::<Result<Vec<_>, _>>()?
                     .join(" ");
                 // Mktemp
-                out.push_str(&format!(
-                    "{pad}{name}__tmp_out=\"$(__sh2_tmpfile)\"\n",
-                    pad = pad,
-                    name = name
-                ));
-                out.push_str(&format!(
-                    "{pad}{name}__tmp_err=\"$(__sh2_tmpfile)\"\n",
-                    pad = pad,
-                    name = name
-                ));
+                emitln!(out, "{name}__tmp_out=\"$(__sh2_tmpfile)\"", name = name);
+                emitln!(out, "{name}__tmp_err=\"$(__sh2_tmpfile)\"", name = name);
 
                 // Run (allow fail semantics)
                 // Use 'if' to suppress ERR trap and capture status
-                out.push_str(&format!(
-                    "{pad}if {} >\"${{{}__tmp_out}}\" 2>\"${{{}__tmp_err}}\"; then {}__status=0; else {}__status=$?; fi;\n",
-                    cmd, name, name, name, name
-                ));
+                emitln!(out, "if {} >\"${{{}__tmp_out}}\" 2>\"${{{}__tmp_err}}\"; then {}__status=0; else {}__status=$?; fi;", cmd, name, name, name, name);
 
                 // Read output
-                out.push_str(&format!(
-                    "{pad}{name}__stdout=\"$(__sh2_read_file \"${name}__tmp_out\")\"\n",
-                    pad = pad,
-                    name = name
-                ));
-                out.push_str(&format!(
-                    "{pad}{name}__stderr=\"$(__sh2_read_file \"${name}__tmp_err\")\"\n",
-                    pad = pad,
-                    name = name
-                ));
+                emitln!(out, "{name}__stdout=\"$(__sh2_read_file \"${name}__tmp_out\")\"", name = name);
+                emitln!(out, "{name}__stderr=\"$(__sh2_read_file \"${name}__tmp_err\")\"", name = name);
 
                 // Cleanup
-                out.push_str(&format!(
-                    "{pad}rm -f \"${name}__tmp_out\" \"${name}__tmp_err\"\n",
-                    pad = pad,
-                    name = name
-                ));
+                emitln!(out, "rm -f \"${name}__tmp_out\" \"${name}__tmp_err\"", name = name);
 
                 // Propagate status purely for __sh2_status tracking, though try_run succeeded as a statement.
-                out.push_str(&format!(
-                    "{pad}__sh2_status=\"${name}__status\"\n",
-                    pad = pad,
-                    name = name
-                ));
+                emitln!(out, "__sh2_status=\"${name}__status\"", name = name);
             } else if is_boolean_val(val) {
                 // Boolean assignment: emit as "true"/"false" string
                 // Format: var="$( if <cond>; then printf true; else printf false; fi )"
@@ -320,7 +361,7 @@ pub(super) fn emit_cmd(
                 out.push('\n');
                 // Boolean assignment always succeeds - the condition result is stored
                 // as 1/0, not reflected in exit status.
-                out.push_str(&format!("{}__sh2_status=0\n", pad));
+                emitln!(out, "__sh2_status=0");
             } else if let Val::Capture { value, allow_fail: true } = val {
                 // capture(..., allow_fail=true) logic
                 // Generates:
@@ -336,8 +377,8 @@ pub(super) fn emit_cmd(
 
                 // Note: We avoid 'local' to support top-level usage and POSIX sh.
 
-                out.push_str(&format!("{}{}__stdout_tmp=$(__sh2_tmpfile)\n", pad, name));
-                out.push_str(&format!("{}{}__stderr_tmp=$(__sh2_tmpfile)\n", pad, name));
+                emitln!(out, "{}__stdout_tmp=$(__sh2_tmpfile)", name);
+                emitln!(out, "{}__stderr_tmp=$(__sh2_tmpfile)", name);
                 
                 let cmd_str = match &**value {
                     Val::Command(args) => emit_cmd_body_raw(args, target)?,
@@ -346,14 +387,11 @@ pub(super) fn emit_cmd(
                 };
 
                 // Use a unique status variable for this capture to avoid conflicts
-                out.push_str(&format!("{}{}__cs=0\n", pad, name));
-                out.push_str(&format!(
-                    "{}( {} ) >\"${{{}__stdout_tmp}}\" 2>\"${{{}__stderr_tmp}}\" || {}__cs=$?\n",
-                    pad, cmd_str, name, name, name
-                ));
+                emitln!(out, "{}__cs=0", name);
+                emitln!(out, "( {} ) >\"${{{}__stdout_tmp}}\" 2>\"${{{}__stderr_tmp}}\" || {}__cs=$?", cmd_str, name, name, name);
                 
-                out.push_str(&format!("{}{}__status=\"${{{}__cs}}\"\n", pad, name, name));
-                out.push_str(&format!("{}__sh2_status=\"${{{}__cs}}\"\n", pad, name));
+                emitln!(out, "{}__status=\"${{{}__cs}}\"", name, name);
+                emitln!(out, "__sh2_status=\"${{{}__cs}}\"", name);
                 // Use safe read_file helper or cat? cat is standard. read_file might have trap logic.
                  // The original code used cat. 
                  // But wait, existing code used $(cat ...).
@@ -363,20 +401,20 @@ pub(super) fn emit_cmd(
                  // Original used `cat`. Let's stick to `cat` to minimize diff risk, or upgrade to `__sh2_read_file` if safe.
                  // `emit_val` for `Val::ReadFile` uses `__sh2_read_file`.
                  // Let's use `cat` as it was verified to work.
-                out.push_str(&format!("{}{}__stdout=$(cat \"${{{}__stdout_tmp}}\")\n", pad, name, name));
-                out.push_str(&format!("{}{}__stderr=$(cat \"${{{}__stderr_tmp}}\")\n", pad, name, name));
-                out.push_str(&format!("{}{}=\"${{{}__stdout}}\"\n", pad, name, name));
-                out.push_str(&format!("{}rm -f \"${{{}__stdout_tmp}}\" \"${{{}__stderr_tmp}}\"\n", pad, name, name));
+                emitln!(out, "{}__stdout=$(cat \"${{{}__stdout_tmp}}\")", name, name);
+                emitln!(out, "{}__stderr=$(cat \"${{{}__stderr_tmp}}\")", name, name);
+                emitln!(out, "{}=\"${{{}__stdout}}\"", name, name);
+                emitln!(out, "rm -f \"${{{}__stdout_tmp}}\" \"${{{}__stderr_tmp}}\"", name, name);
                 
                 // Status captured above into name__status
                 // Restore __sh2_status from the preserved capture status, in case cleanup clobbered it.
-                out.push_str(&format!("{pad}__sh2_status=\"${{{name}__status}}\"\n", pad = pad, name = name));
+                emitln!(out, "__sh2_status=\"${{{name}__status}}\"", name = name);
 
 
             } else if let Val::Args = val {
                 out.push_str(name);
                 out.push_str("=(\"$@\")\n");
-                emit_status_check_ctx(&pad, out, in_cond_ctx);
+                emit_status_check_ctx(out, in_cond_ctx);
             } else if matches!(val, Val::Which(_)) {
                 // which() is allow-fail by design: not-found is normal control flow
                 // Capture status but do NOT call __sh2_check
@@ -386,13 +424,14 @@ pub(super) fn emit_cmd(
                     out.push_str(name);
                     out.push('=');
                     out.push_str(&emit_val(val, target)?);
-                    out.push_str(&format!(" && {}__sh2_status=0 || {}__sh2_status=$?\n", pad, pad));
+                    let pad = out.indentation().to_owned();
+                    writeln!(out, " && {pad}__sh2_status=0 || {pad}__sh2_status=$?");
                 } else {
                     out.push_str(name);
                     out.push('=');
                     out.push_str(&emit_val(val, target)?);
                     out.push('\n');
-                    emit_status_capture(&pad, out);
+                    emit_status_capture(out);
                 }
                 // No __sh2_check: which() returning 1 (not found) is intentional
             } else {
@@ -400,64 +439,89 @@ pub(super) fn emit_cmd(
                 out.push('=');
                 out.push_str(&emit_val(val, target)?);
                 out.push('\n');
-                emit_status_check_ctx(&pad, out, in_cond_ctx);
+                emit_status_check_ctx(out, in_cond_ctx);
             }
         }
         Cmd::Exec {
             args,
             allow_fail,
             loc,
+            stdin,
+            env,
+            cwd,
         } => {
+            let calls_traced = match args.first() {
+                Some(Val::Literal(name)) => ctx.traced_functions.contains(name),
+                Some(Val::CalleeVar(_)) => !ctx.traced_functions.is_empty(),
+                _ => false,
+            };
             if let Some(l) = loc {
-                // In condition context, suppress error location reporting to avoid noise before catch
-                if !in_cond_ctx {
-                    out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                // In condition context, suppress error location reporting to avoid noise before catch.
+                // A function call still records it for the callee's stack frame.
+                if !in_cond_ctx || calls_traced {
+                    emitln!(out, "__sh2_loc=\"{}\"", l);
+                }
+            }
+            // POSIX has no locals: pop the callee's frame once it returns, unless
+            // it ran in a subshell.
+            let unframe = calls_traced && target == TargetShell::Posix && stdin.is_none() && cwd.is_none() && !ctx.track_stderr;
+            if ctx.track_command {
+                emitln!(out, "__sh2_last_cmd={}", emit_command_text(args, target)?);
+            }
+            out.pad();
+            let shell_cmd = emit_exec_command(args, env, cwd.as_ref(), stdin.as_ref(), target)?;
+
+            if ctx.track_stderr {
+                // stdout goes straight through fd 3; stderr is kept for
+                // last_stderr() and shown once the command has finished.
+                writeln!(
+                    out,
+                    "__sh2_status=0; {{ __sh2_last_stderr=$( {{ {}; }} 2>&1 1>&3 3>&- ) || __sh2_status=$?; }} 3>&1",
+                    shell_cmd
+                );
+                out.line("if [ -n \"$__sh2_last_stderr\" ]; then printf '%s\\n' \"$__sh2_last_stderr\" >&2; fi");
+                if !*allow_fail {
+                    let mode = if in_cond_ctx { "return" } else { "exit" };
+                    emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"{}\"", mode);
                 }
-            }
-            out.push_str(&pad);
-            let shell_cmd = args
-                .iter()
-                .map(|a| emit_word(a, target))
-                .collect::<Result<Vec<_>, _>>()?
-                .join(" ");
-
-            if *allow_fail {
+            } else if *allow_fail {
                 // allow_fail: suppresses script failure (returns 0), captures real status in __sh2_status
                 // We use || to suppress 'set -e' and 'trap ERR' for the command.
-                out.push_str(&format!(
-                    "__sh2_status=0; {} || __sh2_status=$?; :\n",
-                    shell_cmd
-                ));
+                writeln!(out, "__sh2_status=0; {} || __sh2_status=$?; :", shell_cmd);
+                if unframe {
+                    out.line("__sh2_unframe");
+                }
             } else {
                 // Normal: capture status in __sh2_status, then check for failure
                 out.push_str(&shell_cmd);
                 out.push_str("; __sh2_status=$?
 ");
+                if unframe {
+                    out.line("__sh2_unframe");
+                }
                 if in_cond_ctx {
                     // In condition context (e.g. try block), we must NOT exit the script.
                     // We use (exit $s) to set $? and trigger errexit if active (which catch handles).
-                    out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"
-", pad));
+                    out.line("__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\" \"return\"");
                 } else {
                     // Normal context: use __sh2_check to fail-fast with diagnostics
-                    out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"exit\"
-", pad));
+                    out.line("__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\" \"exit\"");
                 }
             }
         }
 
         Cmd::ExecReplace(args, loc) => {
              if let Some(l) = loc {
-                 out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                 emitln!(out, "__sh2_loc=\"{}\"", l);
              }
-             out.push_str(&pad);
+             out.pad();
              out.push_str("exec ");
              let shell_args: Vec<String> = args.iter().map(|a| emit_word(a, target)).collect::<Result<Vec<_>, _>>()?;
              out.push_str(&shell_args.join(" "));
              out.push('\n');
         }
         Cmd::Print(val) => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str("printf '%s\\n' ");
             match val {
                 Val::Args => out.push_str("\"$*\""),
@@ -466,7 +530,7 @@ pub(super) fn emit_cmd(
             out.push('\n');
         }
         Cmd::PrintErr(val) => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str("printf '%s\\n' ");
             match val {
                 Val::Args => out.push_str("\"$*\""),
@@ -481,31 +545,36 @@ pub(super) fn emit_cmd(
             else_body,
         } => {
             let cond_str = emit_cond(cond, target)?;
-            out.push_str(&format!("{pad}if {cond_str}; then\n"));
-            for c in then_body {
-                emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
-            }
+            emitln!(out, "if {cond_str}; then");
+            emit_block(then_body, out, opts, in_cond_ctx, ctx)?;
 
             for (cond, body) in elifs {
                 let cond_str = emit_cond(cond, target)?;
-                out.push_str(&format!("{pad}elif {cond_str}; then\n"));
-                for c in body {
-                    emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
-                }
+                emitln!(out, "elif {cond_str}; then");
+                emit_block(body, out, opts, in_cond_ctx, ctx)?;
             }
 
             if !else_body.is_empty() {
-                out.push_str(&format!("{pad}else\n"));
-                for c in else_body {
-                    emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
-                }
+                emitln!(out, "else");
+                emit_block(else_body, out, opts, in_cond_ctx, ctx)?;
             }
 
-            out.push_str(&format!("{pad}fi\n"));
+            emitln!(out, "fi");
         }
         Cmd::Pipe(segments, loc) => {
             if let Some(l) = loc {
-                out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                emitln!(out, "__sh2_loc=\"{}\"", l);
+            }
+            if ctx.track_command {
+                let words = segments
+                    .iter()
+                    .map(|(args, _, _)| emit_command_text(args, target))
+                    .collect::<Result<Vec<_>, _>>()?;
+                emitln!(out, "__sh2_last_cmd={}", words.join("' | '"));
+            }
+            if ctx.track_stderr {
+                // Pipeline stderr is not captured; don't report an earlier command's.
+                out.line("__sh2_last_stderr=");
             }
             // Bash: use subshell with pipefail for robust status capture
             // POSIX: manual pipeline with FIFOs via helper
@@ -515,39 +584,13 @@ pub(super) fn emit_cmd(
 
             match target {
                 TargetShell::Bash => {
-                    out.push_str(&pad);
+                    out.pad();
 
-                    let mut pipe_str = String::new();
-                    for (i, (args, allow_fail)) in segments.iter().enumerate() {
-                        if i > 0 {
-                            pipe_str.push_str(" | ");
-                        }
-
-                        let cmd_str = args
-                            .iter()
-                            .map(|a| emit_word(a, target))
-                            .collect::<Result<Vec<_>, _>>()?
-                            .join(" ");
-
-                        if *allow_fail {
-                            if i < last_idx {
-                                // Non-final segment: suppress failure
-                                pipe_str.push_str(&format!("if {}; then :; else true; fi", cmd_str));
-                            } else {
-                                // Final segment: suppress ERR trap via 'if' context, preserve exit status
-                                pipe_str.push_str(&format!("if {}; then :; else exit $?; fi", cmd_str));
-                            }
-                        } else {
-                            pipe_str.push_str(&cmd_str);
-                        }
-                    }
+                    let pipe_str = emit_bash_pipe_body(segments, target)?;
 
                      // Wrap in subshell to isolate set -o pipefail and set +e
                      // Use 'if' to capture status while suppressing ERR trap for the pipeline itself.
-                     out.push_str(&format!(
-                          "if ( set -o pipefail; set +e; {} ); then __sh2_status=0; else __sh2_status=$?; fi; ",
-                          pipe_str
-                      ));
+                     write!(out, "if ( set -o pipefail; set +e; {} ); then __sh2_status=0; else __sh2_status=$?; fi; ", pipe_str);
 
                     // Return
                     if allow_fail_last {
@@ -557,85 +600,99 @@ pub(super) fn emit_cmd(
                     }
                 }
                 TargetShell::Posix => {
-                    let stages: Vec<String> = segments
-                        .iter()
-                        .map(|(args, _)| {
-                            let parts = args.iter()
-                                .map(|a| emit_word(a, target))
-                                .collect::<Result<Vec<_>, _>>()?;
-                            Ok(parts.join(" "))
-                        })
-                        .collect::<Result<_, _>>()?;
-                    let allow_fails: Vec<bool> = segments.iter().map(|(_, af)| *af).collect();
+                    let stages = emit_posix_pipe_stages(segments, target)?;
+                    let allow_fails: Vec<bool> = segments.iter().map(|(_, af, _)| *af).collect();
 
                     emit_posix_pipeline(
                         out,
-                        &pad,
                         target,
                         &stages,
                         &allow_fails,
                         allow_fail_last,
                         loc.is_some(),
-                    );
+                    )?;
                 }
             }
         }
         Cmd::PipeBlocks(segments, loc) => {
             if let Some(l) = loc {
-                out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                emitln!(out, "__sh2_loc=\"{}\"", l);
             }
             match target {
                 TargetShell::Bash => {
-                    out.push_str(&pad);
-                    let mut pipe_str = String::new();
-                    for (i, seg) in segments.iter().enumerate() {
-                        if i > 0 {
-                            pipe_str.push_str(" | ");
-                        }
-                        pipe_str.push_str("{\n");
-                        for cmd in seg {
-                            emit_cmd(cmd, &mut pipe_str, indent + 2, opts, false, ctx)?;
-                        }
-                        pipe_str.push_str(&format!("{pad}}}"));
-                    }
+                    out.pad();
+                    let pipe_str = emit_pipe_block_stages(segments, out, opts, ctx)?.join(" | ");
 
-                    out.push_str(&format!(
-                        "if ( set -o pipefail; set +e; {} ); then __sh2_status=0; else __sh2_status=$?; fi; ",
-                        pipe_str
-                    ));
+                    write!(out, "if ( set -o pipefail; set +e; {} ); then __sh2_status=0; else __sh2_status=$?; fi; ", pipe_str);
 
                     out.push_str("(exit $__sh2_status)\n");
                 }
                 TargetShell::Posix => {
-                    let stages: Vec<String> = segments
-                        .iter()
-                        .map(|seg| {
-                            let mut s = String::new();
-                            s.push_str("{\n");
-                            for cmd in seg {
-                                emit_cmd(cmd, &mut s, indent + 2, opts, false, ctx)?;
-                            }
-                            s.push_str(&format!("{pad}}}"));
-                            Ok(s)
-                        })
-                        .collect::<Result<_, _>>()?;
+                    let stages = emit_pipe_block_stages(segments, out, opts, ctx)?;
                     let allow_fails = vec![false; segments.len()];
                     emit_posix_pipeline(
                         out,
-                        &pad,
                         target,
                         &stages,
                         &allow_fails,
                         false,
                         loc.is_some(),
-                    );
+                    )?;
+                }
+            }
+        }
+        Cmd::CapturePipe { name, pipe } => {
+            // Same status rules as the statement pipeline, but run inside the
+            // command substitution that feeds `name`.
+            let (loc, allow_fail_last) = match pipe.as_ref() {
+                Cmd::Pipe(segments, loc) => (loc, segments[segments.len() - 1].1),
+                Cmd::PipeBlocks(_, loc) => (loc, false),
+                _ => return Err(CompileError::internal("CapturePipe expects a Pipe or PipeBlocks", target)),
+            };
+            if let Some(l) = loc {
+                emitln!(out, "__sh2_loc=\"{}\"", l);
+            }
+            let body = match (pipe.as_ref(), target) {
+                (Cmd::Pipe(segments, _), TargetShell::Bash) => {
+                    format!(" set -o pipefail; set +e; {} ", emit_bash_pipe_body(segments, target)?)
+                }
+                (Cmd::PipeBlocks(segments, _), TargetShell::Bash) => {
+                    format!(" set -o pipefail; set +e; {} ", emit_pipe_block_stages(segments, out, opts, ctx)?.join(" | "))
+                }
+                (_, TargetShell::Posix) => {
+                    let (stages, allow_fails) = match pipe.as_ref() {
+                        Cmd::Pipe(segments, _) => (
+                            emit_posix_pipe_stages(segments, target)?,
+                            segments.iter().map(|(_, af, _)| *af).collect::<Vec<_>>(),
+                        ),
+                        Cmd::PipeBlocks(segments, _) => (
+                            emit_pipe_block_stages(segments, out, opts, ctx)?,
+                            vec![false; segments.len()],
+                        ),
+                        _ => unreachable!(),
+                    };
+                    let mut s = out.fragment();
+                    s.push('\n');
+                    emit_posix_pipeline(&mut s, target, &stages, &allow_fails, true, loc.is_some())?;
+                    emitln!(s, "exit \"$__sh2_status\"");
+                    s.pad();
+                    s.finish()
+                }
+                _ => unreachable!(),
+            };
+            emitln!(out, "if {}=\"$({})\"; then __sh2_status=0; else __sh2_status=$?; fi", name, body);
+            if !allow_fail_last {
+                if in_cond_ctx {
+                    emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"");
+                } else {
+                    emit_status_check_only(out);
                 }
             }
         }
         Cmd::Case { expr, arms } => {
-            out.push_str(&format!("{}case {} in\n", pad, emit_val(expr, target)?));
+            emitln!(out, "case {} in", emit_val(expr, target)?);
             for (patterns, body) in arms {
-                out.push_str(&pad);
+                out.pad();
                 out.push_str("  ");
                 let pat_strs: Vec<String> = patterns
                     .iter()
@@ -648,38 +705,124 @@ pub(super) fn emit_cmd(
                 out.push_str(&pat_strs.join("|"));
                 out.push_str(")\n");
 
-                for cmd in body {
-                    emit_cmd(cmd, out, indent + 4, opts, in_cond_ctx, ctx)?;
-                }
-                out.push_str(&format!("{}  ;;\n", pad));
+                out.indent();
+                emit_block(body, out, opts, in_cond_ctx, ctx)?;
+                out.dedent();
+                emitln!(out, "  ;;");
             }
-            out.push_str(&format!("{}esac\n", pad));
+            emitln!(out, "esac");
         }
 
         Cmd::While { cond, body } => {
             let cond_str = emit_cond(cond, target)?;
-            out.push_str(&format!("{pad}while {cond_str}; do\n"));
-            for c in body {
-                emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
+            emitln!(out, "while {cond_str}; do");
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
+            emitln!(out, "done");
+        }
+        Cmd::ServiceStart { pidfile, args, log, loc } => {
+            if let Some(l) = loc
+                && !in_cond_ctx
+            {
+                emitln!(out, "__sh2_loc=\"{}\"", l);
+            }
+            let log_str = match log {
+                Some(l) => emit_val(l, target)?,
+                None => "/dev/null".to_string(),
+            };
+            out.pad();
+            write!(out, "__sh2_service_start {} {}", emit_val(pidfile, target)?, log_str);
+            for a in args {
+                out.push(' ');
+                out.push_str(&emit_word(a, target)?);
             }
-            out.push_str(&format!("{pad}done\n"));
+            out.push('\n');
+            emit_status_check_ctx(out, in_cond_ctx);
+        }
+        Cmd::WaitForPort { host, port, timeout, interval, loc } => {
+            if let Some(l) = loc
+                && !in_cond_ctx
+            {
+                emitln!(out, "__sh2_loc=\"{}\"", l);
+            }
+            emitln!(out, "__sh2_wait_for_port {} {} {} {}", emit_word(host, target)?, emit_word(port, target)?, emit_word(timeout, target)?, emit_word(interval, target)?);
+            emit_status_check_ctx(out, in_cond_ctx);
+        }
+        Cmd::ServiceStop { pidfile, grace, loc } => {
+            if let Some(l) = loc
+                && !in_cond_ctx
+            {
+                emitln!(out, "__sh2_loc=\"{}\"", l);
+            }
+            let grace_str = match grace {
+                Some(g) => emit_val(g, target)?,
+                None => "10".to_string(),
+            };
+            emitln!(out, "__sh2_service_stop {} {}", emit_val(pidfile, target)?, grace_str);
+            emit_status_check_ctx(out, in_cond_ctx);
+        }
+        Cmd::Fs { op, args, recursive, force, loc } => {
+            if let Some(l) = loc
+                && !in_cond_ctx
+            {
+                emitln!(out, "__sh2_loc=\"{}\"", l);
+            }
+            let mut line = match op {
+                FsOp::Mkdirs => "mkdir -p".to_string(),
+                FsOp::Copy => "cp".to_string(),
+                FsOp::Move => "mv".to_string(),
+                FsOp::Remove => "rm".to_string(),
+            };
+            if *recursive {
+                // cp -R is the POSIX spelling; rm accepts -R as well
+                line.push_str(" -R");
+            }
+            if *force {
+                line.push_str(" -f");
+            }
+            line.push_str(" --");
+            for a in args {
+                line.push(' ');
+                line.push_str(&emit_val(a, target)?);
+            }
+            out.pad();
+            out.push_str(&line);
+            out.push('\n');
+            emit_status_check_ctx(out, in_cond_ctx);
+        }
+        Cmd::ReplaceInFile { path, pattern, replacement, backup, loc } => {
+            if let Some(l) = loc
+                && !in_cond_ctx
+            {
+                emitln!(out, "__sh2_loc=\"{}\"", l);
+            }
+            emitln!(out, "__sh2_replace_in_file {} {} {} {}", emit_val(path, target)?, emit_val(pattern, target)?, emit_val(replacement, target)?, backup);
+            emit_status_check_ctx(out, in_cond_ctx);
+        }
+        Cmd::AppendLineIfMissing { path, line, backup, loc } => {
+            if let Some(l) = loc
+                && !in_cond_ctx
+            {
+                emitln!(out, "__sh2_loc=\"{}\"", l);
+            }
+            emitln!(out, "__sh2_append_line_if_missing {} {} {}", emit_val(path, target)?, emit_val(line, target)?, backup);
+            emit_status_check_ctx(out, in_cond_ctx);
         }
         Cmd::Require(cmds) => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str("__sh2_require");
             for cmd in cmds {
                 out.push(' ');
                 out.push_str(&emit_word(cmd, target)?);
             }
             out.push('\n');
-            emit_status_check(&pad, out);
+            emit_status_check(out);
         }
         Cmd::Log {
             level,
             msg,
             timestamp,
         } => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str("__sh2_log ");
             match level {
                 LogLevel::Info => out.push_str("'INFO' "),
@@ -699,33 +842,49 @@ pub(super) fn emit_cmd(
             // Policy A: Loop variable effectively declared here.
             // Initialize if unset, preserve if set. Use local since we are in function.
             if target == TargetShell::Bash {
-                out.push_str(&format!("{}local {}=\"${{{}:-}}\"\n", pad, var, var));
+                emitln!(out, "local {}=\"${{{}:-}}\"", var, var);
             } else {
-                out.push_str(&format!("{}{}=\"${{{}:-}}\"\n", pad, var, var));
-            }
+                emitln!(out, "{}=\"${{{}:-}}\"", var, var);
+            }
+
+            // On Bash, `for f in find(...)` streams results like find0() instead of
+            // materialising the whole list first.
+            let streamed = match iterable {
+                crate::ir::ForIterable::List(items) if target == TargetShell::Bash => match items.as_slice() {
+                    [Val::Find { dir, name, type_filter, maxdepth }] => Some(crate::ir::ForIterable::Find0 {
+                        dir: dir.clone(),
+                        name: name.clone(),
+                        type_filter: type_filter.clone(),
+                        maxdepth: maxdepth.clone(),
+                    }),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let iterable = streamed.as_ref().unwrap_or(iterable);
 
             match iterable {
                 crate::ir::ForIterable::List(items) => {
                     // Check if we need POSIX list iteration (file-based)
                     let is_posix_list_mode = target == TargetShell::Posix && items.iter().any(|i|
-                        matches!(i, Val::Split { .. } | Val::Lines(_) | Val::Glob(_)) ||
+                        matches!(i, Val::Split { .. } | Val::Lines(_) | Val::Glob(_) | Val::Find { .. }) ||
                         (if let Val::Var(n) = i { ctx.known_lists.contains(n) } else { false })
                     );
 
                     if is_posix_list_mode {
                         // POSIX List Iteration: Generate stream to temp file, then while-read it to preserve body semantics
-                        out.push_str(&pad);
-                        out.push_str(&format!("__sh2_for_tmp_{}=$(__sh2_tmpfile)\n", var));
-                        out.push_str(&pad);
-                        out.push_str("{\n");
+                        emitln!(out, "__sh2_for_tmp_{}=$(__sh2_tmpfile)", var);
+                        out.line("{");
                         for item in items {
                             match item {
                                 Val::Split { s, delim } => {
-                                    out.push_str(&format!("{}  __sh2_split {} {}\n", pad, emit_val(s, target)?, emit_val(delim, target)?));
+                                    emitln!(out, "  __sh2_split {} {}", emit_val(s, target)?, emit_val(delim, target)?);
                                 }
                                 Val::Lines(inner) => {
                                     if let Val::ReadFile(path) = &**inner {
-                                        out.push_str(&format!("{}  cat {}\n", pad, emit_val(path, target)?));
+                                        emitln!(out, "  cat {}", emit_val(path, target)?);
+                                    } else if let Val::ReadFileOr { path, default } = &**inner {
+                                        emitln!(out, "  __sh2_read_file_or {} {}", emit_val(path, target)?, emit_val(default, target)?);
                                     } else {
                                         return Err(CompileError::unsupported("lines() iteration not supported in POSIX", target));
                                     }
@@ -733,33 +892,34 @@ pub(super) fn emit_cmd(
                                 Val::Glob(_) => {
                                     return Err(CompileError::unsupported("glob() requires bash target", target));
                                 }
+                                Val::Find { .. } => {
+                                    emitln!(out, "  __sh2_find {}", emit_find_args(item, target)?);
+                                }
+                                Val::Lines0(_) => {
+                                    return Err(CompileError::unsupported("lines0()/split0() (NUL-delimited read)", target));
+                                }
                                 Val::Var(n) if ctx.known_lists.contains(n) => {
-                                    out.push_str(&format!("{}  cat \"${}\"\n", pad, n));
+                                    emitln!(out, "  cat \"${}\"", n);
                                 }
                                 _ => {
                                     // Treat other items (literals, unknown string vars) as single lines
-                                    out.push_str(&format!("{}  printf '%s\\n' {}\n", pad, emit_val(item, target)?));
+                                    emitln!(out, "  printf '%s\\n' {}", emit_val(item, target)?);
                                 }
                             }
                         }
-                        out.push_str(&pad);
-                        out.push_str(&format!("}} > \"$__sh2_for_tmp_{}\"\n", var));
+                        emitln!(out, "}} > \"$__sh2_for_tmp_{}\"", var);
                         
 
 
-                        out.push_str(&pad);
-                        out.push_str(&format!("while IFS= read -r {} || [ -n \"${}\" ]; do\n", var, var));
+                        emitln!(out, "while IFS= read -r {} || [ -n \"${}\" ]; do", var, var);
 
-                        for c in body {
-                            emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
-                        }
+                        emit_block(body, out, opts, in_cond_ctx, ctx)?;
                         
-                        out.push_str(&pad);
+                        out.pad();
                         out.push_str("done < \"$__sh2_for_tmp_");
                         out.push_str(var);
                         out.push_str("\"\n");
-                        out.push_str(&pad);
-                        out.push_str(&format!("rm -f \"$__sh2_for_tmp_{}\"\n", var));
+                        emitln!(out, "rm -f \"$__sh2_for_tmp_{}\"", var);
                     } else {
                         // Pre-process any Lines() / Split items for Bash (Arrays)
                         for (idx, item) in items.iter().enumerate() {
@@ -767,28 +927,34 @@ pub(super) fn emit_cmd(
                                 if target == TargetShell::Posix {
                                     return Err(CompileError::unsupported("lines() iteration not supported in POSIX", target));
                                 }
-                                out.push_str(&pad);
-                                out.push_str(&format!("__sh2_lines {} __sh2_for_lines_{}\n", emit_val(inner, target)?, idx));
+                                emitln!(out, "__sh2_lines {} __sh2_for_lines_{}", emit_val(inner, target)?, idx);
                             }
                             if let Val::Split { s, delim } = item {
                                 if target == TargetShell::Bash {
-                                    out.push_str(&pad);
-                                    out.push_str(&format!("__sh2_split __sh2_for_split_{} {} {}\n", idx, emit_val(s, target)?, emit_val(delim, target)?));
+                                    emitln!(out, "__sh2_split __sh2_for_split_{} {} {}", idx, emit_val(s, target)?, emit_val(delim, target)?);
                                 }
                             }
                             if let Val::Glob(inner) = item {
                                 if target == TargetShell::Posix {
                                     return Err(CompileError::unsupported("glob() requires bash target", target));
                                 }
-                                out.push_str(&pad);
-                                out.push_str(&format!("__sh2_glob __sh2_for_glob_{} {}\n", idx, emit_val(inner, target)?));
+                                emitln!(out, "__sh2_glob __sh2_for_glob_{} {}", idx, emit_val(inner, target)?);
                             }
                             if let Val::FindFiles { dir, name } = item {
                                 if target == TargetShell::Posix {
                                     return Err(CompileError::unsupported("find_files() is only supported in Bash (requires NUL-delimited read)", target));
                                 }
-                                out.push_str(&pad);
-                                out.push_str(&format!("__sh2_find_files __sh2_for_find_{} {} {}\n", idx, emit_val(dir, target)?, emit_val(name, target)?));
+                                emitln!(out, "__sh2_find_files __sh2_for_find_{} {} {}", idx, emit_val(dir, target)?, emit_val(name, target)?);
+                            }
+                            if let Val::Find { .. } = item {
+                                emitln!(out, "__sh2_find __sh2_for_find_{} {}", idx, emit_find_args(item, target)?);
+                            }
+                            if let Val::Lines0(argv) = item {
+                                if target == TargetShell::Posix {
+                                    return Err(CompileError::unsupported("lines0()/split0() (NUL-delimited read)", target));
+                                }
+                                emitln!(out, "__sh2_lines0 __sh2_for_lines0_{} {}", idx, emit_words(argv, target)?);
+                                emit_status_check_ctx(out, in_cond_ctx);
                             }
                         }
 
@@ -797,26 +963,28 @@ pub(super) fn emit_cmd(
                             // Empty list iteration: for x in () { ... }
                             // This results in zero iterations. We can optimize this away entirely,
                             // or emit a no-op to ensure valid shell syntax if it's the only stmt.
-                            out.push_str(&pad);
-                            out.push_str(":\n");
+                            out.line(":");
                         } else {
-                            out.push_str(&pad);
-                            out.push_str(&format!("for {} in", var));
+                            out.pad();
+                            write!(out, "for {} in", var);
                             for (idx, item) in items.iter().enumerate() {
                                 match item {
                                     Val::Lines(_) => {
-                                        out.push_str(&format!(" \"${{__sh2_for_lines_{}[@]}}\"", idx));
+                                        write!(out, " \"${{__sh2_for_lines_{}[@]}}\"", idx);
                                     }
                                     Val::Split { .. } => {
                                         // Bash array pre-calc handled above
-                                        out.push_str(&format!(" \"${{__sh2_for_split_{}[@]}}\"", idx));
+                                        write!(out, " \"${{__sh2_for_split_{}[@]}}\"", idx);
                                     }
                                     Val::Glob(_) => {
                                         // Bash array pre-calc handled above
-                                        out.push_str(&format!(" \"${{__sh2_for_glob_{}[@]}}\"", idx));
+                                        write!(out, " \"${{__sh2_for_glob_{}[@]}}\"", idx);
+                                    }
+                                    Val::FindFiles { .. } | Val::Find { .. } => {
+                                        write!(out, " \"${{__sh2_for_find_{}[@]}}\"", idx);
                                     }
-                                    Val::FindFiles { .. } => {
-                                        out.push_str(&format!(" \"${{__sh2_for_find_{}[@]}}\"", idx));
+                                    Val::Lines0(_) => {
+                                        write!(out, " \"${{__sh2_for_lines0_{}[@]}}\"", idx);
                                     }
                                     Val::List(elems) => {
                                         for elem in elems {
@@ -828,7 +996,7 @@ pub(super) fn emit_cmd(
                                         if target == TargetShell::Posix {
                                             return Err(CompileError::unsupported("Iterating over array variable not supported in POSIX", target));
                                         }
-                                        out.push_str(&format!(" \"${{{}[@]}}\"", name));
+                                        write!(out, " \"${{{}[@]}}\"", name);
                                     }
                                     _ => {
                                         out.push(' ');
@@ -838,41 +1006,30 @@ pub(super) fn emit_cmd(
                             }
                             out.push_str("; do\n");
                         
-                            for c in body {
-                                emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
-                            }
+                            emit_block(body, out, opts, in_cond_ctx, ctx)?;
                             
-                            out.push_str(&pad);
-                            out.push_str("done\n");
+                            out.line("done");
                         }
                     }
                 }
                 crate::ir::ForIterable::Range(start, end) => {
-                    out.push_str(&pad);
+                    out.pad();
                     // Use seq for ranges
-                    out.push_str(&format!("for {} in $(seq {} {}); do\n", var, emit_val(start, target)?, emit_val(end, target)?));
+                    writeln!(out, "for {} in $(seq {} {}); do", var, emit_val(start, target)?, emit_val(end, target)?);
                     
-                    for c in body {
-                        emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
-                    }
-                    out.push_str(&pad);
-                    out.push_str("done\n");
+                    emit_block(body, out, opts, in_cond_ctx, ctx)?;
+                    out.line("done");
                 }
                 crate::ir::ForIterable::StdinLines => {
                     // Use a temp variable for reading to preserve 'var' if no input is read
                     // (read clears var on EOF). Policy A init is emitted above (shared with List/Range).
                     let tmp_var = format!("__sh2_read_tmp_{}", var);
-                    let pad_inner = " ".repeat(indent + 2);
-                    out.push_str(&pad);
-                    out.push_str(&format!("while IFS= read -r {} || [ -n \"${}\" ]; do\n", tmp_var, tmp_var));
+                    emitln!(out, "while IFS= read -r {} || [ -n \"${}\" ]; do", tmp_var, tmp_var);
                     // Assign temp to actual loop var at inner indentation
-                    out.push_str(&format!("{}{}=\"${}\"\n", pad_inner, var, tmp_var));
+                    emitln!(out, "  {}=\"${}\"", var, tmp_var);
 
-                    for c in body {
-                        emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
-                    }
-                    out.push_str(&pad);
-                    out.push_str("done\n");
+                    emit_block(body, out, opts, in_cond_ctx, ctx)?;
+                    out.line("done");
                 }
                 crate::ir::ForIterable::Find0 { dir, name, type_filter, maxdepth } => {
                     if target == TargetShell::Posix {
@@ -886,44 +1043,33 @@ pub(super) fn emit_cmd(
                     // HOWEVER: `find` treats any argument starting with `-` as a predicate, even after `--`.
                     // So we must ensure relative paths starting with `-` are prefixed with `./`.
                     let argv_var = format!("__sh2_find0_argv_{}", var);
-                    out.push_str(&pad);
-                    out.push_str(&format!("local -a {}\n", argv_var));
-                    out.push_str(&pad);
+                    emitln!(out, "local -a {}", argv_var);
+                    out.pad();
                     // Runtime check: if dir starts with -, prepend ./
                     // This is safe because absolute paths start with /
-                    out.push_str(&format!("local __sh2_find0_dir_{}={}\n", var, emit_val(dir, target)?));
-                    out.push_str(&pad);
-                    out.push_str(&format!("if [[ \"$__sh2_find0_dir_{}\" == -* ]]; then __sh2_find0_dir_{}=\"./$__sh2_find0_dir_{}\"; fi\n", var, var, var));
-                    out.push_str(&pad);
-                    out.push_str(&format!("{}=(find -- \"$__sh2_find0_dir_{}\" -mindepth 1)\n", argv_var, var));
+                    writeln!(out, "local __sh2_find0_dir_{}={}", var, emit_val(dir, target)?);
+                    emitln!(out, "if [[ \"$__sh2_find0_dir_{}\" == -* ]]; then __sh2_find0_dir_{}=\"./$__sh2_find0_dir_{}\"; fi", var, var, var);
+                    emitln!(out, "{}=(find -- \"$__sh2_find0_dir_{}\" -mindepth 1)", argv_var, var);
                     
                     if let Some(md) = maxdepth {
-                        out.push_str(&pad);
-                        out.push_str(&format!("{}+=(-maxdepth {})\n", argv_var, emit_val(md, target)?));
+                        emitln!(out, "{}+=(-maxdepth {})", argv_var, emit_val(md, target)?);
                     }
                     if let Some(n) = name {
-                        out.push_str(&pad);
-                        out.push_str(&format!("{}+=(-name {})\n", argv_var, emit_val(n, target)?));
+                        emitln!(out, "{}+=(-name {})", argv_var, emit_val(n, target)?);
                     }
                     if let Some(tf) = type_filter {
-                        out.push_str(&pad);
-                        out.push_str(&format!("{}+=(-type {})\n", argv_var, emit_val(tf, target)?));
+                        emitln!(out, "{}+=(-type {})", argv_var, emit_val(tf, target)?);
                     }
-                    out.push_str(&pad);
-                    out.push_str(&format!("{}+=(-print0)\n", argv_var));
+                    emitln!(out, "{}+=(-print0)", argv_var);
 
                     let tmp_var = format!("__sh2_find0_{}", var);
-                    out.push_str(&pad);
-                    out.push_str(&format!("while IFS= read -r -d '' {}; do\n", tmp_var));
-                    let pad_inner = " ".repeat(indent + 2);
-                    out.push_str(&format!("{}{}=\"${}\"\n", pad_inner, var, tmp_var));
+                    emitln!(out, "while IFS= read -r -d '' {}; do", tmp_var);
+                    emitln!(out, "  {}=\"${}\"", var, tmp_var);
 
-                    for c in body {
-                        emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
-                    }
-                    out.push_str(&pad);
+                    emit_block(body, out, opts, in_cond_ctx, ctx)?;
+                    out.pad();
                     // Execute the find command via the array, preventing shell splitting/globbing of arguments.
-                    out.push_str(&format!("done < <(\"${{{}[@]}}\" 2>/dev/null | LC_ALL=C sort -z)\n", argv_var));
+                    writeln!(out, "done < <(\"${{{}[@]}}\" 2>/dev/null | LC_ALL=C sort -z)", argv_var);
                 }
             }
         }
@@ -941,97 +1087,93 @@ pub(super) fn emit_cmd(
             //   local key="$__sh2_k"
             //   local val="${map[$__sh2_k]}"
             //   ...
-            out.push_str(&format!(
-                "{pad}for __sh2_k in \"${{__sh2_keys_{}[@]}}\"; do\n",
-                map
-            ));
-            out.push_str(&format!("{pad}  local {}=\"$__sh2_k\"\n", key_var));
-            out.push_str(&format!(
-                "{pad}  local {}=\"${{{}[$__sh2_k]}}\"\n",
-                val_var, map
-            ));
+            emitln!(out, "for __sh2_k in \"${{__sh2_keys_{}[@]}}\"; do", map);
+            emitln!(out, "  local {}=\"$__sh2_k\"", key_var);
+            emitln!(out, "  local {}=\"${{{}[$__sh2_k]}}\"", val_var, map);
 
-            for c in body {
-                emit_cmd(c, out, indent + 2, opts, in_cond_ctx, ctx)?;
-            }
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
 
-            out.push_str(&format!("{pad}done\n"));
+            emitln!(out, "done");
         }
         Cmd::Break => {
-            out.push_str(&format!("{pad}break\n"));
+            emitln!(out, "break");
         }
         Cmd::Continue => {
-            out.push_str(&format!("{pad}continue\n"));
+            emitln!(out, "continue");
         }
         Cmd::Return(val) => {
             if let Some(v) = val {
                 if is_boolean_expr(v) {
                     let cond_str = emit_cond(v, target)?;
-                    out.push_str(&format!(
-                        "{pad}if {}; then printf '%s' 1; fi\n",
-                        cond_str
-                    ));
-                    out.push_str(&format!("{pad}return 0\n"));
+                    emitln!(out, "if {}; then printf '%s' 1; fi", cond_str);
+                    emitln!(out, "return 0");
                 } else {
-                    out.push_str(&format!("{pad}printf '%s' {}\n", emit_val(v, target)?));
-                    out.push_str(&format!("{pad}return 0\n"));
+                    emitln!(out, "printf '%s' {}", emit_val(v, target)?);
+                    emitln!(out, "return 0");
                 }
             } else {
-                out.push_str(&format!("{pad}return 0\n"));
+                emitln!(out, "return 0");
+            }
+        }
+        Cmd::ReturnBool(v) => {
+            // A traced POSIX caller cannot pop the frame after `if f`, so the
+            // callee pops its own.
+            if ctx.unframe_on_return {
+                out.line("__sh2_unframe");
+            }
+            match v {
+                Val::Bool(true) => emitln!(out, "return 0"),
+                Val::Bool(false) => emitln!(out, "return 1"),
+                _ => emitln!(out, "if {}; then return 0; else return 1; fi", emit_cond(v, target)?),
             }
         }
         Cmd::Exit(val) => {
             if let Some(v) = val {
                 if is_boolean_expr(v) {
                     let cond_str = emit_cond(v, target)?;
-                    out.push_str(&format!(
-                        "{pad}if {}; then exit 0; else exit 1; fi\n",
-                        cond_str
-                    ));
+                    emitln!(out, "if {}; then exit 0; else exit 1; fi", cond_str);
                 } else {
-                    out.push_str(&format!("{pad}exit {}\n", emit_val(v, target)?));
+                    emitln!(out, "exit {}", emit_val(v, target)?);
                 }
             } else {
-                out.push_str(&format!("{pad}exit\n"));
+                emitln!(out, "exit");
             }
         }
-        Cmd::WriteFile { path, content, append } => {
+        Cmd::WriteFile { path, content, append, atomic, mode } => {
+            if *atomic {
+                emitln!(out, "__sh2_write_file_atomic {} {} '{}'", emit_val(path, target)?, emit_val(content, target)?, mode.as_deref().unwrap_or(""));
+                emit_status_check_ctx(out, in_cond_ctx);
+                return Ok(());
+            }
             let op = if *append { ">>" } else { ">" };
-            out.push_str(&pad);
-            out.push_str(&format!(
-                "printf '%s' {} {} {}",
-                emit_val(content, target)?,
-                op,
-                emit_val(path, target)?
-            ));
+            out.pad();
+            write!(out, "printf '%s' {} {} {}", emit_val(content, target)?, op, emit_val(path, target)?);
             out.push('\n');
-            emit_status_check_ctx(&pad, out, in_cond_ctx);
+            emit_status_check_ctx(out, in_cond_ctx);
+            if let Some(m) = mode {
+                emitln!(out, "chmod {} {}", m, emit_val(path, target)?);
+                emit_status_check_ctx(out, in_cond_ctx);
+            }
         }
         Cmd::WithEnv { bindings, body } => {
             // Check for single Exec optimization
             if body.len() == 1 {
-                if let Cmd::Exec { args, .. } = &body[0] {
-                    out.push_str(&pad);
-                    for (k, v) in bindings {
-                        out.push_str(&format!("{}={} ", k, emit_val(v, target)?));
-                    }
-                    let shell_args: Vec<String> =
-                        args.iter().map(|a| emit_word(a, target)).collect::<Result<Vec<_>, _>>()?;
-                    out.push_str(&shell_args.join(" "));
+                if let Cmd::Exec { args, stdin, env, cwd, .. } = &body[0] {
+                    out.pad();
+                    let all_env: Vec<(String, Val)> = bindings.iter().chain(env).cloned().collect();
+                    out.push_str(&emit_exec_command(args, &all_env, cwd.as_ref(), stdin.as_ref(), target)?);
                     out.push('\n');
                     return Ok(());
                 }
             }
 
             // General case: Subshell
-            out.push_str(&format!("{pad}(\n"));
+            emitln!(out, "(");
             for (k, v) in bindings {
-                out.push_str(&format!("{}  export {}={}\n", pad, k, emit_val(v, target)?));
+                emitln!(out, "  export {}={}", k, emit_val(v, target)?);
             }
-            for cmd in body {
-                emit_cmd(cmd, out, indent + 2, opts, in_cond_ctx, ctx)?;
-            }
-            out.push_str(&format!("{pad})\n"));
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
+            emitln!(out, ")");
         }
         Cmd::WithLog { path, append, body } => {
             if target == TargetShell::Posix {
@@ -1041,12 +1183,12 @@ pub(super) fn emit_cmd(
             // Bash implementation using process substitution
             let path_val = emit_val(path, target)?;
 
-            out.push_str(&format!("{pad}(\n"));
-            out.push_str(&format!("{pad}  __sh2_log_path={}\n", path_val));
+            emitln!(out, "(");
+            emitln!(out, "  __sh2_log_path={}", path_val);
 
             if !append {
                 // Truncate file once
-                out.push_str(&format!("{pad}  : > \"$__sh2_log_path\"\n"));
+                emitln!(out, "  : > \"$__sh2_log_path\"");
             }
 
             // Always use append for tee to avoid race conditions between stdout/stderr tees
@@ -1054,20 +1196,16 @@ pub(super) fn emit_cmd(
             // Users want interleaved output.
 
             // Ensure we wait for tee to finish even if the block exits early
-            out.push_str(&format!("{pad}  trap 'exec >&-; exec 2>&-; wait' EXIT\n"));
+            emitln!(out, "  trap 'exec >&-; exec 2>&-; wait' EXIT");
 
-            out.push_str(&format!("{pad}  exec > >(tee -a \"$__sh2_log_path\")\n"));
-            out.push_str(&format!(
-                "{pad}  exec 2> >(tee -a \"$__sh2_log_path\" >&2)\n"
-            ));
+            emitln!(out, "  exec > >(tee -a \"$__sh2_log_path\")");
+            emitln!(out, "  exec 2> >(tee -a \"$__sh2_log_path\" >&2)");
 
-            for cmd in body {
-                emit_cmd(cmd, out, indent + 2, opts, in_cond_ctx, ctx)?;
-            }
-            out.push_str(&format!("{pad})\n"));
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
+            emitln!(out, ")");
         }
         Cmd::WithCwd { path, body } => {
-            out.push_str(&format!("{pad}(\n"));
+            emitln!(out, "(");
             let path_str = emit_val(path, target)?;
             
             // Ticket 9/11: Runtime hint for tilde literal path.
@@ -1080,18 +1218,114 @@ pub(super) fn emit_cmd(
 
             if is_tilde_literal {
                 // Wrap cd in failure check with hint (use exit as this is a subshell)
-                out.push_str(&format!("{pad}  cd {} || {{ __sh2_err=$?; printf '%s\\n' \"hint: '~' is not expanded; use env.HOME & \\\"/path\\\" or an absolute path.\" >&2; exit $__sh2_err; }}\n", path_str));
+                emitln!(out, "  cd {} || {{ __sh2_err=$?; printf '%s\\n' \"hint: '~' is not expanded; use env.HOME & \\\"/path\\\" or an absolute path.\" >&2; exit $__sh2_err; }}", path_str);
             } else {
-                out.push_str(&format!("{pad}  cd {}\n", path_str));
+                emitln!(out, "  cd {}", path_str);
             }
 
-            for cmd in body {
-                emit_cmd(cmd, out, indent + 2, opts, in_cond_ctx, ctx)?;
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
+            emitln!(out, ")");
+        }
+        Cmd::WithDeadline { duration, body, loc } => {
+            // The body runs as a background job with its own process group (Bash) so the
+            // watchdog can kill everything it started; POSIX sh has no job control in scripts,
+            // so there the watchdog walks the process tree instead.
+            if let Some(l) = loc {
+                emitln!(out, "__sh2_loc=\"{}\"", l);
+            }
+            let duration = emit_val(duration, target)?;
+            emitln!(out, "__sh2_dl_secs=$(__sh2_duration_secs {})", duration);
+            emit_status_check_ctx(out, in_cond_ctx);
+            if target == TargetShell::Bash {
+                emitln!(out, "case $- in *m*) __sh2_dl_m=1 ;; *) __sh2_dl_m=0; set -m ;; esac");
+            }
+            emitln!(out, "(");
+            emit_block(body, out, opts, false, ctx)?;
+            emitln!(out, ") </dev/null &");
+            emitln!(out, "__sh2_dl_pid=$!");
+            if target == TargetShell::Bash {
+                emitln!(out, "if [ \"$__sh2_dl_m\" = 0 ]; then set +m; fi");
+            }
+            emitln!(out, "__sh2_deadline_watch \"$__sh2_dl_pid\" \"$__sh2_dl_secs\"");
+            emitln!(out, "if wait \"$__sh2_dl_pid\"; then __sh2_status=0; else __sh2_status=$?; fi");
+            emitln!(out, "__sh2_deadline_end \"$__sh2_dl_pid\" \"$__sh2_dl_watch\" {}", duration);
+            if in_cond_ctx {
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"");
+            } else {
+                emit_status_check_only(out);
+            }
+        }
+        Cmd::WithLimits { limit, body } => {
+            // renice/ionice act on the subshell itself, so the body and everything it
+            // starts inherit the setting. A missing tool only warns; a refused change fails.
+            emitln!(out, "(");
+            // POSIX sh has no $BASHPID; `read` is a builtin, so /proc/self is the subshell.
+            let pid = match target {
+                TargetShell::Bash => "$BASHPID",
+                TargetShell::Posix => "$__sh2_limits_pid",
+            };
+            let mut probe = |tool: &str, args: String, what: String| {
+                emitln!(out, "  if command -v {} >/dev/null 2>&1; then", tool);
+                if target == TargetShell::Posix {
+                    emitln!(out, "    read -r __sh2_limits_pid _ </proc/self/stat || exit $?");
+                }
+                emitln!(out, "    {} {} -p \"{}\" >/dev/null || exit $?", tool, args, pid);
+                emitln!(out, "  else");
+                emitln!(out, "    printf '%s\\n' 'warning: {} not found; running without {}' >&2", tool, what);
+                emitln!(out, "  fi");
+            };
+            match limit {
+                Limit::Nice(n) => probe("renice", format!("-n {}", n), format!("nice({})", n)),
+                Limit::Ionice { class, level } => {
+                    let level = level.map(|l| format!(" -n {}", l)).unwrap_or_default();
+                    probe("ionice", format!("-c {}{}", class, level), "ionice(...)".to_string())
+                }
+                Limit::Ulimit(limits) => {
+                    for (flag, value) in limits {
+                        emitln!(out, "  ulimit -{} {} || exit $?", flag, value);
+                    }
+                }
+            }
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
+            emitln!(out, ")");
+            // The body reported its own failure; only pass the status on.
+            emit_status_capture(out);
+            if in_cond_ctx {
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"\" \"return\"");
+            } else {
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"\"");
+            }
+        }
+        Cmd::WithUmask { mask, saved, body } => {
+            // Runs in the current shell so the body's assignments stay visible. Inside a
+            // condition (e.g. a `try` body) a failure stops the body like it does in `try`,
+            // and its status is kept across the restore.
+            emitln!(out, "{}=$(umask)", saved);
+            emitln!(out, "umask {}", mask);
+            if in_cond_ctx {
+                emitln!(out, "if {{");
+                if body.is_empty() {
+                    emitln!(out, "  :");
+                }
+                for (i, cmd) in body.iter().enumerate() {
+                    emitln!(out, "{}", if i > 0 { "  } && {" } else { "  {" });
+                    out.indent();
+                    emit_block(std::slice::from_ref(cmd), out, opts, true, ctx)?;
+                    out.dedent();
+                }
+                if !body.is_empty() {
+                    emitln!(out, "  }}");
+                }
+                emitln!(out, "}}; then __sh2_status=0; else __sh2_status=$?; fi");
+                emitln!(out, "umask \"${}\"", saved);
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"");
+            } else {
+                emit_block(body, out, opts, false, ctx)?;
+                emitln!(out, "umask \"${}\"", saved);
             }
-            out.push_str(&format!("{pad})\n"));
         }
         Cmd::Cd(path) => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str("cd ");
             out.push_str(&emit_val(path, target)?);
             out.push('\n');
@@ -1099,18 +1333,18 @@ pub(super) fn emit_cmd(
         Cmd::RawLine { line, loc } => {
             if let Some(l) = loc {
                 if !in_cond_ctx {
-                     out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                     emitln!(out, "__sh2_loc=\"{}\"", l);
                 }
             }
-            out.push_str(&pad);
+            out.pad();
             out.push_str(line);
             out.push('\n');
-            out.push_str(&format!("{}__sh2_status=$?\n", pad));
+            emitln!(out, "__sh2_status=$?");
 
             if in_cond_ctx {
-                out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"\n", pad));
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"");
             } else {
-                out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"exit\"\n", pad));
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"exit\"");
             }
         }
         Cmd::Raw { cmd: val, args, loc } => {
@@ -1118,7 +1352,7 @@ pub(super) fn emit_cmd(
              // This is a probe, so it sets __sh2_status but does not fail-fast.
              
              if let Some(l) = loc {
-                 out.push_str(&format!("{}__sh2_loc=\"{}\"\n", pad, l));
+                 emitln!(out, "__sh2_loc=\"{}\"", l);
              }
              
              match args {
@@ -1127,23 +1361,24 @@ pub(super) fn emit_cmd(
                      match val {
                          Val::Literal(s) => {
                              let cmd_escaped = sh_single_quote(s);
-                             out.push_str(&format!("{}__sh2_sh_probe {}\n", pad, cmd_escaped));
+                             emitln!(out, "__sh2_sh_probe {}", cmd_escaped);
                          }
                          Val::Command(words) => {
-                             out.push_str(&format!("{}__sh2_cmd=\"\"\n", pad));
+                             emitln!(out, "__sh2_cmd=\"\"");
                              for (i, w) in words.iter().enumerate() {
-                                 if i > 0 { out.push_str(&format!("{}__sh2_cmd+=' '\n", pad)); }
+                                 if i > 0 { emitln!(out, "__sh2_cmd+=' '"); }
                                  let ws = emit_word(w, target)?;
                                  let wq = sh_single_quote(&ws);
-                                 out.push_str(&format!("{}__sh2_cmd+={}\n", pad, wq));
+                                 emitln!(out, "__sh2_cmd+={}", wq);
                              }
-                             out.push_str(&format!("{}__sh2_sh_probe \"$__sh2_cmd\"\n", pad));
+                             emitln!(out, "__sh2_sh_probe \"$__sh2_cmd\"");
                          }
                          _ => {
-                             out.push_str(&format!("{}__sh2_cmd=", pad));
+                             out.pad();
+                             out.push_str("__sh2_cmd=");
                              out.push_str(&emit_val(val, target)?);
                              out.push('\n');
-                             out.push_str(&format!("{}__sh2_sh_probe \"$__sh2_cmd\"\n", pad));
+                             emitln!(out, "__sh2_sh_probe \"$__sh2_cmd\"");
                          }
                      }
                  }
@@ -1162,31 +1397,32 @@ pub(super) fn emit_cmd(
                      
                      match val {
                          Val::Command(words) => {
-                             out.push_str(&format!("{}__sh2_cmd=\"\"\n", pad));
+                             emitln!(out, "__sh2_cmd=\"\"");
                              for (i, w) in words.iter().enumerate() {
-                                 if i > 0 { out.push_str(&format!("{}__sh2_cmd+=' '\n", pad)); }
+                                 if i > 0 { emitln!(out, "__sh2_cmd+=' '"); }
                                  let ws = emit_word(w, target)?;
                                  let wq = sh_single_quote(&ws);
-                                 out.push_str(&format!("{}__sh2_cmd+={}\n", pad, wq));
+                                 emitln!(out, "__sh2_cmd+={}", wq);
                              }
-                             out.push_str(&format!("{}__sh2_sh_probe_args \"$__sh2_cmd\" {}\n", pad, args_str));
+                             emitln!(out, "__sh2_sh_probe_args \"$__sh2_cmd\" {}", args_str);
                          }
                          Val::Literal(s) => {
                               let cmd_escaped = sh_single_quote(s);
-                              out.push_str(&format!("{}__sh2_sh_probe_args {} {}\n", pad, cmd_escaped, args_str));
+                              emitln!(out, "__sh2_sh_probe_args {} {}", cmd_escaped, args_str);
                          }
                          _ => {
-                              out.push_str(&format!("{}__sh2_cmd=", pad));
+                              out.pad();
+                              out.push_str("__sh2_cmd=");
                               out.push_str(&emit_val(val, target)?);
                               out.push('\n');
-                              out.push_str(&format!("{}__sh2_sh_probe_args \"$__sh2_cmd\" {}\n", pad, args_str));
+                              emitln!(out, "__sh2_sh_probe_args \"$__sh2_cmd\" {}", args_str);
                          }
                      }
                  }
              }
         }
         Cmd::Call { name, args } => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str(name);
             for arg in args {
                 out.push(' ');
@@ -1195,24 +1431,56 @@ pub(super) fn emit_cmd(
             out.push_str("; __sh2_status=$?\n");
 
             if in_cond_ctx {
-                out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"\n", pad));
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"");
             } else {
-                out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"\n", pad));
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"");
             }
         }
         Cmd::Subshell { body } => {
-            out.push_str(&format!("{pad}(\n"));
-            for cmd in body {
-                emit_cmd(cmd, out, indent + 2, opts, in_cond_ctx, ctx)?;
-            }
-            out.push_str(&format!("{pad})\n"));
+            emitln!(out, "(");
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
+            emitln!(out, ")");
         }
         Cmd::Group { body } => {
-            out.push_str(&format!("{pad}{{\n"));
+            emitln!(out, "{{");
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
+            emitln!(out, "}}");
+        }
+        Cmd::Debug { loc, source, vars, breakpoint } => {
+            out.pad();
+            write!(out, "__sh2_debug {} {} {}", sh_single_quote(loc), u8::from(*breakpoint), sh_single_quote(source));
+            for v in vars {
+                write!(out, " {}", v);
+            }
+            out.push('\n');
+        }
+        Cmd::Profile { loc, source, start_var, body } => {
+            emitln!(out, "__sh2_now; {}=$__sh2_nowv", start_var);
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
+            emitln!(out, "__sh2_prof_add \"${}\" {} {}", start_var, sh_single_quote(loc), sh_single_quote(source));
+        }
+        Cmd::DumpVars { loc, vars } => {
+            // Names are padded to one column here, so the helper needs no width logic.
+            let width = vars.iter().map(|v| v.len()).max().unwrap_or(0);
+            out.pad();
+            write!(out, "__sh2_dump_vars {}", sh_single_quote(loc));
+            for v in vars {
+                write!(out, " {}", sh_single_quote(&format!("{:<width$}", v)));
+            }
+            out.push('\n');
+        }
+        Cmd::Unchecked { body } => {
+            // Like a try body, each statement runs in condition context, so a
+            // failure returns from `__sh2_check` instead of exiting; `|| :` then
+            // moves on (and keeps the ERR trap and errexit quiet).
+            if body.is_empty() {
+                emitln!(out, ":");
+            }
             for cmd in body {
-                emit_cmd(cmd, out, indent + 2, opts, in_cond_ctx, ctx)?;
+                emitln!(out, "{{");
+                emit_block(std::slice::from_ref(cmd), out, opts, true, ctx)?;
+                emitln!(out, "}} || :");
             }
-            out.push_str(&format!("{pad}}}\n"));
         }
         Cmd::WithRedirect {
             stdout,
@@ -1310,7 +1578,7 @@ pub(super) fn emit_cmd(
                 // Generate unique ID for this redirect block
                 let uid = ctx.next_id();
 
-                out.push_str(&format!("{pad}{{\n")); // Wrapper block start
+                emitln!(out, "{{"); // Wrapper block start
 
                 // 1. Setup FIFO + Tee Processes
                 let mut stdout_fifo_opt = None;
@@ -1322,12 +1590,12 @@ pub(super) fn emit_cmd(
                 if tee_stdout {
                     let fifo_var = format!("__sh2_fifo_out_{}", uid);
                     stdout_fifo_opt = Some(fifo_var.clone());
-                    out.push_str(&format!("{pad}  {}=\"/tmp/.${{USER:-user}}.sh2.fifo.out.{}.$$\"\n", fifo_var, uid));
+                    emitln!(out, "  {}=\"/tmp/.${{USER:-user}}.sh2.fifo.out.{}.$$\"", fifo_var, uid);
                 }
                 if tee_stderr {
                     let fifo_var = format!("__sh2_fifo_err_{}", uid);
                     stderr_fifo_opt = Some(fifo_var.clone());
-                    out.push_str(&format!("{pad}  {}=\"/tmp/.${{USER:-user}}.sh2.fifo.err.{}.$$\"\n", fifo_var, uid));
+                    emitln!(out, "  {}=\"/tmp/.${{USER:-user}}.sh2.fifo.err.{}.$$\"", fifo_var, uid);
                 }
                 
                 // Install single cleanup trap for both FIFOs (if any)
@@ -1339,7 +1607,7 @@ pub(super) fn emit_cmd(
                     if let Some(ref fifo) = stderr_fifo_opt {
                         trap_cmd.push_str(&format!(" \"${}\"", fifo));
                     }
-                    out.push_str(&format!("{pad}  trap '{}' RETURN\n", trap_cmd));
+                    emitln!(out, "  trap '{}' RETURN", trap_cmd);
                 }
                 
                 // Create FIFOs and start tee processes
@@ -1349,17 +1617,18 @@ pub(super) fn emit_cmd(
                     stdout_pid_opt = Some(pid_var.clone());
 
                     // Create FIFO
-                    out.push_str(&format!("{pad}  mkfifo \"${}\" || {{ __sh2_status=1; return 1; }}\n", fifo_var));
+                    emitln!(out, "  mkfifo \"${}\" || {{ __sh2_status=1; return 1; }}", fifo_var);
                     
                     // Start tee in background reading from FIFO
                     // Determine append mode once (all files must share same mode)
                     let append_mode = stdout_files.first().map(|(_, a)| *a).unwrap_or(false);
-                    out.push_str(&format!("{pad}  ( tee"));
+                    out.pad();
+                    out.push_str("  ( tee");
                     if append_mode {
                         out.push_str(" -a");
                     }
                     for (path, _append) in &stdout_files {
-                        out.push_str(&format!(" {}", emit_val(path, target)?));
+                        write!(out, " {}", emit_val(path, target)?);
                     }
                     if stdout_inherit {
                          // Inherit means keep printing to current stdout (default)
@@ -1367,8 +1636,8 @@ pub(super) fn emit_cmd(
                          // No inherit: suppress tee's stdout
                          out.push_str(" >/dev/null");
                     }
-                    out.push_str(&format!(" < \"${}\" ) &\n", fifo_var));
-                    out.push_str(&format!("{pad}  {}=$!\n", pid_var));
+                    writeln!(out, " < \"${}\" ) &", fifo_var);
+                    emitln!(out, "  {}=$!", pid_var);
                 }
 
                 if tee_stderr {
@@ -1377,43 +1646,45 @@ pub(super) fn emit_cmd(
                     stderr_pid_opt = Some(pid_var.clone());
 
                     // Create FIFO
-                    out.push_str(&format!("{pad}  mkfifo \"${}\" || {{ __sh2_status=1; return 1; }}\n", fifo_var));
+                    emitln!(out, "  mkfifo \"${}\" || {{ __sh2_status=1; return 1; }}", fifo_var);
                      
                     // Start tee in background reading from FIFO
                     // Determine append mode once (all files must share same mode)
                     let append_mode = stderr_files.first().map(|(_, a)| *a).unwrap_or(false);
-                    out.push_str(&format!("{pad}  ( tee"));
+                    out.pad();
+                    out.push_str("  ( tee");
                     if append_mode {
                         out.push_str(" -a");
                     }
                     for (path, _append) in &stderr_files {
-                         out.push_str(&format!(" {}", emit_val(path, target)?));
+                         write!(out, " {}", emit_val(path, target)?);
                     }
                     if stderr_inherit {
                          out.push_str(" >&2"); // Write to current stderr
                     } else {
                          out.push_str(" >/dev/null");
                     }
-                    out.push_str(&format!(" < \"${}\" ) &\n", fifo_var));
-                    out.push_str(&format!("{pad}  {}=$!\n", pid_var));
+                    writeln!(out, " < \"${}\" ) &", fifo_var);
+                    emitln!(out, "  {}=$!", pid_var);
                 }
 
                 // 2. Emit Inner Body
-                out.push_str(&format!("{pad}  {{\n"));
-                for cmd in body {
-                    emit_cmd(cmd, out, indent + 4, opts, in_cond_ctx, ctx)?;
-                }
-            out.push_str(&format!("{pad}  }}")); // Close inner body
+                emitln!(out, "  {{");
+                out.indent();
+                emit_block(body, out, opts, in_cond_ctx, ctx)?;
+                out.dedent();
+            out.pad();
+            out.push_str("  }"); // Close inner body
 
                 // 3. Apply Redirects to Inner Body
                 
                 // Stdout application
                 if let Some(ref fifo) = stdout_fifo_opt {
-                    out.push_str(&format!(" >\"${}\"", fifo));
+                    write!(out, " >\"${}\"", fifo);
                 } else if let Some(first) = stdout_files.first() {
                     let (path, append) = first;
                     let op = if *append { ">>" } else { ">" };
-                    out.push_str(&format!(" {} {}", op, emit_val(path, target)?));
+                    write!(out, " {} {}", op, emit_val(path, target)?);
                 } else if let Some(cross) = stdout_cross {
                      match cross {
                          RedirectOutputTarget::ToStderr => out.push_str(" 1>&2"),
@@ -1423,11 +1694,11 @@ pub(super) fn emit_cmd(
 
                 // Stderr application
                 if let Some(ref fifo) = stderr_fifo_opt {
-                    out.push_str(&format!(" 2>\"${}\"", fifo));
+                    write!(out, " 2>\"${}\"", fifo);
                 } else {
                     if let Some((path, append)) = stderr_files.first() {
                         let op = if *append { ">>" } else { ">" };
-                        out.push_str(&format!(" 2{} {}", op, emit_val(path, target)?));
+                        write!(out, " 2{} {}", op, emit_val(path, target)?);
                     } else if let Some(types) = stderr.as_ref().and_then(|v| v.first()) {
                          if matches!(types, RedirectOutputTarget::ToStdout) {
                              out.push_str(" 2>&1");
@@ -1440,7 +1711,7 @@ pub(super) fn emit_cmd(
                 if let Some(target_redir) = stdin {
                     match target_redir {
                         RedirectInputTarget::File { path } => {
-                            out.push_str(&format!(" < {}", emit_val(path, target)?));
+                            write!(out, " < {}", emit_val(path, target)?);
                         }
                         RedirectInputTarget::HereDoc { content } => {
                            // Use unique delimiter with collision avoidance
@@ -1450,9 +1721,12 @@ pub(super) fn emit_cmd(
                                 delim = format!("__SH2_EOF_{}_{}__", uid, counter);
                                 counter += 1;
                             }
-                            out.push_str(&format!(" <<'{}'", delim));
+                            write!(out, " <<'{}'", delim);
                             heredoc_to_emit = Some((content, delim));
                         }
+                        RedirectInputTarget::Text { value } => {
+                            write!(out, " <<< {}", emit_val(value, target)?);
+                        }
                     }
                 }
                 
@@ -1463,56 +1737,61 @@ pub(super) fn emit_cmd(
                 if let Some((content, delim)) = heredoc_to_emit {
                     out.push_str(content);
                     if !content.ends_with('\n') { out.push('\n'); }
-                    out.push_str(&format!("{}\n", delim));
+                    writeln!(out, "{}", delim);
                 }
                 
                 // 4. Capture Status & Cleanup (Wait for tee completion)
                 let cmd_status_var = format!("__sh2_cs_{}", uid);
-                out.push_str(&format!("{pad}  {}=$?\n", cmd_status_var));
+                emitln!(out, "  {}=$?", cmd_status_var);
                 
                 // Wait for tee processes to complete and capture their statuses
                 let mut has_stdout_tee = false;
                 let mut has_stderr_tee = false;
                 
                 if let Some(_fifo) = &stdout_fifo_opt {
-                     out.push_str(&format!("{pad}  wait \"${}\"\n", stdout_pid_opt.as_ref().unwrap())); 
+                     emitln!(out, "  wait \"${}\"", stdout_pid_opt.as_ref().unwrap()); 
                      let tee_status_var = format!("__sh2_ts_out_{}", uid);
-                     out.push_str(&format!("{pad}  {}=$?\n", tee_status_var));
+                     emitln!(out, "  {}=$?", tee_status_var);
                      has_stdout_tee = true;
                 }
                 if let Some(_fifo) = &stderr_fifo_opt {
-                     out.push_str(&format!("{pad}  wait \"${}\"\n", stderr_pid_opt.as_ref().unwrap()));
+                     emitln!(out, "  wait \"${}\"", stderr_pid_opt.as_ref().unwrap());
                      let tee_status_var = format!("__sh2_ts_err_{}", uid);
-                     out.push_str(&format!("{pad}  {}=$?\n", tee_status_var));
+                     emitln!(out, "  {}=$?", tee_status_var);
                      has_stderr_tee = true;
                 }
 
                 // Compute final status with deterministic precedence: cmd < stdout_tee < stderr_tee
                 let final_status_var = format!("__sh2_final_{}", uid);
-                out.push_str(&format!("{pad}  {}=${{{}:-0}}\n", final_status_var, cmd_status_var));
+                emitln!(out, "  {}=${{{}:-0}}", final_status_var, cmd_status_var);
                 if has_stdout_tee {
                     let tee_status_var = format!("__sh2_ts_out_{}", uid);
-                    out.push_str(&format!("{pad}  if [ \"${{{}:-0}}\" -ne 0 ]; then {}=${}; fi\n", tee_status_var, final_status_var, tee_status_var));
+                    emitln!(out, "  if [ \"${{{}:-0}}\" -ne 0 ]; then {}=${}; fi", tee_status_var, final_status_var, tee_status_var);
                 }
                 if has_stderr_tee {
                     let tee_status_var = format!("__sh2_ts_err_{}", uid);
-                    out.push_str(&format!("{pad}  if [ \"${{{}:-0}}\" -ne 0 ]; then {}=${}; fi\n", tee_status_var, final_status_var, tee_status_var));
+                    emitln!(out, "  if [ \"${{{}:-0}}\" -ne 0 ]; then {}=${}; fi", tee_status_var, final_status_var, tee_status_var);
                 }
 
                 // Propagate to global status variable
-                out.push_str(&format!("{pad}  __sh2_status=${}\n", final_status_var));
-                out.push_str(&format!("{pad}}}"));// Close wrapper block
-                out.push('\n');
+                emitln!(out, "  __sh2_status=${}", final_status_var);
+                out.line("}"); // Close wrapper block
 
             } else {
                  // Simple / Single Target Case (POSIX compatible usually, or Bash single)
                  // Existing logic preserved for compatibility and simplicity where tee is not needed.
 
-                out.push_str(&format!("{pad}{{\n"));
-                for cmd in body {
-                    emit_cmd(cmd, out, indent + 2, opts, in_cond_ctx, ctx)?;
+                // POSIX has no here-string: feed text() through a printf pipe instead
+                // (the body then runs in a subshell).
+                let posix_text_pipe = target == TargetShell::Posix && matches!(stdin, Some(RedirectInputTarget::Text { .. }));
+                if let (Some(RedirectInputTarget::Text { value }), true) = (stdin.as_ref(), posix_text_pipe) {
+                    emitln!(out, "printf '%s\\n' {} | {{", emit_val(value, target)?);
+                } else {
+                    emitln!(out, "{{");
                 }
-                out.push_str(&format!("{pad}}}")); 
+                emit_block(body, out, opts, in_cond_ctx, ctx)?;
+                out.pad();
+                out.push('}');
 
                 // Handle stdin redirection
                 let mut heredoc_content = None;
@@ -1520,7 +1799,7 @@ pub(super) fn emit_cmd(
                 if let Some(target_redir) = stdin {
                     match target_redir {
                         RedirectInputTarget::File { path } => {
-                            out.push_str(&format!(" < {}", emit_val(path, target)?));
+                            write!(out, " < {}", emit_val(path, target)?);
                         }
                         RedirectInputTarget::HereDoc { content } => {
                             heredoc_content = Some(content);
@@ -1532,7 +1811,12 @@ pub(super) fn emit_cmd(
                                 delim = format!("__SH2_EOF__{}__", counter);
                                 counter += 1;
                             }
-                            out.push_str(&format!(" <<'{}'", delim));
+                            write!(out, " <<'{}'", delim);
+                        }
+                        RedirectInputTarget::Text { value } => {
+                            if target == TargetShell::Bash {
+                                write!(out, " <<< {}", emit_val(value, target)?);
+                            }
                         }
                     }
                 }
@@ -1558,11 +1842,11 @@ pub(super) fn emit_cmd(
                    }
                 }
                 
-                let emit_stdout = |out: &mut String, target_redir: &RedirectOutputTarget| -> Result<(), CompileError> {
+                let emit_stdout = |out: &mut ScriptBuilder, target_redir: &RedirectOutputTarget| -> Result<(), CompileError> {
                         match target_redir {
                             RedirectOutputTarget::File { path, append } => {
                                 let op = if *append { ">>" } else { ">" };
-                                out.push_str(&format!(" {} {}", op, emit_val(path, target)?));
+                                write!(out, " {} {}", op, emit_val(path, target)?);
                             }
                             RedirectOutputTarget::ToStderr => out.push_str(" 1>&2"),
                             RedirectOutputTarget::ToStdout => {}, 
@@ -1570,11 +1854,11 @@ pub(super) fn emit_cmd(
                         }
                         Ok(())
                 };
-                let emit_stderr = |out: &mut String, target_redir: &RedirectOutputTarget| -> Result<(), CompileError> {
+                let emit_stderr = |out: &mut ScriptBuilder, target_redir: &RedirectOutputTarget| -> Result<(), CompileError> {
                         match target_redir {
                             RedirectOutputTarget::File { path, append } => {
                                 let op = if *append { ">>" } else { ">" };
-                                out.push_str(&format!(" 2{} {}", op, emit_val(path, target)?));
+                                write!(out, " 2{} {}", op, emit_val(path, target)?);
                             }
                             RedirectOutputTarget::ToStdout => out.push_str(" 2>&1"),
                             RedirectOutputTarget::ToStderr => {},
@@ -1591,6 +1875,13 @@ pub(super) fn emit_cmd(
                     if let Some(t) = stdout_single { emit_stdout(out, t)?; }
                 }
 
+                // The piped POSIX body is a subshell: a fatal failure inside it only exits
+                // that subshell (after reporting), so re-raise it here without a second message.
+                if posix_text_pipe {
+                    let mode = if in_cond_ctx { "return" } else { "exit" };
+                    write!(out, " || __sh2_check \"$?\" \"\" \"{}\"", mode);
+                }
+
                 // Heredoc body
                 if let Some(content) = heredoc_content {
                     let mut delim = "__SH2_EOF__".to_string();
@@ -1614,7 +1905,7 @@ pub(super) fn emit_cmd(
             // Wrap the entire spawned command in a subshell so & applies to the whole unit.
             // This ensures $! refers to the subshell running the actual work.
             // For Cmd::Exec, emit just the raw command without status tracking.
-            out.push_str(&pad);
+            out.pad();
             out.push_str("( ");
 
             match cmd.as_ref() {
@@ -1622,34 +1913,33 @@ pub(super) fn emit_cmd(
                     args,
                     allow_fail: _,
                     loc,
+                    stdin,
+                    env,
+                    cwd,
                 } => {
                     // Simple command: emit inline
                     if let Some(l) = loc {
-                        out.push_str(&format!("__sh2_loc=\"{}\"; ", l));
+                        write!(out, "__sh2_loc=\"{}\"; ", l);
                     }
-                    let shell_cmd = args
-                        .iter()
-                        .map(|a| emit_word(a, target))
-                        .collect::<Result<Vec<_>, _>>()?
-                        .join(" ");
-                    out.push_str(&shell_cmd);
+                    out.push_str(&emit_exec_command(args, env, cwd.as_ref(), stdin.as_ref(), target)?);
                     out.push_str(" ) &\n");
                 }
                 _ => {
                     // Complex command (block, group, etc): emit with increased indent
                     out.push('\n');
-                    emit_cmd(cmd, out, indent + 2, opts, false, ctx)?;
-                    out.push_str(&pad);
-                    out.push_str(") &\n");
+                    emit_block(std::slice::from_ref(cmd.as_ref()), out, opts, false, ctx)?;
+                    out.line(") &");
                 }
             }
+            emitln!(out, "__sh2_spawned=\"${{__sh2_spawned:-}} $!\"");
         }
         Cmd::Wait(opt) => {
             // Wait must update __sh2_status to the exit status of the waited process
             match opt {
                 Some(val) => match val {
                     crate::ir::Val::List(elems) => {
-                        out.push_str(&format!("{pad}wait"));
+                        out.pad();
+                        out.push_str("wait");
                         for elem in elems {
                             out.push(' ');
                             out.push_str(&emit_word(elem, target)?);
@@ -1657,24 +1947,21 @@ pub(super) fn emit_cmd(
                         out.push_str("; __sh2_status=$?\n");
 
                         if in_cond_ctx {
-                            out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"\n", pad));
+                            emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"");
                         } else {
-                            out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"\n", pad));
+                            emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"");
                         }
                     }
                     _ => {
-                        out.push_str(&format!(
-                            "{pad}wait {}; __sh2_status=$?\n",
-                            emit_word(val, target)?
-                        ));
+                        emitln!(out, "wait {}; __sh2_status=$?", emit_word(val, target)?);
                         if in_cond_ctx {
-                            out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"\n", pad));
+                            emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"");
                         } else {
-                            out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"\n", pad));
+                            emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"");
                         }
                     }
                 },
-                None => out.push_str(&format!("{pad}wait; __sh2_status=$?\n")),
+                None => emitln!(out, "wait; __sh2_status=$?"),
             }
         }
         Cmd::TryCatch {
@@ -1685,90 +1972,72 @@ pub(super) fn emit_cmd(
             // Bash: also save ERR trap.
             match target {
                 TargetShell::Bash => {
-                    out.push_str(&format!(
-                        "{pad}local __sh2_e=0; case $- in *e*) __sh2_e=1;; *) __sh2_e=0;; esac; set +e\n"
-                    ));
-                    out.push_str(&format!(
-                        "{pad}local __sh2_err=$(trap -p ERR || true); trap - ERR\n"
-                    ));
+                    emitln!(out, "local __sh2_e=0; case $- in *e*) __sh2_e=1;; *) __sh2_e=0;; esac; set +e");
+                    emitln!(out, "local __sh2_err=$(trap -p ERR || true); trap - ERR");
                 }
                 TargetShell::Posix => {
-                    out.push_str(&format!(
-                        "{pad}case $- in *e*) __sh2_e=1;; *) __sh2_e=0;; esac; set +e\n"
-                    ));
+                    emitln!(out, "case $- in *e*) __sh2_e=1;; *) __sh2_e=0;; esac; set +e");
                 }
             }
 
             // Try body
-            out.push_str(&format!("{pad}if {{\n"));
+            emitln!(out, "if {{");
             if try_body.is_empty() {
-                out.push_str(&format!("{pad}  :\n"));
+                emitln!(out, "  :");
             } else {
                 for (i, cmd) in try_body.iter().enumerate() {
                     if i > 0 {
-                        out.push_str(&format!("{pad}  }} && {{\n"));
+                        emitln!(out, "  }} && {{");
                     } else {
-                        out.push_str(&format!("{pad}  {{\n"));
+                        emitln!(out, "  {{");
                     }
                     // Emit command directly with increased indent and in condition context
-                    emit_cmd(cmd, out, indent + 4, opts, true, ctx)?;
+                    out.indent();
+                    emit_block(std::slice::from_ref(cmd), out, opts, true, ctx)?;
+                    out.dedent();
                 }
-                out.push_str(&format!("{pad}  }}\n"));
+                emitln!(out, "  }}");
             }
-            out.push_str(&format!("{pad}}}; then\n"));
+            emitln!(out, "}}; then");
 
             // Helper to emit restoration logic
-            let emit_restore = |out: &mut String| match target {
+            let emit_restore = |out: &mut ScriptBuilder| match target {
                 TargetShell::Bash => {
-                    out.push_str(&format!(
-                        "{pad}  if [ -n \"$__sh2_err\" ]; then eval \"$__sh2_err\"; fi; if [ \"$__sh2_e\" = 1 ]; then set -e; fi\n"
-                    ));
+                    emitln!(out, "  if [ -n \"$__sh2_err\" ]; then eval \"$__sh2_err\"; fi; if [ \"$__sh2_e\" = 1 ]; then set -e; fi");
                 }
                 TargetShell::Posix => {
                     // Safe reference to __sh2_e using :-0 to prevent nounset errors if somehow undefined
-                    out.push_str(&format!(
-                        "{pad}  if [ \"${{__sh2_e:-0}}\" = 1 ]; then set -e; fi\n"
-                    ));
+                    emitln!(out, "  if [ \"${{__sh2_e:-0}}\" = 1 ]; then set -e; fi");
                 }
             };
 
             emit_restore(out);
-            out.push_str(&format!("{pad}else\n"));
+            emitln!(out, "else");
             emit_restore(out);
 
             // Catch body
             if catch_body.is_empty() {
-                out.push_str(&format!("{pad}  :\n"));
+                emitln!(out, "  :");
             }
-            for cmd in catch_body {
-                emit_cmd(cmd, out, indent + 2, opts, in_cond_ctx, ctx)?;
-            }
-            out.push_str(&format!("{pad}fi\n"));
+            emit_block(catch_body, out, opts, in_cond_ctx, ctx)?;
+            emitln!(out, "fi");
         }
         Cmd::AndThen { left, right } => {
-            out.push_str(&format!("{pad}{{\n"));
-            for cmd in left {
-                emit_cmd(cmd, out, indent + 2, opts, true, ctx)?;
-            }
-            out.push_str(&format!("{pad}}} && {{\n"));
-            for cmd in right {
-                emit_cmd(cmd, out, indent + 2, opts, true, ctx)?;
-            }
-            out.push_str(&format!("{pad}}}\n"));
+            emitln!(out, "{{");
+            emit_block(left, out, opts, true, ctx)?;
+            emitln!(out, "}} && {{");
+            emit_block(right, out, opts, true, ctx)?;
+            emitln!(out, "}}");
         }
         Cmd::OrElse { left, right } => {
-            out.push_str(&format!("{pad}{{\n"));
-            for cmd in left {
-                emit_cmd(cmd, out, indent + 2, opts, true, ctx)?;
-            }
-            out.push_str(&format!("{pad}}} || {{\n"));
-            for cmd in right {
-                emit_cmd(cmd, out, indent + 2, opts, true, ctx)?;
-            }
-            out.push_str(&format!("{pad}}}\n"));
+            emitln!(out, "{{");
+            emit_block(left, out, opts, true, ctx)?;
+            emitln!(out, "}} || {{");
+            emit_block(right, out, opts, true, ctx)?;
+            emitln!(out, "}}");
         }
         Cmd::Export { name, value } => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str("export ");
             out.push_str(name);
             if let Some(v) = value {
@@ -1778,16 +2047,14 @@ pub(super) fn emit_cmd(
             out.push('\n');
         }
         Cmd::Unset(name) => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str("unset ");
             out.push_str(name);
             out.push('\n');
         }
-        Cmd::PipeEachLine { producer, var, body } => {
+        Cmd::PipeEachLine { producer, var, fd, body } => {
             if opts.target != TargetShell::Bash {
-                 return Err(CompileError::new(
-                     "each_line is only supported in Bash"
-                 ));
+                 return Err(CompileError::coded("E0400", "each_line is only supported in Bash"));
             }
             
             let id = ctx.next_id();
@@ -1797,46 +2064,46 @@ pub(super) fn emit_cmd(
             // Initialize loop variable if unset (for 0-iteration case)
             // If already set (from partial branch), preserve value.
             // Use `local` to ensure function scope (implicit let).
-            out.push_str(&format!("{}local {}=\"${{{}:-}}\"\n", pad, var, var));
+            emitln!(out, "local {}=\"${{{}:-}}\"", var, var);
             
             // Create temp file for status (no `local` - works at top-level and in functions)
-            out.push_str(&format!("{}{}=$(__sh2_tmpfile)\n", pad, status_file_var));
+            emitln!(out, "{}=$(__sh2_tmpfile)", status_file_var);
             
-            // Start loop with process substitution, reading into temp var
-            out.push_str(&format!("{}while IFS= read -r {} || [[ -n \"${}\" ]]; do\n", pad, temp_var, temp_var));
+            // Start loop with process substitution, reading into temp var. With an fd
+            // the lines arrive there, so the body's stdin stays the script's.
+            let read_fd = fd.map(|fd| format!("-u {} ", fd)).unwrap_or_default();
+            emitln!(out, "while IFS= read -r {}{} || [[ -n \"${}\" ]]; do", read_fd, temp_var, temp_var);
             // Assign to user variable
-            out.push_str(&format!("{}  {}=\"${}\"\n", pad, var, temp_var));
+            emitln!(out, "  {}=\"${}\"", var, temp_var);
             
-            for stmt in body {
-                emit_cmd(stmt, out, indent + 2, opts, in_cond_ctx, ctx)?;
-            }
+            emit_block(body, out, opts, in_cond_ctx, ctx)?;
             
-            out.push_str(&format!("{}done < <(\n", pad));
+            emitln!(out, "done {}< <(", fd.map(|fd| fd.to_string()).unwrap_or_default());
             
             // Producer runs here - emit_cmd sets __sh2_status for the producer
-            out.push_str(&format!("{}unset __sh2_status\n", pad));
-            emit_cmd(producer, out, indent + 2, opts, false, ctx)?;
+            emitln!(out, "unset __sh2_status");
+            emit_block(std::slice::from_ref(producer.as_ref()), out, opts, false, ctx)?;
             // Capture producer status (if emit_cmd didn't set __sh2_status, use $?)
-            out.push_str(&format!("{}__sh2_status=${{__sh2_status:-$?}}\n", pad));
+            emitln!(out, "__sh2_status=${{__sh2_status:-$?}}");
             
             // Write status to temp file
-            out.push_str(&format!("{}  printf '%s' \"$__sh2_status\" > \"${}\"\n", pad, status_file_var));
-            out.push_str(&format!("{})\n", pad)); // Close process substitution
+            emitln!(out, "  printf '%s' \"$__sh2_status\" > \"${}\"", status_file_var);
+            emitln!(out, ")"); // Close process substitution
             
             // Retrieve and cleanup status
-            out.push_str(&format!("{}__sh2_status=$(<\"${}\")\n", pad, status_file_var));
-            out.push_str(&format!("{}rm -f \"${}\"\n", pad, status_file_var));
+            emitln!(out, "__sh2_status=$(<\"${}\")", status_file_var);
+            emitln!(out, "rm -f \"${}\"", status_file_var);
             // Propagate status to $? so set -e (if active) works
-            out.push_str(&format!("{}(exit $__sh2_status)\n", pad));
+            emitln!(out, "(exit $__sh2_status)");
         }
         Cmd::Source(path) => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str(". ");
             out.push_str(&emit_word(path, target)?);
             out.push('\n');
         }
         Cmd::SaveEnvfile { path, env } => {
-            out.push_str(&pad);
+            out.pad();
             out.push_str("__sh2_save_envfile ");
             out.push_str(&emit_word(path, target)?);
             out.push(' ');
@@ -1844,23 +2111,186 @@ pub(super) fn emit_cmd(
             out.push_str("; __sh2_status=$?\n");
 
             if in_cond_ctx {
-                out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"\n", pad));
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\" \"return\"");
             } else {
-                out.push_str(&format!("{}__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"\n", pad));
+                emitln!(out, "__sh2_check \"$__sh2_status\" \"${{__sh2_loc:-}}\"");
             }
         }
     }
 
     Ok(())}
+
+/// The `for ... in` word list for the PIDs passed to wait_all()/wait_any().
+/// Bash also accepts a list variable; POSIX needs a list literal (enforced in lowering).
+fn emit_pid_list(pids: &Val, builtin: &str, target: TargetShell) -> Result<String, CompileError> {
+    match (pids, target) {
+        (Val::List(elements), _) => emit_words(elements, target),
+        (Val::Var(v), TargetShell::Bash) => Ok(format!("\"${{{}[@]}}\"", v)),
+        (_, TargetShell::Bash) => Err(CompileError::unsupported(
+            format!("{}() argument must be a list literal or list variable", builtin),
+            target,
+        )),
+        (_, TargetShell::Posix) => Err(CompileError::unsupported(
+            format!("{}() requires a list literal on --target posix", builtin),
+            target,
+        )),
+    }
+}
+
+/// Space-separated shell words for `vals`.
+fn emit_words(vals: &[Val], target: TargetShell) -> Result<String, CompileError> {
+    Ok(vals.iter().map(|v| emit_word(v, target)).collect::<Result<Vec<_>, _>>()?.join(" "))
+}
+
+/// Positional arguments for `__sh2_find`: dir, name, type, maxdepth ('' when unset).
+fn emit_find_args(val: &Val, target: TargetShell) -> Result<String, CompileError> {
+    let Val::Find { dir, name, type_filter, maxdepth } = val else {
+        return Err(CompileError::internal("emit_find_args called on a non-find value", target));
+    };
+    let mut parts = vec![emit_val(dir, target)?];
+    for v in [name, type_filter, maxdepth] {
+        parts.push(match v {
+            Some(v) => emit_val(v, target)?,
+            None => "''".to_string(),
+        });
+    }
+    Ok(parts.join(" "))
+}
+
+/// Bash body of a command pipeline (`a | b`). allow_fail stages are wrapped in `if`
+/// so they neither trip pipefail nor the ERR trap; a final allow_fail stage keeps its status.
+fn emit_bash_pipe_body(
+    segments: &[(Vec<Val>, bool, Option<RedirectOutputTarget>)],
+    target: TargetShell,
+) -> Result<String, CompileError> {
+    let last_idx = segments.len() - 1;
+    let mut pipe_str = String::new();
+    for (i, (args, allow_fail, stderr)) in segments.iter().enumerate() {
+        if i > 0 {
+            pipe_str.push_str(" | ");
+        }
+
+        let mut cmd_str = args
+            .iter()
+            .map(|a| emit_word(a, target))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(" ");
+        cmd_str.push_str(&emit_segment_stderr(stderr.as_ref(), target)?);
+
+        if *allow_fail {
+            if i < last_idx {
+                // Non-final segment: suppress failure
+                pipe_str.push_str(&format!("if {}; then :; else true; fi", cmd_str));
+            } else {
+                // Final segment: suppress ERR trap via 'if' context, preserve exit status
+                pipe_str.push_str(&format!("if {}; then :; else exit $?; fi", cmd_str));
+            }
+        } else {
+            pipe_str.push_str(&cmd_str);
+        }
+    }
+    Ok(pipe_str)
+}
+
+/// POSIX stage strings for a command pipeline, one per segment.
+fn emit_posix_pipe_stages(
+    segments: &[(Vec<Val>, bool, Option<RedirectOutputTarget>)],
+    target: TargetShell,
+) -> Result<Vec<String>, CompileError> {
+    segments
+        .iter()
+        .map(|(args, _, stderr)| {
+            let parts = args.iter()
+                .map(|a| emit_word(a, target))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(format!("{}{}", parts.join(" "), emit_segment_stderr(stderr.as_ref(), target)?))
+        })
+        .collect()
+}
+
+/// `{ ...; }` stage strings for a block pipeline, shared by both targets.
+fn emit_pipe_block_stages(
+    segments: &[(Vec<Cmd>, Option<RedirectOutputTarget>)],
+    out: &ScriptBuilder,
+    opts: CodegenOptions,
+    ctx: &mut CodegenContext,
+) -> Result<Vec<String>, CompileError> {
+    segments
+        .iter()
+        .map(|(seg, stderr)| {
+            let mut s = out.fragment();
+            s.push_str("{\n");
+            emit_block(seg, &mut s, opts, false, ctx)?;
+            s.pad();
+            s.push('}');
+            s.push_str(&emit_segment_stderr(stderr.as_ref(), opts.target)?);
+            Ok(s.finish())
+        })
+        .collect()
+}
+
+/// Shell text for a single external command plus its per-run options:
+/// `env` as prefix assignments, `stdin=text(v)` as a here-string on bash or a `printf`
+/// pipe on POSIX (safe because the consumer is one external command), and `cwd` as a
+/// `( cd dir && ... )` subshell so the caller's directory is untouched.
+fn emit_exec_command(
+    args: &[Val],
+    env: &[(String, Val)],
+    cwd: Option<&Val>,
+    stdin: Option<&Val>,
+    target: TargetShell,
+) -> Result<String, CompileError> {
+    let mut shell_cmd = String::new();
+    for (k, v) in env {
+        shell_cmd.push_str(&format!("{}={} ", k, emit_val(v, target)?));
+    }
+    let words = args
+        .iter()
+        .map(|a| emit_word(a, target))
+        .collect::<Result<Vec<_>, _>>()?;
+    shell_cmd.push_str(&words.join(" "));
+    if let Some(v) = stdin {
+        shell_cmd = match target {
+            TargetShell::Bash => format!("{} <<< {}", shell_cmd, emit_val(v, target)?),
+            TargetShell::Posix => format!("printf '%s\\n' {} | {}", emit_val(v, target)?, shell_cmd),
+        };
+    }
+    if let Some(dir) = cwd {
+        shell_cmd = format!("( cd {} && {} )", emit_val(dir, target)?, shell_cmd);
+    }
+    Ok(shell_cmd)
+}
+
+/// The command line `args` runs, as one shell word for `__sh2_last_cmd`.
+fn emit_command_text(args: &[Val], target: TargetShell) -> Result<String, CompileError> {
+    let words = args
+        .iter()
+        .map(|a| emit_word(a, target))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(words.join("' '"))
+}
+
+/// Redirect suffix for a pipeline segment's `stderr=` target (empty when unset).
+fn emit_segment_stderr(stderr: Option<&RedirectOutputTarget>, target: TargetShell) -> Result<String, CompileError> {
+    match stderr {
+        None => Ok(String::new()),
+        Some(RedirectOutputTarget::File { path, append }) => {
+            let op = if *append { "2>>" } else { "2>" };
+            Ok(format!(" {} {}", op, emit_val(path, target)?))
+        }
+        Some(RedirectOutputTarget::ToStdout) => Ok(" 2>&1".to_string()),
+        Some(_) => Err(CompileError::internal("unsupported pipeline segment stderr target", target)),
+    }
+}
+
 fn emit_posix_pipeline(
-    out: &mut String,
-    pad: &str,
+    out: &mut ScriptBuilder,
     target: TargetShell,
     stages: &[String],
     allow_fails: &[bool],
     allow_fail_last: bool,
     _has_loc: bool,
-) {
+) -> Result<(), CompileError> {
     // POSIX sh manual pipeline using FIFOs to simulate pipefail without deadlocks.
     // This implementation:
     // - Is errexit-safe: saves/restores set -e state
@@ -1869,55 +2299,42 @@ fn emit_posix_pipeline(
     //
     // Note: We use $(trap) to capture all current traps as a shell-re-evaluable string.
     // This is POSIX-compliant (unlike trap -p which is not).
+    //
+    // Keepalive descriptors are numbered at compile time (fifo i -> fd 3+i) so the
+    // redirections are plain literals rather than eval'd strings. POSIX only
+    // guarantees single-digit descriptors, which caps a pipeline at 8 stages.
+
+    let num_fifos = stages.len() - 1;
+    if 3 + num_fifos > 10 {
+        return Err(CompileError::unsupported(
+            format!("a pipeline longer than 8 stages (got {})", stages.len()),
+            target,
+        ));
+    }
+    let close_fds: Vec<String> = (0..num_fifos).map(|i| format!("{}>&-", 3 + i)).collect();
+    let close_fds = format!("exec {};", close_fds.join(" "));
 
-    out.push_str(pad);
-    out.push_str("{\n");
-    let indent_pad = format!("{}  ", pad);
+    out.line("{");
+    out.indent();
 
     // Save errexit state and user traps, then disable errexit
-    out.push_str(&format!(
-        "{}case $- in *e*) __sh2_e=1;; *) __sh2_e=0;; esac; set +e;\n",
-        indent_pad
-    ));
-    out.push_str(&format!("{}__sh2_saved_traps=$(trap);\n", indent_pad));
+    emitln!(out, "case $- in *e*) __sh2_e=1;; *) __sh2_e=0;; esac; set +e;");
+    emitln!(out, "__sh2_saved_traps=$(trap);");
 
-    let num_fifos = stages.len() - 1;
-    out.push_str(&format!(
-        "{}__sh2_base=\"${{TMPDIR:-/tmp}}/sh2_fifo_$$\";\n",
-        indent_pad
-    ));
-    out.push_str(&format!("{}rm -f \"${{__sh2_base}}_\"*;\n", indent_pad));
+    emitln!(out, "__sh2_base=\"${{TMPDIR:-/tmp}}/sh2_fifo_$$\";");
+    emitln!(out, "rm -f \"${{__sh2_base}}_\"*;");
 
     for i in 0..num_fifos {
-        out.push_str(&format!(
-            "{}mkfifo \"${{__sh2_base}}_{}\";\n",
-            indent_pad, i
-        ));
+        emitln!(out, "mkfifo \"${{__sh2_base}}_{}\";", i);
     }
 
     // Set cleanup traps
-    out.push_str(&format!(
-        "{}trap 'rm -f \"${{__sh2_base}}_\"*' EXIT;\n",
-        indent_pad
-    ));
-    out.push_str(&format!(
-        "{}trap 'rm -f \"${{__sh2_base}}_\"*; exit 1' INT TERM QUIT;\n",
-        indent_pad
-    ));
+    emitln!(out, "trap 'rm -f \"${{__sh2_base}}_\"*' EXIT;");
+    emitln!(out, "trap 'rm -f \"${{__sh2_base}}_\"*; exit 1' INT TERM QUIT;");
 
     // Open keepalive FDs (fd 3+)
-    out.push_str(&format!("{}__sh2_fd=3;\n", indent_pad));
-    out.push_str(&format!("{}__sh2_fds=\"\";\n", indent_pad));
     for i in 0..num_fifos {
-        out.push_str(&format!(
-            "{}eval \"exec ${{__sh2_fd}}<>\\\"${{__sh2_base}}_{}\\\"\";\n",
-            indent_pad, i
-        ));
-        out.push_str(&format!(
-            "{}__sh2_fds=\"$__sh2_fds $__sh2_fd\";\n",
-            indent_pad
-        ));
-        out.push_str(&format!("{}__sh2_fd=$((__sh2_fd + 1));\n", indent_pad));
+        emitln!(out, "exec {}<>\"${{__sh2_base}}_{}\";", 3 + i, i);
     }
 
     // Launch stages
@@ -1931,66 +2348,50 @@ fn emit_posix_pipeline(
         }
 
         // Child closes keepalive FDs before running command
-        out.push_str(&format!(
-            "{}( for fd in $__sh2_fds; do eval \"exec $fd>&-\"; done; {} ) {} & __sh2_p{}=$!;\n",
-            indent_pad, cmd, redir, i
-        ));
+        emitln!(out, "( {} {} ) {} & __sh2_p{}=$!;", close_fds, cmd, redir, i);
     }
 
     // Close keepalive FDs in parent
-    out.push_str(&format!(
-        "{}for fd in $__sh2_fds; do eval \"exec $fd>&-\"; done;\n",
-        indent_pad
-    ));
+    out.line(&close_fds);
 
     // Wait and collect statuses
     for i in 0..stages.len() {
-        out.push_str(&format!(
-            "{}wait \"$__sh2_p{}\"; __sh2_s{}=$?;\n",
-            indent_pad, i, i
-        ));
+        emitln!(out, "wait \"$__sh2_p{}\"; __sh2_s{}=$?;", i, i);
     }
 
     // Compute effective status (rightmost non-zero wins, ignoring allow_fail stages)
-    out.push_str(&format!("{}__sh2_status=0;\n", indent_pad));
+    emitln!(out, "__sh2_status=0;");
     for i in 0..stages.len() {
         if !allow_fails[i] || i == stages.len() - 1 {
-            out.push_str(&format!(
-                "{}if [ \"$__sh2_s{}\" -ne 0 ]; then __sh2_status=\"$__sh2_s{}\"; fi;\n",
-                indent_pad, i, i
-            ));
+            emitln!(out, "if [ \"$__sh2_s{}\" -ne 0 ]; then __sh2_status=\"$__sh2_s{}\"; fi;", i, i);
         }
     }
 
     // Cleanup FIFOs and reset traps to default
-    out.push_str(&format!("{}trap - EXIT INT TERM QUIT;\n", indent_pad));
-    out.push_str(&format!("{}rm -f \"${{__sh2_base}}_\"*;\n", indent_pad));
+    emitln!(out, "trap - EXIT INT TERM QUIT;");
+    emitln!(out, "rm -f \"${{__sh2_base}}_\"*;");
 
     // Restore user traps (if any were saved)
-    out.push_str(&format!(
-        "{}if [ -n \"$__sh2_saved_traps\" ]; then eval \"$__sh2_saved_traps\"; fi;\n",
-        indent_pad
-    ));
+    emitln!(out, "if [ -n \"$__sh2_saved_traps\" ]; then eval \"$__sh2_saved_traps\"; fi;");
 
     // Restore errexit if it was set
-    out.push_str(&format!(
-        "{}if [ \"$__sh2_e\" = 1 ]; then set -e; fi;\n",
-        indent_pad
-    ));
+    emitln!(out, "if [ \"$__sh2_e\" = 1 ]; then set -e; fi;");
 
     // Return status
     if allow_fail_last {
-        out.push_str(&format!("{}:\n", indent_pad));
+        emitln!(out, ":");
     } else {
         match target {
-            TargetShell::Bash => out.push_str(&format!("{}(exit $__sh2_status)\n", indent_pad)),
+            TargetShell::Bash => emitln!(out, "(exit $__sh2_status)"),
             TargetShell::Posix => {
                 // Do not explicitly exit (which kills script if no subshell) or print (because we lack trap ERR equivalent)
                 // Just relying on (exit) to set status and trigger errexit if enabled.
-                out.push_str(&format!("{}(exit $__sh2_status)\n", indent_pad));
+                emitln!(out, "(exit $__sh2_status)");
             }
         }
     }
 
-    out.push_str(&format!("{}}}\n", pad));
+    out.dedent();
+    out.line("}");
+    Ok(())
 }
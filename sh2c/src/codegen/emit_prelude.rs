@@ -1,18 +1,227 @@
 use super::{PreludeUsage, TargetShell};
 
-pub(super) fn emit_prelude(target: TargetShell, usage: &PreludeUsage) -> String {
+/// Which slice of the prelude to produce.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum PreludePart {
+    /// Helper definitions plus the global setup they need (traps, errtrace),
+    /// inlined into a standalone script.
+    Inline,
+    /// Helper definitions only. This is the body of the `--runtime-lib` file; each
+    /// script keeps its own setup via [`emit_prelude_setup`].
+    Runtime,
+    /// Helper definitions only, for the given usage. An emitted library defines
+    /// these on demand and never touches the caller's traps or shell options.
+    Library,
+}
+
+const POSIX_TMPFILES_INIT: &str = "__sh2_tmpfiles=\"\"\n";
+const PROFILE_LOG_INIT: &str = "__sh2_prof_log=\"${TMPDIR:-/tmp}/sh2-profile.$$\"; : > \"$__sh2_prof_log\"\n";
+const CACHE_DIR_INIT: &str = "__sh2_cache_dir=\"$(mktemp -d \"${TMPDIR:-/tmp}/sh2-cache.XXXXXX\")\" || exit 1\n";
+const BASH_CACHE_INIT: &str = "declare -A __sh2_cache=()\n";
+const BASH_ERR_TRAP: &str = "set -o errtrace\ntrap '__sh2_err_handler' ERR\n";
+
+/// The single EXIT trap: POSIX split() temp-file cleanup, killing spawned jobs,
+/// the `--profile` report, and removing the `@cache` directory.
+fn exit_trap(target: TargetShell, usage: &PreludeUsage) -> String {
+    let handlers: Vec<&str> = [
+        (target == TargetShell::Posix && usage.split, "__sh2_cleanup_tmpfiles"),
+        (usage.spawn, "__sh2_kill_spawned"),
+        (usage.profile, "__sh2_prof_report"),
+        (usage.cache, "__sh2_cache_cleanup"),
+    ]
+    .into_iter()
+    .filter_map(|(on, handler)| on.then_some(handler))
+    .collect();
+    match handlers.as_slice() {
+        [] => String::new(),
+        [one] => format!("trap {} EXIT\n", one),
+        many => format!("trap '{}' EXIT\n", many.join("; ")),
+    }
+}
+
+/// The global statements an inline prelude would run, for scripts that source
+/// the runtime library instead.
+pub(super) fn emit_prelude_setup(target: TargetShell, usage: &PreludeUsage) -> String {
+    let mut s = String::new();
+    match target {
+        TargetShell::Bash => {
+            if usage.loc {
+                s.push_str(BASH_ERR_TRAP);
+            }
+        }
+        TargetShell::Posix => {
+            if usage.split {
+                s.push_str(POSIX_TMPFILES_INIT);
+            }
+        }
+    }
+    if usage.profile {
+        s.push_str(PROFILE_LOG_INIT);
+    }
+    if usage.cache {
+        s.push_str(CACHE_DIR_INIT);
+        if target == TargetShell::Bash {
+            s.push_str(BASH_CACHE_INIT);
+        }
+    }
+    s.push_str(&exit_trap(target, usage));
+    s
+}
+
+pub(super) fn emit_prelude(target: TargetShell, usage: &PreludeUsage, part: PreludePart) -> String {
     let mut s = String::new();
 
-    // Always emit __sh2_check for fail-fast behavior
+    // Always emit __sh2_check for fail-fast behavior; with a call stack it also prints the trace.
     match target {
         TargetShell::Bash => {
-            s.push_str("__sh2_check() { local s=\"$1\"; local loc=\"$2\"; local mode=\"$3\"; if (( s != 0 )); then if [[ \"$mode\" == \"return\" ]]; then return \"$s\"; else if [[ -n \"$loc\" ]]; then printf 'Error in %s\\n' \"$loc\" >&2; fi; exit \"$s\"; fi; fi; }\n");
+            s.push_str("__sh2_check() { local s=\"$1\"; local loc=\"$2\"; local mode=\"$3\"; if (( s != 0 )); then if [[ \"$mode\" == \"return\" ]]; then return \"$s\"; else if [[ -n \"$loc\" ]]; then printf 'Error in %s\\n' \"$loc\" >&2;");
+            if usage.call_stack {
+                s.push_str(" if [[ -n \"${__sh2_stack:-}\" ]]; then printf '  %s at %s\\n%s' \"$__sh2_fn\" \"$loc\" \"$__sh2_stack\" >&2; fi;");
+            }
+            s.push_str(" fi; exit \"$s\"; fi; fi; }\n");
         }
         TargetShell::Posix => {
-            s.push_str("__sh2_check() { __sh2_s=\"$1\"; __sh2_l=\"$2\"; __sh2_m=\"$3\"; if [ \"$__sh2_s\" -ne 0 ]; then if [ \"$__sh2_m\" = \"return\" ]; then return \"$__sh2_s\"; fi; if [ -n \"$__sh2_l\" ]; then printf 'Error in %s\\n' \"$__sh2_l\" >&2; fi; exit \"$__sh2_s\"; fi; }\n");
+            s.push_str("__sh2_check() { __sh2_s=\"$1\"; __sh2_l=\"$2\"; __sh2_m=\"$3\"; if [ \"$__sh2_s\" -ne 0 ]; then if [ \"$__sh2_m\" = \"return\" ]; then return \"$__sh2_s\"; fi; if [ -n \"$__sh2_l\" ]; then printf 'Error in %s\\n' \"$__sh2_l\" >&2;");
+            if usage.call_stack {
+                s.push_str(" if [ -n \"${__sh2_stack:-}\" ]; then printf '  %s at %s\\n%s' \"$__sh2_fn\" \"$__sh2_l\" \"$__sh2_stack\" >&2; fi;");
+            }
+            s.push_str(" fi; exit \"$__sh2_s\"; fi; }\n");
         }
     }
 
+    if usage.call_stack {
+        // Call stack for error traces: each function entry records its caller
+        // and the caller's location, innermost first. Bash keeps the stack in
+        // locals, so returning pops it; POSIX callers pop after the call.
+        s.push_str(r#"__sh2_frame() {
+  if [ -n "${__sh2_fn:-}" ]; then __sh2_stack="  $__sh2_fn at ${__sh2_loc:-?}
+${__sh2_stack:-}"; fi
+  __sh2_fn="$1"
+}
+"#);
+        if target == TargetShell::Posix {
+            s.push_str(r#"__sh2_unframe() {
+  __sh2_fn="${__sh2_stack#  }"; __sh2_fn="${__sh2_fn%% at *}"
+  __sh2_stack="${__sh2_stack#*
+}"
+}
+"#);
+        }
+    }
+
+    if usage.debug {
+        // `sh2do --debug`: every statement calls __sh2_debug with its location,
+        // whether it is a breakpoint(), its source line and the variables in scope.
+        // Commands are read from fd 8 (the terminal, or $SH2_DEBUG_INPUT) so the
+        // script's own stdin is left alone.
+        if target == TargetShell::Bash {
+            s.push_str("__sh2_debug() {\n  local __sh2_dl __sh2_db __sh2_ds __sh2_dc __sh2_dv\n");
+        } else {
+            s.push_str("__sh2_debug() {\n");
+        }
+        s.push_str(r#"  __sh2_dl="$1"; __sh2_db="$2"; __sh2_ds="$3"; shift 3
+  if [ "${__sh2_dbg_mode:-}" != step ] && [ "$__sh2_db" != 1 ]; then
+    if [ "${__sh2_dbg_mode:-}" != run ] || [ -z "${SH2_BREAK:-}" ]; then return 0; fi
+    __sh2_dv="${__sh2_dl%:*}"
+    for __sh2_dc in $SH2_BREAK; do
+      case "$__sh2_dv" in "$__sh2_dc"|*/"$__sh2_dc") __sh2_db=1 ;; esac
+    done
+    if [ "$__sh2_db" != 1 ]; then return 0; fi
+  fi
+  if [ -z "${__sh2_dbg_in:-}" ]; then
+    __sh2_dbg_in="${SH2_DEBUG_INPUT:-/dev/tty}"
+    if ! { command exec 8<"$__sh2_dbg_in"; } 2>/dev/null; then
+      printf '[debug] cannot read commands from %s; continuing\n' "$__sh2_dbg_in" >&2
+      __sh2_dbg_mode=off
+      return 0
+    fi
+  fi
+  printf '[debug] %s: %s\n' "$__sh2_dl" "$__sh2_ds" >&2
+  while :; do
+    printf '(debug) ' >&2
+    if ! IFS= read -r __sh2_dc <&8; then
+      printf '\n' >&2
+      __sh2_dbg_mode=off
+      return 0
+    fi
+    case "$__sh2_dc" in
+      ""|s|step) __sh2_dbg_mode=step; return 0 ;;
+      c|continue) __sh2_dbg_mode=run; return 0 ;;
+      v|vars) for __sh2_dv in "$@"; do __sh2_debug_show "$__sh2_dv"; done ;;
+      "p "*) __sh2_debug_show "${__sh2_dc#p }" ;;
+      q|quit) exit 1 ;;
+      *) printf 'commands: s(tep), c(ontinue), v(ars), p <name>, q(uit)\n' >&2 ;;
+    esac
+  done
+}
+"#);
+        s.push_str(r#"__sh2_debug_show() {
+  case "$1" in ""|[0-9]*|*[!A-Za-z0-9_]*) printf 'not a variable name: %s\n' "$1" >&2; return 0 ;; esac
+"#);
+        match target {
+            TargetShell::Bash => s.push_str(r#"  local __sh2_dd
+  if ! declare -p "$1" >/dev/null 2>&1; then printf '%s is not set\n' "$1" >&2; return 0; fi
+  __sh2_dd="$(declare -p "$1")"
+  case "$__sh2_dd" in
+    "declare -a "*|"declare -A "*) printf '%s\n' "${__sh2_dd#declare -? }" >&2 ;;
+    *) printf '%s=%s\n' "$1" "${!1-}" >&2 ;;
+  esac
+}
+"#),
+            TargetShell::Posix => s.push_str(r#"  if eval "[ -z \"\${$1+x}\" ]"; then printf '%s is not set\n' "$1" >&2; return 0; fi
+  eval "printf '%s=%s\n' \"\$1\" \"\${$1}\"" >&2
+}
+"#),
+        }
+    }
+
+    if usage.dump_vars {
+        // Arguments: the call's location, then each name padded to a common width.
+        match target {
+            TargetShell::Bash => s.push_str(r#"__sh2_dump_vars() {
+  local __sh2_dp __sh2_dn __sh2_dd __sh2_dv __sh2_dk __sh2_de __sh2_ds
+  local -a __sh2_dks
+  printf 'dump_vars at %s\n' "$1" >&2; shift
+  for __sh2_dp in "$@"; do
+    __sh2_dn="${__sh2_dp%% *}"
+    if ! __sh2_dd="$(declare -p "$__sh2_dn" 2>/dev/null)"; then
+      __sh2_dv="<unset>"
+    else
+      case "$__sh2_dd" in
+        "declare -a"*|"declare -A"*)
+          eval "__sh2_dks=(\"\${!${__sh2_dn}[@]}\")"
+          __sh2_dv=""; __sh2_ds=""
+          for __sh2_dk in "${__sh2_dks[@]}"; do
+            eval "__sh2_de=\"\${${__sh2_dn}[\$__sh2_dk]}\""
+            case "$__sh2_dd" in
+              "declare -A"*) __sh2_dv+="$__sh2_ds$__sh2_dk: $__sh2_de" ;;
+              *) __sh2_dv+="$__sh2_ds$__sh2_de" ;;
+            esac
+            __sh2_ds=", "
+          done
+          case "$__sh2_dd" in
+            "declare -A"*) __sh2_dv="{$__sh2_dv}" ;;
+            *) __sh2_dv="[$__sh2_dv]" ;;
+          esac
+          ;;
+        *) __sh2_dv="${!__sh2_dn}" ;;
+      esac
+    fi
+    printf '  %s  %s\n' "$__sh2_dp" "$__sh2_dv" >&2
+  done
+}
+"#),
+            TargetShell::Posix => s.push_str(r#"__sh2_dump_vars() {
+  printf 'dump_vars at %s\n' "$1" >&2; shift
+  for __sh2_dp in "$@"; do
+    __sh2_dn="${__sh2_dp%% *}"
+    if eval "[ -z \"\${$__sh2_dn+x}\" ]"; then __sh2_dv="<unset>"; else eval "__sh2_dv=\"\${$__sh2_dn}\""; fi
+    printf '  %s  %s\n' "$__sh2_dp" "$__sh2_dv" >&2
+  done
+}
+"#),
+        }
+    }
 
     if usage.sh_probe {
         match target {
@@ -39,6 +248,16 @@ pub(super) fn emit_prelude(target: TargetShell, usage: &PreludeUsage) -> String
     if usage.coalesce {
         s.push_str("__sh2_coalesce() { if [ -n \"$1\" ]; then printf '%s' \"$1\"; else printf '%s' \"$2\"; fi; }\n");
     }
+    // Both append a sentinel so trailing newlines count, and run awk in the C
+    // locale so every awk measures bytes; `len` then drops UTF-8 continuation bytes.
+    if usage.len {
+        s.push_str(r#"__sh2_len() { printf '%sx' "$1" | LC_ALL=C awk '{ gsub(/[\200-\277]/, ""); n += length($0) } END { print n + NR - 2 }'; }
+"#);
+    }
+    if usage.bytes_len {
+        s.push_str(r#"__sh2_bytes_len() { printf '%sx' "$1" | LC_ALL=C awk '{ n += length($0) } END { print n + NR - 2 }'; }
+"#);
+    }
     if usage.trim {
         s.push_str(r#"__sh2_trim() { awk -v s="$1" 'BEGIN { sub(/^[[:space:]]+/, "", s); sub(/[[:space:]]+$/, "", s); printf "%s", s }'; }
 "#);
@@ -70,8 +289,10 @@ pub(super) fn emit_prelude(target: TargetShell, usage: &PreludeUsage) -> String
 "#);
             }
             TargetShell::Posix => {
-                s.push_str(r#"__sh2_tmpfiles=""
-__sh2_tmpfile() {
+                if part == PreludePart::Inline {
+                    s.push_str(POSIX_TMPFILES_INIT);
+                }
+                s.push_str(r#"__sh2_tmpfile() {
     t=$(mktemp) || exit 1
     __sh2_tmpfiles="$__sh2_tmpfiles $t"
     echo "$t"
@@ -79,8 +300,11 @@ __sh2_tmpfile() {
 __sh2_cleanup_tmpfiles() {
     for t in $__sh2_tmpfiles; do rm -f "$t"; done
 }
-trap __sh2_cleanup_tmpfiles EXIT
-__sh2_split() {
+"#);
+                if part == PreludePart::Inline {
+                    s.push_str(&exit_trap(target, usage));
+                }
+                s.push_str(r#"__sh2_split() {
   awk -v s="$1" -v sep="$2" 'BEGIN {
      if(sep=="") { print s; exit }
      len=length(sep);
@@ -110,8 +334,9 @@ __sh2_split() {
 }
 "#);
 
-                s.push_str("set -o errtrace\n");
-                s.push_str("trap '__sh2_err_handler' ERR\n");
+                if part == PreludePart::Inline {
+                    s.push_str(BASH_ERR_TRAP);
+                }
             }
             if usage.matches {
                 s.push_str("__sh2_matches() { [[ \"$1\" =~ $2 ]]; }\n");
@@ -250,11 +475,354 @@ __sh2_split() {
         );
 
     }
+    if usage.have {
+        // Cache entries are newline-delimited "+cmd" / "-cmd" records so the lookup is a
+        // single case match and no associative arrays are needed on POSIX sh.
+        s.push_str(
+            r#"__sh2_have() {
+  case "${__sh2_have_cache-}" in
+    *"
++$1
+"*) return 0 ;;
+    *"
+-$1
+"*) return 1 ;;
+  esac
+  if __sh2_which "$1" >/dev/null; then
+    __sh2_have_cache="${__sh2_have_cache-}
++$1
+"
+    return 0
+  fi
+  __sh2_have_cache="${__sh2_have_cache-}
+-$1
+"
+  return 1
+}
+"#,
+        );
+    }
+    if usage.service {
+        s.push_str(
+            r#"__sh2_service_running() {
+  __sh2_svc_pid=""
+  [ -f "$1" ] || return 1
+  IFS= read -r __sh2_svc_pid < "$1" || :
+  case "$__sh2_svc_pid" in ''|*[!0-9]*) return 1 ;; esac
+  kill -0 "$__sh2_svc_pid" 2>/dev/null
+}
+__sh2_service_start() {
+  __sh2_svc_file="$1"; __sh2_svc_log="$2"; shift 2
+  if __sh2_service_running "$__sh2_svc_file"; then
+    printf '%s\n' "service already running (pid $__sh2_svc_pid, pidfile $__sh2_svc_file)" >&2
+    return 1
+  fi
+  "$@" </dev/null >>"$__sh2_svc_log" 2>&1 &
+  printf '%s\n' "$!" > "$__sh2_svc_file"
+}
+__sh2_service_stop() {
+  if ! __sh2_service_running "$1"; then
+    rm -f "$1"
+    return 0
+  fi
+  kill -TERM "$__sh2_svc_pid" 2>/dev/null || :
+  __sh2_svc_i=0
+  while kill -0 "$__sh2_svc_pid" 2>/dev/null; do
+    if [ "$__sh2_svc_i" -ge "$2" ]; then
+      kill -KILL "$__sh2_svc_pid" 2>/dev/null || :
+      break
+    fi
+    sleep 1
+    __sh2_svc_i=$((__sh2_svc_i + 1))
+  done
+  rm -f "$1"
+}
+"#,
+        );
+    }
+    if usage.input_list {
+        // The prompt goes to stderr so it stays visible when stdout is captured; with no
+        // terminal attached every non-empty stdin line is taken until EOF.
+        match target {
+            TargetShell::Bash => s.push_str(
+                r#"__sh2_input_list() {
+  local -n __ref="$1"
+  local __line
+  __ref=()
+  if [ -t 0 ]; then
+    printf '%s\n' "$2" >&2
+    while IFS= read -r __line; do
+      if [ -z "$__line" ]; then break; fi
+      __ref+=("$__line")
+    done
+  else
+    while IFS= read -r __line || [ -n "$__line" ]; do
+      if [ -n "$__line" ]; then __ref+=("$__line"); fi
+    done
+  fi
+  return 0
+}
+"#,
+            ),
+            TargetShell::Posix => s.push_str(
+                r#"__sh2_input_list() {
+  if [ -t 0 ]; then
+    printf '%s\n' "$1" >&2
+    while IFS= read -r __sh2_il_line; do
+      if [ -z "$__sh2_il_line" ]; then break; fi
+      printf '%s\n' "$__sh2_il_line"
+    done
+  else
+    while IFS= read -r __sh2_il_line || [ -n "$__sh2_il_line" ]; do
+      if [ -n "$__sh2_il_line" ]; then printf '%s\n' "$__sh2_il_line"; fi
+    done
+  fi
+  return 0
+}
+"#,
+            ),
+        }
+    }
+    if usage.wait_for_port {
+        // Bash can open the socket itself via /dev/tcp; POSIX sh has no such facility,
+        // so the probe shells out to `nc -z`.
+        match target {
+            TargetShell::Bash => s.push_str(
+                r#"__sh2_port_open() { ( exec 3<>"/dev/tcp/$1/$2" ) 2>/dev/null; }
+"#,
+            ),
+            TargetShell::Posix => s.push_str(
+                r#"__sh2_port_open() {
+  if ! command -v nc >/dev/null 2>&1; then
+    printf '%s\n' "wait_for_port: nc is required on this target" >&2
+    return 2
+  fi
+  nc -z "$1" "$2" >/dev/null 2>&1
+}
+"#,
+            ),
+        }
+        s.push_str(
+            r#"__sh2_wait_for_port() {
+  case "$3:$4" in *[!0-9:]*|:*|*:|*:0) printf '%s\n' "wait_for_port: timeout and interval must be whole seconds (interval > 0)" >&2; return 2 ;; esac
+  __sh2_wfp_elapsed=0
+  while :; do
+    if __sh2_port_open "$1" "$2"; then return 0; else __sh2_wfp_rc=$?; fi
+    if [ "$__sh2_wfp_rc" -eq 2 ]; then return 2; fi
+    if [ "$__sh2_wfp_elapsed" -ge "$3" ]; then
+      printf '%s\n' "wait_for_port: timed out after $3s waiting for $1:$2" >&2
+      return 1
+    fi
+    sleep "$4"
+    __sh2_wfp_elapsed=$((__sh2_wfp_elapsed + $4))
+  done
+}
+"#,
+        );
+    }
+    if usage.spawn {
+        // Jobs still running when the script exits are killed rather than left behind.
+        // Bash only signals PIDs it still lists as jobs, so a reused PID is never hit.
+        match target {
+            TargetShell::Bash => s.push_str(
+                r#"__sh2_kill_spawned() {
+  local p
+  for p in $(jobs -p); do
+    case " ${__sh2_spawned:-} " in *" $p "*) kill "$p" 2>/dev/null || : ;; esac
+  done
+}
+"#,
+            ),
+            TargetShell::Posix => s.push_str(
+                r#"__sh2_kill_spawned() {
+  for __sh2_p in ${__sh2_spawned:-}; do kill "$__sh2_p" 2>/dev/null || : ; done
+}
+"#,
+            ),
+        }
+        // With POSIX split() the combined trap was already installed with the temp-file cleanup.
+        if part == PreludePart::Inline && !(target == TargetShell::Posix && usage.split) {
+            s.push_str(&exit_trap(target, usage));
+        }
+    }
+    if usage.profile {
+        // Times are in microseconds: EPOCHREALTIME where the shell has it, else
+        // `date +%s%N`, or whole seconds from a `date` without %N.
+        s.push_str(r#"__sh2_now() {
+  if [ -n "${EPOCHREALTIME:-}" ]; then
+    __sh2_nowv="$EPOCHREALTIME"; __sh2_nowv="${__sh2_nowv%[.,]*}${__sh2_nowv#*[.,]}"
+    return 0
+  fi
+  __sh2_nowv="$(date +%s%N)"
+  case "$__sh2_nowv" in
+    *[!0-9]*) __sh2_nowv="${__sh2_nowv%%[!0-9]*}000000" ;;
+    *) __sh2_nowv="${__sh2_nowv%???}" ;;
+  esac
+}
+__sh2_prof_add() {
+  __sh2_now
+  printf '%s\t%s\t%s\n' "$((__sh2_nowv - $1))" "$2" "$3" >> "$__sh2_prof_log"
+}
+__sh2_prof_report() {
+  if [ -s "$__sh2_prof_log" ]; then
+    printf 'profile: slowest lines (total time, runs)\n' >&2
+    awk -F '\t' '{ t[$2] += $1; n[$2]++; s[$2] = $3 }
+      END { for (k in t) printf "%.0f\t%d\t%s\t%s\n", t[k], n[k], k, s[k] }' "$__sh2_prof_log" \
+      | sort -rn | head -n 10 \
+      | awk -F '\t' '{ printf "  %10.3f ms %5dx  %s  %s\n", $1 / 1000, $2, $3, $4 }' >&2
+  fi
+  rm -f "$__sh2_prof_log"
+}
+"#);
+        if part == PreludePart::Inline {
+            s.push_str(PROFILE_LOG_INIT);
+            // Otherwise the trap, with the report included, is already installed.
+            if !(usage.spawn || target == TargetShell::Posix && usage.split) {
+                s.push_str(&exit_trap(target, usage));
+            }
+        }
+    }
+    if usage.cache {
+        // Entries live in memory (bash) and in a directory shared with the
+        // `$(...)` subshells that value calls run in; the key file guards
+        // against checksum collisions. Failed calls are not cached. A function
+        // only fails by exiting, so the wrapper exits with `__sh2_cache_status`
+        // just as the function would have, and only its own error is reported.
+        match target {
+            TargetShell::Bash => s.push_str(r#"__sh2_cache_call() {
+  local __sh2_cn="$1" __sh2_ci="$2" __sh2_ck __sh2_cf __sh2_cv
+  shift 2
+  __sh2_cache_status=0
+  printf -v __sh2_ck '%s\037' "$__sh2_cn" "$@"
+  if [[ -n "${__sh2_cache[$__sh2_ck]+x}" ]]; then printf '%s' "${__sh2_cache[$__sh2_ck]}"; return 0; fi
+  __sh2_cf="$(printf '%s' "$__sh2_ck" | cksum)"; __sh2_cf="$__sh2_cache_dir/${__sh2_cf%% *}"
+  if [[ -f "$__sh2_cf.key" && "$(<"$__sh2_cf.key")" == "${__sh2_ck}x" ]]; then
+    __sh2_cv="$(<"$__sh2_cf.val")"
+  else
+    local __sh2_suppress_err_depth=$(( ${__sh2_suppress_err_depth:-0} + 1 ))
+    __sh2_cv="$(__sh2_suppress_err_depth=$(( __sh2_suppress_err_depth - 1 )); "$__sh2_ci" "$@"; __sh2_cs=$?; printf x; exit "$__sh2_cs")"
+    __sh2_cache_status=$?
+    if (( __sh2_cache_status != 0 )); then printf '%s' "${__sh2_cv%x}"; return 0; fi
+    if [[ ! -f "$__sh2_cf.key" ]]; then printf '%s' "$__sh2_cv" > "$__sh2_cf.val"; printf '%sx' "$__sh2_ck" > "$__sh2_cf.key"; fi
+  fi
+  __sh2_cv="${__sh2_cv%x}"
+  __sh2_cache[$__sh2_ck]="$__sh2_cv"
+  printf '%s' "$__sh2_cv"
+}
+"#),
+            TargetShell::Posix => s.push_str(r#"__sh2_cache_call() {
+  __sh2_cn="$1"; __sh2_ci="$2"; shift 2
+  __sh2_cache_status=0
+  __sh2_ck="$(printf '%s\037' "$__sh2_cn" "$@"; printf x)"; __sh2_ck="${__sh2_ck%x}"
+  __sh2_cf="$(printf '%s' "$__sh2_ck" | cksum)"; __sh2_cf="$__sh2_cache_dir/${__sh2_cf%% *}"
+  if [ -f "$__sh2_cf.key" ] && [ "$(cat "$__sh2_cf.key")" = "${__sh2_ck}x" ]; then
+    __sh2_cv="$(cat "$__sh2_cf.val")"
+  else
+    __sh2_cv="$("$__sh2_ci" "$@"; __sh2_cs=$?; printf x; exit "$__sh2_cs")" || __sh2_cache_status=$?
+    if [ "$__sh2_cache_status" -ne 0 ]; then printf '%s' "${__sh2_cv%x}"; return 0; fi
+    if [ ! -f "$__sh2_cf.key" ]; then printf '%s' "$__sh2_cv" > "$__sh2_cf.val"; printf '%sx' "$__sh2_ck" > "$__sh2_cf.key"; fi
+  fi
+  printf '%s' "${__sh2_cv%x}"
+}
+"#),
+        }
+        s.push_str("__sh2_cache_cleanup() { rm -rf \"$__sh2_cache_dir\"; }\n");
+        if part == PreludePart::Inline {
+            s.push_str(CACHE_DIR_INIT);
+            if target == TargetShell::Bash {
+                s.push_str(BASH_CACHE_INIT);
+            }
+            if !(usage.spawn || usage.profile || target == TargetShell::Posix && usage.split) {
+                s.push_str(&exit_trap(target, usage));
+            }
+        }
+    }
+    if usage.deadline {
+        s.push_str(
+            r#"__sh2_duration_secs() {
+  case "$1" in
+    ''|[!0-9]*|*[!0-9smhd]*|*[smhd]?*) printf 'invalid deadline: %s (expected e.g. 30s, 5m, 2h)\n' "$1" >&2; return 2 ;;
+  esac
+  __sh2_ds_n=${1%[smhd]}
+  case "$1" in
+    *m) echo $((__sh2_ds_n * 60)) ;;
+    *h) echo $((__sh2_ds_n * 3600)) ;;
+    *d) echo $((__sh2_ds_n * 86400)) ;;
+    *) echo "$__sh2_ds_n" ;;
+  esac
+}
+"#,
+        );
+        // Signals a deadline block and everything below it: its process group in Bash
+        // (which also reaches children that were re-parented), and in both targets the
+        // process tree as `ps` shows it.
+        if target == TargetShell::Bash {
+            s.push_str(
+                r#"__sh2_kill_job() {
+  kill -s "$1" -- "-$2" 2>/dev/null || :
+"#,
+            );
+        } else {
+            s.push_str(
+                r#"__sh2_kill_job() {
+"#,
+            );
+        }
+        s.push_str(
+            r#"  for __sh2_kj in $(ps -eo pid= -o ppid= 2>/dev/null | awk -v root="$2" '
+    { parent[$1] = $2 }
+    END {
+      seen[root] = 1
+      do { more = 0; for (p in parent) if (!(p in seen) && (parent[p] in seen)) { seen[p] = 1; more = 1 } } while (more)
+      print root
+      for (p in seen) if (p != root) print p
+    }'); do
+    kill -s "$1" "$__sh2_kj" 2>/dev/null || :
+  done
+}
+__sh2_deadline_watch() {
+  (
+    trap 'kill "$__sh2_dw_sleep" 2>/dev/null; exit 0' TERM
+    sleep "$2" &
+    __sh2_dw_sleep=$!
+    wait "$__sh2_dw_sleep"
+    # Fired: the caller's cancel must not cut a kill loop short.
+    trap '' TERM
+    __sh2_kill_job TERM "$1"
+    trap 'kill "$__sh2_dw_sleep" 2>/dev/null; exit 124' TERM
+    if kill -0 "$1" 2>/dev/null; then
+      sleep 2 &
+      __sh2_dw_sleep=$!
+      wait "$__sh2_dw_sleep"
+      trap '' TERM
+      __sh2_kill_job KILL "$1"
+    fi
+    exit 124
+  ) >/dev/null 2>&1 &
+  __sh2_dl_watch=$!
+}
+__sh2_deadline_end() {
+  kill "$2" 2>/dev/null || :
+  if wait "$2"; then :; else
+    __sh2_de_rc=$?
+    if [ "$__sh2_de_rc" -eq 124 ]; then
+      printf 'deadline of %s exceeded\n' "$3" >&2
+      __sh2_status=124
+    fi
+  fi
+  __sh2_kill_job TERM "$1"
+}
+"#,
+        );
+    }
     if usage.require {
         s.push_str(r#"__sh2_require() { for c in "$@"; do if ! command -v -- "$c" >/dev/null 2>&1; then printf '%s\n' "missing required command: $c" >&2; exit 127; fi; done; }
 "#);
     }
-    if usage.tmpfile {
+    // POSIX split() already defines a self-cleaning __sh2_tmpfile above. The runtime
+    // library always includes that one, so it must not be shadowed there.
+    let posix_runtime = part == PreludePart::Runtime && target == TargetShell::Posix;
+    if !posix_runtime && (usage.tmpfile || (usage.posix_list_tmpfile && target == TargetShell::Posix && !usage.split)) {
         s.push_str(r#"__sh2_tmpfile() { if command -v mktemp >/dev/null 2>&1; then mktemp; else printf "%s/sh2_tmp_%s_%s" "${TMPDIR:-/tmp}" "$$" "$(awk 'BEGIN{srand();print int(rand()*1000000)}')"; fi; }
 "#);
     }
@@ -274,14 +842,116 @@ __sh2_split() {
             TargetShell::Posix => {} // Compile error handled in emit_cmd
         }
     }
+    if usage.lines0 && target == TargetShell::Bash {
+        // The producer's output goes through a temp file (NUL bytes survive in files, not
+        // in variables) so its exit status can be reported before any record is used.
+        s.push_str(r#"__sh2_lines0() {
+  local -n __ref="$1"
+  shift
+  local __tmp __rec __s
+  __ref=()
+  __tmp="$(mktemp)" || return 1
+  "$@" > "$__tmp" || { __s=$?; rm -f "$__tmp"; return "$__s"; }
+  while IFS= read -r -d '' __rec || [ -n "$__rec" ]; do
+    __ref+=("$__rec")
+  done < "$__tmp"
+  rm -f "$__tmp"
+}
+"#);
+    }
+    if usage.find {
+        // The root itself is excluded (-mindepth 1) and a leading '-' in dir is
+        // neutralised with ./ so find cannot read it as a primary.
+        match target {
+            TargetShell::Bash => {
+                s.push_str(r#"__sh2_find() {
+  local -n __ref="$1"
+  local __dir="$2" __f
+  case "$__dir" in -*) __dir="./$__dir" ;; esac
+  local -a __args=(find "$__dir" -mindepth 1)
+  if [ -n "$5" ]; then __args+=(-maxdepth "$5"); fi
+  if [ -n "$3" ]; then __args+=(-name "$3"); fi
+  if [ -n "$4" ]; then __args+=(-type "$4"); fi
+  __ref=()
+  while IFS= read -r -d '' __f; do
+    __ref+=("$__f")
+  done < <("${__args[@]}" -print0 2>/dev/null | LC_ALL=C sort -z)
+}
+"#);
+            }
+            TargetShell::Posix => {
+                s.push_str(r#"__sh2_find() {
+  __sh2_fd_dir="$1"; __sh2_fd_name="$2"; __sh2_fd_type="$3"; __sh2_fd_depth="$4"
+  case "$__sh2_fd_dir" in -*) __sh2_fd_dir="./$__sh2_fd_dir" ;; esac
+  set -- "$__sh2_fd_dir" -mindepth 1
+  if [ -n "$__sh2_fd_depth" ]; then set -- "$@" -maxdepth "$__sh2_fd_depth"; fi
+  if [ -n "$__sh2_fd_name" ]; then set -- "$@" -name "$__sh2_fd_name"; fi
+  if [ -n "$__sh2_fd_type" ]; then set -- "$@" -type "$__sh2_fd_type"; fi
+  find "$@" -print 2>/dev/null | LC_ALL=C sort
+}
+"#);
+            }
+        }
+    }
     if usage.read_file {
         s.push_str(
             r#"__sh2_read_file() { cat "$1"; }
+"#,
+        );
+    }
+    if usage.read_file_or {
+        // Only a missing path falls back to the default; unreadable files still fail
+        s.push_str(
+            r#"__sh2_read_file_or() { if [ -e "$1" ] || [ -L "$1" ]; then cat "$1"; else printf '%s' "$2"; fi; }
 "#,
         );
     }
     if usage.write_file {
         s.push_str(r#"__sh2_write_file() { if [ "$3" = "true" ]; then printf '%s' "$2" >> "$1"; else printf '%s' "$2" > "$1"; fi; }
+"#);
+    }
+    if usage.file_edit {
+        // Literal (not regex) matching via awk index() sidesteps GNU/BSD `sed -i` differences.
+        // The result is copied back with `cat >` so the file keeps its mode, owner and links.
+        s.push_str(r#"__sh2_replace_in_file() {
+  if [ -z "$2" ]; then printf '%s\n' "replace_in_file: pattern must not be empty" >&2; return 2; fi
+  if [ ! -f "$1" ]; then printf '%s\n' "replace_in_file: $1: no such file" >&2; return 1; fi
+  __sh2_rf_nl=1
+  if [ -n "$(tail -c 1 "$1")" ]; then __sh2_rf_nl=0; fi
+  __sh2_rf_tmp="$1.sh2tmp.$$"
+  if ! __sh2_rf_pat="$2" __sh2_rf_rep="$3" __sh2_rf_nl="$__sh2_rf_nl" awk 'BEGIN { p = ENVIRON["__sh2_rf_pat"]; r = ENVIRON["__sh2_rf_rep"]; n = length(p) }
+{ s = $0; o = ""; while (index(s, p) > 0) { i = index(s, p); o = o substr(s, 1, i - 1) r; s = substr(s, i + n) } if (NR > 1) printf "\n"; printf "%s", o s }
+END { if (NR > 0 && ENVIRON["__sh2_rf_nl"] == "1") printf "\n" }' "$1" > "$__sh2_rf_tmp"; then rm -f "$__sh2_rf_tmp"; return 1; fi
+  if cmp -s "$1" "$__sh2_rf_tmp"; then rm -f "$__sh2_rf_tmp"; return 0; fi
+  if [ "$4" = "true" ] && ! cp -p "$1" "$1.bak"; then rm -f "$__sh2_rf_tmp"; return 1; fi
+  if ! cat "$__sh2_rf_tmp" > "$1"; then rm -f "$__sh2_rf_tmp"; return 1; fi
+  rm -f "$__sh2_rf_tmp"
+}
+__sh2_append_line_if_missing() {
+  if [ -f "$1" ] && grep -Fqx -e "$2" "$1"; then return 0; fi
+  if [ "$3" = "true" ] && [ -f "$1" ] && ! cp -p "$1" "$1.bak"; then return 1; fi
+  if [ -s "$1" ] && [ -n "$(tail -c 1 "$1")" ]; then printf '\n' >> "$1" || return 1; fi
+  printf '%s\n' "$2" >> "$1"
+}
+"#);
+    }
+    if usage.write_file_atomic {
+        // mktemp creates the temp file 0600, so the final mode is always set explicitly:
+        // the requested mode, or what a plain `>` redirect would produce under the umask.
+        s.push_str(r#"__sh2_write_file_atomic() {
+  __sh2_wa_name="${1##*/}"
+  case "$1" in */*) __sh2_wa_dir="${1%/*}"; [ -n "$__sh2_wa_dir" ] || __sh2_wa_dir=/ ;; *) __sh2_wa_dir=. ;; esac
+  if command -v mktemp >/dev/null 2>&1; then
+    __sh2_wa_tmp="$(mktemp "$__sh2_wa_dir/.$__sh2_wa_name.XXXXXX")" || return 1
+  else
+    __sh2_wa_tmp="$__sh2_wa_dir/.$__sh2_wa_name.$$"
+  fi
+  __sh2_wa_mode="$3"
+  if [ -z "$__sh2_wa_mode" ]; then __sh2_wa_mode="$(printf '%o' "$((0666 & ~$(umask)))")"; fi
+  if printf '%s' "$2" > "$__sh2_wa_tmp" && chmod "$__sh2_wa_mode" "$__sh2_wa_tmp" && mv -f "$__sh2_wa_tmp" "$1"; then return 0; fi
+  rm -f "$__sh2_wa_tmp"
+  return 1
+}
 "#);
     }
     if usage.lines {
@@ -329,6 +999,28 @@ __sh2_glob() {
         }
     }
 
+    if usage.render {
+        match target {
+            TargetShell::Bash => {
+                // Keys/values are handed to awk through the environment so that no
+                // escape processing (as with `awk -v`) is applied to map values.
+                s.push_str(r##"__sh2_render() {
+  local -n __sh2_rmap="$3"
+  local -n __sh2_rkeys="__sh2_keys_$3"
+  local -a __sh2_renv=()
+  local __sh2_rk __sh2_ri=0
+  for __sh2_rk in "${__sh2_rkeys[@]}"; do
+    __sh2_renv+=("__SH2_RK_${__sh2_ri}=${__sh2_rk}" "__SH2_RV_${__sh2_ri}=${__sh2_rmap[$__sh2_rk]}")
+    __sh2_ri=$((__sh2_ri + 1))
+  done
+  env ${__sh2_renv[@]+"${__sh2_renv[@]}"} __SH2_RN="$__sh2_ri" __SH2_RT="$1" __SH2_RE="$2" awk 'function esc(v) { if (mode == "html") { gsub(/&/, "\\&amp;", v); gsub(/</, "\\&lt;", v); gsub(/>/, "\\&gt;", v); gsub(/"/, "\\&quot;", v); gsub(/\047/, "\\&#39;", v); } else if (mode == "shell") { gsub(/\047/, "\047\\\\\047\047", v); v = "\047" v "\047"; } return v; } BEGIN { n = ENVIRON["__SH2_RN"] + 0; mode = ENVIRON["__SH2_RE"]; t = ENVIRON["__SH2_RT"]; for (i = 0; i < n; i++) m[ENVIRON["__SH2_RK_" i]] = ENVIRON["__SH2_RV_" i]; out = ""; while ((p = index(t, "{{")) > 0) { rest = substr(t, p + 2); q = index(rest, "}}"); if (q == 0) break; k = substr(rest, 1, q - 1); gsub(/^[ \t]+|[ \t]+$/, "", k); out = out substr(t, 1, p - 1); if (k in m) out = out esc(m[k]); else out = out "{{" substr(rest, 1, q - 1) "}}"; t = substr(rest, q + 2); } printf "%s", out t; }'
+}
+"##);
+            }
+            TargetShell::Posix => {} // Compile error handled in emit_val
+        }
+    }
+
     if usage.arg_dynamic {
         match target {
             TargetShell::Bash => {
@@ -343,7 +1035,7 @@ __sh2_glob() {
                 s.push_str("case \"$idx\" in (''|*[!0-9]*) printf 'Error: arg(): index must be an integer >= 1\\n' >&2; kill -TERM $$; exit 1;; esac; ");
                 s.push_str("if [ \"$idx\" -lt 1 ]; then printf 'Error: arg(): index must be an integer >= 1\\n' >&2; kill -TERM $$; exit 1; fi; ");
                 s.push_str("if [ \"$idx\" -gt \"$#\" ]; then printf 'Error: arg(): index %s out of range (argc=%s)\\n' \"$idx\" \"$#\" >&2; kill -TERM $$; exit 1; fi; ");
-                s.push_str("eval \"printf '%s' \\\"\\${$idx}\\\"\"; ");
+                s.push_str("shift $((idx - 1)); printf '%s' \"$1\"; ");
                 s.push_str("}\n");
             }
         }
@@ -400,8 +1092,8 @@ __sh2_glob() {
         s.push_str(r#"while true; do "#);
         s.push_str(r#"if [ "$__sh2_default" = "1" ]; then printf '%s [Y/n] ' "$__sh2_prompt" >&2; else printf '%s [y/N] ' "$__sh2_prompt" >&2; fi; "#);
         s.push_str(r#"if ! IFS= read -r __sh2_ans; then printf '%s' "$__sh2_default"; return 0; fi; "#);
-        s.push_str(r#"__sh2_ans_lc="$(printf '%s' "$__sh2_ans" | tr '[:upper:]' '[:lower:]')"; "#);
-        s.push_str(r#"case "$__sh2_ans_lc" in y|yes) printf '%s' '1'; return 0;; n|no) printf '%s' '0'; return 0;; '') printf '%s' "$__sh2_default"; return 0;; esac; "#);
+        // Case-insensitive match via bracket patterns: no subshell or external tr needed
+        s.push_str(r#"case "$__sh2_ans" in [Yy]|[Yy][Ee][Ss]) printf '%s' '1'; return 0;; [Nn]|[Nn][Oo]) printf '%s' '0'; return 0;; '') printf '%s' "$__sh2_default"; return 0;; esac; "#);
         s.push_str("done; }\n");
     }
 
@@ -1,8 +1,8 @@
-use super::helpers::sh_single_quote;
+use super::helpers::{is_boolean_val, sh_single_quote};
 use super::emit_prelude::is_prelude_helper;
 use super::TargetShell;
 use crate::error::CompileError;
-use crate::ir::Val;
+use crate::ir::{RenderEscape, Val};
 
 pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileError> {
     match v {
@@ -27,6 +27,14 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
                 _ => Ok(format!("\"$( __sh2_read_file {} )\"", path)),
             }
         }
+        Val::ReadFileOr { path, default } => {
+            let path = emit_word(path, target)?;
+            let default = emit_word(default, target)?;
+            match target {
+                TargetShell::Bash => Ok(format!("\"$( trap '' ERR; __sh2_read_file_or {} {} )\"", path, default)),
+                _ => Ok(format!("\"$( __sh2_read_file_or {} {} )\"", path, default)),
+            }
+        }
         Val::Lines(_) => {
              return Err(CompileError::unsupported(
                 "lines() is only valid in 'for' loops or 'let' assignment",
@@ -39,11 +47,15 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
                 target,
             ));
         }
+        Val::Lines0(_) => Err(CompileError::coded("E0303", "lines0()/split0() are only valid in 'for' loops or 'let' assignment").with_target(target)),
+        Val::Find { .. } => Err(CompileError::coded("E0303", "find() returns a list; use it in a 'for' loop or 'let' assignment").with_target(target)),
 
 
 
 
         Val::Home => Ok("\"$( __sh2_home )\"".to_string()),
+        Val::LastCommand => Ok("\"${__sh2_last_cmd:-}\"".to_string()),
+        Val::LastStderr => Ok("\"${__sh2_last_stderr:-}\"".to_string()),
         Val::PathJoin(args) => {
             let parts: Vec<String> = args.iter().map(|a| emit_word(a, target)).collect::<Result<_, _>>()?;
             Ok(format!("\"$( __sh2_path_join {} )\"", parts.join(" ")))
@@ -54,7 +66,7 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
         }
         Val::Capture { value, allow_fail } => {
             if *allow_fail {
-                return Err(CompileError::new("capture(..., allow_fail=true) is only allowed in 'let' assignment (e.g. let res = capture(...))").with_target(target));
+                return Err(CompileError::coded("E0303", "capture(..., allow_fail=true) is only allowed in 'let' assignment (e.g. let res = capture(...))").with_target(target));
             }
             emit_val(value, target)
         }
@@ -68,12 +80,13 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
                 .collect::<Result<_, CompileError>>()?;
             Ok(format!("\"$( {} )\"", seg_strs.join(" | ")))
         }
-        Val::Len(inner) => {
-            Ok(format!(
-                "\"$( printf \"%s\" {} | awk 'BEGIN{{l=0}} {{l=length($0)}} END{{print l}}' )\"",
-                emit_val(inner, target)?
-            ))
-        }
+        Val::Len(inner) => Ok(format!("\"$( __sh2_len {} )\"", emit_val(inner, target)?)),
+        Val::BytesLen(inner) => Ok(format!("\"$( __sh2_bytes_len {} )\"", emit_val(inner, target)?)),
+        Val::Awk { program, input } => Ok(format!(
+            "\"$( printf '%s' {} | awk {} )\"",
+            emit_val(input, target)?,
+            sh_single_quote(program)
+        )),
         Val::Arg(n) => Ok(format!("\"${}\"", n)),
         Val::ArgDynamic(index) => {
             let idx_str = emit_arg_index_word(index, target)?;
@@ -86,7 +99,9 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
             "\"$( __sh2_args_positionals {} )\"",
             emit_val(inner, target)?
         )),
-        Val::Index { list, index } => {
+        Val::Index { list, index, optional } => {
+            // `?[` reads a missing element as empty, even under `set -u`.
+            let fallback = if *optional { ":-" } else { "" };
             match &**list {
                 Val::ArgsFlags(_) => {
                     Ok(format!(
@@ -108,7 +123,7 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
                     }
                     match &**list {
                         Val::Var(name) => {
-                            Ok(format!("\"${{{}[{}]}}\"", name, emit_index_expr(index, target)?))
+                            Ok(format!("\"${{{}[{}]{}}}\"", name, emit_index_expr(index, target)?, fallback))
                         }
                         Val::List(elems) => {
                             let mut arr_str = String::new();
@@ -119,15 +134,17 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
                                 arr_str.push_str(&emit_word(elem, target)?);
                             }
                             Ok(format!(
-                                "\"$( arr=({}); idx=$(( {} )); printf \"%s\" \"${{arr[idx]}}\" )\"",
+                                "\"$( arr=({}); idx=$(( {} )); printf \"%s\" \"${{arr[idx]{}}}\" )\"",
                                 arr_str,
-                                emit_index_expr(index, target)?
+                                emit_index_expr(index, target)?,
+                                fallback
                             ))
                         }
                         Val::Args => {
                             Ok(format!(
-                                "\"$( arr=(\"$@\"); idx=$(( {} )); printf \"%s\" \"${{arr[idx]}}\" )\"",
-                                emit_index_expr(index, target)?
+                                "\"$( arr=(\"$@\"); idx=$(( {} )); printf \"%s\" \"${{arr[idx]{}}}\" )\"",
+                                emit_index_expr(index, target)?,
+                                fallback
                             ))
                         }
                         _ => Err(CompileError::internal("Index implemented only for variables and list literals", target)),
@@ -135,6 +152,16 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
                 }
             }
         }
+        Val::IfElse { then_val, else_val, .. } if is_boolean_val(then_val) && is_boolean_val(else_val) => Ok(format!(
+            "\"$( if {}; then printf \"%s\" \"true\"; else printf \"%s\" \"false\"; fi )\"",
+            emit_cond(v, target)?
+        )),
+        Val::IfElse { cond, then_val, else_val } => Ok(format!(
+            "\"$( if {}; then printf '%s' {}; else printf '%s' {}; fi )\"",
+            emit_cond(cond, target)?,
+            emit_val(then_val, target)?,
+            emit_val(else_val, target)?
+        )),
         Val::Join { list, sep } => {
             if target == TargetShell::Posix {
                 return Err(CompileError::unsupported("List join is not supported in POSIX sh target", target));
@@ -171,6 +198,9 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
             }
         }
         Val::Count(inner) => match &**inner {
+            Val::List(elems) if elems.iter().any(|e| matches!(e, Val::Spread(_))) => {
+                Ok(format!("\"$(( {} ))\"", spread_count(elems, target)?))
+            }
             Val::List(elems) => match target {
                 TargetShell::Bash => Ok(format!("\"{}\"", elems.len())),
                 TargetShell::Posix => Err(CompileError::unsupported("List literals not supported in POSIX target", target)),
@@ -224,16 +254,14 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
                 emit_cond(inner, target)?
             ))
         }
-        Val::Input(prompt) => match target {
-            TargetShell::Bash => {
-                let p = emit_val(prompt, target)?;
-                Ok(format!(
-                    "\"$( printf '%s' {} >&2; IFS= read -r __sh2_in; printf '%s' \"$__sh2_in\" )\"",
-                    p
-                ))
-            }
-            TargetShell::Posix => Err(CompileError::unsupported("input(...) is not supported in POSIX sh target", target)),
-        },
+        Val::Input(prompt) => {
+            // Plain `read -r` inside a command substitution works on both targets
+            let p = emit_val(prompt, target)?;
+            Ok(format!(
+                "\"$( printf '%s' {} >&2; IFS= read -r __sh2_in; printf '%s' \"$__sh2_in\" )\"",
+                p
+            ))
+        }
         Val::Args => Err(CompileError::internal("args cannot be embedded/concatenated inside a word", target)),
         Val::Call { name, args } => {
             let (func_name, needs_prefix) = if name == "default" {
@@ -260,6 +288,12 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
                 target,
             ));
         }
+        Val::InputList(_) => Err(CompileError::unsupported(
+            "input_list() can only be used in 'let' assignments",
+            target,
+        )),
+        Val::RawArg(_) => Err(CompileError::coded("E0303", "raw_arg() can only be used as a direct argument of run(...) or sudo(...)")
+        .with_target(target)),
         Val::Spawn { .. } => {
             return Err(CompileError::unsupported(
                 "spawn() can only be used in 'let' assignments",
@@ -278,22 +312,45 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
                 target,
             ));
         }
+        Val::WaitAny { .. } => {
+            return Err(CompileError::unsupported(
+                "wait_any() can only be used in 'let' assignments",
+                target,
+            ));
+        }
 
         Val::JsonKv(blob) => {
             Ok(format!("\"$( __sh2_json_kv {} )\"", emit_word(blob, target)?))
         }
-        Val::Matches(..) | Val::StartsWith { .. } => {
+        Val::Matches(..) | Val::StartsWith { .. } | Val::Have(..) | Val::ServiceRunning(..) | Val::WaitForPort { .. } => {
             Ok(format!(
                 "\"$( if {}; then printf \"%s\" \"true\"; else printf \"%s\" \"false\"; fi )\"",
                 emit_cond(v, target)?
             ))
         }
-        Val::MapIndex { map, key } => {
+        Val::MapIndex { map, key, optional } => {
             if target == TargetShell::Posix {
                 return Err(CompileError::unsupported("map/dict is only supported in Bash target", target));
             }
             let escaped_key = sh_single_quote(key);
-            Ok(format!("\"${{{}[{}]}}\"", map, escaped_key))
+            let fallback = if *optional { ":-" } else { "" };
+            Ok(format!("\"${{{}[{}]{}}}\"", map, escaped_key, fallback))
+        }
+        Val::Render { template, map, escape } => {
+            if target == TargetShell::Posix {
+                return Err(CompileError::unsupported("map/dict is only supported in Bash target", target));
+            }
+            let mode = match escape {
+                RenderEscape::None => "none",
+                RenderEscape::Html => "html",
+                RenderEscape::Shell => "shell",
+            };
+            Ok(format!(
+                "\"$( __sh2_render {} {} {} )\"",
+                emit_word(template, target)?,
+                sh_single_quote(mode),
+                sh_single_quote(map)
+            ))
         }
         Val::MapLiteral(_) => Err(CompileError::unsupported("Map literal is only allowed in 'let' assignment", target)),
         Val::Compare { .. }
@@ -309,18 +366,30 @@ pub(super) fn emit_val(v: &Val, target: TargetShell) -> Result<String, CompileEr
         | Val::IsWritable(..)
         | Val::IsNonEmpty(..)
         | Val::List(..)
+        | Val::Spread(..)
         | Val::Split { .. }
         | Val::ContainsLine { .. }
-        | Val::Confirm { .. } => Err(CompileError::new("Cannot emit boolean/list value as string").with_target(target)),
-        Val::BoolVar(name) => Ok(format!("\"${}\"", name)),
+        | Val::Confirm { .. }
+        | Val::BoolCall { .. } => Err(CompileError::coded("E0304", "Cannot emit boolean/list value as string").with_target(target)),
+        Val::BoolVar(name) | Val::CalleeVar(name) => Ok(format!("\"${}\"", name)),
     }
 }
 
 pub(super) fn emit_word(v: &Val, target: TargetShell) -> Result<String, CompileError> {
-    if let Val::Args = v {
-        return Ok("\"$@\"".to_string());
+    match v {
+        Val::Args => Ok("\"$@\"".to_string()),
+        Val::Spread(list) => match &**list {
+            Val::Args => Ok("\"$@\"".to_string()),
+            Val::Var(name) if target == TargetShell::Bash => Ok(format!("\"${{{}[@]}}\"", name)),
+            _ => Err(CompileError::unsupported("spreading a list variable is not supported in POSIX sh, which has no arrays", target)),
+        },
+        // Deliberately unquoted: the shell splits the value on IFS and expands globs.
+        Val::RawArg(inner) => match &**inner {
+            Val::Var(name) => Ok(format!("${}", name)),
+            other => Ok(format!("$(printf '%s' {})", emit_val(other, target)?)),
+        },
+        _ => emit_val(v, target),
     }
-    emit_val(v, target)
 }
 
 pub(super) fn emit_cond(v: &Val, target: TargetShell) -> Result<String, CompileError> {
@@ -353,6 +422,12 @@ pub(super) fn emit_cond(v: &Val, target: TargetShell) -> Result<String, CompileE
                 ))
             }
         }
+        Val::IfElse { cond, then_val, else_val } => Ok(format!(
+            "{{ if {}; then {}; else {}; fi; }}",
+            emit_cond(cond, target)?,
+            emit_cond(then_val, target)?,
+            emit_cond(else_val, target)?
+        )),
         Val::And(left, right) => {
             let mut l_str = emit_cond(left, target)?;
             let mut r_str = emit_cond(right, target)?;
@@ -472,6 +547,22 @@ pub(super) fn emit_cond(v: &Val, target: TargetShell) -> Result<String, CompileE
                 emit_val(prefix, target)?
             ))
         }
+        Val::Have(cmd) => Ok(format!("__sh2_have {}", emit_word(cmd, target)?)),
+        Val::BoolCall { name, args } => {
+            let mut words = vec![name.clone()];
+            for a in args {
+                words.push(emit_word(a, target)?);
+            }
+            Ok(words.join(" "))
+        }
+        Val::ServiceRunning(pidfile) => Ok(format!("__sh2_service_running {}", emit_word(pidfile, target)?)),
+        Val::WaitForPort { host, port, timeout, interval } => Ok(format!(
+            "__sh2_wait_for_port {} {} {} {} 2>/dev/null",
+            emit_word(host, target)?,
+            emit_word(port, target)?,
+            emit_word(timeout, target)?,
+            emit_word(interval, target)?
+        )),
         Val::BoolVar(name) => {
             // Boolean variable: check if equals "true"
             Ok(format!("[ \"${}\" = \"true\" ]", name))
@@ -544,14 +635,13 @@ pub(super) fn emit_arith_expr(v: &Val, target: TargetShell) -> Result<String, Co
         }
         Val::Command(args) => emit_cmdsub_raw(args, target),
         Val::CommandPipe(segments) => emit_cmdsub_pipe_raw(segments, target),
-        Val::Len(inner) => {
-            // Raw command substitution: emits $( ... )
-            Ok(format!(
-                "$( printf \"%s\" {} | awk '{{ print length($0) }}' )",
-                emit_val(inner, target)?
-            ))
-        }
+        // Raw command substitutions: emit $( ... )
+        Val::Len(inner) => Ok(format!("$( __sh2_len {} )", emit_val(inner, target)?)),
+        Val::BytesLen(inner) => Ok(format!("$( __sh2_bytes_len {} )", emit_val(inner, target)?)),
         Val::Count(inner) => match &**inner {
+            Val::List(elems) if elems.iter().any(|e| matches!(e, Val::Spread(_))) => {
+                Ok(format!("({})", spread_count(elems, target)?))
+            }
             Val::List(elems) => match target {
                 TargetShell::Bash => Ok(elems.len().to_string()),
                 TargetShell::Posix => Err(CompileError::unsupported("List literals not supported in POSIX target", target)),
@@ -568,6 +658,21 @@ pub(super) fn emit_arith_expr(v: &Val, target: TargetShell) -> Result<String, Co
 }
 
 
+/// Arithmetic sum counting a list literal whose items include `...` spreads.
+fn spread_count(elems: &[Val], target: TargetShell) -> Result<String, CompileError> {
+    let fixed = elems.iter().filter(|e| !matches!(e, Val::Spread(_))).count();
+    let mut terms = vec![fixed.to_string()];
+    for e in elems {
+        if let Val::Spread(inner) = e {
+            match &**inner {
+                Val::Args => terms.push("$#".to_string()),
+                Val::Var(name) if target == TargetShell::Bash => terms.push(format!("${{#{}[@]}}", name)),
+                _ => return Err(CompileError::unsupported("spreading a list variable is not supported in POSIX sh, which has no arrays", target)),
+            }
+        }
+    }
+    Ok(terms.join(" + "))
+}
 
 fn emit_arg_index_word(v: &Val, target: TargetShell) -> Result<String, CompileError> {
     // Both Bash and POSIX support "$i" and "$((...))"
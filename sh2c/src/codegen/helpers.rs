@@ -1,12 +1,16 @@
 use crate::ir::Val;
 
 /// Escape single quotes within a string literal for safe shell quoting.
-/// Replaces ' with '\'' and wraps in '...'
+/// Replaces ' with '\'' and wraps in '...'. Control characters other than
+/// newline and tab are spliced in with `printf` so the script itself stays
+/// plain text.
 pub(super) fn sh_single_quote(s: &str) -> String {
     let mut out = String::from("'");
     for ch in s.chars() {
         if ch == '\'' {
             out.push_str("'\\''");
+        } else if ch.is_ascii_control() && ch != '\n' && ch != '\t' {
+            out.push_str(&format!("'\"$(printf '\\{:03o}')\"'", ch as u32));
         } else {
             out.push(ch);
         }
@@ -15,6 +19,16 @@ pub(super) fn sh_single_quote(s: &str) -> String {
     out
 }
 
+/// Values whose assignment cannot fail: no command substitution, arithmetic
+/// or helper call, so `$?` after `name=value` is always 0.
+pub(super) fn is_static_val(v: &Val) -> bool {
+    match v {
+        Val::Literal(_) | Val::Number(_) | Val::Var(_) => true,
+        Val::Concat(l, r) => is_static_val(l) && is_static_val(r),
+        _ => false,
+    }
+}
+
 /// Check if a Val represents a boolean expression (comparison, logical op, predicate, etc.)
 /// These require special handling when assigned to variables.
 pub(super) fn is_boolean_val(v: &Val) -> bool {
@@ -35,11 +49,15 @@ pub(super) fn is_boolean_val(v: &Val) -> bool {
             | Val::IsNonEmpty(_)
             | Val::Matches(_, _)
             | Val::StartsWith { .. }
+            | Val::Have(_)
+            | Val::ServiceRunning(_)
+            | Val::WaitForPort { .. }
             | Val::ContainsList { .. }
             | Val::ContainsSubstring { .. }
             | Val::ContainsLine { .. }
             | Val::Confirm { .. }
-    )
+            | Val::BoolCall { .. }
+    ) || matches!(v, Val::IfElse { then_val, else_val, .. } if is_boolean_val(then_val) && is_boolean_val(else_val))
 }
 
 pub(super) fn is_boolean_expr(v: &Val) -> bool {
@@ -59,23 +77,46 @@ pub(super) fn is_boolean_expr(v: &Val) -> bool {
             | Val::IsNonEmpty(..)
             | Val::Bool(..)
             | Val::Matches(..)
-    )
+            | Val::Have(..)
+            | Val::ServiceRunning(..)
+            | Val::WaitForPort { .. }
+            | Val::BoolCall { .. }
+    ) || matches!(v, Val::IfElse { then_val, else_val, .. } if is_boolean_expr(then_val) && is_boolean_expr(else_val))
 }
 
+/// `*`, `?` and `[...]` classes stay active; everything else is quoted. Classes were
+/// validated by the parser and are copied through, with `[^` spelled `[!` for POSIX;
+/// an unclosed `[` is matched literally.
 pub(super) fn emit_case_glob_pattern(glob: &str) -> String {
     let mut out = String::new();
     let mut literal_buf = String::new();
 
-    for c in glob.chars() {
-        if c == '*' || c == '?' {
+    let mut rest = glob;
+    while let Some(c) = rest.chars().next() {
+        let class_len = if c == '[' { glob_class_len(rest) } else { None };
+        if c == '*' || c == '?' || class_len.is_some() {
             if !literal_buf.is_empty() {
                 out.push_str(&sh_single_quote(&literal_buf));
                 literal_buf.clear();
             }
-            out.push(c);
+            match class_len {
+                Some(len) => {
+                    let class = &rest[..len];
+                    let class = match class.strip_prefix("[^") {
+                        Some(tail) => format!("[!{}", tail),
+                        None => class.to_string(),
+                    };
+                    // Quoted members stay literal and keep `[*]` apart from array syntax.
+                    out.push_str(&class.replace('*', "'*'").replace('?', "'?'"));
+                    rest = &rest[len..];
+                    continue;
+                }
+                None => out.push(c),
+            }
         } else {
             literal_buf.push(c);
         }
+        rest = &rest[c.len_utf8()..];
     }
     if !literal_buf.is_empty() {
         out.push_str(&sh_single_quote(&literal_buf));
@@ -86,3 +127,25 @@ pub(super) fn emit_case_glob_pattern(glob: &str) -> String {
     }
     out
 }
+
+/// Byte length of the `[...]` class at the start of `s`, including nested
+/// `[:name:]` items, if it is closed.
+fn glob_class_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+    if matches!(bytes.get(i), Some(b'!' | b'^')) {
+        i += 1;
+    }
+    let body_start = i;
+    while i < bytes.len() {
+        match bytes[i] {
+            b']' if i > body_start => return Some(i + 1),
+            b'[' if bytes.get(i + 1) == Some(&b':') => {
+                let close = s[i + 2..].find(":]")?;
+                i += close + 4;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
@@ -0,0 +1,343 @@
+//! `--minify`: shrink a generated script without changing what it does.
+//!
+//! Full-line comments and blank lines are dropped (never inside quotes or
+//! here-documents), and compiler-internal `__sh2_*` names are shortened.
+//! Only names the script uses as code are renamed: text inside quotes and
+//! here-documents is left alone unless the quoted word is exactly such a name
+//! (a list passed by name) or is a `trap` action, so user strings that happen
+//! to contain `__sh2_` come through unchanged.
+
+use std::collections::{HashMap, HashSet};
+
+const INTERNAL_PREFIX: &str = "__sh2_";
+
+pub fn minify_script(script: &str, keep: &HashSet<String>) -> String {
+    rename_internal(&strip_lines(script), keep)
+}
+
+/// Every internal name in `text`; pass a runtime library's names as `keep` so
+/// a minified script still matches the helpers it sources.
+pub fn internal_names(text: &str) -> HashSet<String> {
+    internal_tokens(text).into_iter().map(|(_, name)| name.to_string()).collect()
+}
+
+/// An open quoting context; an empty stack means plain, unquoted code.
+#[derive(Clone, Copy, PartialEq)]
+enum Quote {
+    Single,
+    /// `$'...'`, where backslash escapes apply.
+    Ansi,
+    Double,
+    /// `'...'` handed to `trap`, which runs it as code later.
+    Action,
+    /// `$(...)`, with the number of `(` opened inside it and not yet closed.
+    Subst(usize),
+}
+
+/// How an `__sh2_*` token is used where it appears.
+#[derive(Clone, Copy, PartialEq)]
+enum Use {
+    /// Unquoted, or expanded with `$` inside double quotes.
+    Code,
+    /// The whole quoted word, e.g. a list passed by name: `"__sh2_tmp_list_1"`.
+    Word,
+    /// Part of quoted or here-document text.
+    Text,
+}
+
+struct HereDoc {
+    delim: String,
+    strip_tabs: bool,
+    /// Unquoted delimiter: the body still expands `$name`.
+    expands: bool,
+}
+
+fn strip_lines(script: &str) -> String {
+    let mut out = String::with_capacity(script.len());
+    let mut quotes = Vec::new();
+    let mut heredoc: Option<HereDoc> = None;
+
+    for (i, line) in script.lines().enumerate() {
+        if let Some(doc) = &heredoc {
+            let body = if doc.strip_tabs { line.trim_start_matches('\t') } else { line };
+            if body == doc.delim {
+                heredoc = None;
+            }
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+        if quotes.is_empty() {
+            let trimmed = line.trim_start();
+            let shebang = i == 0 && trimmed.starts_with("#!");
+            if trimmed.is_empty() || (trimmed.starts_with('#') && !shebang) {
+                continue;
+            }
+        }
+        out.push_str(line);
+        out.push('\n');
+        heredoc = scan_line(line, &mut quotes, |_, _| {});
+    }
+    out
+}
+
+/// Tracks quoting across `line`, reporting where each `__sh2_*` token starts
+/// and how it is used, and returns a here-document the line opens, if any.
+fn scan_line(line: &str, quotes: &mut Vec<Quote>, mut on_token: impl FnMut(usize, Use)) -> Option<HereDoc> {
+    let bytes = line.as_bytes();
+    let mut heredoc = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c == b'_' && line[i..].starts_with(INTERNAL_PREFIX) && (i == 0 || !is_ident(bytes[i - 1])) {
+            on_token(i, token_use(line, i, quotes.last().copied()));
+        }
+        match quotes.last().copied() {
+            Some(Quote::Single | Quote::Action) => {
+                if c == b'\'' {
+                    quotes.pop();
+                }
+            }
+            Some(q @ (Quote::Ansi | Quote::Double)) => {
+                let close = if q == Quote::Ansi { b'\'' } else { b'"' };
+                if c == b'\\' {
+                    i += 1;
+                } else if c == close {
+                    quotes.pop();
+                } else if q == Quote::Double && c == b'$' && bytes.get(i + 1) == Some(&b'(') {
+                    quotes.push(Quote::Subst(0));
+                    i += 1;
+                }
+            }
+            None | Some(Quote::Subst(_)) => match c {
+                b'\\' => i += 1,
+                b'$' if bytes.get(i + 1) == Some(&b'(') => {
+                    quotes.push(Quote::Subst(0));
+                    i += 1;
+                }
+                b'(' => {
+                    if let Some(Quote::Subst(depth)) = quotes.last_mut() {
+                        *depth += 1;
+                    }
+                }
+                b')' => match quotes.last_mut() {
+                    Some(Quote::Subst(0)) => {
+                        quotes.pop();
+                    }
+                    Some(Quote::Subst(depth)) => *depth -= 1,
+                    _ => {}
+                },
+                b'\'' if i > 0 && bytes[i - 1] == b'$' => quotes.push(Quote::Ansi),
+                b'\'' if is_trap_action(&line[..i]) => quotes.push(Quote::Action),
+                b'\'' => quotes.push(Quote::Single),
+                b'"' => quotes.push(Quote::Double),
+                b'#' if i == 0 || bytes[i - 1].is_ascii_whitespace() || bytes[i - 1] == b';' => break,
+                // `<<<` is a here-string, not a here-document.
+                b'<' if bytes.get(i + 1) == Some(&b'<') && bytes.get(i + 2) == Some(&b'<') => i += 2,
+                b'<' if bytes.get(i + 1) == Some(&b'<') => {
+                    let (doc, next) = heredoc_start(line, i + 2);
+                    if doc.is_some() {
+                        heredoc = doc;
+                    }
+                    i = next;
+                    continue;
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+    heredoc
+}
+
+/// Whether a quote opened after `before` starts the action of a `trap`.
+fn is_trap_action(before: &str) -> bool {
+    let Some(rest) = before.trim_end().strip_suffix("trap") else { return false };
+    before.len() > rest.len() + 4 && !rest.ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// How the `__sh2_*` token at `start` is used, given the innermost quoting.
+fn token_use(line: &str, start: usize, quote: Option<Quote>) -> Use {
+    let bytes = line.as_bytes();
+    let end = start + bytes[start..].iter().take_while(|&&b| is_ident(b)).count();
+    let before = &line[..start];
+    let close = match quote {
+        None | Some(Quote::Subst(_) | Quote::Action) => return Use::Code,
+        Some(Quote::Double) if before.ends_with('$') || before.ends_with("${") => return Use::Code,
+        Some(Quote::Double) => b'"',
+        Some(Quote::Single | Quote::Ansi) => b'\'',
+    };
+    if before.as_bytes().last() == Some(&close) && bytes.get(end) == Some(&close) {
+        Use::Word
+    } else {
+        Use::Text
+    }
+}
+
+/// Parses the delimiter after `<<` at `start`; returns it and where to resume.
+fn heredoc_start(line: &str, start: usize) -> (Option<HereDoc>, usize) {
+    let rest = &line[start..];
+    let strip_tabs = rest.starts_with('-');
+    let rest = rest.trim_start_matches('-');
+    let lead = rest.len() - rest.trim_start().len();
+    let rest = rest.trim_start();
+    let offset = line.len() - rest.len();
+    match rest.chars().next() {
+        Some(q @ ('\'' | '"')) => match rest[1..].find(q) {
+            Some(end) => {
+                let delim = rest[1..1 + end].to_string();
+                (Some(HereDoc { delim, strip_tabs, expands: false }), offset + end + 2)
+            }
+            None => (None, start + lead),
+        },
+        _ => {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if len == 0 {
+                return (None, start);
+            }
+            let delim = rest[..len].to_string();
+            (Some(HereDoc { delim, strip_tabs, expands: true }), offset + len)
+        }
+    }
+}
+
+fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Byte offsets and text of every `__sh2_*` token that starts a word.
+fn internal_tokens(text: &str) -> Vec<(usize, &str)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut from = 0;
+    while let Some(pos) = text[from..].find(INTERNAL_PREFIX) {
+        let start = from + pos;
+        let mut end = start + INTERNAL_PREFIX.len();
+        while end < bytes.len() && is_ident(bytes[end]) {
+            end += 1;
+        }
+        if (start == 0 || !is_ident(bytes[start - 1])) && end > start + INTERNAL_PREFIX.len() {
+            tokens.push((start, &text[start..end]));
+        }
+        from = end;
+    }
+    tokens
+}
+
+/// Every `__sh2_*` token with its byte offset and how it is used.
+fn internal_uses(text: &str) -> Vec<(usize, &str, Use)> {
+    let mut uses = Vec::new();
+    let mut quotes = Vec::new();
+    let mut heredoc: Option<HereDoc> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let at = offset;
+        offset += line.len();
+        let token = |start: usize| {
+            let len = line[start..].bytes().take_while(|&b| is_ident(b)).count();
+            &line[start..start + len]
+        };
+        if let Some(doc) = &heredoc {
+            let content = line.trim_end_matches('\n');
+            let body = if doc.strip_tabs { content.trim_start_matches('\t') } else { content };
+            if body == doc.delim {
+                heredoc = None;
+                continue;
+            }
+            for (start, name) in internal_tokens(line) {
+                let expanded = line[..start].ends_with('$') || line[..start].ends_with("${");
+                let how = if doc.expands && expanded { Use::Code } else { Use::Text };
+                uses.push((at + start, name, how));
+            }
+            continue;
+        }
+        heredoc = scan_line(line, &mut quotes, |start, how| uses.push((at + start, token(start), how)));
+    }
+    uses.retain(|(_, name, _)| name.len() > INTERNAL_PREFIX.len());
+    uses
+}
+
+/// Picks a short prefix that no existing word in the script already uses.
+fn short_prefix(text: &str) -> String {
+    for letter in "smzqxkjv".chars() {
+        let prefix = format!("__{}", letter);
+        let taken = text.match_indices(&prefix).any(|(at, _)| {
+            let before_ok = at == 0 || !is_ident(text.as_bytes()[at - 1]);
+            before_ok && text[at + prefix.len()..].starts_with(|c: char| c.is_ascii_digit())
+        });
+        if !taken {
+            return prefix;
+        }
+    }
+    // Eight letters all taken is implausible; fall back to something longer.
+    "__sh2m".to_string()
+}
+
+fn rename_internal(text: &str, keep: &HashSet<String>) -> String {
+    let mut tokens = internal_uses(text);
+    // A name seen only inside quoted text is the user's, not the compiler's.
+    let code: HashSet<&str> = tokens.iter().filter(|t| t.2 == Use::Code).map(|t| t.1).collect();
+    tokens.retain(|&(_, name, how)| how != Use::Text && code.contains(name));
+
+    // Most frequent names get the shortest replacements; ties keep first-use order.
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (i, (_, name, _)) in tokens.iter().enumerate() {
+        if !keep.contains(*name) {
+            counts.entry(name).or_insert((0, i)).0 += 1;
+        }
+    }
+    let mut names: Vec<(&str, (usize, usize))> = counts.into_iter().collect();
+    names.sort_by_key(|&(_, (count, first))| (std::cmp::Reverse(count), first));
+
+    let prefix = short_prefix(text);
+    let renames: HashMap<&str, String> = names
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| (*name, format!("{}{}", prefix, i)))
+        .collect();
+
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, name, _) in tokens {
+        if let Some(short) = renames.get(name) {
+            out.push_str(&text[last..start]);
+            out.push_str(short);
+            last = start + name.len();
+        }
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_quoted_and_heredoc_lines() {
+        let script = "#!/bin/sh\n# note\n\nx='a\n\n# not a comment'\ncat <<'EOF'\n# body\n\nEOF\necho ok # trailing\n";
+        let out = strip_lines(script);
+        assert_eq!(out, "#!/bin/sh\nx='a\n\n# not a comment'\ncat <<'EOF'\n# body\n\nEOF\necho ok # trailing\n");
+    }
+
+    #[test]
+    fn renames_internal_names_consistently() {
+        let keep: HashSet<String> = ["__sh2_check".to_string()].into_iter().collect();
+        let out = rename_internal("__sh2_s=1; __sh2_check \"$__sh2_s\"; my__sh2_x=2; __s0=3\n", &keep);
+        assert_eq!(out, "__m0=1; __sh2_check \"$__m0\"; my__sh2_x=2; __s0=3\n");
+    }
+
+    #[test]
+    fn leaves_user_text_alone() {
+        let script = "__sh2_s=1\nprintf '%s\\n' 'keep __sh2_s here' \"__sh2_s=$__sh2_s ${__sh2_s:-}\" '__sh2_other'\n\
+                      trap '__sh2_s; echo \"$__sh2_s\"' EXIT\nx=\"$( __sh2_s 'a __sh2_s' \"__sh2_s\" )\"\ncat <<'EOF'\n$__sh2_s\nEOF\n";
+        let out = rename_internal(script, &HashSet::new());
+        assert_eq!(
+            out,
+            "__s0=1\nprintf '%s\\n' 'keep __sh2_s here' \"__sh2_s=$__s0 ${__s0:-}\" '__sh2_other'\n\
+             trap '__s0; echo \"$__s0\"' EXIT\nx=\"$( __s0 'a __sh2_s' \"__s0\" )\"\ncat <<'EOF'\n$__sh2_s\nEOF\n"
+        );
+    }
+}
@@ -1,22 +1,31 @@
 pub mod posix_lint;
 pub use posix_lint::{PosixLint, PosixLintKind, lint_script, render_lints};
 
+pub mod minify;
+
+pub mod size;
+
 pub(crate) mod helpers;
 
 use crate::error::CompileError;
 use crate::ir::Function;
 pub use crate::target::TargetShell;
+use std::fmt;
+use std::io;
+
+mod builder;
+use self::builder::{ScriptBuilder, emitln};
 
 mod emit_prelude;
-use self::emit_prelude::emit_prelude;
+use self::emit_prelude::{emit_prelude, emit_prelude_setup, PreludePart};
 
 mod emit_val;
 
 mod emit_cmd;
-use self::emit_cmd::emit_cmd;
+use self::emit_cmd::emit_cmds;
 
 mod scan_usage;
-use self::scan_usage::scan_usage;
+use self::scan_usage::{scan_usage, scan_usage_traced};
 use self::scan_usage::PreludeUsage;
 
 #[derive(Clone, Debug, Copy)]
@@ -45,58 +54,372 @@ fn shebang(target: TargetShell) -> &'static str {
 
 
 
+/// Version tag shared by a runtime library and the scripts that source it. Helpers
+/// change freely between compiler releases, so anything but an exact match is refused.
+pub fn runtime_lib_version(target: TargetShell) -> String {
+    format!("sh2c {} ({})", env!("CARGO_PKG_VERSION"), target)
+}
+
+/// The shared runtime library written by `--runtime-lib`: every prelude helper,
+/// without the traps and shell options individual scripts set up themselves.
+pub fn emit_runtime_lib(target: TargetShell) -> String {
+    let mut out = format!(
+        "# sh2 runtime library, generated by sh2c {}. Sourced by scripts compiled with --runtime-lib; do not edit.\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    if target == TargetShell::Bash {
+        out.push_str("# shellcheck shell=bash\n");
+    }
+    out.push_str(&format!("__sh2_rt_version={}\n", helpers::sh_single_quote(&runtime_lib_version(target))));
+    out.push_str(&emit_prelude(target, &PreludeUsage::all(), PreludePart::Runtime));
+    out
+}
+
+/// Sources the runtime library and refuses to run against a different version.
+/// Relative paths are resolved against the script's own directory.
+fn emit_runtime_lib_loader(target: TargetShell, lib_path: &str) -> String {
+    let version = helpers::sh_single_quote(&runtime_lib_version(target));
+    format!(
+        "__sh2_rt={path}\n\
+         case \"$__sh2_rt\" in /*) ;; *) __sh2_rt=\"$(dirname -- \"$0\")/$__sh2_rt\" ;; esac\n\
+         if [ ! -r \"$__sh2_rt\" ]; then printf 'Error: sh2 runtime library not found: %s\\n' \"$__sh2_rt\" >&2; exit 127; fi\n\
+         . \"$__sh2_rt\"\n\
+         if [ \"${{__sh2_rt_version:-}}\" != {version} ]; then printf 'Error: %s is sh2 runtime %s, this script needs %s\\n' \"$__sh2_rt\" \"${{__sh2_rt_version:-unknown}}\" {version} >&2; exit 127; fi\n",
+        path = helpers::sh_single_quote(lib_path),
+        version = version,
+    )
+}
+
 pub fn emit_with_options(funcs: &[Function], opts: CodegenOptions) -> Result<String, CompileError> {
-    let usage = scan_usage(funcs, opts.include_diagnostics);
-    let mut out = String::new();
+    emit_script(funcs, opts, None)
+}
 
-    // Emit shebang as the very first line
-    out.push_str(shebang(opts.target));
-    out.push('\n');
+/// Like [`emit_with_options_checked`], but the prelude is replaced by a loader that
+/// sources the runtime library at `lib_path` (see [`emit_runtime_lib`]).
+pub fn emit_with_runtime_lib(funcs: &[Function], opts: CodegenOptions, lib_path: &str) -> Result<String, CompileError> {
+    let out = emit_script(funcs, opts, Some(lib_path))?;
+    check_posix(out, opts)
+}
+
+fn emit_script(funcs: &[Function], opts: CodegenOptions, runtime_lib: Option<&str>) -> Result<String, CompileError> {
+    // Generated code runs a few dozen bytes per IR command; reserving up front
+    // keeps large programs from repeatedly regrowing the buffer.
+    let cmd_count: usize = funcs.iter().map(|f| f.commands.len()).sum();
+    let mut out = String::with_capacity(64 * cmd_count + 4096);
+    emit_chunks(funcs, opts, runtime_lib, |chunk| {
+        out.push_str(&chunk);
+        Ok(())
+    })?;
+    Ok(out)
+}
 
+/// Failure while streaming a script with [`emit_to_writer`].
+#[derive(Debug)]
+pub enum EmitError {
+    Compile(CompileError),
+    Io(io::Error),
+}
+
+impl From<CompileError> for EmitError {
+    fn from(e: CompileError) -> Self {
+        EmitError::Compile(e)
+    }
+}
+
+impl From<io::Error> for EmitError {
+    fn from(e: io::Error) -> Self {
+        EmitError::Io(e)
+    }
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmitError::Compile(e) => e.fmt(f),
+            EmitError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Writes the same script as [`emit_with_options_checked`] to `w`, one function at a
+/// time, so only the prelude and the function being generated are held in memory.
+///
+/// POSIX lints run on each piece before it is written. A failure can still leave
+/// the pieces before it in `w`; write to a temporary file when that matters.
+pub fn emit_to_writer<W: io::Write>(w: &mut W, funcs: &[Function], opts: CodegenOptions) -> Result<(), EmitError> {
+    emit_chunks(funcs, opts, None, |chunk| {
+        let chunk = check_posix(chunk, opts)?;
+        w.write_all(chunk.as_bytes())?;
+        Ok(())
+    })
+}
+
+/// Generates the script in pieces (shebang and prelude, then each function, then
+/// the call to `main`) and hands each to `sink` as soon as it is complete.
+fn emit_chunks<E: From<CompileError>>(
+    funcs: &[Function],
+    opts: CodegenOptions,
+    runtime_lib: Option<&str>,
+    mut sink: impl FnMut(String) -> Result<(), E>,
+) -> Result<(), E> {
+    let usage = scan_usage(funcs, opts.include_diagnostics);
     // Usage-aware prelude emission
-    out.push_str(&emit_prelude(opts.target, &usage));
-    let mut ctx = CodegenContext::default();
+    let prelude = match runtime_lib {
+        None => emit_prelude(opts.target, &usage, PreludePart::Inline),
+        Some(path) => emit_runtime_lib_loader(opts.target, path) + &emit_prelude_setup(opts.target, &usage),
+    };
+    let mut out = ScriptBuilder::with_capacity(prelude.len() + 64);
+
+    // Emit shebang as the very first line
+    out.line(shebang(opts.target));
+    out.push_str(&prelude);
+    sink(out.finish())?;
+    let mut ctx = CodegenContext {
+        track_command: usage.last_command,
+        track_stderr: usage.last_stderr,
+        traced_functions: if usage.call_stack { funcs.iter().map(|f| f.name.clone()).collect() } else { HashSet::new() },
+        ..Default::default()
+    };
 
     for (i, f) in funcs.iter().enumerate() {
+        let mut out = ScriptBuilder::with_capacity(64 * f.commands.len() + 64);
         if i > 0 {
             out.push('\n');
         }
-        out.push_str(&format!("{}() {{\n", f.name));
-        if usage.loc && opts.target == TargetShell::Bash {
-            out.push_str("  local __sh2_loc=\"\"\n");
+        emit_function(&mut out, f, opts, usage.loc, None, &mut ctx)?;
+        sink(out.finish())?;
+    }
+
+    let mut out = String::new();
+    if usage.parse_args {
+        out.push_str("\n__sh2_parsed_args=\"$(__sh2_parse_args \"$@\")\"\n");
+    }
+    if usage.debug {
+        // Run to the first breakpoint if there is one, otherwise stop before the
+        // first statement.
+        if usage.breakpoint {
+            out.push_str("__sh2_dbg_mode=run\n");
+        } else {
+            out.push_str("if [ -n \"${SH2_BREAK:-}\" ]; then __sh2_dbg_mode=run; else __sh2_dbg_mode=step; fi\n");
+        }
+    }
+    let entry = funcs.iter().find(|f| f.entry).map_or("main", |f| f.name.as_str());
+    out.push_str(&format!("__sh2_status=0\n{} \"$@\"\n", entry));
+    sink(out)
+}
+
+fn emit_function(
+    out: &mut ScriptBuilder,
+    f: &Function,
+    opts: CodegenOptions,
+    has_loc: bool,
+    lazy_helpers: Option<&[&str]>,
+    ctx: &mut CodegenContext,
+) -> Result<(), CompileError> {
+    if f.cache {
+        // The body is defined under another name behind a wrapper that looks up
+        // and stores its output (see `__sh2_cache_call`).
+        emitln!(
+            out,
+            "{}() {{ __sh2_cache_call {} __sh2_uncached_{} \"$@\"; if [ \"$__sh2_cache_status\" -ne 0 ]; then exit \"$__sh2_cache_status\"; fi; }}",
+            f.name,
+            f.name,
+            f.name
+        );
+        emitln!(out, "__sh2_uncached_{}() {{", f.name);
+    } else {
+        emitln!(out, "{}() {{", f.name);
+    }
+    out.indent();
+    if let Some(helpers) = lazy_helpers.filter(|h| !h.is_empty()) {
+        emitln!(out, "__sh2_lib_load {}", helpers.join(" "));
+    }
+    ctx.unframe_on_return = false;
+    if ctx.traced_functions.contains(&f.name) {
+        ctx.unframe_on_return = opts.target == TargetShell::Posix;
+        if opts.target == TargetShell::Bash {
+            out.line("local __sh2_stack=\"${__sh2_stack:-}\" __sh2_fn=\"${__sh2_fn:-}\"");
+        }
+        emitln!(out, "__sh2_frame {}", crate::names::display(&f.name));
+    }
+    if has_loc && opts.target == TargetShell::Bash {
+        out.line("local __sh2_loc=\"\"");
+    }
+    for (idx, param) in f.params.iter().enumerate() {
+        match opts.target {
+            TargetShell::Bash => emitln!(out, "local {}=\"${{{}}}\"", param, idx + 1),
+            TargetShell::Posix => emitln!(out, "{}=\"${{{}}}\"", param, idx + 1),
+        }
+    }
+    emit_cmds(&f.commands, out, opts, false, ctx)?;
+    out.dedent();
+    out.line("}");
+    Ok(())
+}
+
+/// A file of function definitions meant to be sourced: no prelude runs at load
+/// time and nothing calls `main`.
+///
+/// Usage is scanned per function. Each function starts by asking
+/// `__sh2_lib_load` for the helper groups it needs, which are defined the first
+/// time any function asks, so sourcing the file adds only the functions
+/// themselves and the loader to the caller's namespace.
+pub fn emit_library(funcs: &[Function], opts: CodegenOptions) -> Result<String, CompileError> {
+    let base = emit_prelude(opts.target, &PreludeUsage::default(), PreludePart::Library);
+    // Helper groups in prelude order, each with the code only it defines.
+    let mut groups: Vec<(&'static str, String)> = Vec::new();
+    let mut per_function: Vec<(Vec<&'static str>, PreludeUsage)> = Vec::new();
+
+    for f in funcs {
+        let usage = scan_usage(std::slice::from_ref(f), opts.include_diagnostics);
+        if usage.parse_args {
+            return Err(CompileError::unsupported(
+                format!("parse_args() in library function '{}'", f.name),
+                opts.target,
+            ));
+        }
+        if f.cache {
+            return Err(CompileError::unsupported(
+                format!("@cache on library function '{}'", f.name),
+                opts.target,
+            ));
         }
-        for (idx, param) in f.params.iter().enumerate() {
-            match opts.target {
-                TargetShell::Bash => {
-                    out.push_str(&format!("  local {}=\"${{{}}}\"\n", param, idx + 1))
-                }
-                TargetShell::Posix => out.push_str(&format!("  {}=\"${{{}}}\"\n", param, idx + 1)),
+        let mut wanted = vec!["check"];
+        for (flag, on) in usage.flags() {
+            // The error-location handler only runs from an ERR trap, and a library
+            // leaves the caller's traps alone.
+            if !on || flag == "loc" {
+                continue;
+            }
+            let mut only = PreludeUsage::default();
+            only.set_flag(flag, true);
+            let code = emit_prelude(opts.target, &only, PreludePart::Library);
+            let own = code.strip_prefix(base.as_str()).unwrap_or(&code);
+            if own.is_empty() {
+                continue;
             }
+            if !groups.iter().any(|(name, _)| *name == flag) {
+                groups.push((flag, own.to_string()));
+            }
+            wanted.push(flag);
         }
-        for cmd in &f.commands {
-            emit_cmd(cmd, &mut out, 2, opts, false, &mut ctx)?;
+        per_function.push((wanted, usage));
+    }
+
+    let mut out = ScriptBuilder::with_capacity(base.len() + 1024);
+    emitln!(out, "# Generated by sh2c {}. Source this file; it only defines functions.", env!("CARGO_PKG_VERSION"));
+    if opts.target == TargetShell::Bash {
+        out.line("# shellcheck shell=bash");
+    }
+    out.push_str("__sh2_lib_load() {\n");
+    out.push_str("  for __sh2_h in \"$@\"; do\n");
+    out.push_str("    case \" ${__sh2_lib_loaded:-} \" in *\" $__sh2_h \"*) continue ;; esac\n");
+    out.push_str("    __sh2_lib_loaded=\"${__sh2_lib_loaded:-} $__sh2_h\"\n");
+    out.push_str("    case \"$__sh2_h\" in\n");
+    // Helper bodies stay unindented: some contain multi-line quoted programs.
+    for (name, code) in std::iter::once(("check", base.clone())).chain(groups) {
+        emitln!(out, "      {})", name);
+        out.push_str(&code);
+        out.push_str("        ;;\n");
+    }
+    out.push_str("    esac\n");
+    out.push_str("  done\n");
+    out.push_str("}\n");
+
+    let mut ctx = CodegenContext::default();
+    for (f, (wanted, usage)) in funcs.iter().zip(&per_function) {
+        out.push('\n');
+        ctx.track_command = usage.last_command;
+        ctx.track_stderr = usage.last_stderr;
+        emit_function(&mut out, f, opts, usage.loc, Some(wanted), &mut ctx)?;
+    }
+    check_posix(out.finish(), opts)
+}
+
+/// Helper functions defined at the top level of a prelude.
+fn prelude_helpers(prelude: &str) -> Vec<&str> {
+    prelude
+        .lines()
+        .filter_map(|line| line.split_once("() {").map(|(name, _)| name))
+        .filter(|name| name.starts_with("__sh2_") && !name.contains(' '))
+        .collect()
+}
+
+/// `--emit-prelude-report`: which helpers the prelude carries, what each costs,
+/// and the statement that first needed it.
+pub fn prelude_report(funcs: &[Function], opts: CodegenOptions) -> String {
+    let (usage, triggers) = scan_usage_traced(funcs, opts.include_diagnostics);
+    let prelude = emit_prelude(opts.target, &usage, PreludePart::Inline);
+    let helpers = prelude_helpers(&prelude);
+    let mut out = format!(
+        "prelude for {} target: {} bytes, {} helper(s)\n",
+        opts.target,
+        prelude.len(),
+        helpers.len()
+    );
+
+    let base = emit_prelude(opts.target, &PreludeUsage::default(), PreludePart::Inline);
+    out.push_str(&format!(
+        "  always: {} ({} bytes)\n",
+        prelude_helpers(&base).join(", "),
+        base.len()
+    ));
+
+    let mut attributed: Vec<&str> = prelude_helpers(&base);
+    for (flag, on) in usage.flags() {
+        if !on {
+            continue;
         }
-        out.push_str("}\n");
+        // A flag's cost is what disappears without it; helpers two flags both
+        // need show up under neither and are listed as shared below.
+        let mut without = usage.clone();
+        without.set_flag(flag, false);
+        let reduced = emit_prelude(opts.target, &without, PreludePart::Inline);
+        let own: Vec<&str> = helpers
+            .iter()
+            .copied()
+            .filter(|h| !prelude_helpers(&reduced).contains(h))
+            .collect();
+        attributed.extend(&own);
+        let what = if own.is_empty() { "no helper".to_string() } else { own.join(", ") };
+        let place = match triggers.iter().find(|t| t.flag == flag) {
+            Some(t) => match &t.loc {
+                Some(loc) => format!("at {}", loc),
+                None => format!("in {}()", t.func),
+            },
+            None => "by generated code".to_string(),
+        };
+        out.push_str(&format!(
+            "  {}: {} ({} bytes), first needed {}\n",
+            flag,
+            what,
+            prelude.len().saturating_sub(reduced.len()),
+            place
+        ));
     }
 
-    if usage.parse_args {
-        out.push_str("\n__sh2_parsed_args=\"$(__sh2_parse_args \"$@\")\"\n");
+    let shared: Vec<&str> = helpers.iter().copied().filter(|h| !attributed.contains(h)).collect();
+    if !shared.is_empty() {
+        out.push_str(&format!("  shared: {}\n", shared.join(", ")));
     }
-    out.push_str("__sh2_status=0\nmain \"$@\"\n");
-    Ok(out)
+    out
 }
 
 /// Emit shell script with POSIX compatibility checking
 /// Returns Ok(script) if successful, or Err(lint_message) if POSIX lints fail
 pub fn emit_with_options_checked(funcs: &[Function], opts: CodegenOptions) -> Result<String, CompileError> {
     let out = emit_with_options(funcs, opts)?;
-    
+    check_posix(out, opts)
+}
+
+fn check_posix(out: String, opts: CodegenOptions) -> Result<String, CompileError> {
     // Run POSIX lints if targeting POSIX
     if opts.target == TargetShell::Posix {
         let lints = lint_script(&out);
         if !lints.is_empty() {
             return Err(CompileError {
                 message: render_lints(&lints),
+                code: Some("E0400"),
                 target: Some(opts.target),
                 location: None,
             });
@@ -111,33 +434,27 @@ pub fn emit_with_options_checked(funcs: &[Function], opts: CodegenOptions) -> Re
 // (e.g., `"; __sh2_status=$?\n"`) or use custom `"exit"` variants. 
 // Those are intentionally left manual to preserve byte-for-byte identical output without over-engineering.
 
-fn emit_line(pad: &str, out: &mut String, content: &str) {
-    out.push_str(pad);
-    out.push_str(content);
-    out.push('\n');
-}
-
-fn emit_status_capture(pad: &str, out: &mut String) {
-    emit_line(pad, out, "__sh2_status=$?");
+fn emit_status_capture(out: &mut ScriptBuilder) {
+    out.line("__sh2_status=$?");
 }
 
-pub(super) fn emit_status_check(pad: &str, out: &mut String) {
-    emit_status_capture(pad, out);
-    emit_line(pad, out, "__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\"");
+fn emit_status_check(out: &mut ScriptBuilder) {
+    emit_status_capture(out);
+    out.line("__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\"");
 }
 
-pub(super) fn emit_status_check_ctx(pad: &str, out: &mut String, in_cond_ctx: bool) {
-    emit_status_capture(pad, out);
+fn emit_status_check_ctx(out: &mut ScriptBuilder, in_cond_ctx: bool) {
+    emit_status_capture(out);
     if in_cond_ctx {
-        emit_line(pad, out, "__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\" \"return\"");
+        out.line("__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\" \"return\"");
     } else {
-        emit_line(pad, out, "__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\"");
+        out.line("__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\"");
     }
 }
 
 // Used when capture and check are separated (e.g., by a `$!` capture in Spawn)
-pub(super) fn emit_status_check_only(pad: &str, out: &mut String) {
-    emit_line(pad, out, "__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\"");
+fn emit_status_check_only(out: &mut ScriptBuilder) {
+    out.line("__sh2_check \"$__sh2_status\" \"${__sh2_loc:-}\"");
 }
 
 use std::collections::HashSet;
@@ -145,6 +462,16 @@ use std::collections::HashSet;
 #[derive(Default)]
 pub(super) struct CodegenContext {
     pub(super) known_lists: HashSet<String>,
+    /// Record each command line for `last_command()`.
+    pub(super) track_command: bool,
+    /// Keep each command's stderr for `last_stderr()`.
+    pub(super) track_stderr: bool,
+    /// Functions that push a call-stack frame on entry; empty when error
+    /// locations are off.
+    pub(super) traced_functions: HashSet<String>,
+    /// The function being emitted pops its own call-stack frame before a
+    /// `-> bool` return, since its caller tests it directly.
+    pub(super) unframe_on_return: bool,
     uid_counter: usize,
 }
 
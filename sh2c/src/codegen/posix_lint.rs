@@ -31,8 +31,10 @@ impl PosixLint {
 pub fn lint_script(script: &str) -> Vec<PosixLint> {
     let mut lints = Vec::new();
     
-    // Check for double-bracket test
-    if script.contains("[[") || script.contains("]]") {
+    // Check for double-bracket test; `[[:alpha:]]` in a case pattern is a POSIX class
+    let opens = script.match_indices("[[").any(|(i, _)| !script[i + 2..].starts_with(':'));
+    let closes = script.match_indices("]]").any(|(i, _)| !script[..i].ends_with(':'));
+    if opens || closes {
         lints.push(PosixLint::new(
             PosixLintKind::DoubleBracketTest,
             "posix-lint: bash-only construct detected: [[ ... ]]".to_string(),
@@ -121,7 +123,7 @@ pub fn lint_script(script: &str) -> Vec<PosixLint> {
 
 /// Render lint errors as a user-friendly error message
 pub fn render_lints(lints: &[PosixLint]) -> String {
-    let mut msg = String::from("error: POSIX target emitted bash-only shell constructs:\n");
+    let mut msg = format!("error: {}\n", crate::diag_codes::tag("POSIX target emitted bash-only shell constructs:", "E0400"));
     
     for lint in lints {
         msg.push_str("  - ");
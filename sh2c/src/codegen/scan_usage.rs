@@ -2,6 +2,7 @@ use crate::ir::{
     Cmd, Val, Function,
     RedirectOutputTarget, RedirectInputTarget,
 };
+use super::helpers::is_static_val;
 
 #[derive(Debug, Default, Clone)]
 pub(super) struct PreludeUsage {
@@ -21,17 +22,30 @@ pub(super) struct PreludeUsage {
     pub save_envfile: bool,
     pub json_kv: bool,
     pub which: bool,
+    pub have: bool,
+    pub service: bool,
+    pub wait_for_port: bool,
+    pub input_list: bool,
     pub require: bool,
     pub tmpfile: bool,
     pub find_files: bool,
     pub read_file: bool,
+    pub read_file_or: bool,
+    /// split()/lines()/find() lists that the POSIX target buffers through a temp file.
+    pub posix_list_tmpfile: bool,
     pub write_file: bool,
+    pub write_file_atomic: bool,
+    pub file_edit: bool,
+    pub find: bool,
+    pub lines0: bool,
     pub log: bool,
     pub home: bool,
     pub path_join: bool,
     pub loc: bool,
     pub uid: bool,
     pub lines: bool,
+    pub len: bool,
+    pub bytes_len: bool,
     pub contains: bool,
     pub starts_with: bool,
     pub arg_dynamic: bool,
@@ -39,33 +53,237 @@ pub(super) struct PreludeUsage {
     pub sh_probe_args: bool,
     pub confirm: bool,
     pub glob: bool,
+    pub render: bool,
+    /// spawn(): background jobs are killed when the script exits.
+    pub spawn: bool,
+    /// `with deadline(...)`: duration parsing, the watchdog, and job killing.
+    pub deadline: bool,
+    /// Functions record their caller for error traces; needs `loc` and more than one function.
+    pub call_stack: bool,
+    /// Debugger hooks (`sh2do --debug`): the prompt and variable display.
+    pub debug: bool,
+    /// A `breakpoint()` statement: the debugger starts running instead of stepping.
+    pub breakpoint: bool,
+    /// dump_vars(): prints a table of variables and their values.
+    pub dump_vars: bool,
+    /// `--profile`: statement timing, the log file, and the report at exit.
+    pub profile: bool,
+    /// `@cache` functions: the lookup wrapper and the cache directory.
+    pub cache: bool,
+    /// last_command(): each command records its text before it runs. No helper code.
+    pub last_command: bool,
+    /// last_stderr(): each command's stderr is captured and replayed. No helper code.
+    pub last_stderr: bool,
+    /// Set only while building `--emit-prelude-report`.
+    pub trace: Option<UsageTrace>,
+}
+
+/// Where a prelude flag was first switched on.
+#[derive(Debug, Clone)]
+pub(super) struct UsageTrigger {
+    pub flag: &'static str,
+    /// `file:line:col` of the innermost statement carrying a location, if any.
+    pub loc: Option<String>,
+    pub func: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub(super) struct UsageTrace {
+    func: String,
+    pub triggers: Vec<UsageTrigger>,
+}
+
+macro_rules! prelude_flags {
+    ($($name:ident),* $(,)?) => {
+        impl PreludeUsage {
+            /// Every flag with its current value, in declaration order.
+            pub(super) fn flags(&self) -> Vec<(&'static str, bool)> {
+                vec![$((stringify!($name), self.$name)),*]
+            }
+
+            pub(super) fn set_flag(&mut self, name: &str, on: bool) {
+                match name {
+                    $(stringify!($name) => self.$name = on,)*
+                    _ => {}
+                }
+            }
+        }
+    };
+}
+
+prelude_flags!(
+    coalesce,
+    trim,
+    before,
+    after,
+    replace,
+    split,
+    matches,
+    parse_args,
+    args_flags,
+    args_positionals,
+    args_flag_get,
+    list_get,
+    load_envfile,
+    save_envfile,
+    json_kv,
+    which,
+    have,
+    service,
+    wait_for_port,
+    input_list,
+    require,
+    tmpfile,
+    find_files,
+    read_file,
+    read_file_or,
+    posix_list_tmpfile,
+    write_file,
+    write_file_atomic,
+    file_edit,
+    find,
+    lines0,
+    log,
+    home,
+    path_join,
+    loc,
+    call_stack,
+    debug,
+    dump_vars,
+    profile,
+    cache,
+    uid,
+    lines,
+    contains,
+    starts_with,
+    arg_dynamic,
+    sh_probe,
+    sh_probe_args,
+    confirm,
+    glob,
+    render,
+    spawn,
+    deadline,
+);
+
+impl PreludeUsage {
+    /// Every helper, as bundled into the `--runtime-lib` file.
+    pub(super) fn all() -> Self {
+        let mut usage = Self::default();
+        for (name, _) in usage.flags() {
+            usage.set_flag(name, true);
+        }
+        usage
+    }
+
+    /// Attributes flags that turned on since `before` and are not yet claimed.
+    fn claim(&mut self, before: &[(&'static str, bool)], loc: Option<&str>) {
+        let now = self.flags();
+        let Some(trace) = self.trace.as_mut() else { return };
+        for ((flag, on), (_, was)) in now.into_iter().zip(before) {
+            if on && !was && !trace.triggers.iter().any(|t| t.flag == flag) {
+                trace.triggers.push(UsageTrigger {
+                    flag,
+                    loc: loc.map(str::to_string),
+                    func: trace.func.clone(),
+                });
+            }
+        }
+    }
 }
 
 pub(super) fn scan_usage(funcs: &[Function], include_diagnostics: bool) -> PreludeUsage {
     let mut usage = PreludeUsage::default();
     for f in funcs {
+        usage.cache |= f.cache;
         for cmd in &f.commands {
             visit_cmd(cmd, &mut usage, include_diagnostics);
         }
     }
+    usage.call_stack = usage.loc && funcs.len() > 1;
     usage
 }
 
+/// Like [`scan_usage`], also recording the statement that first needed each flag.
+pub(super) fn scan_usage_traced(funcs: &[Function], include_diagnostics: bool) -> (PreludeUsage, Vec<UsageTrigger>) {
+    let mut usage = PreludeUsage { trace: Some(UsageTrace::default()), ..Default::default() };
+    for f in funcs {
+        if let Some(trace) = usage.trace.as_mut() {
+            trace.func = f.name.clone();
+        }
+        let before = usage.flags();
+        for cmd in &f.commands {
+            visit_cmd(cmd, &mut usage, include_diagnostics);
+        }
+        usage.cache |= f.cache;
+        // Whatever no located statement claimed belongs to the function as a whole.
+        usage.claim(&before, None);
+    }
+    usage.call_stack = usage.loc && funcs.len() > 1;
+    let triggers = usage.trace.take().map(|t| t.triggers).unwrap_or_default();
+    (usage, triggers)
+}
+
 fn visit_cmd(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
+    if usage.trace.is_none() {
+        return visit_cmd_kind(cmd, usage, include_diagnostics);
+    }
+    let before = usage.flags();
+    visit_cmd_kind(cmd, usage, include_diagnostics);
+    // Statements without a location leave their flags to the enclosing one.
+    if let Some(loc) = cmd_loc(cmd) {
+        usage.claim(&before, Some(loc));
+    }
+}
+
+fn cmd_loc(cmd: &Cmd) -> Option<&str> {
+    match cmd {
+        Cmd::Assign(_, _, loc)
+        | Cmd::Exec { loc, .. }
+        | Cmd::Pipe(_, loc)
+        | Cmd::PipeBlocks(_, loc)
+        | Cmd::ServiceStart { loc, .. }
+        | Cmd::WaitForPort { loc, .. }
+        | Cmd::WithDeadline { loc, .. }
+        | Cmd::ServiceStop { loc, .. }
+        | Cmd::Fs { loc, .. }
+        | Cmd::ReplaceInFile { loc, .. }
+        | Cmd::AppendLineIfMissing { loc, .. }
+        | Cmd::Raw { loc, .. }
+        | Cmd::RawLine { loc, .. }
+        | Cmd::ExecReplace(_, loc) => loc.as_deref(),
+        _ => None,
+    }
+}
+
+fn visit_cmd_kind(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
     match cmd {
         Cmd::Assign(_, val, loc) => {
-            if include_diagnostics && loc.is_some() {
+            // Static assignments are emitted without `__sh2_loc` (see `emit_cmds`).
+            if include_diagnostics && loc.is_some() && !is_static_val(val) {
                 usage.loc = true;
             }
+            if matches!(val, Val::Find { .. }) {
+                usage.posix_list_tmpfile = true;
+            }
             visit_val(val, usage);
         }
-        Cmd::Exec { args, loc, .. } => {
+        Cmd::Exec { args, loc, stdin, env, cwd, .. } => {
             if include_diagnostics && loc.is_some() {
                 usage.loc = true;
             }
             for a in args {
                 visit_val(a, usage)
             }
+            if let Some(v) = stdin {
+                visit_val(v, usage);
+            }
+            for (_, v) in env {
+                visit_val(v, usage);
+            }
+            if let Some(v) = cwd {
+                visit_val(v, usage);
+            }
         }
         Cmd::Print(val) | Cmd::PrintErr(val) => visit_val(val, usage),
         Cmd::If {
@@ -92,20 +310,26 @@ fn visit_cmd(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
             if include_diagnostics && loc.is_some() {
                 usage.loc = true;
             }
-            for (args, _) in segs {
+            for (args, _, stderr) in segs {
                 for a in args {
                     visit_val(a, usage)
                 }
+                if let Some(t) = stderr {
+                    visit_redirect_output(t, usage);
+                }
             }
         }
         Cmd::PipeBlocks(segs, loc) => {
             if include_diagnostics && loc.is_some() {
                 usage.loc = true;
             }
-            for s in segs {
+            for (s, stderr) in segs {
                 for c in s {
                     visit_cmd(c, usage, include_diagnostics)
                 }
+                if let Some(t) = stderr {
+                    visit_redirect_output(t, usage);
+                }
             }
         }
         Cmd::PipeEachLine { producer, body, .. } => {
@@ -115,6 +339,7 @@ fn visit_cmd(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
                 visit_cmd(c, usage, include_diagnostics);
             }
         }
+        Cmd::CapturePipe { pipe, .. } => visit_cmd(pipe, usage, include_diagnostics),
         Cmd::Case { expr, arms } => {
             visit_val(expr, usage);
             for (_, body) in arms {
@@ -126,6 +351,9 @@ fn visit_cmd(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
         Cmd::For { iterable, body, .. } => {
             match iterable {
                 crate::ir::ForIterable::List(items) => {
+                     if items.iter().any(|i| matches!(i, Val::Split { .. } | Val::Lines(_) | Val::Find { .. })) {
+                         usage.posix_list_tmpfile = true;
+                     }
                      for i in items {
                          visit_val(i, usage);
                      }
@@ -157,6 +385,30 @@ fn visit_cmd(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
                 visit_cmd(c, usage, include_diagnostics);
             }
         }
+        Cmd::ServiceStart { pidfile, args, log, .. } => {
+            usage.service = true;
+            visit_val(pidfile, usage);
+            for a in args {
+                visit_val(a, usage);
+            }
+            if let Some(l) = log {
+                visit_val(l, usage);
+            }
+        }
+        Cmd::WaitForPort { host, port, timeout, interval, .. } => {
+            usage.wait_for_port = true;
+            visit_val(host, usage);
+            visit_val(port, usage);
+            visit_val(timeout, usage);
+            visit_val(interval, usage);
+        }
+        Cmd::ServiceStop { pidfile, grace, .. } => {
+            usage.service = true;
+            visit_val(pidfile, usage);
+            if let Some(g) = grace {
+                visit_val(g, usage);
+            }
+        }
         Cmd::Require(vals) => {
             usage.require = true;
             for v in vals {
@@ -167,14 +419,42 @@ fn visit_cmd(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
             usage.log = true;
             visit_val(msg, usage);
         }
-        Cmd::WriteFile { path, content, .. } => {
+        Cmd::Fs { args, .. } => {
+            for a in args {
+                visit_val(a, usage);
+            }
+        }
+        Cmd::ReplaceInFile { path, pattern, replacement, .. } => {
+            usage.file_edit = true;
+            visit_val(path, usage);
+            visit_val(pattern, usage);
+            visit_val(replacement, usage);
+        }
+        Cmd::AppendLineIfMissing { path, line, .. } => {
+            usage.file_edit = true;
+            visit_val(path, usage);
+            visit_val(line, usage);
+        }
+        Cmd::WriteFile { path, content, atomic, .. } => {
             usage.write_file = true;
+            usage.write_file_atomic |= *atomic;
             visit_val(path, usage);
             visit_val(content, usage);
         }
         Cmd::Cd(val) => visit_val(val, usage),
 
-        Cmd::Subshell { body } | Cmd::Group { body } => {
+        Cmd::Subshell { body } | Cmd::Group { body } | Cmd::Unchecked { body } => {
+            for c in body {
+                visit_cmd(c, usage, include_diagnostics)
+            }
+        }
+        Cmd::Debug { breakpoint, .. } => {
+            usage.debug = true;
+            usage.breakpoint |= *breakpoint;
+        }
+        Cmd::DumpVars { .. } => usage.dump_vars = true,
+        Cmd::Profile { body, .. } => {
+            usage.profile = true;
             for c in body {
                 visit_cmd(c, usage, include_diagnostics)
             }
@@ -202,7 +482,10 @@ fn visit_cmd(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
                 visit_cmd(c, usage, include_diagnostics);
             }
         }
-        Cmd::Spawn(inner) => visit_cmd(inner, usage, include_diagnostics),
+        Cmd::Spawn(inner) => {
+            usage.spawn = true;
+            visit_cmd(inner, usage, include_diagnostics);
+        }
         Cmd::Wait(opt) => {
             if let Some(v) = opt {
                 visit_val(v, usage)
@@ -260,6 +543,18 @@ fn visit_cmd(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
                 visit_cmd(c, usage, include_diagnostics);
             }
         }
+        Cmd::WithDeadline { duration, body, .. } => {
+            usage.deadline = true;
+            visit_val(duration, usage);
+            for c in body {
+                visit_cmd(c, usage, include_diagnostics);
+            }
+        }
+        Cmd::WithUmask { body, .. } | Cmd::WithLimits { body, .. } => {
+            for c in body {
+                visit_cmd(c, usage, include_diagnostics);
+            }
+        }
         Cmd::WithCwd { path, body } => {
             visit_val(path, usage);
             for c in body {
@@ -272,6 +567,7 @@ fn visit_cmd(cmd: &Cmd, usage: &mut PreludeUsage, include_diagnostics: bool) {
                  visit_val(v, usage);
              }
         }
+        Cmd::ReturnBool(v) => visit_val(v, usage),
         Cmd::Raw { cmd: val, args, loc } => {
              if let Some(_) = args {
                  usage.sh_probe_args = true;
@@ -306,7 +602,7 @@ fn visit_redirect_output(target: &RedirectOutputTarget, usage: &mut PreludeUsage
 
 fn visit_redirect_input(target: &RedirectInputTarget, usage: &mut PreludeUsage) {
     match target {
-        RedirectInputTarget::File { path } => visit_val(path, usage),
+        RedirectInputTarget::File { path } | RedirectInputTarget::Text { value: path } => visit_val(path, usage),
         _ => {}
     }
 }
@@ -330,17 +626,49 @@ fn visit_val(val: &Val, usage: &mut PreludeUsage) {
                 visit_val(a, usage);
             }
         }
+        Val::BoolCall { args, .. } => {
+            for a in args {
+                visit_val(a, usage);
+            }
+        }
         Val::Which(v) => {
             usage.which = true;
             visit_val(v, usage);
         }
+        Val::Have(v) => {
+            usage.which = true;
+            usage.have = true;
+            visit_val(v, usage);
+        }
+        Val::WaitForPort { host, port, timeout, interval } => {
+            usage.wait_for_port = true;
+            visit_val(host, usage);
+            visit_val(port, usage);
+            visit_val(timeout, usage);
+            visit_val(interval, usage);
+        }
+        Val::ServiceRunning(v) => {
+            usage.service = true;
+            visit_val(v, usage);
+        }
         Val::ReadFile(v) => {
             usage.read_file = true;
             visit_val(v, usage);
         }
+        Val::ReadFileOr { path, default } => {
+            usage.read_file_or = true;
+            visit_val(path, usage);
+            visit_val(default, usage);
+        }
         Val::Home => {
             usage.home = true;
         }
+        Val::LastCommand => {
+            usage.last_command = true;
+        }
+        Val::LastStderr => {
+            usage.last_stderr = true;
+        }
         Val::PathJoin(args) => {
             usage.path_join = true;
             for a in args {
@@ -351,10 +679,30 @@ fn visit_val(val: &Val, usage: &mut PreludeUsage) {
             usage.lines = true;
             visit_val(inner, usage);
         }
+        Val::Len(inner) => {
+            usage.len = true;
+            visit_val(inner, usage);
+        }
+        Val::BytesLen(inner) => {
+            usage.bytes_len = true;
+            visit_val(inner, usage);
+        }
+        Val::Lines0(argv) => {
+            usage.lines0 = true;
+            for a in argv {
+                visit_val(a, usage);
+            }
+        }
         Val::Glob(inner) => {
             usage.glob = true;
             visit_val(inner, usage);
         }
+        Val::RawArg(inner) => visit_val(inner, usage),
+        Val::InputList(prompt) => {
+            usage.input_list = true;
+            usage.tmpfile = true;
+            visit_val(prompt, usage);
+        }
         Val::Split { s, delim } => {
             usage.split = true;
             visit_val(s, usage);
@@ -369,6 +717,7 @@ fn visit_val(val: &Val, usage: &mut PreludeUsage) {
             visit_val(right, usage);
         }
         Val::Not(v)
+        | Val::Spread(v)
         | Val::Exists(v)
         | Val::IsDir(v)
         | Val::IsFile(v)
@@ -377,7 +726,7 @@ fn visit_val(val: &Val, usage: &mut PreludeUsage) {
         | Val::IsReadable(v)
         | Val::IsWritable(v)
         | Val::IsNonEmpty(v)
-        | Val::Len(v)
+        | Val::Awk { input: v, .. }
         | Val::Count(v)
         | Val::BoolStr(v)
         | Val::Input(v)
@@ -415,7 +764,7 @@ fn visit_val(val: &Val, usage: &mut PreludeUsage) {
             visit_val(prefix, usage);
         }
         Val::ParseArgs => usage.parse_args = true,
-        Val::Index { list, index } => {
+        Val::Index { list, index, .. } => {
             visit_val(list, usage);
             visit_val(index, usage);
             if let Val::ArgsFlags(_) = **list {
@@ -429,6 +778,11 @@ fn visit_val(val: &Val, usage: &mut PreludeUsage) {
             visit_val(list, usage);
             visit_val(sep, usage);
         }
+        Val::IfElse { cond, then_val, else_val } => {
+            visit_val(cond, usage);
+            visit_val(then_val, usage);
+            visit_val(else_val, usage);
+        }
         Val::TryRun(args) => {
             usage.tmpfile = true;
             usage.read_file = true;
@@ -463,12 +817,24 @@ fn visit_val(val: &Val, usage: &mut PreludeUsage) {
             visit_val(needle, usage);
         }
 
+        Val::Render { template, .. } => {
+            usage.render = true;
+            visit_val(template, usage);
+        }
         Val::FindFiles { dir, name } => {
             usage.find_files = true;
             visit_val(dir, usage);
             visit_val(name, usage);
         }
+        Val::Find { dir, name, type_filter, maxdepth } => {
+            usage.find = true;
+            visit_val(dir, usage);
+            for v in [name, type_filter, maxdepth].into_iter().flatten() {
+                visit_val(v, usage);
+            }
+        }
         Val::Spawn { args, .. } => {
+            usage.spawn = true;
             for a in args {
                 visit_val(a, usage);
             }
@@ -476,7 +842,7 @@ fn visit_val(val: &Val, usage: &mut PreludeUsage) {
         Val::Wait { pid, .. } => {
             visit_val(pid, usage);
         }
-        Val::WaitAll { pids, .. } => {
+        Val::WaitAll { pids, .. } | Val::WaitAny { pids, .. } => {
             visit_val(pids, usage);
         }
         _ => {}
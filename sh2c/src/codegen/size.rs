@@ -0,0 +1,71 @@
+//! `--max-size` and `--warn-size`: what each part of a generated script costs.
+//!
+//! Sizes are measured on the final text (after `--minify` and the sandbox guard),
+//! so they add up to what actually gets embedded. A user function runs from its
+//! `name() {` line to the next unindented `}`; everything else (shebang, prelude
+//! helpers, traps, the call to `main`) counts as prelude.
+
+pub struct SizeBreakdown {
+    pub total: usize,
+    pub prelude: usize,
+    /// User functions in script order.
+    pub functions: Vec<(String, usize)>,
+}
+
+pub fn breakdown(script: &str, functions: &[&str]) -> SizeBreakdown {
+    let mut sizes: Vec<(String, usize)> = Vec::new();
+    let mut prelude = 0;
+    let mut current: Option<usize> = None;
+
+    for line in script.split_inclusive('\n') {
+        if let Some(idx) = current {
+            sizes[idx].1 += line.len();
+            if line.trim_end_matches('\n') == "}" {
+                current = None;
+            }
+            continue;
+        }
+        let opened = line
+            .strip_suffix("() {\n")
+            .filter(|name| functions.contains(name));
+        match opened {
+            Some(name) => {
+                sizes.push((name.to_string(), line.len()));
+                current = Some(sizes.len() - 1);
+            }
+            None => prelude += line.len(),
+        }
+    }
+
+    SizeBreakdown { total: script.len(), prelude, functions: sizes }
+}
+
+impl SizeBreakdown {
+    /// One row per part, largest first, with its share of the whole script.
+    pub fn report(&self) -> String {
+        let mut rows: Vec<(String, usize)> = vec![("prelude".to_string(), self.prelude)];
+        rows.extend(self.functions.iter().map(|(name, bytes)| (format!("{}()", name), *bytes)));
+        rows.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+
+        let mut out = String::new();
+        for (name, bytes) in rows {
+            let share = (bytes * 100).checked_div(self.total).unwrap_or(0);
+            out.push_str(&format!("  {:<20}{:>8} bytes {:>3}%\n", name, bytes, share));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_functions_from_prelude() {
+        let script = "#!/bin/sh\n__sh2_x() { :; }\ngreet() {\n  echo hi\n}\n\nmain() {\n  greet\n}\nmain \"$@\"\n";
+        let sizes = breakdown(script, &["greet", "main"]);
+        assert_eq!(sizes.functions, vec![("greet".to_string(), 22), ("main".to_string(), 19)]);
+        assert_eq!(sizes.prelude + 22 + 19, script.len());
+        assert!(sizes.report().starts_with("  prelude "), "{}", sizes.report());
+    }
+}
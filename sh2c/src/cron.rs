@@ -0,0 +1,188 @@
+//! `sh2c --emit-cron "<schedule>"`: a crontab line and a wrapper script written
+//! next to the compiled script.
+//!
+//! The wrapper is plain POSIX sh. It takes a lock so overlapping runs are
+//! skipped (`flock` when installed, a lock directory otherwise), appends the
+//! script's output to a dated log file, and on failure mails the end of the log
+//! and/or posts to a webhook. Those settings come from an optional
+//! `@cron("key=value", ...)` attribute on the entry function.
+
+use crate::ast::{Attribute, Program};
+use std::path::{Path, PathBuf};
+
+const KEYS: &[&str] = &["log_dir", "lock_file", "mail", "webhook"];
+
+const MACROS: &[&str] = &["@reboot", "@yearly", "@annually", "@monthly", "@weekly", "@daily", "@midnight", "@hourly"];
+
+/// Checks one `@cron` argument; called by the parser so a typo fails even
+/// without `--emit-cron`.
+pub fn check_arg(arg: &str) -> Result<(), String> {
+    let Some((key, value)) = arg.split_once('=') else {
+        return Err(format!("@cron arguments are \"key=value\" strings, got {:?}", arg));
+    };
+    if !KEYS.contains(&key) {
+        return Err(format!("@cron has no key '{}' (expected one of: {})", key, KEYS.join(", ")));
+    }
+    if value.is_empty() || value.contains(['\n', '\r']) {
+        return Err(format!("@cron {} must be a non-empty single line", key));
+    }
+    Ok(())
+}
+
+/// Accepts five cron fields (`0 3 * * 1-5`) or a macro such as `@daily`.
+pub fn check_schedule(schedule: &str) -> bool {
+    let fields: Vec<&str> = schedule.split_whitespace().collect();
+    match fields.as_slice() {
+        [one] if one.starts_with('@') => MACROS.contains(one),
+        _ => {
+            fields.len() == 5
+                && fields
+                    .iter()
+                    .all(|f| f.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '*' | ',' | '-' | '/')))
+        }
+    }
+}
+
+/// The files `--emit-cron` writes for `script`: the wrapper and the crontab line.
+pub struct CronFiles {
+    pub wrapper_path: PathBuf,
+    pub wrapper: String,
+    pub crontab_path: PathBuf,
+    pub crontab: String,
+}
+
+/// Builds the wrapper and crontab line for `script`, whose entry function is
+/// `entry`. Fails when another function carries `@cron`, since it would be ignored.
+pub fn files(program: &Program, entry: &str, script: &Path, schedule: &str) -> Result<CronFiles, String> {
+    if let Some(other) = program.functions.iter().find(|f| f.name != entry && f.attr("cron").is_some()) {
+        return Err(format!(
+            "@cron on '{}' has no effect; put it on the entry function '{}'",
+            other.name, entry
+        ));
+    }
+    let attr = program.functions.iter().find(|f| f.name == entry).and_then(|f| f.attr("cron"));
+    let get = |key: &str| attr.and_then(|a| value(a, key));
+
+    let name = script.file_stem().unwrap_or(script.as_os_str()).to_string_lossy().into_owned();
+    let script_abs = std::path::absolute(script).unwrap_or_else(|_| script.to_path_buf());
+    let wrapper_path = script.with_file_name(format!("{}-cron.sh", name));
+    let wrapper_abs = std::path::absolute(&wrapper_path).unwrap_or_else(|_| wrapper_path.clone());
+    let source = Path::new(&program.entry_file).file_name().unwrap_or_default().to_string_lossy();
+
+    let default_log_dir = format!("/var/log/{}", name);
+    let default_lock = format!("/tmp/{}.lock", name);
+    let settings = [
+        ("script", script_abs.to_string_lossy().into_owned()),
+        ("name", name.clone()),
+        ("log_dir", get("log_dir").unwrap_or(&default_log_dir).to_string()),
+        ("lock_file", get("lock_file").unwrap_or(&default_lock).to_string()),
+        ("mail_to", get("mail").unwrap_or_default().to_string()),
+        ("webhook", get("webhook").unwrap_or_default().to_string()),
+        ("job_json", json_str(&name)),
+    ];
+    let mut wrapper = format!("#!/bin/sh\n# Generated by sh2c from {}: runs {} from cron.\n", source, name);
+    for (var, val) in settings {
+        wrapper.push_str(&format!("{}={}\n", var, quote(&val)));
+    }
+    wrapper.push_str(WRAPPER_BODY);
+
+    let crontab = format!(
+        "# Generated by sh2c from {}\n{} {}\n",
+        source,
+        schedule.split_whitespace().collect::<Vec<_>>().join(" "),
+        crontab_command(&wrapper_abs.to_string_lossy())
+    );
+    Ok(CronFiles { crontab_path: script.with_file_name(format!("{}.crontab", name)), wrapper_path, wrapper, crontab })
+}
+
+const WRAPPER_BODY: &str = r#"
+mkdir -p "$log_dir" || exit 1
+log="$log_dir/$name-$(date +%Y-%m-%d).log"
+stamp() { date '+%Y-%m-%d %H:%M:%S'; }
+
+# One run at a time: a start while the previous run holds the lock is skipped.
+if command -v flock >/dev/null 2>&1; then
+    exec 9>"$lock_file" || exit 1
+    if ! flock -n 9; then
+        echo "$(stamp) $name: previous run still active, skipped" >>"$log"
+        exit 0
+    fi
+else
+    if ! mkdir "$lock_file.d" 2>/dev/null; then
+        echo "$(stamp) $name: previous run still active, skipped" >>"$log"
+        exit 0
+    fi
+    trap 'rmdir "$lock_file.d"' EXIT
+    trap 'exit 130' INT
+    trap 'exit 143' TERM
+fi
+
+echo "$(stamp) $name: start" >>"$log"
+"$script" "$@" >>"$log" 2>&1
+status=$?
+echo "$(stamp) $name: exit $status" >>"$log"
+
+if [ "$status" -ne 0 ]; then
+    if [ -n "$mail_to" ] && command -v mail >/dev/null 2>&1; then
+        tail -n 50 "$log" | mail -s "$name failed with status $status on $(hostname)" "$mail_to"
+    fi
+    if [ -n "$webhook" ] && command -v curl >/dev/null 2>&1; then
+        curl -fsS -m 10 -X POST -H 'Content-Type: application/json' \
+            -d "{\"job\":$job_json,\"status\":$status,\"host\":\"$(hostname)\"}" \
+            "$webhook" >/dev/null 2>&1
+    fi
+fi
+exit "$status"
+"#;
+
+fn value<'a>(attr: &'a Attribute, key: &str) -> Option<&'a str> {
+    attr.args.iter().rev().find_map(|a| a.strip_prefix(key)?.strip_prefix('='))
+}
+
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// The wrapper path as a crontab command: cron treats `%` as a newline unless escaped.
+fn crontab_command(path: &str) -> String {
+    let path = if path.contains(|c: char| c.is_whitespace() || c == '\'') { quote(path) } else { path.to_string() };
+    path.replace('%', "\\%")
+}
+
+/// `s` as a JSON string literal.
+fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_schedules() {
+        assert!(check_schedule("0 3 * * *"));
+        assert!(check_schedule("*/15 9-17 * * mon-fri"));
+        assert!(check_schedule("@daily"));
+        assert!(!check_schedule("@sometimes"));
+        assert!(!check_schedule("0 3 * *"));
+        assert!(!check_schedule("0 3 * * * ; rm -rf /"));
+    }
+
+    #[test]
+    fn escapes_percent_for_crontab() {
+        assert_eq!(crontab_command("/opt/jobs/a%b-cron.sh"), "/opt/jobs/a\\%b-cron.sh");
+        assert_eq!(crontab_command("/opt/my jobs/a-cron.sh"), "'/opt/my jobs/a-cron.sh'");
+    }
+}
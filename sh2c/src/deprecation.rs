@@ -0,0 +1,64 @@
+//! Warnings for calls to functions marked `@deprecated`.
+//!
+//! Each call site gets one warning carrying the attribute's message, so a
+//! library can point its users at the replacement before removing the old
+//! function. Calls made from inside another deprecated function are not
+//! reported: those are the library's own compatibility shims.
+
+use crate::ast::{self, ExprKind, Program, StmtKind};
+use crate::names;
+use crate::span::Span;
+use crate::warn_walk::{self, Reporter, Visitor};
+use std::collections::HashMap;
+use std::path::Path;
+
+struct Checker<'a> {
+    deprecated: &'a HashMap<&'a str, &'a ast::Attribute>,
+    r: Reporter<'a>,
+}
+
+/// Returns one formatted warning per call to a deprecated function.
+pub fn check_deprecated_calls(program: &Program, base: Option<&Path>) -> Vec<String> {
+    let deprecated: HashMap<&str, &ast::Attribute> = program
+        .functions
+        .iter()
+        .filter_map(|f| f.attr("deprecated").map(|a| (f.name.as_str(), a)))
+        .collect();
+    if deprecated.is_empty() {
+        return Vec::new();
+    }
+
+    warn_walk::check_functions(program, base, |func, r| {
+        if func.attr("deprecated").is_none() {
+            Checker { deprecated: &deprecated, r }.block(&func.body);
+        }
+    })
+}
+
+impl Checker<'_> {
+    fn call(&mut self, name: &str, span: Span) {
+        let Some(attr) = self.deprecated.get(name) else { return };
+        let mut msg = format!("call to deprecated function '{}'", names::display(name));
+        if let Some(note) = attr.args.first() {
+            msg.push_str(": ");
+            msg.push_str(note);
+        }
+        self.r.warn("W0004", &msg, span);
+    }
+}
+
+impl Visitor for Checker<'_> {
+    fn stmt(&mut self, stmt: &ast::Stmt) {
+        if let StmtKind::Call { name, .. } = &stmt.node {
+            self.call(name, stmt.span);
+        }
+        warn_walk::walk_stmt(self, stmt);
+    }
+
+    fn expr(&mut self, expr: &ast::Expr) {
+        if let ExprKind::Call { name, .. } = &expr.node {
+            self.call(name, expr.span);
+        }
+        warn_walk::walk_expr(self, expr);
+    }
+}
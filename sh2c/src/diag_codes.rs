@@ -0,0 +1,861 @@
+//! Stable codes for compiler diagnostics, and the extended descriptions printed by
+//! `sh2c explain <code>`.
+//!
+//! A diagnostic carries its code from the site that builds it (`Diagnostic::code`,
+//! `CompileError::code`). Messages built without one are classified by text when
+//! they are rendered, as a fallback. Codes are never renumbered or reused: a new
+//! kind of error gets a new entry, and a retired code keeps its entry.
+
+/// One diagnostic code.
+pub struct DiagCode {
+    pub code: &'static str,
+    pub title: &'static str,
+    /// Lower-case message fragments that select this code for messages built
+    /// without one; the first entry of `CODES` with a matching fragment wins.
+    matches: &'static [Match],
+    pub explanation: &'static str,
+}
+
+enum Match {
+    Prefix(&'static str),
+    Contains(&'static str),
+}
+
+use Match::{Contains, Prefix};
+
+/// Code for messages no entry matches.
+pub const UNCATEGORIZED: &str = "E0000";
+
+pub static CODES: &[DiagCode] = &[
+    DiagCode {
+        code: "E0001",
+        title: "unterminated string literal",
+        matches: &[Prefix("unterminated string"), Prefix("unterminated triple"), Prefix("unterminated raw"), Prefix("unexpected eof in string escape")],
+        explanation: "\
+A string literal is missing its closing quote, so the rest of the file was read as
+part of the string.
+
+    print(\"hello)
+
+Close the string. Use a triple-quoted string (\"\"\"...\"\"\") for text that spans lines.",
+    },
+    DiagCode {
+        code: "E0002",
+        title: "unexpected character",
+        matches: &[Prefix("unexpected character")],
+        explanation: "\
+The source contains a character that does not start any token of the language,
+for example a shell operator such as `$` or a backtick outside a string.
+
+    let x = $HOME
+
+Write shell values the sh2 way (`env.HOME`) or put the text inside a string.",
+    },
+    DiagCode {
+        code: "E0003",
+        title: "unterminated block comment",
+        matches: &[Prefix("unterminated block comment")],
+        explanation: "\
+A `/*` block comment reaches the end of the file without its closing `*/`.
+Block comments nest, so every `/*` inside one needs its own `*/`:
+
+    /* outer /* inner */ still commented
+
+Close each comment, or use `#` for line comments.",
+    },
+    DiagCode {
+        code: "E0004",
+        title: "invalid escape sequence",
+        matches: &[Prefix("unknown escape sequence"), Prefix("invalid unicode escape"), Contains("cannot hold nul bytes")],
+        explanation: "\
+A backslash in a string starts an escape sequence, and only these are defined:
+\\n \\t \\r \\e \\\\ \\\" \\$ \\{ \\} and \\u{hex} with 1 to 6 hex digits.
+
+    print(\"C:\\temp\\data\")    // \\d is not an escape
+
+Double the backslash (`\\\\d`) or write a raw string, r\"C:\\temp\\data\", which keeps
+every backslash as written. NUL (`\\0`, `\\u{0}`) cannot appear in a shell string.",
+    },
+    DiagCode {
+        code: "E0005",
+        title: "invalid number literal",
+        matches: &[Prefix("invalid number literal"), Prefix("unknown number suffix"), Prefix("number literal")],
+        explanation: "\
+A number literal is malformed or out of range. Numbers are decimal or `0x` hex,
+may group digits with `_`, and may end in a size suffix that multiplies them out:
+
+    K M G T        powers of 1000   (10M  is 10000000)
+    Ki Mi Gi Ti    powers of 1024   (4Ki  is 4096)
+
+    let limit = 10mb      // unknown suffix
+    let n = 1__000        // `_` must sit between digits
+
+Values are limited to shell arithmetic, a signed 64-bit integer.",
+    },
+    DiagCode {
+        code: "E0100",
+        title: "syntax error",
+        matches: &[
+            Prefix("expected "),
+            Prefix("unexpected "),
+            Prefix("extra tokens"),
+            Prefix("unterminated interpolation"),
+            Prefix("interpolated string"),
+            Prefix("the & operator requires whitespace"),
+            Prefix("pipe requires at least two segments"),
+            Prefix("chained qualified calls"),
+            Contains("inside interpolation holes"),
+        ],
+        explanation: "\
+The parser found a token it did not expect at this point. The message names what
+it was looking for; the caret marks the token it found instead. A common cause is
+an unclosed `(` or `{` on an earlier line.
+
+    print(\"a\" \"b\")              // error: expected `)` or `,`",
+    },
+    DiagCode {
+        code: "E0101",
+        title: "code outside a function",
+        matches: &[Prefix("top-level statements are not allowed"), Prefix("import is only allowed at top-level")],
+        explanation: "\
+Only `import` and `func` may appear at the top level of a file, and `import` may
+appear nowhere else.
+
+    print(\"hi\")
+    func main() { }
+
+Move the statement into `func main() { ... }`, and imports to the top of the file.",
+    },
+    DiagCode {
+        code: "E0102",
+        title: "missing entry point",
+        matches: &[Prefix("no entrypoint"), Prefix("--entry ")],
+        explanation: "\
+Every program needs a `func main()`; the generated script calls it with the
+script's arguments.
+
+    func main() {
+        print(\"hello\")
+    }
+
+With `--entry <name>` the script calls `func <name>(...)` instead, which must be
+defined with `func` in the program (an `extern func` does not count).",
+    },
+    DiagCode {
+        code: "E0103",
+        title: "shell syntax",
+        matches: &[Contains("shell syntax;")],
+        explanation: "\
+The source contains shell syntax that sh2 spells differently. sh2 variables are
+read by name and environment variables through `env`; commands run through
+`run(...)` rather than as bare words.
+
+    echo \"hello $USER\"              // error
+    print(\"hello \" & env.USER)      // sh2
+
+The help line under the error shows the sh2 version of the code it found.",
+    },
+    DiagCode {
+        code: "E0104",
+        title: "invalid case pattern",
+        matches: &[Prefix("glob pattern \""), Prefix("string case pattern")],
+        explanation: "\
+`glob(\"...\")` case patterns understand `*`, `?` and `[...]` classes such as
+`[0-9]`, `[!a-z]` or `[[:upper:]]`. A class must be closed and non-empty, a `]`
+needs its `[`, and a pattern may not start or end with whitespace, since it has
+to match exactly.
+
+A plain string pattern matches exactly, so one containing `*`, `?` or `[` is
+rejected as ambiguous:
+
+    case name {
+        \"*.txt\" => { ... }            // error
+        glob(\"*.txt\") => { ... }      // any name ending in .txt
+        literal(\"*.txt\") => { ... }   // only the text *.txt
+    }",
+    },
+    DiagCode {
+        code: "E0200",
+        title: "undefined variable",
+        matches: &[Prefix("undefined variable"), Prefix("cannot set undeclared variable")],
+        explanation: "\
+A variable is read or assigned with `set` before any `let` declares it in scope.
+
+    set count = 1        // error: not declared
+
+Declare it first with `let count = 1`. Variables declared inside a block are not
+visible after the block ends.",
+    },
+    DiagCode {
+        code: "E0201",
+        title: "variable declared twice",
+        matches: &[Contains("already declared in this scope")],
+        explanation: "\
+`let` declares a new variable, and a name can only be declared once per scope.
+
+    let n = 1
+    let n = 2            // error
+
+Use `set n = 2` to change the value of an existing variable.",
+    },
+    DiagCode {
+        code: "E0202",
+        title: "unknown function",
+        matches: &[Prefix("unknown function")],
+        explanation: "\
+An expression calls a function that is neither defined in the program nor a
+builtin. Expression calls are checked at compile time because the shell would
+otherwise fail only when the line runs.
+
+    let v = lenght(s)    // error: did you mean `len`?
+
+Fix the spelling, define the function with `func`, or run an external command
+with `run(\"cmd\", ...)`.",
+    },
+    DiagCode {
+        code: "E0203",
+        title: "unknown import alias",
+        matches: &[Prefix("unknown import alias")],
+        explanation: "\
+A qualified call `alias.func()` names an alias that no `import ... as alias`
+in this file declares.
+
+    import \"lib/fs.sh2\" as fs
+    func main() { fx.copy_all() }    // error
+
+Use one of the declared aliases or add the import.",
+    },
+    DiagCode {
+        code: "E0204",
+        title: "duplicate import alias",
+        matches: &[Prefix("duplicate import alias")],
+        explanation: "\
+Two imports in the same file use the same alias, so qualified calls through it
+would be ambiguous. Give each import its own alias.",
+    },
+    DiagCode {
+        code: "E0205",
+        title: "function defined twice",
+        matches: &[Contains("is already defined in"), Contains("was first defined here")],
+        explanation: "\
+Functions share one namespace across the program and its imports, so each name
+can be defined only once; the shell would silently keep the last one. The error
+points at both definitions. Rename or remove one of them.",
+    },
+    DiagCode {
+        code: "E0206",
+        title: "reserved name",
+        matches: &[Prefix("function name '"), Prefix("variable name '"), Prefix("parameter name '")],
+        explanation: "\
+The name collides with something the generated script depends on, and defining
+it would break the script in ways that are hard to trace:
+
+- names starting with `__sh2_`, which belong to the generated runtime
+- functions named like a compiler helper (`trim`, `replace`) or a shell builtin
+  (`test`, `local`, `printf`)
+- variables named like one the shell itself uses (`IFS`, `PATH`, `HOME`)
+
+Choose a different name. To change a shell variable on purpose, write
+`set env.PATH = ...` or use `with env { PATH: ... } { ... }`.",
+    },
+    DiagCode {
+        code: "E0207",
+        title: "import cycle",
+        matches: &[Prefix("import cycle detected")],
+        explanation: "\
+Files import each other in a loop. Move the shared functions into a separate file
+that both import.",
+    },
+    DiagCode {
+        code: "E0208",
+        title: "unreadable import",
+        matches: &[Prefix("failed to read"), Prefix("failed to resolve path")],
+        explanation: "\
+An imported file could not be found or read. Import paths are relative to the
+file that contains the `import`.",
+    },
+    DiagCode {
+        code: "E0209",
+        title: "sh block touches an unlisted variable",
+        matches: &[Prefix("sh block reads"), Prefix("sh block assigns")],
+        explanation: "\
+A `sh use(...) export(...) { ... }` block reads (`$name`) or assigns (`name=...`)
+an sh2 variable that is not in its lists:
+
+    let dir = \"/tmp\"
+    sh use() { \"ls $dir\" }          // error: dir is not in use(...)
+
+Add the variable to `use(...)` to read it, or to `export(...)` to hand a new value
+back to sh2. A plain `sh { ... }` block without lists is not checked.",
+    },
+    DiagCode {
+        code: "E0210",
+        title: "duplicate parameter",
+        matches: &[Prefix("duplicate parameter"), Contains("was first declared here")],
+        explanation: "\
+A function lists the same parameter name twice, so one of the arguments could
+never be read:
+
+    func copy(src, src) { ... }    // error
+
+Give each parameter its own name.",
+    },
+    DiagCode {
+        code: "E0300",
+        title: "wrong number of arguments",
+        matches: &[
+            Contains(" requires exactly "),
+            Contains(" requires at least "),
+            Contains(" requires 1 or 2 "),
+            Contains(" requires 2 or 3 "),
+            Contains(" requires exactly one "),
+            Contains(" takes no arguments"),
+            Contains(" takes at most "),
+            Contains("does not accept positional arguments"),
+            Contains("requires at least one argument"),
+            Contains("requires at least one positional argument"),
+            Contains(", which takes "),
+        ],
+        explanation: "\
+A builtin was called with too many or too few arguments. The message lists the
+arguments it expects, in order. Calls through a variable holding a function
+reference are checked against that function's parameters too.
+
+    let found = contains(items)            // error
+    let found = contains(items, \"x\")",
+    },
+    DiagCode {
+        code: "E0301",
+        title: "unknown or repeated option",
+        matches: &[
+            Prefix("unknown option"),
+            Prefix("unknown run option"),
+            Prefix("unknown run() option"),
+            Prefix("unknown sh() option"),
+            Prefix("unknown confirm() option"),
+            Prefix("unknown argument"),
+            Prefix("duplicate argument"),
+            Prefix("named argument"),
+            Prefix("allow_fail=true is not supported"),
+            Prefix("shell option is not supported"),
+            Contains("does not accept named arguments"),
+            Contains("specified more than once"),
+        ],
+        explanation: "\
+A named option (`name=value`) is not supported by this call, or was given twice.
+Only builtins accept options, and each accepts its own set; the message lists them.
+
+    run(\"make\", retries=3)         // error: unknown option
+
+User-defined functions take positional arguments only.",
+    },
+    DiagCode {
+        code: "E0302",
+        title: "statement used as a value",
+        matches: &[Contains("is a statement"), Contains("returns a value; use it"), Contains("returns a boolean; use it")],
+        explanation: "\
+Some builtins are statements and produce no value, while others produce a value
+and do nothing useful on their own.
+
+    let r = write_file(\"a.txt\", s)   // error: statement
+    read_file(\"a.txt\")               // error: value is discarded
+
+Call statements on their own line, and bind values with `let`.",
+    },
+    DiagCode {
+        code: "E0303",
+        title: "builtin used in the wrong place",
+        matches: &[
+            Contains("must be bound via let"),
+            Contains("can only be used"),
+            Contains("only valid"),
+            Contains("only allowed"),
+            Contains("is only supported on"),
+            Contains("only accepts"),
+            Contains("cannot be used in"),
+            Contains("returns a list; use it"),
+            Contains("options are not allowed"),
+            Contains("is not a valid condition"),
+            Contains("cannot be embedded"),
+            Contains("can only be bound"),
+            Contains("requires a producer"),
+        ],
+        explanation: "\
+The builtin is valid only in certain positions, because the shell code it turns
+into needs a variable to store into or a loop to drive it.
+
+    print(try_run(\"make\").status)       // error
+    let r = try_run(\"make\")
+    print(r.status)
+
+The message names the allowed positions.",
+    },
+    DiagCode {
+        code: "E0304",
+        title: "invalid option value",
+        matches: &[
+            Contains("must be true/false"),
+            Contains("must be a boolean"),
+            Contains("must be boolean"),
+            Contains("must be bool"),
+            Contains("must be a true/false"),
+            Contains("must be literal"),
+            Contains("must be an octal"),
+            Contains("must be a non-negative"),
+            Contains("class must be"),
+            Contains("must be an integer"),
+            Contains("must be a map"),
+            Contains("must be actual arguments"),
+            Contains("must be args()"),
+            Contains("expects a map literal"),
+            Contains("expects a list literal"),
+            Contains("expects text("),
+            Contains("is not a valid environment variable name"),
+            Contains("implies allow_missing"),
+            Contains("cannot be combined"),
+            Contains("must be a scalar"),
+            Contains("requires a scalar"),
+            Contains("requires a list of pids"),
+            Contains("requires a string literal name"),
+            Contains("must be a list"),
+            Contains("supports only"),
+            Contains("cannot contain nul"),
+            Prefix("cannot emit boolean/list value"),
+            Prefix("unsupported type in"),
+        ],
+        explanation: "\
+An argument or option has the wrong kind of value. Options that change the
+generated code, such as `allow_fail=` or `append=`, must be literals so the
+compiler can see them.
+
+    run(\"make\", allow_fail=flag)     // error
+    run(\"make\", allow_fail=true)",
+    },
+    DiagCode {
+        code: "E0305",
+        title: "invalid field access",
+        matches: &[Prefix("unknown field"), Prefix("field access")],
+        explanation: "\
+Fields (`r.status`, `r.stdout`, `r.stderr`) exist only on variables bound to a
+`try_run(...)` result, and parsed arguments expose `flags` and `positionals`.",
+    },
+    DiagCode {
+        code: "E0306",
+        title: "computed working directory",
+        matches: &[Prefix("cwd(...) requires a string literal path")],
+        explanation: "\
+`with cwd(...)` takes a literal path so the directory a block runs in is visible
+in the source.
+
+    with cwd(dir) { ... }          // error
+
+Use `cd(dir)` inside a `subshell { ... }` when the directory is computed.",
+    },
+    DiagCode {
+        code: "E0307",
+        title: "unacknowledged raw argument",
+        matches: &[Prefix("raw_arg() emits its value unquoted")],
+        explanation: "\
+`raw_arg(value)` passes its value unquoted, so the shell splits and globs it.
+Because that is an injection risk, the call must say so explicitly with
+`raw_arg(value, allow_split=true)`.",
+    },
+    DiagCode {
+        code: "E0308",
+        title: "invalid awk() program",
+        matches: &[Prefix("awk() program")],
+        explanation: "\
+The first argument of `awk(program, input)` must be a plain string literal that
+awk can parse: strings, regular expressions and brackets must be closed.
+
+    awk(\"{ print $\" & n & \" }\", data)     // error: not a literal
+
+Feed runtime values through the input instead, or select fields inside the
+program (`awk(\"{ print $2 }\", data)`).",
+    },
+    DiagCode {
+        code: "E0400",
+        title: "not supported on this target",
+        matches: &[
+            Contains("not supported in posix"),
+            Contains("not supported for posix"),
+            Contains("is not supported in bash target"),
+            Contains("requires bash"),
+            Contains("only supported in bash"),
+            Contains("is bash-only"),
+            Contains("on --target posix"),
+            Contains("not supported in posix sh target"),
+            Contains("posix target emitted bash-only"),
+        ],
+        explanation: "\
+The feature has no equivalent in POSIX sh (lists, maps, NUL-delimited reads,
+process substitution and similar), so it can only be compiled with `--target bash`.
+Either compile for bash or rewrite the code without the feature.",
+    },
+    DiagCode {
+        code: "E0500",
+        title: "redirect error",
+        matches: &[
+            Contains("redirect"),
+            Contains("inherit_stdout()"),
+            Contains("inherit_stderr()"),
+            Contains("cannot append to stdin"),
+            Contains("stdin file()"),
+            Contains("stderr="),
+            Contains("pipeline segment stderr"),
+        ],
+        explanation: "\
+A `with redirect { ... }` block or a `stderr=` option combines targets that cannot
+work together, such as sending stdout to stderr and stderr to stdout at once, or
+mixing append and overwrite for the same stream.
+
+    with redirect { stdout: [file(\"a.log\"), file(\"b.log\", append=true)] } { ... }",
+    },
+    DiagCode {
+        code: "E0600",
+        title: "sandbox guard cannot check command",
+        matches: &[Contains("cannot be used with --sandbox-guard")],
+        explanation: "\
+With `--sandbox-guard`, every command the script runs is checked against the
+manifest. Raw shell code (`sh(...)`) and commands whose names are computed at run
+time cannot be checked, so they are rejected.",
+    },
+    DiagCode {
+        code: "E0601",
+        title: "non-deterministic output",
+        matches: &[Prefix("--deterministic:")],
+        explanation: "\
+`--deterministic` compiles the script twice and the outputs differed. This is a
+compiler bug; please report it with the script.",
+    },
+    DiagCode {
+        code: "E0602",
+        title: "script over size budget",
+        matches: &[Prefix("--max-size:")],
+        explanation: "\
+The generated script is larger than the byte budget given with `--max-size`. The
+message lists how many bytes the prelude and each function contribute.
+
+Shrink the script with `--minify`, move shared helpers out with `--runtime-lib`,
+or split rarely used functions into a separate script. Use `--warn-size` instead
+to report the overrun without failing the build.",
+    },
+    DiagCode {
+        code: "E0603",
+        title: "invalid @service settings",
+        matches: &[Prefix("@service")],
+        explanation: "\
+`@service(...)` on the entry function configures the unit written by
+`--emit-systemd`. Each argument is a `\"key=value\"` string; the keys are
+description, after, type, exec, restart, restart_sec, user, group, working_dir,
+env_file and wanted_by, and `type` and `restart` take systemd's own values.
+
+    @service(\"description=Queue worker\", \"restart=always\", \"user=worker\")
+    func main() { ... }
+
+The attribute is only read from the entry function (`main`, or the `--entry`
+function); anywhere else it is rejected.",
+    },
+    DiagCode {
+        code: "E0604",
+        title: "invalid @cron settings",
+        matches: &[Prefix("@cron")],
+        explanation: "\
+`@cron(...)` on the entry function configures the wrapper written by
+`--emit-cron`. Each argument is a `\"key=value\"` string; the keys are log_dir,
+lock_file, mail and webhook.
+
+    @cron(\"log_dir=/var/log/backup\", \"mail=ops@example.com\")
+    func main() { ... }
+
+The attribute is only read from the entry function (`main`, or the `--entry`
+function); anywhere else it is rejected.",
+    },
+    DiagCode {
+        code: "E0605",
+        title: "invalid @pure function",
+        matches: &[Prefix("@pure")],
+        explanation: "\
+A `@pure` function may only bind locals with `let`, branch with `if`, and
+`return` a value built from strings, numbers, arithmetic, comparisons,
+`len()`, the string builtins (trim, before, after, replace, default,
+coalesce) and other `@pure` functions. Commands, output, files and the
+environment are all off limits, because calls with literal arguments are
+evaluated by the compiler instead of at runtime.
+
+    @pure
+    func slug(name) {
+        return replace(trim(name), \" \", \"-\")
+    }
+
+Drop `@pure` from a function that needs to do more than compute a value.",
+    },
+    DiagCode {
+        code: "E0606",
+        title: "invalid @inline function",
+        matches: &[Prefix("@inline")],
+        explanation: "\
+`@inline` expands a function at each `let x = f(...)` call site instead of
+calling it in a subshell, so its body has to be simple enough to copy: any
+number of `let`s followed by a `return`, using only strings, numbers,
+variables, `&` and arithmetic.
+
+    @inline
+    func label(env, n) {
+        return env & \"-\" & n
+    }
+
+A function that also has `@cache` cannot be inlined. Remove `@inline` from a
+function that needs to do more.",
+    },
+    DiagCode {
+        code: "E0900",
+        title: "internal compiler error",
+        matches: &[
+            Prefix("internal error"),
+            Prefix("internal:"),
+            Contains("should have been rejected"),
+            Contains("implemented only for"),
+            Contains("expects command or"),
+            Contains("expects a pipe or"),
+            Contains("to be materialized"),
+            Contains("called on a non-"),
+            Contains("failed to retrieve source"),
+        ],
+        explanation: "\
+The compiler reached a state it should not be able to reach. This is a bug in
+sh2c, not in your script; please report it with the smallest script that
+triggers it.",
+    },
+    DiagCode {
+        code: UNCATEGORIZED,
+        title: "uncategorized error",
+        matches: &[],
+        explanation: "\
+This diagnostic has not been given its own code yet. The message itself describes
+the problem; if it is unclear, please report it so it can get a code and an
+extended description.",
+    },
+    DiagCode {
+        code: "W0001",
+        title: "tainted value reaches the shell",
+        matches: &[Prefix("tainted value from")],
+        explanation: "\
+A value that comes from outside the script (arguments, environment, input) flows
+into shell code parsed at run time, such as the command string of `sh(...)`.
+Whoever controls that input can run arbitrary commands.
+
+    sh(\"ls \" & arg(1))
+
+Pass such values as data: `run(\"ls\", arg(1))` or `sh(\"ls \\\"$1\\\"\", args=[arg(1)])`,
+or validate them with `matches()` first. `--deny-tainted-sh` turns this warning
+into an error.",
+    },
+    DiagCode {
+        code: "W0002",
+        title: "unused import alias",
+        matches: &[Prefix("unused import alias")],
+        explanation: "\
+An `import \"file\" as alias` whose alias is never used in a qualified call
+(`alias.func()`). Its functions are not compiled into the script.
+
+Remove the import, or call through the alias. `sh2c --fix` removes it.",
+    },
+    DiagCode {
+        code: "W0003",
+        title: "missing \"--\" before an input argument",
+        matches: &[Contains("before any \"--\"")],
+        explanation: "\
+A file utility such as `rm`, `mv` or `chmod` receives a value from outside the
+script (`arg()`, `args`, `env`, `input()`, or a variable assigned from one), with
+no `--` before it. A value starting with `-` is parsed as an option:
+
+    let name = arg(1)
+    run(\"rm\", name)          // name = \"-rf\" removes more than intended
+
+Insert `\"--\"` to end option parsing: `run(\"rm\", \"--\", name)`.
+`sh2c --fix` inserts it.",
+    },
+    DiagCode {
+        code: "W0004",
+        title: "call to a deprecated function",
+        matches: &[Prefix("call to deprecated function")],
+        explanation: "\
+The called function is marked `@deprecated`, usually by the author of an imported
+library, and may be removed in a later version:
+
+    @deprecated(\"use fetch_all()\")
+    func fetch() { ... }
+
+Follow the hint in the message and switch to the replacement.",
+    },
+    DiagCode {
+        code: "W0005",
+        title: "stdin read inside each_line",
+        matches: &[Prefix("stdin read inside each_line")],
+        explanation: "\
+The body of an `each_line` loop runs with the pipeline's output on stdin, so
+`input()`, `confirm()` or `for x in stdin_lines()` inside it reads the lines
+meant for the loop instead of the terminal or the script's input:
+
+    run(\"ls\") | each_line f {
+        let answer = input(\"delete \" & f & \"? \")   // eats the next file name
+    }
+
+Write `each_line(fd=3) f { ... }` so the loop reads its lines on fd 3 and the
+body keeps the script's stdin.",
+    },
+    DiagCode {
+        code: "W0006",
+        title: "possibly unset variable read by shell code",
+        matches: &[Contains("may be unset here: shell code reads it")],
+        explanation: "\
+sh2 expressions may only read variables assigned on every path, but raw shell
+code in `sh(\"...\")` or a plain `sh { ... }` block is not checked that way. A
+`$name` there that refers to a variable only some branches assign expands to
+nothing, or aborts the script under `set -u`:
+
+    if verbose {
+        let level = \"debug\"
+    }
+    sh(\"run-tool --log $level\")   // unset when not verbose
+
+Assign the variable before the branch and update it with `set`, or pass it
+through `sh use(level) { ... }`, which requires it to be assigned.",
+    },
+    DiagCode {
+        code: "W0007",
+        title: "let shadows an earlier declaration",
+        matches: &[Contains("shadows an earlier declaration")],
+        explanation: "\
+A `let` inside a block may reuse a name that an earlier, finished branch
+declared, but both declarations are the same shell variable. When the earlier
+branch may have run, the inner `let` silently replaces its value, and when the
+inner block is skipped the old value is still there:
+
+    if cached {
+        let path = cache_path()
+    }
+    for f in files {
+        let path = f                   // shadows the cached path
+    }
+
+Give the inner variable its own name, or declare the variable once before the
+branches and assign it with `set`.",
+    },
+    DiagCode {
+        code: "W0008",
+        title: "result status never checked",
+        matches: &[Prefix("status of '")],
+        explanation: "\
+`try_run(...)` and `capture(..., allow_fail=true)` keep the script running
+when the command fails and record the exit code with the result. If nothing
+reads it, the failure goes unnoticed:
+
+    let r = try_run(\"make\")
+    print(r.stdout)                      // make may have failed
+
+Inspect `r.status` (or `status()` right after the call), or use `run(...)` /
+`capture(...)` without allow_fail so a failure stops the script.",
+    },
+    DiagCode {
+        code: "W0009",
+        title: "unreachable case pattern",
+        matches: &[Prefix("unreachable case pattern"), Prefix("duplicate case pattern"), Contains("and can never match")],
+        explanation: "\
+`case` tries its patterns in order and runs the first arm that matches, so a
+pattern that an earlier one already covers is dead code:
+
+    case file {
+        glob(\"*.log\") => { rotate(file) }
+        glob(\"app.*.log\") => { ... }   // shadowed by glob(\"*.log\")
+        \"a\" | \"a\" => { ... }         // duplicate
+        _ => { skip(file) }
+        \"README\" => { ... }            // after `_`
+    }
+
+Move the more specific pattern before the general one, or remove it.",
+    },
+];
+
+/// The code for a diagnostic message built without one.
+pub fn classify(msg: &str) -> &'static str {
+    let msg = msg.trim_start().to_lowercase();
+    CODES
+        .iter()
+        .find(|c| {
+            c.matches.iter().any(|m| match m {
+                Prefix(p) => msg.starts_with(p),
+                Contains(s) => msg.contains(s),
+            })
+        })
+        .map_or(UNCATEGORIZED, |c| c.code)
+}
+
+/// `msg` with `code` appended to the first line: `... not supported [E0400]`.
+pub fn tag(msg: &str, code: &str) -> String {
+    debug_assert!(lookup(code).is_some(), "unknown diagnostic code {}", code);
+    match msg.split_once('\n') {
+        Some((first, rest)) => format!("{} [{}]\n{}", first, code, rest),
+        None => format!("{} [{}]", msg, code),
+    }
+}
+
+/// `msg` tagged with `code`, or with the code `classify` picks when there is none.
+pub fn tagged_with(msg: &str, code: Option<&str>) -> String {
+    tag(msg, code.unwrap_or_else(|| classify(msg)))
+}
+
+/// `msg` tagged with the code `classify` picks for it.
+pub fn tagged(msg: &str) -> String {
+    tagged_with(msg, None)
+}
+
+/// Looks up a code for `sh2c explain`; case-insensitive, so `e0200` works too.
+pub fn lookup(code: &str) -> Option<&'static DiagCode> {
+    CODES.iter().find(|c| c.code.eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn codes_are_unique() {
+        let mut seen = HashSet::new();
+        for c in CODES {
+            assert!(seen.insert(c.code), "duplicate code {}", c.code);
+            assert!(c.matches.iter().all(|m| match m {
+                Prefix(s) | Contains(s) => *s == s.to_lowercase(),
+            }), "{} has a pattern that is not lower-case", c.code);
+        }
+    }
+
+    #[test]
+    fn classifies_by_message() {
+        assert_eq!(classify("Expected RParen, got Run"), "E0100");
+        assert_eq!(classify("undefined variable 'x'"), "E0200");
+        assert_eq!(classify("matches() requires exactly 2 arguments (text, regex)"), "E0300");
+        assert_eq!(classify("lines() not supported in POSIX"), "E0400");
+        assert_eq!(classify("something new"), UNCATEGORIZED);
+    }
+
+    #[test]
+    fn tag_goes_on_the_first_line() {
+        assert_eq!(tagged("undefined variable 'x'"), "undefined variable 'x' [E0200]");
+        assert_eq!(tagged("Expected RParen\nhelp: close it"), "Expected RParen [E0100]\nhelp: close it");
+    }
+
+    #[test]
+    fn a_given_code_wins_over_the_message() {
+        assert_eq!(tagged_with("undefined variable 'x'", Some("E0303")), "undefined variable 'x' [E0303]");
+        assert_eq!(tagged_with("undefined variable 'x'", None), "undefined variable 'x' [E0200]");
+    }
+
+    #[test]
+    fn lookup_ignores_case() {
+        assert_eq!(lookup("e0202").map(|c| c.title), Some("unknown function"));
+        assert!(lookup("E9999").is_none());
+    }
+}
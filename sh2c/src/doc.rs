@@ -0,0 +1,250 @@
+//! `sh2c doc --man`: a roff man page for a compiled tool.
+//!
+//! The text comes from `main`'s `///` doc comment, or else the `#` comment
+//! block directly above `func main` (or, failing that, the comment block at the
+//! top of the file). Its first line is
+//! the one-line summary, optionally written `name - summary`; lines starting
+//! with `--flag` describe options; everything else is the description, with
+//! blank comment lines separating paragraphs.
+//!
+//! Options are also collected from the code: every `.flags["--name"]` lookup on
+//! a `parse_args()` result is listed even when the comment says nothing about
+//! it. `main`'s parameters make up the synopsis.
+//!
+//! The same page, as plain text, is what a compiled script prints for
+//! `--help` when `main` has a `///` doc comment (see `help_text`).
+
+use crate::ast::Program;
+use crate::lexer::{self, TokenKind};
+use crate::span::SourceMap;
+use std::path::Path;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ManPage {
+    pub name: String,
+    pub summary: String,
+    pub params: Vec<String>,
+    pub paragraphs: Vec<String>,
+    /// Flag (`--tag`), its argument placeholder if documented, and description.
+    pub options: Vec<(String, String, String)>,
+}
+
+/// Collects the man page contents for `program`, named after `script`.
+pub fn man_page(program: &Program, script: &Path) -> ManPage {
+    let mut page = ManPage {
+        name: script.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    let Some(sm) = program.source_maps.get(&program.entry_file) else {
+        return page;
+    };
+    let main = program.functions.iter().find(|f| f.name == "main" && f.file == program.entry_file);
+    if let Some(main) = main {
+        page.params = main.params.clone();
+    }
+
+    let start = main.map(|f| f.attrs.iter().map(|a| a.span.start).fold(f.span.start, usize::min));
+    let mut comment = match main {
+        Some(f) if !f.doc.is_empty() => f.doc.clone(),
+        _ => start.map(|pos| comment_above(sm, pos)).unwrap_or_default(),
+    };
+    if comment.is_empty() {
+        comment = header_comment(sm.src());
+    }
+
+    let mut lines = comment.into_iter();
+    if let Some(first) = lines.next() {
+        match first.split_once(" - ") {
+            Some((name, summary)) if !name.contains(' ') => {
+                page.name = name.to_string();
+                page.summary = summary.to_string();
+            }
+            _ => page.summary = first,
+        }
+    }
+    let mut paragraph = String::new();
+    for line in lines {
+        if line.starts_with("--") {
+            let (flag, rest) = line.split_once(' ').unwrap_or((&line, ""));
+            let rest = rest.trim_start();
+            // `--tag NAME  Image tag`: an upper-case word before the text is the argument.
+            let (arg, desc) = match rest.split_once(' ') {
+                Some((arg, desc)) if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_uppercase() || c == '_') => {
+                    (arg.to_string(), desc.trim_start().to_string())
+                }
+                _ => (String::new(), rest.to_string()),
+            };
+            page.options.push((flag.to_string(), arg, desc));
+        } else if line.is_empty() {
+            if !paragraph.is_empty() {
+                page.paragraphs.push(std::mem::take(&mut paragraph));
+            }
+        } else {
+            if !paragraph.is_empty() {
+                paragraph.push(' ');
+            }
+            paragraph.push_str(&line);
+        }
+    }
+    if !paragraph.is_empty() {
+        page.paragraphs.push(paragraph);
+    }
+
+    for flag in flags_used(sm, &program.entry_file) {
+        if !page.options.iter().any(|(f, _, _)| *f == flag) {
+            page.options.push((flag, String::new(), String::new()));
+        }
+    }
+    page
+}
+
+/// `--help` text for a script whose `main` has a `///` doc comment, unless the
+/// script reads `--help` itself.
+pub fn help_text(program: &Program) -> Option<String> {
+    let main = program.functions.iter().find(|f| f.name == "main" && f.file == program.entry_file)?;
+    let sm = program.source_maps.get(&program.entry_file)?;
+    if main.doc.is_empty() || flags_used(sm, &program.entry_file).iter().any(|f| f == "--help") {
+        return None;
+    }
+    Some(man_page(program, Path::new(&program.entry_file)).to_help())
+}
+
+impl ManPage {
+    /// Plain-text rendering: usage line, summary, description and options.
+    pub fn to_help(&self) -> String {
+        let mut usage = format!("Usage: {}", self.name);
+        if !self.options.is_empty() {
+            usage.push_str(" [OPTIONS]");
+        }
+        for p in &self.params {
+            usage.push_str(&format!(" <{}>", p));
+        }
+        let mut blocks = vec![usage];
+        if !self.summary.is_empty() {
+            blocks.push(self.summary.clone());
+        }
+        blocks.extend(self.paragraphs.iter().cloned());
+        if !self.options.is_empty() {
+            let heads: Vec<String> = self
+                .options
+                .iter()
+                .map(|(flag, arg, _)| if arg.is_empty() { flag.clone() } else { format!("{} {}", flag, arg) })
+                .collect();
+            let width = heads.iter().map(String::len).max().unwrap_or(0);
+            let mut options = "Options:".to_string();
+            for (head, (_, _, desc)) in heads.iter().zip(&self.options) {
+                let line = format!("  {:width$}  {}", head, desc, width = width);
+                options.push('\n');
+                options.push_str(line.trim_end());
+            }
+            blocks.push(options);
+        }
+        blocks.join("\n\n")
+    }
+
+    pub fn to_roff(&self) -> String {
+        let mut out = format!(".TH {} 1\n.SH NAME\n{}", escape(&self.name.to_uppercase()), escape(&self.name));
+        if !self.summary.is_empty() {
+            out.push_str(&format!(" \\- {}", escape(&self.summary)));
+        }
+        out.push_str(&format!("\n.SH SYNOPSIS\n.B {}\n", escape(&self.name)));
+        if !self.options.is_empty() {
+            out.push_str("[\\fIOPTIONS\\fR]\n");
+        }
+        for p in &self.params {
+            out.push_str(&format!("\\fI{}\\fR\n", escape(p)));
+        }
+        if !self.paragraphs.is_empty() {
+            out.push_str(".SH DESCRIPTION\n");
+            out.push_str(&self.paragraphs.iter().map(|p| escape(p)).collect::<Vec<_>>().join("\n.PP\n"));
+            out.push('\n');
+        }
+        if !self.options.is_empty() {
+            out.push_str(".SH OPTIONS\n");
+            for (flag, arg, desc) in &self.options {
+                out.push_str(&format!(".TP\n\\fB{}\\fR", escape(flag)));
+                if !arg.is_empty() {
+                    out.push_str(&format!(" \\fI{}\\fR", escape(arg)));
+                }
+                out.push('\n');
+                if !desc.is_empty() {
+                    out.push_str(&escape(desc));
+                    out.push('\n');
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Text of the `#` lines directly above `pos`, without the `#` markers.
+fn comment_above(sm: &SourceMap, pos: usize) -> Vec<String> {
+    let (line, _) = sm.line_col(pos);
+    let mut lines = Vec::new();
+    for n in (1..line).rev() {
+        match comment_text(sm.line_snippet(n)) {
+            Some(text) => lines.push(text),
+            None => break,
+        }
+    }
+    lines.reverse();
+    trim_blank(lines)
+}
+
+/// The comment block the file starts with, after any `#!` line.
+fn header_comment(src: &str) -> Vec<String> {
+    let lines = src
+        .lines()
+        .skip_while(|l| l.starts_with("#!"))
+        .map_while(comment_text)
+        .collect();
+    trim_blank(lines)
+}
+
+fn comment_text(line: &str) -> Option<String> {
+    let rest = line.trim_start().strip_prefix('#')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest).trim_end().to_string())
+}
+
+fn trim_blank(mut lines: Vec<String>) -> Vec<String> {
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    let lead = lines.iter().take_while(|l| l.is_empty()).count();
+    lines.split_off(lead)
+}
+
+/// `--name` keys of every `.flags["--name"]` lookup in the file, in order.
+fn flags_used(sm: &SourceMap, file: &str) -> Vec<String> {
+    let Ok(tokens) = lexer::lex(sm, file) else { return Vec::new() };
+    let kinds: Vec<&TokenKind> = tokens.iter().map(|t| &t.kind).collect();
+    let mut flags: Vec<String> = Vec::new();
+    for w in kinds.windows(5) {
+        if let [TokenKind::Dot, TokenKind::Ident(field), TokenKind::LBracket, TokenKind::String(key), TokenKind::RBracket] = w
+            && field == "flags"
+            && key.starts_with("--")
+            && !flags.contains(key)
+        {
+            flags.push(key.clone());
+        }
+    }
+    flags
+}
+
+/// Escapes text for roff: backslashes, hyphens, and a leading `.` or `'`.
+fn escape(text: &str) -> String {
+    let out = text.replace('\\', "\\e").replace('-', "\\-");
+    if out.starts_with(['.', '\'']) { format!("\\&{}", out) } else { out }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_roff_control_characters() {
+        assert_eq!(escape("--dry-run"), "\\-\\-dry\\-run");
+        assert_eq!(escape(".hidden"), "\\&.hidden");
+        assert_eq!(escape("a\\b"), "a\\eb");
+    }
+}
@@ -1,10 +1,19 @@
+use crate::audit;
 use crate::codegen::{self, TargetShell};
+use crate::cron;
+use crate::doc;
+use crate::error::CompileError;
+use crate::fixes;
 use crate::loader;
 use crate::lower;
-use crate::semantics;
+use crate::sandbox;
+use crate::stages;
+use crate::systemd;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
@@ -13,15 +22,61 @@ pub enum Mode {
     EmitAst,
     EmitIr,
     EmitSh,
+    /// Compile, then report quoting-sensitive sites instead of the script.
+    AuditQuoting,
+    /// Apply machine-applicable suggestions to the source files in place.
+    Fix,
+    /// List the prelude helpers the script needs, their size, and what needed them.
+    PreludeReport,
+    /// Emit a file that only defines the module's functions, for sourcing.
+    EmitLib,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CompileOptions {
     pub target: TargetShell,
     pub include_diagnostics: bool,
     pub out_path: Option<PathBuf>,
     pub chmod_x: bool,
     pub mode: Mode,
+    /// Turn taint warnings (external input reaching sh()/exec) into errors.
+    pub deny_tainted_sh: bool,
+    /// Write the sandbox manifest (statically known commands) to this path.
+    pub sandbox_manifest: Option<PathBuf>,
+    /// Route computed command names through a runtime check against the manifest.
+    pub sandbox_guard: bool,
+    /// Compile a second time and fail unless both outputs are byte-identical.
+    pub deterministic: bool,
+    /// Source the prelude from this shared runtime file instead of inlining it, and
+    /// write the file. Relative paths are relative to the output script.
+    pub runtime_lib: Option<PathBuf>,
+    /// Drop comments and blank lines and shorten internal names in the output.
+    pub minify: bool,
+    /// Fail when the emitted script is larger than this many bytes.
+    pub max_size: Option<usize>,
+    /// Warn when the emitted script is larger than this many bytes.
+    pub warn_size: Option<usize>,
+    /// Write the script to `out_path` while it is generated instead of returning
+    /// it; the returned string is then empty. Without `out_path` it is ignored. Options that need the whole
+    /// script first (`minify`, size budgets, `deterministic`, the runtime library
+    /// and sandbox guard) fall back to building it in memory.
+    pub stream: bool,
+    /// Function the script calls on its last line instead of `main`.
+    pub entry: Option<String>,
+    /// For `compile_project`: write each input to `<out_dir>/<name>.sh`. Single-file
+    /// compiles use `out_path` and ignore this.
+    pub out_dir: Option<PathBuf>,
+    /// Also write a systemd `.service` unit next to `out_path` (see `systemd`);
+    /// only scripts get one, not libraries or the analysis modes.
+    pub emit_systemd: bool,
+    /// Also write a cron wrapper and crontab line for this schedule next to
+    /// `out_path` (see `cron`).
+    pub emit_cron: Option<String>,
+    /// Compile for `sh2do --debug`: pause at breakpoints with an interactive prompt.
+    pub debug: bool,
+    /// Time each function-body statement and print the slowest lines when the
+    /// script exits. Libraries (`--emit-lib`) are never instrumented.
+    pub profile: bool,
 }
 
 impl Default for CompileOptions {
@@ -32,10 +87,26 @@ impl Default for CompileOptions {
             out_path: None,
             chmod_x: false, // Default: false (library hygiene)
             mode: Mode::Default, // Default: EmitSh behavior
+            deny_tainted_sh: false,
+            sandbox_manifest: None,
+            sandbox_guard: false,
+            deterministic: false,
+            runtime_lib: None,
+            minify: false,
+            max_size: None,
+            warn_size: None,
+            stream: false,
+            entry: None,
+            out_dir: None,
+            emit_systemd: false,
+            emit_cron: None,
+            debug: false,
+            profile: false,
         }
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct DriverError {
     pub code: i32,
     pub msg: String,
@@ -51,84 +122,511 @@ impl DriverError {
     }
 }
 
+/// Wall-clock time spent in each compiler stage (`sh2c --timings`).
+/// `check` covers semantic analysis and the taint/audit passes; `total` also
+/// includes file IO, import resolution and any `--deterministic` recompile.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageTimings {
+    pub lex: Duration,
+    pub parse: Duration,
+    pub check: Duration,
+    pub lower: Duration,
+    pub codegen: Duration,
+    pub total: Duration,
+}
+
+impl StageTimings {
+    pub fn report(&self) -> String {
+        let mut out = String::from("timings:\n");
+        for (stage, d) in [
+            ("lex", self.lex),
+            ("parse", self.parse),
+            ("check", self.check),
+            ("lower", self.lower),
+            ("codegen", self.codegen),
+            ("total", self.total),
+        ] {
+            out.push_str(&format!("  {:<8}{:>10.3} ms\n", stage, d.as_secs_f64() * 1000.0));
+        }
+        out
+    }
+}
+
 pub fn compile_file(path: &Path, options: CompileOptions) -> Result<String, DriverError> {
-    let diag_base_dir = path.parent()
-        .map(|p| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf()));
-        
-    // IO Check: Ensure file exists and is readable to return correct exit code (1) vs compile error (2)
+    compile_file_with_warnings(path, options).map(|(out, _)| out)
+}
+
+/// Like `compile_file`, but also returns non-fatal diagnostics (taint warnings)
+/// for the caller to print.
+pub fn compile_file_with_warnings(path: &Path, options: CompileOptions) -> Result<(String, Vec<String>), DriverError> {
+    compile_file_timed(path, options).map(|(out, warnings, _)| (out, warnings))
+}
+
+/// Like `compile_file_with_warnings`, but also reports per-stage durations.
+pub fn compile_file_timed(path: &Path, options: CompileOptions) -> Result<(String, Vec<String>, StageTimings), DriverError> {
+    compile_timed(path, options, None)
+}
+
+/// Like `compile_file_timed`, but files already parsed for an earlier input of the
+/// same build (shared imports) come from `cache` instead of being read again.
+pub fn compile_file_cached(
+    path: &Path,
+    options: CompileOptions,
+    cache: &mut loader::ParseCache,
+) -> Result<(String, Vec<String>, StageTimings), DriverError> {
+    compile_timed(path, options, Some(cache))
+}
+
+fn compile_timed(
+    path: &Path,
+    options: CompileOptions,
+    cache: Option<&mut loader::ParseCache>,
+) -> Result<(String, Vec<String>, StageTimings), DriverError> {
+    let started = Instant::now();
+    let mut timings = StageTimings::default();
+    let (out, warnings) = compile_stages(path, options, &mut timings, cache, &mut Vec::new())?;
+    timings.total = started.elapsed();
+    Ok((out, warnings, timings))
+}
+
+/// What `compile_project` produced for one input.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    /// The input file, as given.
+    pub entry: PathBuf,
+    /// Where the output was written; `None` without `out_dir` or when compiling failed.
+    pub output: Option<PathBuf>,
+    /// Canonical paths of every file the entry imports, directly or not, sorted.
+    pub imports: Vec<PathBuf>,
+    /// Size of the emitted output in bytes (0 when compiling failed).
+    pub size: usize,
+    /// The emitted output, kept only when there is no `out_dir` to write it to.
+    pub script: Option<String>,
+    /// Non-fatal diagnostics (taint, fix and deprecation warnings).
+    pub warnings: Vec<String>,
+    pub error: Option<DriverError>,
+    pub timings: StageTimings,
+}
+
+impl Artifact {
+    pub fn ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The file `compile_project` writes for `input`: `<out_dir>/<file stem>.sh`.
+pub fn artifact_path(out_dir: &Path, input: &Path) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    out_dir.join(format!("{}.sh", stem))
+}
+
+/// Compiles every input in one pass, sharing parsed imports between them, and
+/// describes each result instead of stopping at the first failure. With
+/// `options.out_dir` each output is written to [`artifact_path`]; without it
+/// nothing is written and each artifact carries its `script` instead. `out_path`
+/// and `stream` are ignored.
+pub fn compile_project(inputs: &[PathBuf], options: &CompileOptions) -> Vec<Artifact> {
+    let dir_error = options.out_dir.as_ref().and_then(|dir| {
+        std::fs::create_dir_all(dir)
+            .err()
+            .map(|e| DriverError::io(format!("Failed to create {}: {}", dir.display(), e)))
+    });
+    let mut cache = loader::ParseCache::default();
+    let mut written: Vec<PathBuf> = Vec::new();
+
+    inputs
+        .iter()
+        .map(|input| {
+            let output = options.out_dir.as_deref().map(|dir| artifact_path(dir, input));
+            let mut artifact = Artifact {
+                entry: input.clone(),
+                output: None,
+                imports: Vec::new(),
+                size: 0,
+                script: None,
+                warnings: Vec::new(),
+                error: None,
+                timings: StageTimings::default(),
+            };
+            if let Some(e) = &dir_error {
+                artifact.error = Some(e.clone());
+                return artifact;
+            }
+            if let Some(out) = output.as_ref().filter(|out| written.contains(out)) {
+                artifact.error = Some(DriverError::io(format!(
+                    "{} would overwrite {}, written for an earlier input",
+                    input.display(),
+                    out.display()
+                )));
+                return artifact;
+            }
+
+            let opts = CompileOptions { out_path: output.clone(), stream: false, ..options.clone() };
+            let started = Instant::now();
+            let mut sources = Vec::new();
+            match compile_stages(input, opts, &mut artifact.timings, Some(&mut cache), &mut sources) {
+                Ok((out, warnings)) => {
+                    artifact.size = out.len();
+                    artifact.warnings = warnings;
+                    match &output {
+                        Some(path) => written.push(path.clone()),
+                        None => artifact.script = Some(out),
+                    }
+                    artifact.output = output;
+                }
+                Err(e) => artifact.error = Some(e),
+            }
+            artifact.timings.total = started.elapsed();
+            let entry = std::fs::canonicalize(input).ok();
+            sources.retain(|s| Some(s) != entry.as_ref());
+            sources.sort();
+            artifact.imports = sources;
+            artifact
+        })
+        .collect()
+}
+
+/// Where the compiler writes the runtime library. The script resolves a relative
+/// path against its own directory, so do the same with the output file here.
+fn runtime_lib_file(lib: &Path, out_path: Option<&Path>) -> PathBuf {
+    match out_path.and_then(Path::parent) {
+        Some(dir) if lib.is_relative() => dir.join(lib),
+        _ => lib.to_path_buf(),
+    }
+}
+
+/// Leaves an up-to-date library untouched so scripts sharing it see no churn.
+fn write_runtime_lib(file: &Path, target: TargetShell) -> Result<(), DriverError> {
+    let lib = codegen::emit_runtime_lib(target);
+    if std::fs::read_to_string(file).is_ok_and(|existing| existing == lib) {
+        return Ok(());
+    }
+    std::fs::write(file, lib)
+        .map_err(|e| DriverError::io(format!("Failed to write runtime library {}: {}", file.display(), e)))
+}
+
+/// A file written next to the output script: path, contents, and whether it
+/// is executable.
+type Companion = (PathBuf, String, bool);
+
+/// The `--emit-systemd` unit and `--emit-cron` wrapper and crontab line for the
+/// output script, if asked for.
+fn deployment_files(ast: &crate::ast::Program, options: &CompileOptions) -> Result<Vec<Companion>, DriverError> {
+    let wanted = (options.emit_systemd || options.emit_cron.is_some())
+        && matches!(options.mode, Mode::Default | Mode::EmitSh);
+    if !wanted {
+        return Ok(Vec::new());
+    }
+    let Some(out_path) = &options.out_path else {
+        let flag = if options.emit_systemd { "--emit-systemd" } else { "--emit-cron" };
+        return Err(DriverError::io(format!("{} requires an output file", flag)));
+    };
+    let entry = options.entry.as_deref().unwrap_or("main");
+    let compile_err = |code: &'static str| move |msg: String| DriverError::compile(CompileError::coded(code, msg).to_string());
+
+    let mut files = Vec::new();
+    if options.emit_systemd {
+        let unit = systemd::unit(ast, entry, out_path).map_err(compile_err("E0603"))?;
+        files.push((systemd::unit_path(out_path), unit, false));
+    }
+    if let Some(schedule) = &options.emit_cron {
+        let cron = cron::files(ast, entry, out_path, schedule).map_err(compile_err("E0604"))?;
+        files.push((cron.wrapper_path, cron.wrapper, true));
+        files.push((cron.crontab_path, cron.crontab, false));
+    }
+    Ok(files)
+}
+
+fn write_companions(files: &[Companion]) -> Result<(), DriverError> {
+    for (path, text, executable) in files {
+        std::fs::write(path, text)
+            .map_err(|e| DriverError::io(format!("Failed to write to {}: {}", path.display(), e)))?;
+        if *executable {
+            set_executable(path);
+        }
+    }
+    Ok(())
+}
+
+fn over_budget(flag: &str, limit: usize, sizes: &codegen::size::SizeBreakdown) -> String {
+    format!(
+        "{}: script is {} bytes, {} over the budget of {} bytes\n{}",
+        flag,
+        sizes.total,
+        sizes.total - limit,
+        limit,
+        sizes.report().trim_end()
+    )
+}
+
+/// A bare file name has an empty parent; treat it as "." so diagnostics stay
+/// relative instead of falling back to the absolute path of the source.
+fn diag_base_dir(path: &Path) -> Option<PathBuf> {
+    path.parent()
+        .map(|p| if p.as_os_str().is_empty() { Path::new(".") } else { p })
+        .map(|p| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf()))
+}
+
+/// IO Check: Ensure file exists and is readable to return correct exit code (1) vs compile error (2)
+fn check_readable(path: &Path) -> Result<(), DriverError> {
     if !path.exists() {
         return Err(DriverError::io(format!("File not found: {}", path.display())));
     }
     if let Err(e) = std::fs::File::open(path) {
         return Err(DriverError::io(format!("Unable to read file: {} ({})", path.display(), e)));
     }
+    Ok(())
+}
+
+/// `sh2c doc --man`: the roff man page for the script at `path` (see `doc`).
+pub fn man_page(path: &Path) -> Result<String, DriverError> {
+    check_readable(path)?;
+    let program = loader::load(path)
+        .map_err(|d| DriverError::compile(d.format(diag_base_dir(path).as_deref())))?;
+    Ok(doc::man_page(&program, path).to_roff())
+}
+
+fn compile_stages(
+    path: &Path,
+    options: CompileOptions,
+    timings: &mut StageTimings,
+    cache: Option<&mut loader::ParseCache>,
+    sources: &mut Vec<PathBuf>,
+) -> Result<(String, Vec<String>), DriverError> {
+    let diag_base_dir = diag_base_dir(path);
+    check_readable(path)?;
         
-    let mut ast = loader::load_program_with_imports(path)
+    let loaded = match cache {
+        Some(cache) => loader::load_cached(path, cache),
+        None => loader::load_with_report(path),
+    };
+    let (mut ast, report) = loaded
         .map_err(|d| DriverError::compile(d.format(diag_base_dir.as_deref())))?;
+    timings.lex = report.timings.lex;
+    timings.parse = report.timings.parse;
+    sources.extend(ast.source_maps.keys().map(PathBuf::from));
+
+    let suggestions = fixes::collect(&ast, &report.unused_imports, options.target);
+    if let Mode::Fix = options.mode {
+        return fixes::apply(&ast, &suggestions, diag_base_dir.as_deref())
+            .map(|summary| (summary, Vec::new()))
+            .map_err(|e| DriverError::io(format!("Failed to apply fixes: {}", e)));
+    }
 
     if let Mode::EmitAst = options.mode {
         ast.strip_spans();
-        return Ok(format!("{:#?}", ast));
+        return Ok((format!("{:#?}", ast), Vec::new()));
     }
 
-    // Semantic analysis: check variable declarations before lowering
-    semantics::check_semantics(&ast, &semantics::SemanticOptions {
+    let stage = Instant::now();
+    let check_opts = stages::CheckOptions {
+        diag_base_dir: diag_base_dir.as_deref(),
+        deny_tainted_sh: options.deny_tainted_sh,
+    };
+    let mut warnings = stages::check_program(&ast, &suggestions, &check_opts).map_err(DriverError::compile)?;
+
+    // Deployment files read `@service`/`@cron` from the AST, so they are built
+    // before lowering and written once the script itself is.
+    let companions = deployment_files(&ast, &options)?;
+
+    // Source findings are collected before lowering consumes the AST.
+    let audit_findings = (options.mode == Mode::AuditQuoting)
+        .then(|| audit::audit_program(&ast, diag_base_dir.as_deref()));
+    timings.check = stage.elapsed();
+
+    let stage = Instant::now();
+    let lower_opts = lower::LowerOptions {
+        include_diagnostics: options.include_diagnostics,
         diag_base_dir: diag_base_dir.clone(),
-    }).map_err(|e| DriverError::compile(e.to_string()))?;
-
-    let ir = lower::lower_with_options(
-        ast,
-        &lower::LowerOptions {
-            include_diagnostics: options.include_diagnostics,
-            diag_base_dir: diag_base_dir.clone(),
-            target: options.target,
+        target: options.target,
+        debug: options.debug,
+        profile: options.profile && options.mode != Mode::EmitLib,
+    };
+    let mut ir = match options.mode {
+        Mode::EmitLib => lower::lower_library(ast, &lower_opts),
+        _ => match &options.entry {
+            Some(entry) => lower::lower_with_entry(ast, &lower_opts, entry),
+            None => lower::lower_with_options(ast, &lower_opts),
         },
-    ).map_err(|e| DriverError::compile(e.to_string()))?;
+    }.map_err(|e| DriverError::compile(e.to_string()))?;
 
+    let mut guard_prelude = None;
+    if options.sandbox_manifest.is_some() || options.sandbox_guard {
+        let manifest = sandbox::analyze(&mut ir, options.sandbox_guard)
+            .map_err(|e| DriverError::compile(e.to_string()))?;
+        if let Some(path) = &options.sandbox_manifest {
+            std::fs::write(path, manifest.to_json(options.target))
+                .map_err(|e| DriverError::io(format!("Failed to write to {}: {}", path.display(), e)))?;
+        }
+        if options.sandbox_guard {
+            guard_prelude = Some(manifest.guard_prelude());
+        }
+    }
+    timings.lower = stage.elapsed();
 
     if let Mode::EmitIr = options.mode {
         let mut ir_stripped = ir;
         for f in &mut ir_stripped {
              f.strip_spans();
         }
-        return Ok(format!("{:#?}", ir_stripped));
+        return Ok((format!("{:#?}", ir_stripped), warnings));
     }
 
-    if let Mode::Check = options.mode {
-        codegen::emit_with_options_checked(
+    if let Mode::PreludeReport = options.mode {
+        let report = codegen::prelude_report(
             &ir,
             codegen::CodegenOptions {
                 target: options.target,
                 include_diagnostics: options.include_diagnostics,
             },
-        ).map_err(|e| DriverError::compile(e.to_string()))?;
-        return Ok("OK".to_string());
+        );
+        return Ok((report, warnings));
+    }
+
+    let stage = Instant::now();
+    let codegen_opts = codegen::CodegenOptions {
+        target: options.target,
+        include_diagnostics: options.include_diagnostics,
+    };
+    let streamable = options.stream
+        && matches!(options.mode, Mode::Default | Mode::EmitSh)
+        && options.runtime_lib.is_none()
+        && guard_prelude.is_none()
+        && !options.minify
+        && options.max_size.is_none()
+        && options.warn_size.is_none()
+        && !options.deterministic;
+    // Stdout is always buffered, so a failed compile prints nothing.
+    if let Some(out_path) = options.out_path.as_ref().filter(|_| streamable) {
+        stream_to_file(&ir, codegen_opts, out_path)?;
+        if options.chmod_x {
+            set_executable(out_path);
+        }
+        write_companions(&companions)?;
+        timings.codegen = stage.elapsed();
+        return Ok((String::new(), warnings));
     }
+    let out = match (&options.runtime_lib, options.mode) {
+        (_, Mode::EmitLib) => codegen::emit_library(&ir, codegen_opts),
+        (Some(lib), _) => codegen::emit_with_runtime_lib(&ir, codegen_opts, &lib.to_string_lossy()),
+        (None, _) => codegen::emit_with_options_checked(&ir, codegen_opts),
+    }.map_err(|e| DriverError::compile(e.to_string()))?;
 
-    // Default or EmitSh
-    let out = codegen::emit_with_options_checked(
-        &ir,
-        codegen::CodegenOptions {
-            target: options.target,
-            include_diagnostics: options.include_diagnostics,
+    // The guard functions go right after the shebang, ahead of the prelude.
+    let out = match guard_prelude {
+        Some(guard) => match out.split_once('\n') {
+            Some((shebang, rest)) => format!("{}\n{}{}", shebang, guard, rest),
+            None => out,
         },
-    ).map_err(|e| DriverError::compile(e.to_string()))?;
-    
+        None => out,
+    };
+    let out = if options.minify {
+        // Names the runtime library defines must survive so the script still finds them.
+        let keep = match options.runtime_lib {
+            Some(_) => codegen::minify::internal_names(&codegen::emit_runtime_lib(options.target)),
+            None => Default::default(),
+        };
+        codegen::minify::minify_script(&out, &keep)
+    } else {
+        out
+    };
+    timings.codegen = stage.elapsed();
+
+    if options.max_size.is_some() || options.warn_size.is_some() {
+        let names: Vec<&str> = ir.iter().map(|f| f.name.as_str()).collect();
+        let sizes = codegen::size::breakdown(&out, &names);
+        if let Some(limit) = options.max_size.filter(|&limit| sizes.total > limit) {
+            return Err(DriverError::compile(
+                CompileError::coded("E0602", over_budget("--max-size", limit, &sizes)).to_string(),
+            ));
+        }
+        if let Some(limit) = options.warn_size.filter(|&limit| sizes.total > limit) {
+            warnings.push(over_budget("--warn-size", limit, &sizes));
+        }
+    }
+
+    if options.deterministic {
+        let rerun = CompileOptions {
+            mode: Mode::EmitSh,
+            out_path: None,
+            sandbox_manifest: None,
+            deterministic: false,
+            stream: false,
+            ..options.clone()
+        };
+        let (again, _) = compile_file_with_warnings(path, rerun)?;
+        if again != out {
+            return Err(DriverError::compile(CompileError::coded("E0601", format!(
+                "--deterministic: two compilations of {} produced different output",
+                path.display()
+            )).to_string()));
+        }
+    }
+
+    if let Some(mut findings) = audit_findings {
+        findings.extend(audit::audit_generated(&out));
+        return Ok((audit::format_report(&findings), warnings));
+    }
+
+    if let Mode::Check = options.mode {
+        return Ok(("OK".to_string(), warnings));
+    }
+
+    if let Some(lib) = &options.runtime_lib {
+        write_runtime_lib(&runtime_lib_file(lib, options.out_path.as_deref()), options.target)?;
+    }
+
     if let Some(out_path) = &options.out_path {
         std::fs::write(out_path, &out)
             .map_err(|e| DriverError::io(format!("Failed to write to {}: {}", out_path.display(), e)))?;
         
-        #[cfg(unix)]
-        {
-            if options.chmod_x {
-                if let Ok(metadata) = std::fs::metadata(out_path) {
-                    let mut perms = metadata.permissions();
-                    perms.set_mode(perms.mode() | 0o111);
-                    let _ = std::fs::set_permissions(out_path, perms);
-                }
-            }
+        // Libraries are sourced, never executed.
+        if options.chmod_x && options.mode != Mode::EmitLib {
+            set_executable(out_path);
         }
+        write_companions(&companions)?;
     }
     
-    Ok(out)
+    Ok((out, warnings))
+}
+
+fn emit_error(err: codegen::EmitError, dest: &str) -> DriverError {
+    match err {
+        codegen::EmitError::Compile(e) => DriverError::compile(e.to_string()),
+        codegen::EmitError::Io(e) => DriverError::io(format!("Failed to write to {}: {}", dest, e)),
+    }
+}
+
+/// Streams into a file beside `out_path` and renames it into place, so a failed
+/// compile never leaves a truncated script behind.
+fn stream_to_file(ir: &[crate::ir::Function], opts: codegen::CodegenOptions, out_path: &Path) -> Result<(), DriverError> {
+    let mut partial = out_path.as_os_str().to_owned();
+    partial.push(".sh2c-partial");
+    let partial = PathBuf::from(partial);
+
+    let written = std::fs::File::create(&partial)
+        .map_err(codegen::EmitError::from)
+        .and_then(|file| {
+            let mut w = std::io::BufWriter::new(file);
+            codegen::emit_to_writer(&mut w, ir, opts)?;
+            w.flush()?;
+            Ok(())
+        })
+        .and_then(|()| std::fs::rename(&partial, out_path).map_err(codegen::EmitError::from));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&partial);
+        return Err(emit_error(e, &out_path.display().to_string()));
+    }
+    Ok(())
+}
+
+fn set_executable(path: &Path) {
+    #[cfg(unix)]
+    if let Ok(metadata) = std::fs::metadata(path) {
+        let mut perms = metadata.permissions();
+        perms.set_mode(perms.mode() | 0o111);
+        let _ = std::fs::set_permissions(path, perms);
+    }
+    #[cfg(not(unix))]
+    let _ = path;
 }
@@ -1,9 +1,12 @@
+use crate::diag_codes::tag;
 use crate::target::TargetShell;
 use std::fmt;
 
 #[derive(Debug, Clone)]
 pub struct CompileError {
     pub message: String,
+    /// Code from `diag_codes::CODES`, when the site that built the error set one.
+    pub code: Option<&'static str>,
     pub target: Option<TargetShell>,
     pub location: Option<String>,
 }
@@ -12,11 +15,28 @@ impl CompileError {
     pub fn new(message: impl Into<String>) -> Self {
         Self {
             message: message.into(),
+            code: None,
             target: None,
             location: None,
         }
     }
 
+    /// An error with a known code, appended to the first line of `message`.
+    pub fn coded(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            message: tag(&message.into(), code),
+            code: Some(code),
+            target: None,
+            location: None,
+        }
+    }
+
+    /// Records the code of a message that was already rendered with it.
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
     pub fn with_target(mut self, target: TargetShell) -> Self {
         self.target = Some(target);
         self
@@ -29,7 +49,8 @@ impl CompileError {
 
     pub fn unsupported(feature: impl Into<String>, target: TargetShell) -> Self {
         Self {
-            message: format!("{} is not supported in {} target", feature.into(), target),
+            message: tag(&format!("{} is not supported in {} target", feature.into(), target), "E0400"),
+            code: Some("E0400"),
             target: Some(target),
             location: None,
         }
@@ -37,7 +58,8 @@ impl CompileError {
 
     pub fn internal(msg: impl Into<String>, target: TargetShell) -> Self {
         Self {
-            message: format!("Internal error: {}", msg.into()),
+            message: tag(&format!("Internal error: {}", msg.into()), "E0900"),
+            code: Some("E0900"),
             target: Some(target),
             location: None,
         }
@@ -0,0 +1,535 @@
+//! Machine-applicable suggestions (`sh2c --fix`).
+//!
+//! Each `Suggestion` is a diagnostic together with the exact source edits that
+//! resolve it. A normal compile reports them (warnings, or an error for code that
+//! cannot compile for the target); `--fix` writes the edits back to the files.
+//!
+//! Covered so far:
+//! - `run()` of a file utility with a value from script input and no `--` before it
+//! - `pwd()` / `ppid()` under `--target posix`, rewritten to `env.PWD` / `env.PPID`
+//! - aliased imports that no qualified call uses
+
+use crate::ast::{self, ExprKind, Program, StmtKind};
+use crate::codegen::TargetShell;
+use crate::loader::UnusedImport;
+use crate::span::Span;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Replace the source text at `span` with `replacement`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub span: Span,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// Canonical path of the file the edits apply to.
+    pub file: String,
+    pub span: Span,
+    pub message: String,
+    pub code: &'static str,
+    /// What the edits do, shown as `help:` under the diagnostic.
+    pub help: String,
+    pub edits: Vec<Edit>,
+    /// The program cannot compile until this is fixed.
+    pub is_error: bool,
+}
+
+impl Suggestion {
+    pub fn render(&self, program: &Program, base: Option<&Path>) -> String {
+        let located = match program.source_maps.get(&self.file) {
+            Some(sm) => sm.format_coded_diagnostic(&self.file, base, Some(self.code), &self.message, self.span),
+            None => crate::diag_codes::tag(&self.message, self.code),
+        };
+        format!("{}\nhelp: {} (sh2c --fix applies this)", located, self.help)
+    }
+}
+
+/// Commands that accept `--` to end option parsing, and that act on paths an
+/// attacker-controlled value could turn into an option (`-rf`, `--reference=...`).
+const END_OF_OPTIONS_COMMANDS: &[&str] = &[
+    "rm", "mv", "cp", "ln", "chmod", "chown", "chgrp", "mkdir", "rmdir", "touch", "cat",
+];
+
+struct Collector<'a> {
+    program: &'a Program,
+    target: TargetShell,
+    file: &'a str,
+    /// Variables of the current function assigned from script input somewhere.
+    external: HashSet<String>,
+    out: Vec<Suggestion>,
+}
+
+/// Collects every fixable finding in the loaded program.
+pub fn collect(program: &Program, unused_imports: &[UnusedImport], target: TargetShell) -> Vec<Suggestion> {
+    let mut out = Vec::new();
+    for func in &program.functions {
+        // Imported functions are copied under mangled names; fix each source once.
+        if func.name.starts_with("__imp_") {
+            continue;
+        }
+        let mut c = Collector { program, target, file: &func.file, external: HashSet::new(), out: Vec::new() };
+        c.mark_external(&func.body);
+        c.block(&func.body);
+        out.extend(c.out);
+    }
+    for import in unused_imports {
+        let span = program
+            .source_maps
+            .get(&import.file)
+            .map_or(import.span, |sm| whole_lines(sm.src(), import.span));
+        out.push(Suggestion {
+            file: import.file.clone(),
+            span: import.span,
+            message: format!("unused import alias '{}'", import.alias),
+            code: "W0002",
+            help: "remove the import".to_string(),
+            edits: vec![Edit { span, replacement: String::new() }],
+            is_error: false,
+        });
+    }
+    out
+}
+
+/// Every `let`/`set` of a variable and every `for` list item in `stmts`, with
+/// the value it assigns.
+fn assignments<'a>(stmts: &'a [ast::Stmt], out: &mut Vec<(&'a str, &'a ast::Expr)>) {
+    for stmt in stmts {
+        match &stmt.node {
+            StmtKind::Let { name, value } | StmtKind::Set { target: ast::LValue::Var(name), value } => {
+                out.push((&name.node, value));
+            }
+            StmtKind::If { then_body, elifs, else_body, .. } => {
+                assignments(then_body, out);
+                for e in elifs {
+                    assignments(&e.body, out);
+                }
+                if let Some(body) = else_body {
+                    assignments(body, out);
+                }
+            }
+            StmtKind::For { var, iterable, body } => {
+                if let ast::ForIterable::List(items) = iterable {
+                    out.extend(items.iter().map(|item| (var.node.as_str(), item)));
+                }
+                assignments(body, out);
+            }
+            StmtKind::While { body, .. }
+            | StmtKind::ForMap { body, .. }
+            | StmtKind::Subshell { body }
+            | StmtKind::Group { body }
+            | StmtKind::Unchecked { body }
+            | StmtKind::WithLimits { body, .. }
+            | StmtKind::WithEnv { body, .. }
+            | StmtKind::WithRedirect { body, .. }
+            | StmtKind::WithCwd { body, .. }
+            | StmtKind::WithLog { body, .. }
+            | StmtKind::WithDeadline { body, .. }
+            | StmtKind::WithUmask { body, .. } => assignments(body, out),
+            StmtKind::TryCatch { try_body: a, catch_body: b } | StmtKind::AndThen { left: a, right: b } | StmtKind::OrElse { left: a, right: b } => {
+                assignments(a, out);
+                assignments(b, out);
+            }
+            StmtKind::Case { arms, .. } => {
+                for arm in arms {
+                    assignments(&arm.body, out);
+                }
+            }
+            StmtKind::Pipe(segments) => {
+                for seg in segments {
+                    if let ast::PipeSegment::Block(body) | ast::PipeSegment::EachLine(_, _, body) = &seg.node {
+                        assignments(body, out);
+                    }
+                }
+            }
+            StmtKind::Spawn { stmt } => assignments(std::slice::from_ref(stmt.as_ref()), out),
+            _ => {}
+        }
+    }
+}
+
+/// Widens `span` to the full lines it covers when nothing else is on them, so a
+/// deletion does not leave a blank line behind.
+fn whole_lines(src: &str, span: Span) -> Span {
+    let line_start = src[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = src[span.end..].find('\n').map_or(src.len(), |i| span.end + i + 1);
+    let before = &src[line_start..span.start];
+    let after = &src[span.end..line_end];
+    if before.trim().is_empty() && after.trim().is_empty() {
+        Span::new(line_start, line_end)
+    } else {
+        span
+    }
+}
+
+/// Applies `edits` to `src`. Edits are applied back to front; one that overlaps
+/// an edit already applied is skipped. Returns the new text and the number of
+/// edits applied.
+pub fn apply_edits(src: &str, edits: &[&Edit]) -> (String, usize) {
+    let mut sorted: Vec<&Edit> = edits.to_vec();
+    sorted.sort_by(|a, b| b.span.start.cmp(&a.span.start).then(b.span.end.cmp(&a.span.end)));
+    let mut out = src.to_string();
+    let mut applied = 0;
+    let mut floor = usize::MAX;
+    for edit in sorted {
+        if edit.span.end > floor || edit.span.end > src.len() {
+            continue;
+        }
+        out.replace_range(edit.span.start..edit.span.end, &edit.replacement);
+        floor = edit.span.start;
+        applied += 1;
+    }
+    (out, applied)
+}
+
+/// Writes every suggestion's edits back to its file and returns a summary, one
+/// line per changed file.
+#[cfg(feature = "fs")]
+pub fn apply(program: &Program, suggestions: &[Suggestion], base: Option<&Path>) -> std::io::Result<String> {
+    let mut by_file: std::collections::BTreeMap<&str, Vec<&Edit>> = Default::default();
+    for s in suggestions {
+        by_file.entry(&s.file).or_default().extend(&s.edits);
+    }
+    let mut summary = Vec::new();
+    for (file, edits) in by_file {
+        let Some(sm) = program.source_maps.get(file) else { continue };
+        let (fixed, applied) = apply_edits(sm.src(), &edits);
+        if applied == 0 {
+            continue;
+        }
+        std::fs::write(file, fixed)?;
+        let noun = if applied == 1 { "fix" } else { "fixes" };
+        summary.push(format!("{}: applied {} {}", crate::diag_path::display_path(file, base), applied, noun));
+    }
+    if summary.is_empty() {
+        return Ok("no fixes to apply".to_string());
+    }
+    Ok(summary.join("\n"))
+}
+
+impl Collector<'_> {
+    fn source(&self, span: Span) -> &str {
+        self.program
+            .source_maps
+            .get(self.file)
+            .and_then(|sm| sm.src().get(span.start..span.end))
+            .unwrap_or("")
+    }
+
+    /// Whether `expr` carries a value from outside the script: `arg()`, `args`,
+    /// `env`, `input()`, or a variable assigned from one of those.
+    fn is_external(&self, expr: &ast::Expr) -> bool {
+        match &expr.node {
+            ExprKind::Arg(_) | ExprKind::Args | ExprKind::Env(_) | ExprKind::EnvDot(_) | ExprKind::Input(_) => true,
+            ExprKind::Var(name) => self.external.contains(name),
+            ExprKind::Concat(l, r) | ExprKind::Coalesce(l, r) => self.is_external(l) || self.is_external(r),
+            ExprKind::Index { list: e, .. } | ExprKind::Field { base: e, .. } => self.is_external(e),
+            ExprKind::IfElse { then_expr, else_expr, .. } => self.is_external(then_expr) || self.is_external(else_expr),
+            ExprKind::List(items) => items.iter().any(|e| self.is_external(e)),
+            ExprKind::Call { name, args, .. } => {
+                matches!(name.as_str(), "argv" | "parse_args") || args.iter().any(|e| self.is_external(e))
+            }
+            _ => false,
+        }
+    }
+
+    /// Marks variables assigned from script input anywhere in `stmts`, so a use
+    /// before the assignment in a loop is covered too.
+    fn mark_external(&mut self, stmts: &[ast::Stmt]) {
+        let mut found = Vec::new();
+        assignments(stmts, &mut found);
+        loop {
+            let before = self.external.len();
+            for (name, value) in &found {
+                if self.is_external(value) {
+                    self.external.insert(name.to_string());
+                }
+            }
+            if self.external.len() == before {
+                break;
+            }
+        }
+    }
+
+    fn block(&mut self, stmts: &[ast::Stmt]) {
+        for s in stmts {
+            self.stmt(s);
+        }
+    }
+
+    // SYNC WITH: audit::audit_stmt (traversal shape).
+    fn stmt(&mut self, stmt: &ast::Stmt) {
+        match &stmt.node {
+            StmtKind::Let { value, .. } | StmtKind::Set { value, .. } | StmtKind::LetValues { value, .. } => self.expr(value),
+            StmtKind::ReturnValues(values) => values.iter().for_each(|v| self.expr(v)),
+            StmtKind::Run(call) => self.run_call(call),
+            StmtKind::Print(e)
+            | StmtKind::PrintErr(e)
+            | StmtKind::Exit(Some(e))
+            | StmtKind::Return(Some(e))
+            | StmtKind::Wait(Some(e))
+            | StmtKind::Sh(e)
+            | StmtKind::Cd { path: e }
+            | StmtKind::Source { path: e }
+            | StmtKind::Export { value: Some(e), .. } => self.expr(e),
+            StmtKind::Exec(args) | StmtKind::QualifiedCall { args, .. } => {
+                for e in args {
+                    self.expr(e);
+                }
+            }
+            StmtKind::Call { args, options, .. } => {
+                for e in args {
+                    self.expr(e);
+                }
+                for o in options {
+                    self.expr(&o.value);
+                }
+            }
+            StmtKind::If { cond, then_body, elifs, else_body } => {
+                self.expr(cond);
+                self.block(then_body);
+                for e in elifs {
+                    self.expr(&e.cond);
+                    self.block(&e.body);
+                }
+                if let Some(body) = else_body {
+                    self.block(body);
+                }
+            }
+            StmtKind::While { cond, body } => {
+                self.expr(cond);
+                self.block(body);
+            }
+            StmtKind::For { iterable, body, .. } => {
+                match iterable {
+                    ast::ForIterable::List(items) => {
+                        for e in items {
+                            self.expr(e);
+                        }
+                    }
+                    ast::ForIterable::Range(start, end) => {
+                        self.expr(start);
+                        self.expr(end);
+                    }
+                    ast::ForIterable::Find0(_) | ast::ForIterable::StdinLines => {}
+                }
+                self.block(body);
+            }
+            StmtKind::ForMap { body, .. }
+            | StmtKind::Subshell { body }
+            | StmtKind::Group { body }
+            | StmtKind::Unchecked { body }
+            | StmtKind::WithLimits { body, .. }
+            | StmtKind::WithEnv { body, .. }
+            | StmtKind::WithRedirect { body, .. } => self.block(body),
+            StmtKind::WithCwd { path, body }
+            | StmtKind::WithLog { path, body, .. }
+            | StmtKind::WithDeadline { duration: path, body } | StmtKind::WithUmask { mask: path, body } => {
+                self.expr(path);
+                self.block(body);
+            }
+            StmtKind::TryCatch { try_body, catch_body } => {
+                self.block(try_body);
+                self.block(catch_body);
+            }
+            StmtKind::AndThen { left, right } | StmtKind::OrElse { left, right } => {
+                self.block(left);
+                self.block(right);
+            }
+            StmtKind::Pipe(segments) => self.pipe_segments(segments),
+            StmtKind::Spawn { stmt } => self.stmt(stmt),
+            StmtKind::Case { expr, arms } => {
+                self.expr(expr);
+                for arm in arms {
+                    self.block(&arm.body);
+                }
+            }
+            StmtKind::ShBlock { .. }
+            | StmtKind::Exit(None)
+            | StmtKind::Return(None)
+            | StmtKind::Wait(None)
+            | StmtKind::Export { value: None, .. }
+            | StmtKind::Unset { .. }
+            | StmtKind::Break
+            | StmtKind::Continue => {}
+        }
+    }
+
+    fn pipe_segments(&mut self, segments: &[ast::Spanned<ast::PipeSegment>]) {
+        for seg in segments {
+            match &seg.node {
+                ast::PipeSegment::Run(call) => self.run_call(call),
+                ast::PipeSegment::Sudo(call) => {
+                    for e in &call.args {
+                        self.expr(e);
+                    }
+                }
+                ast::PipeSegment::Block(body) | ast::PipeSegment::EachLine(_, _, body) => self.block(body),
+            }
+        }
+    }
+
+    fn run_call(&mut self, call: &ast::RunCall) {
+        self.end_of_options(&call.args);
+        for e in &call.args {
+            self.expr(e);
+        }
+    }
+
+    /// `run("rm", path)`: a `path` from script input starting with `-` would be
+    /// taken as an option.
+    fn end_of_options(&mut self, args: &[ast::Expr]) {
+        let Some(ExprKind::Literal(cmd)) = args.first().map(|a| &a.node) else { return };
+        if !END_OF_OPTIONS_COMMANDS.contains(&cmd.as_str()) {
+            return;
+        }
+        for arg in &args[1..] {
+            match &arg.node {
+                ExprKind::Literal(s) if s == "--" => return,
+                ExprKind::Literal(_) | ExprKind::Number(_) => continue,
+                ExprKind::Var(_)
+                | ExprKind::Concat(..)
+                | ExprKind::Coalesce(..)
+                | ExprKind::Arg(_)
+                | ExprKind::Env(_)
+                | ExprKind::EnvDot(_)
+                | ExprKind::Field { .. }
+                | ExprKind::Index { .. }
+                | ExprKind::IfElse { .. } => {
+                    // Values the script computes itself are left alone.
+                    if !self.is_external(arg) {
+                        continue;
+                    }
+                    let value = self.source(arg.span).to_string();
+                    self.out.push(Suggestion {
+                        file: self.file.to_string(),
+                        span: arg.span,
+                        message: format!(
+                            "{} receives `{}` before any \"--\"; a value starting with '-' would be read as an option",
+                            cmd, value
+                        ),
+                        code: "W0003",
+                        help: format!("insert \"--\" before `{}`", value),
+                        edits: vec![Edit {
+                            span: Span::new(arg.span.start, arg.span.start),
+                            replacement: "\"--\", ".to_string(),
+                        }],
+                        is_error: false,
+                    });
+                    return;
+                }
+                // Lists and argument splices may carry options on purpose.
+                _ => return,
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &ast::Expr) {
+        match &expr.node {
+            ExprKind::Pwd | ExprKind::Ppid if self.target == TargetShell::Posix => {
+                let (call, var) = if matches!(expr.node, ExprKind::Pwd) { ("pwd", "PWD") } else { ("ppid", "PPID") };
+                self.out.push(Suggestion {
+                    file: self.file.to_string(),
+                    span: expr.span,
+                    message: format!("{}() is not supported in POSIX sh target", call),
+                    code: "E0400",
+                    help: format!("use `env.{}`, which POSIX shells also set", var),
+                    edits: vec![Edit { span: expr.span, replacement: format!("env.{}", var) }],
+                    is_error: true,
+                });
+            }
+            ExprKind::Run(call) => self.run_call(call),
+            ExprKind::Pipeline(segments) => self.pipe_segments(segments),
+            ExprKind::Call { args, options, .. } | ExprKind::Sudo { args, options } => {
+                for e in args {
+                    self.expr(e);
+                }
+                for o in options {
+                    self.expr(&o.value);
+                }
+            }
+            ExprKind::QualifiedCall { args, .. } | ExprKind::Command(args) | ExprKind::List(args) => {
+                for e in args {
+                    self.expr(e);
+                }
+            }
+            ExprKind::Concat(l, r)
+            | ExprKind::Coalesce(l, r)
+            | ExprKind::And(l, r)
+            | ExprKind::Or(l, r)
+            | ExprKind::Join { list: l, sep: r }
+            | ExprKind::Index { list: l, index: r, .. }
+            | ExprKind::Arith { left: l, right: r, .. }
+            | ExprKind::Compare { left: l, right: r, .. } => {
+                self.expr(l);
+                self.expr(r);
+            }
+            ExprKind::IfElse { cond, then_expr, else_expr } => {
+                self.expr(cond);
+                self.expr(then_expr);
+                self.expr(else_expr);
+            }
+            ExprKind::Not(e)
+            | ExprKind::Spread(e)
+            | ExprKind::Exists(e)
+            | ExprKind::IsDir(e)
+            | ExprKind::IsFile(e)
+            | ExprKind::IsSymlink(e)
+            | ExprKind::IsExec(e)
+            | ExprKind::IsReadable(e)
+            | ExprKind::IsWritable(e)
+            | ExprKind::IsNonEmpty(e)
+            | ExprKind::BoolStr(e)
+            | ExprKind::Len(e)
+            | ExprKind::Count(e)
+            | ExprKind::Arg(e)
+            | ExprKind::Env(e)
+            | ExprKind::Input(e)
+            | ExprKind::Field { base: e, .. } => self.expr(e),
+            ExprKind::Sh { cmd, options } | ExprKind::Capture { expr: cmd, options } => {
+                self.expr(cmd);
+                for o in options {
+                    self.expr(&o.value);
+                }
+            }
+            ExprKind::MapLiteral(entries) => {
+                for (_, v) in entries {
+                    self.expr(v);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_apply_back_to_front() {
+        let insert = Edit { span: Span::new(3, 3), replacement: "\"--\", ".to_string() };
+        let replace = Edit { span: Span::new(0, 2), replacement: "mv".to_string() };
+        let (out, n) = apply_edits("rm(x)", &[&replace, &insert]);
+        assert_eq!(out, "mv(\"--\", x)");
+        assert_eq!(n, 2);
+    }
+
+    #[test]
+    fn overlapping_edits_are_skipped() {
+        let a = Edit { span: Span::new(0, 4), replacement: "a".to_string() };
+        let b = Edit { span: Span::new(2, 6), replacement: "b".to_string() };
+        let (out, n) = apply_edits("0123456789", &[&a, &b]);
+        assert_eq!(out, "01b6789");
+        assert_eq!(n, 1);
+    }
+
+    #[test]
+    fn deletion_widens_to_whole_line() {
+        let src = "import \"a\" as a\nfunc main() {}\n";
+        assert_eq!(whole_lines(src, Span::new(0, 15)), Span::new(0, 16));
+        assert_eq!(whole_lines("x; import y", Span::new(3, 11)), Span::new(3, 11));
+    }
+}
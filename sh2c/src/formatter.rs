@@ -18,28 +18,77 @@ pub fn format_program(program: &Program) -> String {
         out.push('\n');
     }
 
+    // Externs
+    for (i, ext) in program.externs.iter().enumerate() {
+        if i > 0 || !program.imports.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format_doc(&ext.doc));
+        out.push_str(&format!("extern func {}({})", ext.name, ext.params.join(", ")));
+    }
+    if !program.externs.is_empty() {
+        out.push('\n');
+    }
+
     // Functions
     for (i, func) in program.functions.iter().enumerate() {
-        if i > 0 || !program.imports.is_empty() {
+        if i > 0 || !program.imports.is_empty() || !program.externs.is_empty() {
             out.push_str("\n\n");
         }
-        out.push_str(&format_function(func));
+        if program.inits.contains(&func.name) {
+            out.push_str(&format!("init {{\n{}\n}}", format_block(&func.body, 1, true)));
+        } else {
+            out.push_str(&format_function(func));
+        }
+    }
+
+    for hook in &program.hooks {
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        let keyword = match hook.kind {
+            HookKind::Enter => "on_enter",
+            HookKind::Exit => "on_exit",
+        };
+        out.push_str(&format!("{}({}) {{\n{}\n}}", keyword, hook.targets.join(", "), format_block(&hook.body, 1, true)));
     }
 
     // Top-level statements
+    if !program.top_level.is_empty() {
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        out.push_str(&format_block(&program.top_level, 0, true));
+    }
+
 
-    
     // Ensure single trailing newline
     if !out.ends_with('\n') {
         out.push('\n');
     }
-    out
+    // Non-ASCII identifiers reach the AST in their shell-safe spelling.
+    crate::names::display_idents(&out).into_owned()
 }
 
 fn format_function(func: &Function) -> String {
     let params = func.params.join(", ");
     let body = format_block(&func.body, 1, true);
-    format!("func {}({}) {{\n{}\n}}", func.name, params, body)
+    let mut attrs = String::new();
+    for attr in &func.attrs {
+        attrs.push('@');
+        attrs.push_str(&attr.name);
+        if !attr.args.is_empty() {
+            let args: Vec<String> = attr.args.iter().map(|a| format!("\"{}\"", sh_escape(a))).collect();
+            attrs.push_str(&format!("({})", args.join(", ")));
+        }
+        attrs.push('\n');
+    }
+    let ret = if func.returns_bool { " -> bool" } else { "" };
+    format!("{}{}func {}({}){} {{\n{}\n}}", format_doc(&func.doc), attrs, func.name, params, ret, body)
+}
+
+fn format_doc(doc: &[String]) -> String {
+    doc.iter().map(|line| if line.is_empty() { "///\n".to_string() } else { format!("/// {}\n", line) }).collect()
 }
 
 fn indent_str(depth: usize) -> String {
@@ -75,6 +124,8 @@ fn is_simple_atom(stmt: &Stmt) -> bool {
         StmtKind::Call { .. } |
         StmtKind::Set { .. } |
         StmtKind::Return(_) |
+        StmtKind::ReturnValues(_) |
+        StmtKind::LetValues { .. } |
         StmtKind::Exit(_) |
         StmtKind::Break |
         StmtKind::Continue |
@@ -94,7 +145,10 @@ fn is_simple_atom(stmt: &Stmt) -> bool {
 // The caller adds indentation unless it's inline.
 fn format_stmt(stmt: &Stmt, depth: usize) -> String {
     match &stmt.node {
-        StmtKind::Let { name, value } => format!("let {} = {}", name, format_expr(value)),
+        StmtKind::Let { name, value } => match &value.node {
+            ExprKind::Pipeline(segments) => format!("let {} = {}", name, format_pipe_segments(segments, depth)),
+            _ => format!("let {} = {}", name, format_expr(value)),
+        },
         StmtKind::Run(call) => format_run_call(call),
         StmtKind::Exec(args) => {
             let parts: Vec<String> = args.iter().map(format_expr).collect();
@@ -161,29 +215,25 @@ fn format_stmt(stmt: &Stmt, depth: usize) -> String {
                 indent_str(depth)
             )
         }
-        StmtKind::Pipe(segments) => {
-            let parts: Vec<String> = segments.iter().map(|seg| {
-                match &seg.node {
-                    PipeSegment::Run(call) => format_run_call(call),
-                    PipeSegment::Sudo(call) => {
-                        let mut parts: Vec<String> = call.args.iter().map(format_expr).collect();
-                        for opt in &call.options {
-                            parts.push(format!("{} = {}", opt.name, format_expr(&opt.value)));
-                        }
-                        format!("sudo({})", parts.join(", "))
-                    },
-                    PipeSegment::Block(stmts) => format!("{{\n{}\n{}}}", format_block(stmts, depth + 1, false), indent_str(depth)),
-                    PipeSegment::EachLine(var, stmts) => format!("each_line {} {{\n{}\n{}}}", var, format_block(stmts, depth + 1, false), indent_str(depth)),
-                }
-            }).collect();
-            parts.join(" | ")
-        }
+        StmtKind::Pipe(segments) => format_pipe_segments(segments, depth),
         StmtKind::Return(opt) => {
              match opt {
                  Some(e) => format!("return {}", format_expr(e)),
                  None => "return".to_string(),
              }
         }
+        StmtKind::ReturnValues(values) => {
+            let parts: Vec<String> = values.iter().map(format_expr).collect();
+            format!("return {}", parts.join(", "))
+        }
+        StmtKind::LetValues { names, value, tuple } => {
+            let names: Vec<&str> = names.iter().map(|n| n.node.as_str()).collect();
+            if *tuple {
+                format!("let ({}) = {}", names.join(", "), format_expr(value))
+            } else {
+                format!("let {} = {}", names.join(", "), format_expr(value))
+            }
+        }
         StmtKind::Exit(opt) => {
              match opt {
                  Some(e) => format!("exit {}", format_expr(e)),
@@ -202,13 +252,23 @@ fn format_stmt(stmt: &Stmt, depth: usize) -> String {
         StmtKind::Unset { name } => format!("unset(\"{}\")", name),
         StmtKind::Source { path } => format!("source({})", format_expr(path)),
         StmtKind::Sh(expr) => format!("sh({})", format_expr(expr)),
-        StmtKind::ShBlock(lines) => {
-             // sh { "line1", "line2" }
+        StmtKind::ShBlock { lines, bridge } => {
+             // sh use(a) export(b) { "line1", "line2" }
              let joined = lines.iter().map(|l| format!("\"{}\"", sh_escape(l))).collect::<Vec<_>>().join(", ");
-             format!("sh {{ {} }}", joined)
+             let mut head = String::from("sh ");
+             if let Some(bridge) = bridge {
+                 head.push_str(&format!("use({}) ", bridge.uses.join(", ")));
+                 if !bridge.exports.is_empty() {
+                     head.push_str(&format!("export({}) ", bridge.exports.join(", ")));
+                 }
+             }
+             format!("{}{{ {} }}", head, joined)
         }
-        StmtKind::Call { name, args } => {
-            let parts: Vec<String> = args.iter().map(format_expr).collect();
+        StmtKind::Call { name, args, options } => {
+            let mut parts: Vec<String> = args.iter().map(format_expr).collect();
+            for o in options {
+                parts.push(format!("{}={}", o.name, format_expr(&o.value)));
+            }
              format!("{}({})", name, parts.join(", "))
         }
         StmtKind::QualifiedCall { .. } => {
@@ -233,7 +293,8 @@ fn format_stmt(stmt: &Stmt, depth: usize) -> String {
             let indent = indent_str(depth);
             let inner_indent = indent_str(depth + 1);
             for arm in arms {
-                 let pats: Vec<String> = arm.patterns.iter().map(|p| match p {
+                 let pats: Vec<String> = arm.patterns.iter().map(|p| match &p.node {
+                     Pattern::Literal(s) if s.contains(crate::parser::GLOB_CHARS) => format!("literal(\"{}\")", sh_escape(s)),
                      Pattern::Literal(s) => format!("\"{}\"", sh_escape(s)),
                      Pattern::Glob(s) => format!("glob(\"{}\")", sh_escape(s)),
                      Pattern::Wildcard => "_".to_string(),
@@ -251,6 +312,30 @@ fn format_stmt(stmt: &Stmt, depth: usize) -> String {
         StmtKind::WithCwd { path, body } => {
             format!("with cwd({}) {{\n{}\n{}}}", format_expr(path), format_block(body, depth + 1, false), indent_str(depth))
         }
+        StmtKind::WithDeadline { duration, body } => {
+            format!("with deadline({}) {{\n{}\n{}}}", format_expr(duration), format_block(body, depth + 1, false), indent_str(depth))
+        }
+        StmtKind::WithLimits { limit, body } => {
+            let setting = match limit {
+                Limit::Nice(n) => format!("nice({})", n),
+                Limit::Ionice { class, level: Some(level) } => format!("ionice({:?}, {})", class, level),
+                Limit::Ionice { class, level: None } => format!("ionice({:?})", class),
+                Limit::Ulimit(limits) => {
+                    let limits: Vec<String> = limits
+                        .iter()
+                        .map(|(name, value)| match value {
+                            Some(n) => format!("{}={}", name, n),
+                            None => format!("{}=\"unlimited\"", name),
+                        })
+                        .collect();
+                    format!("ulimit({})", limits.join(", "))
+                }
+            };
+            format!("with {} {{\n{}\n{}}}", setting, format_block(body, depth + 1, false), indent_str(depth))
+        }
+        StmtKind::WithUmask { mask, body } => {
+            format!("with umask({}) {{\n{}\n{}}}", format_expr(mask), format_block(body, depth + 1, false), indent_str(depth))
+        }
         StmtKind::WithLog { path, append, body } => {
              let opts = if *append { ", append=true" } else { "" };
              format!("with log({}{}) {{\n{}\n{}}}", format_expr(path), opts, format_block(body, depth + 1, false), indent_str(depth))
@@ -285,6 +370,9 @@ fn format_stmt(stmt: &Stmt, depth: usize) -> String {
         StmtKind::Group { body } => {
             format!("group {{\n{}\n{}}}", format_block(body, depth + 1, false), indent_str(depth))
         }
+        StmtKind::Unchecked { body } => {
+            format!("unchecked {{\n{}\n{}}}", format_block(body, depth + 1, false), indent_str(depth))
+        }
         StmtKind::Spawn { stmt } => {
              // Spawn wraps a single stmt, but that stmt effectively can be a group/block
              // If inner is a group, emit spawn { ... }
@@ -328,9 +416,37 @@ fn format_redirect_input_target(t: &RedirectInputTarget) -> String {
          RedirectInputTarget::HereDoc { content } => {
              format!("heredoc(\"{}\")", sh_escape(content))
          }
+         RedirectInputTarget::Text { value } => {
+             format!("text({})", format_expr(value))
+         }
     }
 }
 
+fn format_pipe_segments(segments: &[Spanned<PipeSegment>], depth: usize) -> String {
+    let with_stderr = |s: String, call: &RunCall| match &call.stderr {
+        Some(t) => format!("{} stderr={}", s, format_redirect_output_target(&t.node)),
+        None => s,
+    };
+    let parts: Vec<String> = segments.iter().map(|seg| {
+        match &seg.node {
+            PipeSegment::Run(call) => with_stderr(format_run_call(call), call),
+            PipeSegment::Sudo(call) => {
+                let mut parts: Vec<String> = call.args.iter().map(format_expr).collect();
+                for opt in &call.options {
+                    parts.push(format!("{} = {}", opt.name, format_expr(&opt.value)));
+                }
+                with_stderr(format!("sudo({})", parts.join(", ")), call)
+            },
+            PipeSegment::Block(stmts) => format!("{{\n{}\n{}}}", format_block(stmts, depth + 1, false), indent_str(depth)),
+            PipeSegment::EachLine(var, fd, stmts) => {
+                let fd = fd.map(|fd| format!("(fd={})", fd)).unwrap_or_default();
+                format!("each_line{} {} {{\n{}\n{}}}", fd, var, format_block(stmts, depth + 1, false), indent_str(depth))
+            }
+        }
+    }).collect();
+    parts.join(" | ")
+}
+
 fn format_run_call(call: &RunCall) -> String {
     let mut parts: Vec<String> = call.args.iter().map(format_expr).collect();
     for opt in &call.options {
@@ -349,11 +465,12 @@ fn format_expr_prec(kind: &ExprKind, min_prec: u8) -> String {
     // 1: ||
     // 2: &&
     // 3: compare (==, !=, <, etc)
-    // 4: concat (&)
-    // 5: add/sub
-    // 6: mul/div/mod
-    // 7: prefix (!, -)
-    // 8: suffix/call/atom
+    // 4: coalesce (??)
+    // 5: concat (&)
+    // 6: add/sub
+    // 7: mul/div/mod
+    // 8: prefix (!, -)
+    // 9: suffix/call/atom
 
     match kind {
         ExprKind::Or(l, r) => {
@@ -374,21 +491,28 @@ fn format_expr_prec(kind: &ExprKind, min_prec: u8) -> String {
             };
             wrap_parens(min_prec, 3, format!("{} {} {}", format_expr_prec(&left.node, 3), op_str, format_expr_prec(&right.node, 3 + 1)))
         }
+        ExprKind::Coalesce(l, r) => {
+            // Right-associative, so only the left operand needs parens at this level.
+            wrap_parens(min_prec, 4, format!("{} ?? {}", format_expr_prec(&l.node, 5), format_expr_prec(&r.node, 4)))
+        }
         ExprKind::Concat(l, r) => {
-             wrap_parens(min_prec, 4, format!("{} & {}", format_expr_prec(&l.node, 4), format_expr_prec(&r.node, 4 + 1)))
+             wrap_parens(min_prec, 5, format!("{} & {}", format_expr_prec(&l.node, 5), format_expr_prec(&r.node, 5 + 1)))
         }
         ExprKind::Arith { left, op, right } => {
              let (prec, op_str) = match op {
-                 ArithOp::Add => (5, "+"),
-                 ArithOp::Sub => (5, "-"),
-                 ArithOp::Mul => (6, "*"),
-                 ArithOp::Div => (6, "/"),
-                 ArithOp::Mod => (6, "%"),
+                 ArithOp::Add => (6, "+"),
+                 ArithOp::Sub => (6, "-"),
+                 ArithOp::Mul => (7, "*"),
+                 ArithOp::Div => (7, "/"),
+                 ArithOp::Mod => (7, "%"),
              };
              wrap_parens(min_prec, prec, format!("{} {} {}", format_expr_prec(&left.node, prec), op_str, format_expr_prec(&right.node, prec + 1)))
         }
         ExprKind::Not(e) => {
-            wrap_parens(min_prec, 7, format!("!{}", format_expr_prec(&e.node, 7)))
+            wrap_parens(min_prec, 8, format!("!{}", format_expr_prec(&e.node, 8)))
+        }
+        ExprKind::Spread(e) => {
+            wrap_parens(min_prec, 8, format!("...{}", format_expr_prec(&e.node, 9)))
         }
         ExprKind::Literal(s) => format!("\"{}\"", sh_escape(s)),
         ExprKind::Var(s) => s.clone(),
@@ -425,6 +549,7 @@ fn format_expr_prec(kind: &ExprKind, min_prec: u8) -> String {
              let parts: Vec<String> = args.iter().map(format_expr).collect();
              format!("capture(run({}))", parts.join(", "))
         }
+        ExprKind::Pipeline(segments) => format_pipe_segments(segments, 0),
         ExprKind::CommandPipe(segs) => {
              // pipe segments: each segment is a run() call separated by |
              let seg_strs: Vec<String> = segs.iter().map(|s| {
@@ -442,17 +567,38 @@ fn format_expr_prec(kind: &ExprKind, min_prec: u8) -> String {
         ExprKind::BoolStr(e) => format!("bool_str({})", format_expr(e)),
         ExprKind::Count(e) => format!("count({})", format_expr(e)),
         ExprKind::Input(e) => format!("input({})", format_expr(e)),
-        ExprKind::Index { list, index } => {
-            // Suffix precedence 8
-            wrap_parens(min_prec, 8, format!("{}[{}]", format_expr_prec(&list.node, 8), format_expr(index)))
+        ExprKind::Index { list, index, optional } => {
+            // Suffix precedence 9
+            let open = if *optional { "?[" } else { "[" };
+            wrap_parens(min_prec, 9, format!("{}{}{}]", format_expr_prec(&list.node, 9), open, format_expr(index)))
+        }
+        ExprKind::MapIndex { map, key, optional } => {
+            let is_name = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !crate::lexer::keywords().contains(&key.as_str());
+            match (optional, is_name) {
+                (true, true) => format!("{}?.{}", map, key),
+                (true, false) => format!("{}?[\"{}\"]", map, sh_escape(key)),
+                (false, _) => format!("{}[\"{}\"]", map, sh_escape(key)),
+            }
         }
         ExprKind::Field { base, name } => {
-             // Suffix precedence 8
-            wrap_parens(min_prec, 8, format!("{}.{}", format_expr_prec(&base.node, 8), name))
+             // Suffix precedence 9
+            wrap_parens(min_prec, 9, format!("{}.{}", format_expr_prec(&base.node, 9), name))
         }
         ExprKind::Join { list, sep } => {
             format!("join({}, {})", format_expr(list), format_expr(sep))
         }
+        ExprKind::IfElse { cond, then_expr, else_expr } => {
+            let mut out = format!("if {} {{ {} }}", format_expr(cond), format_expr(then_expr));
+            let mut rest = else_expr;
+            while let ExprKind::IfElse { cond, then_expr, else_expr } = &rest.node {
+                out.push_str(&format!(" elif {} {{ {} }}", format_expr(cond), format_expr(then_expr)));
+                rest = else_expr;
+            }
+            out.push_str(&format!(" else {{ {} }}", format_expr(rest)));
+            out
+        }
         ExprKind::EnvDot(name) => format!("env.{}", name),
         ExprKind::IsSymlink(e) => format!("is_symlink({})", format_expr(e)),
         ExprKind::IsExec(e) => format!("is_exec({})", format_expr(e)),
@@ -509,7 +655,6 @@ fn format_expr_prec(kind: &ExprKind, min_prec: u8) -> String {
             }
             format!("sudo({})", parts.join(", "))
         }
-        _ => panic!("Formatting unimplemented for ExprKind: {:?}", kind),
     }
 }
 
@@ -560,14 +705,9 @@ fn sh_escape(s: &str) -> String {
             '\n' => out.push_str("\\n"),
             '\r' => out.push_str("\\r"),
             '\t' => out.push_str("\\t"),
-            '\0' => out.push_str("\\0"),
-            // $ is special in interpolated strings, but here we escape generically for "..."
-            // If the string is intended to be interpolated, we might need to handle $.
-            // But ExprKind::Literal usually implies raw data unless parsed from interpolated.
-            // If it's a literal, we escape $ to match sh2 string logic if it allows $?
-            // "foo$bar" -> is that var sub?
-            // In sh2, "..." handles escapes.
-            '$' => out.push_str("\\$"), 
+            '\x1b' => out.push_str("\\e"),
+            '$' => out.push_str("\\$"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
             _ => out.push(c),
         }
     }
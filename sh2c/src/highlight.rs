@@ -0,0 +1,344 @@
+//! `sh2c emit-syntax`: editor highlighting generated from the keyword tables
+//! in `lang_spec`, so editors pick up new keywords and builtins with the
+//! compiler instead of by hand.
+//!
+//! Three formats are supported: a Vim syntax file, a TextMate grammar (the one
+//! shipped in `editors/vscode`), and tree-sitter highlight queries. Only the
+//! word lists are generated; string, comment and operator rules are fixed.
+
+use crate::lang_spec::{BUILTINS, KEYWORDS, LITERALS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxFormat {
+    Vim,
+    TmLanguage,
+    TreeSitterQueries,
+}
+
+impl SyntaxFormat {
+    pub const NAMES: &[&str] = &["vim", "tmLanguage", "tree-sitter-queries"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "vim" => Some(SyntaxFormat::Vim),
+            "tmLanguage" => Some(SyntaxFormat::TmLanguage),
+            "tree-sitter-queries" => Some(SyntaxFormat::TreeSitterQueries),
+            _ => None,
+        }
+    }
+}
+
+pub fn emit(format: SyntaxFormat) -> String {
+    match format {
+        SyntaxFormat::Vim => vim(),
+        SyntaxFormat::TmLanguage => tm_language(),
+        SyntaxFormat::TreeSitterQueries => tree_sitter_queries(),
+    }
+}
+
+/// Arguments of `:syntax keyword`, which Vim would not read as keywords.
+const VIM_KEYWORD_ARGS: &[&str] = &[
+    "cchar", "conceal", "concealends", "contained", "containedin", "contains", "display", "excludenl", "extend",
+    "fold", "keepend", "nextgroup", "oneline", "skipempty", "skipnl", "skipwhite", "transparent",
+];
+
+fn vim() -> String {
+    let mut out = String::from(
+        "\" Vim syntax file\n\" Language: sh2\n\" Generated by `sh2c emit-syntax vim`; do not edit.\n\n\
+         if exists(\"b:current_syntax\")\n  finish\nendif\n\n",
+    );
+    for (group, words) in [("sh2Keyword", KEYWORDS), ("sh2Builtin", BUILTINS), ("sh2Boolean", LITERALS)] {
+        let (matches, keywords): (Vec<&str>, Vec<&str>) = words.iter().partition(|w| VIM_KEYWORD_ARGS.contains(w));
+        for chunk in keywords.chunks(10) {
+            out.push_str(&format!("syn keyword {} {}\n", group, chunk.join(" ")));
+        }
+        for word in matches {
+            out.push_str(&format!("syn match {} \"\\<{}\\>\"\n", group, word));
+        }
+    }
+    out.push_str(VIM_RULES);
+    out
+}
+
+const VIM_RULES: &str = r##"
+syn match sh2Number "\<\d\+\>"
+syn match sh2Operator "\.\.\.\?\||>\|[=!<>]=\|&&\|||\|??\|=>\|[-+*/%&=|<>!]"
+syn match sh2Escape "\\." contained
+syn region sh2String start=+"+ skip=+\\\\\|\\"+ end=+"+ contains=sh2Escape
+syn region sh2RawString start=+\<r"+ end=+"+
+syn region sh2Interpolated start=+\$"+ skip=+\\\\\|\\"+ end=+"+ contains=sh2Escape,sh2Embedded
+syn region sh2Embedded matchgroup=sh2Delimiter start=+{+ end=+}+ contained contains=TOP
+syn match sh2Comment "#.*$" contains=@Spell
+
+hi def link sh2Keyword Keyword
+hi def link sh2Builtin Function
+hi def link sh2Boolean Boolean
+hi def link sh2Number Number
+hi def link sh2Operator Operator
+hi def link sh2Escape SpecialChar
+hi def link sh2String String
+hi def link sh2RawString String
+hi def link sh2Interpolated String
+hi def link sh2Delimiter Delimiter
+hi def link sh2Comment Comment
+
+let b:current_syntax = "sh2"
+"##;
+
+/// The TextMate grammar; `editors/vscode/syntaxes/sh2.tmLanguage.json` is this output.
+pub fn tm_language() -> String {
+    TM_TEMPLATE
+        .replace("{keywords}", &KEYWORDS.join("|"))
+        .replace("{builtins}", &BUILTINS.join("|"))
+        .replace("{literals}", &LITERALS.join("|"))
+}
+
+const TM_TEMPLATE: &str = r##"{
+    "$schema": "https://raw.githubusercontent.com/martinring/tmlanguage/master/tmlanguage.json",
+    "name": "sh2",
+    "scopeName": "source.sh2",
+    "patterns": [
+        {
+            "include": "#comments"
+        },
+        {
+            "include": "#keywords"
+        },
+        {
+            "include": "#builtins"
+        },
+        {
+            "include": "#functions"
+        },
+        {
+            "include": "#literals"
+        },
+        {
+            "include": "#strings"
+        },
+        {
+            "include": "#numbers"
+        },
+        {
+            "include": "#operators"
+        },
+        {
+            "include": "#punctuation"
+        }
+    ],
+    "repository": {
+        "comments": {
+            "patterns": [
+                {
+                    "name": "comment.line.number-sign.sh2",
+                    "match": "#.*$"
+                }
+            ]
+        },
+        "keywords": {
+            "patterns": [
+                {
+                    "name": "keyword.control.sh2",
+                    "match": "\\b({keywords})\\b"
+                }
+            ]
+        },
+        "builtins": {
+            "patterns": [
+                {
+                    "name": "support.function.builtin.sh2",
+                    "match": "\\b({builtins})\\b"
+                }
+            ]
+        },
+        "functions": {
+            "patterns": [
+                {
+                    "match": "\\b([a-zA-Z_][a-zA-Z0-9_]*)\\.([a-zA-Z_][a-zA-Z0-9_]*)(?=\\()",
+                    "captures": {
+                        "1": {
+                            "name": "entity.name.namespace.sh2"
+                        },
+                        "2": {
+                            "name": "entity.name.function.sh2"
+                        }
+                    }
+                }
+            ]
+        },
+        "literals": {
+            "patterns": [
+                {
+                    "name": "constant.language.boolean.sh2",
+                    "match": "\\b({literals})\\b"
+                }
+            ]
+        },
+        "strings": {
+            "patterns": [
+                {
+                    "name": "string.quoted.double.interpolated.sh2",
+                    "begin": "\\$\"",
+                    "end": "\"",
+                    "patterns": [
+                        {
+                            "name": "meta.embedded.expression.sh2",
+                            "begin": "\\{",
+                            "end": "\\}",
+                            "patterns": [
+                                {
+                                    "include": "$self"
+                                }
+                            ]
+                        },
+                        {
+                            "name": "constant.character.escape.sh2",
+                            "match": "\\\\(?:u\\{[0-9a-fA-F]{1,6}\\}|.)"
+                        }
+                    ]
+                },
+                {
+                    "name": "string.quoted.double.raw.sh2",
+                    "begin": "r\"",
+                    "end": "\""
+                },
+                {
+                    "name": "string.quoted.double.sh2",
+                    "begin": "\"",
+                    "end": "\"",
+                    "patterns": [
+                        {
+                            "name": "constant.character.escape.sh2",
+                            "match": "\\\\(?:u\\{[0-9a-fA-F]{1,6}\\}|.)"
+                        }
+                    ]
+                }
+            ]
+        },
+        "numbers": {
+            "patterns": [
+                {
+                    "name": "constant.numeric.sh2",
+                    "match": "\\b[0-9]+\\b"
+                }
+            ]
+        },
+        "operators": {
+            "patterns": [
+                {
+                    "name": "keyword.operator.spread.sh2",
+                    "match": "\\.\\.\\."
+                },
+                {
+                    "name": "keyword.operator.range.sh2",
+                    "match": "\\.\\."
+                },
+                {
+                    "name": "keyword.operator.pipe-block.sh2",
+                    "match": "\\|>"
+                },
+                {
+                    "name": "keyword.operator.comparison.sh2",
+                    "match": "(==|!=|<=|>=|<|>)"
+                },
+                {
+                    "name": "keyword.operator.logical.sh2",
+                    "match": "(&&|\\|\\|)"
+                },
+                {
+                    "name": "keyword.operator.coalesce.sh2",
+                    "match": "\\?\\?"
+                },
+                {
+                    "name": "keyword.operator.arithmetic.sh2",
+                    "match": "(\\+|-|\\*|/|%)"
+                },
+                {
+                    "name": "keyword.operator.assignment.sh2",
+                    "match": "="
+                },
+                {
+                    "name": "keyword.operator.concat.sh2",
+                    "match": "&"
+                },
+                {
+                    "name": "keyword.operator.arrow.sh2",
+                    "match": "=>"
+                },
+                {
+                    "name": "keyword.operator.pipe.sh2",
+                    "match": "\\|"
+                },
+                {
+                    "name": "keyword.operator.not.sh2",
+                    "match": "!"
+                }
+            ]
+        },
+        "punctuation": {
+            "patterns": [
+                {
+                    "name": "punctuation.separator.dot.sh2",
+                    "match": "\\."
+                },
+                {
+                    "name": "punctuation.separator.colon.sh2",
+                    "match": ":"
+                },
+                {
+                    "name": "punctuation.separator.semicolon.sh2",
+                    "match": ";"
+                },
+                {
+                    "name": "punctuation.separator.comma.sh2",
+                    "match": ","
+                },
+                {
+                    "name": "punctuation.brackets.round.sh2",
+                    "match": "[()]"
+                },
+                {
+                    "name": "punctuation.brackets.square.sh2",
+                    "match": "[\\[\\]]"
+                },
+                {
+                    "name": "punctuation.brackets.curly.sh2",
+                    "match": "[{}]"
+                }
+            ]
+        }
+    }
+}
+"##;
+
+/// Highlight queries (`highlights.scm`). Words are matched by text on
+/// `identifier` nodes, so the queries do not depend on how a grammar names
+/// its keyword tokens.
+fn tree_sitter_queries() -> String {
+    let mut out = String::from(
+        "; tree-sitter highlight queries for sh2\n\
+         ; Generated by `sh2c emit-syntax tree-sitter-queries`; do not edit.\n\n\
+         (comment) @comment\n(string) @string\n(number) @number\n",
+    );
+    for (capture, words) in [("keyword", KEYWORDS), ("function.builtin", BUILTINS), ("boolean", LITERALS)] {
+        out.push_str(&format!("\n((identifier) @{c}\n (#any-of? @{c}", c = capture));
+        for chunk in words.chunks(8) {
+            let quoted: Vec<String> = chunk.iter().map(|w| format!("\"{}\"", w)).collect();
+            out.push_str(&format!("\n  {}", quoted.join(" ")));
+        }
+        out.push_str("))\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vim_matches_words_that_are_keyword_arguments() {
+        let vim = vim();
+        assert!(vim.contains("syn match sh2Builtin \"\\<contains\\>\"\n"), "{}", vim);
+        assert!(!vim.lines().any(|l| l.starts_with("syn keyword") && l.split(' ').any(|w| w == "contains")));
+    }
+}
@@ -0,0 +1,158 @@
+//! `on_enter` / `on_exit` hooks, woven into their target functions before any
+//! checking, so the rest of the compiler only ever sees ordinary statements.
+//!
+//! Enter hooks are prepended to the function body in source order. Exit hooks
+//! run in reverse source order before every `return` (including those in nested
+//! blocks) and at the end of a body that can fall off it. Returns inside
+//! subshells, pipelines and `spawn` only leave that child process, so they are
+//! left alone. Hooks only see functions of their own file, which keeps every
+//! woven statement's span in the right source map.
+
+use crate::ast::{Function, Hook, HookKind, Program, Stmt, StmtKind};
+use crate::span::{Diagnostic, SourceMap, Span};
+
+/// Moves `program.hooks` into the bodies of the functions they name.
+pub fn weave(program: &mut Program, sm: &SourceMap) -> Result<(), Diagnostic> {
+    let hooks = std::mem::take(&mut program.hooks);
+    for hook in &hooks {
+        let error = |msg: String, span: Span| Diagnostic {
+            msg,
+            code: None,
+            span,
+            sm: Some(sm.clone()),
+            file: Some(hook.file.clone()),
+            help: None,
+            additional: Vec::new(),
+        };
+        if let Some(span) = find_return(&hook.body) {
+            return Err(error(format!("`return` is not allowed in an {} hook", keyword(hook)), span));
+        }
+        for target in hook.targets.iter().filter(|t| *t != "*") {
+            if !program.functions.iter().any(|f| f.file == hook.file && f.name == *target) {
+                let msg = format!(
+                    "unknown function '{}' in {}(...); hooks apply to functions of the same file",
+                    target,
+                    keyword(hook)
+                );
+                return Err(error(msg, hook.span));
+            }
+        }
+    }
+
+    for func in &mut program.functions {
+        // `*` skips `init` blocks and functions whose bodies must stay a pure computation.
+        let wildcard =
+            !program.inits.contains(&func.name) && func.attr("pure").is_none() && func.attr("inline").is_none();
+        let applies = |hook: &&Hook| {
+            hook.file == func.file && hook.targets.iter().any(|t| *t == func.name || (t == "*" && wildcard))
+        };
+        let enter: Vec<Stmt> = hooks
+            .iter()
+            .filter(applies)
+            .filter(|h| h.kind == HookKind::Enter)
+            .flat_map(|h| h.body.iter().cloned())
+            .collect();
+        let exit: Vec<Stmt> = hooks
+            .iter()
+            .rev()
+            .filter(applies)
+            .filter(|h| h.kind == HookKind::Exit)
+            .flat_map(|h| h.body.iter().cloned())
+            .collect();
+        if !exit.is_empty() {
+            weave_exit(func, &exit);
+        }
+        if !enter.is_empty() {
+            func.body.splice(0..0, enter);
+        }
+    }
+    Ok(())
+}
+
+fn keyword(hook: &Hook) -> &'static str {
+    match hook.kind {
+        HookKind::Enter => "on_enter",
+        HookKind::Exit => "on_exit",
+    }
+}
+
+fn weave_exit(func: &mut Function, exit: &[Stmt]) {
+    before_returns(&mut func.body, exit);
+    let falls_off = !matches!(
+        func.body.last().map(|s| &s.node),
+        Some(StmtKind::Return(_) | StmtKind::ReturnValues(_) | StmtKind::Exit(_))
+    );
+    if falls_off {
+        func.body.extend(exit.iter().cloned());
+    }
+}
+
+/// Inserts `exit` before each `return` in `stmts` and the blocks nested in it.
+fn before_returns(stmts: &mut Vec<Stmt>, exit: &[Stmt]) {
+    let mut i = 0;
+    while i < stmts.len() {
+        match &mut stmts[i].node {
+            StmtKind::Return(_) | StmtKind::ReturnValues(_) => {
+                stmts.splice(i..i, exit.iter().cloned());
+                i += exit.len();
+            }
+            StmtKind::If { then_body, elifs, else_body, .. } => {
+                before_returns(then_body, exit);
+                for elif in elifs {
+                    before_returns(&mut elif.body, exit);
+                }
+                if let Some(body) = else_body {
+                    before_returns(body, exit);
+                }
+            }
+            StmtKind::Case { arms, .. } => arms.iter_mut().for_each(|arm| before_returns(&mut arm.body, exit)),
+            StmtKind::TryCatch { try_body: left, catch_body: right }
+            | StmtKind::AndThen { left, right }
+            | StmtKind::OrElse { left, right } => {
+                before_returns(left, exit);
+                before_returns(right, exit);
+            }
+            StmtKind::While { body, .. }
+            | StmtKind::For { body, .. }
+            | StmtKind::ForMap { body, .. }
+            | StmtKind::WithEnv { body, .. }
+            | StmtKind::WithCwd { body, .. }
+            | StmtKind::WithLog { body, .. }
+            | StmtKind::WithDeadline { body, .. }
+            | StmtKind::WithUmask { body, .. }
+            | StmtKind::WithRedirect { body, .. }
+            | StmtKind::Group { body }
+            | StmtKind::Unchecked { body } => before_returns(body, exit),
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// The first `return` anywhere in `stmts`, nested blocks included.
+pub(crate) fn find_return(stmts: &[Stmt]) -> Option<Span> {
+    stmts.iter().find_map(|stmt| match &stmt.node {
+        StmtKind::Return(_) | StmtKind::ReturnValues(_) => Some(stmt.span),
+        StmtKind::If { then_body, elifs, else_body, .. } => find_return(then_body)
+            .or_else(|| elifs.iter().find_map(|elif| find_return(&elif.body)))
+            .or_else(|| else_body.as_deref().and_then(find_return)),
+        StmtKind::Case { arms, .. } => arms.iter().find_map(|arm| find_return(&arm.body)),
+        StmtKind::TryCatch { try_body: left, catch_body: right }
+        | StmtKind::AndThen { left, right }
+        | StmtKind::OrElse { left, right } => find_return(left).or_else(|| find_return(right)),
+        StmtKind::While { body, .. }
+        | StmtKind::For { body, .. }
+        | StmtKind::ForMap { body, .. }
+        | StmtKind::WithEnv { body, .. }
+        | StmtKind::WithCwd { body, .. }
+        | StmtKind::WithLog { body, .. }
+        | StmtKind::WithDeadline { body, .. }
+        | StmtKind::WithUmask { body, .. }
+        | StmtKind::WithRedirect { body, .. }
+        | StmtKind::Group { body }
+        | StmtKind::Unchecked { body }
+        | StmtKind::Subshell { body }
+        | StmtKind::WithLimits { body, .. } => find_return(body),
+        _ => None,
+    })
+}
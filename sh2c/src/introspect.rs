@@ -0,0 +1,61 @@
+//! `sh2c introspect --json`: a machine-readable summary of the language for
+//! the LSP, the docs generator and other tools, so they do not keep their own
+//! copies of the keyword and builtin lists.
+//!
+//! Everything comes from the tables the compiler itself uses: `lang_spec`,
+//! the lexer's reserved words and the parser's attribute list.
+
+use crate::lang_spec::{BUILTIN_ARITIES, KEYWORDS, LITERALS, OPERATORS, STATEMENTS};
+
+pub fn summary_json() -> String {
+    let mut s = String::from("{\n");
+    s.push_str(&format!("  \"version\": {},\n", json_str(env!("CARGO_PKG_VERSION"))));
+    s.push_str(&format!("  \"keywords\": {},\n", json_list(KEYWORDS.iter().copied())));
+    s.push_str(&format!("  \"reserved_words\": {},\n", json_list(crate::lexer::keywords())));
+    s.push_str(&format!("  \"literals\": {},\n", json_list(LITERALS.iter().copied())));
+    s.push_str(&format!("  \"operators\": {},\n", json_list(OPERATORS.iter().copied())));
+
+    let statements: Vec<String> = STATEMENTS
+        .iter()
+        .map(|(name, syntax)| format!("    {{\"name\": {}, \"syntax\": {}}}", json_str(name), json_str(syntax)))
+        .collect();
+    s.push_str(&format!("  \"statements\": [\n{}\n  ],\n", statements.join(",\n")));
+
+    let mut builtins: Vec<_> = BUILTIN_ARITIES.to_vec();
+    builtins.sort_by_key(|(name, _, _)| *name);
+    let builtins: Vec<String> = builtins
+        .iter()
+        .map(|(name, min, max)| {
+            let max = max.map_or("null".to_string(), |m| m.to_string());
+            format!("    {{\"name\": {}, \"min_args\": {}, \"max_args\": {}}}", json_str(name), min, max)
+        })
+        .collect();
+    s.push_str(&format!("  \"builtins\": [\n{}\n  ],\n", builtins.join(",\n")));
+
+    let attributes: Vec<String> = crate::parser::ATTRIBUTES
+        .iter()
+        .map(|(name, arity)| format!("    {{\"name\": {}, \"max_args\": {}}}", json_str(name), arity.end()))
+        .collect();
+    s.push_str(&format!("  \"attributes\": [\n{}\n  ]\n", attributes.join(",\n")));
+    s.push_str("}\n");
+    s
+}
+
+fn json_list<'a>(items: impl IntoIterator<Item = &'a str>) -> String {
+    let items: Vec<String> = items.into_iter().map(json_str).collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -4,6 +4,10 @@ pub struct Function {
     pub params: Vec<String>,
     pub commands: Vec<Cmd>,
     pub file: String,
+    /// Called by the last line of the script: `main`, or the `--entry` function.
+    pub entry: bool,
+    /// `@cache`: output is remembered per argument list for the rest of the run.
+    pub cache: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -26,6 +30,9 @@ pub enum Val {
     And(Box<Val>, Box<Val>),
     Or(Box<Val>, Box<Val>),
     Not(Box<Val>),
+    /// `...xs` as a word: each item of the list variable (or `args`) becomes
+    /// its own argument. Only valid where `emit_word` is used.
+    Spread(Box<Val>),
     Exists(Box<Val>),
     IsDir(Box<Val>),
     IsFile(Box<Val>),
@@ -34,20 +41,36 @@ pub enum Val {
     IsReadable(Box<Val>),
     IsWritable(Box<Val>),
     IsNonEmpty(Box<Val>),
+    /// Length in characters: UTF-8 sequences count once.
     Len(Box<Val>),
+    /// `bytes_len(s)`: length in bytes.
+    BytesLen(Box<Val>),
+    /// `awk(program, input)`: the program text is a compile-time literal, the
+    /// input is fed on stdin, and the result is awk's stdout.
+    Awk {
+        program: String,
+        input: Box<Val>,
+    },
     Arg(u32),
     ArgDynamic(Box<Val>),
     Index {
         list: Box<Val>,
         index: Box<Val>,
+        optional: bool,
     },
     Join {
         list: Box<Val>,
         sep: Box<Val>,
     },
+    /// Conditional value: only the chosen branch is evaluated.
+    IfElse {
+        cond: Box<Val>,
+        then_val: Box<Val>,
+        else_val: Box<Val>,
+    },
     Count(Box<Val>),
     Bool(bool),
-    Number(u32),
+    Number(u64),
     List(Vec<Val>),
     Args,
     Status,
@@ -62,6 +85,9 @@ pub enum Val {
     Argv0,
     Argc,
     Input(Box<Val>),
+    /// Reads lines into a list: interactively until an empty line, otherwise until EOF.
+    /// Restricted-context (let only).
+    InputList(Box<Val>),
     Confirm { prompt: Box<Val>, default: bool },
     Matches(Box<Val>, Box<Val>),
     ParseArgs,
@@ -71,25 +97,58 @@ pub enum Val {
         name: String,
         args: Vec<Val>,
     },
+    /// Call to a `-> bool` function: true when it returns status 0.
+    BoolCall {
+        name: String,
+        args: Vec<Val>,
+    },
     LoadEnvfile(Box<Val>),
     JsonKv(Box<Val>),
     MapLiteral(Vec<(String, Val)>),
     MapIndex {
         map: String,
         key: String,
+        optional: bool,
     },
     Which(Box<Val>),
     ReadFile(Box<Val>),
+    /// read_file(path, default=...): yields `default` when the file does not exist.
+    ReadFileOr {
+        path: Box<Val>,
+        default: Box<Val>,
+    },
     TryRun(Vec<Val>),
     Home,
+    /// `last_command()`: text of the most recent command.
+    LastCommand,
+    /// `last_stderr()`: what the most recent command wrote to stderr.
+    LastStderr,
     PathJoin(Vec<Val>),
     Lines(Box<Val>),
+    /// lines0()/split0(): NUL-delimited records read from the output of this argv (bash-only).
+    Lines0(Vec<Val>),
     /// Glob pattern expansion (bash-only). Returns list of matched paths.
     Glob(Box<Val>),
+    /// Command argument emitted unquoted so the shell word-splits (and globs) it.
+    /// Only valid as a word of run()/sudo(); see `raw_arg()`.
+    RawArg(Box<Val>),
     FindFiles {
         dir: Box<Val>,
         name: Box<Val>,
     },
+    /// find(dir, name=, type=, max_depth=): sorted paths below `dir` (the root itself excluded).
+    Find {
+        dir: Box<Val>,
+        name: Option<Box<Val>>,
+        type_filter: Option<Box<Val>>,
+        maxdepth: Option<Box<Val>>,
+    },
+    /// Template rendering (bash-only). Substitutes `{{key}}` placeholders with entries of a map variable.
+    Render {
+        template: Box<Val>,
+        map: String,
+        escape: RenderEscape,
+    },
 
     ContainsList {
         list: Box<Val>,
@@ -104,11 +163,24 @@ pub enum Val {
         needle: Box<Val>,
     },
     StartsWith { text: Box<Val>, prefix: Box<Val> },
+    /// Boolean command-existence probe; results are cached per command name.
+    Have(Box<Val>),
+    /// True when the pidfile names a live process.
+    ServiceRunning(Box<Val>),
+    /// True once `host:port` accepts a TCP connection, false after `timeout` seconds.
+    WaitForPort {
+        host: Box<Val>,
+        port: Box<Val>,
+        timeout: Box<Val>,
+        interval: Box<Val>,
+    },
     Split { s: Box<Val>, delim: Box<Val> },
 
     /// A variable known to hold a boolean value ("1" or "0").
     /// Used in conditions to emit `[ "$var" = "1" ]` instead of non-empty check.
     BoolVar(String),
+    /// A variable holding a function reference, as the first word of a call.
+    CalleeVar(String),
     Capture {
         value: Box<Val>,
         allow_fail: bool,
@@ -130,6 +202,40 @@ pub enum Val {
         allow_fail: bool,
         loc: Option<String>,
     },
+    /// Wait for the first PID in a list to finish, kill the others, and return its exit code.
+    WaitAny {
+        pids: Box<Val>,
+        allow_fail: bool,
+        loc: Option<String>,
+    },
+}
+
+/// Escaping applied to map values substituted by `render()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderEscape {
+    None,
+    Html,
+    Shell,
+}
+
+/// Priority or resource limits of a `Cmd::WithLimits` subshell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Limit {
+    /// `renice -n <n>`.
+    Nice(i64),
+    /// `ionice -c <class> [-n <level>]`.
+    Ionice { class: u8, level: Option<i64> },
+    /// `ulimit -<flag> <value>` for each entry, values already in the target shell's units.
+    Ulimit(Vec<(char, String)>),
+}
+
+/// Filesystem statement kinds; see `Cmd::Fs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FsOp {
+    Mkdirs,
+    Copy,
+    Move,
+    Remove,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -178,6 +284,12 @@ pub enum Cmd {
         args: Vec<Val>,
         allow_fail: bool,
         loc: Option<String>,
+        /// `run(..., stdin=text(v))`: here-string input for the command.
+        stdin: Option<Val>,
+        /// `run(..., env={...})`: prefix assignments for this command only.
+        env: Vec<(String, Val)>,
+        /// `run(..., cwd=dir)`: run in a subshell after `cd dir`.
+        cwd: Option<Val>,
     },
     Print(Val),
     PrintErr(Val),
@@ -187,13 +299,22 @@ pub enum Cmd {
         elifs: Vec<(Val, Vec<Cmd>)>,
         else_body: Vec<Cmd>,
     },
-    Pipe(Vec<(Vec<Val>, bool)>, Option<String>),
-    PipeBlocks(Vec<Vec<Cmd>>, Option<String>),
+    /// Command pipeline: (args, allow_fail, per-segment stderr redirect) per stage.
+    Pipe(Vec<(Vec<Val>, bool, Option<RedirectOutputTarget>)>, Option<String>),
+    /// Mixed block/command pipeline: (body, per-segment stderr redirect) per stage.
+    PipeBlocks(Vec<(Vec<Cmd>, Option<RedirectOutputTarget>)>, Option<String>),
     PipeEachLine {
         producer: Box<Cmd>,
         var: String,
+        /// Read the lines on this fd instead of stdin, leaving stdin to the body.
+        fd: Option<u8>,
         body: Vec<Cmd>,
     },
+    /// `let name = run(..) | ...`: binds the stdout of a lowered `Pipe`/`PipeBlocks`.
+    CapturePipe {
+        name: String,
+        pipe: Box<Cmd>,
+    },
     Case {
         expr: Val,
         arms: Vec<(Vec<Pattern>, Vec<Cmd>)>,
@@ -216,7 +337,32 @@ pub enum Cmd {
     Break,
     Continue,
     Return(Option<Val>),
+    /// `return cond` in a `-> bool` function: status 0 when `cond` holds, else 1.
+    ReturnBool(Val),
     Require(Vec<Val>),
+    /// Start a command in the background, recording its PID in `pidfile` and
+    /// appending stdout/stderr to `log` (or discarding them).
+    ServiceStart {
+        pidfile: Val,
+        args: Vec<Val>,
+        log: Option<Val>,
+        loc: Option<String>,
+    },
+    /// Block until `host:port` accepts a TCP connection; fails after `timeout` seconds.
+    WaitForPort {
+        host: Val,
+        port: Val,
+        timeout: Val,
+        interval: Val,
+        loc: Option<String>,
+    },
+    /// Stop the process named by `pidfile` (TERM, then KILL after `grace` seconds)
+    /// and remove the pidfile.
+    ServiceStop {
+        pidfile: Val,
+        grace: Option<Val>,
+        loc: Option<String>,
+    },
     Exit(Option<Val>),
     WithEnv {
         bindings: Vec<(String, Val)>,
@@ -231,10 +377,55 @@ pub enum Cmd {
         path: Val,
         body: Vec<Cmd>,
     },
+    /// Run `body` as its own job; kill it and every process it started once `duration` passes.
+    WithDeadline {
+        duration: Val,
+        body: Vec<Cmd>,
+        loc: Option<String>,
+    },
+    /// Run `body` under umask `mask`, keeping the previous mask in `saved` to restore after it.
+    WithUmask {
+        mask: String,
+        saved: String,
+        body: Vec<Cmd>,
+    },
+    /// Run `body` in a subshell at a lower priority or under resource limits.
+    WithLimits {
+        limit: Limit,
+        body: Vec<Cmd>,
+    },
+    /// mkdirs/copy/move/remove, kept as a semantic node rather than a plain Exec.
+    Fs {
+        op: FsOp,
+        args: Vec<Val>,
+        recursive: bool,
+        force: bool,
+        loc: Option<String>,
+    },
+    /// Replace every literal occurrence of `pattern` in `path`; the file is only
+    /// rewritten (and backed up to `path.bak` when `backup`) if something changed.
+    ReplaceInFile {
+        path: Val,
+        pattern: Val,
+        replacement: Val,
+        backup: bool,
+        loc: Option<String>,
+    },
+    /// Append `line` to `path` unless the file already contains it as a whole line.
+    AppendLineIfMissing {
+        path: Val,
+        line: Val,
+        backup: bool,
+        loc: Option<String>,
+    },
     WriteFile {
         path: Val,
         content: Val,
         append: bool,
+        /// Write to a same-directory temp file, then rename it over `path`.
+        atomic: bool,
+        /// Octal permission bits (e.g. "0644") applied to the written file.
+        mode: Option<String>,
     },
     Log {
         level: LogLevel,
@@ -254,6 +445,35 @@ pub enum Cmd {
     Group {
         body: Vec<Cmd>,
     },
+    /// Each statement runs with its failure ignored; `__sh2_status` keeps the status.
+    Unchecked {
+        body: Vec<Cmd>,
+    },
+    /// Debugger hook before a statement (`sh2do --debug`): pauses when stepping,
+    /// at a `--break` position, or always for a `breakpoint()` statement.
+    Debug {
+        loc: String,
+        /// The statement's source line, shown at the prompt.
+        source: String,
+        /// Variables in scope, for the `vars` command.
+        vars: Vec<String>,
+        breakpoint: bool,
+    },
+    /// A function-body statement timed for `--profile`; the elapsed time is
+    /// appended to the profile log under `loc` once `body` finishes.
+    Profile {
+        loc: String,
+        /// The statement's source line, shown in the report.
+        source: String,
+        /// Holds the start time; one per function so nested calls keep their own.
+        start_var: String,
+        body: Vec<Cmd>,
+    },
+    /// `dump_vars()`: prints the variables declared so far and their values to stderr.
+    DumpVars {
+        loc: String,
+        vars: Vec<String>,
+    },
     WithRedirect {
         stdout: Option<Vec<RedirectOutputTarget>>,
         stderr: Option<Vec<RedirectOutputTarget>>,
@@ -300,6 +520,8 @@ pub enum RedirectOutputTarget {
 pub enum RedirectInputTarget {
     File { path: Val },
     HereDoc { content: String },
+    /// Bash `<<<`; POSIX `printf '%s\n' ... |`.
+    Text { value: Val },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -326,7 +548,7 @@ impl Cmd {
             Cmd::Pipe(_, loc) => *loc = None,
             Cmd::PipeBlocks(blocks, loc) => {
                 *loc = None;
-                for block in blocks {
+                for (block, _) in blocks {
                     for cmd in block { cmd.strip_spans(); }
                 }
             }
@@ -334,6 +556,7 @@ impl Cmd {
                 producer.strip_spans();
                 for cmd in body { cmd.strip_spans(); }
             }
+            Cmd::CapturePipe { pipe, .. } => pipe.strip_spans(),
             Cmd::ExecReplace(_, loc) => *loc = None,
             
             // Recursive cases
@@ -349,8 +572,15 @@ impl Cmd {
             Cmd::WithEnv { body, .. } => for c in body { c.strip_spans(); },
             Cmd::WithLog { body, .. } => for c in body { c.strip_spans(); },
             Cmd::WithCwd { body, .. } => for c in body { c.strip_spans(); },
+            Cmd::WithUmask { body, .. } => for c in body { c.strip_spans(); },
+            Cmd::WithLimits { body, .. } => for c in body { c.strip_spans(); },
+            Cmd::WithDeadline { body, loc, .. } => {
+                *loc = None;
+                for c in body { c.strip_spans(); }
+            }
             Cmd::Subshell { body } => for c in body { c.strip_spans(); },
             Cmd::Group { body } => for c in body { c.strip_spans(); },
+            Cmd::Unchecked { body } => for c in body { c.strip_spans(); },
             Cmd::WithRedirect { body, .. } => for c in body { c.strip_spans(); },
             Cmd::Spawn(cmd) => cmd.strip_spans(),
             Cmd::TryCatch { try_body, catch_body } => {
@@ -372,9 +602,16 @@ impl Cmd {
             Cmd::Break => {},
             Cmd::Continue => {},
             Cmd::Return(_) => {},
+            Cmd::ReturnBool(_) => {},
             Cmd::Require(_) => {},
+            Cmd::ServiceStart { loc, .. } => *loc = None,
+            Cmd::ServiceStop { loc, .. } => *loc = None,
+            Cmd::WaitForPort { loc, .. } => *loc = None,
             Cmd::Exit(_) => {},
             Cmd::WriteFile { .. } => {},
+            Cmd::Fs { loc, .. } => *loc = None,
+            Cmd::ReplaceInFile { loc, .. } => *loc = None,
+            Cmd::AppendLineIfMissing { loc, .. } => *loc = None,
             Cmd::Log { .. } => {},
             Cmd::Cd(_) => {},
             Cmd::Raw { loc, .. } => *loc = None,
@@ -385,6 +622,9 @@ impl Cmd {
             Cmd::Unset(_) => {},
             Cmd::Source(_) => {},
             Cmd::SaveEnvfile { .. } => {},
+            Cmd::Debug { .. } => {},
+            Cmd::DumpVars { .. } => {},
+            Cmd::Profile { body, .. } => for c in body { c.strip_spans(); },
 
         }
     }
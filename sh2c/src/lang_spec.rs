@@ -3,6 +3,7 @@
 
 pub const KEYWORDS: &[&str] = &[
     "func",
+    "extern",
     "let",
     "if",
     "elif",
@@ -10,6 +11,7 @@ pub const KEYWORDS: &[&str] = &[
     "while",
     "for",
     "in",
+    "each_line",
     "try",
     "catch",
     "with",
@@ -20,6 +22,7 @@ pub const KEYWORDS: &[&str] = &[
     "subshell",
     "group",
     "spawn",
+    "pipe",
     "return",
     "exit",
     "break",
@@ -36,6 +39,7 @@ pub const BUILTINS: &[&str] = &[
     "print",
     "print_err",
     "capture",
+    "try_run",
     "status",
     "pid",
     "ppid",
@@ -56,6 +60,7 @@ pub const BUILTINS: &[&str] = &[
     "is_writable",
     "is_non_empty",
     "len",
+    "bytes_len",
     "count",
     "join",
     "input",
@@ -71,18 +76,28 @@ pub const BUILTINS: &[&str] = &[
     "stdout",
     "stderr",
     "stdin",
+    "stdin_lines",
     "glob",
+    "find",
+    "find0",
+    "find_files",
+    "raw_arg",
     "bool_str",
     "sudo",
     "lines",
+    "lines0",
+    "split0",
     "split",
+    "split_once",
     "trim",
     "replace",
+    "awk",
     "matches",
     "contains",
     "contains_line",
     "starts_with",
     "json_kv",
+    "render",
     "load_envfile",
     "save_envfile",
     "parse_args",
@@ -95,7 +110,19 @@ pub const BUILTINS: &[&str] = &[
     "read_file",
     "write_file",
     "append_file",
+    "replace_in_file",
+    "append_line_if_missing",
+    "mkdirs",
+    "copy",
+    "move",
+    "remove",
     "which",
+    "have",
+    "service_start",
+    "service_stop",
+    "service_running",
+    "wait_for_port",
+    "input_list",
 ];
 
 pub const OPERATORS: &[&str] = &[
@@ -115,3 +142,166 @@ pub const LITERALS: &[&str] = &[
     "true",
     "false",
 ];
+
+/// Statement forms, named after the `statement` alternatives in
+/// `artifacts/grammar/sh2.ebnf` (without the `_stmt` suffix), with a short
+/// syntax summary.
+pub const STATEMENTS: &[(&str, &str)] = &[
+    ("let", "let name = expr"),
+    ("run", "run(cmd, args..., options...)"),
+    ("exec", "exec(cmd, args...)"),
+    ("print", "print(expr)"),
+    ("print_err", "print_err(expr)"),
+    ("if", "if cond { ... } elif cond { ... } else { ... }"),
+    ("while", "while cond { ... }"),
+    ("for", "for name in expr { ... }"),
+    ("for_map", "for (key, value) in map { ... }"),
+    ("try_catch", "try { ... } catch { ... }"),
+    ("case", "case expr { pattern => { ... } }"),
+    ("with", "with env { ... } | cwd(dir) | log(path) | redirect { ... } { ... }"),
+    ("subshell", "subshell { ... }"),
+    ("group", "group { ... }"),
+    ("pipe", "pipe segment | segment ..."),
+    ("return", "return [expr]"),
+    ("exit", "exit [expr]"),
+    ("break", "break"),
+    ("continue", "continue"),
+    ("set", "set target = expr"),
+    ("call", "name(args...)"),
+    ("qualified_call", "alias.name(args...)"),
+    ("export", "export(\"NAME\", [expr])"),
+    ("unset", "unset(\"NAME\")"),
+    ("source", "source(path)"),
+    ("sh", "sh(cmd, options...) | sh { ... }"),
+    ("cd", "cd(path)"),
+    ("wait", "wait(pid)"),
+    ("and_then", "stmt && stmt"),
+    ("or_else", "stmt || stmt"),
+];
+
+/// Positional argument counts of every builtin in `BUILTINS` and
+/// `builtins::ALL_BUILTINS`: (name, minimum, maximum or `None` if unbounded).
+/// Named options such as `allow_fail=` are not counted.
+pub const BUILTIN_ARITIES: &[(&str, usize, Option<usize>)] = &[
+    ("run", 1, None),
+    ("exec", 1, None),
+    ("print", 1, Some(1)),
+    ("print_err", 1, Some(1)),
+    ("capture", 1, Some(1)),
+    ("try_run", 1, None),
+    ("status", 0, Some(0)),
+    ("pid", 0, Some(0)),
+    ("ppid", 0, Some(0)),
+    ("uid", 0, Some(0)),
+    ("pwd", 0, Some(0)),
+    ("self_pid", 0, Some(0)),
+    ("argv0", 0, Some(0)),
+    ("argc", 0, Some(0)),
+    ("arg", 1, Some(1)),
+    ("args", 0, Some(0)),
+    ("argv", 0, Some(0)),
+    ("exists", 1, Some(1)),
+    ("is_dir", 1, Some(1)),
+    ("is_file", 1, Some(1)),
+    ("is_symlink", 1, Some(1)),
+    ("is_exec", 1, Some(1)),
+    ("is_readable", 1, Some(1)),
+    ("is_writable", 1, Some(1)),
+    ("is_non_empty", 1, Some(1)),
+    ("len", 1, Some(1)),
+    ("bytes_len", 1, Some(1)),
+    ("count", 1, Some(1)),
+    ("join", 2, Some(2)),
+    ("input", 1, Some(1)),
+    ("confirm", 1, Some(1)),
+    ("export", 1, Some(2)),
+    ("unset", 1, Some(1)),
+    ("source", 1, Some(1)),
+    ("sh", 1, Some(1)),
+    ("wait", 1, Some(1)),
+    ("cd", 1, Some(1)),
+    ("heredoc", 1, Some(1)),
+    ("file", 1, Some(1)),
+    ("stdout", 0, Some(0)),
+    ("stderr", 0, Some(0)),
+    ("stdin", 0, Some(0)),
+    ("stdin_lines", 0, Some(0)),
+    ("glob", 1, Some(1)),
+    ("find", 0, Some(1)),
+    ("find0", 0, Some(0)),
+    ("find_files", 0, Some(0)),
+    ("raw_arg", 1, Some(1)),
+    ("bool_str", 1, Some(1)),
+    ("sudo", 1, None),
+    ("lines", 1, Some(1)),
+    ("lines0", 1, Some(1)),
+    ("split0", 1, Some(1)),
+    ("split", 2, Some(2)),
+    ("split_once", 2, Some(2)),
+    ("trim", 1, Some(1)),
+    ("replace", 3, Some(3)),
+    ("awk", 2, Some(2)),
+    ("matches", 2, Some(2)),
+    ("contains", 2, Some(2)),
+    ("contains_line", 2, Some(2)),
+    ("starts_with", 2, Some(2)),
+    ("json_kv", 1, Some(1)),
+    ("render", 2, Some(2)),
+    ("load_envfile", 1, Some(1)),
+    ("save_envfile", 2, Some(2)),
+    ("parse_args", 0, Some(0)),
+    ("home", 0, Some(0)),
+    ("breakpoint", 0, Some(0)),
+    ("dump_vars", 0, Some(0)),
+    ("last_command", 0, Some(0)),
+    ("last_stderr", 0, Some(0)),
+    ("path_join", 1, None),
+    ("to_stdout", 0, Some(0)),
+    ("to_stderr", 0, Some(0)),
+    ("inherit_stdout", 0, Some(0)),
+    ("inherit_stderr", 0, Some(0)),
+    ("read_file", 1, Some(1)),
+    ("write_file", 2, Some(3)),
+    ("append_file", 2, Some(2)),
+    ("replace_in_file", 3, Some(3)),
+    ("append_line_if_missing", 2, Some(2)),
+    ("mkdirs", 1, Some(1)),
+    ("copy", 2, Some(2)),
+    ("move", 2, Some(2)),
+    ("remove", 1, Some(1)),
+    ("which", 1, Some(1)),
+    ("have", 1, Some(1)),
+    ("service_start", 2, Some(3)),
+    ("service_stop", 1, Some(2)),
+    ("service_running", 1, Some(1)),
+    ("wait_for_port", 2, Some(2)),
+    ("input_list", 1, Some(1)),
+    ("require", 1, Some(1)),
+    ("log_info", 1, Some(2)),
+    ("log_warn", 1, Some(2)),
+    ("log_error", 1, Some(2)),
+    ("spawn", 1, Some(1)),
+    ("wait_all", 1, Some(1)),
+    ("wait_any", 1, Some(1)),
+    ("before", 2, Some(2)),
+    ("after", 2, Some(2)),
+    ("coalesce", 2, Some(2)),
+    ("default", 2, Some(2)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_builtin_has_an_arity() {
+        let names = BUILTINS.iter().chain(crate::builtins::ALL_BUILTINS.iter());
+        for name in names {
+            assert!(BUILTIN_ARITIES.iter().any(|(n, _, _)| n == name), "no arity for builtin '{}'", name);
+        }
+        for (name, min, max) in BUILTIN_ARITIES {
+            assert!(BUILTINS.contains(name) || crate::builtins::is_builtin(name), "'{}' is not a builtin", name);
+            assert!(max.is_none_or(|max| *min <= max), "bad arity for '{}'", name);
+        }
+    }
+}
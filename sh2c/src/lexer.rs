@@ -32,18 +32,228 @@ impl<'a> Lexer<'a> {
         c
     }
 
-    fn error<T>(&self, msg: &str, start: usize) -> Result<T, Diagnostic> {
+    /// Consumes the rest of an identifier and returns its text, borrowed from
+    /// the source so keywords never allocate. Past ASCII, identifiers follow
+    /// UAX #31 (`XID_Continue`).
+    fn take_ident(&mut self, start: usize) -> &'a str {
+        while self.peek().is_some_and(|ch| ch.is_ascii_alphanumeric() || *ch == '_' || unicode_ident::is_xid_continue(*ch)) {
+            self.next();
+        }
+        &self.sm.src()[start..self.pos]
+    }
+
+    /// Decodes the escape sequence whose backslash, at `start`, has just been
+    /// consumed.
+    fn escape(&mut self, start: usize) -> Result<char, Diagnostic> {
+        match decode_escape(|| self.next()) {
+            Ok(c) => Ok(c),
+            Err((code, msg)) => self.error_with_help(code, &msg, ESCAPE_HELP, start),
+        }
+    }
+
+    /// Lexes a number literal: decimal or `0x` hex, `_` between digits, and an
+    /// optional size suffix (`K`, `M`, `G`, `T` or `Ki`, `Mi`, `Gi`, `Ti`) that
+    /// multiplies it out. The value must fit shell arithmetic (a signed 64-bit
+    /// integer).
+    fn number(&mut self, start: usize) -> Result<u64, Diagnostic> {
+        while self.peek().is_some_and(|ch| ch.is_ascii_alphanumeric() || *ch == '_') {
+            self.next();
+        }
+        let text = &self.sm.src()[start..self.pos];
+        let (digits, radix, suffix) = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => (hex, 16, ""),
+            None => {
+                let split = text.find(|ch: char| ch.is_ascii_alphabetic()).unwrap_or(text.len());
+                (&text[..split], 10, &text[split..])
+            }
+        };
+        if digits.split('_').any(|group| group.is_empty()) {
+            let msg = format!("Invalid number literal `{}`: `_` may only separate digits", text);
+            return self.error("E0005", &msg, start);
+        }
+        let Ok(value) = u64::from_str_radix(&digits.replace('_', ""), radix) else {
+            let msg = if radix == 16 {
+                format!("Invalid number literal `{}`: expected hex digits after `0x`", text)
+            } else {
+                format!("Invalid number literal `{}`", text)
+            };
+            return self.error("E0005", &msg, start);
+        };
+        let multiplier: u64 = match suffix {
+            "" => 1,
+            "K" => 1_000,
+            "M" => 1_000_000,
+            "G" => 1_000_000_000,
+            "T" => 1_000_000_000_000,
+            "Ki" => 1 << 10,
+            "Mi" => 1 << 20,
+            "Gi" => 1 << 30,
+            "Ti" => 1 << 40,
+            _ => {
+                let msg = format!("Unknown number suffix `{}` in `{}`", suffix, text);
+                let help = "size suffixes are K, M, G, T (powers of 1000) and Ki, Mi, Gi, Ti (powers of 1024)";
+                return self.error_with_help("E0005", &msg, help, start);
+            }
+        };
+        match value.checked_mul(multiplier) {
+            Some(n) if n <= i64::MAX as u64 => Ok(n),
+            _ => {
+                let msg = format!("Number literal `{}` is too large for shell arithmetic (max {})", text, i64::MAX);
+                self.error("E0005", &msg, start)
+            }
+        }
+    }
+
+    fn error_with_help<T>(&self, code: &'static str, msg: &str, help: &str, start: usize) -> Result<T, Diagnostic> {
+        self.error(code, msg, start).map_err(|d| Diagnostic { help: Some(help.to_string()), ..d })
+    }
+
+    fn error<T>(&self, code: &'static str, msg: &str, start: usize) -> Result<T, Diagnostic> {
         let span = Span::new(start, self.pos);
         Err(Diagnostic {
             msg: msg.to_string(),
+            code: Some(code),
             span,
             sm: Some(self.sm.clone()),
             file: Some(self.file.to_string()),
             help: None,
+            additional: Vec::new(),
         })
     }
 }
 
+/// Words the lexer reads as their own token instead of an identifier.
+const RESERVED_WORDS: &[(&str, TokenKind)] = &[
+    ("func", TokenKind::Func),
+    ("extern", TokenKind::Extern),
+    ("run", TokenKind::Run),
+    ("print", TokenKind::Print),
+    ("print_err", TokenKind::PrintErr),
+    ("if", TokenKind::If),
+    ("elif", TokenKind::Elif),
+    ("else", TokenKind::Else),
+    ("let", TokenKind::Let),
+    ("case", TokenKind::Case),
+    ("while", TokenKind::While),
+    ("for", TokenKind::For),
+    ("in", TokenKind::In),
+    ("args", TokenKind::Args),
+    ("with", TokenKind::With),
+    ("env", TokenKind::Env),
+    ("cd", TokenKind::Cd),
+    ("cwd", TokenKind::Cwd),
+    ("sh", TokenKind::Sh),
+    ("break", TokenKind::Break),
+    ("continue", TokenKind::Continue),
+    ("return", TokenKind::Return),
+    ("exit", TokenKind::Exit),
+    ("capture", TokenKind::Capture),
+    ("subshell", TokenKind::Subshell),
+    ("group", TokenKind::Group),
+    ("redirect", TokenKind::Redirect),
+    ("stdout", TokenKind::Stdout),
+    ("stderr", TokenKind::Stderr),
+    ("stdin", TokenKind::Stdin),
+    ("file", TokenKind::File),
+    ("append", TokenKind::Append),
+    ("spawn", TokenKind::Spawn),
+    ("wait", TokenKind::Wait),
+    ("try", TokenKind::Try),
+    ("catch", TokenKind::Catch),
+    ("export", TokenKind::Export),
+    ("unset", TokenKind::Unset),
+    ("exists", TokenKind::Exists),
+    ("is_dir", TokenKind::IsDir),
+    ("is_file", TokenKind::IsFile),
+    ("is_symlink", TokenKind::IsSymlink),
+    ("is_exec", TokenKind::IsExec),
+    ("is_readable", TokenKind::IsReadable),
+    ("is_writable", TokenKind::IsWritable),
+    ("is_non_empty", TokenKind::IsNonEmpty),
+    ("bool_str", TokenKind::BoolStr),
+    ("len", TokenKind::Len),
+    ("source", TokenKind::Source),
+    ("arg", TokenKind::Arg),
+    ("index", TokenKind::Index),
+    ("join", TokenKind::Join),
+    ("exec", TokenKind::Exec),
+    ("status", TokenKind::Status),
+    ("pid", TokenKind::Pid),
+    ("count", TokenKind::Count),
+    ("uid", TokenKind::Uid),
+    ("ppid", TokenKind::Ppid),
+    ("pwd", TokenKind::Pwd),
+    ("self_pid", TokenKind::SelfPid),
+    ("argv0", TokenKind::Argv0),
+    ("argc", TokenKind::Argc),
+    ("true", TokenKind::True),
+    ("false", TokenKind::False),
+    ("set", TokenKind::Set),
+    ("pipe", TokenKind::PipeKw),
+    ("log", TokenKind::Log),
+    ("import", TokenKind::Import),
+    ("input", TokenKind::Input),
+    ("confirm", TokenKind::Confirm),
+    ("each_line", TokenKind::EachLine),
+    ("as", TokenKind::As),
+];
+
+/// Every word the lexer reserves: the language keywords plus builtins that
+/// have their own token, such as `print` and `len`. None of them can name a
+/// variable or function.
+pub fn keywords() -> Vec<&'static str> {
+    RESERVED_WORDS.iter().map(|(word, _)| *word).collect()
+}
+
+fn keyword_kind(ident: &str) -> Option<TokenKind> {
+    RESERVED_WORDS.iter().find(|(word, _)| *word == ident).map(|(_, kind)| kind.clone())
+}
+
+const ESCAPE_HELP: &str =
+    "string escapes are \\n \\t \\r \\e \\\\ \\\" \\$ \\{ \\} and \\u{hex}; a raw string r\"...\" keeps backslashes as written";
+
+/// Decodes one escape sequence, reading the characters after its backslash
+/// from `next`. Shared by plain, triple-quoted and `$"..."` strings so every
+/// string form agrees on what a backslash means. Errors carry their diagnostic code.
+pub(crate) fn decode_escape(mut next: impl FnMut() -> Option<char>) -> Result<char, (&'static str, String)> {
+    let Some(c) = next() else {
+        return Err(("E0001", "Unexpected EOF in string escape".to_string()));
+    };
+    decode_escape_char(c, next).map_err(|msg| ("E0004", msg))
+}
+
+fn decode_escape_char(c: char, mut next: impl FnMut() -> Option<char>) -> Result<char, String> {
+    match c {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        'e' => Ok('\x1b'),
+        '\\' | '"' | '$' | '{' | '}' => Ok(c),
+        '0' => Err("`\\0` is not allowed in strings: shell strings cannot hold NUL bytes".to_string()),
+        'u' => {
+            let invalid = || "Invalid unicode escape: expected `\\u{...}` with 1 to 6 hex digits".to_string();
+            if next() != Some('{') {
+                return Err(invalid());
+            }
+            let mut hex = String::new();
+            loop {
+                match next() {
+                    Some('}') => break,
+                    Some(h) if h.is_ascii_hexdigit() && hex.len() < 6 => hex.push(h),
+                    _ => return Err(invalid()),
+                }
+            }
+            let code = u32::from_str_radix(&hex, 16).map_err(|_| invalid())?;
+            match char::from_u32(code) {
+                Some('\0') => Err("`\\u{0}` is not allowed in strings: shell strings cannot hold NUL bytes".to_string()),
+                Some(ch) => Ok(ch),
+                None => Err(format!("Invalid unicode escape: `\\u{{{}}}` is not a Unicode scalar value", hex)),
+            }
+        }
+        _ => Err(format!("Unknown escape sequence `\\{}` in string", c)),
+    }
+}
+
 pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
     let mut tokens = Vec::new();
     let mut lexer = Lexer::new(sm, file);
@@ -55,6 +265,7 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                 lexer.next();
             }
             // ... simple tokens same as before ...
+            // A comment; this also skips a leading `#!/usr/bin/env sh2do` line.
             '#' => {
                 while let Some(&c) = lexer.peek() {
                     if c == '\n' {
@@ -71,6 +282,7 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
             ']' => { lexer.next(); tokens.push(Token { kind: TokenKind::RBracket, span: Span::new(start, lexer.pos) }); }
             ',' => { lexer.next(); tokens.push(Token { kind: TokenKind::Comma, span: Span::new(start, lexer.pos) }); }
             ':' => { lexer.next(); tokens.push(Token { kind: TokenKind::Colon, span: Span::new(start, lexer.pos) }); }
+            '@' => { lexer.next(); tokens.push(Token { kind: TokenKind::At, span: Span::new(start, lexer.pos) }); }
             '=' => {
                 lexer.next();
                 if lexer.peek() == Some(&'=') {
@@ -93,13 +305,43 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
             '/' => {
                 lexer.next();
                 if lexer.peek() == Some(&'/') {
-                    // Comment
+                    // Comment; exactly three slashes make a doc comment for the next item.
+                    lexer.next();
+                    let mut slashes = 2;
+                    while lexer.peek() == Some(&'/') {
+                        lexer.next();
+                        slashes += 1;
+                    }
+                    let mut text = String::new();
                     while let Some(&c) = lexer.peek() {
                         if c == '\n' {
                             break;
                         }
+                        text.push(c);
                         lexer.next();
                     }
+                    if slashes == 3 {
+                        let text = text.strip_prefix(' ').unwrap_or(&text).trim_end().to_string();
+                        tokens.push(Token { kind: TokenKind::DocComment(text), span: Span::new(start, lexer.pos) });
+                    }
+                } else if lexer.peek() == Some(&'*') {
+                    // Block comment; nested `/* */` pairs must balance.
+                    lexer.next();
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match lexer.next() {
+                            Some('/') if lexer.peek() == Some(&'*') => {
+                                lexer.next();
+                                depth += 1;
+                            }
+                            Some('*') if lexer.peek() == Some(&'/') => {
+                                lexer.next();
+                                depth -= 1;
+                            }
+                            Some(_) => {}
+                            None => return lexer.error("E0003", "Unterminated block comment (missing `*/`)", start),
+                        }
+                    }
                 } else {
                      tokens.push(Token { kind: TokenKind::Slash, span: Span::new(start, lexer.pos) });
                 }
@@ -136,8 +378,14 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                 lexer.next();
                 if let Some('.') = lexer.peek() {
                      lexer.next();
+                     let kind = if lexer.peek() == Some(&'.') {
+                         lexer.next();
+                         TokenKind::Ellipsis
+                     } else {
+                         TokenKind::DotDot
+                     };
                      tokens.push(Token {
-                         kind: TokenKind::DotDot,
+                         kind,
                          span: Span::new(start, lexer.pos),
                      });
                 } else {
@@ -156,6 +404,24 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                     tokens.push(Token { kind: TokenKind::Pipe, span: Span::new(start, lexer.pos) });
                 }
             }
+            '?' => {
+                lexer.next();
+                let kind = match lexer.peek() {
+                    Some(&'?') => TokenKind::QuestionQuestion,
+                    Some(&'.') => TokenKind::QuestionDot,
+                    Some(&'[') => TokenKind::QuestionBracket,
+                    _ => {
+                        return lexer.error_with_help(
+                            "E0002",
+                            "Unexpected character: ?",
+                            "use `a ?? b` for a fallback value, `m?.key` or `xs?[i]` for a lookup that may be missing, or `if cond { a } else { b }` to choose between two",
+                            start,
+                        );
+                    }
+                };
+                lexer.next();
+                tokens.push(Token { kind, span: Span::new(start, lexer.pos) });
+            }
             '$' => { lexer.next(); tokens.push(Token { kind: TokenKind::Dollar, span: Span::new(start, lexer.pos) }); }
             '!' => {
                 lexer.next();
@@ -201,27 +467,12 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
 
                         if let Some(ch) = lexer.next() {
                             if ch == '\\' {
-                                if let Some(escaped) = lexer.next() {
-                                    match escaped {
-                                        'n' => s.push('\n'),
-                                        't' => s.push('\t'),
-                                        'r' => s.push('\r'),
-                                        '\\' => s.push('\\'),
-                                        '"' => s.push('"'),
-                                        '$' => {
-                                            s.push('\\');
-                                            s.push('$');
-                                        }
-                                        _ => s.push(escaped),
-                                    }
-                                } else {
-                                    return lexer.error("Unexpected EOF in string escape", start);
-                                }
+                                s.push(lexer.escape(lexer.pos - 1)?);
                             } else {
                                 s.push(ch);
                             }
                         } else {
-                             return lexer.error("Unterminated triple-quoted string", start);
+                             return lexer.error("E0001", "Unterminated triple-quoted string", start);
                         }
                     }
                     tokens.push(Token {
@@ -237,18 +488,9 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                             break;
                         }
                          if ch == '\\' {
+                            let esc_start = lexer.pos;
                             lexer.next(); // consume backslash
-                            if let Some(&escaped) = lexer.peek() {
-                                match escaped {
-                                    'n' => { s.push('\n'); lexer.next(); }
-                                    't' => { s.push('\t'); lexer.next(); }
-                                    'r' => { s.push('\r'); lexer.next(); }
-                                    '\\' => { s.push('\\'); lexer.next(); }
-                                    '"' => { s.push('"'); lexer.next(); }
-                                    '$' => { s.push('\\'); s.push('$'); lexer.next(); }
-                                    _ => { s.push(escaped); lexer.next(); }
-                                }
-                            }
+                            s.push(lexer.escape(esc_start)?);
                         } else {
                             s.push(ch);
                             lexer.next();
@@ -258,7 +500,7 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                         lexer.next(); // consume closing quote
                     } else {
                         // EOF before quote
-                        return lexer.error("Unterminated string (missing closing quote)", start);
+                        return lexer.error("E0001", "Unterminated string (missing closing quote)", start);
                     }
                     tokens.push(Token {
                         kind: TokenKind::String(s),
@@ -267,15 +509,7 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                 }
             }
             _ if c.is_ascii_digit() => {
-                let mut num_str = String::new();
-                while let Some(&ch) = lexer.peek() {
-                    if !ch.is_ascii_digit() {
-                        break;
-                    }
-                    num_str.push(ch);
-                    lexer.next();
-                }
-                let n: u32 = num_str.parse().expect("Invalid number literal");
+                let n = lexer.number(start)?;
                 tokens.push(Token {
                     kind: TokenKind::Number(n),
                     span: Span::new(start, lexer.pos),
@@ -318,7 +552,7 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                             if let Some(ch) = lexer.next() {
                                 s.push(ch);
                             } else {
-                                return lexer.error("Unterminated raw triple-quoted string", start);
+                                return lexer.error("E0001", "Unterminated raw triple-quoted string", start);
                             }
                         }
                         tokens.push(Token {
@@ -351,7 +585,7 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                                    lexer.next();
                                }
                            } else {
-                               return lexer.error("Unterminated raw string", start);
+                               return lexer.error("E0001", "Unterminated raw string", start);
                            }
                         }
                         tokens.push(Token {
@@ -361,108 +595,14 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                     }
                 } else {
                     // Identifier starting with r
-                    let mut ident = String::from("r");
-                    while let Some(&ch) = lexer.peek() {
-                        if !ch.is_ascii_alphanumeric() && ch != '_' {
-                            break;
-                        }
-                        ident.push(ch);
-                        lexer.next();
-                    }
-                    let kind = match ident.as_str() {
-                         "run" => TokenKind::Run,
-                         "return" => TokenKind::Return,
-                         "redirect" => TokenKind::Redirect,
-                         _ => TokenKind::Ident(ident),
-                    };
+                    let ident = lexer.take_ident(start);
+                    let kind = keyword_kind(ident).unwrap_or_else(|| TokenKind::Ident(crate::names::ascii_ident(ident)));
                     tokens.push(Token { kind, span: Span::new(start, lexer.pos) });
                 }
             }
-            _ if c.is_ascii_alphabetic() || c == '_' => {
-
-                let mut ident = String::new();
-                while let Some(&ch) = lexer.peek() {
-                    if !ch.is_ascii_alphanumeric() && ch != '_' {
-                        break;
-                    }
-                    ident.push(ch);
-                    lexer.next();
-                }
-                
-                let kind = match ident.as_str() {
-                    "func" => TokenKind::Func,
-                    "run" => TokenKind::Run,
-                    "print" => TokenKind::Print,
-                    "print_err" => TokenKind::PrintErr,
-                    "if" => TokenKind::If,
-                    "elif" => TokenKind::Elif,
-                    "else" => TokenKind::Else,
-                    "let" => TokenKind::Let,
-                    "case" => TokenKind::Case,
-                    "while" => TokenKind::While,
-                    "for" => TokenKind::For,
-                    "in" => TokenKind::In,
-                    "args" => TokenKind::Args,
-                    "with" => TokenKind::With,
-                    "env" => TokenKind::Env,
-                    "cd" => TokenKind::Cd,
-                    "cwd" => TokenKind::Cwd,
-                    "sh" => TokenKind::Sh,
-                    "break" => TokenKind::Break,
-                    "continue" => TokenKind::Continue,
-                    "return" => TokenKind::Return,
-                    "exit" => TokenKind::Exit,
-                    "capture" => TokenKind::Capture,
-                    "subshell" => TokenKind::Subshell,
-                    "group" => TokenKind::Group,
-                    "redirect" => TokenKind::Redirect,
-                    "stdout" => TokenKind::Stdout,
-                    "stderr" => TokenKind::Stderr,
-                    "stdin" => TokenKind::Stdin,
-                    "file" => TokenKind::File,
-                    "append" => TokenKind::Append,
-                    "spawn" => TokenKind::Spawn,
-                    "wait" => TokenKind::Wait,
-                    "try" => TokenKind::Try,
-                    "catch" => TokenKind::Catch,
-                    "export" => TokenKind::Export,
-                    "unset" => TokenKind::Unset,
-                    "exists" => TokenKind::Exists,
-                    "is_dir" => TokenKind::IsDir,
-                    "is_file" => TokenKind::IsFile,
-                    "is_symlink" => TokenKind::IsSymlink,
-                    "is_exec" => TokenKind::IsExec,
-                    "is_readable" => TokenKind::IsReadable,
-                    "is_writable" => TokenKind::IsWritable,
-                    "is_non_empty" => TokenKind::IsNonEmpty,
-                    "bool_str" => TokenKind::BoolStr,
-                    "len" => TokenKind::Len,
-                    "source" => TokenKind::Source,
-                    "arg" => TokenKind::Arg,
-                    "index" => TokenKind::Index,
-                    "join" => TokenKind::Join,
-                    "exec" => TokenKind::Exec,
-                    "status" => TokenKind::Status,
-                    "pid" => TokenKind::Pid,
-                    "count" => TokenKind::Count,
-                    "uid" => TokenKind::Uid,
-                    "ppid" => TokenKind::Ppid,
-                    "pwd" => TokenKind::Pwd,
-                    "self_pid" => TokenKind::SelfPid,
-                    "argv0" => TokenKind::Argv0,
-                    "argc" => TokenKind::Argc,
-                    "true" => TokenKind::True,
-                    "false" => TokenKind::False,
-                    "set" => TokenKind::Set,
-                    "pipe" => TokenKind::PipeKw,
-                    "log" => TokenKind::Log,
-                    "import" => TokenKind::Import,
-                    "input" => TokenKind::Input,
-                    "confirm" => TokenKind::Confirm,
-                    "each_line" => TokenKind::EachLine,
-                    "as" => TokenKind::As,
-                    _ => TokenKind::Ident(ident),
-                };
+            _ if c.is_ascii_alphabetic() || c == '_' || unicode_ident::is_xid_start(c) => {
+                let ident = lexer.take_ident(start);
+                let kind = keyword_kind(ident).unwrap_or_else(|| TokenKind::Ident(crate::names::ascii_ident(ident)));
                 tokens.push(Token {
                     kind,
                     span: Span::new(start, lexer.pos),
@@ -472,8 +612,26 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
                 lexer.next();
                 tokens.push(Token { kind: TokenKind::Semi, span: Span::new(start, lexer.pos) });
             }
+            '\'' => {
+                lexer.next();
+                return lexer.error_with_help(
+                    "E0103",
+                    "Single quotes are shell syntax; sh2 strings use double quotes",
+                    "write \"...\"; sh2 strings are never interpolated, so \"$HOME\" stays literal",
+                    start,
+                );
+            }
+            '`' => {
+                lexer.next();
+                return lexer.error_with_help(
+                    "E0103",
+                    "Backticks are shell syntax; sh2 has no backtick command substitution",
+                    "capture a command's output with `capture(run(\"cmd\", \"arg\"))`",
+                    start,
+                );
+            }
             _ => { 
-                return lexer.error(&format!("Unexpected character: {}", c), start);
+                return lexer.error("E0002", &format!("Unexpected character: {}", c), start);
             }
         }
     }
@@ -484,6 +642,7 @@ pub fn lex(sm: &SourceMap, file: &str) -> Result<Vec<Token>, Diagnostic> {
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenKind {
     Func,
+    Extern,
     Run,
     Print,
     PrintErr,
@@ -498,6 +657,9 @@ pub enum TokenKind {
     Dollar,
     Pipe,
     OrOr,
+    QuestionQuestion,
+    QuestionDot,
+    QuestionBracket,
     AndAnd,
     Amp,
     Bang,
@@ -567,7 +729,7 @@ pub enum TokenKind {
     Argc,
     True,
     False,
-    Number(u32),
+    Number(u64),
     Ident(String),
     String(String),
     LParen,
@@ -579,6 +741,7 @@ pub enum TokenKind {
     Comma,
     Dot,
     DotDot,
+    Ellipsis,
     Set,
     PipeKw,
     Log,
@@ -588,6 +751,9 @@ pub enum TokenKind {
     EachLine,
     As,
     Semi,
+    At,
+    /// A `/// text` line; the parser attaches it to the item that follows.
+    DocComment(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1,20 +1,41 @@
 pub mod ast;
+pub mod audit;
 pub mod builtins;
 pub mod codegen;
+pub mod cron;
+pub mod deprecation;
+pub mod diag_codes;
 pub mod diag_path;
+pub mod doc;
 pub mod error;
+pub mod fixes;
+pub mod hooks;
 pub mod lexer;
 pub mod loader;
 pub mod lower;
 pub mod ir;
 pub mod parser;
+pub mod pure;
 pub(crate) mod names;
 pub(crate) mod resolver;
 pub(crate) mod suggest;
+pub(crate) mod warn_walk;
 pub mod span;
 pub mod target;
 pub mod sudo;
+pub mod systemd;
 pub mod formatter;
+pub mod highlight;
+pub mod introspect;
 pub mod lang_spec;
+#[cfg(feature = "fs")]
 pub mod driver;
+pub mod playground;
+pub mod sandbox;
 pub mod semantics;
+pub mod stages;
+pub mod stdin_guard;
+pub mod taint;
+pub mod unchecked_status;
+#[cfg(feature = "fs")]
+pub mod verify;
@@ -1,10 +1,11 @@
-use crate::ast::{Function, Program};
+use crate::ast::{ExternFunc, Function, Program};
 use crate::lexer;
 use crate::parser;
-use crate::span::SourceMap;
+use crate::span::{SourceMap, Span};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use crate::span::Diagnostic;
 
@@ -15,10 +16,56 @@ struct Loader {
     source_maps: HashMap<String, SourceMap>,
     functions: HashMap<String, (Function, PathBuf)>,
     function_order: Vec<String>,
+    /// `extern func` declarations from every loaded file, first declaration wins.
+    externs: Vec<ExternFunc>,
     file_functions: HashMap<PathBuf, HashSet<String>>,
     /// Per-file function store for robust lazy clone on demand.
     /// Avoids relying on global name uniqueness when registering mangled functions.
     file_defined_funcs: HashMap<PathBuf, HashMap<String, Function>>,
+    timings: LoadTimings,
+    unused_imports: Vec<UnusedImport>,
+    /// Numbered `init` functions, imports before importers.
+    inits: Vec<String>,
+    /// Source text by path for `load_str`; `None` reads files from disk.
+    memory: Option<HashMap<PathBuf, String>>,
+}
+
+/// Time spent lexing and parsing, summed over the entry file and its imports.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadTimings {
+    pub lex: Duration,
+    pub parse: Duration,
+}
+
+/// An `import ... as alias` whose alias no qualified call in the file uses.
+#[derive(Debug, Clone)]
+pub struct UnusedImport {
+    /// Canonical path of the importing file (a key of `Program::source_maps`).
+    pub file: String,
+    pub alias: String,
+    pub span: Span,
+}
+
+/// Parsed files kept across loads, so a build of several entry files that import
+/// the same modules reads and parses each module once. Entries are never
+/// invalidated: keep a cache for one build, not across edits.
+pub struct ParseCache {
+    files: HashMap<PathBuf, (SourceMap, Program)>,
+    /// Off for one-off loads, which would only pay for copying into the cache.
+    enabled: bool,
+}
+
+impl Default for ParseCache {
+    fn default() -> Self {
+        ParseCache { files: HashMap::new(), enabled: true }
+    }
+}
+
+/// What the loader found besides the program itself.
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    pub timings: LoadTimings,
+    pub unused_imports: Vec<UnusedImport>,
 }
 
 impl Loader {
@@ -30,8 +77,29 @@ impl Loader {
             source_maps: HashMap::new(),
             functions: HashMap::new(),
             function_order: Vec::new(),
+            externs: Vec::new(),
             file_functions: HashMap::new(),
             file_defined_funcs: HashMap::new(),
+            timings: LoadTimings::default(),
+            unused_imports: Vec::new(),
+            inits: Vec::new(),
+            memory: None,
+        }
+    }
+
+    /// The real path of `path` on disk, or `path` itself for in-memory sources.
+    fn canonicalize(&self, path: &Path) -> std::io::Result<PathBuf> {
+        match &self.memory {
+            Some(files) if files.contains_key(path) => Ok(path.to_path_buf()),
+            Some(_) => Err(std::io::ErrorKind::NotFound.into()),
+            None => fs::canonicalize(path),
+        }
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<String> {
+        match &self.memory {
+            Some(files) => files.get(path).cloned().ok_or_else(|| std::io::ErrorKind::NotFound.into()),
+            None => fs::read_to_string(path),
         }
     }
 }
@@ -42,8 +110,8 @@ pub fn load_program_with_imports(entry_path: &Path) -> Result<Program, Diagnosti
 
 // ...
 
-fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Result<(), Diagnostic> {
-    let canonical_path = match fs::canonicalize(entry_path) {
+fn load_program_with_imports_impl(loader: &mut Loader, cache: &mut ParseCache, entry_path: &Path) -> Result<(), Diagnostic> {
+    let canonical_path = match loader.canonicalize(entry_path) {
         Ok(p) => p,
         Err(e) => {
             return Err(Diagnostic {
@@ -55,10 +123,12 @@ fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Res
                     }
                     m
                 },
+                code: Some("E0208"),
                 span: crate::span::Span::new(0, 0),
                 sm: None,
                 file: Some(entry_path.to_string_lossy().to_string()),
                 help: None,
+                additional: Vec::new(),
             });
         }
     };
@@ -85,37 +155,49 @@ fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Res
 
         return Err(Diagnostic {
             msg: format!("Import cycle detected: {}", cycle_msg),
+            code: Some("E0207"),
             span: crate::span::Span::new(0, 0),
             sm: None,
             file: Some(canonical_path.to_string_lossy().to_string()),
             help: None,
+            additional: Vec::new(),
         });
     }
 
     loader.visiting.insert(canonical_path.clone());
     loader.stack.push(canonical_path.clone());
 
-    let src = match fs::read_to_string(&canonical_path) {
-        Ok(s) => s,
-        Err(e) => {
-            return Err(Diagnostic {
-                msg: format!("Failed to read {}: {}", canonical_path.display(), e),
-                span: crate::span::Span::new(0, 0),
-                sm: None,
-                file: Some(canonical_path.to_string_lossy().to_string()),
-                help: None,
-            });
+    let file_str = canonical_path.to_string_lossy().to_string();
+    let (sm, mut program) = match cache.files.get(&canonical_path) {
+        Some((sm, program)) => (sm.clone(), program.clone()),
+        None => {
+            let parsed = parse_file(loader, &canonical_path, &file_str)?;
+            if cache.enabled {
+                cache.files.insert(canonical_path.clone(), (parsed.0.clone(), parsed.1.clone()));
+            }
+            parsed
         }
     };
-
-    let file_str = canonical_path.to_string_lossy().to_string();
-    let sm = SourceMap::new(src);
+    // Only the entry file may be written script-style.
+    if loader.stack.len() > 1
+        && let Some(stmt) = program.top_level.first()
+    {
+        return Err(Diagnostic {
+            msg: "Top-level statements are not allowed in an imported module; move them into init { ... } or a function."
+                .to_string(),
+            code: Some("E0101"),
+            span: stmt.span,
+            sm: Some(sm),
+            file: Some(file_str),
+            help: None,
+            additional: Vec::new(),
+        });
+    }
+    crate::lower::implicit_main(&mut program, &sm)?;
+    crate::hooks::weave(&mut program, &sm)?;
     // Invariant: source_maps is keyed by canonical-path string (file_str).
     // ImportIndex.sm retrieval below must use the same key.
-    loader.source_maps.insert(file_str.clone(), sm.clone());
-
-    let tokens = lexer::lex(&sm, &file_str)?;
-    let mut program = parser::parse(&tokens, &sm, &file_str)?;
+    loader.source_maps.insert(file_str.clone(), sm);
 
     let base_dir = canonical_path.parent().unwrap_or(Path::new("."));
     
@@ -128,15 +210,17 @@ fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Res
             import_path.set_extension("sh2");
         }
         
-        let import_canonical = match fs::canonicalize(&import_path) {
+        let import_canonical = match loader.canonicalize(&import_path) {
             Ok(p) => p,
             Err(e) => {
                 return Err(Diagnostic {
                     msg: format!("Failed to resolve path {}: {}", import_path.display(), e),
+                    code: Some("E0208"),
                     span: import.span,
                     sm: loader.source_maps.get(&file_str).cloned(),
                     file: Some(file_str.clone()),
                     help: None,
+                    additional: Vec::new(),
                 });
             }
         };
@@ -145,11 +229,12 @@ fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Res
             alias_map.insert(alias.clone(), import_canonical.clone());
         }
         
-        load_program_with_imports_impl(loader, &import_path)?;
+        load_program_with_imports_impl(loader, cache, &import_path)?;
     }
     
     // 2. Populate file_functions for this file (before rewrite, so cross-file validation works)
-    let func_names: HashSet<String> = program.functions.iter().map(|f| f.name.clone()).collect();
+    let func_names: HashSet<String> =
+        program.functions.iter().map(|f| f.name.clone()).filter(|name| !program.inits.contains(name)).collect();
     loader.file_functions.insert(canonical_path.clone(), func_names);
 
     // 3. Resolve Pass: Validate qualified calls and fill resolved_path/resolved_mangled
@@ -172,14 +257,35 @@ fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Res
         rewrite_qualified_calls(func, &mut all_needed, &mut needed_set);
     }
 
+    for import in &program.imports {
+        if let Some(alias) = &import.alias
+            && !all_needed.iter().any(|(used, _, _)| used == alias)
+        {
+            loader.unused_imports.push(UnusedImport {
+                file: file_str.clone(),
+                alias: alias.clone(),
+                span: import.span,
+            });
+        }
+    }
+
     // Populate file_defined_funcs AFTER rewrite so cloned functions have no QualifiedCall nodes.
     // D1 lazy registration clones from here, so clones must already be rewritten.
-    let func_map: HashMap<String, Function> = program.functions.iter()
-        .map(|f| (f.name.clone(), f.clone())).collect();
-    loader.file_defined_funcs.insert(canonical_path.clone(), func_map);
+    // The entry file is never an import target (that would be a cycle), so its
+    // functions are not copied.
+    if loader.stack.len() > 1 {
+        let func_map: HashMap<String, Function> = program.functions.iter()
+            .map(|f| (f.name.clone(), f.clone())).collect();
+        loader.file_defined_funcs.insert(canonical_path.clone(), func_map);
+    }
 
     // ... rest of loop ...
-    for func in program.functions {
+    for mut func in program.functions {
+        // Each module's `init` block gets its own function, numbered in run order.
+        if program.inits.contains(&func.name) {
+            func.name = format!("{}_{}", crate::names::INIT, loader.inits.len());
+            loader.inits.push(func.name.clone());
+        }
         // ... (keep panics for semantics) ...
         if matches!(
             func.name.as_str(),
@@ -190,14 +296,16 @@ fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Res
                     "Function name '{}' is reserved (prelude helper); choose a different name.",
                     func.name
                 ),
+                code: Some("E0206"),
                 span: func.span, // We have func.span here!
                 sm: loader.source_maps.get(&func.file).cloned(),
                 file: Some(func.file.clone()),
                 help: None,
+                additional: Vec::new(),
             });
         }
 
-        if let Some((_, defined_at)) = loader.functions.get(&func.name) {
+        if let Some((first, defined_at)) = loader.functions.get(&func.name) {
             // It could be a mangled function defined multiple times via alias aliasing?
             // Wait, if it's already defined, we don't need to panic if it's exactly the same exported function.
             // Actually, mangled functions are already deduped during rewrite if imported.
@@ -209,10 +317,20 @@ fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Res
                         func.name,
                         defined_at.display()
                     ),
+                    code: Some("E0205"),
                     span: func.span,
                     sm: loader.source_maps.get(&func.file).cloned(),
                     file: Some(func.file.clone()),
                     help: None,
+                    additional: vec![Diagnostic {
+                        msg: format!("'{}' was first defined here", func.name),
+                        code: Some("E0205"),
+                        span: first.span,
+                        sm: loader.source_maps.get(&first.file).cloned(),
+                        file: Some(first.file.clone()),
+                        help: None,
+                        additional: Vec::new(),
+                    }],
                 });
             }
         }
@@ -225,6 +343,28 @@ fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Res
         }
     }
 
+    // Externs are global: any module may declare the shell functions it calls,
+    // and two declarations of the same name must agree.
+    for ext in std::mem::take(&mut program.externs) {
+        if crate::lang_spec::BUILTINS.contains(&ext.name.as_str()) {
+            return Err(extern_error(loader, &ext, format!("extern func '{}' has the name of a builtin; choose a different name", ext.name)));
+        }
+        match loader.externs.iter().find(|e| e.name == ext.name) {
+            Some(prev) if prev.params.len() != ext.params.len() => {
+                let msg = format!(
+                    "extern func '{}' is declared with {} parameter(s) here and {} in {}",
+                    ext.name,
+                    ext.params.len(),
+                    prev.params.len(),
+                    prev.file
+                );
+                return Err(extern_error(loader, &ext, msg));
+            }
+            Some(_) => {}
+            None => loader.externs.push(ext),
+        }
+    }
+
     // 5. D1 Lazy: Register only the mangled functions that were actually referenced
     //    Uses file_defined_funcs for precise per-file lookup (avoids global name uniqueness assumption)
     for (alias, func_name, target_path) in all_needed {
@@ -247,20 +387,93 @@ fn load_program_with_imports_impl(loader: &mut Loader, entry_path: &Path) -> Res
     Ok(())
 }
 
+fn parse_file(loader: &mut Loader, path: &Path, file_str: &str) -> Result<(SourceMap, Program), Diagnostic> {
+    let src = loader.read(path).map_err(|e| Diagnostic {
+        msg: format!("Failed to read {}: {}", path.display(), e),
+        code: Some("E0208"),
+        span: crate::span::Span::new(0, 0),
+        sm: None,
+        file: Some(file_str.to_string()),
+        help: None,
+        additional: Vec::new(),
+    })?;
+    let sm = SourceMap::new(src);
+
+    // In-memory loads are what the wasm build uses, and it has no clock.
+    if loader.memory.is_some() {
+        let tokens = lexer::lex(&sm, file_str)?;
+        let program = parser::parse(&tokens, &sm, file_str)?;
+        return Ok((sm, program));
+    }
+    let started = Instant::now();
+    let tokens = lexer::lex(&sm, file_str)?;
+    let lexed = Instant::now();
+    let program = parser::parse(&tokens, &sm, file_str)?;
+    loader.timings.lex += lexed - started;
+    loader.timings.parse += lexed.elapsed();
+    Ok((sm, program))
+}
+
+fn extern_error(loader: &Loader, ext: &ExternFunc, msg: String) -> Diagnostic {
+    Diagnostic {
+        msg,
+        code: None,
+        span: ext.span,
+        sm: loader.source_maps.get(&ext.file).cloned(),
+        file: Some(ext.file.clone()),
+        help: None,
+        additional: Vec::new(),
+    }
+}
+
 pub fn load(entry_path: &Path) -> Result<Program, Diagnostic> {
+    load_timed(entry_path).map(|(program, _)| program)
+}
+
+/// Like `load`, but also reports how long lexing and parsing took.
+pub fn load_timed(entry_path: &Path) -> Result<(Program, LoadTimings), Diagnostic> {
+    load_with_report(entry_path).map(|(program, report)| (program, report.timings))
+}
+
+/// Like `load`, but also returns the timings and unused imports found on the way.
+pub fn load_with_report(entry_path: &Path) -> Result<(Program, LoadReport), Diagnostic> {
+    load_cached(entry_path, &mut ParseCache { files: HashMap::new(), enabled: false })
+}
+
+/// Like `load_with_report`, but reuses (and fills) `cache` for the files it reads.
+/// Lex and parse timings only count files that were not cached yet.
+pub fn load_cached(entry_path: &Path, cache: &mut ParseCache) -> Result<(Program, LoadReport), Diagnostic> {
+    finish(Loader::new(), cache, entry_path)
+}
+
+/// Loads a program from source text alone, named `file` in diagnostics.
+/// Nothing is read from disk, so any `import` fails to resolve.
+pub fn load_str(file: &str, src: &str) -> Result<Program, Diagnostic> {
     let mut loader = Loader::new();
-    load_program_with_imports_impl(&mut loader, entry_path)?;
+    loader.memory = Some(HashMap::from([(PathBuf::from(file), src.to_string())]));
+    let mut cache = ParseCache { files: HashMap::new(), enabled: false };
+    finish(loader, &mut cache, Path::new(file)).map(|(program, _)| program)
+}
+
+fn finish(mut loader: Loader, cache: &mut ParseCache, entry_path: &Path) -> Result<(Program, LoadReport), Diagnostic> {
+    load_program_with_imports_impl(&mut loader, cache, entry_path)?;
+
+    if let Some(ext) = loader.externs.iter().find(|e| loader.functions.contains_key(&e.name)) {
+        let (_, defined_at) = &loader.functions[&ext.name];
+        let msg = format!("extern func '{}' is also defined in {}", ext.name, defined_at.display());
+        return Err(extern_error(&loader, ext, msg));
+    }
 
     // Construct final program in deterministic order
     let mut functions = Vec::new();
-    for name in loader.function_order {
+    for name in std::mem::take(&mut loader.function_order) {
         let (func, _) = loader.functions.remove(&name).unwrap();
         functions.push(func);
     }
 
     // ... same span logic ...
     let span = crate::span::Span { start: 0, end: 0 };
-    let entry_file = fs::canonicalize(entry_path)
+    let entry_file = loader.canonicalize(entry_path)
         .map_err(|e| Diagnostic {
             msg: {
                 let mut m = format!("Failed to resolve path {}: {}", entry_path.display(), e);
@@ -270,22 +483,33 @@ pub fn load(entry_path: &Path) -> Result<Program, Diagnostic> {
                 }
                 m
             },
+            code: Some("E0208"),
             span: crate::span::Span::new(0, 0),
             sm: None,
             file: Some(entry_path.to_string_lossy().to_string()),
             help: None,
+            additional: Vec::new(),
         })?
         .to_string_lossy()
         .to_string();
 
-    Ok(Program {
+    let program = Program {
         imports: vec![],
         functions,
+        externs: loader.externs,
+        hooks: vec![],
+        inits: loader.inits,
+        top_level: vec![],
 
         span,
         source_maps: loader.source_maps,
         entry_file,
-    })
+    };
+    let report = LoadReport {
+        timings: loader.timings,
+        unused_imports: loader.unused_imports,
+    };
+    Ok((program, report))
 }
 
 
@@ -316,13 +540,16 @@ fn rewrite_stmt(
                 let entry = (ns, name, path);
                 if needed_set.insert(entry.clone()) { needed.push(entry); }
                 
-                stmt.node = StmtKind::Call { name: mangled, args };
+                stmt.node = StmtKind::Call { name: mangled, args, options: Vec::new() };
             } else { unreachable!() }
         }
 
-        StmtKind::Let { value, .. } | StmtKind::Set { value, .. } => {
+        StmtKind::Let { value, .. } | StmtKind::Set { value, .. } | StmtKind::LetValues { value, .. } => {
             rewrite_expr(value, needed, needed_set);
         }
+        StmtKind::ReturnValues(values) => {
+            for v in values.iter_mut() { rewrite_expr(v, needed, needed_set); }
+        }
         StmtKind::Run(call) => {
             for a in call.args.iter_mut() { rewrite_expr(a, needed, needed_set); }
             for o in call.options.iter_mut() { rewrite_expr(&mut o.value, needed, needed_set); }
@@ -371,19 +598,7 @@ fn rewrite_stmt(
             for s in try_body.iter_mut() { rewrite_stmt(s, needed, needed_set); }
             for s in catch_body.iter_mut() { rewrite_stmt(s, needed, needed_set); }
         }
-        StmtKind::Pipe(segments) => {
-            for seg in segments.iter_mut() {
-                match &mut seg.node {
-                    crate::ast::PipeSegment::Run(call) | crate::ast::PipeSegment::Sudo(call) => {
-                        for a in call.args.iter_mut() { rewrite_expr(a, needed, needed_set); }
-                        for o in call.options.iter_mut() { rewrite_expr(&mut o.value, needed, needed_set); }
-                    }
-                    crate::ast::PipeSegment::Block(body) | crate::ast::PipeSegment::EachLine(_, body) => {
-                        for s in body.iter_mut() { rewrite_stmt(s, needed, needed_set); }
-                    }
-                }
-            }
-        }
+        StmtKind::Pipe(segments) => rewrite_pipe_segments(segments, needed, needed_set),
         StmtKind::Exec(args) => {
             for a in args.iter_mut() { rewrite_expr(a, needed, needed_set); }
         }
@@ -391,7 +606,7 @@ fn rewrite_stmt(
             for (_, v) in bindings.iter_mut() { rewrite_expr(v, needed, needed_set); }
             for s in body.iter_mut() { rewrite_stmt(s, needed, needed_set); }
         }
-        StmtKind::WithCwd { path, body } => {
+        StmtKind::WithCwd { path, body } | StmtKind::WithDeadline { duration: path, body } | StmtKind::WithUmask { mask: path, body } => {
             rewrite_expr(path, needed, needed_set);
             for s in body.iter_mut() { rewrite_stmt(s, needed, needed_set); }
         }
@@ -414,10 +629,8 @@ fn rewrite_stmt(
                     }
                 }
             }
-            if let Some(tgt) = stdin {
-                if let crate::ast::RedirectInputTarget::File { path } = tgt {
-                    rewrite_expr(path, needed, needed_set);
-                }
+            if let Some(crate::ast::RedirectInputTarget::File { path } | crate::ast::RedirectInputTarget::Text { value: path }) = stdin {
+                rewrite_expr(path, needed, needed_set);
             }
             for s in body.iter_mut() { rewrite_stmt(s, needed, needed_set); }
         }
@@ -427,14 +640,18 @@ fn rewrite_stmt(
                 for s in arm.body.iter_mut() { rewrite_stmt(s, needed, needed_set); }
             }
         }
-        StmtKind::Call { args, .. } => {
+        StmtKind::Call { args, options, .. } => {
             for a in args.iter_mut() { rewrite_expr(a, needed, needed_set); }
+            for o in options.iter_mut() { rewrite_expr(&mut o.value, needed, needed_set); }
         }
         StmtKind::AndThen { left, right } | StmtKind::OrElse { left, right } => {
             for s in left.iter_mut() { rewrite_stmt(s, needed, needed_set); }
             for s in right.iter_mut() { rewrite_stmt(s, needed, needed_set); }
         }
-        StmtKind::Subshell { body } | StmtKind::Group { body } => {
+        StmtKind::Subshell { body }
+        | StmtKind::Group { body }
+        | StmtKind::Unchecked { body }
+        | StmtKind::WithLimits { body, .. } => {
             for s in body.iter_mut() { rewrite_stmt(s, needed, needed_set); }
         }
         StmtKind::Spawn { stmt: inner } => {
@@ -444,6 +661,27 @@ fn rewrite_stmt(
     }
 }
 
+fn rewrite_pipe_segments(
+    segments: &mut [crate::ast::Spanned<crate::ast::PipeSegment>],
+    needed: &mut Vec<(String, String, PathBuf)>,
+    needed_set: &mut HashSet<(String, String, PathBuf)>,
+) {
+    for seg in segments.iter_mut() {
+        match &mut seg.node {
+            crate::ast::PipeSegment::Run(call) | crate::ast::PipeSegment::Sudo(call) => {
+                for a in call.args.iter_mut() { rewrite_expr(a, needed, needed_set); }
+                for o in call.options.iter_mut() { rewrite_expr(&mut o.value, needed, needed_set); }
+                if let Some(crate::ast::RedirectOutputTarget::File { path, .. }) = call.stderr.as_deref_mut().map(|t| &mut t.node) {
+                    rewrite_expr(path, needed, needed_set);
+                }
+            }
+            crate::ast::PipeSegment::Block(body) | crate::ast::PipeSegment::EachLine(_, _, body) => {
+                for s in body.iter_mut() { rewrite_stmt(s, needed, needed_set); }
+            }
+        }
+    }
+}
+
 fn rewrite_expr(
     expr: &mut crate::ast::Expr,
     needed: &mut Vec<(String, String, PathBuf)>,
@@ -485,15 +723,21 @@ fn rewrite_expr(
                 for a in block.iter_mut() { rewrite_expr(a, needed, needed_set); }
             }
         }
-        ExprKind::Concat(l, r) | ExprKind::And(l, r) | ExprKind::Or(l, r) | ExprKind::Join { list: l, sep: r } | ExprKind::Index { list: l, index: r } => {
+        ExprKind::Pipeline(segments) => rewrite_pipe_segments(segments, needed, needed_set),
+        ExprKind::Concat(l, r) | ExprKind::Coalesce(l, r) | ExprKind::And(l, r) | ExprKind::Or(l, r) | ExprKind::Join { list: l, sep: r } | ExprKind::Index { list: l, index: r, .. } => {
             rewrite_expr(l, needed, needed_set);
             rewrite_expr(r, needed, needed_set);
         }
+        ExprKind::IfElse { cond, then_expr, else_expr } => {
+            rewrite_expr(cond, needed, needed_set);
+            rewrite_expr(then_expr, needed, needed_set);
+            rewrite_expr(else_expr, needed, needed_set);
+        }
         ExprKind::Arith { left, right, .. } | ExprKind::Compare { left, right, .. } => {
             rewrite_expr(left, needed, needed_set);
             rewrite_expr(right, needed, needed_set);
         }
-        ExprKind::Not(e) | ExprKind::Exists(e) | ExprKind::IsDir(e) | ExprKind::IsFile(e) | ExprKind::IsSymlink(e) | ExprKind::IsExec(e) | ExprKind::IsReadable(e) | ExprKind::IsWritable(e) | ExprKind::IsNonEmpty(e) | ExprKind::BoolStr(e) | ExprKind::Len(e) | ExprKind::Count(e) | ExprKind::Arg(e) | ExprKind::Env(e) | ExprKind::Input(e) | ExprKind::Field { base: e, .. } => {
+        ExprKind::Not(e) | ExprKind::Spread(e) | ExprKind::Exists(e) | ExprKind::IsDir(e) | ExprKind::IsFile(e) | ExprKind::IsSymlink(e) | ExprKind::IsExec(e) | ExprKind::IsReadable(e) | ExprKind::IsWritable(e) | ExprKind::IsNonEmpty(e) | ExprKind::BoolStr(e) | ExprKind::Len(e) | ExprKind::Count(e) | ExprKind::Arg(e) | ExprKind::Env(e) | ExprKind::Input(e) | ExprKind::Field { base: e, .. } => {
             rewrite_expr(e, needed, needed_set);
         }
         ExprKind::MapLiteral(entries) => {
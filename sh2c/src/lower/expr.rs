@@ -3,7 +3,7 @@ use crate::builtins;
 use crate::ir;
 use crate::span::{Span, SourceMap};
 use crate::error::CompileError;
-use super::{LoweringContext, LowerOptions, resolve_span};
+use super::{Callee, LoweringContext, LowerOptions, resolve_span};
 use super::sudo::{lower_run_call_args, lower_sudo_command};
 
 fn validate_arg_index_expr(
@@ -50,6 +50,9 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
         ast::ExprKind::Var(s) => {
             if ctx.is_bool_var(&s) {
                 Ok(ir::Val::BoolVar(s))
+            } else if ctx.is_func_name(&s) {
+                // A function reference is the function's name.
+                Ok(ir::Val::Literal(s))
             } else {
                 Ok(ir::Val::Var(s))
             }
@@ -85,6 +88,52 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                 right: Box::new(lower_expr(*right, out, ctx, sm, file)?),
             })
         }
+        ast::ExprKind::Compare { left, op, right }
+            if op.is_ordering() && matches!(&left.node, ast::ExprKind::Compare { op, .. } if op.is_ordering()) =>
+        {
+            // A chain `a < b <= c` nests to the left; unwind it into its operands.
+            let mut operands = vec![*right];
+            let mut ops = vec![op];
+            let mut head = *left;
+            loop {
+                match head.node {
+                    ast::ExprKind::Compare { left, op, right } if op.is_ordering() => {
+                        operands.push(*right);
+                        ops.push(op);
+                        head = *left;
+                    }
+                    node => {
+                        operands.push(ast::Expr { node, span: head.span });
+                        break;
+                    }
+                }
+            }
+            operands.reverse();
+            ops.reverse();
+            // Inner operands are compared twice; evaluate each of them once.
+            let last = operands.len() - 1;
+            let mut vals = Vec::new();
+            for (i, operand) in operands.into_iter().enumerate() {
+                let val = lower_expr(operand, out, ctx, sm, file)?;
+                if i == 0 || i == last || matches!(val, ir::Val::Var(_) | ir::Val::Literal(_) | ir::Val::Number(_)) {
+                    vals.push(val);
+                } else {
+                    ctx.tmp_counter += 1;
+                    let tmp = format!("__sh2_tmp_{}", ctx.tmp_counter);
+                    out.push(ir::Cmd::Assign(tmp.clone(), val, None));
+                    vals.push(ir::Val::Var(tmp));
+                }
+            }
+            let mut chain: Option<ir::Val> = None;
+            for (op, pair) in ops.into_iter().zip(vals.windows(2)) {
+                let cmp = ir::Val::Compare { left: Box::new(pair[0].clone()), op: compare_op(op), right: Box::new(pair[1].clone()) };
+                chain = Some(match chain {
+                    Some(prev) => ir::Val::And(Box::new(prev), Box::new(cmp)),
+                    None => cmp,
+                });
+            }
+            Ok(chain.expect("a chain has at least two comparisons"))
+        }
         ast::ExprKind::Compare { left, op, right } => {
             // Check for boolean literal comparisons (eq/neq with true/false_
             let get_bool = |e: &ast::Expr| if let ast::ExprKind::Bool(b) = e.node { Some(b) } else { None };
@@ -118,17 +167,9 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                     (false, false) => Ok(pred_val),    // pred != false
                 }
             } else {
-                let op = match op {
-                    ast::CompareOp::Eq => ir::CompareOp::Eq,
-                    ast::CompareOp::NotEq => ir::CompareOp::NotEq,
-                    ast::CompareOp::Lt => ir::CompareOp::Lt,
-                    ast::CompareOp::Le => ir::CompareOp::Le,
-                    ast::CompareOp::Gt => ir::CompareOp::Gt,
-                    ast::CompareOp::Ge => ir::CompareOp::Ge,
-                };
                 Ok(ir::Val::Compare {
                     left: Box::new(lower_expr(*left, out, ctx, sm, file)?),
-                    op,
+                    op: compare_op(op),
                     right: Box::new(lower_expr(*right, out, ctx, sm, file)?),
                 })
             }
@@ -142,6 +183,12 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
             Box::new(lower_expr(*right, out, ctx, sm, file)?),
         )),
         ast::ExprKind::Not(expr) => Ok(ir::Val::Not(Box::new(lower_expr(*expr, out, ctx, sm, file)?))),
+        ast::ExprKind::Spread(_) => Err(CompileError::new(sm.format_diagnostic(
+            file,
+            opts.diag_base_dir.as_deref(),
+            "`...` can only be used to spread a list into call arguments or list items",
+            e.span,
+        ))),
         ast::ExprKind::Exists(path) => Ok(ir::Val::Exists(Box::new(lower_expr(*path, out, ctx, sm, file)?))),
         ast::ExprKind::IsDir(path) => Ok(ir::Val::IsDir(Box::new(lower_expr(*path, out, ctx, sm, file)?))),
         ast::ExprKind::IsFile(path) => Ok(ir::Val::IsFile(Box::new(lower_expr(*path, out, ctx, sm, file)?))),
@@ -169,10 +216,10 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
             
             // Optimize: if literal number >= 1, use Val::Arg(n) for direct $n expansion
             if let ir::Val::Number(n) = &index_val {
-                if *n >= 1 {
-                    Ok(ir::Val::Arg(*n))
+                if let Ok(n) = u32::try_from(*n) && n >= 1 {
+                    Ok(ir::Val::Arg(n))
                 } else {
-                    // Invalid literal (0 or would-be-negative): use dynamic path which returns empty
+                    // Invalid literal (0, too large, or would-be-negative): use dynamic path which returns empty
                     Ok(ir::Val::ArgDynamic(Box::new(index_val)))
                 }
             } else {
@@ -180,9 +227,10 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                 Ok(ir::Val::ArgDynamic(Box::new(index_val)))
             }
         }
-        ast::ExprKind::Index { list, index } => Ok(ir::Val::Index {
+        ast::ExprKind::Index { list, index, optional } => Ok(ir::Val::Index {
             list: Box::new(lower_expr(*list, out, ctx, sm, file)?),
             index: Box::new(lower_expr(*index, out, ctx, sm, file)?),
+            optional,
         }),
         ast::ExprKind::Field { base, name } => {
             let b = lower_expr(*base, out, ctx, sm, file)?;
@@ -208,6 +256,37 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
             list: Box::new(lower_expr(*list, out, ctx, sm, file)?),
             sep: Box::new(lower_expr(*sep, out, ctx, sm, file)?),
         }),
+        ast::ExprKind::Coalesce(l, r) => {
+            let left = lower_expr(*l, out, ctx, sm, file)?;
+            let right = lower_expr(*r, out, ctx, sm, file)?;
+            // Literal operands decide the result at compile time: a non-empty
+            // literal or a number on the left always wins, and an empty right
+            // side changes nothing.
+            Ok(match (left, right) {
+                (ir::Val::Literal(s), right) if s.is_empty() => right,
+                (left @ (ir::Val::Literal(_) | ir::Val::Number(_)), _) => left,
+                (left, ir::Val::Literal(s)) if s.is_empty() => left,
+                (left, right) => ir::Val::Call { name: "coalesce".to_string(), args: vec![left, right] },
+            })
+        }
+        ast::ExprKind::IfElse { cond, then_expr, else_expr } => {
+            let cond = lower_expr(*cond, out, ctx, sm, file)?;
+            let then_val = lower_expr(*then_expr, out, ctx, sm, file)?;
+            let else_val = lower_expr(*else_expr, out, ctx, sm, file)?;
+            let is_list = |v: &ir::Val| {
+                matches!(v, ir::Val::List(_) | ir::Val::Split { .. } | ir::Val::Lines(_) | ir::Val::Args | ir::Val::MapLiteral(_))
+                    || matches!(v, ir::Val::Var(n) if ctx.is_list_var(n))
+            };
+            if is_list(&then_val) || is_list(&else_val) {
+                return Err(CompileError::new(sm.format_diagnostic(
+                    file,
+                    opts.diag_base_dir.as_deref(),
+                    "if expression branches must be strings, numbers or booleans; use an if statement to choose between lists",
+                    e.span,
+                )));
+            }
+            Ok(ir::Val::IfElse { cond: Box::new(cond), then_val: Box::new(then_val), else_val: Box::new(else_val) })
+        }
         ast::ExprKind::Count(inner) => Ok(ir::Val::Count(Box::new(lower_expr(*inner, out, ctx, sm, file)?))),
         ast::ExprKind::Bool(b) => Ok(ir::Val::Bool(b)),
         ast::ExprKind::Number(n) => Ok(ir::Val::Number(n)),
@@ -226,8 +305,9 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
             }
             let lowered_args = args
                 .into_iter()
-                .map(|a| lower_expr(a, out, ctx, sm, file))
-                .collect::<Result<Vec<_>, _>>()?;
+                .map(|a| lower_words(a, out, ctx, sm, file))
+                .collect::<Result<Vec<_>, _>>()?
+                .concat();
             Ok(ir::Val::Command(lowered_args))
         }
         ast::ExprKind::CommandPipe(segments) => {
@@ -235,17 +315,24 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                 .into_iter()
                 .map(|seg| {
                     seg.into_iter()
-                        .map(|a| lower_expr(a, out, ctx, sm, file))
+                        .map(|a| lower_words(a, out, ctx, sm, file))
                         .collect::<Result<Vec<_>, _>>()
+                        .map(|words| words.concat())
                 })
                 .collect::<Result<Vec<_>, _>>()?;
             Ok(ir::Val::CommandPipe(lowered_segments))
         }
+        ast::ExprKind::Pipeline(_) => Err(CompileError::new(sm.format_diagnostic(
+            file,
+            opts.diag_base_dir.as_deref(),
+            "a run(...) | ... pipeline can only be bound directly with let (e.g. let out = run(\"a\") | run(\"b\"))",
+            e.span,
+        ))),
         ast::ExprKind::List(exprs) => {
-            let lowered_exprs = exprs
-                .into_iter()
-                .map(|e| lower_expr(e, out, ctx, sm, file))
-                .collect::<Result<Vec<_>, _>>()?;
+            let mut lowered_exprs = Vec::new();
+            for e in exprs {
+                lowered_exprs.extend(lower_words(e, out, ctx, sm, file)?);
+            }
             Ok(ir::Val::List(lowered_exprs))
         }
         ast::ExprKind::Args => Ok(ir::Val::Args),
@@ -315,8 +402,9 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
 
             let lowered_args = run_call.args
                 .into_iter()
-                .map(|a| lower_expr(a, out, ctx, sm, file))
-                .collect::<Result<Vec<_>, _>>()?;
+                .map(|a| lower_words(a, out, ctx, sm, file))
+                .collect::<Result<Vec<_>, _>>()?
+                .concat();
             Ok(ir::Val::Command(lowered_args))
         }
 
@@ -462,6 +550,55 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                 }
                 let arg = lower_expr(args.into_iter().next().unwrap(), out, ctx, sm, file)?;
                 Ok(ir::Val::Which(Box::new(arg)))
+            } else if name == "have" {
+                if args.len() != 1 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "have() requires exactly 1 argument (cmd)",
+                        e.span,
+                    )));
+                }
+                let arg = lower_expr(args.into_iter().next().unwrap(), out, ctx, sm, file)?;
+                Ok(ir::Val::Have(Box::new(arg)))
+            } else if name == "service_running" {
+                if args.len() != 1 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "service_running() requires exactly 1 argument (pidfile)",
+                        e.span,
+                    )));
+                }
+                let arg = lower_expr(args.into_iter().next().unwrap(), out, ctx, sm, file)?;
+                Ok(ir::Val::ServiceRunning(Box::new(arg)))
+            } else if name == "input_list" {
+                if args.len() != 1 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "input_list() requires exactly 1 argument (prompt)",
+                        e.span,
+                    )));
+                }
+                let prompt = lower_expr(args.into_iter().next().unwrap(), out, ctx, sm, file)?;
+                Ok(ir::Val::InputList(Box::new(prompt)))
+            } else if name == "wait_for_port" {
+                let (host, port, timeout, interval) =
+                    lower_wait_for_port_args(args, options, e.span, out, ctx, sm, file)?;
+                Ok(ir::Val::WaitForPort {
+                    host: Box::new(host),
+                    port: Box::new(port),
+                    timeout: Box::new(timeout),
+                    interval: Box::new(interval),
+                })
+            } else if name == "service_start" || name == "service_stop" {
+                Err(CompileError::new(sm.format_diagnostic(
+                    file,
+                    opts.diag_base_dir.as_deref(),
+                    &format!("{}() is a statement and cannot be used in an expression", name),
+                    e.span,
+                )))
             } else if name == "try_run" {
                 return Err(CompileError::new(sm.format_diagnostic(
                     file,
@@ -469,6 +606,13 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                     "try_run() must be bound via let (e.g., let r = try_run(...))",
                     e.span,
                 )));
+            } else if name == "split_once" {
+                Err(CompileError::new(sm.format_diagnostic(
+                    file,
+                    opts.diag_base_dir.as_deref(),
+                    "split_once() yields two values and must be bound via let (e.g., let (key, value) = split_once(line, \":\"))",
+                    e.span,
+                )))
             } else if name == "require" {
                 return Err(CompileError::new(sm.format_diagnostic(
                     file,
@@ -486,7 +630,63 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                     )));
                 }
                 let arg = lower_expr(args.into_iter().next().unwrap(), out, ctx, sm, file)?;
-                Ok(ir::Val::ReadFile(Box::new(arg)))
+                let mut default = None;
+                let mut allow_missing = None;
+                for opt in options {
+                    match opt.name.as_str() {
+                        "default" => {
+                            if default.is_some() {
+                                return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "Duplicate argument 'default'", opt.span)));
+                            }
+                            default = Some(lower_expr(opt.value, out, ctx, sm, file)?);
+                        }
+                        "allow_missing" => {
+                            if allow_missing.is_some() {
+                                return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "Duplicate argument 'allow_missing'", opt.span)));
+                            }
+                            match opt.value.node {
+                                ast::ExprKind::Bool(b) => allow_missing = Some((b, opt.span)),
+                                _ => {
+                                    return Err(CompileError::new(sm.format_diagnostic(
+                                        file,
+                                        opts.diag_base_dir.as_deref(),
+                                        "allow_missing must be true/false",
+                                        opt.value.span,
+                                    )));
+                                }
+                            }
+                        }
+                        _ => {
+                            return Err(CompileError::new(sm.format_diagnostic(
+                                file,
+                                opts.diag_base_dir.as_deref(),
+                                &format!("Unknown option {:?} for read_file(); supported options are default and allow_missing", opt.name),
+                                opt.span,
+                            )));
+                        }
+                    }
+                }
+                match (default, allow_missing) {
+                    (Some(_), Some((false, span))) => Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "read_file(): default= implies allow_missing=true; remove allow_missing=false",
+                        span,
+                    ))),
+                    (Some(d), _) => Ok(ir::Val::ReadFileOr { path: Box::new(arg), default: Box::new(d) }),
+                    (None, Some((true, _))) => Ok(ir::Val::ReadFileOr {
+                        path: Box::new(arg),
+                        default: Box::new(ir::Val::Literal(String::new())),
+                    }),
+                    (None, _) => Ok(ir::Val::ReadFile(Box::new(arg))),
+                }
+            } else if matches!(name.as_str(), "mkdirs" | "copy" | "move" | "remove") && !ctx.user_funcs.contains(&name) {
+                Err(CompileError::new(sm.format_diagnostic(
+                    file,
+                    opts.diag_base_dir.as_deref(),
+                    &format!("{}() is a statement, not an expression", name),
+                    e.span,
+                )))
             } else if name == "write_file" {
                 return Err(CompileError::new(sm.format_diagnostic(
                     file,
@@ -501,7 +701,7 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                     "append_file() is a statement, not an expression",
                     e.span,
                 )));
-            } else if matches!(name.as_str(), "log_info" | "log_warn" | "log_error") {
+            } else if matches!(name.as_str(), "log_info" | "log_warn" | "log_error" | "replace_in_file" | "append_line_if_missing" | "breakpoint" | "dump_vars") {
                 return Err(CompileError::new(sm.format_diagnostic(
                     file,
                     opts.diag_base_dir.as_deref(),
@@ -513,6 +713,16 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                     return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "home() takes no arguments", e.span)));
                 }
                 Ok(ir::Val::Home)
+            } else if name == "last_command" || name == "last_stderr" {
+                if !args.is_empty() {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        format!("{}() takes no arguments", name).as_str(),
+                        e.span,
+                    )));
+                }
+                Ok(if name == "last_command" { ir::Val::LastCommand } else { ir::Val::LastStderr })
             } else if name == "path_join" {
                 if args.is_empty() {
                     return Err(CompileError::new(sm.format_diagnostic(
@@ -538,6 +748,56 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                 }
                 let arg = lower_expr(args.into_iter().next().unwrap(), out, ctx, sm, file)?;
                 Ok(ir::Val::Lines(Box::new(arg)))
+            } else if name == "bytes_len" {
+                if args.len() != 1 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "bytes_len() requires exactly 1 argument",
+                        e.span,
+                    )));
+                }
+                let arg = lower_expr(args.into_iter().next().unwrap(), out, ctx, sm, file)?;
+                Ok(ir::Val::BytesLen(Box::new(arg)))
+            } else if name == "lines0" || name == "split0" {
+                if args.len() != 1 || !options.is_empty() {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        &format!("{}() requires exactly 1 argument: run(...) or read_file(path)", name),
+                        e.span,
+                    )));
+                }
+                // Shell variables cannot hold NUL bytes, so the records must be read straight
+                // from the producing command rather than from an already-captured string.
+                let src = args.into_iter().next().unwrap();
+                let argv = match &src.node {
+                    ast::ExprKind::Run(run_call) => {
+                        let (argv, allow_fail) = lower_run_call_args(run_call, out, ctx, sm, file, opts)?;
+                        if allow_fail {
+                            return Err(CompileError::new(sm.format_diagnostic(
+                                file,
+                                opts.diag_base_dir.as_deref(),
+                                &format!("allow_fail=true is not supported inside {}()", name),
+                                src.span,
+                            )));
+                        }
+                        argv
+                    }
+                    ast::ExprKind::Call { name: inner, args: inner_args, .. } if inner == "read_file" && inner_args.len() == 1 => {
+                        let path = lower_expr(inner_args[0].clone(), out, ctx, sm, file)?;
+                        vec![ir::Val::Literal("cat".to_string()), ir::Val::Literal("--".to_string()), path]
+                    }
+                    _ => {
+                        return Err(CompileError::new(sm.format_diagnostic(
+                            file,
+                            opts.diag_base_dir.as_deref(),
+                            &format!("{}() reads NUL-delimited data directly from run(...) or read_file(path); shell strings cannot contain NUL bytes", name),
+                            src.span,
+                        )));
+                    }
+                };
+                Ok(ir::Val::Lines0(argv))
             } else if name == "split" {
                 if args.len() != 2 {
                     return Err(CompileError::new(sm.format_diagnostic(
@@ -554,6 +814,94 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                     s: Box::new(s),
                     delim: Box::new(delim),
                 })
+            } else if name == "awk" {
+                if args.len() != 2 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "awk() requires exactly 2 arguments (program, input)",
+                        e.span,
+                    )));
+                }
+                let mut iter = args.into_iter();
+                let prog = iter.next().unwrap();
+                let ast::ExprKind::Literal(program) = prog.node else {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "awk() program must be a plain string literal; pass runtime values through the input",
+                        prog.span,
+                    )));
+                };
+                if let Err(msg) = check_awk_program(&program) {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        &format!("awk() program {}", msg),
+                        prog.span,
+                    )));
+                }
+                let input = lower_expr(iter.next().unwrap(), out, ctx, sm, file)?;
+                Ok(ir::Val::Awk { program, input: Box::new(input) })
+            } else if name == "render" {
+                if args.len() != 2 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "render() requires exactly 2 arguments (template, map)",
+                        e.span,
+                    )));
+                }
+                let mut escape = None;
+                for opt in options {
+                    match opt.name.as_str() {
+                        "escape" => {
+                            if escape.is_some() {
+                                return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "Duplicate argument 'escape'", opt.span)));
+                            }
+                            escape = Some(match &opt.value.node {
+                                ast::ExprKind::Literal(s) if s == "none" => ir::RenderEscape::None,
+                                ast::ExprKind::Literal(s) if s == "html" => ir::RenderEscape::Html,
+                                ast::ExprKind::Literal(s) if s == "shell" => ir::RenderEscape::Shell,
+                                _ => {
+                                    return Err(CompileError::new(sm.format_diagnostic(
+                                        file,
+                                        opts.diag_base_dir.as_deref(),
+                                        "render() escape must be literal \"none\", \"html\", or \"shell\"",
+                                        opt.value.span,
+                                    )));
+                                }
+                            });
+                        }
+                        _ => {
+                            return Err(CompileError::new(sm.format_diagnostic(
+                                file,
+                                opts.diag_base_dir.as_deref(),
+                                format!("Unknown argument '{}'. Supported: escape", opt.name).as_str(),
+                                opt.span,
+                            )));
+                        }
+                    }
+                }
+                let mut iter = args.into_iter();
+                let template = lower_expr(iter.next().unwrap(), out, ctx, sm, file)?;
+                let map_arg = iter.next().unwrap();
+                let map = match map_arg.node {
+                    ast::ExprKind::Var(m) => m,
+                    _ => {
+                        return Err(CompileError::new(sm.format_diagnostic(
+                            file,
+                            opts.diag_base_dir.as_deref(),
+                            "render() map must be a map variable (e.g., let vars = { \"name\": \"x\" })",
+                            map_arg.span,
+                        )));
+                    }
+                };
+                Ok(ir::Val::Render {
+                    template: Box::new(template),
+                    map,
+                    escape: escape.unwrap_or(ir::RenderEscape::None),
+                })
             } else if name == "glob" {
                 if args.len() != 1 {
                     return Err(CompileError::new(sm.format_diagnostic(
@@ -565,6 +913,106 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                 }
                 let arg = lower_expr(args.into_iter().next().unwrap(), out, ctx, sm, file)?;
                 Ok(ir::Val::Glob(Box::new(arg)))
+            } else if name == "raw_arg" {
+                if args.len() != 1 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "raw_arg() requires exactly 1 argument (value)",
+                        e.span,
+                    )));
+                }
+                // Unquoted expansion is the whole point of raw_arg(), so it must be
+                // acknowledged at every call site rather than enabled globally.
+                let mut acknowledged = false;
+                for opt in options {
+                    if opt.name != "allow_split" {
+                        return Err(CompileError::new(sm.format_diagnostic(
+                            file,
+                            opts.diag_base_dir.as_deref(),
+                            &format!("Unknown option {:?} for raw_arg(); only allow_split=true is supported", opt.name),
+                            opt.span,
+                        )));
+                    }
+                    match opt.value.node {
+                        ast::ExprKind::Bool(b) => acknowledged = b,
+                        _ => {
+                            return Err(CompileError::new(sm.format_diagnostic(
+                                file,
+                                opts.diag_base_dir.as_deref(),
+                                "allow_split must be true/false",
+                                opt.value.span,
+                            )));
+                        }
+                    }
+                }
+                if !acknowledged {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "raw_arg() emits its value unquoted, so the shell word-splits and globs it; acknowledge this with raw_arg(value, allow_split=true)",
+                        e.span,
+                    )));
+                }
+                let arg = lower_expr(args.into_iter().next().unwrap(), out, ctx, sm, file)?;
+                Ok(ir::Val::RawArg(Box::new(arg)))
+            } else if name == "find" && !ctx.user_funcs.contains(&name) {
+                if args.len() > 1 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "find() takes at most 1 positional argument (dir); use name=, type= and max_depth= for filters",
+                        e.span,
+                    )));
+                }
+                let dir = match args.into_iter().next() {
+                    Some(d) => lower_expr(d, out, ctx, sm, file)?,
+                    None => ir::Val::Literal(".".to_string()),
+                };
+                let mut name_arg = None;
+                let mut type_filter = None;
+                let mut maxdepth = None;
+                for opt in options {
+                    let slot = match opt.name.as_str() {
+                        "name" => &mut name_arg,
+                        "type" => &mut type_filter,
+                        "max_depth" => &mut maxdepth,
+                        _ => {
+                            return Err(CompileError::new(sm.format_diagnostic(
+                                file,
+                                opts.diag_base_dir.as_deref(),
+                                &format!("Unknown argument '{}'. Supported: name, type, max_depth", opt.name),
+                                opt.span,
+                            )));
+                        }
+                    };
+                    if slot.is_some() {
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &format!("Duplicate argument '{}'", opt.name), opt.span)));
+                    }
+                    // Same literal-only rules as find0(): type is "f" or "d", depth is a non-negative integer
+                    let valid = match (opt.name.as_str(), &opt.value.node) {
+                        ("type", ast::ExprKind::Literal(s)) => s == "f" || s == "d",
+                        ("type", _) => false,
+                        ("max_depth", ast::ExprKind::Number(_)) => true,
+                        ("max_depth", _) => false,
+                        _ => true,
+                    };
+                    if !valid {
+                        let msg = if opt.name == "type" {
+                            "find() type must be literal \"f\" (files) or \"d\" (directories)"
+                        } else {
+                            "find() max_depth must be a non-negative integer literal"
+                        };
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), msg, opt.value.span)));
+                    }
+                    *slot = Some(Box::new(lower_expr(opt.value, out, ctx, sm, file)?));
+                }
+                Ok(ir::Val::Find {
+                    dir: Box::new(dir),
+                    name: name_arg,
+                    type_filter,
+                    maxdepth,
+                })
             } else if name == "find_files" {
                 // Ensure no positional arguments
                 if !args.is_empty() {
@@ -739,14 +1187,15 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                     allow_fail,
                     loc,
                 })
-            } else if name == "wait_all" {
-                // wait_all(pids) or wait_all(pids, allow_fail=true)
-                // Lowers to loop IR that waits for all PIDs and returns first non-zero exit code
+            } else if name == "wait_all" || name == "wait_any" {
+                // wait_all(pids) / wait_any(pids), optionally with allow_fail=true.
+                // wait_all waits for every PID and returns the first non-zero exit code in list order;
+                // wait_any returns the exit code of the first job to finish and kills the rest.
                 if args.len() != 1 {
                     return Err(CompileError::new(sm.format_diagnostic(
                         file,
                         opts.diag_base_dir.as_deref(),
-                        "wait_all() requires exactly 1 positional argument (a list of PIDs)",
+                        &format!("{}() requires exactly 1 positional argument (a list of PIDs)", name),
                         e.span,
                     )));
                 }
@@ -789,6 +1238,14 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                 
                 // Validate it's a list - target-aware check
                 match &pids_expr.node {
+                    ast::ExprKind::List(items) if items.is_empty() && name == "wait_any" => {
+                        return Err(CompileError::new(sm.format_diagnostic(
+                            file,
+                            opts.diag_base_dir.as_deref(),
+                            "wait_any() needs at least one PID",
+                            pids_expr.span,
+                        )));
+                    }
                     ast::ExprKind::List(_) => { /* ok for all targets */ }
                     ast::ExprKind::Var(_) | ast::ExprKind::Call { .. } => {
                         // Only allow on Bash target - POSIX requires inline list literals
@@ -796,7 +1253,7 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                             return Err(CompileError::new(sm.format_diagnostic(
                                 file,
                                 opts.diag_base_dir.as_deref(),
-                                "wait_all() on --target posix requires an inline list literal, e.g. wait_all([p1, p2])",
+                                &format!("{0}() on --target posix requires an inline list literal, e.g. {0}([p1, p2])", name),
                                 pids_expr.span,
                             )));
                         }
@@ -806,18 +1263,19 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                         return Err(CompileError::new(sm.format_diagnostic(
                             file,
                             opts.diag_base_dir.as_deref(),
-                            "wait_all() requires a list of PIDs",
+                            &format!("{}() requires a list of PIDs", name),
                             pids_expr.span,
                         )));
                     }
                 }
                 
-                // Return a special WaitAll value that codegen will handle
-                Ok(ir::Val::WaitAll {
-                    pids: Box::new(pids_val),
-                    allow_fail,
-                    loc,
-                })
+                // Return a special WaitAll/WaitAny value that codegen will handle
+                let pids = Box::new(pids_val);
+                if name == "wait_any" {
+                    Ok(ir::Val::WaitAny { pids, allow_fail, loc })
+                } else {
+                    Ok(ir::Val::WaitAll { pids, allow_fail, loc })
+                }
             } else if name == "save_envfile" {
                 return Err(CompileError::new(sm.format_diagnostic(
                     file,
@@ -841,8 +1299,22 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                         e.span,
                     )));
                 }
+                let mut name = name;
                 if !ctx.user_funcs.contains(&name) && !builtins::is_prelude_helper(&name) {
-                    return Err(CompileError::new(sm.format_diagnostic(
+                    match ctx.call_through(&name, args.len(), e.span, sm, file)? {
+                        Some(Callee::Func(target)) => name = target,
+                        Some(Callee::Var) => {
+                            let mut words = vec![ir::Val::CalleeVar(name)];
+                            for a in args {
+                                words.extend(lower_words(a, out, ctx, sm, file)?);
+                            }
+                            return Ok(ir::Val::Command(words));
+                        }
+                        None => {}
+                    }
+                }
+                if !ctx.user_funcs.contains(&name) && !builtins::is_prelude_helper(&name) {
+                    let mut msg = sm.format_diagnostic(
                         file,
                         opts.diag_base_dir.as_deref(),
                         &format!(
@@ -851,12 +1323,33 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                             name, name, name
                         ),
                         e.span,
-                    )));
+                    );
+                    let user_funcs = ctx.user_funcs.iter().map(String::as_str);
+                    if let Some(s) = builtins::suggest_function(&name, user_funcs) {
+                        msg.push_str(&format!("\nhelp: did you mean `{}`?", s));
+                    }
+                    return Err(CompileError::new(msg));
+                }
+                ctx.check_extern_arity(&name, args.len(), e.span, sm, file)?;
+                if let Some(n) = ctx.sigs.multi.get(&name) {
+                    let names: Vec<String> = (b'a'..).take(*n).map(|c| (c as char).to_string()).collect();
+                    let msg = format!(
+                        "function '{}' returns {} values; call it as `let {} = {}(...)`",
+                        crate::names::display(&name),
+                        n,
+                        names.join(", "),
+                        crate::names::display(&name)
+                    );
+                    return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &msg, e.span)));
                 }
                 let lowered_args = args
                     .into_iter()
-                    .map(|a| lower_expr(a, out, ctx, sm, file))
-                    .collect::<Result<Vec<_>, _>>()?;
+                    .map(|a| lower_words(a, out, ctx, sm, file))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .concat();
+                if ctx.sigs.bools.contains(&name) {
+                    return Ok(ir::Val::BoolCall { name, args: lowered_args });
+                }
                 Ok(ir::Val::Call {
                     name,
                     args: lowered_args,
@@ -875,7 +1368,7 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
                 .collect::<Result<Vec<_>, _>>()?;
             Ok(ir::Val::MapLiteral(lowered_entries))
         }
-        ast::ExprKind::MapIndex { map, key } => Ok(ir::Val::MapIndex { map, key }),
+        ast::ExprKind::MapIndex { map, key, optional } => Ok(ir::Val::MapIndex { map, key, optional }),
         ast::ExprKind::Capture { expr, options } => {
             let expr_span = expr.span; // Capture span before move
             // Path C: Hoisting is handled by parser for nested run(...) inside $(...).
@@ -993,3 +1486,163 @@ pub(super) fn lower_expr<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut Low
         }
     }
 }
+
+/// Lowers a call argument or list item, where `...xs` spreads a list into
+/// several words. A spread list literal is spliced in place; a computed list
+/// such as `split(...)` is stored in a temporary array first.
+pub(super) fn lower_words<'a>(e: ast::Expr, out: &mut Vec<ir::Cmd>, ctx: &mut LoweringContext<'a>, sm: &SourceMap, file: &str) -> Result<Vec<ir::Val>, CompileError> {
+    let ast::ExprKind::Spread(inner) = e.node else {
+        return Ok(vec![lower_expr(e, out, ctx, sm, file)?]);
+    };
+    let opts = ctx.opts();
+    let list = lower_expr(*inner, out, ctx, sm, file)?;
+    let error = |msg: &str| Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), msg, e.span)));
+    match list {
+        ir::Val::List(items) => Ok(items),
+        ir::Val::Args => Ok(vec![ir::Val::Spread(Box::new(ir::Val::Args))]),
+        ir::Val::Var(ref name) if !ctx.is_list_var(name) => error(&format!("`...` needs a list, but '{}' is not a list", name)),
+        ir::Val::Var(_) | ir::Val::Split { .. } | ir::Val::Lines(_) | ir::Val::Lines0(_) | ir::Val::Find { .. }
+            if opts.target == crate::codegen::TargetShell::Posix =>
+        {
+            error("spreading a list variable is not supported in POSIX sh, which has no arrays; spread `args` or a list literal instead")
+        }
+        ir::Val::Var(_) => Ok(vec![ir::Val::Spread(Box::new(list))]),
+        ir::Val::Split { .. } | ir::Val::Lines(_) | ir::Val::Lines0(_) | ir::Val::Find { .. } => {
+            ctx.tmp_counter += 1;
+            let tmp = format!("__sh2_tmp_list_{}", ctx.tmp_counter);
+            ctx.insert_list_var(&tmp);
+            out.push(ir::Cmd::Assign(tmp.clone(), list, None));
+            Ok(vec![ir::Val::Spread(Box::new(ir::Val::Var(tmp)))])
+        }
+        _ => error("`...` needs a list, such as a list variable, `args` or `split(...)`"),
+    }
+}
+
+fn compare_op(op: ast::CompareOp) -> ir::CompareOp {
+    match op {
+        ast::CompareOp::Eq => ir::CompareOp::Eq,
+        ast::CompareOp::NotEq => ir::CompareOp::NotEq,
+        ast::CompareOp::Lt => ir::CompareOp::Lt,
+        ast::CompareOp::Le => ir::CompareOp::Le,
+        ast::CompareOp::Gt => ir::CompareOp::Gt,
+        ast::CompareOp::Ge => ir::CompareOp::Ge,
+    }
+}
+
+/// Lower `wait_for_port(host, port, timeout=30, interval=1)` arguments, shared by the
+/// statement form (fatal on timeout) and the boolean expression form.
+pub(super) fn lower_wait_for_port_args<'a>(
+    args: Vec<ast::Expr>,
+    options: Vec<ast::CallOption>,
+    span: Span,
+    out: &mut Vec<ir::Cmd>,
+    ctx: &mut LoweringContext<'a>,
+    sm: &SourceMap,
+    file: &str,
+) -> Result<(ir::Val, ir::Val, ir::Val, ir::Val), CompileError> {
+    let opts = ctx.opts();
+    if args.len() != 2 {
+        return Err(CompileError::new(sm.format_diagnostic(
+            file,
+            opts.diag_base_dir.as_deref(),
+            "wait_for_port() requires exactly 2 positional arguments (host, port)",
+            span,
+        )));
+    }
+    let mut timeout = None;
+    let mut interval = None;
+    for opt in options {
+        let slot = match opt.name.as_str() {
+            "timeout" => &mut timeout,
+            "interval" => &mut interval,
+            other => {
+                return Err(CompileError::new(sm.format_diagnostic(
+                    file,
+                    opts.diag_base_dir.as_deref(),
+                    &format!("Unknown option {:?} for wait_for_port(); supported options are timeout and interval", other),
+                    opt.span,
+                )));
+            }
+        };
+        if slot.is_some() {
+            return Err(CompileError::new(sm.format_diagnostic(
+                file,
+                opts.diag_base_dir.as_deref(),
+                &format!("{} specified more than once", opt.name),
+                opt.span,
+            )));
+        }
+        *slot = Some(opt.value);
+    }
+    let mut iter = args.into_iter();
+    let host = lower_expr(iter.next().unwrap(), out, ctx, sm, file)?;
+    let port = lower_expr(iter.next().unwrap(), out, ctx, sm, file)?;
+    let timeout = match timeout {
+        Some(t) => lower_expr(t, out, ctx, sm, file)?,
+        None => ir::Val::Number(30),
+    };
+    let interval = match interval {
+        Some(i) => lower_expr(i, out, ctx, sm, file)?,
+        None => ir::Val::Number(1),
+    };
+    Ok((host, port, timeout, interval))
+}
+
+/// Catches awk programs that cannot parse: unterminated strings or regexes and
+/// unbalanced brackets. Returns the rest of the error message.
+fn check_awk_program(program: &str) -> Result<(), String> {
+    let mut open: Vec<char> = Vec::new();
+    let mut prev = '\n';
+    let mut chars = program.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => loop {
+                match chars.next() {
+                    Some('\\') => {
+                        chars.next();
+                    }
+                    Some('"') => break,
+                    Some('\n') | None => return Err("has an unterminated string".to_string()),
+                    Some(_) => {}
+                }
+            },
+            // A `/` where an operand is expected starts a regex, otherwise it divides.
+            '/' if matches!(prev, '\n' | '(' | ',' | '{' | ';' | '!' | '~' | '&' | '|') => loop {
+                match chars.next() {
+                    Some('\\') => {
+                        chars.next();
+                    }
+                    Some('/') => break,
+                    Some('\n') | None => return Err("has an unterminated regular expression".to_string()),
+                    Some(_) => {}
+                }
+            },
+            '#' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '{' | '(' | '[' => open.push(c),
+            '}' | ')' | ']' => {
+                let expected = match c {
+                    '}' => '{',
+                    ')' => '(',
+                    _ => '[',
+                };
+                if open.pop() != Some(expected) {
+                    return Err(format!("has an unmatched '{}'", c));
+                }
+            }
+            _ => {}
+        }
+        if !c.is_whitespace() || c == '\n' {
+            prev = c;
+        }
+    }
+    match open.last() {
+        Some(c) => Err(format!("has an unclosed '{}'", c)),
+        None => Ok(()),
+    }
+}
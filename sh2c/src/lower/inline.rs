@@ -0,0 +1,216 @@
+//! Inlining of small functions at `let x = f(...)` call sites.
+//!
+//! A value call costs a command substitution (a subshell) per call. A function
+//! whose body is only lets followed by a `return` of a string or arithmetic
+//! value can instead be expanded in place: its parameters and locals are bound
+//! to `__sh2_inl_<func>_<name>` variables (or replaced directly by variable and
+//! number arguments), and the returned value is assigned to the `let` target.
+//!
+//! Functions marked `@inline` are expanded whenever their shape allows it.
+//! Others are expanded only when they are small and their result cannot end in
+//! a newline, which `$( )` would have removed.
+
+use crate::ast;
+use crate::ir::{Cmd, Function, Val};
+use crate::span::Span;
+use std::collections::{HashMap, HashSet};
+
+/// Largest function (in value nodes) that is inlined without `@inline`.
+const AUTO_INLINE_MAX_NODES: usize = 8;
+
+/// Whether an `@inline` function's body has a shape this pass can expand.
+pub(super) fn check_body(body: &[ast::Stmt]) -> Result<(), Span> {
+    let Some((last, lets)) = body.split_last() else { return Ok(()) };
+    for stmt in lets {
+        match &stmt.node {
+            ast::StmtKind::Let { value, .. } | ast::StmtKind::Set { target: ast::LValue::Var(_), value } => {
+                check_expr(value)?
+            }
+            _ => return Err(stmt.span),
+        }
+    }
+    match &last.node {
+        ast::StmtKind::Return(Some(value)) => check_expr(value),
+        _ => Err(last.span),
+    }
+}
+
+fn check_expr(e: &ast::Expr) -> Result<(), Span> {
+    match &e.node {
+        ast::ExprKind::Literal(_) | ast::ExprKind::Number(_) | ast::ExprKind::Var(_) => Ok(()),
+        ast::ExprKind::Concat(l, r) | ast::ExprKind::Arith { left: l, right: r, .. } => {
+            check_expr(l)?;
+            check_expr(r)
+        }
+        _ => Err(e.span),
+    }
+}
+
+/// A function body reduced to its assignments and returned value.
+struct Candidate {
+    params: Vec<String>,
+    lets: Vec<(String, Val, Option<String>)>,
+    ret: Val,
+}
+
+fn candidate(f: &Function, forced: bool) -> Option<Candidate> {
+    if f.cache {
+        return None;
+    }
+    let (last, init) = f.commands.split_last()?;
+    let Cmd::Return(Some(ret)) = last else { return None };
+    let lets = init
+        .iter()
+        .map(|c| match c {
+            Cmd::Assign(name, val, loc) => Some((name.clone(), val.clone(), loc.clone())),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let mut nodes = 0;
+    for v in lets.iter().map(|(_, v, _)| v).chain([ret]) {
+        nodes += size(v)?;
+    }
+    if !forced && (nodes > AUTO_INLINE_MAX_NODES || !ends_clean(ret)) {
+        return None;
+    }
+    Some(Candidate { params: f.params.clone(), lets, ret: ret.clone() })
+}
+
+/// Node count of a value the pass can copy, or `None` for anything else.
+fn size(v: &Val) -> Option<usize> {
+    match v {
+        Val::Literal(_) | Val::Number(_) | Val::Var(_) => Some(1),
+        Val::Concat(l, r) | Val::Arith { left: l, right: r, .. } => Some(1 + size(l)? + size(r)?),
+        _ => None,
+    }
+}
+
+/// Whether `v` is known not to end in a newline.
+fn ends_clean(v: &Val) -> bool {
+    match v {
+        Val::Number(_) | Val::Arith { .. } => true,
+        Val::Literal(s) => !s.ends_with('\n'),
+        Val::Concat(l, r) if matches!(&**r, Val::Literal(s) if s.is_empty()) => ends_clean(l),
+        Val::Concat(_, r) => ends_clean(r),
+        _ => false,
+    }
+}
+
+fn substitute(v: &Val, names: &HashMap<&str, Val>) -> Val {
+    match v {
+        Val::Var(name) => names.get(name.as_str()).cloned().unwrap_or_else(|| v.clone()),
+        Val::Concat(l, r) => Val::Concat(Box::new(substitute(l, names)), Box::new(substitute(r, names))),
+        Val::Arith { left, op, right } => Val::Arith {
+            left: Box::new(substitute(left, names)),
+            op: op.clone(),
+            right: Box::new(substitute(right, names)),
+        },
+        _ => v.clone(),
+    }
+}
+
+impl Candidate {
+    /// The commands that replace `target = f(args)`.
+    fn expand(&self, func: &str, target: &str, args: &[Val], loc: &Option<String>) -> Option<Vec<Cmd>> {
+        if args.len() != self.params.len() || args.iter().any(|a| matches!(a, Val::Args)) {
+            return None;
+        }
+        let temp = |name: &str| format!("__sh2_inl_{}_{}", func, name);
+        let assigned: HashSet<&str> = self.lets.iter().map(|(name, _, _)| name.as_str()).collect();
+        let mut names: HashMap<&str, Val> = HashMap::new();
+        let mut out = Vec::new();
+        for (param, arg) in self.params.iter().zip(args) {
+            // A variable or number means the same in every context, so it can
+            // stand in for the parameter; anything else is evaluated once, first.
+            if matches!(arg, Val::Var(_) | Val::Number(_)) && !assigned.contains(param.as_str()) {
+                names.insert(param, arg.clone());
+            } else {
+                out.push(Cmd::Assign(temp(param), arg.clone(), loc.clone()));
+                names.insert(param, Val::Var(temp(param)));
+            }
+        }
+        for (name, val, let_loc) in &self.lets {
+            let val = substitute(val, &names);
+            names.insert(name, Val::Var(temp(name)));
+            out.push(Cmd::Assign(temp(name), val, let_loc.clone()));
+        }
+        out.push(Cmd::Assign(target.to_string(), substitute(&self.ret, &names), loc.clone()));
+        Some(out)
+    }
+}
+
+/// Expands calls to inlinable functions in every function of `funcs`.
+/// `forced` holds the names of `@inline` functions.
+pub(super) fn inline_calls(funcs: &mut [Function], forced: &HashSet<String>) {
+    let candidates: HashMap<String, Candidate> = funcs
+        .iter()
+        .filter_map(|f| Some((f.name.clone(), candidate(f, forced.contains(&f.name))?)))
+        .collect();
+    if candidates.is_empty() {
+        return;
+    }
+    for f in funcs.iter_mut() {
+        block(&mut f.commands, &candidates);
+    }
+}
+
+fn block(cmds: &mut Vec<Cmd>, candidates: &HashMap<String, Candidate>) {
+    let mut i = 0;
+    while i < cmds.len() {
+        if let Cmd::Assign(target, Val::Call { name, args }, loc) = &cmds[i]
+            && let Some(expanded) = candidates.get(name).and_then(|c| c.expand(name, target, args, loc))
+        {
+            let n = expanded.len();
+            cmds.splice(i..=i, expanded);
+            i += n;
+            continue;
+        }
+        nested(&mut cmds[i], candidates);
+        i += 1;
+    }
+}
+
+// SYNC WITH: ir::Cmd::strip_spans (traversal shape).
+fn nested(cmd: &mut Cmd, candidates: &HashMap<String, Candidate>) {
+    match cmd {
+        Cmd::If { then_body, elifs, else_body, .. } => {
+            block(then_body, candidates);
+            for (_, body) in elifs {
+                block(body, candidates);
+            }
+            block(else_body, candidates);
+        }
+        Cmd::PipeBlocks(blocks, _) => {
+            for (body, _) in blocks {
+                block(body, candidates);
+            }
+        }
+        Cmd::TryCatch { try_body: left, catch_body: right }
+        | Cmd::AndThen { left, right }
+        | Cmd::OrElse { left, right } => {
+            block(left, candidates);
+            block(right, candidates);
+        }
+        Cmd::PipeEachLine { body, .. }
+        | Cmd::While { body, .. }
+        | Cmd::For { body, .. }
+        | Cmd::ForMap { body, .. }
+        | Cmd::WithEnv { body, .. }
+        | Cmd::WithLog { body, .. }
+        | Cmd::WithCwd { body, .. }
+        | Cmd::WithDeadline { body, .. }
+        | Cmd::WithUmask { body, .. }
+        | Cmd::WithLimits { body, .. }
+        | Cmd::Subshell { body }
+        | Cmd::Group { body }
+        | Cmd::Unchecked { body }
+        | Cmd::WithRedirect { body, .. }
+        | Cmd::Profile { body, .. } => block(body, candidates),
+        Cmd::Case { arms, .. } => {
+            for (_, body) in arms {
+                block(body, candidates);
+            }
+        }
+        _ => {}
+    }
+}
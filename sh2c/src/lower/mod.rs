@@ -1,15 +1,36 @@
 use crate::ast;
 use crate::ir;
-use crate::span::{Span, SourceMap};
+use crate::span::{Diagnostic, Span, SourceMap};
 use crate::error::CompileError;
 
 mod expr;
 
+mod inline;
+
 mod stmt;
 use self::stmt::lower_stmt;
 
 mod sudo;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+/// What call sites need to know about each user function.
+#[derive(Debug, Default)]
+pub(super) struct Signatures {
+    /// Number of parameters
+    params: HashMap<String, usize>,
+    /// Functions that `return a, b`, and how many values they return
+    multi: HashMap<String, usize>,
+    /// Functions declared `-> bool`
+    bools: HashSet<String>,
+}
+
+/// What a call through a variable invokes.
+pub(super) enum Callee {
+    /// The variable is known to hold this function, which is called directly.
+    Func(String),
+    /// Whatever the variable holds at runtime, such as a callback parameter.
+    Var,
+}
 
 #[derive(Clone, Debug)]
 pub(super) struct LoweringContext<'a> {
@@ -18,23 +39,42 @@ pub(super) struct LoweringContext<'a> {
     bool_vars: HashSet<String>,
     /// Variables that are known to hold list values (e.g. from list literals)
     list_vars: HashSet<String>,
-    /// User-defined function names for call validation
+    /// Variables bound to a function reference, and the function they name
+    func_refs: HashMap<String, String>,
+    /// User-defined function names for call validation (including externs)
     user_funcs: &'a HashSet<String>,
+    /// `extern func` names and their parameter counts
+    externs: &'a HashMap<String, usize>,
+    sigs: &'a Signatures,
+    /// The function being lowered is declared `-> bool`.
+    returns_bool: bool,
     opts: &'a LowerOptions,
     in_let_rhs: bool,
     tmp_counter: usize,
+    /// Parameters and variables declared so far, for `dump_vars()` and the debugger.
+    declared_vars: Vec<String>,
 }
 
 impl<'a> LoweringContext<'a> {
-    fn new(opts: &'a LowerOptions, user_funcs: &'a HashSet<String>) -> Self {
+    fn new(
+        opts: &'a LowerOptions,
+        user_funcs: &'a HashSet<String>,
+        externs: &'a HashMap<String, usize>,
+        sigs: &'a Signatures,
+    ) -> Self {
         Self {
             run_results: HashSet::new(),
             bool_vars: HashSet::new(),
             list_vars: HashSet::new(),
+            func_refs: HashMap::new(),
             user_funcs,
+            externs,
+            sigs,
+            returns_bool: false,
             opts,
             in_let_rhs: false,
             tmp_counter: 0,
+            declared_vars: Vec::new(),
         }
     }
 
@@ -42,6 +82,12 @@ impl<'a> LoweringContext<'a> {
         self.opts
     }
 
+    fn declare_var(&mut self, name: &str) {
+        if !self.declared_vars.iter().any(|v| v == name) {
+            self.declared_vars.push(name.to_string());
+        }
+    }
+
     fn insert(&mut self, name: &str) {
         self.run_results.insert(name.to_string());
     }
@@ -50,6 +96,14 @@ impl<'a> LoweringContext<'a> {
         self.run_results.remove(name);
     }
 
+    /// Drops everything known about `name`'s value.
+    fn forget(&mut self, name: &str) {
+        self.run_results.remove(name);
+        self.bool_vars.remove(name);
+        self.list_vars.remove(name);
+        self.func_refs.remove(name);
+    }
+
     fn insert_bool_var(&mut self, name: &str) {
         self.bool_vars.insert(name.to_string());
     }
@@ -66,7 +120,82 @@ impl<'a> LoweringContext<'a> {
         self.list_vars.contains(name)
     }
 
+    /// Rejects a call to an `extern func` with the wrong number of arguments.
+    fn check_extern_arity(&self, name: &str, argc: usize, span: Span, sm: &SourceMap, file: &str) -> Result<(), CompileError> {
+        match self.externs.get(name) {
+            Some(&n) if n != argc => Err(CompileError::new(sm.format_diagnostic(
+                file,
+                self.opts.diag_base_dir.as_deref(),
+                &format!("extern func '{}' takes {} argument(s), got {}", name, n, argc),
+                span,
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `name` used as a value is a reference to the function of that
+    /// name, rather than a variable.
+    fn is_func_name(&self, name: &str) -> bool {
+        self.user_funcs.contains(name) && !self.declared_vars.iter().any(|v| v == name)
+    }
+
+    /// The function `name` refers to when used as a value: a user function
+    /// (`let cb = on_error`), or a variable bound to one.
+    fn func_ref(&self, name: &str) -> Option<String> {
+        if let Some(target) = self.func_refs.get(name) {
+            return Some(target.clone());
+        }
+        self.is_func_name(name).then(|| name.to_string())
+    }
 
+    /// Records what `name` holds after `name = value`.
+    fn bind_func_ref(&mut self, name: &str, value: &ast::Expr) {
+        match &value.node {
+            ast::ExprKind::Var(v) => match self.func_ref(v) {
+                Some(target) => {
+                    self.func_refs.insert(name.to_string(), target);
+                }
+                None => {
+                    self.func_refs.remove(name);
+                }
+            },
+            _ => {
+                self.func_refs.remove(name);
+            }
+        }
+    }
+
+    /// Resolves a call to `name`, which is not a function, through a variable
+    /// holding a function reference. `None` means `name` is no variable.
+    fn call_through(
+        &self,
+        name: &str,
+        argc: usize,
+        span: Span,
+        sm: &SourceMap,
+        file: &str,
+    ) -> Result<Option<Callee>, CompileError> {
+        if let Some(target) = self.func_refs.get(name) {
+            let params = self.sigs.params.get(target).or_else(|| self.externs.get(target));
+            if let Some(&n) = params
+                && n != argc
+            {
+                let msg = format!(
+                    "'{}' refers to function '{}', which takes {} argument(s), got {}",
+                    name,
+                    crate::names::display(target),
+                    n,
+                    argc
+                );
+                return Err(CompileError::new(sm.format_diagnostic(file, self.opts.diag_base_dir.as_deref(), &msg, span)));
+            }
+            return Ok(Some(Callee::Func(target.clone())));
+        }
+        if self.declared_vars.iter().any(|v| v == name) {
+            return Ok(Some(Callee::Var));
+        }
+        Ok(None)
+    }
 
     fn intersection(&self, other: &Self) -> Self {
         let run_results = self
@@ -84,14 +213,32 @@ impl<'a> LoweringContext<'a> {
             .intersection(&other.list_vars)
             .cloned()
             .collect();
+        let func_refs = self
+            .func_refs
+            .iter()
+            .filter(|(k, v)| other.func_refs.get(*k) == Some(*v))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        // Shell variables outlive the branch that set them.
+        let mut declared_vars = self.declared_vars.clone();
+        for v in &other.declared_vars {
+            if !declared_vars.contains(v) {
+                declared_vars.push(v.clone());
+            }
+        }
         Self {
             run_results,
             bool_vars,
             list_vars,
+            func_refs,
             user_funcs: self.user_funcs,
+            externs: self.externs,
+            sigs: self.sigs,
+            returns_bool: self.returns_bool,
             opts: self.opts,
             in_let_rhs: self.in_let_rhs,
             tmp_counter: std::cmp::max(self.tmp_counter, other.tmp_counter),
+            declared_vars,
         }
     }
 }
@@ -103,6 +250,12 @@ pub struct LowerOptions {
     pub include_diagnostics: bool,
     pub diag_base_dir: Option<PathBuf>,
     pub target: crate::codegen::TargetShell,
+    /// Instrument every statement with a debugger hook and keep `breakpoint()`
+    /// statements (which otherwise compile to nothing). Used by `sh2do --debug`.
+    pub debug: bool,
+    /// Time each statement of a function body and report the slowest lines at
+    /// exit (`--profile`).
+    pub profile: bool,
 }
 
 impl Default for LowerOptions {
@@ -111,6 +264,8 @@ impl Default for LowerOptions {
             include_diagnostics: true,
             diag_base_dir: None,
             target: crate::codegen::TargetShell::Bash,
+            debug: false,
+            profile: false,
         }
     }
 }
@@ -121,10 +276,79 @@ pub fn lower(p: ast::Program) -> Result<Vec<ir::Function>, CompileError> {
 }
 
 pub fn lower_with_options(p: ast::Program, opts: &LowerOptions) -> Result<Vec<ir::Function>, CompileError> {
-    let has_main = p.functions.iter().any(|f| f.name == "main");
+    lower_program(p, opts, Some("main"))
+}
 
-    // Collect user-defined function names for call validation
-    let user_funcs: HashSet<String> = p.functions.iter().map(|f| f.name.clone()).collect();
+/// Lower a script whose last line calls `entry` (`--entry`) instead of `main`.
+/// Parameters are untyped, so the entry's parameters simply take the script's
+/// arguments as strings, in order.
+pub fn lower_with_entry(p: ast::Program, opts: &LowerOptions, entry: &str) -> Result<Vec<ir::Function>, CompileError> {
+    lower_program(p, opts, Some(entry))
+}
+
+/// Lower a module for `--emit-lib`: the same as a script, except that no `main` is needed.
+pub fn lower_library(p: ast::Program, opts: &LowerOptions) -> Result<Vec<ir::Function>, CompileError> {
+    lower_program(p, opts, None)
+}
+
+/// Turns the top-level statements of a script-style entry file into the body
+/// of `func main()`.
+pub fn implicit_main(p: &mut ast::Program, sm: &SourceMap) -> Result<(), Diagnostic> {
+    let (Some(first), Some(last)) = (p.top_level.first(), p.top_level.last()) else {
+        return Ok(());
+    };
+    if p.functions.iter().any(|f| f.name == "main") {
+        return Err(Diagnostic {
+            msg: "Top-level statements are not allowed in a file that defines func main(); move them into main."
+                .to_string(),
+            code: Some("E0101"),
+            span: first.span,
+            sm: Some(sm.clone()),
+            file: Some(p.entry_file.clone()),
+            help: None,
+            additional: Vec::new(),
+        });
+    }
+    let span = first.span.merge(last.span);
+    p.functions.push(ast::Function {
+        doc: Vec::new(),
+        name: "main".to_string(),
+        params: Vec::new(),
+        returns_bool: false,
+        body: std::mem::take(&mut p.top_level),
+        span,
+        file: p.entry_file.clone(),
+        attrs: Vec::new(),
+    });
+    Ok(())
+}
+
+fn lower_program(mut p: ast::Program, opts: &LowerOptions, entry: Option<&str>) -> Result<Vec<ir::Function>, CompileError> {
+    // The loader does these file by file; a program parsed directly still needs them.
+    if !p.hooks.is_empty() || !p.top_level.is_empty() {
+        let sm = p.source_maps.get(&p.entry_file).cloned().expect("Missing source map for entry file");
+        let base = opts.diag_base_dir.as_deref();
+        implicit_main(&mut p, &sm).map_err(|d| CompileError::new(d.format(base)))?;
+        crate::hooks::weave(&mut p, &sm).map_err(|d| CompileError::new(d.format(base)))?;
+    }
+    crate::pure::check(&p, opts.diag_base_dir.as_deref())?;
+    let folded_away = crate::pure::fold(&mut p, entry);
+    let has_entry = entry.is_none_or(|name| p.functions.iter().any(|f| f.name == name));
+
+    // Collect user-defined and extern function names for call validation
+    let externs: HashMap<String, usize> = p.externs.iter().map(|e| (e.name.clone(), e.params.len())).collect();
+    let user_funcs: HashSet<String> = p.functions.iter().map(|f| f.name.clone()).chain(externs.keys().cloned()).collect();
+    let mut sigs = Signatures::default();
+    for f in &p.functions {
+        let sm = p.source_maps.get(&f.file).expect("Missing source map");
+        sigs.params.insert(f.name.clone(), f.params.len());
+        if let Some(n) = return_count(f, sm, opts)? {
+            sigs.multi.insert(f.name.clone(), n);
+        }
+        if f.returns_bool {
+            sigs.bools.insert(f.name.clone());
+        }
+    }
 
     let entry_file = &p.entry_file;
     let maps = &p.source_maps;
@@ -135,24 +359,191 @@ pub fn lower_with_options(p: ast::Program, opts: &LowerOptions) -> Result<Vec<ir
 
     let mut ir_funcs = Vec::new();
 
-    if !has_main {
-        return Err(CompileError::new(entry_sm.format_diagnostic(entry_file, opts.diag_base_dir.as_deref(), "No entrypoint: define `func main()`.", p.span)));
+    if !has_entry {
+        let msg = match entry {
+            Some("main") | None => "No entrypoint: define `func main()`.".to_string(),
+            Some(name) if externs.contains_key(name) => {
+                format!("--entry {}: an extern function cannot be the entry point; define `func {}(...)`.", name, name)
+            }
+            Some(name) => format!("--entry {}: no such function; define `func {}(...)`.", name, name),
+        };
+        return Err(CompileError::new(entry_sm.format_diagnostic(entry_file, opts.diag_base_dir.as_deref(), &msg, p.span)));
+    }
+    // `init` blocks run once, in import order, before the entry function's own body.
+    if let Some(first) = p.inits.first() {
+        let Some(entry) = entry else {
+            let init = p.functions.iter().find(|f| f.name == *first).expect("init function");
+            let sm = maps.get(&init.file).expect("Missing source map");
+            let msg = "`init { ... }` blocks run before the entry function, and a library has none";
+            return Err(CompileError::new(sm.format_diagnostic(&init.file, opts.diag_base_dir.as_deref(), msg, init.span)));
+        };
+        let main = p.functions.iter_mut().find(|f| f.name == entry).expect("entry function");
+        let calls = p.inits.iter().map(|name| ast::Stmt {
+            node: ast::StmtKind::Call { name: name.clone(), args: Vec::new(), options: Vec::new() },
+            span: main.span,
+        });
+        main.body.splice(0..0, calls);
     }
+    // A documented `main` answers `--help` with its doc comment, before anything
+    // else runs.
+    if entry == Some("main")
+        && let Some(help) = crate::doc::help_text(&p)
+    {
+        let main = p.functions.iter_mut().find(|f| f.name == "main").expect("entry function");
+        let span = main.span;
+        let expr = |node| ast::Expr { node, span };
+        let cond = ast::ExprKind::Compare {
+            left: Box::new(expr(ast::ExprKind::Arg(Box::new(expr(ast::ExprKind::Number(1)))))),
+            op: ast::CompareOp::Eq,
+            right: Box::new(expr(ast::ExprKind::Literal("--help".to_string()))),
+        };
+        let then_body = vec![
+            ast::Stmt { node: ast::StmtKind::Print(expr(ast::ExprKind::Literal(help))), span },
+            ast::Stmt { node: ast::StmtKind::Exit(Some(expr(ast::ExprKind::Number(0)))), span },
+        ];
+        let check = ast::StmtKind::If { cond: expr(cond), then_body, elifs: Vec::new(), else_body: None };
+        main.body.insert(0, ast::Stmt { node: check, span });
+    }
+    let mut forced_inline = HashSet::new();
     for f in p.functions {
         let sm = maps.get(&f.file).expect("Missing source map");
-        ir_funcs.push(lower_function(f, sm, opts, &user_funcs)?);
+        let is_entry = entry == Some(f.name.as_str());
+        if let Some(attr) = f.attr("inline") {
+            let display = crate::names::display(&f.name);
+            let bad = if f.attr("cache").is_some() {
+                Some((format!("@inline function '{}' cannot also be @cache", display), attr.span))
+            } else {
+                inline::check_body(&f.body).err().map(|span| {
+                    let msg = format!(
+                        "@inline function '{}' can only contain lets and a final return of a string or arithmetic value",
+                        display
+                    );
+                    (msg, span)
+                })
+            };
+            if let Some((msg, span)) = bad {
+                return Err(CompileError::new(sm.format_diagnostic(&f.file, opts.diag_base_dir.as_deref(), &msg, span)));
+            }
+            forced_inline.insert(f.name.clone());
+        }
+        let dead = folded_away.contains(&f.name);
+        if let Some(attr) = f.attr("cache") {
+            let display = crate::names::display(&f.name);
+            let bad = if sigs.multi.contains_key(&f.name) {
+                Some(format!("@cache function '{}' cannot return multiple values", display))
+            } else if f.returns_bool {
+                Some(format!("@cache function '{}' cannot be declared `-> bool`", display))
+            } else {
+                None
+            };
+            if let Some(msg) = bad {
+                return Err(CompileError::new(sm.format_diagnostic(&f.file, opts.diag_base_dir.as_deref(), &msg, attr.span)));
+            }
+        }
+        let func = lower_function(f, sm, opts, &user_funcs, &externs, &sigs, is_entry)?;
+        if !dead {
+            ir_funcs.push(func);
+        }
+    }
+    // Breakpoints and timings should see the functions that were written.
+    if !opts.debug && !opts.profile {
+        inline::inline_calls(&mut ir_funcs, &forced_inline);
     }
 
     Ok(ir_funcs)
 }
 
+/// The number of values `f` returns when it uses `return a, b`, after checking
+/// that every `return` in it gives that many.
+fn return_count(f: &ast::Function, sm: &SourceMap, opts: &LowerOptions) -> Result<Option<usize>, CompileError> {
+    fn collect(stmts: &[ast::Stmt], out: &mut Vec<(usize, Span)>) {
+        for stmt in stmts {
+            match &stmt.node {
+                ast::StmtKind::Return(v) => out.push((v.iter().count(), stmt.span)),
+                ast::StmtKind::ReturnValues(values) => out.push((values.len(), stmt.span)),
+                ast::StmtKind::If { then_body, elifs, else_body, .. } => {
+                    collect(then_body, out);
+                    for elif in elifs {
+                        collect(&elif.body, out);
+                    }
+                    collect(else_body.as_deref().unwrap_or_default(), out);
+                }
+                ast::StmtKind::Case { arms, .. } => arms.iter().for_each(|arm| collect(&arm.body, out)),
+                ast::StmtKind::TryCatch { try_body: left, catch_body: right }
+                | ast::StmtKind::AndThen { left, right }
+                | ast::StmtKind::OrElse { left, right } => {
+                    collect(left, out);
+                    collect(right, out);
+                }
+                ast::StmtKind::While { body, .. }
+                | ast::StmtKind::For { body, .. }
+                | ast::StmtKind::ForMap { body, .. }
+                | ast::StmtKind::WithEnv { body, .. }
+                | ast::StmtKind::WithCwd { body, .. }
+                | ast::StmtKind::WithLog { body, .. }
+                | ast::StmtKind::WithDeadline { body, .. }
+                | ast::StmtKind::WithUmask { body, .. }
+                | ast::StmtKind::WithRedirect { body, .. }
+                | ast::StmtKind::Group { body }
+                | ast::StmtKind::Unchecked { body } => collect(body, out),
+                _ => {}
+            }
+        }
+    }
+    let mut returns = Vec::new();
+    collect(&f.body, &mut returns);
+    let Some(&(n, _)) = returns.iter().find(|(n, _)| *n > 1) else { return Ok(None) };
+    if let Some(&(m, span)) = returns.iter().find(|(m, _)| *m != n) {
+        let msg = format!(
+            "function '{}' returns {} values elsewhere, so every return in it must give {} values (this one gives {})",
+            crate::names::display(&f.name),
+            n,
+            n,
+            m
+        );
+        return Err(CompileError::new(sm.format_diagnostic(&f.file, opts.diag_base_dir.as_deref(), &msg, span)));
+    }
+    Ok(Some(n))
+}
+
 /// Lower a single function
-fn lower_function(f: ast::Function, sm: &SourceMap, opts: &LowerOptions, user_funcs: &HashSet<String>) -> Result<ir::Function, CompileError> {
+fn lower_function(
+    f: ast::Function,
+    sm: &SourceMap,
+    opts: &LowerOptions,
+    user_funcs: &HashSet<String>,
+    externs: &HashMap<String, usize>,
+    sigs: &Signatures,
+    entry: bool,
+) -> Result<ir::Function, CompileError> {
+    let cache = f.attr("cache").is_some();
     let mut body = Vec::new();
-    let mut ctx = LoweringContext::new(opts, user_funcs);
-
-    for stmt in f.body {
-        ctx = lower_stmt(stmt, &mut body, ctx, sm, &f.file, opts)?;
+    let mut ctx = LoweringContext::new(opts, user_funcs, externs, sigs);
+    ctx.returns_bool = f.returns_bool;
+    ctx.declared_vars = f.params.clone();
+    if opts.profile {
+        let start_var = format!("__sh2_pt_{}", f.name);
+        for stmt in f.body {
+            // Nothing runs after these, so there is no end to time.
+            if matches!(stmt.node, ast::StmtKind::Return(_) | ast::StmtKind::Exit(_)) {
+                ctx = lower_block(vec![stmt], &mut body, ctx, sm, &f.file, opts)?;
+                continue;
+            }
+            let (line, _) = sm.line_col(stmt.span.start);
+            let loc = resolve_span(stmt.span, sm, &f.file, opts.diag_base_dir.as_deref());
+            let source = sm.line_snippet(line).trim().to_string();
+            let mut inner = Vec::new();
+            ctx = lower_block(vec![stmt], &mut inner, ctx, sm, &f.file, opts)?;
+            if !inner.is_empty() {
+                body.push(ir::Cmd::Profile { loc, source, start_var: start_var.clone(), body: inner });
+            }
+        }
+    } else {
+        lower_block(f.body, &mut body, ctx, sm, &f.file, opts)?;
+    }
+    // Running off the end of a `-> bool` function gives false.
+    if f.returns_bool && !matches!(body.last(), Some(ir::Cmd::ReturnBool(_))) {
+        body.push(ir::Cmd::ReturnBool(ir::Val::Bool(false)));
     }
 
     Ok(ir::Function {
@@ -160,12 +551,14 @@ fn lower_function(f: ast::Function, sm: &SourceMap, opts: &LowerOptions, user_fu
         params: f.params,
         commands: body,
         file: f.file,
+        entry,
+        cache,
     })
 }
 
 /// Helper to lower a block of statements sequentially
 pub(super) fn lower_block<'a>(
-    stmts: &[ast::Stmt],
+    stmts: Vec<ast::Stmt>,
     out: &mut Vec<ir::Cmd>,
     mut ctx: LoweringContext<'a>,
     sm: &SourceMap,
@@ -173,17 +566,43 @@ pub(super) fn lower_block<'a>(
     opts: &'a LowerOptions,
 ) -> Result<LoweringContext<'a>, CompileError> {
     for stmt in stmts {
-        // We pass a clone of the statement because lower_stmt consumes it
-        // Note: lower_stmt signature might need update or we keep cloning here if lower_stmt consumes.
-        // lower_stmt(stmt, ...) -> Stmt is large? Spanned<StmtKind>.
-        // StmtKind can be large. 
-        // If lower_stmt consumes Stmt, we must clone if we have &Stmt.
-        // Let's check lower_stmt.
-        ctx = lower_stmt(stmt.clone(), out, ctx, sm, file, opts)?;
+        // `breakpoint()` lowers to its own hook.
+        if opts.debug && !matches!(&stmt.node, ast::StmtKind::Call { name, .. } if name == "breakpoint") {
+            out.push(debug_hook(stmt.span, &ctx, sm, file, false));
+        }
+        let declared = match &stmt.node {
+            ast::StmtKind::Let { name, .. } => vec![name.node.clone()],
+            ast::StmtKind::LetValues { names, .. } => names.iter().map(|n| n.node.clone()).collect(),
+            ast::StmtKind::For { var, .. } => {
+                ctx.declare_var(&var.node);
+                Vec::new()
+            }
+            ast::StmtKind::ForMap { key_var, val_var, .. } => {
+                ctx.declare_var(&key_var.node);
+                ctx.declare_var(&val_var.node);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        };
+        ctx = lower_stmt(stmt, out, ctx, sm, file, opts)?;
+        for name in declared {
+            ctx.declare_var(&name);
+        }
     }
     Ok(ctx)
 }
 
+/// The debugger hook for a statement at `span`.
+pub(super) fn debug_hook(span: Span, ctx: &LoweringContext, sm: &SourceMap, file: &str, breakpoint: bool) -> ir::Cmd {
+    let (line, _) = sm.line_col(span.start);
+    ir::Cmd::Debug {
+        loc: resolve_span(span, sm, file, ctx.opts.diag_base_dir.as_deref()),
+        source: sm.line_snippet(line).trim().to_string(),
+        vars: ctx.declared_vars.clone(),
+        breakpoint,
+    }
+}
+
 pub(super) fn resolve_span(
     span: Span,
     sm: &SourceMap,
@@ -2,8 +2,8 @@ use crate::ast::{self, Spanned};
 use crate::ir;
 use crate::span::SourceMap;
 use crate::error::CompileError;
-use super::{LoweringContext, LowerOptions, lower_block, resolve_span};
-use super::expr::lower_expr;
+use super::{Callee, LoweringContext, LowerOptions, lower_block, resolve_span};
+use super::expr::{lower_expr, lower_wait_for_port_args, lower_words};
 use super::sudo::{lower_run_call_args, lower_sudo_call_args};
 
 
@@ -15,7 +15,8 @@ use super::sudo::{lower_run_call_args, lower_sudo_call_args};
 /// - Comparisons (==, !=, <, >, <=, >=)
 /// - Logical operators (&&, ||, !)
 /// - Known predicate builtins: exists, is_dir, is_file, is_symlink, is_exec,
-///   is_readable, is_writable, is_non_empty, matches, contains, contains_line, confirm
+///   is_readable, is_writable, is_non_empty, matches, contains, contains_line, confirm,
+///   have, service_running, wait_for_port
 ///
 /// If new boolean-returning builtins are added, this list must be updated.
 fn is_bool_expr(e: &ast::Expr) -> bool {
@@ -23,6 +24,7 @@ fn is_bool_expr(e: &ast::Expr) -> bool {
         ast::ExprKind::Bool(_) => true,
         ast::ExprKind::Compare { .. } => true,
         ast::ExprKind::And(_, _) | ast::ExprKind::Or(_, _) | ast::ExprKind::Not(_) => true,
+        ast::ExprKind::IfElse { then_expr, else_expr, .. } => is_bool_expr(then_expr) && is_bool_expr(else_expr),
         ast::ExprKind::Call { name, .. } => {
             // Allowlist of known boolean-returning builtins
             matches!(
@@ -38,6 +40,9 @@ fn is_bool_expr(e: &ast::Expr) -> bool {
                     | "matches"
                     | "contains_line"
                     | "confirm"
+                    | "have"
+                    | "service_running"
+                    | "wait_for_port"
             )
         }
         _ => false,
@@ -65,6 +70,24 @@ pub(super) fn lower_stmt<'a>(
     };
     match stmt.node {
         ast::StmtKind::Let { name, value } => {
+            if let ast::ExprKind::Pipeline(segments) = value.node {
+                // Lower as a statement pipeline so per-segment options and status
+                // handling match `run(..) | run(..)`; only the stdout is redirected.
+                let pipe_stmt = ast::Stmt {
+                    node: ast::StmtKind::Pipe(segments),
+                    span: value.span,
+                };
+                let mut pipe_out = Vec::new();
+                let mut ctx = lower_stmt(pipe_stmt, &mut pipe_out, ctx, sm, file, opts)?;
+                let pipe = pipe_out.pop().expect("pipeline lowering emits a command");
+                out.extend(pipe_out);
+                out.push(ir::Cmd::CapturePipe {
+                    name: name.node.clone(),
+                    pipe: Box::new(pipe),
+                });
+                ctx.remove(&name.node);
+                return Ok(ctx);
+            }
             // Special handling for try_run to allow it ONLY during strict let-binding lowering.
             if let ast::ExprKind::Call {
                 name: func_name,
@@ -82,8 +105,9 @@ pub(super) fn lower_stmt<'a>(
                     let lowered_args = args
                         .clone()
                         .into_iter()
-                        .map(|a| lower_expr(a, out, &mut ctx, sm, file))
-                        .collect::<Result<Vec<_>, _>>()?;
+                        .map(|a| lower_words(a, out, &mut ctx, sm, file))
+                        .collect::<Result<Vec<_>, _>>()?
+                        .concat();
                     out.push(ir::Cmd::Assign(
                         name.node.clone(),
                         ir::Val::TryRun(lowered_args),
@@ -95,13 +119,15 @@ pub(super) fn lower_stmt<'a>(
             }
             // Check if RHS is a boolean expression and track it
             let is_bool = is_bool_expr(&value);
+            ctx.bind_func_ref(&name.node, &value);
             
             ctx.in_let_rhs = true;
             let val_ir = lower_expr(value, out, &mut ctx, sm, file)?;
             ctx.in_let_rhs = false;
             
+            let is_bool = is_bool || matches!(&val_ir, ir::Val::BoolCall { .. });
             let is_capture_result = matches!(&val_ir, ir::Val::Capture { allow_fail: true, .. });
-            let is_list = matches!(&val_ir, ir::Val::List(_) | ir::Val::Split { .. } | ir::Val::Lines(_));
+            let is_list = matches!(&val_ir, ir::Val::List(_) | ir::Val::Split { .. } | ir::Val::Lines(_) | ir::Val::InputList(_) | ir::Val::Find { .. } | ir::Val::Lines0(_));
 
             out.push(ir::Cmd::Assign(
                 name.node.clone(),
@@ -125,13 +151,51 @@ pub(super) fn lower_stmt<'a>(
             let ir_args = run_call
                 .args
                 .into_iter()
-                .map(|a| lower_expr(a, out, &mut ctx, sm, file))
-                .collect::<Result<Vec<_>, _>>()?;
+                .map(|a| lower_words(a, out, &mut ctx, sm, file))
+                .collect::<Result<Vec<_>, _>>()?
+                .concat();
 
             let mut allow_fail = false;
             let mut seen_allow_fail = false;
+            let mut stdin = None;
+            let mut env: Option<Vec<(String, ir::Val)>> = None;
+            let mut cwd = None;
             for opt in run_call.options {
-                if opt.name == "allow_fail" {
+                if opt.name == "env" {
+                    if env.is_some() {
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "env specified more than once", opt.span)));
+                    }
+                    let ast::ExprKind::MapLiteral(entries) = opt.value.node else {
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "run() env= expects a map literal (e.g. env={\"KEY\": \"value\"})", opt.value.span)));
+                    };
+                    let mut bindings = Vec::new();
+                    for (key, value) in entries {
+                        let valid = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                        if !valid {
+                            return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &format!("run() env= key '{}' is not a valid environment variable name", key), opt.value.span)));
+                        }
+                        bindings.push((key, lower_expr(value, out, &mut ctx, sm, file)?));
+                    }
+                    env = Some(bindings);
+                } else if opt.name == "cwd" {
+                    if cwd.is_some() {
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "cwd specified more than once", opt.span)));
+                    }
+                    cwd = Some(lower_expr(opt.value, out, &mut ctx, sm, file)?);
+                } else if opt.name == "stdin" {
+                    if stdin.is_some() {
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "stdin specified more than once", opt.span)));
+                    }
+                    match opt.value.node {
+                        ast::ExprKind::Call { name, mut args, .. } if name == "text" && args.len() == 1 => {
+                            stdin = Some(lower_expr(args.remove(0), out, &mut ctx, sm, file)?);
+                        }
+                        _ => {
+                            return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "run() stdin= expects text(expr)", opt.value.span)));
+                        }
+                    }
+                } else if opt.name == "allow_fail" {
                     if seen_allow_fail {
                         return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "allow_fail specified more than once", opt.span)));
                     }
@@ -150,6 +214,9 @@ pub(super) fn lower_stmt<'a>(
                 args: ir_args,
                 allow_fail,
                 loc,
+                stdin,
+                env: env.unwrap_or_default(),
+                cwd,
             });
             Ok(ctx)
         }
@@ -184,7 +251,7 @@ pub(super) fn lower_stmt<'a>(
             let cond_val = lower_expr(cond, out, &mut ctx, sm, file)?;
 
             let mut t_cmds = Vec::new();
-            let ctx_then = lower_block(&then_body, &mut t_cmds, ctx.clone(), sm, file, opts)?;
+            let ctx_then = lower_block(then_body, &mut t_cmds, ctx.clone(), sm, file, opts)?;
 
             let mut lowered_elifs = Vec::new();
             let mut ctx_elifs = Vec::new();
@@ -192,14 +259,14 @@ pub(super) fn lower_stmt<'a>(
             for elif in elifs {
                 let mut body_cmds = Vec::new();
                 let elif_cond = lower_expr(elif.cond.clone(), out, &mut ctx, sm, file)?; // Evaluate cond in original context
-                let ctx_elif = lower_block(&elif.body, &mut body_cmds, ctx.clone(), sm, file, opts)?;
+                let ctx_elif = lower_block(elif.body, &mut body_cmds, ctx.clone(), sm, file, opts)?;
                 lowered_elifs.push((elif_cond, body_cmds));
                 ctx_elifs.push(ctx_elif);
             }
 
             let mut e_cmds = Vec::new();
             let ctx_else = if let Some(body) = else_body {
-                lower_block(&body, &mut e_cmds, ctx.clone(), sm, file, opts)?
+                lower_block(body, &mut e_cmds, ctx.clone(), sm, file, opts)?
             } else {
                 ctx.clone()
             };
@@ -228,17 +295,17 @@ pub(super) fn lower_stmt<'a>(
                 let mut body_cmds = Vec::new();
 
                 for p in &arm.patterns {
-                    if matches!(p, ast::Pattern::Wildcard) {
+                    if matches!(p.node, ast::Pattern::Wildcard) {
                         has_wildcard = true;
                     }
                 }
 
-                let ctx_arm = lower_block(&arm.body, &mut body_cmds, ctx.clone(), sm, file, opts)?;
+                let ctx_arm = lower_block(arm.body, &mut body_cmds, ctx.clone(), sm, file, opts)?;
 
                 let patterns = arm
                     .patterns
                     .into_iter()
-                    .map(|p| match p {
+                    .map(|p| match p.node {
                         ast::Pattern::Literal(s) => ir::Pattern::Literal(s),
                         ast::Pattern::Glob(s) => ir::Pattern::Glob(s),
                         ast::Pattern::Wildcard => ir::Pattern::Wildcard,
@@ -272,7 +339,7 @@ pub(super) fn lower_stmt<'a>(
         ast::StmtKind::While { cond, body } => {
             let cond_val = lower_expr(cond, out, &mut ctx, sm, file)?;
             let mut lower_body = Vec::new();
-            let ctx_body = lower_block(&body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
             out.push(ir::Cmd::While {
                 cond: cond_val,
                 body: lower_body,
@@ -325,7 +392,7 @@ pub(super) fn lower_stmt<'a>(
                         Some(m) => {
                             match &m.node {
                                 // Strict validation: maxdepth must be a non-negative integer literal.
-                                // Note: `ast::ExprKind::Number` contains a `u32`, so it is strictly non-negative.
+                                // Note: `ast::ExprKind::Number` is unsigned, so it is strictly non-negative.
                                 // Negative values (like `-1`) are parsed as unary expressions (not `Number` literals)
                                 // and are correctly rejected here by the catch-all arm.
                                 ast::ExprKind::Number(_) => {
@@ -356,7 +423,7 @@ pub(super) fn lower_stmt<'a>(
             };
             
             let mut lower_body = Vec::new();
-            let ctx_body = lower_block(&body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
 
             out.push(ir::Cmd::For {
                 var: var.node,
@@ -371,17 +438,17 @@ pub(super) fn lower_stmt<'a>(
             let mut each_line_seg = None;
             
             for (i, seg) in segments.iter().enumerate() {
-                if let ast::PipeSegment::EachLine(var, body) = &seg.node {
+                if let ast::PipeSegment::EachLine(var, fd, body) = &seg.node {
                     if i != last_idx {
                         let msg = "each_line must be the last segment of a pipeline";
                         let loc = resolve_span(seg.span, sm, file, opts.diag_base_dir.as_deref());
                         return Err(CompileError::new(msg).with_location(loc));
                     }
-                    each_line_seg = Some((var, body));
+                    each_line_seg = Some((var, *fd, body));
                 }
             }
 
-            if let Some((var, body)) = each_line_seg {
+            if let Some((var, fd, body)) = each_line_seg {
                 // Determine producer segments (all but the last)
                 let producer_segments = &segments[0..last_idx];
                 if producer_segments.is_empty() {
@@ -425,11 +492,12 @@ pub(super) fn lower_stmt<'a>(
                 // Yes, declare it defined.
                 ctx.insert(&var.node);
                 
-                let ctx_after_body = lower_block(body, &mut body_cmds, ctx, sm, file, opts)?;
+                let ctx_after_body = lower_block(body.clone(), &mut body_cmds, ctx, sm, file, opts)?;
                 
                 out.push(ir::Cmd::PipeEachLine {
                     producer: Box::new(producer_cmd),
                     var: var.node.clone(),
+                    fd,
                     body: body_cmds,
                 });
                 
@@ -446,11 +514,13 @@ pub(super) fn lower_stmt<'a>(
                          match &seg.node {
                             ast::PipeSegment::Run(run_call) => {
                                  let (args, allow_fail) = lower_run_call_args(run_call, out, &mut ctx, sm, file, opts)?;
-                                 lowered_segments.push((args, allow_fail));
+                                 let stderr = lower_segment_stderr(run_call, out, &mut ctx, sm, file)?;
+                                 lowered_segments.push((args, allow_fail, stderr));
                             }
                             ast::PipeSegment::Sudo(run_call) => {
                                  let (args, allow_fail) = lower_sudo_call_args(run_call, out, &mut ctx, sm, file, opts)?;
-                                 lowered_segments.push((args, allow_fail));
+                                 let stderr = lower_segment_stderr(run_call, out, &mut ctx, sm, file)?;
+                                 lowered_segments.push((args, allow_fail, stderr));
                             }
                             _ => unreachable!(),
                          }
@@ -462,39 +532,48 @@ pub(super) fn lower_stmt<'a>(
                     
                     for seg in segments {
                         let mut block_cmds = Vec::new(); 
+                        let mut seg_stderr = None;
                         let seg_loc = if opts.include_diagnostics {
                             Some(resolve_span(seg.span, sm, file, opts.diag_base_dir.as_deref()))
                         } else {
                             None
                         };
     
-                        match &seg.node {
+                        match seg.node {
                             ast::PipeSegment::Block(stmts) => {
                                  lower_block(stmts, &mut block_cmds, ctx.clone(), sm, file, opts)?;
                             }
                             ast::PipeSegment::Run(run_call) => {
                                 let mut seg_ctx = ctx.clone();
-                                let (args, allow_fail) = lower_run_call_args(run_call, out, &mut seg_ctx, sm, file, opts)?;
+                                let (args, allow_fail) = lower_run_call_args(&run_call, out, &mut seg_ctx, sm, file, opts)?;
+                                seg_stderr = lower_segment_stderr(&run_call, out, &mut seg_ctx, sm, file)?;
                                 
                                 block_cmds.push(ir::Cmd::Exec {
                                     args,
                                     allow_fail,
                                     loc: seg_loc, 
+                                    stdin: None,
+                                    env: Vec::new(),
+                                    cwd: None,
                                 });
                             }
                             ast::PipeSegment::Sudo(run_call) => {
                                 let mut seg_ctx = ctx.clone();
-                                let (args, allow_fail) = lower_sudo_call_args(run_call, out, &mut seg_ctx, sm, file, opts)?;
+                                let (args, allow_fail) = lower_sudo_call_args(&run_call, out, &mut seg_ctx, sm, file, opts)?;
+                                seg_stderr = lower_segment_stderr(&run_call, out, &mut seg_ctx, sm, file)?;
                                 
                                 block_cmds.push(ir::Cmd::Exec {
                                     args,
                                     allow_fail,
                                     loc: seg_loc, 
+                                    stdin: None,
+                                    env: Vec::new(),
+                                    cwd: None,
                                 });
                             }
                             ast::PipeSegment::EachLine(..) => unreachable!("EachLine handled above"),
                         }
-                        lower_segments.push(block_cmds);
+                        lower_segments.push((block_cmds, seg_stderr));
                     }
                     out.push(ir::Cmd::PipeBlocks(lower_segments, loc));
                 }
@@ -509,6 +588,155 @@ pub(super) fn lower_stmt<'a>(
             out.push(ir::Cmd::Continue);
             Ok(ctx)
         }
+        ast::StmtKind::ReturnValues(values) => {
+            if ctx.returns_bool {
+                let msg = "a `-> bool` function cannot return several values";
+                return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), msg, stmt.span)));
+            }
+            // Values go back through global registers the caller copies right
+            // after the call, so no subshell is needed on either target.
+            for (i, v) in values.into_iter().enumerate() {
+                let val = lower_expr(v, out, &mut ctx, sm, file)?;
+                out.push(ir::Cmd::Assign(format!("__sh2_ret{}", i + 1), val, None));
+            }
+            out.push(ir::Cmd::Return(None));
+            Ok(ctx)
+        }
+        ast::StmtKind::LetValues { names, value, tuple: true }
+            if !matches!(&value.node, ast::ExprKind::Call { name, .. } if !crate::builtins::is_builtin(name)) =>
+        {
+            let span = value.span;
+            let pattern = names.iter().map(|n| n.node.as_str()).collect::<Vec<_>>().join(", ");
+            // split_once() reads its operands twice, so they are evaluated once
+            // into temporaries unless they are literals.
+            let stash = |val: ir::Val, out: &mut Vec<ir::Cmd>, ctx: &mut LoweringContext| match val {
+                ir::Val::Literal(_) | ir::Val::Number(_) => val,
+                _ => {
+                    ctx.tmp_counter += 1;
+                    let tmp = format!("__sh2_tmp_{}", ctx.tmp_counter);
+                    out.push(ir::Cmd::Assign(tmp.clone(), val, None));
+                    ir::Val::Var(tmp)
+                }
+            };
+            let values = match value.node {
+                ast::ExprKind::Call { name, args, .. } if name == "split_once" => {
+                    if args.len() != 2 {
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "split_once() requires exactly 2 arguments (text, separator)", span)));
+                    }
+                    if names.len() != 2 {
+                        let msg = format!("split_once() yields 2 values, but {} names are given", names.len());
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &msg, span)));
+                    }
+                    let mut iter = args.into_iter();
+                    let text = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
+                    let text = stash(text, out, &mut ctx);
+                    let sep = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
+                    let sep = stash(sep, out, &mut ctx);
+                    // Without the separator, the whole text is the head and the tail is empty.
+                    vec![
+                        ir::Val::Call { name: "before".to_string(), args: vec![text.clone(), sep.clone()] },
+                        ir::Val::Call { name: "after".to_string(), args: vec![text, sep] },
+                    ]
+                }
+                ast::ExprKind::List(items) => {
+                    if items.len() != names.len() {
+                        let msg = format!("list has {} items, but {} names are given", items.len(), names.len());
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &msg, span)));
+                    }
+                    items
+                        .into_iter()
+                        .map(|item| lower_expr(item, out, &mut ctx, sm, file))
+                        .collect::<Result<Vec<_>, _>>()?
+                }
+                node => {
+                    let val = lower_expr(ast::Expr { node, span }, out, &mut ctx, sm, file)?;
+                    let is_list = matches!(&val, ir::Val::Split { .. } | ir::Val::Lines(_) | ir::Val::Args)
+                        || matches!(&val, ir::Val::Var(n) if ctx.is_list_var(n));
+                    if !is_list {
+                        let msg = format!("`let ({})` needs a list, split_once(...) or a function returning {} values", pattern, names.len());
+                        return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &msg, span)));
+                    }
+                    ctx.tmp_counter += 1;
+                    let tmp = format!("__sh2_tmp_list_{}", ctx.tmp_counter);
+                    ctx.insert_list_var(&tmp);
+                    out.push(ir::Cmd::Assign(tmp.clone(), val, None));
+                    // The arity is only known at run time: fail like any other
+                    // runtime error rather than binding missing items as empty.
+                    let msg = format!("Error: let ({}): expected {} values, got ", pattern, names.len());
+                    out.push(ir::Cmd::If {
+                        cond: ir::Val::Compare {
+                            left: Box::new(ir::Val::Count(Box::new(ir::Val::Var(tmp.clone())))),
+                            op: ir::CompareOp::NotEq,
+                            right: Box::new(ir::Val::Number(names.len() as u64)),
+                        },
+                        then_body: vec![
+                            ir::Cmd::PrintErr(ir::Val::Concat(
+                                Box::new(ir::Val::Literal(msg)),
+                                Box::new(ir::Val::Count(Box::new(ir::Val::Var(tmp.clone())))),
+                            )),
+                            ir::Cmd::Exit(Some(ir::Val::Number(1))),
+                        ],
+                        elifs: Vec::new(),
+                        else_body: Vec::new(),
+                    });
+                    (0..names.len())
+                        .map(|i| ir::Val::Index {
+                            list: Box::new(ir::Val::Var(tmp.clone())),
+                            index: Box::new(ir::Val::Number(i as u64)),
+                            optional: false,
+                        })
+                        .collect()
+                }
+            };
+            for (name, val) in names.into_iter().zip(values) {
+                ctx.forget(&name.node);
+                out.push(ir::Cmd::Assign(name.node, val, loc.clone()));
+            }
+            Ok(ctx)
+        }
+        ast::StmtKind::LetValues { names, value, .. } => {
+            let span = value.span;
+            let ast::ExprKind::Call { name: mut func, args, options } = value.node else {
+                let msg = format!("`let` with {} names needs a call to a function that returns {} values", names.len(), names.len());
+                return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &msg, span)));
+            };
+            if !ctx.user_funcs.contains(&func)
+                && let Some(Callee::Func(target)) = ctx.call_through(&func, args.len(), span, sm, file)?
+            {
+                func = target;
+            }
+            match ctx.sigs.multi.get(&func) {
+                Some(&n) if n == names.len() => {}
+                Some(&n) => {
+                    let msg = format!("function '{}' returns {} values, but {} names are given", crate::names::display(&func), n, names.len());
+                    return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &msg, span)));
+                }
+                None => {
+                    let msg = format!(
+                        "function '{}' does not return multiple values; use `let x = {}(...)`",
+                        crate::names::display(&func),
+                        crate::names::display(&func)
+                    );
+                    return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &msg, span)));
+                }
+            }
+            let call = ast::Stmt { node: ast::StmtKind::Call { name: func, args, options }, span: stmt.span };
+            let mut ctx = lower_stmt(call, out, ctx, sm, file, opts)?;
+            for (i, name) in names.into_iter().enumerate() {
+                ctx.forget(&name.node);
+                out.push(ir::Cmd::Assign(name.node, ir::Val::Var(format!("__sh2_ret{}", i + 1)), None));
+            }
+            Ok(ctx)
+        }
+        ast::StmtKind::Return(e) if ctx.returns_bool => {
+            let Some(e) = e else {
+                let msg = "`return` in a `-> bool` function needs a value, e.g. `return true`";
+                return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), msg, stmt.span)));
+            };
+            let val = lower_expr(e, out, &mut ctx, sm, file)?;
+            out.push(ir::Cmd::ReturnBool(val));
+            Ok(ctx)
+        }
         ast::StmtKind::Return(e) => {
             let val = e.map(|x| lower_expr(x, out, &mut ctx, sm, file)).transpose()?;
             out.push(ir::Cmd::Return(val));
@@ -528,7 +756,7 @@ pub(super) fn lower_stmt<'a>(
                 })
                 .collect::<Result<Vec<_>, _>>()?;
             let mut lower_body = Vec::new();
-            let ctx_body = lower_block(&body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
             out.push(ir::Cmd::WithEnv {
                 bindings: lowered_bindings,
                 body: lower_body,
@@ -537,10 +765,10 @@ pub(super) fn lower_stmt<'a>(
         }
         ast::StmtKind::AndThen { left, right } => {
             let mut lower_left = Vec::new();
-            let ctx_left = lower_block(&left, &mut lower_left, ctx.clone(), sm, file, opts)?;
+            let ctx_left = lower_block(left, &mut lower_left, ctx.clone(), sm, file, opts)?;
 
             let mut lower_right = Vec::new();
-            let ctx_right = lower_block(&right, &mut lower_right, ctx_left.clone(), sm, file, opts)?;
+            let ctx_right = lower_block(right, &mut lower_right, ctx_left.clone(), sm, file, opts)?;
 
             out.push(ir::Cmd::AndThen {
                 left: lower_left,
@@ -550,10 +778,10 @@ pub(super) fn lower_stmt<'a>(
         }
         ast::StmtKind::OrElse { left, right } => {
             let mut lower_left = Vec::new();
-            let ctx_left = lower_block(&left, &mut lower_left, ctx.clone(), sm, file, opts)?;
+            let ctx_left = lower_block(left, &mut lower_left, ctx.clone(), sm, file, opts)?;
 
             let mut lower_right = Vec::new();
-            let ctx_right = lower_block(&right, &mut lower_right, ctx_left.clone(), sm, file, opts)?;
+            let ctx_right = lower_block(right, &mut lower_right, ctx_left.clone(), sm, file, opts)?;
 
             out.push(ir::Cmd::OrElse {
                 left: lower_left,
@@ -572,17 +800,108 @@ pub(super) fn lower_stmt<'a>(
             }
             let lowered_path = lower_expr(path, out, &mut ctx, sm, file)?;
             let mut lower_body = Vec::new();
-            let ctx_body = lower_block(&body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
             out.push(ir::Cmd::WithCwd {
                 path: lowered_path,
                 body: lower_body,
             });
             Ok(ctx_body)
         }
+        ast::StmtKind::WithDeadline { duration, body } => {
+            let literal = match &duration.node {
+                ast::ExprKind::Literal(s) => Some(s.clone()),
+                ast::ExprKind::Number(n) => Some(n.to_string()),
+                _ => None,
+            };
+            if let Some(text) = literal
+                && !is_duration(&text)
+            {
+                return Err(CompileError::new(sm.format_diagnostic(
+                    file,
+                    opts.diag_base_dir.as_deref(),
+                    &format!("invalid deadline {:?}: expected whole seconds or a number with s, m, h or d (e.g. \"30s\", \"5m\")", text),
+                    duration.span,
+                )));
+            }
+            let lowered_duration = lower_expr(duration, out, &mut ctx, sm, file)?;
+            let mut lower_body = Vec::new();
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            out.push(ir::Cmd::WithDeadline {
+                duration: lowered_duration,
+                body: lower_body,
+                loc,
+            });
+            Ok(ctx_body)
+        }
+        ast::StmtKind::WithLimits { limit, body } => {
+            let limit = match limit {
+                ast::Limit::Nice(n) => ir::Limit::Nice(n),
+                ast::Limit::Ionice { class, level } => {
+                    let class = match class.as_str() {
+                        "realtime" => 1,
+                        "best-effort" => 2,
+                        _ => 3,
+                    };
+                    ir::Limit::Ionice { class, level }
+                }
+                ast::Limit::Ulimit(limits) => {
+                    let posix = opts.target == crate::codegen::TargetShell::Posix;
+                    let limits = limits
+                        .into_iter()
+                        .map(|(name, value)| {
+                            let flag = ast::ULIMITS.iter().find(|(n, _)| *n == name).map_or('n', |(_, f)| *f);
+                            // Sizes are given in bytes; bash counts KiB, and POSIX sh counts
+                            // `fsize` and `core` in 512-byte blocks.
+                            let unit = match flag {
+                                'f' | 'c' if posix => 512,
+                                'f' | 'c' | 's' | 'v' => 1024,
+                                _ => 1,
+                            };
+                            let value = value.map_or("unlimited".to_string(), |v| (v as u64).div_ceil(unit).to_string());
+                            (flag, value)
+                        })
+                        .collect();
+                    ir::Limit::Ulimit(limits)
+                }
+            };
+            let mut lower_body = Vec::new();
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            out.push(ir::Cmd::WithLimits { limit, body: lower_body });
+            Ok(ctx_body)
+        }
+        ast::StmtKind::WithUmask { mask, body } => {
+            let digits = match &mask.node {
+                ast::ExprKind::Literal(s) => s.clone(),
+                ast::ExprKind::Number(n) => n.to_string(),
+                _ => String::new(),
+            };
+            if !(1..=4).contains(&digits.len()) || !digits.chars().all(|c| ('0'..='7').contains(&c)) {
+                return Err(CompileError::new(sm.format_diagnostic(
+                    file,
+                    opts.diag_base_dir.as_deref(),
+                    "umask must be an octal literal such as 022 or \"0077\"",
+                    mask.span,
+                )));
+            }
+            if let Some(span) = crate::hooks::find_return(&body).or_else(|| find_loop_exit(&body)) {
+                return Err(CompileError::new(sm.format_diagnostic(
+                    file,
+                    opts.diag_base_dir.as_deref(),
+                    "`return`, `break` and `continue` cannot be used in a `with umask(...)` block, because the previous umask would not be restored",
+                    span,
+                )));
+            }
+            ctx.tmp_counter += 1;
+            let saved = format!("__sh2_umask_{}", ctx.tmp_counter);
+            let mut lower_body = Vec::new();
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            out.push(ir::Cmd::WithUmask { mask: format!("{:0>3}", digits), saved, body: lower_body });
+            Ok(ctx_body)
+        }
         ast::StmtKind::WithLog { path, append, body } => {
             let lowered_path = lower_expr(path, out, &mut ctx, sm, file)?;
             let mut lower_body = Vec::new();
-            let ctx_body = lower_block(&body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
             out.push(ir::Cmd::WithLog {
                 path: lowered_path,
                 append,
@@ -653,13 +972,17 @@ pub(super) fn lower_stmt<'a>(
             }
             Ok(ctx)
         }
-        ast::StmtKind::ShBlock(lines) => {
+        ast::StmtKind::ShBlock { lines, bridge } => {
             for s in lines {
                 out.push(ir::Cmd::RawLine { line: s, loc: loc.clone() });
             }
+            // Whatever the shell left in an exported variable is a plain string.
+            for name in bridge.iter().flat_map(|b| &b.exports) {
+                ctx.forget(name);
+            }
             Ok(ctx)
         }
-        ast::StmtKind::Call { name, args } => {
+        ast::StmtKind::Call { name, args, options } => {
             if name == "save_envfile" {
                 if args.len() != 2 {
                     return Err(CompileError::new(sm.format_diagnostic(
@@ -673,6 +996,22 @@ pub(super) fn lower_stmt<'a>(
                 let path = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
                 let env = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
                 out.push(ir::Cmd::SaveEnvfile { path, env });
+            } else if name == "breakpoint" {
+                if !args.is_empty() || !options.is_empty() {
+                    return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "breakpoint() takes no arguments", stmt.span)));
+                }
+                // Without --debug a breakpoint compiles to nothing.
+                if opts.debug {
+                    out.push(super::debug_hook(stmt.span, &ctx, sm, file, true));
+                }
+            } else if name == "dump_vars" {
+                if !args.is_empty() || !options.is_empty() {
+                    return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "dump_vars() takes no arguments", stmt.span)));
+                }
+                out.push(ir::Cmd::DumpVars {
+                    loc: super::resolve_span(stmt.span, sm, file, opts.diag_base_dir.as_deref()),
+                    vars: ctx.declared_vars.clone(),
+                });
             } else if name == "load_envfile" {
                 return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "load_envfile() returns a value; use it in an expression (e.g., let m = load_envfile(\"env.meta\"))", stmt.span)));
             } else if name == "which" {
@@ -712,6 +1051,8 @@ pub(super) fn lower_stmt<'a>(
                     path,
                     content,
                     append: true,
+                    atomic: false,
+                    mode: None,
                 });
             } else if name == "write_file" {
                 if args.len() < 2 || args.len() > 3 {
@@ -735,11 +1076,140 @@ pub(super) fn lower_stmt<'a>(
                 } else {
                     false
                 };
+                let mut atomic = false;
+                let mut mode = None;
+                for opt in options {
+                    match opt.name.as_str() {
+                        "atomic" => match opt.value.node {
+                            ast::ExprKind::Bool(b) => atomic = b,
+                            _ => {
+                                return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "write_file: atomic must be boolean literal", opt.value.span)));
+                            }
+                        },
+                        "mode" => {
+                            let digits = match &opt.value.node {
+                                ast::ExprKind::Literal(s) => s.clone(),
+                                ast::ExprKind::Number(n) => n.to_string(),
+                                _ => String::new(),
+                            };
+                            if !(3..=4).contains(&digits.len()) || !digits.chars().all(|c| ('0'..='7').contains(&c)) {
+                                return Err(CompileError::new(sm.format_diagnostic(
+                                    file,
+                                    opts.diag_base_dir.as_deref(),
+                                    "write_file: mode must be an octal permission literal such as 0644 or \"0600\"",
+                                    opt.value.span,
+                                )));
+                            }
+                            mode = Some(digits);
+                        }
+                        _ => {
+                            return Err(CompileError::new(sm.format_diagnostic(
+                                file,
+                                opts.diag_base_dir.as_deref(),
+                                &format!("Unknown option {:?} for write_file(); supported options are atomic and mode", opt.name),
+                                opt.span,
+                            )));
+                        }
+                    }
+                }
+                if atomic && append {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "write_file: atomic=true replaces the whole file and cannot be combined with append",
+                        stmt.span,
+                    )));
+                }
                 out.push(ir::Cmd::WriteFile {
                     path,
                     content,
                     append,
+                    atomic,
+                    mode,
                 });
+            } else if matches!(name.as_str(), "mkdirs" | "copy" | "move" | "remove") && !ctx.user_funcs.contains(&name) {
+                // These names are common enough that a user-defined function of the same name wins.
+                let (op, usage, arity, allowed): (ir::FsOp, &str, usize, &[&str]) = match name.as_str() {
+                    "mkdirs" => (ir::FsOp::Mkdirs, "mkdirs() requires exactly 1 argument (path)", 1, &[]),
+                    "copy" => (ir::FsOp::Copy, "copy() requires exactly 2 arguments (src, dst)", 2, &["recursive", "force"]),
+                    "move" => (ir::FsOp::Move, "move() requires exactly 2 arguments (src, dst)", 2, &["force"]),
+                    _ => (ir::FsOp::Remove, "remove() requires exactly 1 argument (path)", 1, &["recursive", "force"]),
+                };
+                if args.len() != arity {
+                    return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), usage, stmt.span)));
+                }
+                let mut recursive = false;
+                let mut force = false;
+                for opt in options {
+                    if allowed.is_empty() {
+                        return Err(CompileError::new(sm.format_diagnostic(
+                            file,
+                            opts.diag_base_dir.as_deref(),
+                            &format!("{}() takes no options; got {:?}", name, opt.name),
+                            opt.span,
+                        )));
+                    }
+                    if !allowed.contains(&opt.name.as_str()) {
+                        return Err(CompileError::new(sm.format_diagnostic(
+                            file,
+                            opts.diag_base_dir.as_deref(),
+                            &format!("Unknown option {:?} for {}(); supported options are {}", opt.name, name, allowed.join(" and ")),
+                            opt.span,
+                        )));
+                    }
+                    let b = match opt.value.node {
+                        ast::ExprKind::Bool(b) => b,
+                        _ => {
+                            return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &format!("{}: {} must be boolean literal", name, opt.name), opt.value.span)));
+                        }
+                    };
+                    if opt.name == "recursive" {
+                        recursive = b;
+                    } else {
+                        force = b;
+                    }
+                }
+                let mut fs_args = Vec::new();
+                for a in args {
+                    fs_args.push(lower_expr(a, out, &mut ctx, sm, file)?);
+                }
+                out.push(ir::Cmd::Fs { op, args: fs_args, recursive, force, loc });
+            } else if name == "replace_in_file" || name == "append_line_if_missing" {
+                let (arity, usage) = if name == "replace_in_file" {
+                    (3, "replace_in_file() requires exactly 3 arguments (path, pattern, replacement)")
+                } else {
+                    (2, "append_line_if_missing() requires exactly 2 arguments (path, line)")
+                };
+                if args.len() != arity {
+                    return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), usage, stmt.span)));
+                }
+                let mut backup = false;
+                for opt in options {
+                    if opt.name != "backup" {
+                        return Err(CompileError::new(sm.format_diagnostic(
+                            file,
+                            opts.diag_base_dir.as_deref(),
+                            &format!("Unknown option {:?} for {}(); only backup= is supported", opt.name, name),
+                            opt.span,
+                        )));
+                    }
+                    match opt.value.node {
+                        ast::ExprKind::Bool(b) => backup = b,
+                        _ => {
+                            return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &format!("{}: backup must be boolean literal", name), opt.value.span)));
+                        }
+                    }
+                }
+                let mut iter = args.into_iter();
+                let path = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
+                if name == "replace_in_file" {
+                    let pattern = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
+                    let replacement = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
+                    out.push(ir::Cmd::ReplaceInFile { path, pattern, replacement, backup, loc });
+                } else {
+                    let line = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
+                    out.push(ir::Cmd::AppendLineIfMissing { path, line, backup, loc });
+                }
             } else if name == "read_file" {
                 return Err(CompileError::new(sm.format_diagnostic(
                     file,
@@ -801,32 +1271,140 @@ pub(super) fn lower_stmt<'a>(
                     msg,
                     timestamp,
                 });
+            } else if name == "service_start" {
+                if args.len() < 2 || args.len() > 3 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "service_start() requires 2 or 3 arguments (pidfile, run(...), [logfile])",
+                        stmt.span,
+                    )));
+                }
+                let mut iter = args.into_iter();
+                let pidfile = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
+                let cmd_expr = iter.next().unwrap();
+                let ast::ExprKind::Run(run_call) = &cmd_expr.node else {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "service_start() only accepts run(...) as the command",
+                        cmd_expr.span,
+                    )));
+                };
+                let (cmd_args, allow_fail) = lower_run_call_args(run_call, out, &mut ctx, sm, file, opts)?;
+                if allow_fail {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "allow_fail=true is not supported in service_start(); the service runs in the background",
+                        cmd_expr.span,
+                    )));
+                }
+                let log = iter
+                    .next()
+                    .map(|l| lower_expr(l, out, &mut ctx, sm, file))
+                    .transpose()?;
+                out.push(ir::Cmd::ServiceStart {
+                    pidfile,
+                    args: cmd_args,
+                    log,
+                    loc,
+                });
+            } else if name == "service_stop" {
+                if args.is_empty() || args.len() > 2 {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        "service_stop() requires 1 or 2 arguments (pidfile, [grace_seconds])",
+                        stmt.span,
+                    )));
+                }
+                let mut iter = args.into_iter();
+                let pidfile = lower_expr(iter.next().unwrap(), out, &mut ctx, sm, file)?;
+                let grace = iter
+                    .next()
+                    .map(|g| lower_expr(g, out, &mut ctx, sm, file))
+                    .transpose()?;
+                out.push(ir::Cmd::ServiceStop { pidfile, grace, loc });
+            } else if name == "wait_for_port" {
+                let (host, port, timeout, interval) =
+                    lower_wait_for_port_args(args, options, stmt.span, out, &mut ctx, sm, file)?;
+                out.push(ir::Cmd::WaitForPort { host, port, timeout, interval, loc });
+            } else if name == "service_running" {
+                return Err(CompileError::new(sm.format_diagnostic(
+                    file,
+                    opts.diag_base_dir.as_deref(),
+                    "service_running() returns a boolean; use it in a condition (e.g., if service_running(\"app.pid\") { ... })",
+                    stmt.span,
+                )));
             } else {
                 // Generic call (Command)
+                if let Some(opt) = options.first() {
+                    return Err(CompileError::new(sm.format_diagnostic(
+                        file,
+                        opts.diag_base_dir.as_deref(),
+                        &format!("Named argument {:?} is not supported when calling {}()", opt.name, name),
+                        opt.span,
+                    )));
+                }
+                let mut name = name;
+                if !ctx.user_funcs.contains(&name) {
+                    match ctx.call_through(&name, args.len(), stmt.span, sm, file)? {
+                        Some(Callee::Func(target)) => name = target,
+                        Some(Callee::Var) => {
+                            let mut cmd_args = vec![ir::Val::CalleeVar(name)];
+                            for a in args {
+                                cmd_args.extend(lower_words(a, out, &mut ctx, sm, file)?);
+                            }
+                            out.push(ir::Cmd::Exec { args: cmd_args, allow_fail: false, loc, stdin: None, env: Vec::new(), cwd: None });
+                            return Ok(ctx);
+                        }
+                        None => {}
+                    }
+                }
+                ctx.check_extern_arity(&name, args.len(), stmt.span, sm, file)?;
+                if ctx.sigs.bools.contains(&name) {
+                    let msg = format!(
+                        "function '{}' returns a bool; use it in a condition (e.g. `if {}(...)`) or `let ok = {}(...)`",
+                        crate::names::display(&name),
+                        crate::names::display(&name),
+                        crate::names::display(&name)
+                    );
+                    return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &msg, stmt.span)));
+                }
                 let mut cmd_args = vec![ir::Val::Literal(name)];
                 for a in args {
-                    cmd_args.push(lower_expr(a, out, &mut ctx, sm, file)?);
+                    cmd_args.extend(lower_words(a, out, &mut ctx, sm, file)?);
                 }
                 out.push(ir::Cmd::Exec {
                     args: cmd_args,
                     allow_fail: false,
                     loc,
+                    stdin: None,
+                    env: Vec::new(),
+                    cwd: None,
                 });
             }
             Ok(ctx)
         }
         ast::StmtKind::Subshell { body } => {
             let mut lower_body = Vec::new();
-            lower_block(&body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
             out.push(ir::Cmd::Subshell { body: lower_body });
             Ok(ctx)
         }
         ast::StmtKind::Group { body } => {
             let mut lower_body = Vec::new();
-            let ctx_body = lower_block(&body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
             out.push(ir::Cmd::Group { body: lower_body });
             Ok(ctx_body)
         }
+        ast::StmtKind::Unchecked { body } => {
+            let mut lower_body = Vec::new();
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            out.push(ir::Cmd::Unchecked { body: lower_body });
+            Ok(ctx_body)
+        }
         ast::StmtKind::WithRedirect {
             stdout,
             stderr,
@@ -834,7 +1412,7 @@ pub(super) fn lower_stmt<'a>(
             body,
         } => {
             let mut lowered_body = Vec::new();
-            let ctx_body = lower_block(&body, &mut lowered_body, ctx.clone(), sm, file, opts)?;
+            let ctx_body = lower_block(body, &mut lowered_body, ctx.clone(), sm, file, opts)?;
 
             let lower_output_target = |t: ast::RedirectOutputTarget, out: &mut Vec<ir::Cmd>, c: &mut LoweringContext| -> Result<ir::RedirectOutputTarget, CompileError> {
                  Ok(match t {
@@ -855,6 +1433,9 @@ pub(super) fn lower_stmt<'a>(
                         path: lower_expr(path, out, c, sm, file)?,
                     },
                     ast::RedirectInputTarget::HereDoc { content } => ir::RedirectInputTarget::HereDoc { content },
+                    ast::RedirectInputTarget::Text { value } => ir::RedirectInputTarget::Text {
+                        value: lower_expr(value, out, c, sm, file)?,
+                    },
                 })
             };
 
@@ -897,10 +1478,10 @@ pub(super) fn lower_stmt<'a>(
             catch_body,
         } => {
             let mut lower_try = Vec::new();
-            let ctx_try = lower_block(&try_body, &mut lower_try, ctx.clone(), sm, file, opts)?;
+            let ctx_try = lower_block(try_body, &mut lower_try, ctx.clone(), sm, file, opts)?;
 
             let mut lower_catch = Vec::new();
-            let ctx_catch = lower_block(&catch_body, &mut lower_catch, ctx.clone(), sm, file, opts)?;
+            let ctx_catch = lower_block(catch_body, &mut lower_catch, ctx.clone(), sm, file, opts)?;
 
             out.push(ir::Cmd::TryCatch {
                 try_body: lower_try,
@@ -927,19 +1508,21 @@ pub(super) fn lower_stmt<'a>(
         }
         ast::StmtKind::Exec(args) => {
             let exec_args = args.into_iter()
-                .map(|a| lower_expr(a, out, &mut ctx, sm, file))
-                .collect::<Result<Vec<_>, _>>()?;
+                .map(|a| lower_words(a, out, &mut ctx, sm, file))
+                .collect::<Result<Vec<_>, _>>()?
+                .concat();
             out.push(ir::Cmd::ExecReplace(exec_args, loc));
             Ok(ctx)
         }
         ast::StmtKind::Set { target, value } => {
             match target {
                 ast::LValue::Var(name) => {
+                    ctx.bind_func_ref(&name.node, &value);
                     let val = lower_expr(value, out, &mut ctx, sm, file)?;
                     
                     // List Inference
                     let is_list = match &val {
-                        ir::Val::List(_) | ir::Val::Split { .. } | ir::Val::Lines(_) => true,
+                        ir::Val::List(_) | ir::Val::Split { .. } | ir::Val::Lines(_) | ir::Val::Find { .. } | ir::Val::Lines0(_) => true,
                         ir::Val::Var(n) => ctx.is_list_var(n),
                         _ => false,
                     };
@@ -980,7 +1563,7 @@ pub(super) fn lower_stmt<'a>(
             body,
         } => {
             let mut lower_body = Vec::new();
-            let ctx_body = lower_block(&body, &mut lower_body, ctx.clone(), sm, file, opts)?;
+            let ctx_body = lower_block(body, &mut lower_body, ctx.clone(), sm, file, opts)?;
             out.push(ir::Cmd::ForMap {
                 key_var: key_var.node,
                 val_var: val_var.node,
@@ -994,3 +1577,53 @@ pub(super) fn lower_stmt<'a>(
         }
     }
 }
+
+/// `30`, `30s`, `5m`, `2h` or `1d`; mirrors the runtime check in `__sh2_duration_secs`.
+fn is_duration(text: &str) -> bool {
+    let digits = text.strip_suffix(['s', 'm', 'h', 'd']).unwrap_or(text);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// The first `break` or `continue` in `stmts` that would leave them, ignoring
+/// those that belong to a loop nested inside.
+fn find_loop_exit(stmts: &[ast::Stmt]) -> Option<crate::span::Span> {
+    use ast::StmtKind;
+    stmts.iter().find_map(|stmt| match &stmt.node {
+        StmtKind::Break | StmtKind::Continue => Some(stmt.span),
+        StmtKind::If { then_body, elifs, else_body, .. } => find_loop_exit(then_body)
+            .or_else(|| elifs.iter().find_map(|elif| find_loop_exit(&elif.body)))
+            .or_else(|| else_body.as_deref().and_then(find_loop_exit)),
+        StmtKind::Case { arms, .. } => arms.iter().find_map(|arm| find_loop_exit(&arm.body)),
+        StmtKind::TryCatch { try_body: left, catch_body: right }
+        | StmtKind::AndThen { left, right }
+        | StmtKind::OrElse { left, right } => find_loop_exit(left).or_else(|| find_loop_exit(right)),
+        StmtKind::WithEnv { body, .. }
+        | StmtKind::WithLog { body, .. }
+        | StmtKind::WithUmask { body, .. }
+        | StmtKind::Group { body }
+        | StmtKind::Unchecked { body } => find_loop_exit(body),
+        _ => None,
+    })
+}
+
+/// Lower the optional `stderr=<target>` suffix of a run/sudo pipeline segment.
+fn lower_segment_stderr(
+    run_call: &ast::RunCall,
+    out: &mut Vec<ir::Cmd>,
+    ctx: &mut LoweringContext,
+    sm: &SourceMap,
+    file: &str,
+) -> Result<Option<ir::RedirectOutputTarget>, CompileError> {
+    let Some(target) = run_call.stderr.as_deref() else {
+        return Ok(None);
+    };
+    Ok(Some(match &target.node {
+        ast::RedirectOutputTarget::File { path, append } => ir::RedirectOutputTarget::File {
+            path: lower_expr(path.clone(), out, ctx, sm, file)?,
+            append: *append,
+        },
+        ast::RedirectOutputTarget::ToStdout => ir::RedirectOutputTarget::ToStdout,
+        // The parser only accepts file(...) and to_stdout() for segment redirects.
+        _ => return Err(CompileError::internal("unexpected pipeline segment stderr target", ctx.opts().target)),
+    }))
+}
@@ -4,7 +4,7 @@ use crate::span::SourceMap;
 use crate::error::CompileError;
 use crate::sudo::SudoSpec;
 use super::{LoweringContext, LowerOptions};
-use super::expr::lower_expr;
+use super::expr::{lower_expr, lower_words};
 
 pub(super) fn lower_run_call_args<'a>(
     run_call: &ast::RunCall,
@@ -17,13 +17,16 @@ pub(super) fn lower_run_call_args<'a>(
     let lowered_args = run_call
         .args
         .iter()
-        .map(|a| lower_expr(a.clone(), out, ctx, sm, file))
-        .collect::<Result<Vec<_>, _>>()?;
+        .map(|a| lower_words(a.clone(), out, ctx, sm, file))
+        .collect::<Result<Vec<_>, _>>()?
+        .concat();
 
     let mut allow_fail = false;
     for opt in &run_call.options {
         if opt.name == "shell" {
              return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), "shell option is not supported in run(...); use sh(...) for raw shell code", opt.span)));
+        } else if opt.name == "stdin" || opt.name == "env" || opt.name == "cwd" {
+             return Err(CompileError::new(sm.format_diagnostic(file, opts.diag_base_dir.as_deref(), &format!("{}= is only supported on a standalone run(...) statement; use a with block around the pipeline instead", opt.name), opt.span)));
         } else if opt.name == "allow_fail" {
              if let ast::ExprKind::Bool(b) = opt.value.node {
                  allow_fail = b;
@@ -67,7 +70,7 @@ pub(super) fn lower_sudo_command<'a>(
 
     // Add positional args (command + args), lowered
     for arg in args {
-        argv.push(lower_expr(arg, out, ctx, sm, file)?);
+        argv.extend(lower_words(arg, out, ctx, sm, file)?);
     }
     
     // Extract allow_fail check from spec (it handles the boolean logic)
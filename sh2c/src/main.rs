@@ -1,10 +1,15 @@
 use sh2c::driver::{self, CompileOptions, DriverError, Mode};
 use sh2c::codegen::TargetShell;
+use sh2c::highlight::{self, SyntaxFormat};
+use std::path::{Path, PathBuf};
 use std::process;
 
 struct Config {
-    filename: String,
+    inputs: Vec<String>,
     options: CompileOptions,
+    timings: bool,
+    /// Write `<stem>.sh` per input here (`--out-dir`) instead of one output.
+    out_dir: Option<PathBuf>,
 }
 
 struct CliError {
@@ -29,23 +34,109 @@ impl CliError {
 
 fn usage_text() -> &'static str {
     "Usage: sh2c [flags] <script.sh2> [flags]\n\
+     \x20      sh2c [flags] --out-dir <dir> <script.sh2|dir>...\n\
+     \x20      sh2c explain <code>    Describe an error code such as E0200\n\
+     \x20      sh2c doc --man <script.sh2> [-o <file>]\n\
+     \x20                             Write a man page from main's doc comment and flags\n\
+     \x20      sh2c verify [--targets bash,posix] [--stdin <file>] <script.sh2> [-- args...]\n\
+     \x20                             Run the script for each target and report differences\n\
+     \x20      sh2c emit-syntax <vim|tmLanguage|tree-sitter-queries> [-o <file>]\n\
+     \x20                             Write editor highlighting for the current keywords\n\
+     \x20      sh2c introspect --json Print keywords, statements and builtin arities as JSON\n\
      Flags:\n\
      \x20 --target <bash|posix>  Select output shell dialect (default: bash)\n\
      \x20 -o, --out <file>       Write output to file instead of stdout (auto-chmod +x)\n\
+     \x20 --out-dir <dir>        Compile each input (or each .sh2 in a dir) to <dir>/<name>.sh\n\
      \x20 --check                Check syntax and semantics without emitting code\n\
+     \x20 --entry <func>         Call this function instead of main (args as strings)\n\
      \x20 --no-diagnostics       Disable error location reporting and traps\n\
+     \x20 --deny-tainted-sh      Fail when input/env/args values reach sh() or exec()\n\
+     \x20 --sandbox <file>       Write a JSON manifest of the commands the script runs\n\
+     \x20 --sandbox-guard        With --sandbox, refuse undeclared commands at runtime\n\
+     \x20 --deterministic        Fail unless a second compilation is byte-identical\n\
+     \x20 --runtime-lib <file>   Source helpers from a shared runtime file (written too)\n\
+     \x20 --minify               Strip comments/blank lines and shorten internal names\n\
+     \x20 --emit-systemd         Also write <name>.service next to the output script\n\
+     \x20 --emit-cron <sched>    Also write a <name>-cron.sh wrapper and <name>.crontab line\n\
+     \x20 --timings              Print per-stage compile times to stderr\n\
+     \x20 --profile              Time each statement; report the slowest lines at exit\n\
+     \x20 --max-size <bytes>     Fail if the output is larger (suffixes K, M allowed)\n\
+     \x20 --warn-size <bytes>    Warn if the output is larger, with a size breakdown\n\
      \x20 --no-chmod-x           Do not set executable bit on output file\n\
      \x20 --chmod-x              Set executable bit on output file (default)\n\
      \x20 --emit-ast             Emit AST (debug)\n\
      \x20 --emit-ir              Emit IR (debug)\n\
      \x20 --emit-sh              Emit Shell (default)\n\
+     \x20 --emit-prelude-report  List included runtime helpers, their size and cause\n\
+     \x20 --emit-lib             Emit a sourceable file of functions (no main call)\n\
+     \x20 --audit-quoting        List injection-sensitive sites (sh, raw_arg, glob, eval)\n\
+     \x20 --fix                  Apply suggested fixes to the source files in place\n\
      \x20 -h, --help             Print help information\n\
      \x20 -V, --version          Print version information and exit"
 }
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("explain") {
+        match explain(&args[2..]) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("{}", e.msg);
+                process::exit(e.code);
+            }
+        }
+        return;
+    }
     
+    if args.get(1).map(String::as_str) == Some("doc") {
+        if let Err(e) = doc(&args[2..]) {
+            eprintln!("{}", e.msg);
+            if e.show_usage {
+                eprintln!("{}", usage_text());
+            }
+            process::exit(e.code);
+        }
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("emit-syntax") {
+        if let Err(e) = emit_syntax(&args[2..]) {
+            eprintln!("{}", e.msg);
+            if e.show_usage {
+                eprintln!("{}", usage_text());
+            }
+            process::exit(e.code);
+        }
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("introspect") {
+        match &args[2..] {
+            [flag] if flag == "--json" => print!("{}", sh2c::introspect::summary_json()),
+            _ => {
+                eprintln!("error: introspect requires --json, e.g. sh2c introspect --json");
+                eprintln!("{}", usage_text());
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("verify") {
+        let code = match verify(&args[2..]) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("{}", e.msg);
+                if e.show_usage {
+                    eprintln!("{}", usage_text());
+                }
+                e.code
+            }
+        };
+        process::exit(code);
+    }
+
     let config = match parse_args(args) {
         Ok(c) => c,
         Err(e) => {
@@ -71,18 +162,26 @@ fn parse_args(args: Vec<String>) -> Result<Config, CliError> {
         return Err(CliError::usage("error: missing input file"));
     }
 
-    let mut filename: Option<String> = None;
+    let mut inputs: Vec<String> = Vec::new();
+    let mut out_dir: Option<PathBuf> = None;
     let mut options = CompileOptions::default();
     // Default CLI behavior: chmod_x=true is documented default in usage text.
     // But library default is false. We should set it to true here for CLI parity.
     options.chmod_x = true;
+    // The CLI writes an -o script as it is generated.
+    options.stream = true;
     
     let mut emit_ast = false;
     let mut emit_ir = false;
     let mut emit_sh = false;
     let mut check = false;
+    let mut audit_quoting = false;
+    let mut fix = false;
+    let mut prelude_report = false;
+    let mut emit_lib = false;
     
     let mut chmod_x_flag: Option<bool> = None;
+    let mut timings = false;
 
     let mut i = 1;
     while i < args.len() {
@@ -107,9 +206,79 @@ fn parse_args(args: Vec<String>) -> Result<Config, CliError> {
             }
             options.target = parse_target(val)?;
             i += 1;
+        } else if arg == "--entry" {
+            match args.get(i + 1) {
+                Some(name) if !name.is_empty() => options.entry = Some(name.clone()),
+                _ => return Err(CliError::usage("error: --entry requires a function name")),
+            }
+            i += 2;
         } else if arg == "--no-diagnostics" {
             options.include_diagnostics = false;
             i += 1;
+        } else if arg == "--profile" {
+            options.profile = true;
+            i += 1;
+        } else if arg == "--deny-tainted-sh" {
+            options.deny_tainted_sh = true;
+            i += 1;
+        } else if arg == "--sandbox" {
+            if i + 1 < args.len() {
+                options.sandbox_manifest = Some(std::path::PathBuf::from(&args[i + 1]));
+                i += 2;
+            } else {
+                return Err(CliError::usage("error: --sandbox requires an argument"));
+            }
+        } else if arg == "--sandbox-guard" {
+            options.sandbox_guard = true;
+            i += 1;
+        } else if arg == "--runtime-lib" {
+            if i + 1 < args.len() {
+                options.runtime_lib = Some(std::path::PathBuf::from(&args[i + 1]));
+                i += 2;
+            } else {
+                return Err(CliError::usage("error: --runtime-lib requires an argument"));
+            }
+        } else if let Some(val) = arg.strip_prefix("--runtime-lib=") {
+            if val.is_empty() {
+                return Err(CliError::usage("error: --runtime-lib requires an argument"));
+            }
+            options.runtime_lib = Some(std::path::PathBuf::from(val));
+            i += 1;
+        } else if arg == "--minify" {
+            options.minify = true;
+            i += 1;
+        } else if arg == "--deterministic" {
+            options.deterministic = true;
+            i += 1;
+        } else if arg == "--max-size" || arg == "--warn-size" {
+            let Some(val) = args.get(i + 1) else {
+                return Err(CliError::usage(format!("error: {} requires an argument", arg)));
+            };
+            let bytes = parse_size(arg, val)?;
+            if arg == "--max-size" {
+                options.max_size = Some(bytes);
+            } else {
+                options.warn_size = Some(bytes);
+            }
+            i += 2;
+        } else if arg == "--emit-systemd" {
+            options.emit_systemd = true;
+            i += 1;
+        } else if arg == "--emit-cron" {
+            let Some(schedule) = args.get(i + 1) else {
+                return Err(CliError::usage("error: --emit-cron requires a schedule such as \"0 3 * * *\""));
+            };
+            if !sh2c::cron::check_schedule(schedule) {
+                return Err(CliError::usage(format!(
+                    "error: --emit-cron expects five cron fields such as \"0 3 * * *\" or a macro such as @daily, got '{}'",
+                    schedule
+                )));
+            }
+            options.emit_cron = Some(schedule.clone());
+            i += 2;
+        } else if arg == "--timings" {
+            timings = true;
+            i += 1;
         } else if arg == "-o" || arg == "--out" {
             if i + 1 < args.len() {
                 options.out_path = Some(std::path::PathBuf::from(&args[i + 1]));
@@ -117,6 +286,12 @@ fn parse_args(args: Vec<String>) -> Result<Config, CliError> {
             } else {
                 return Err(CliError::usage(format!("error: {} requires an argument", arg)));
             }
+        } else if arg == "--out-dir" {
+            match args.get(i + 1) {
+                Some(dir) => out_dir = Some(PathBuf::from(dir)),
+                None => return Err(CliError::usage("error: --out-dir requires an argument")),
+            }
+            i += 2;
         } else if arg == "--no-chmod-x" {
              if let Some(true) = chmod_x_flag {
                  return Err(CliError::usage("error: --no-chmod-x cannot be used with --chmod-x"));
@@ -138,54 +313,281 @@ fn parse_args(args: Vec<String>) -> Result<Config, CliError> {
         } else if arg == "--emit-sh" {
             emit_sh = true;
             i += 1;
+        } else if arg == "--emit-lib" {
+            emit_lib = true;
+            i += 1;
+        } else if arg == "--emit-prelude-report" {
+            prelude_report = true;
+            i += 1;
         } else if arg == "--check" {
             check = true;
             i += 1;
+        } else if arg == "--audit-quoting" {
+            audit_quoting = true;
+            i += 1;
+        } else if arg == "--fix" {
+            fix = true;
+            i += 1;
         } else if arg.starts_with("-") {
              return Err(CliError::usage(format!("error: Unexpected argument: {}", arg)));
         } else {
-            if filename.is_some() {
-                 return Err(CliError::usage(format!("error: Unexpected argument: {} (script already specified)", arg)));
-            }
-            filename = Some(arg.clone());
+            inputs.push(arg.clone());
             i += 1;
         }
     }
 
+    if inputs.len() > 1 && out_dir.is_none() {
+        return Err(CliError::usage(format!(
+            "error: Unexpected argument: {} (script already specified; use --out-dir to compile several)",
+            inputs[1]
+        )));
+    }
+    if out_dir.is_some() && options.out_path.is_some() {
+        return Err(CliError::usage("error: --out-dir cannot be used with --out"));
+    }
+    if inputs.len() > 1 && options.sandbox_manifest.is_some() {
+        return Err(CliError::usage("error: --sandbox takes a single input script"));
+    }
+
     if check && options.out_path.is_some() {
         return Err(CliError::usage_with_code("error: --check cannot be used with --out", 2));
     }
+    if audit_quoting && options.out_path.is_some() {
+        return Err(CliError::usage_with_code("error: --audit-quoting cannot be used with --out", 2));
+    }
+    if fix && options.out_path.is_some() {
+        return Err(CliError::usage_with_code("error: --fix cannot be used with --out", 2));
+    }
     
-    if chmod_x_flag.is_some() && options.out_path.is_none() {
-        return Err(CliError::usage("error: --no-chmod-x/--chmod-x require --out"));
+    if options.sandbox_guard && options.sandbox_manifest.is_none() {
+        return Err(CliError::usage("error: --sandbox-guard requires --sandbox"));
     }
 
-    if (emit_ast as u8 + emit_ir as u8 + emit_sh as u8 + check as u8) > 1 {
-         return Err(CliError::usage("error: multiple action flags specified (choose only one of: --emit-ast, --emit-ir, --emit-sh, --check)"));
+    if chmod_x_flag.is_some() && options.out_path.is_none() && out_dir.is_none() {
+        return Err(CliError::usage("error: --no-chmod-x/--chmod-x require --out or --out-dir"));
+    }
+
+    if (emit_ast as u8 + emit_ir as u8 + emit_sh as u8 + check as u8 + audit_quoting as u8 + fix as u8 + prelude_report as u8 + emit_lib as u8) > 1 {
+         return Err(CliError::usage("error: multiple action flags specified (choose only one of: --emit-ast, --emit-ir, --emit-sh, --emit-prelude-report, --emit-lib, --check, --audit-quoting, --fix)"));
+    }
+    if emit_lib && options.runtime_lib.is_some() {
+        return Err(CliError::usage("error: --emit-lib cannot be used with --runtime-lib"));
+    }
+    if emit_lib && options.entry.is_some() {
+        return Err(CliError::usage("error: --emit-lib cannot be used with --entry"));
+    }
+    if emit_lib && options.sandbox_guard {
+        return Err(CliError::usage("error: --emit-lib cannot be used with --sandbox-guard"));
     }
     
     if emit_ast { options.mode = Mode::EmitAst; }
     else if emit_ir { options.mode = Mode::EmitIr; }
     else if emit_sh { options.mode = Mode::EmitSh; }
     else if check { options.mode = Mode::Check; }
+    else if audit_quoting { options.mode = Mode::AuditQuoting; }
+    else if fix { options.mode = Mode::Fix; }
+    else if prelude_report { options.mode = Mode::PreludeReport; }
+    else if emit_lib { options.mode = Mode::EmitLib; }
+
+    let deploy_flag = match (options.emit_systemd, options.emit_cron.is_some()) {
+        (true, _) => Some("--emit-systemd"),
+        (false, true) => Some("--emit-cron"),
+        (false, false) => None,
+    };
+    if let Some(flag) = deploy_flag {
+        if options.out_path.is_none() && out_dir.is_none() {
+            return Err(CliError::usage(format!("error: {} requires --out or --out-dir", flag)));
+        }
+        if !matches!(options.mode, Mode::Default | Mode::EmitSh) {
+            return Err(CliError::usage(format!("error: {} only works when emitting scripts", flag)));
+        }
+    }
+
+    if out_dir.is_some() && !matches!(options.mode, Mode::Default | Mode::EmitSh | Mode::EmitLib) {
+        return Err(CliError::usage("error: --out-dir only works when emitting scripts or libraries"));
+    }
 
     if let Some(flag) = chmod_x_flag {
         options.chmod_x = flag;
     }
 
-    let filename = match filename {
-        Some(f) => f,
-        None => {
-             return Err(CliError::usage("error: missing input file"));
-        }
-    };
+    if inputs.is_empty() {
+        return Err(CliError::usage("error: missing input file"));
+    }
 
     Ok(Config {
-        filename,
+        inputs,
         options,
+        timings,
+        out_dir,
     })
 }
 
+fn explain(args: &[String]) -> Result<String, CliError> {
+    let [code] = args else {
+        return Err(CliError::usage("error: explain requires exactly one error code, e.g. sh2c explain E0200"));
+    };
+    let entry = sh2c::diag_codes::lookup(code)
+        .ok_or_else(|| CliError::usage(format!("error: unknown error code: {}", code)))?;
+    Ok(format!("{}: {}\n\n{}", entry.code, entry.title, entry.explanation))
+}
+
+/// `sh2c doc --man <script> [-o <file>]`: prints or writes the man page.
+fn doc(args: &[String]) -> Result<(), CliError> {
+    let mut man = false;
+    let mut out: Option<PathBuf> = None;
+    let mut script: Option<&String> = None;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--man" {
+            man = true;
+            i += 1;
+        } else if arg == "-o" || arg == "--out" {
+            let path = args
+                .get(i + 1)
+                .ok_or_else(|| CliError::usage(format!("error: {} requires an argument", arg)))?;
+            out = Some(PathBuf::from(path));
+            i += 2;
+        } else if arg.starts_with('-') || script.is_some() {
+            return Err(CliError::usage(format!("error: unexpected argument for doc: {}", arg)));
+        } else {
+            script = Some(arg);
+            i += 1;
+        }
+    }
+    if !man {
+        return Err(CliError::usage("error: doc needs an output format; only --man is supported"));
+    }
+    let script = script.ok_or_else(|| CliError::usage("error: doc requires a script, e.g. sh2c doc --man main.sh2"))?;
+
+    let page = driver::man_page(Path::new(script)).map_err(CliError::from_driver)?;
+    match out {
+        Some(path) => std::fs::write(&path, page).map_err(|e| CliError {
+            code: 1,
+            msg: format!("Failed to write to {}: {}", path.display(), e),
+            show_usage: false,
+        }),
+        None => {
+            print!("{}", page);
+            Ok(())
+        }
+    }
+}
+
+/// `sh2c emit-syntax <format> [-o <file>]`: prints or writes highlighting rules.
+fn emit_syntax(args: &[String]) -> Result<(), CliError> {
+    let mut out: Option<PathBuf> = None;
+    let mut format: Option<SyntaxFormat> = None;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "-o" || arg == "--out" {
+            let path = args
+                .get(i + 1)
+                .ok_or_else(|| CliError::usage(format!("error: {} requires an argument", arg)))?;
+            out = Some(PathBuf::from(path));
+            i += 2;
+        } else if format.is_none() && let Some(f) = SyntaxFormat::parse(arg) {
+            format = Some(f);
+            i += 1;
+        } else {
+            return Err(CliError::usage(format!(
+                "error: unexpected argument for emit-syntax: {} (formats: {})",
+                arg,
+                SyntaxFormat::NAMES.join(", ")
+            )));
+        }
+    }
+    let format = format.ok_or_else(|| {
+        CliError::usage(format!("error: emit-syntax requires a format: {}", SyntaxFormat::NAMES.join(", ")))
+    })?;
+
+    let text = highlight::emit(format);
+    match out {
+        Some(path) => std::fs::write(&path, text).map_err(|e| CliError {
+            code: 1,
+            msg: format!("Failed to write to {}: {}", path.display(), e),
+            show_usage: false,
+        }),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/// `sh2c verify`: exits 0 when every run agrees, 1 on a divergence and 2 when
+/// a target does not compile.
+fn verify(args: &[String]) -> Result<i32, CliError> {
+    let (flags, script_args) = match args.iter().position(|a| a == "--") {
+        Some(pos) => (&args[..pos], &args[pos + 1..]),
+        None => (args, &[][..]),
+    };
+    let mut targets = vec![TargetShell::Bash, TargetShell::Posix];
+    let mut stdin: Option<PathBuf> = None;
+    let mut script: Option<&String> = None;
+    let mut i = 0;
+    while i < flags.len() {
+        let arg = &flags[i];
+        if arg == "--targets" || arg == "--stdin" {
+            let value = flags
+                .get(i + 1)
+                .ok_or_else(|| CliError::usage(format!("error: {} requires a value", arg)))?;
+            if arg == "--targets" {
+                targets = value.split(',').map(parse_target).collect::<Result<_, _>>()?;
+            } else {
+                stdin = Some(PathBuf::from(value));
+            }
+            i += 2;
+        } else if arg.starts_with('-') || script.is_some() {
+            return Err(CliError::usage(format!("error: unexpected argument for verify: {}", arg)));
+        } else {
+            script = Some(arg);
+            i += 1;
+        }
+    }
+    let script = script.ok_or_else(|| CliError::usage("error: verify requires a script, e.g. sh2c verify main.sh2"))?;
+
+    let mut runs = Vec::new();
+    let mut code = 0;
+    for target in targets {
+        let shell = sh2c::verify::shell_for(target);
+        match sh2c::verify::run_target(Path::new(script), target, script_args, stdin.as_deref()) {
+            Ok(Some(run)) => {
+                println!("{}: exit {}, {} line(s) of output", run.label(), sh2c::verify::describe_status(run.status), run.stdout.lines().count());
+                runs.push(run);
+            }
+            Ok(None) => println!("{} ({}): skipped, {} not found on PATH", target, shell, shell),
+            Err(e) if e.code == 2 => {
+                println!("{} ({}): does not compile", target, shell);
+                eprintln!("{}", e.msg);
+                code = 2;
+            }
+            Err(e) => return Err(CliError::from_driver(e)),
+        }
+    }
+
+    let divergences = sh2c::verify::divergences(&runs);
+    for line in &divergences {
+        println!("diverges: {}", line);
+    }
+    if !divergences.is_empty() {
+        // The other runs' stderr usually explains a changed status or output.
+        for run in runs.iter().filter(|r| !r.stderr.is_empty()) {
+            println!("stderr of {}:", run.label());
+            for line in run.stderr.lines() {
+                println!("  {}", line);
+            }
+        }
+        return Ok(if code == 0 { 1 } else { code });
+    }
+    if code == 0 {
+        println!("verify: {} run(s) agree", runs.len());
+    }
+    Ok(code)
+}
+
 fn parse_target(s: &str) -> Result<TargetShell, CliError> {
     match s {
         "bash" => Ok(TargetShell::Bash),
@@ -194,14 +596,43 @@ fn parse_target(s: &str) -> Result<TargetShell, CliError> {
     }
 }
 
+/// A byte count, optionally with a binary `K` or `M` suffix (`16K` is 16384).
+fn parse_size(flag: &str, s: &str) -> Result<usize, CliError> {
+    let (digits, unit) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1024),
+        Some((i, 'M' | 'm')) => (&s[..i], 1024 * 1024),
+        _ => (s, 1),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(|| CliError::usage(format!("error: {} expects a byte count such as 16384 or 16K, got '{}'", flag, s)))
+}
+
 fn compile(config: Config) -> Result<(), CliError> {
-    let path = std::path::Path::new(&config.filename);
+    if let Some(out_dir) = &config.out_dir {
+        return build(&config, out_dir);
+    }
+    let path = Path::new(&config.inputs[0]);
+    if path.is_dir() {
+        return Err(CliError::usage(format!(
+            "error: {} is a directory; use --out-dir to compile the .sh2 files in it",
+            path.display()
+        )));
+    }
     
     let mode = config.options.mode;
     let has_out_path = config.options.out_path.is_some();
     
-    let result = driver::compile_file(path, config.options)
+    let (result, warnings, timings) = driver::compile_file_timed(path, config.options)
         .map_err(CliError::from_driver)?;
+    for w in &warnings {
+        eprintln!("warning: {}", w);
+    }
+    if config.timings {
+        eprint!("{}", timings.report());
+    }
         
     // Driver handles writing to file if out_path is set.
     // If not, it returns the content (or "OK" for check). 
@@ -215,8 +646,9 @@ fn compile(config: Config) -> Result<(), CliError> {
     
     if !has_out_path {
         match mode {
-             Mode::Default | Mode::EmitSh => print!("{}", result),
-             Mode::Check | Mode::EmitAst | Mode::EmitIr => println!("{}", result),
+             Mode::Default | Mode::EmitSh | Mode::EmitLib => print!("{}", result),
+             Mode::Check | Mode::EmitAst | Mode::EmitIr | Mode::AuditQuoting | Mode::Fix => println!("{}", result),
+             Mode::PreludeReport => print!("{}", result),
         }
     } else if mode == Mode::Check {
         // Edge case: check with out_path? CLI parser rejects check+out.
@@ -226,3 +658,72 @@ fn compile(config: Config) -> Result<(), CliError> {
     
     Ok(())
 }
+
+/// `--out-dir`: compile every input in one process, sharing parsed imports.
+/// Keeps going after a failure so one run reports every broken input.
+fn build(config: &Config, out_dir: &Path) -> Result<(), CliError> {
+    let inputs = expand_inputs(&config.inputs)?;
+    for (i, input) in inputs.iter().enumerate() {
+        let out = driver::artifact_path(out_dir, input);
+        if let Some(other) = inputs[..i].iter().find(|o| driver::artifact_path(out_dir, o) == out) {
+            return Err(CliError::usage(format!(
+                "error: {} and {} would both be written to {}",
+                other.display(),
+                input.display(),
+                out.display()
+            )));
+        }
+    }
+
+    let options = CompileOptions { out_dir: Some(out_dir.to_path_buf()), ..config.options.clone() };
+    let artifacts = driver::compile_project(&inputs, &options);
+    for artifact in &artifacts {
+        for w in &artifact.warnings {
+            eprintln!("warning: {}", w);
+        }
+        if let Some(e) = &artifact.error {
+            eprintln!("{}", e.msg);
+        } else if config.timings {
+            eprintln!("{}:", artifact.entry.display());
+            eprint!("{}", artifact.timings.report());
+        }
+    }
+
+    let failed: Vec<&driver::DriverError> = artifacts.iter().filter_map(|a| a.error.as_ref()).collect();
+    match failed.first() {
+        Some(first) => Err(CliError {
+            code: first.code,
+            msg: format!("error: {} of {} input(s) failed to compile", failed.len(), artifacts.len()),
+            show_usage: false,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Input files in command-line order; a directory stands for the `.sh2` files
+/// directly inside it, sorted by name (modules kept in subdirectories are skipped).
+fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>, CliError> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = PathBuf::from(input);
+        if !path.is_dir() {
+            files.push(path);
+            continue;
+        }
+        let entries = std::fs::read_dir(&path).map_err(|e| CliError {
+            code: 1,
+            msg: format!("Unable to read directory: {} ({})", path.display(), e),
+            show_usage: false,
+        })?;
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "sh2"))
+            .collect();
+        if found.is_empty() {
+            return Err(CliError::usage(format!("error: no .sh2 files in {}", path.display())));
+        }
+        found.sort();
+        files.extend(found);
+    }
+    Ok(files)
+}
@@ -5,3 +5,103 @@ pub(crate) fn mangle(alias: &str, func: &str) -> String {
     debug_assert!(func.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
     format!("__imp_{}__{}", alias, func)
 }
+
+/// Name of the function an `init { ... }` block is parsed into. The loader
+/// numbers them (`__sh2_init_0`, ...) so every module keeps its own.
+pub(crate) const INIT: &str = "__sh2_init";
+
+/// The `alias.func` spelling of a name produced by `mangle`, for diagnostics.
+pub(crate) fn display(name: &str) -> String {
+    if name.starts_with(INIT) {
+        return "init".to_string();
+    }
+    match name.strip_prefix("__imp_").and_then(|rest| rest.split_once("__")) {
+        Some((alias, func)) => format!("{}.{}", alias, func),
+        None => name.to_string(),
+    }
+}
+
+/// Shell variable and function names are ASCII, so each other character of an
+/// identifier is spelled `__U` plus six hex digits: `größe` is compiled as
+/// `gr__U0000F6__U0000DFe`. `display_idents` turns them back for messages.
+pub(crate) fn ascii_ident(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 8);
+    for c in name.chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            out.push_str(&format!("__U{:06X}", c as u32));
+        }
+    }
+    out
+}
+
+/// `text` with every identifier spelled by `ascii_ident` shown as written.
+pub(crate) fn display_idents(text: &str) -> std::borrow::Cow<'_, str> {
+    if !text.contains("__U") {
+        return std::borrow::Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find("__U") {
+        out.push_str(&rest[..i]);
+        let hex = rest.get(i + 3..i + 9).filter(|h| h.bytes().all(|b| b.is_ascii_digit() || (b'A'..=b'F').contains(&b)));
+        match hex.and_then(|h| u32::from_str_radix(h, 16).ok()).and_then(char::from_u32) {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[i + 9..];
+            }
+            None => {
+                out.push_str("__U");
+                rest = &rest[i + 3..];
+            }
+        }
+    }
+    out.push_str(rest);
+    std::borrow::Cow::Owned(out)
+}
+
+/// Prefix of every name the generated runtime defines.
+pub(crate) const RUNTIME_PREFIX: &str = "__sh2_";
+
+/// Shell builtins a function definition would replace, for the script and for
+/// the runtime helpers that call them.
+const SHELL_BUILTINS: &[&str] = &[
+    ".", ":", "alias", "break", "builtin", "cd", "command", "continue", "declare", "echo", "eval", "exec",
+    "exit", "export", "false", "getopts", "hash", "kill", "local", "printf", "read", "readonly", "return",
+    "set", "shift", "shopt", "source", "test", "times", "trap", "true", "type", "typeset", "ulimit", "umask",
+    "unalias", "unset", "wait",
+];
+
+/// Variables the shell itself reads or sets; a plain `let` of one changes how
+/// every later command runs, or is overwritten behind the script's back.
+const SHELL_VARIABLES: &[&str] = &[
+    "BASHOPTS", "BASHPID", "BASH_ENV", "CDPATH", "ENV", "EUID", "FUNCNAME", "HOME", "IFS", "LINENO",
+    "OLDPWD", "OPTARG", "OPTIND", "PATH", "PIPESTATUS", "PPID", "PS1", "PS2", "PS4", "PWD", "RANDOM",
+    "SECONDS", "SHELLOPTS", "UID",
+];
+
+/// Why `name` cannot be used for a user function, if it cannot.
+pub(crate) fn reserved_function(name: &str) -> Option<&'static str> {
+    if name.starts_with(RUNTIME_PREFIX) {
+        Some("the `__sh2_` prefix belongs to the generated runtime")
+    } else if SHELL_BUILTINS.contains(&name) {
+        Some("it is a shell builtin the generated script relies on")
+    } else {
+        None
+    }
+}
+
+/// Why `name` cannot be used for a user variable or parameter, if it cannot.
+pub(crate) fn reserved_variable(name: &str) -> Option<String> {
+    if name.starts_with(RUNTIME_PREFIX) {
+        Some("the `__sh2_` prefix belongs to the generated runtime".to_string())
+    } else if SHELL_VARIABLES.contains(&name) {
+        Some(format!(
+            "the shell itself uses it; to change it on purpose, use `set env.{0} = ...` or `with env {{ {0}: ... }}`",
+            name
+        ))
+    } else {
+        None
+    }
+}
@@ -6,6 +6,8 @@ pub(crate) struct Parser<'a> {
     pub pos: usize,
     pub sm: &'a SourceMap,
     pub file: &'a str,
+    /// Errors the parser recovered from, in source order.
+    pub errors: Vec<Diagnostic>,
 }
 
 impl<'a> Parser<'a> {
@@ -15,6 +17,7 @@ impl<'a> Parser<'a> {
             pos: 0,
             sm,
             file,
+            errors: Vec::new(),
         }
     }
 
@@ -33,6 +36,85 @@ impl<'a> Parser<'a> {
         }
         t
     }
+
+    fn line_of(&self, idx: usize) -> usize {
+        self.sm.line_col(self.tokens[idx].span.start).0
+    }
+
+    /// Panic-mode recovery for a statement that started at `stmt_start` and failed
+    /// with `err`: records the error and skips to the next statement boundary, so
+    /// the enclosing block can carry on with the statement after it.
+    ///
+    /// A boundary is a `;` (consumed), the `}` closing the block, a `func` keyword,
+    /// or the first token on a later line, each only once every bracket the failed
+    /// statement opened has been closed again.
+    pub fn recover_stmt(&mut self, err: Diagnostic, stmt_start: usize) {
+        self.errors.push(err);
+        let mut depth: usize = 0;
+        for t in &self.tokens[stmt_start..self.pos] {
+            match t.kind {
+                TokenKind::LBrace | TokenKind::LParen | TokenKind::LBracket | TokenKind::QuestionBracket => depth += 1,
+                TokenKind::RBrace | TokenKind::RParen | TokenKind::RBracket => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+        }
+        let last_line = self.line_of(self.pos.saturating_sub(1).max(stmt_start));
+        // An error on a later line, no further indented than the statement itself,
+        // is the next statement: whatever the failed one left open was never closed.
+        if self.pos > stmt_start && self.peek().is_some() {
+            let (line, col) = self.sm.line_col(self.tokens[self.pos].span.start);
+            let (_, start_col) = self.sm.line_col(self.tokens[stmt_start].span.start);
+            if line > last_line && col <= start_col {
+                depth = 0;
+            }
+        }
+        // The statement failed on its first token: skip that token regardless, so
+        // recovery always makes progress.
+        let mut forced = self.pos == stmt_start;
+        while let Some(t) = self.peek() {
+            if depth == 0 && !forced {
+                match t.kind {
+                    TokenKind::Semi => {
+                        self.advance();
+                        return;
+                    }
+                    TokenKind::RBrace | TokenKind::Func => return,
+                    _ if self.line_of(self.pos) > last_line => return,
+                    _ => {}
+                }
+            }
+            if t.kind == TokenKind::Func {
+                // Unbalanced brackets never swallow the next function.
+                return;
+            }
+            forced = false;
+            match t.kind {
+                TokenKind::LBrace | TokenKind::LParen | TokenKind::LBracket | TokenKind::QuestionBracket => depth += 1,
+                TokenKind::RBrace | TokenKind::RParen | TokenKind::RBracket => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    /// Recovery for a top-level item that failed to parse: records `err` and skips
+    /// to the next `func`, `extern`, `import` or attribute.
+    pub fn recover_item(&mut self, err: Diagnostic, item_start: usize) {
+        self.errors.push(err);
+        if self.pos == item_start {
+            self.advance();
+        }
+        while let Some(t) = self.peek() {
+            if matches!(t.kind, TokenKind::Func | TokenKind::Extern | TokenKind::Import | TokenKind::At) {
+                break;
+            }
+            self.advance();
+        }
+    }
 }
 
 use crate::span::Diagnostic;
@@ -43,13 +125,19 @@ impl<'a> Parser<'a> {
     pub fn error<T>(&self, msg: &str, span: Span) -> ParsResult<T> {
         Err(Diagnostic {
             msg: msg.to_string(),
+            code: None,
             span,
             sm: Some(self.sm.clone()),
             file: Some(self.file.to_string()),
             help: None,
+            additional: Vec::new(),
         })
     }
 
+    pub fn error_with_help<T>(&self, msg: &str, help: &str, span: Span) -> ParsResult<T> {
+        self.error(msg, span).map_err(|d| Diagnostic { help: Some(help.to_string()), ..d })
+    }
+
     pub fn previous_span(&self) -> Span {
         if self.pos > 0 {
             self.tokens[self.pos - 1].span
@@ -138,7 +226,7 @@ impl<'a> Parser<'a> {
                     }
                 }
                 args.push(self.parse_expr()?);
-                if !self.match_kind(TokenKind::Comma) { break; }
+                if !self.match_list_separator(&TokenKind::RParen) { break; }
             }
             self.expect(TokenKind::RParen)?;
         }
@@ -166,6 +254,31 @@ impl<'a> Parser<'a> {
         false
     }
 
+    /// Consumes the separator after an item of a bracketed list that ends at `close`
+    /// (call arguments, parameters, list and map literals): a `,`, or nothing at all
+    /// when the next item starts an expression on a new line. Returns false once the list is over,
+    /// which is also what makes a trailing comma before `close` legal.
+    pub fn match_list_separator(&mut self, close: &TokenKind) -> bool {
+        let comma = self.match_kind(TokenKind::Comma);
+        match self.peek() {
+            Some(t) if t.kind == *close => false,
+            Some(_) if comma => true,
+            Some(t) => {
+                is_expr_start(Some(&t.kind)) && self.pos > 0 && self.line_of(self.pos) > self.line_of(self.pos - 1)
+            }
+            None => false,
+        }
+    }
+
+    /// Whether a value follows `return` or `exit`. An `if` only counts on the same
+    /// line, so a bare `return` followed by an `if` statement stays bare.
+    pub(super) fn at_optional_value(&self) -> bool {
+        match self.peek_kind() {
+            Some(TokenKind::If) => self.pos > 0 && self.line_of(self.pos) == self.line_of(self.pos - 1),
+            k => is_expr_start(k),
+        }
+    }
+
     /// Consumes explicit separators (semicolons). 
     /// Newlines are treated as whitespace by the lexer, so statements separated by newlines 
     /// are parsed sequentially without explicit separator tokens.
@@ -175,3 +288,48 @@ impl<'a> Parser<'a> {
         }
     }
 }
+
+pub(super) fn is_expr_start(k: Option<&TokenKind>) -> bool {
+    matches!(
+        k,
+        Some(
+            TokenKind::String(_)
+                | TokenKind::Ident(_)
+                | TokenKind::Dollar
+                | TokenKind::LParen
+                | TokenKind::LBracket
+                | TokenKind::Env
+                | TokenKind::Args
+                | TokenKind::Capture
+                | TokenKind::Exists
+                | TokenKind::IsDir
+                | TokenKind::IsFile
+                | TokenKind::IsSymlink
+                | TokenKind::IsExec
+                | TokenKind::IsReadable
+                | TokenKind::IsWritable
+                | TokenKind::IsNonEmpty
+                | TokenKind::BoolStr
+                | TokenKind::Len
+                | TokenKind::Arg
+                | TokenKind::Index
+                | TokenKind::Join
+                | TokenKind::Status
+                | TokenKind::Pid
+                | TokenKind::Count
+                | TokenKind::Uid
+                | TokenKind::Ppid
+                | TokenKind::Pwd
+                | TokenKind::SelfPid
+                | TokenKind::Argv0
+                | TokenKind::Argc
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Number(_)
+                | TokenKind::Minus
+                | TokenKind::Bang
+                | TokenKind::Input
+                | TokenKind::Confirm
+        )
+    )
+}
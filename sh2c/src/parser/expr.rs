@@ -38,36 +38,61 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_comparison(&mut self) -> ParsResult<Expr> {
-        let left = self.parse_concat()?;
+        let mut left = self.parse_coalesce()?;
+        let mut chained = false;
 
-        if let Some(kind) = self.peek_kind() {
+        // `0 <= x < 10` nests to the left; lowering reads it as a chain.
+        while let Some(kind) = self.peek_kind() {
             let op = match kind {
-                TokenKind::EqEq => Some(CompareOp::Eq),
-                TokenKind::NotEq => Some(CompareOp::NotEq),
-                TokenKind::Lt => Some(CompareOp::Lt),
-                TokenKind::Le => Some(CompareOp::Le),
-                TokenKind::Gt => Some(CompareOp::Gt),
-                TokenKind::Ge => Some(CompareOp::Ge),
-                _ => None,
+                TokenKind::EqEq => CompareOp::Eq,
+                TokenKind::NotEq => CompareOp::NotEq,
+                TokenKind::Lt => CompareOp::Lt,
+                TokenKind::Le => CompareOp::Le,
+                TokenKind::Gt => CompareOp::Gt,
+                TokenKind::Ge => CompareOp::Ge,
+                _ => break,
             };
-
-            if let Some(op) = op {
-                self.advance();
-                let right = self.parse_concat()?;
-                let span = left.span.merge(right.span);
-                return Ok(Expr {
-                    node: ExprKind::Compare {
-                        left: Box::new(left),
-                        op,
-                        right: Box::new(right),
-                    },
-                    span,
-                });
+            if chained {
+                let prev_ordering = matches!(&left.node, ExprKind::Compare { op, .. } if op.is_ordering());
+                if !(prev_ordering && op.is_ordering()) {
+                    return self.error_with_help(
+                        "Expected `&&` between these comparisons; only `<`, `<=`, `>` and `>=` can be chained",
+                        "join the comparisons with `&&`, e.g. `a == b && b == c`",
+                        self.current_span(),
+                    );
+                }
             }
+            self.advance();
+            let right = self.parse_coalesce()?;
+            let span = left.span.merge(right.span);
+            left = Expr {
+                node: ExprKind::Compare {
+                    left: Box::new(left),
+                    op,
+                    right: Box::new(right),
+                },
+                span,
+            };
+            chained = true;
         }
         Ok(left)
     }
 
+    /// `a ?? b ?? c`, right-associative: binds looser than `&` and tighter
+    /// than comparisons.
+    fn parse_coalesce(&mut self) -> ParsResult<Expr> {
+        let left = self.parse_concat()?;
+        if !self.match_kind(TokenKind::QuestionQuestion) {
+            return Ok(left);
+        }
+        let right = self.parse_coalesce()?;
+        let span = left.span.merge(right.span);
+        Ok(Expr {
+            node: ExprKind::Coalesce(Box::new(left), Box::new(right)),
+            span,
+        })
+    }
+
     fn parse_concat(&mut self) -> ParsResult<Expr> {
         let mut left = self.parse_sum()?;
         while self.peek_kind() == Some(&TokenKind::Amp) {
@@ -154,6 +179,13 @@ impl<'a> Parser<'a> {
                 node: ExprKind::Not(Box::new(expr)),
                 span,
             })
+        } else if self.match_kind(TokenKind::Ellipsis) {
+            let list = self.parse_primary()?;
+            let span = start.merge(list.span);
+            Ok(Expr {
+                node: ExprKind::Spread(Box::new(list)),
+                span,
+            })
         } else if self.match_kind(TokenKind::Minus) {
             let right = self.parse_unary()?;
             let span = start.merge(right.span);
@@ -224,7 +256,40 @@ impl<'a> Parser<'a> {
                     self.error("Expected identifier after dot", self.current_span())?;
                     unreachable!() // or let it propagate, but ? already returns
                 }
-            } else if self.match_kind(TokenKind::LBracket) {
+            } else if self.match_kind(TokenKind::QuestionDot) {
+                // `m?.key` is `m?["key"]` for keys that are plain identifiers.
+                let ExprKind::Var(map) = &expr.node else {
+                    return self.error_with_help(
+                        "Expected a map variable before `?.`",
+                        "store the value with `let` first, then look up `name?.key`",
+                        start,
+                    );
+                };
+                let key = match self.peek_kind() {
+                    Some(TokenKind::Ident(key)) => key.clone(),
+                    Some(TokenKind::Status) => "status".to_string(),
+                    Some(TokenKind::Stdout) => "stdout".to_string(),
+                    Some(TokenKind::Stderr) => "stderr".to_string(),
+                    _ => {
+                        return self.error_with_help(
+                            "Expected a key name after `?.`",
+                            "use `m?[\"key\"]` for keys that are not identifiers",
+                            self.current_span(),
+                        );
+                    }
+                };
+                let map = map.clone();
+                let end = self.advance().unwrap().span;
+                expr = Expr {
+                    node: ExprKind::MapIndex { map, key, optional: true },
+                    span: start.merge(end),
+                };
+            } else if let Some(optional) = self.peek_kind().and_then(|k| match k {
+                TokenKind::LBracket => Some(false),
+                TokenKind::QuestionBracket => Some(true),
+                _ => None,
+            }) {
+                self.advance();
                 // Map indexing check: var["key"]
                 let mut is_map = false;
                 if let ExprKind::Var(ref name) = expr.node {
@@ -242,6 +307,7 @@ impl<'a> Parser<'a> {
                                 node: ExprKind::MapIndex {
                                     map: name.clone(),
                                     key,
+                                    optional,
                                 },
                                 span,
                             };
@@ -259,6 +325,7 @@ impl<'a> Parser<'a> {
                         node: ExprKind::Index {
                             list: Box::new(expr),
                             index: Box::new(index),
+                            optional,
                         },
                         span,
                     };
@@ -348,10 +415,8 @@ impl<'a> Parser<'a> {
                     self.expect(TokenKind::Colon)?;
                     let val = self.parse_expr()?;
                     entries.push((key, val));
-                    if !self.match_kind(TokenKind::Comma) {
-                        if self.peek_kind() != Some(&TokenKind::RBrace) {
-                            self.error("Expected comma or closing brace", self.current_span())?;
-                        }
+                    if !self.match_list_separator(&TokenKind::RBrace) && self.peek_kind() != Some(&TokenKind::RBrace) {
+                        self.error("Expected comma or closing brace", self.current_span())?;
                     }
                 }
                 // RBrace consumed by while loop condition? No, matched_kind consumed it if true?
@@ -461,7 +526,7 @@ impl<'a> Parser<'a> {
                                     args.push(self.parse_expr()?);
                                 }
 
-                                if !self.match_kind(TokenKind::Comma) {
+                                if !self.match_list_separator(&TokenKind::RParen) {
                                     break;
                                 }
                             }
@@ -517,10 +582,10 @@ impl<'a> Parser<'a> {
 
                                 if is_named {
                                     // Named argument - only allowed for specific builtins
-                                    let allowed_builtins = ["run", "sudo", "sh", "capture", "confirm", "find_files", "find0", "wait", "wait_all"];
+                                    let allowed_builtins = ["run", "sudo", "sh", "capture", "confirm", "find_files", "find0", "wait", "wait_all", "wait_any", "render", "raw_arg", "wait_for_port", "read_file", "find"];
                                     if !allowed_builtins.contains(&s.as_str()) {
                                         return self.error(
-                                            "Named arguments are only supported for builtins: run, sudo, sh, capture, confirm, find_files, find0, wait, wait_all",
+                                            "Named arguments are only supported for builtins: run, sudo, sh, capture, confirm, find_files, find0, wait, wait_all, wait_any, render, raw_arg, wait_for_port, read_file, find",
                                             self.current_span()
                                         );
                                     }
@@ -544,7 +609,7 @@ impl<'a> Parser<'a> {
                                     args.push(self.parse_expr()?);
                                 }
 
-                                if !self.match_kind(TokenKind::Comma) {
+                                if !self.match_list_separator(&TokenKind::RParen) {
                                     break;
                                 }
                             }
@@ -556,7 +621,7 @@ impl<'a> Parser<'a> {
 
                     // Arity validation
                     match s.as_str() {
-                        "before" | "after" | "coalesce" | "default" | "split" => {
+                        "before" | "after" | "coalesce" | "default" | "split" | "render" => {
                             if args.len() != 2 {
                                 // We can error here with the call span
                                 self.error(
@@ -625,6 +690,8 @@ impl<'a> Parser<'a> {
                             }
                         }
                     }
+                } else if let Some(err) = self.shell_variable_error(span) {
+                    err
                 } else {
                     self.parse_command_substitution(span, false)
                 }
@@ -634,7 +701,7 @@ impl<'a> Parser<'a> {
                 if !self.match_kind(TokenKind::RBracket) {
                     loop {
                         exprs.push(self.parse_expr()?);
-                        if !self.match_kind(TokenKind::Comma) {
+                        if !self.match_list_separator(&TokenKind::RBracket) {
                             break;
                         }
                     }
@@ -770,6 +837,7 @@ impl<'a> Parser<'a> {
                     node: ExprKind::Index {
                         list: Box::new(list),
                         index: Box::new(index),
+                        optional: false,
                     },
                     span: span.merge(self.previous_span()),
                 })
@@ -898,6 +966,7 @@ impl<'a> Parser<'a> {
                 node: ExprKind::Number(*n),
                 span,
             }),
+            TokenKind::If => self.parse_if_expr(span),
             TokenKind::Semi => {
                  return self.error("Unexpected statement separator ';' inside expression. Use ';' only between statements.", span);
              }
@@ -946,7 +1015,7 @@ impl<'a> Parser<'a> {
                             args.push(self.parse_expr()?);
                         }
                         
-                        if !self.match_kind(TokenKind::Comma) {
+                        if !self.match_list_separator(&TokenKind::RParen) {
                             break;
                         }
                     }
@@ -965,6 +1034,51 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// `if cond { a } elif cond2 { b } else { c }` as a value, after the `if`
+    /// at `if_span`. Each branch holds a single expression and `else` is
+    /// required, so the expression always has a value.
+    fn parse_if_expr(&mut self, if_span: Span) -> ParsResult<Expr> {
+        let cond = self.parse_expr()?;
+        let then_expr = self.parse_brace_expr()?;
+        let else_expr = if self.match_kind(TokenKind::Elif) {
+            self.parse_if_expr(self.previous_span())?
+        } else if self.peek_kind() == Some(&TokenKind::Else)
+            && self.tokens.get(self.pos + 1).map(|t| &t.kind) == Some(&TokenKind::If)
+        {
+            self.advance(); // else
+            self.advance(); // if
+            self.parse_if_expr(self.previous_span())?
+        } else if self.match_kind(TokenKind::Else) {
+            self.parse_brace_expr()?
+        } else {
+            return self.error_with_help(
+                "An `if` used as a value needs an `else` branch",
+                "add `else { ... }` with the value to use when the condition is false",
+                if_span.merge(self.previous_span()),
+            );
+        };
+        Ok(Expr {
+            node: ExprKind::IfElse {
+                cond: Box::new(cond),
+                then_expr: Box::new(then_expr),
+                else_expr: Box::new(else_expr),
+            },
+            span: if_span.merge(self.previous_span()),
+        })
+    }
+
+    /// One branch of an `if` expression: `{ expr }`.
+    fn parse_brace_expr(&mut self) -> ParsResult<Expr> {
+        self.expect(TokenKind::LBrace)?;
+        let expr = self.parse_expr()?;
+        if self.peek_kind() != Some(&TokenKind::RBrace) {
+            let span = self.peek().map_or(self.previous_span(), |t| t.span);
+            return self.error("Expected `}` after the value of an `if` expression branch; branches hold a single expression", span);
+        }
+        self.advance();
+        Ok(expr)
+    }
+
     fn parse_command_substitution(&mut self, start_span: Span, is_capture: bool) -> ParsResult<Expr> {
         self.expect(TokenKind::LParen)?;
         let mut segments = Vec::new();
@@ -1154,7 +1268,7 @@ impl<'a> Parser<'a> {
 
                                      args.push(self.parse_expr()?);
 
-                                     if !self.match_kind(TokenKind::Comma) {
+                                     if !self.match_list_separator(&TokenKind::RParen) {
                                          break;
                                      }
                                 }
@@ -1220,52 +1334,10 @@ impl<'a> Parser<'a> {
     }
                     
     fn parse_interpolated_string(&mut self, raw: &str, span: Span) -> ParsResult<Expr> {
-        // P0 Fix: Treat all strings as literals (no implicit expansion of $ or ${}).
-        // We simply return the raw content as a single Literal expr.
-        // We retain \$ -> $ unescaping for backward compatibility if desired, 
-        // OR we treat \$ as literal \$ if strictness implies raw?
-        // Plan says: "Preserve \$ unescaping (or treat as literal $)".
-        // If I want print("\$FOO") to be literal, then \$ must be $.
-        // BUT if "..." is STRICT literal, then $FOO is literal $FOO.
-        // So escaping is NOT needed for $FOO.
-        // But escaping might be needed for " (quote).
-        // The lexer handles " escaping inside string token.
-        // Here we receive raw string content (already stripped of surrounding quotes?).
-        // No, `raw` here is content.
-        // Lexer provides raw string content?
-        // Wait, `parse_interpolated_string` is called by `parse_atom` on `TokenKind::String(raw)`.
-        // The `raw` string from lexer usually handles escapes?
-        // Let's assume `raw` is the literal content characters.
-        // If so, we just wrap it.
-        // However, existing code had a loop for `\$` unescaping.
-        // "We retain \$ -> $ unescaping for backward compatibility".
-        
-        // Actually, if strict literal `"$FOO"` means literal `$FOO`.
-        // Then `"\$FOO"` means literal `\$FOO`?
-        // Or does `\` escape `$`?
-        // In bash `'...'`, `\` is literal.
-        // In sh2 `strict literal`, maybe `\` escapes? 
-        // I will stick to "Treat all strings as literals".
-        // But to support `"` inside string, lexer handled `\"`.
-        // Does lexer handle `\$`? Probably not.
-        // I will implementation simple pass-through OR basic unescape if needed.
-        // Previous correct implementation (before I reverted it):
-        
-        let mut buf = String::new();
-        let mut i = 0;
-        while i < raw.len() {
-             if raw[i..].starts_with("\\$") {
-                 buf.push('$');
-                 i += 2;
-                 continue;
-             }
-             let ch = raw[i..].chars().next().unwrap();
-             buf.push(ch);
-             i += ch.len_utf8();
-        }
-        
+        // Strings are strict literals: `$` and `${...}` are never expanded, and
+        // escapes were already decoded by the lexer, so the text is used as is.
         Ok(Expr {
-            node: ExprKind::Literal(buf),
+            node: ExprKind::Literal(raw.to_string()),
             span,
         })
     }
@@ -1375,36 +1447,21 @@ impl<'a> Parser<'a> {
         
         while let Some((i, c)) = chars.next() {
             if c == '\\' {
-                if let Some((_, next)) = chars.peek() {
-                    let next_char = *next;
-
-                    // Explicit escape rule: \{ and \} become literal { and }
-                    if next_char == '{' || next_char == '}' {
-                        buf.push(next_char);
-                        chars.next(); // Consume the peeked character
-                        continue; // Loop will advance to next char
-                    }
-                    // Standard escapes
-                    match next_char {
-                        'n' => { buf.push('\n'); chars.next(); continue; }
-                        't' => { buf.push('\t'); chars.next(); continue; }
-                        'r' => { buf.push('\r'); chars.next(); continue; }
-                        '\\' => { buf.push('\\'); chars.next(); continue; }
-                        '"' => { buf.push('"'); chars.next(); continue; }
-                        '$' => { buf.push('$'); chars.next(); continue; }
-                         _ => {
-                             // Unknown escape: preserve both backslash and next char
-                             buf.push('\\');
-                             buf.push(next_char);
-                             chars.next();
-                             continue;
-                         }
+                let mut esc_end = i + 1;
+                let decoded = crate::lexer::decode_escape(|| {
+                    chars.next().map(|(j, ch)| {
+                        esc_end = j + ch.len_utf8();
+                        ch
+                    })
+                });
+                match decoded {
+                    Ok(ch) => buf.push(ch),
+                    Err((code, msg)) => {
+                        let esc = Span::new(inner_start_offset + i, inner_start_offset + esc_end);
+                        return self.error(&msg, esc).map_err(|d| d.with_code(code));
                     }
-                } else {
-                    // Trailing backslash
-                    buf.push('\\');
-                    continue;
                 }
+                continue;
             }
             
             if c == '{' {
@@ -1510,7 +1567,7 @@ impl<'a> Parser<'a> {
                 }
                 
                 // Parse expression from content
-                let sub_sm = crate::span::SourceMap::new(content.clone());
+                let sub_sm = crate::span::SourceMap::new(content);
                 let tokens = crate::lexer::lex(&sub_sm, "interpolation").map_err(|d| {
                      let mut d = d;
                      d.msg = format!("Lexer error inside interpolation: {}", d.msg);
@@ -1579,8 +1636,9 @@ impl<'a> Parser<'a> {
             return Ok(Expr { node: ExprKind::Literal(String::new()), span });
         }
 
-        let mut expr = parts[0].clone();
-        for p in parts.into_iter().skip(1) {
+        let mut parts = parts.into_iter();
+        let mut expr = parts.next().unwrap();
+        for p in parts {
             expr = Expr {
                 node: ExprKind::Concat(Box::new(expr), Box::new(p)),
                 span,
@@ -1592,10 +1650,12 @@ impl<'a> Parser<'a> {
     fn make_error(&self, msg: &str, span: Span) -> Diagnostic {
          Diagnostic {
             msg: msg.to_string(),
+            code: None,
             span,
             sm: Some(self.sm.clone()),
             file: Some(self.file.to_string()),
             help: None,
+            additional: Vec::new(),
         }
     }
     pub(crate) fn parse_sh_options(&mut self, allow_allow_fail: bool) -> Result<Vec<CallOption>, Diagnostic> {
@@ -1,17 +1,60 @@
 mod common;
 mod expr;
+mod shellisms;
 mod stmt;
 
+pub(crate) use self::stmt::GLOB_CHARS;
 use self::common::{ParsResult, Parser};
 use crate::ast::*;
 use crate::lexer::{Token, TokenKind};
-use crate::span::SourceMap;
+use crate::span::{Diagnostic, SourceMap, Span};
+use std::borrow::Cow;
 use std::collections::HashMap;
 
+/// Parses a whole file, failing if any syntax error was found. The returned
+/// diagnostic is the first error; any later ones are attached to it.
 pub fn parse(tokens: &[Token], sm: &SourceMap, file: &str) -> ParsResult<Program> {
-    let mut parser = Parser::new(tokens, sm, file);
+    let (program, mut errors) = parse_recovering(tokens, sm, file);
+    if errors.is_empty() {
+        return Ok(program);
+    }
+    let mut first = errors.remove(0);
+    first.additional.extend(errors);
+    Err(first)
+}
+
+/// Parses a whole file, recovering from syntax errors instead of stopping at the
+/// first one. Returns the partial program (every function and statement that did
+/// parse) together with all errors found, for tools such as the language server.
+pub fn parse_recovering(tokens: &[Token], sm: &SourceMap, file: &str) -> (Program, Vec<Diagnostic>) {
+    // Doc comments are trivia: set aside by the start of the token they precede
+    // and attached to the item starting there.
+    let mut docs: HashMap<usize, Vec<String>> = HashMap::new();
+    let code: Cow<[Token]> = if tokens.iter().any(|t| matches!(t.kind, TokenKind::DocComment(_))) {
+        let mut pending = Vec::new();
+        let mut code = Vec::with_capacity(tokens.len());
+        for t in tokens {
+            match &t.kind {
+                TokenKind::DocComment(text) => pending.push(text.clone()),
+                _ => {
+                    if !pending.is_empty() {
+                        docs.insert(t.span.start, std::mem::take(&mut pending));
+                    }
+                    code.push(t.clone());
+                }
+            }
+        }
+        Cow::Owned(code)
+    } else {
+        Cow::Borrowed(tokens)
+    };
+    let mut parser = Parser::new(&code, sm, file);
     let mut imports = Vec::new();
     let mut functions = Vec::new();
+    let mut externs = Vec::new();
+    let mut hooks = Vec::new();
+    let mut inits = Vec::new();
+    let mut top_level = Vec::new();
     let mut seen_aliases: HashMap<String, crate::span::Span> = HashMap::new();
 
     let start_span = parser.current_span();
@@ -22,96 +65,41 @@ pub fn parse(tokens: &[Token], sm: &SourceMap, file: &str) -> ParsResult<Program
             break;
         }
 
-        if parser.match_kind(TokenKind::Import) {
-            let import_start = parser.previous_span();
-            match parser.peek_kind() {
-                Some(TokenKind::String(path)) => {
-                    let path = path.clone();
-                    parser.advance();
-
-                    let alias = if parser.match_kind(TokenKind::As) {
-                        match parser.peek_kind() {
-                            Some(TokenKind::Ident(a)) => {
-                                let a = a.clone();
-                                parser.advance();
-                                Some(a)
-                            }
-                            _ => {
-                                return parser.error(
-                                    "Expected identifier after 'as'",
-                                    parser.current_span(),
-                                );
-                            }
-                        }
-                    } else {
-                        None
-                    };
-
-                    // Check for duplicate alias in same file
-                    if let Some(ref a) = alias {
-                        let alias_span = parser.previous_span();
-                        if seen_aliases.contains_key(a) {
-                            return parser.error(
-                                &format!("Duplicate import alias '{}'", a),
-                                alias_span,
-                            );
-                        }
-                        seen_aliases.insert(a.clone(), alias_span);
-                    }
-
-                    let span = import_start.merge(parser.previous_span());
-                    imports.push(Import { path, alias, span });
-                }
-                _ => {
-                    return parser.error(
-                        "Expected string literal after import",
-                        parser.current_span(),
-                    );
-                }
-            }
+        let item_start = parser.pos;
+        let doc = docs.remove(&parser.current_span().start).unwrap_or_default();
+        let result = if parser.match_kind(TokenKind::Import) {
+            parse_import(&mut parser, &mut seen_aliases).map(|import| imports.push(import))
         } else if parser.match_kind(TokenKind::Func) {
-            let start = parser.previous_span(); // 'func' span
-            let name = if let Some(TokenKind::Ident(s)) = parser.peek_kind() {
-                s.clone()
+            parse_function(&mut parser).map(|func| functions.push(Function { doc, ..func }))
+        } else if parser.match_kind(TokenKind::Extern) {
+            parse_extern(&mut parser).map(|ext| externs.push(ExternFunc { doc, ..ext }))
+        } else if parser.peek_kind() == Some(&TokenKind::At) {
+            parse_attributed_function(&mut parser).map(|func| functions.push(Function { doc, ..func }))
+        } else if matches!(parser.peek_kind(), Some(TokenKind::Ident(k)) if k == "init") {
+            if inits.is_empty() {
+                parse_init(&mut parser).map(|func| {
+                    inits.push(func.name.clone());
+                    functions.push(func);
+                })
             } else {
-                parser.error("Expected function name", parser.current_span())?
-            };
-            parser.advance();
-
-            parser.expect(TokenKind::LParen)?;
-            let mut params = Vec::new();
-            if !parser.match_kind(TokenKind::RParen) {
-                loop {
-                    if let Some(TokenKind::Ident(p)) = parser.peek_kind() {
-                        params.push(p.clone());
-                        parser.advance();
-                    } else {
-                        parser.error("Expected parameter name", parser.current_span())?;
-                    }
-                    if !parser.match_kind(TokenKind::Comma) {
-                        break;
-                    }
-                }
-                parser.expect(TokenKind::RParen)?;
+                parser.error("Only one `init { ... }` block is allowed per file", parser.current_span())
             }
-
-            let body = parser.parse_brace_stmt_block()?;
-            // RBrace consumed
-            let end = parser.previous_span();
-            let span = start.merge(end);
-
-            functions.push(Function {
-                name,
-                params,
-                body,
-                span,
-                file: file.to_string(),
-            });
+        } else if let Some(kind) = hook_kind(&parser) {
+            parse_hook(&mut parser, kind).map(|hook| hooks.push(hook))
         } else {
-            return parser.error(
-                "Top-level statements are not allowed. Move code into func main() { ... }.",
-                parser.current_span(),
-            );
+            parser.parse_stmt().map(|stmt| top_level.push(stmt))
+        };
+        if let Err(e) = result {
+            parser.recover_item(e, item_start);
+        }
+    }
+
+    // Shell would silently keep the last definition; name both instead.
+    for (i, func) in functions.iter().enumerate() {
+        if let Some(first) = functions[..i].iter().find(|f| f.name == func.name) {
+            let msg = format!("Function '{}' is already defined in this file", func.name);
+            let note = format!("'{}' was first defined here", func.name);
+            parser.errors.push(with_first_site(&parser, &msg, func.span, &note, first.span));
         }
     }
 
@@ -122,11 +110,291 @@ pub fn parse(tokens: &[Token], sm: &SourceMap, file: &str) -> ParsResult<Program
         start_span // Empty file?
     };
 
-    Ok(Program {
+    let program = Program {
         imports,
         functions,
+        externs,
+        hooks,
+        inits,
+        top_level,
         span,
         source_maps: HashMap::new(),  // Filled by loader later
         entry_file: file.to_string(), // Initial parse sets this, loader might override or correct it
+    };
+    (program, parser.errors)
+}
+
+/// Parses the rest of an `import` item; the keyword has been consumed.
+fn parse_import(
+    parser: &mut Parser,
+    seen_aliases: &mut HashMap<String, crate::span::Span>,
+) -> ParsResult<Import> {
+    let import_start = parser.previous_span();
+    let path = match parser.peek_kind() {
+        Some(TokenKind::String(path)) => {
+            let path = path.clone();
+            parser.advance();
+            path
+        }
+        _ => {
+            return parser.error(
+                "Expected string literal after import",
+                parser.current_span(),
+            );
+        }
+    };
+
+    let alias = if parser.match_kind(TokenKind::As) {
+        match parser.peek_kind() {
+            Some(TokenKind::Ident(a)) => {
+                let a = a.clone();
+                parser.advance();
+                Some(a)
+            }
+            _ => {
+                return parser.error(
+                    "Expected identifier after 'as'",
+                    parser.current_span(),
+                );
+            }
+        }
+    } else {
+        None
+    };
+
+    // Check for duplicate alias in same file
+    if let Some(ref a) = alias {
+        let alias_span = parser.previous_span();
+        if seen_aliases.contains_key(a) {
+            return parser.error(
+                &format!("Duplicate import alias '{}'", a),
+                alias_span,
+            );
+        }
+        seen_aliases.insert(a.clone(), alias_span);
+    }
+
+    let span = import_start.merge(parser.previous_span());
+    Ok(Import { path, alias, span })
+}
+
+/// Attributes understood by the compiler, with the number of string arguments
+/// each accepts.
+pub const ATTRIBUTES: &[(&str, std::ops::RangeInclusive<usize>)] = &[("deprecated", 0..=1), ("service", 0..=11), ("cron", 0..=4), ("cache", 0..=0), ("pure", 0..=0), ("inline", 0..=0)];
+
+/// Parses one or more `@name` / `@name("arg", ...)` lines followed by a `func`.
+fn parse_attributed_function(parser: &mut Parser) -> ParsResult<Function> {
+    let mut attrs: Vec<Attribute> = Vec::new();
+    while parser.match_kind(TokenKind::At) {
+        let start = parser.previous_span();
+        let name = match parser.peek_kind() {
+            Some(TokenKind::Ident(name)) => name.clone(),
+            _ => return parser.error("Expected attribute name after '@'", parser.current_span()),
+        };
+        parser.advance();
+        let name_span = parser.previous_span();
+
+        let mut args = Vec::new();
+        if parser.match_kind(TokenKind::LParen) && !parser.match_kind(TokenKind::RParen) {
+            loop {
+                match parser.peek_kind() {
+                    Some(TokenKind::String(s)) => {
+                        args.push(s.clone());
+                        parser.advance();
+                    }
+                    _ => {
+                        return parser.error("Attribute arguments must be string literals", parser.current_span());
+                    }
+                }
+                if !parser.match_kind(TokenKind::Comma) {
+                    break;
+                }
+            }
+            parser.expect(TokenKind::RParen)?;
+        }
+        let span = start.merge(parser.previous_span());
+
+        let Some((_, arity)) = ATTRIBUTES.iter().find(|(known, _)| *known == name) else {
+            return parser.error(&format!("unknown attribute '@{}'", name), name_span);
+        };
+        if !arity.contains(&args.len()) {
+            return parser.error(
+                &format!("@{} takes at most {} argument(s), got {}", name, arity.end(), args.len()),
+                span,
+            );
+        }
+        if attrs.iter().any(|a| a.name == name) {
+            return parser.error(&format!("duplicate attribute '@{}'", name), span);
+        }
+        let checked = match name.as_str() {
+            "service" => args.iter().try_for_each(|a| crate::systemd::check_arg(a)),
+            "cron" => args.iter().try_for_each(|a| crate::cron::check_arg(a)),
+            _ => Ok(()),
+        };
+        if let Err(msg) = checked {
+            return parser.error(&msg, span);
+        }
+        attrs.push(Attribute { name, args, span });
+        parser.consume_separators();
+    }
+
+    if !parser.match_kind(TokenKind::Func) {
+        return parser.error("Expected 'func' after attribute", parser.current_span());
+    }
+    let mut func = parse_function(parser)?;
+    func.attrs = attrs;
+    Ok(func)
+}
+
+/// Parses the rest of a `func` item; the keyword has been consumed.
+fn parse_function(parser: &mut Parser) -> ParsResult<Function> {
+    let start = parser.previous_span(); // 'func' span
+    let (name, params) = parse_signature(parser)?;
+    // `-> bool` is the only declared return type.
+    let returns_bool = parser.match_kind(TokenKind::Minus);
+    if returns_bool {
+        parser.expect(TokenKind::Gt)?;
+        match parser.peek_kind() {
+            Some(TokenKind::Ident(t)) if t == "bool" => {
+                parser.advance();
+            }
+            _ => parser.error("Expected `bool` after `->` (the only declared return type)", parser.current_span())?,
+        }
+    }
+
+    let body = parser.parse_brace_stmt_block()?;
+    // RBrace consumed
+    let end = parser.previous_span();
+    let span = start.merge(end);
+
+    Ok(Function {
+        doc: Vec::new(),
+        name,
+        params,
+        returns_bool,
+        body,
+        span,
+        file: parser.file.to_string(),
+        attrs: Vec::new(),
     })
 }
+
+/// Parses the rest of an `extern func name(params)` item; `extern` has been consumed.
+fn parse_extern(parser: &mut Parser) -> ParsResult<ExternFunc> {
+    let start = parser.previous_span(); // 'extern' span
+    parser.expect(TokenKind::Func)?;
+    let (name, params) = parse_signature(parser)?;
+    let span = start.merge(parser.previous_span());
+    if parser.peek_kind() == Some(&TokenKind::LBrace) {
+        return parser.error(
+            &format!("extern func '{}' cannot have a body; it is defined by the shell at runtime", name),
+            parser.current_span(),
+        );
+    }
+    Ok(ExternFunc {
+        doc: Vec::new(),
+        name,
+        params,
+        span,
+        file: parser.file.to_string(),
+    })
+}
+
+/// Parses `init { ... }` into a parameterless function that runs before the
+/// entry function.
+fn parse_init(parser: &mut Parser) -> ParsResult<Function> {
+    parser.advance();
+    let start = parser.previous_span();
+    let body = parser.parse_brace_stmt_block()?;
+    Ok(Function {
+        doc: Vec::new(),
+        name: crate::names::INIT.to_string(),
+        params: Vec::new(),
+        returns_bool: false,
+        body,
+        span: start.merge(parser.previous_span()),
+        file: parser.file.to_string(),
+        attrs: Vec::new(),
+    })
+}
+
+/// `on_enter` / `on_exit` at the start of an item.
+fn hook_kind(parser: &Parser) -> Option<HookKind> {
+    match parser.peek_kind() {
+        Some(TokenKind::Ident(k)) if k == "on_enter" => Some(HookKind::Enter),
+        Some(TokenKind::Ident(k)) if k == "on_exit" => Some(HookKind::Exit),
+        _ => None,
+    }
+}
+
+/// Parses `on_enter(name, ...) { ... }` / `on_exit(*) { ... }`.
+fn parse_hook(parser: &mut Parser, kind: HookKind) -> ParsResult<Hook> {
+    parser.advance();
+    let start = parser.previous_span();
+    parser.expect(TokenKind::LParen)?;
+    let mut targets = Vec::new();
+    loop {
+        match parser.peek_kind() {
+            Some(TokenKind::Ident(name)) => targets.push(name.clone()),
+            Some(TokenKind::Star) => targets.push("*".to_string()),
+            _ => return parser.error("Expected a function name or `*` in hook targets", parser.current_span()),
+        }
+        parser.advance();
+        if !parser.match_kind(TokenKind::Comma) {
+            break;
+        }
+    }
+    parser.expect(TokenKind::RParen)?;
+    let body = parser.parse_brace_stmt_block()?;
+    Ok(Hook {
+        kind,
+        targets,
+        body,
+        span: start.merge(parser.previous_span()),
+        file: parser.file.to_string(),
+    })
+}
+
+/// Parses `name(param, ...)` after `func`.
+fn parse_signature(parser: &mut Parser) -> ParsResult<(String, Vec<String>)> {
+    let name = if let Some(TokenKind::Ident(s)) = parser.peek_kind() {
+        s.clone()
+    } else {
+        parser.error("Expected function name", parser.current_span())?
+    };
+    parser.advance();
+
+    parser.expect(TokenKind::LParen)?;
+    let mut params: Vec<String> = Vec::new();
+    let mut param_spans = Vec::new();
+    if !parser.match_kind(TokenKind::RParen) {
+        loop {
+            if let Some(TokenKind::Ident(p)) = parser.peek_kind() {
+                let p = p.clone();
+                let span = parser.current_span();
+                if let Some(i) = params.iter().position(|q| *q == p) {
+                    let msg = format!("duplicate parameter '{}' in function '{}'", p, name);
+                    let note = format!("parameter '{}' was first declared here", p);
+                    return Err(with_first_site(parser, &msg, span, &note, param_spans[i]));
+                }
+                params.push(p);
+                param_spans.push(span);
+                parser.advance();
+            } else {
+                parser.error("Expected parameter name", parser.current_span())?;
+            }
+            if !parser.match_list_separator(&TokenKind::RParen) {
+                break;
+            }
+        }
+        parser.expect(TokenKind::RParen)?;
+    }
+    Ok((name, params))
+}
+
+/// An error at `span` followed by a note at the earlier `first` site.
+fn with_first_site(parser: &Parser, msg: &str, span: Span, note: &str, first: Span) -> Diagnostic {
+    let mut err = parser.error::<()>(msg, span).unwrap_err();
+    err.additional.push(parser.error::<()>(note, first).unwrap_err());
+    err
+}
@@ -0,0 +1,197 @@
+//! Targeted errors for shell syntax pasted into sh2 source: `$VAR`, `${VAR}`,
+//! `$1`, and command lines such as `echo hi` or `ls -la && make` at statement
+//! level. Each error's help line spells out the sh2 equivalent. Backticks are
+//! caught by the lexer.
+
+use super::common::{ParsResult, Parser};
+use crate::lexer::TokenKind;
+use crate::span::Span;
+
+impl<'a> Parser<'a> {
+    /// After a statement-initial identifier that is neither a call nor an
+    /// assignment: true if the line reads as a shell command (`echo hi`,
+    /// `ls -la`, or a bare `ls`).
+    pub(super) fn at_shell_command(&self, name_span: Span) -> bool {
+        let Some(t) = self.peek() else {
+            return true;
+        };
+        if self.sm.line_col(t.span.start).0 > self.sm.line_col(name_span.start).0 {
+            return true;
+        }
+        matches!(
+            t.kind,
+            TokenKind::String(_)
+                | TokenKind::Ident(_)
+                | TokenKind::Number(_)
+                | TokenKind::Minus
+                | TokenKind::Dollar
+                | TokenKind::Slash
+                | TokenKind::Star
+                | TokenKind::Semi
+                | TokenKind::RBrace
+                | TokenKind::AndAnd
+                | TokenKind::OrOr
+                | TokenKind::Pipe
+        )
+    }
+
+    /// The error for a shell command line starting at `name_span`, with its
+    /// `run(...)` / `print(...)` translation as help.
+    pub(super) fn shell_command_error<T>(&self, name_span: Span) -> ParsResult<T> {
+        let src = &self.sm.src()[name_span.start..];
+        let line = &src[..src.find('\n').unwrap_or(src.len())];
+        let parts = split_command_line(line);
+        let first = match parts.first() {
+            Some(Part::Command(words)) if !words.is_empty() => words,
+            _ => return self.error("Unexpected token", name_span),
+        };
+        let shown = first.iter().map(|w| w.text).collect::<Vec<_>>().join(" ");
+        let end = first.last().map_or(name_span.end, |w| name_span.start + w.end);
+        let span = Span::new(name_span.start, end.max(name_span.end));
+        let msg = if first[0].text == "echo" {
+            format!("`{}` is shell syntax; sh2 prints with print(...)", shown)
+        } else {
+            format!("`{}` is shell syntax; sh2 runs commands with run(...)", shown)
+        };
+        let help = if parts.len() == 1 && first.len() == 1 {
+            format!("write `run(\"{0}\")` to run the command, or `{0}()` to call a function", first[0].text)
+        } else {
+            format!("write it as: {}", translate(&parts))
+        };
+        self.error_with_help(&msg, &help, span).map_err(|d| d.with_code("E0103"))
+    }
+
+    /// The error for `$NAME`, `${NAME}` or `$1` once `$` (at `dollar`) has been
+    /// consumed, or `None` if what follows is not one of those.
+    pub(super) fn shell_variable_error<T>(&self, dollar: Span) -> Option<ParsResult<T>> {
+        let next = self.peek().filter(|t| t.span.start == dollar.end)?;
+        let (shown, name, span) = match &next.kind {
+            TokenKind::Ident(name) => (format!("${}", name), name.clone(), dollar.merge(next.span)),
+            TokenKind::Number(n) => {
+                let msg = format!("`${}` is shell syntax; sh2 reads script arguments with arg(n)", n);
+                let help = format!("use `arg({})`", n);
+                return Some(self.error_with_help(&msg, &help, dollar.merge(next.span)).map_err(|d| d.with_code("E0103")));
+            }
+            TokenKind::LBrace => {
+                let ident = self.tokens.get(self.pos + 1)?;
+                let TokenKind::Ident(name) = &ident.kind else {
+                    return None;
+                };
+                let close = self.tokens.get(self.pos + 2).filter(|t| t.kind == TokenKind::RBrace);
+                let end = close.map_or(ident.span, |t| t.span);
+                (format!("${{{}}}", name), name.clone(), dollar.merge(end))
+            }
+            _ => return None,
+        };
+        let msg = format!("`{}` is shell syntax; sh2 reads variables by name", shown);
+        let help = format!("use `{0}` for an sh2 variable, or `env.{0}` for an environment variable", name);
+        Some(self.error_with_help(&msg, &help, span).map_err(|d| d.with_code("E0103")))
+    }
+}
+
+/// One shell word, with its text and end offset within the line.
+struct Word<'s> {
+    text: &'s str,
+    end: usize,
+}
+
+enum Part<'s> {
+    Command(Vec<Word<'s>>),
+    Op(&'s str),
+}
+
+/// Splits a shell command line into commands and the `&&`, `||`, `|` and `;`
+/// between them, stopping at a comment or a closing `}`. Quotes are kept.
+fn split_command_line(line: &str) -> Vec<Part<'_>> {
+    let bytes = line.as_bytes();
+    let mut parts = vec![Part::Command(Vec::new())];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' | b'\t' | b'\r' => i += 1,
+            b'#' | b'}' => break,
+            b'&' | b'|' | b';' => {
+                let len = if i + 1 < bytes.len() && bytes[i + 1] == bytes[i] && bytes[i] != b';' { 2 } else { 1 };
+                parts.push(Part::Op(&line[i..i + len]));
+                parts.push(Part::Command(Vec::new()));
+                i += len;
+            }
+            _ => {
+                let start = i;
+                let mut quote = None;
+                while i < bytes.len() {
+                    let b = bytes[i];
+                    match quote {
+                        Some(q) if b == q => quote = None,
+                        Some(_) => {}
+                        None if b == b'"' || b == b'\'' => quote = Some(b),
+                        None if matches!(b, b' ' | b'\t' | b'\r' | b'&' | b'|' | b';' | b'}') => break,
+                        None => {}
+                    }
+                    i += 1;
+                }
+                if let Some(Part::Command(words)) = parts.last_mut() {
+                    words.push(Word { text: &line[start..i], end: i });
+                }
+            }
+        }
+    }
+    parts.retain(|p| !matches!(p, Part::Command(words) if words.is_empty()));
+    parts
+}
+
+/// The sh2 spelling of a split command line.
+fn translate(parts: &[Part]) -> String {
+    let piped = parts.iter().any(|p| matches!(p, Part::Op("|")));
+    let mut out = String::new();
+    for part in parts {
+        match part {
+            Part::Op(";") => out.push_str("; "),
+            Part::Op(op) => {
+                out.push(' ');
+                out.push_str(op);
+                out.push(' ');
+            }
+            Part::Command(words) if words[0].text == "echo" && !piped => {
+                let args: Vec<String> = words[1..].iter().map(|w| sh2_word(w.text)).collect();
+                out.push_str(&format!("print({})", join_print_args(&args)));
+            }
+            Part::Command(words) => {
+                let args: Vec<String> = words.iter().map(|w| sh2_word(w.text)).collect();
+                out.push_str(&format!("run({})", args.join(", ")));
+            }
+        }
+    }
+    out
+}
+
+/// A shell word as an sh2 expression: `$NAME` becomes `env.NAME`, anything
+/// else a string literal.
+fn sh2_word(word: &str) -> String {
+    if let Some(name) = word.strip_prefix('$')
+        && !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return format!("env.{}", name);
+    }
+    let unquoted = if word.len() >= 2
+        && (word.starts_with('"') && word.ends_with('"') || word.starts_with('\'') && word.ends_with('\''))
+    {
+        &word[1..word.len() - 1]
+    } else {
+        word
+    };
+    format!("\"{}\"", unquoted.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `echo`'s arguments joined by spaces, as one `print` argument.
+fn join_print_args(args: &[String]) -> String {
+    if args.is_empty() {
+        return "\"\"".to_string();
+    }
+    if args.iter().all(|a| a.starts_with('"')) {
+        let inner: Vec<&str> = args.iter().map(|a| &a[1..a.len() - 1]).collect();
+        return format!("\"{}\"", inner.join(" "));
+    }
+    args.join(" & \" \" & ")
+}
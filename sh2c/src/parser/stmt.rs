@@ -1,9 +1,62 @@
-use super::common::{ParsResult, Parser};
+use super::common::{is_expr_start, ParsResult, Parser};
 use crate::ast::*;
 use crate::lexer::TokenKind;
 use crate::span::Span;
 use crate::sudo::SudoSpec;
 
+/// Statement builtins that take `name=value` arguments.
+const NAMED_ARG_STMTS: &[&str] =
+    &["wait_for_port", "write_file", "replace_in_file", "append_line_if_missing", "mkdirs", "copy", "move", "remove"];
+
+/// Characters a `glob(...)` case pattern treats specially.
+pub(crate) const GLOB_CHARS: [char; 3] = ['*', '?', '['];
+
+/// Checks a `glob(...)` case pattern: `[...]` classes must be closed, non-empty
+/// and made of characters that are safe unquoted in a shell `case` pattern, and
+/// the pattern may not start or end with whitespace, which is almost always a
+/// typo since it has to match exactly.
+fn check_glob(pattern: &str) -> Result<(), String> {
+    if pattern.starts_with(char::is_whitespace) || pattern.ends_with(char::is_whitespace) {
+        return Err("starts or ends with whitespace; remove it (spaces inside the pattern are fine)".to_string());
+    }
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ']' => return Err("has a `]` without a matching `[`; use literal(...) to match brackets exactly".to_string()),
+            '[' => {
+                if matches!(chars.peek(), Some('!' | '^')) {
+                    chars.next();
+                }
+                let mut empty = true;
+                loop {
+                    match chars.next() {
+                        None => return Err("has an unclosed `[`; close the class or use literal(...) to match brackets exactly".to_string()),
+                        Some(']') if empty => return Err("has an empty `[]` class".to_string()),
+                        Some(']') => break,
+                        Some('[') if chars.peek() == Some(&':') => {
+                            chars.next();
+                            let name: String = chars.by_ref().take_while(|c| *c != ':').collect();
+                            if chars.next() != Some(']') || !name.chars().all(|c| c.is_ascii_lowercase()) || name.is_empty() {
+                                return Err("has a malformed `[:class:]` inside `[...]`".to_string());
+                            }
+                        }
+                        Some(c) if c.is_ascii_alphanumeric() || "-_.,:+@%/=~*?".contains(c) => {}
+                        Some(c) => {
+                            return Err(format!(
+                                "uses '{}' inside `[...]`; classes may hold letters, digits, `[:class:]` names and `-_.,:+@%/=~*?`",
+                                c
+                            ))
+                        }
+                    }
+                    empty = false;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 impl<'a> Parser<'a> {
     pub fn parse_stmt(&mut self) -> ParsResult<Stmt> {
         let mut left = self.parse_stmt_atom()?;
@@ -39,12 +92,22 @@ impl<'a> Parser<'a> {
     pub fn parse_brace_stmt_block(&mut self) -> ParsResult<Vec<Stmt>> {
         self.expect(TokenKind::LBrace)?;
         let mut body = Vec::new();
+        let errors_before = self.errors.len();
         loop {
             self.consume_separators();
-            if self.peek_kind() == Some(&TokenKind::RBrace) {
-                break;
+            match self.peek_kind() {
+                Some(TokenKind::RBrace) => break,
+                // Recovery stopped at the next function: this block was left open by
+                // an error that has already been reported.
+                Some(TokenKind::Func) if self.errors.len() > errors_before => return Ok(body),
+                _ => {}
+            }
+            let start = self.pos;
+            match self.parse_stmt() {
+                Ok(stmt) => body.push(stmt),
+                Err(e) if self.peek().is_none() => return Err(e),
+                Err(e) => self.recover_stmt(e, start),
             }
-            body.push(self.parse_stmt()?);
         }
         self.expect(TokenKind::RBrace)?;
         Ok(body)
@@ -52,7 +115,7 @@ impl<'a> Parser<'a> {
 
     fn parse_stmt_atom(&mut self) -> ParsResult<Stmt> {
         let start_span = self.current_span();
-        let kind = self.peek_kind().cloned();
+        let kind = self.peek_kind();
 
         if kind.is_none() {
             self.error("Expected statement, got EOF", start_span)?;
@@ -60,6 +123,33 @@ impl<'a> Parser<'a> {
         let kind = kind.unwrap();
 
         let stmt_kind = match kind {
+            TokenKind::Let if self.tokens.get(self.pos + 1).map(|t| &t.kind) == Some(&TokenKind::LParen) => {
+                // `let (a, b) = ...` destructures; see StmtKind::LetValues.
+                self.advance();
+                self.advance();
+                let mut names = Vec::new();
+                loop {
+                    match self.peek_kind() {
+                        Some(TokenKind::Ident(s)) => names.push(Spanned::new(s.clone(), self.current_span())),
+                        _ => self.error("Expected variable name in `let (...)`", self.current_span())?,
+                    }
+                    self.advance();
+                    if !self.match_kind(TokenKind::Comma) {
+                        break;
+                    }
+                }
+                self.expect(TokenKind::RParen)?;
+                if names.len() < 2 {
+                    return self.error_with_help(
+                        "Expected at least two names in `let (...)`",
+                        "bind a single value with `let name = ...`",
+                        start_span.merge(self.previous_span()),
+                    );
+                }
+                self.expect(TokenKind::Equals)?;
+                let value = self.parse_expr()?;
+                StmtKind::LetValues { names, value, tuple: true }
+            }
             TokenKind::Let => {
                 self.advance();
                 let name = if let Some(TokenKind::Ident(s)) = self.peek_kind() {
@@ -69,9 +159,29 @@ impl<'a> Parser<'a> {
                     self.error("Expected variable name after let", self.current_span())?
                 };
                 self.advance();
-                self.expect(TokenKind::Equals)?;
-                let value = self.parse_expr()?;
-                StmtKind::Let { name, value }
+                if self.match_kind(TokenKind::Comma) {
+                    let mut names = vec![name];
+                    loop {
+                        match self.peek_kind() {
+                            Some(TokenKind::Ident(s)) => names.push(Spanned::new(s.clone(), self.current_span())),
+                            _ => self.error("Expected variable name after ','", self.current_span())?,
+                        }
+                        self.advance();
+                        if !self.match_kind(TokenKind::Comma) {
+                            break;
+                        }
+                    }
+                    self.expect(TokenKind::Equals)?;
+                    let value = self.parse_expr()?;
+                    StmtKind::LetValues { names, value, tuple: false }
+                } else {
+                    self.expect(TokenKind::Equals)?;
+                    let value = match self.parse_let_pipeline()? {
+                        Some(pipeline) => pipeline,
+                        None => self.parse_expr()?,
+                    };
+                    StmtKind::Let { name, value }
+                }
             }
             TokenKind::Run => {
                 let mut segments = Vec::new();
@@ -79,7 +189,8 @@ impl<'a> Parser<'a> {
                 
                 // Parse first run call
                 let start_run = self.current_span();
-                let run_call = self.parse_run_call()?;
+                let mut run_call = self.parse_run_call()?;
+                run_call.stderr = self.parse_pipe_segment_stderr()?;
                 segments.push(Spanned::new(PipeSegment::Run(run_call), start_run.merge(self.previous_span())));
 
                 while self.match_kind(TokenKind::Pipe) {
@@ -90,6 +201,12 @@ impl<'a> Parser<'a> {
                     // Extract back if just one
                     let seg = segments.pop().unwrap();
                     if let PipeSegment::Run(r) = seg.node {
+                        if let Some(t) = &r.stderr {
+                            return self.error(
+                                "stderr= is only supported on pipeline segments; use `with redirect { stderr: ... }` for a single command",
+                                t.span,
+                            );
+                        }
                         StmtKind::Run(r)
                     } else {
                         unreachable!()
@@ -105,7 +222,7 @@ impl<'a> Parser<'a> {
                 if !self.match_kind(TokenKind::RParen) {
                     loop {
                         args.push(self.parse_expr()?);
-                        if !self.match_kind(TokenKind::Comma) {
+                        if !self.match_list_separator(&TokenKind::RParen) {
                             break;
                         }
                     }
@@ -120,6 +237,7 @@ impl<'a> Parser<'a> {
                 self.advance();
                 self.expect(TokenKind::LParen)?;
                 let expr = self.parse_expr()?;
+                self.match_kind(TokenKind::Comma);
                 self.expect(TokenKind::RParen)?;
                 StmtKind::Print(expr)
             }
@@ -127,6 +245,7 @@ impl<'a> Parser<'a> {
                 self.advance();
                 self.expect(TokenKind::LParen)?;
                 let expr = self.parse_expr()?;
+                self.match_kind(TokenKind::Comma);
                 self.expect(TokenKind::RParen)?;
                 StmtKind::PrintErr(expr)
             }
@@ -183,35 +302,50 @@ impl<'a> Parser<'a> {
                     }
                     let mut patterns = Vec::new();
                     loop {
-                        if let Some(TokenKind::String(s)) = self.peek_kind() {
+                        let pat_start = self.current_span();
+                        let pattern = if let Some(TokenKind::String(s)) = self.peek_kind() {
                             let s = s.clone();
+                            if s.contains(GLOB_CHARS) {
+                                let msg = format!(
+                                    "string case pattern \"{}\" contains glob characters that match literally; write glob(\"{}\") to match it as a pattern or literal(\"{}\") to match it exactly",
+                                    s, s, s
+                                );
+                                self.error(&msg, pat_start).map_err(|d| d.with_code("E0104"))?;
+                            }
                             self.advance();
-                            patterns.push(Pattern::Literal(s));
+                            Pattern::Literal(s)
                         } else if let Some(TokenKind::Ident(s)) = self.peek_kind() {
-                            if s == "glob" {
+                            if s == "glob" || s == "literal" {
+                                let is_glob = s == "glob";
                                 self.advance();
                                 self.expect(TokenKind::LParen)?;
-                                if let Some(TokenKind::String(p)) = self.peek_kind() {
-                                    patterns.push(Pattern::Glob(p.clone()));
+                                let pattern = if let Some(TokenKind::String(p)) = self.peek_kind() {
+                                    let p = p.clone();
+                                    if is_glob && let Err(why) = check_glob(&p) {
+                                        self.error(&format!("glob pattern \"{}\" {}", p, why), self.current_span()).map_err(|d| d.with_code("E0104"))?;
+                                    }
                                     self.advance();
+                                    if is_glob { Pattern::Glob(p) } else { Pattern::Literal(p) }
                                 } else {
                                     self.error(
-                                        "Expected string literal for glob",
+                                        &format!("Expected string literal for {}", if is_glob { "glob" } else { "literal" }),
                                         self.current_span(),
-                                    )?;
-                                }
+                                    )?
+                                };
                                 self.expect(TokenKind::RParen)?;
+                                pattern
                             } else {
                                 self.error(
-                                    "Expected string, glob(\"...\"), or _",
+                                    "Expected string, glob(\"...\"), literal(\"...\"), or _",
                                     self.current_span(),
-                                )?;
+                                )?
                             }
                         } else if self.match_kind(TokenKind::Underscore) {
-                            patterns.push(Pattern::Wildcard);
+                            Pattern::Wildcard
                         } else {
-                            self.error("Expected pattern", self.current_span())?;
-                        }
+                            self.error("Expected pattern", self.current_span())?
+                        };
+                        patterns.push(Spanned::new(pattern, pat_start.merge(self.previous_span())));
 
                         if !self.match_kind(TokenKind::Pipe) {
                             break;
@@ -287,7 +421,7 @@ impl<'a> Parser<'a> {
                             } else {
                                 // This is a list: continue parsing comma-separated items
                                 items.push(first_expr);
-                                while self.match_kind(TokenKind::Comma) {
+                                while self.match_list_separator(&TokenKind::RParen) {
                                     items.push(self.parse_expr()?);
                                 }
                                 self.expect(TokenKind::RParen)?;
@@ -396,16 +530,28 @@ impl<'a> Parser<'a> {
             }
             TokenKind::Return => {
                 self.advance();
-                let val = if is_expr_start(self.peek_kind()) {
+                let val = if self.at_optional_value() {
                     Some(self.parse_expr()?)
                 } else {
                     None
                 };
-                StmtKind::Return(val)
+                match val {
+                    Some(first) if self.match_kind(TokenKind::Comma) => {
+                        let mut values = vec![first];
+                        loop {
+                            values.push(self.parse_expr()?);
+                            if !self.match_kind(TokenKind::Comma) {
+                                break;
+                            }
+                        }
+                        StmtKind::ReturnValues(values)
+                    }
+                    val => StmtKind::Return(val),
+                }
             }
             TokenKind::Exit => {
                 self.advance();
-                let code = if is_expr_start(self.peek_kind()) {
+                let code = if self.at_optional_value() {
                     Some(self.parse_expr()?)
                 } else {
                     None
@@ -494,9 +640,27 @@ impl<'a> Parser<'a> {
                     self.expect(TokenKind::RParen)?;
                     let body = self.parse_brace_stmt_block()?;
                     StmtKind::WithLog { path, append, body }
+                } else if matches!(self.peek_kind(), Some(TokenKind::Ident(s)) if s == "deadline") {
+                    self.advance();
+                    self.expect(TokenKind::LParen)?;
+                    let duration = self.parse_expr()?;
+                    self.expect(TokenKind::RParen)?;
+                    let body = self.parse_brace_stmt_block()?;
+                    StmtKind::WithDeadline { duration, body }
+                } else if matches!(self.peek_kind(), Some(TokenKind::Ident(s)) if s == "umask") {
+                    self.advance();
+                    self.expect(TokenKind::LParen)?;
+                    let mask = self.parse_expr()?;
+                    self.expect(TokenKind::RParen)?;
+                    let body = self.parse_brace_stmt_block()?;
+                    StmtKind::WithUmask { mask, body }
+                } else if matches!(self.peek_kind(), Some(TokenKind::Ident(s)) if s == "nice" || s == "ionice" || s == "ulimit") {
+                    let limit = self.parse_limit()?;
+                    let body = self.parse_brace_stmt_block()?;
+                    StmtKind::WithLimits { limit, body }
                 } else {
                     self.error(
-                        "Expected 'env', 'cwd', 'redirect', or 'log' after 'with'",
+                        "Expected 'env', 'cwd', 'redirect', 'log', 'deadline', 'umask', 'nice', 'ionice', or 'ulimit' after 'with'",
                         self.current_span(),
                     )?
                 }
@@ -603,9 +767,11 @@ impl<'a> Parser<'a> {
                              cmd: Box::new(cmd),
                              options,
                         },
-                        span: self.current_span(), // Approximate span, or use better tracking
+                        span: start_span.merge(self.previous_span()),
                     })
-                } else if self.match_kind(TokenKind::LBrace) {
+                } else if self.peek_kind() == Some(&TokenKind::LBrace) || self.at_sh_bridge() {
+                    let bridge = self.parse_sh_bridge()?;
+                    self.expect(TokenKind::LBrace)?;
                     let mut lines = Vec::new();
                     while !self.match_kind(TokenKind::RBrace) {
                         if let Some(TokenKind::String(s)) = self.peek_kind() {
@@ -623,7 +789,7 @@ impl<'a> Parser<'a> {
                             }
                         }
                     }
-                    StmtKind::ShBlock(lines)
+                    StmtKind::ShBlock { lines, bridge }
                 } else {
                     self.error("Expected ( or {{ after sh", self.current_span())?
                 }
@@ -753,7 +919,11 @@ impl<'a> Parser<'a> {
                     StmtKind::Run(RunCall {
                         args: run_args,
                         options: run_options,
+                        stderr: None,
                     })
+                } else if name == "unchecked" && self.peek_kind() == Some(&TokenKind::LBrace) {
+                    let body = self.parse_brace_stmt_block()?;
+                    StmtKind::Unchecked { body }
                 } else if self.peek_kind() == Some(&TokenKind::Dot) {
                     self.advance(); // consume Dot
                     let (func_name, func_span, args, _) =
@@ -768,26 +938,42 @@ impl<'a> Parser<'a> {
                     // Generic Call: name(args, ...)
                     self.expect(TokenKind::LParen)?;
                     let mut args = Vec::new();
+                    let mut options = Vec::new();
+                    let allows_named = NAMED_ARG_STMTS.contains(&name.as_str());
                     if !self.match_kind(TokenKind::RParen) {
                         loop {
                             // Lookahead for named argument key=value
-                            if let Some(TokenKind::Ident(_)) = self.peek_kind() {
-                                if let Some(TokenKind::Equals) = self.tokens.get(self.pos + 1).map(|t| &t.kind) {
-                                    return self.error(
-                                        "Named arguments are only supported for builtins: run, sudo, sh, capture, confirm",
-                                        self.current_span()
+                            if let Some(TokenKind::Ident(opt_name)) = self.peek_kind()
+                                && let Some(TokenKind::Equals) = self.tokens.get(self.pos + 1).map(|t| &t.kind)
+                            {
+                                if !allows_named {
+                                    let msg = format!(
+                                        "Named arguments are only supported for builtins: run, sudo, sh, capture, confirm, {}",
+                                        NAMED_ARG_STMTS.join(", ")
                                     );
+                                    return self.error(&msg, self.current_span());
                                 }
+                                let opt_name = opt_name.clone();
+                                let opt_span = self.advance().unwrap().span;
+                                self.expect(TokenKind::Equals)?;
+                                let value = self.parse_expr()?;
+                                options.push(CallOption { name: opt_name, value, span: opt_span });
+                                if !self.match_list_separator(&TokenKind::RParen) {
+                                    break;
+                                }
+                                continue;
                             }
 
                             args.push(self.parse_expr()?);
-                            if !self.match_kind(TokenKind::Comma) {
+                            if !self.match_list_separator(&TokenKind::RParen) {
                                 break;
                             }
                         }
                         self.expect(TokenKind::RParen)?;
                     }
-                    StmtKind::Call { name, args }
+                    StmtKind::Call { name, args, options }
+                } else if self.at_shell_command(name_span) {
+                    return self.shell_command_error(name_span);
                 } else {
                     // Regular assignment? Ident = Expr
                     self.expect(TokenKind::Equals)?;
@@ -799,7 +985,7 @@ impl<'a> Parser<'a> {
                 }
             }
             TokenKind::Import => {
-                self.error("import is only allowed at top-level", start_span)?
+                self.error("import is only allowed at top-level", start_span).map_err(|d| d.with_code("E0101"))?
             }
             _ => {
                 self.error(format!("Unexpected token: {:?}", kind).as_str(), start_span)?
@@ -817,23 +1003,41 @@ impl<'a> Parser<'a> {
         self.expect(TokenKind::LParen)?;
         let mut args = Vec::new();
         let mut options = Vec::new();
+        let mut saw_check = false;
 
         while !self.match_kind(TokenKind::RParen) {
-            let is_option = if let Some(TokenKind::Ident(_)) = self.peek_kind() {
-                if let Some(TokenKind::Equals) = self.tokens.get(self.pos + 1).map(|t| &t.kind) {
-                    true
-                } else {
-                    false
-                }
-            } else {
-                false
+            // `stdin`, `env` and `cwd` lex as keywords but are also accepted as option names.
+            let name = match self.peek_kind() {
+                Some(TokenKind::Ident(s)) => Some(s.as_str()),
+                Some(TokenKind::Stdin) => Some("stdin"),
+                Some(TokenKind::Env) => Some("env"),
+                Some(TokenKind::Cwd) => Some("cwd"),
+                _ => None,
             };
+            let is_option = name.is_some()
+                && self.tokens.get(self.pos + 1).map(|t| &t.kind) == Some(&TokenKind::Equals);
 
             if is_option {
-                let name = if let Some(TokenKind::Ident(s)) = self.peek_kind() { s.clone() } else { unreachable!() };
+                let name = name.unwrap().to_string();
                 let name_span = self.advance().unwrap().span;
                 self.expect(TokenKind::Equals)?;
-                let value = self.parse_expr()?;
+                let mut value = self.parse_expr()?;
+                // `check=false` is spelled `allow_fail=true` from here on.
+                let name = if name == "check" {
+                    let ExprKind::Bool(b) = value.node else {
+                        return self.error("check must be true or false", value.span);
+                    };
+                    if options.iter().any(|o: &CallOption| o.name == "allow_fail") {
+                        return self.error("check and allow_fail cannot both be given", name_span);
+                    }
+                    saw_check = true;
+                    value.node = ExprKind::Bool(!b);
+                    "allow_fail".to_string()
+                } else if name == "allow_fail" && saw_check {
+                    return self.error("check and allow_fail cannot both be given", name_span);
+                } else {
+                    name
+                };
                 options.push(CallOption {
                     name,
                     value,
@@ -845,7 +1049,130 @@ impl<'a> Parser<'a> {
 
             self.match_kind(TokenKind::Comma);
         }
-        Ok(RunCall { args, options })
+        Ok(RunCall { args, options, stderr: None })
+    }
+
+    /// The setting of `with nice(n)`, `with ionice(class[, level])` or
+    /// `with ulimit(name=value, ...)`, checked here since it must be a literal.
+    fn parse_limit(&mut self) -> ParsResult<Limit> {
+        let kind = match self.peek_kind() {
+            Some(TokenKind::Ident(s)) => s.clone(),
+            _ => return self.error("Expected nice, ionice or ulimit", self.current_span()),
+        };
+        let start = self.advance().unwrap().span;
+        let call = self.parse_call_args_and_options()?;
+        let span = start.merge(self.previous_span());
+        // `-5` parses as `0 - 5`.
+        let whole = |e: &Expr| match &e.node {
+            ExprKind::Number(n) => Some(*n as i64),
+            ExprKind::Arith { left, op: ArithOp::Sub, right } => match (&left.node, &right.node) {
+                (ExprKind::Number(0), ExprKind::Number(n)) => Some(-(*n as i64)),
+                _ => None,
+            },
+            _ => None,
+        };
+        match kind.as_str() {
+            "nice" => {
+                if call.args.len() != 1 || !call.options.is_empty() {
+                    return self.error("nice() requires exactly one argument, e.g. `with nice(10) { ... }`", span);
+                }
+                match whole(&call.args[0]) {
+                    Some(n) if (-20..=19).contains(&n) => Ok(Limit::Nice(n)),
+                    _ => self.error("nice() value must be an integer from -20 to 19", call.args[0].span),
+                }
+            }
+            "ionice" => {
+                if call.args.is_empty() || call.args.len() > 2 || !call.options.is_empty() {
+                    return self.error("ionice() requires 1 or 2 arguments (class, level), e.g. `with ionice(\"best-effort\", 7) { ... }`", span);
+                }
+                let class = match &call.args[0].node {
+                    ExprKind::Literal(s) if ["realtime", "best-effort", "idle"].contains(&s.as_str()) => s.clone(),
+                    _ => return self.error("ionice() class must be \"idle\", \"best-effort\" or \"realtime\"", call.args[0].span),
+                };
+                let level = match call.args.get(1) {
+                    None => None,
+                    Some(arg) if class == "idle" => return self.error("the \"idle\" I/O class takes no level", arg.span),
+                    Some(arg) => match whole(arg) {
+                        Some(n) if (0..=7).contains(&n) => Some(n),
+                        _ => return self.error("ionice() level must be an integer from 0 to 7", arg.span),
+                    },
+                };
+                Ok(Limit::Ionice { class, level })
+            }
+            _ => {
+                if !call.args.is_empty() {
+                    return self.error("ulimit() does not accept positional arguments; name each limit, e.g. `ulimit(nofile=1024)`", span);
+                }
+                if call.options.is_empty() {
+                    return self.error("ulimit() requires at least one limit, e.g. `with ulimit(nofile=1024, cpu=60) { ... }`", span);
+                }
+                let mut limits: Vec<(String, Option<i64>)> = Vec::new();
+                for opt in call.options {
+                    if !ULIMITS.iter().any(|(name, _)| *name == opt.name) {
+                        let msg = format!("unknown option '{}' for ulimit(); use nofile, cpu, fsize, core, stack or vmem", opt.name);
+                        return self.error(&msg, opt.span);
+                    }
+                    if limits.iter().any(|(name, _)| *name == opt.name) {
+                        return self.error(&format!("ulimit() option '{}' is specified more than once", opt.name), opt.span);
+                    }
+                    let value = match &opt.value.node {
+                        ExprKind::Literal(s) if s == "unlimited" => None,
+                        ExprKind::Number(n) => Some(*n as i64),
+                        _ => {
+                            let msg = format!("ulimit() option '{}' must be a non-negative integer or \"unlimited\"", opt.name);
+                            return self.error(&msg, opt.value.span);
+                        }
+                    };
+                    limits.push((opt.name, value));
+                }
+                Ok(Limit::Ulimit(limits))
+            }
+        }
+    }
+
+    /// True at `use(` or `export(` following `sh`.
+    fn at_sh_bridge(&self) -> bool {
+        let is_list_kw = match self.peek_kind() {
+            Some(TokenKind::Ident(s)) => s == "use",
+            Some(TokenKind::Export) => true,
+            _ => false,
+        };
+        is_list_kw && self.tokens.get(self.pos + 1).map(|t| &t.kind) == Some(&TokenKind::LParen)
+    }
+
+    /// Parses the optional `use(a, b) export(c)` lists of a `sh { ... }` block.
+    fn parse_sh_bridge(&mut self) -> ParsResult<Option<ShBridge>> {
+        if !self.at_sh_bridge() {
+            return Ok(None);
+        }
+        let mut bridge = ShBridge { uses: Vec::new(), exports: Vec::new() };
+        if matches!(self.peek_kind(), Some(TokenKind::Ident(s)) if s == "use") {
+            self.advance();
+            bridge.uses = self.parse_sh_bridge_names("use")?;
+        }
+        if self.match_kind(TokenKind::Export) {
+            bridge.exports = self.parse_sh_bridge_names("export")?;
+        }
+        Ok(Some(bridge))
+    }
+
+    fn parse_sh_bridge_names(&mut self, list: &str) -> ParsResult<Vec<String>> {
+        self.expect(TokenKind::LParen)?;
+        let mut names: Vec<String> = Vec::new();
+        while !self.match_kind(TokenKind::RParen) {
+            let Some(TokenKind::Ident(name)) = self.peek_kind() else {
+                return self.error(&format!("Expected variable name in sh {}(...)", list), self.current_span());
+            };
+            if names.contains(name) {
+                return self.error(&format!("'{}' is listed twice in sh {}(...)", name, list), self.current_span());
+            }
+            names.push(name.clone());
+            self.advance();
+            if !self.match_kind(TokenKind::Comma) && self.peek_kind() != Some(&TokenKind::RParen) {
+                return self.error("Expected comma or closing parenthesis", self.current_span());
+            }
+        }
+        Ok(names)
     }
 
     fn parse_run_call(&mut self) -> ParsResult<RunCall> {
@@ -878,11 +1205,17 @@ impl<'a> Parser<'a> {
             Ok(Spanned::new(PipeSegment::Block(body), start.merge(end)))
         } else if self.peek_kind() == Some(&TokenKind::Run) {
             let start = self.current_span();
-            let call = self.parse_run_call()?;
+            let mut call = self.parse_run_call()?;
+            call.stderr = self.parse_pipe_segment_stderr()?;
             let end = self.previous_span();
             Ok(Spanned::new(PipeSegment::Run(call), start.merge(end)))
         } else if self.match_kind(TokenKind::EachLine) {
             let start = self.previous_span();
+            let fd = if self.peek_kind() == Some(&TokenKind::LParen) {
+                Some(self.parse_each_line_fd()?)
+            } else {
+                None
+            };
             let ident = if let Some(TokenKind::Ident(s)) = self.peek_kind() {
                 let span = self.peek().unwrap().span;
                 Spanned::new(s.clone(), span)
@@ -892,11 +1225,12 @@ impl<'a> Parser<'a> {
             self.advance();
             let body = self.parse_brace_stmt_block()?;
             let end = self.previous_span();
-            Ok(Spanned::new(PipeSegment::EachLine(ident, body), start.merge(end)))
+            Ok(Spanned::new(PipeSegment::EachLine(ident, fd, body), start.merge(end)))
         } else if let Some(TokenKind::Ident(s)) = self.peek_kind() {
             if s == "sudo" {
                 let start = self.current_span();
-                let call = self.parse_sudo_call()?;
+                let mut call = self.parse_sudo_call()?;
+                call.stderr = self.parse_pipe_segment_stderr()?;
                 let end = self.previous_span();
                 Ok(Spanned::new(PipeSegment::Sudo(call), start.merge(end)))
             } else {
@@ -907,6 +1241,71 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// The `(fd=N)` of `each_line(fd=N) var`; 0-2 are the script's own streams.
+    fn parse_each_line_fd(&mut self) -> ParsResult<u8> {
+        let start = self.current_span();
+        let call = self.parse_call_args_and_options()?;
+        let span = start.merge(self.previous_span());
+        match (call.args.as_slice(), call.options.as_slice()) {
+            ([], [opt]) if opt.name == "fd" => match opt.value.node {
+                ExprKind::Number(n) if (3..=9).contains(&n) => Ok(n as u8),
+                _ => self.error("each_line fd must be an integer from 3 to 9", opt.value.span),
+            },
+            _ => self.error("each_line accepts only an fd option, e.g. `each_line(fd=3) line { ... }`", span),
+        }
+    }
+
+    /// Parse `run(...) | ...` on the right-hand side of `let`. Returns `None` (without
+    /// consuming input) unless a `run(...)` is followed by `|`, so plain `run(...)`
+    /// expressions keep their existing parse.
+    fn parse_let_pipeline(&mut self) -> ParsResult<Option<Expr>> {
+        if self.peek_kind() != Some(&TokenKind::Run) {
+            return Ok(None);
+        }
+        let save = self.pos;
+        let start = self.current_span();
+        let Ok(mut first) = self.parse_run_call() else {
+            self.pos = save;
+            return Ok(None);
+        };
+        first.stderr = self.parse_pipe_segment_stderr()?;
+        if self.peek_kind() != Some(&TokenKind::Pipe) {
+            self.pos = save;
+            return Ok(None);
+        }
+        let mut segments = vec![Spanned::new(PipeSegment::Run(first), start.merge(self.previous_span()))];
+        while self.match_kind(TokenKind::Pipe) {
+            let seg = self.parse_pipe_segment()?;
+            if matches!(seg.node, PipeSegment::EachLine(..)) {
+                return self.error("each_line cannot be used in a let-bound pipeline; it produces no output to capture", seg.span);
+            }
+            segments.push(seg);
+        }
+        Ok(Some(Expr {
+            node: ExprKind::Pipeline(segments),
+            span: start.merge(self.previous_span()),
+        }))
+    }
+
+    /// Parse an optional `stderr=<target>` suffix after a run/sudo pipeline segment.
+    fn parse_pipe_segment_stderr(&mut self) -> ParsResult<Option<Box<Spanned<RedirectOutputTarget>>>> {
+        if self.peek_kind() != Some(&TokenKind::Stderr)
+            || self.tokens.get(self.pos + 1).map(|t| &t.kind) != Some(&TokenKind::Equals)
+        {
+            return Ok(None);
+        }
+        self.advance();
+        self.advance();
+        let start = self.current_span();
+        let target = self.parse_redirect_output_target()?;
+        let span = start.merge(self.previous_span());
+        match target {
+            RedirectOutputTarget::File { .. } | RedirectOutputTarget::ToStdout => {
+                Ok(Some(Box::new(Spanned::new(target, span))))
+            }
+            _ => self.error("pipeline segment stderr= supports only file(...) or to_stdout()", span),
+        }
+    }
 
     // Parse stdout/stderr redirect target (single or list)
     fn parse_redirect_output_list(&mut self, stream_name: &str) -> ParsResult<Vec<Spanned<RedirectOutputTarget>>> {
@@ -1096,56 +1495,17 @@ impl<'a> Parser<'a> {
                 self.advance();
                 self.expect(TokenKind::RParen)?;
                 Ok(RedirectInputTarget::HereDoc { content })
+            } else if s == "text" {
+                self.advance();
+                self.expect(TokenKind::LParen)?;
+                let value = self.parse_expr()?;
+                self.expect(TokenKind::RParen)?;
+                Ok(RedirectInputTarget::Text { value })
             } else {
-                self.error("Expected redirect input target (file or heredoc)", self.current_span())?
+                self.error("Expected redirect input target (file, heredoc, or text)", self.current_span())?
             }
         } else {
-            self.error("Expected redirect input target (file or heredoc)", self.current_span())?
+            self.error("Expected redirect input target (file, heredoc, or text)", self.current_span())?
         }
     }
 }
-
-fn is_expr_start(k: Option<&TokenKind>) -> bool {
-    matches!(
-        k,
-        Some(
-            TokenKind::String(_)
-                | TokenKind::Ident(_)
-                | TokenKind::Dollar
-                | TokenKind::LParen
-                | TokenKind::LBracket
-                | TokenKind::Env
-                | TokenKind::Args
-                | TokenKind::Capture
-                | TokenKind::Exists
-                | TokenKind::IsDir
-                | TokenKind::IsFile
-                | TokenKind::IsSymlink
-                | TokenKind::IsExec
-                | TokenKind::IsReadable
-                | TokenKind::IsWritable
-                | TokenKind::IsNonEmpty
-                | TokenKind::BoolStr
-                | TokenKind::Len
-                | TokenKind::Arg
-                | TokenKind::Index
-                | TokenKind::Join
-                | TokenKind::Status
-                | TokenKind::Pid
-                | TokenKind::Count
-                | TokenKind::Uid
-                | TokenKind::Ppid
-                | TokenKind::Pwd
-                | TokenKind::SelfPid
-                | TokenKind::Argv0
-                | TokenKind::Argc
-                | TokenKind::True
-                | TokenKind::False
-                | TokenKind::Number(_)
-                | TokenKind::Minus
-                | TokenKind::Bang
-                | TokenKind::Input
-                | TokenKind::Confirm
-        )
-    )
-}
@@ -0,0 +1,32 @@
+//! In-memory compilation for the browser playground: source text in, shell
+//! script out, without touching files, processes or the clock.
+//!
+//! The wasm module is built from the library alone:
+//! `cargo rustc -p sh2c --lib --no-default-features --features wasm
+//! --target wasm32-unknown-unknown --crate-type cdylib`, then `wasm-bindgen`
+//! generates the JavaScript glue for `compile_str_to_bash`.
+
+use crate::codegen::{self, CodegenOptions, TargetShell};
+use crate::{fixes, loader, lower, stages};
+
+/// Name of the single source file in diagnostics.
+pub const FILE_NAME: &str = "main.sh2";
+
+/// Compiles `src` for `target` and returns the script, or the diagnostics as
+/// `sh2c` would print them. Imports are not available.
+pub fn compile_str(src: &str, target: TargetShell) -> Result<String, String> {
+    let program = loader::load_str(FILE_NAME, src).map_err(|d| d.format(None))?;
+    let suggestions = fixes::collect(&program, &[], target);
+    stages::check_program(&program, &suggestions, &stages::CheckOptions::default())?;
+
+    let lower_opts = lower::LowerOptions { include_diagnostics: true, diag_base_dir: None, target, debug: false, profile: false };
+    let ir = lower::lower_with_options(program, &lower_opts).map_err(|e| e.to_string())?;
+    codegen::emit_with_options_checked(&ir, CodegenOptions { target, include_diagnostics: true }).map_err(|e| e.to_string())
+}
+
+/// The playground's entry point: `src` compiled for bash.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+pub fn compile_str_to_bash(src: &str) -> Result<String, String> {
+    compile_str(src, TargetShell::Bash)
+}
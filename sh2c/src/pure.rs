@@ -0,0 +1,589 @@
+//! `@pure` functions: checked at compile time and folded where possible.
+//!
+//! A pure function may only bind locals, branch, and return values computed
+//! from strings, arithmetic and the string builtins. Calls to it whose
+//! arguments are all literals are evaluated here, before lowering, and
+//! replaced with the result, so the generated script neither defines nor
+//! calls the function unless some call still needs it at runtime.
+//!
+//! Evaluation mirrors the shell semantics of the generated code. Anything it
+//! cannot reproduce exactly (non-numeric arithmetic, backslashes reaching the
+//! awk-based builtins, lengths of non-ASCII text) leaves the call in place.
+
+use crate::ast::{self, ArithOp, CompareOp, ExprKind, LValue, Program, StmtKind};
+use crate::error::CompileError;
+use crate::names;
+use crate::span::SourceMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Builtins a pure function may call, with their argument counts.
+const PURE_BUILTINS: &[(&str, usize)] =
+    &[("trim", 1), ("before", 2), ("after", 2), ("replace", 3), ("coalesce", 2), ("default", 2)];
+
+/// Upper bound on evaluation steps per folded call, so recursion cannot hang the compiler.
+const FUEL: usize = 10_000;
+
+/// Rejects `@pure` functions whose bodies do anything but compute a value.
+pub fn check(program: &Program, base: Option<&Path>) -> Result<(), CompileError> {
+    let pure: HashSet<&str> =
+        program.functions.iter().filter(|f| f.attr("pure").is_some()).map(|f| f.name.as_str()).collect();
+    for func in program.functions.iter().filter(|f| f.attr("pure").is_some()) {
+        let Some(sm) = program.source_maps.get(&func.file) else { continue };
+        let c = Checker { pure: &pure, name: &func.name, sm, file: &func.file, base };
+        if func.returns_bool {
+            return Err(c.error("`-> bool`; its result is an exit status", func.span));
+        }
+        c.block(&func.body)?;
+    }
+    Ok(())
+}
+
+struct Checker<'a> {
+    pure: &'a HashSet<&'a str>,
+    name: &'a str,
+    sm: &'a SourceMap,
+    file: &'a str,
+    base: Option<&'a Path>,
+}
+
+impl Checker<'_> {
+    fn error(&self, what: &str, span: crate::span::Span) -> CompileError {
+        let msg = format!("@pure function '{}' cannot use {}", names::display(self.name), what);
+        CompileError::new(self.sm.format_coded_diagnostic(self.file, self.base, Some("E0605"), &msg, span)).with_code("E0605")
+    }
+
+    fn block(&self, stmts: &[ast::Stmt]) -> Result<(), CompileError> {
+        stmts.iter().try_for_each(|s| self.stmt(s))
+    }
+
+    fn stmt(&self, stmt: &ast::Stmt) -> Result<(), CompileError> {
+        match &stmt.node {
+            StmtKind::Let { value, .. } | StmtKind::Set { target: LValue::Var(_), value } => self.expr(value),
+            StmtKind::Return(value) => value.iter().try_for_each(|e| self.expr(e)),
+            StmtKind::If { cond, then_body, elifs, else_body } => {
+                self.expr(cond)?;
+                self.block(then_body)?;
+                for elif in elifs {
+                    self.expr(&elif.cond)?;
+                    self.block(&elif.body)?;
+                }
+                else_body.iter().try_for_each(|b| self.block(b))
+            }
+            StmtKind::Set { .. } => Err(self.error("environment variables", stmt.span)),
+            StmtKind::Call { name, .. } => Err(self.error(&format!("'{}()' as a statement", names::display(name)), stmt.span)),
+            _ => Err(self.error("this statement; only let, if and return are allowed", stmt.span)),
+        }
+    }
+
+    fn expr(&self, e: &ast::Expr) -> Result<(), CompileError> {
+        match &e.node {
+            ExprKind::Literal(_) | ExprKind::Number(_) | ExprKind::Bool(_) | ExprKind::Var(_) => Ok(()),
+            ExprKind::Concat(l, r) | ExprKind::Coalesce(l, r) | ExprKind::And(l, r) | ExprKind::Or(l, r) => {
+                self.expr(l)?;
+                self.expr(r)
+            }
+            ExprKind::Arith { left, right, .. } | ExprKind::Compare { left, right, .. } => {
+                self.expr(left)?;
+                self.expr(right)
+            }
+            ExprKind::Not(inner) | ExprKind::Len(inner) => self.expr(inner),
+            ExprKind::IfElse { cond, then_expr, else_expr } => {
+                self.expr(cond)?;
+                self.expr(then_expr)?;
+                self.expr(else_expr)
+            }
+            ExprKind::Call { name, args, options } => {
+                let allowed =
+                    self.pure.contains(name.as_str()) || PURE_BUILTINS.iter().any(|(b, _)| b == name);
+                if !allowed {
+                    return Err(self.error(
+                        &format!("'{}()'; only other @pure functions and string builtins can be called", names::display(name)),
+                        e.span,
+                    ));
+                }
+                if let Some(o) = options.first() {
+                    return Err(self.error(&format!("the named argument '{}'", o.name), o.span));
+                }
+                args.iter().try_for_each(|a| self.expr(a))
+            }
+            _ => Err(self.error("this expression; only strings, numbers and arithmetic are allowed", e.span)),
+        }
+    }
+}
+
+/// Replaces calls to `@pure` functions that have only literal arguments with
+/// their results. Returns the pure functions that no remaining code calls, so
+/// the caller can leave them out; libraries (`entry` is `None`) keep them all,
+/// since their functions are their API.
+pub fn fold(program: &mut Program, entry: Option<&str>) -> HashSet<String> {
+    let pure: HashMap<String, ast::Function> = program
+        .functions
+        .iter()
+        .filter(|f| f.attr("pure").is_some())
+        .map(|f| (f.name.clone(), f.clone()))
+        .collect();
+    if pure.is_empty() {
+        return HashSet::new();
+    }
+
+    let mut refs: HashMap<String, HashSet<String>> = HashMap::new();
+    for func in program.functions.iter_mut() {
+        let mut folder = Folder { pure: &pure, refs: HashSet::new() };
+        folder.block(&mut func.body);
+        refs.insert(func.name.clone(), folder.refs);
+    }
+    let Some(entry) = entry else { return HashSet::new() };
+
+    let mut live: HashSet<String> = HashSet::from([entry.to_string()]);
+    let mut todo: Vec<&str> = program.functions.iter().filter(|f| !pure.contains_key(&f.name) || f.name == entry).map(|f| f.name.as_str()).collect();
+    while let Some(name) = todo.pop() {
+        for callee in refs.get(name).into_iter().flatten() {
+            if pure.contains_key(callee) && live.insert(callee.clone()) {
+                todo.push(callee);
+            }
+        }
+    }
+    pure.into_keys().filter(|name| !live.contains(name)).collect()
+}
+
+struct Folder<'a> {
+    pure: &'a HashMap<String, ast::Function>,
+    /// Names still called (or mentioned as a literal) after folding.
+    refs: HashSet<String>,
+}
+
+impl Folder<'_> {
+    fn block(&mut self, stmts: &mut [ast::Stmt]) {
+        for s in stmts {
+            self.stmt(s);
+        }
+    }
+
+    // SYNC WITH: loader::rewrite_stmt (traversal shape).
+    fn stmt(&mut self, stmt: &mut ast::Stmt) {
+        match &mut stmt.node {
+            StmtKind::Let { value, .. } | StmtKind::Set { value, .. } | StmtKind::LetValues { value, .. } => self.expr(value),
+            StmtKind::ReturnValues(values) => values.iter_mut().for_each(|v| self.expr(v)),
+            StmtKind::Run(call) => self.run_call(call),
+            StmtKind::Print(e)
+            | StmtKind::PrintErr(e)
+            | StmtKind::Exit(Some(e))
+            | StmtKind::Return(Some(e))
+            | StmtKind::Wait(Some(e))
+            | StmtKind::Sh(e)
+            | StmtKind::Cd { path: e }
+            | StmtKind::Export { value: Some(e), .. }
+            | StmtKind::Source { path: e } => self.expr(e),
+            StmtKind::If { cond, then_body, elifs, else_body } => {
+                self.expr(cond);
+                self.block(then_body);
+                for elif in elifs.iter_mut() {
+                    self.expr(&mut elif.cond);
+                    self.block(&mut elif.body);
+                }
+                if let Some(body) = else_body {
+                    self.block(body);
+                }
+            }
+            StmtKind::While { cond, body } => {
+                self.expr(cond);
+                self.block(body);
+            }
+            StmtKind::For { iterable, body, .. } => {
+                match iterable {
+                    ast::ForIterable::List(items) => items.iter_mut().for_each(|i| self.expr(i)),
+                    ast::ForIterable::Range(start, end) => {
+                        self.expr(start);
+                        self.expr(end);
+                    }
+                    ast::ForIterable::Find0(spec) => {
+                        for e in [&mut spec.dir, &mut spec.name, &mut spec.type_filter, &mut spec.maxdepth].into_iter().flatten() {
+                            self.expr(e);
+                        }
+                    }
+                    _ => {}
+                }
+                self.block(body);
+            }
+            StmtKind::TryCatch { try_body: left, catch_body: right }
+            | StmtKind::AndThen { left, right }
+            | StmtKind::OrElse { left, right } => {
+                self.block(left);
+                self.block(right);
+            }
+            StmtKind::Pipe(segments) => self.segments(segments),
+            StmtKind::Exec(args) => args.iter_mut().for_each(|a| self.expr(a)),
+            StmtKind::WithEnv { bindings, body } => {
+                bindings.iter_mut().for_each(|(_, v)| self.expr(v));
+                self.block(body);
+            }
+            StmtKind::WithCwd { path, body }
+            | StmtKind::WithDeadline { duration: path, body } | StmtKind::WithUmask { mask: path, body }
+            | StmtKind::WithLog { path, body, .. } => {
+                self.expr(path);
+                self.block(body);
+            }
+            StmtKind::WithRedirect { stdout, stderr, stdin, body } => {
+                for t in stdout.iter_mut().chain(stderr.iter_mut()).flatten() {
+                    if let ast::RedirectOutputTarget::File { path, .. } = &mut t.node {
+                        self.expr(path);
+                    }
+                }
+                if let Some(ast::RedirectInputTarget::File { path } | ast::RedirectInputTarget::Text { value: path }) = stdin {
+                    self.expr(path);
+                }
+                self.block(body);
+            }
+            StmtKind::Case { expr, arms } => {
+                self.expr(expr);
+                arms.iter_mut().for_each(|arm| self.block(&mut arm.body));
+            }
+            StmtKind::Call { name, args, options } => {
+                self.refs.insert(name.clone());
+                args.iter_mut().for_each(|a| self.expr(a));
+                options.iter_mut().for_each(|o| self.expr(&mut o.value));
+            }
+            StmtKind::ForMap { body, .. }
+            | StmtKind::Subshell { body }
+            | StmtKind::Group { body }
+            | StmtKind::Unchecked { body }
+            | StmtKind::WithLimits { body, .. } => self.block(body),
+            StmtKind::Spawn { stmt: inner } => self.stmt(inner),
+            _ => {}
+        }
+    }
+
+    fn run_call(&mut self, call: &mut ast::RunCall) {
+        call.args.iter_mut().for_each(|a| self.expr(a));
+        call.options.iter_mut().for_each(|o| self.expr(&mut o.value));
+        if let Some(ast::RedirectOutputTarget::File { path, .. }) = call.stderr.as_deref_mut().map(|t| &mut t.node) {
+            self.expr(path);
+        }
+    }
+
+    fn segments(&mut self, segments: &mut [ast::Spanned<ast::PipeSegment>]) {
+        for seg in segments.iter_mut() {
+            match &mut seg.node {
+                ast::PipeSegment::Run(call) | ast::PipeSegment::Sudo(call) => self.run_call(call),
+                ast::PipeSegment::Block(body) | ast::PipeSegment::EachLine(_, _, body) => self.block(body),
+            }
+        }
+    }
+
+    fn expr(&mut self, e: &mut ast::Expr) {
+        self.expr_in(e, false);
+    }
+
+    /// `numeric` is set for the direct operands of arithmetic, where a string
+    /// literal would turn `+` into concatenation.
+    fn expr_in(&mut self, e: &mut ast::Expr, numeric: bool) {
+        match &mut e.node {
+            // A variable may be a function reference.
+            ExprKind::Literal(s) | ExprKind::Var(s) => {
+                self.refs.insert(s.clone());
+            }
+            ExprKind::Command(args) => args.iter_mut().for_each(|a| self.expr(a)),
+            ExprKind::CommandPipe(pipeline) => pipeline.iter_mut().flatten().for_each(|a| self.expr(a)),
+            ExprKind::Pipeline(segments) => self.segments(segments),
+            ExprKind::Concat(l, r)
+            | ExprKind::Coalesce(l, r)
+            | ExprKind::And(l, r)
+            | ExprKind::Or(l, r)
+            | ExprKind::Join { list: l, sep: r }
+            | ExprKind::Index { list: l, index: r, .. }
+            | ExprKind::Compare { left: l, right: r, .. } => {
+                self.expr(l);
+                self.expr(r);
+            }
+            ExprKind::Arith { left, right, .. } => {
+                self.expr_in(left, true);
+                self.expr_in(right, true);
+            }
+            ExprKind::IfElse { cond, then_expr, else_expr } => {
+                self.expr(cond);
+                self.expr(then_expr);
+                self.expr(else_expr);
+            }
+            ExprKind::Not(inner)
+            | ExprKind::Spread(inner)
+            | ExprKind::Exists(inner)
+            | ExprKind::IsDir(inner)
+            | ExprKind::IsFile(inner)
+            | ExprKind::IsSymlink(inner)
+            | ExprKind::IsExec(inner)
+            | ExprKind::IsReadable(inner)
+            | ExprKind::IsWritable(inner)
+            | ExprKind::IsNonEmpty(inner)
+            | ExprKind::BoolStr(inner)
+            | ExprKind::Len(inner)
+            | ExprKind::Count(inner)
+            | ExprKind::Arg(inner)
+            | ExprKind::Env(inner)
+            | ExprKind::Input(inner)
+            | ExprKind::Field { base: inner, .. } => self.expr(inner),
+            ExprKind::MapLiteral(entries) => entries.iter_mut().for_each(|(_, v)| self.expr(v)),
+            ExprKind::List(items) => items.iter_mut().for_each(|i| self.expr(i)),
+            ExprKind::Sudo { args, options } => {
+                args.iter_mut().for_each(|a| self.expr(a));
+                options.iter_mut().for_each(|o| self.expr(&mut o.value));
+            }
+            ExprKind::Run(call) => self.run_call(call),
+            ExprKind::Capture { expr: inner, options } | ExprKind::Sh { cmd: inner, options } => {
+                self.expr(inner);
+                options.iter_mut().for_each(|o| self.expr(&mut o.value));
+            }
+            ExprKind::Confirm { prompt, default } => {
+                self.expr(prompt);
+                if let Some(d) = default {
+                    self.expr(d);
+                }
+            }
+            ExprKind::Call { name, args, options } => {
+                args.iter_mut().for_each(|a| self.expr(a));
+                options.iter_mut().for_each(|o| self.expr(&mut o.value));
+                if let Some(node) = self.try_fold(name, args, numeric) {
+                    e.node = node;
+                } else {
+                    self.refs.insert(name.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn try_fold(&self, name: &str, args: &[ast::Expr], numeric: bool) -> Option<ExprKind> {
+        self.pure.get(name)?;
+        let args = args
+            .iter()
+            .map(|a| match &a.node {
+                ExprKind::Literal(s) => Some(s.clone()),
+                ExprKind::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let mut eval = Eval { pure: self.pure, fuel: FUEL };
+        let value = eval.call(name, args)?;
+        if numeric {
+            // Only a plain number keeps `f() + 1` arithmetic.
+            let n: u64 = value.parse().ok()?;
+            (n.to_string() == value).then_some(ExprKind::Number(n))
+        } else {
+            Some(ExprKind::Literal(value))
+        }
+    }
+}
+
+enum Value {
+    Str(String),
+    Bool(bool),
+}
+
+/// Interpreter for the subset of sh2 that [`check`] allows. Every method
+/// returns `None` when the result would depend on something it does not model.
+struct Eval<'a> {
+    pure: &'a HashMap<String, ast::Function>,
+    fuel: usize,
+}
+
+impl Eval<'_> {
+    fn tick(&mut self) -> Option<()> {
+        self.fuel = self.fuel.checked_sub(1)?;
+        Some(())
+    }
+
+    fn call(&mut self, name: &str, args: Vec<String>) -> Option<String> {
+        let func = self.pure.get(name)?;
+        if func.params.len() != args.len() {
+            return None;
+        }
+        let mut vars: HashMap<String, Value> =
+            func.params.iter().cloned().zip(args.into_iter().map(Value::Str)).collect();
+        let ret = self.block(&func.body, &mut vars)?.unwrap_or_default();
+        // The call site captures the result with `$( )`, which drops trailing newlines.
+        Some(ret.trim_end_matches('\n').to_string())
+    }
+
+    /// Runs `stmts`; the inner `Some` carries the value of a `return`.
+    fn block(&mut self, stmts: &[ast::Stmt], vars: &mut HashMap<String, Value>) -> Option<Option<String>> {
+        for stmt in stmts {
+            self.tick()?;
+            match &stmt.node {
+                StmtKind::Let { name, value } => {
+                    let v = self.value(value, vars)?;
+                    vars.insert(name.node.clone(), v);
+                }
+                StmtKind::Set { target: LValue::Var(name), value } => {
+                    let v = self.value(value, vars)?;
+                    vars.insert(name.node.clone(), v);
+                }
+                StmtKind::Return(None) => return Some(Some(String::new())),
+                StmtKind::Return(Some(e)) => return Some(Some(self.string(e, vars)?)),
+                StmtKind::If { cond, then_body, elifs, else_body } => {
+                    let branch = if self.cond(cond, vars)? {
+                        Some(then_body)
+                    } else {
+                        let mut taken = None;
+                        for elif in elifs {
+                            if self.cond(&elif.cond, vars)? {
+                                taken = Some(&elif.body);
+                                break;
+                            }
+                        }
+                        taken.or(else_body.as_ref())
+                    };
+                    if let Some(body) = branch
+                        && let Some(ret) = self.block(body, vars)?
+                    {
+                        return Some(Some(ret));
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(None)
+    }
+
+    fn value(&mut self, e: &ast::Expr, vars: &HashMap<String, Value>) -> Option<Value> {
+        match &e.node {
+            ExprKind::Bool(b) => Some(Value::Bool(*b)),
+            ExprKind::Compare { .. } | ExprKind::And(..) | ExprKind::Or(..) | ExprKind::Not(_) => {
+                self.cond(e, vars).map(Value::Bool)
+            }
+            ExprKind::IfElse { cond, then_expr, else_expr } => {
+                let branch = if self.cond(cond, vars)? { then_expr } else { else_expr };
+                self.value(branch, vars)
+            }
+            _ => self.string(e, vars).map(Value::Str),
+        }
+    }
+
+    fn string(&mut self, e: &ast::Expr, vars: &HashMap<String, Value>) -> Option<String> {
+        self.tick()?;
+        match &e.node {
+            ExprKind::Literal(s) => Some(s.clone()),
+            ExprKind::Number(n) => Some(n.to_string()),
+            ExprKind::Var(name) => match vars.get(name)? {
+                Value::Str(s) => Some(s.clone()),
+                Value::Bool(_) => None,
+            },
+            ExprKind::Concat(l, r) => Some(self.string(l, vars)? + &self.string(r, vars)?),
+            ExprKind::Coalesce(l, r) => {
+                let left = self.string(l, vars)?;
+                if left.is_empty() { self.string(r, vars) } else { Some(left) }
+            }
+            ExprKind::Arith { left, op, right } => {
+                let is_lit = |e: &ast::Expr| matches!(e.node, ExprKind::Literal(_));
+                if is_lit(left) || is_lit(right) {
+                    // Lowering treats `+` with a string literal operand as concatenation.
+                    return match op {
+                        ArithOp::Add => Some(self.string(left, vars)? + &self.string(right, vars)?),
+                        _ => None,
+                    };
+                }
+                let l = self.int(left, vars)?;
+                let r = self.int(right, vars)?;
+                let n = match op {
+                    ArithOp::Add => l.checked_add(r),
+                    ArithOp::Sub => l.checked_sub(r),
+                    ArithOp::Mul => l.checked_mul(r),
+                    ArithOp::Div => l.checked_div(r),
+                    ArithOp::Mod => l.checked_rem(r),
+                }?;
+                Some(n.to_string())
+            }
+            ExprKind::Len(inner) => {
+                let s = self.string(inner, vars)?;
+                Some(s.chars().count().to_string())
+            }
+            ExprKind::IfElse { cond, then_expr, else_expr } => {
+                let branch = if self.cond(cond, vars)? { then_expr } else { else_expr };
+                self.string(branch, vars)
+            }
+            ExprKind::Call { name, args, .. } => {
+                let args = args.iter().map(|a| self.string(a, vars)).collect::<Option<Vec<_>>>()?;
+                if self.pure.contains_key(name) {
+                    return self.call(name, args);
+                }
+                builtin(name, &args)
+            }
+            _ => None,
+        }
+    }
+
+    fn int(&mut self, e: &ast::Expr, vars: &HashMap<String, Value>) -> Option<i64> {
+        let s = self.string(e, vars)?;
+        let n: i64 = s.parse().ok()?;
+        // Leading zeros, `+` and the like mean something else (or nothing) to the shell.
+        (n.to_string() == s).then_some(n)
+    }
+
+    fn cond(&mut self, e: &ast::Expr, vars: &HashMap<String, Value>) -> Option<bool> {
+        self.tick()?;
+        match &e.node {
+            ExprKind::Bool(b) => Some(*b),
+            ExprKind::Var(name) => match vars.get(name)? {
+                Value::Bool(b) => Some(*b),
+                Value::Str(_) => None,
+            },
+            ExprKind::Not(inner) => self.cond(inner, vars).map(|b| !b),
+            ExprKind::IfElse { cond, then_expr, else_expr } => {
+                let branch = if self.cond(cond, vars)? { then_expr } else { else_expr };
+                self.cond(branch, vars)
+            }
+            ExprKind::And(l, r) => Some(self.cond(l, vars)? && self.cond(r, vars)?),
+            ExprKind::Or(l, r) => Some(self.cond(l, vars)? || self.cond(r, vars)?),
+            ExprKind::Compare { left, op, right } => match &left.node {
+                // A chain: `a < b <= c` is `a < b && b <= c`.
+                ExprKind::Compare { op: inner, right: middle, .. } if op.is_ordering() && inner.is_ordering() => {
+                    Some(self.cond(left, vars)? && self.compare(middle, op, right, vars)?)
+                }
+                _ => self.compare(left, op, right, vars),
+            },
+            _ => None,
+        }
+    }
+
+    fn compare(&mut self, left: &ast::Expr, op: &CompareOp, right: &ast::Expr, vars: &HashMap<String, Value>) -> Option<bool> {
+        if matches!(left.node, ExprKind::Bool(_)) || matches!(right.node, ExprKind::Bool(_)) {
+            return None;
+        }
+        match op {
+            CompareOp::Eq => Some(self.string(left, vars)? == self.string(right, vars)?),
+            CompareOp::NotEq => Some(self.string(left, vars)? != self.string(right, vars)?),
+            _ => {
+                let l = self.int(left, vars)?;
+                let r = self.int(right, vars)?;
+                Some(match op {
+                    CompareOp::Lt => l < r,
+                    CompareOp::Le => l <= r,
+                    CompareOp::Gt => l > r,
+                    _ => l >= r,
+                })
+            }
+        }
+    }
+}
+
+/// The prelude's string helpers. Those built on `awk -v` interpret
+/// backslashes in their arguments, so such calls are left for runtime.
+fn builtin(name: &str, args: &[String]) -> Option<String> {
+    let (_, arity) = PURE_BUILTINS.iter().find(|(b, _)| *b == name)?;
+    if args.len() != *arity {
+        return None;
+    }
+    if matches!(name, "coalesce" | "default") {
+        return Some(if args[0].is_empty() { args[1].clone() } else { args[0].clone() });
+    }
+    if args.iter().any(|a| a.contains('\\') || !a.is_ascii()) || args.get(1).is_some_and(|sep| sep.is_empty()) {
+        return None;
+    }
+    let s = args[0].as_str();
+    Some(match name {
+        "trim" => s.trim_matches(|c: char| c.is_ascii_whitespace() || c == '\x0b').to_string(),
+        "before" => s.find(args[1].as_str()).map_or(s, |i| &s[..i]).to_string(),
+        "after" => s.find(args[1].as_str()).map_or("", |i| &s[i + args[1].len()..]).to_string(),
+        "replace" => s.replace(args[1].as_str(), &args[2]),
+        _ => return None,
+    })
+}
@@ -19,10 +19,12 @@ fn diag_unknown_alias(ns: &str, ns_span: Span, index: &ImportIndex) -> Diagnosti
         .map(|s| format!("did you mean '{}'?", s));
     Diagnostic {
         msg: format!("unknown import alias '{}'", ns),
+        code: Some("E0203"),
         span: ns_span,
         sm: index.sm.cloned(),
         file: Some(index.file.to_string()),
         help,
+        additional: Vec::new(),
     }
 }
 
@@ -37,10 +39,12 @@ fn diag_unknown_func(ns: &str, name: &str, name_span: Span, index: &ImportIndex,
         .map(|s| format!("did you mean '{}.{}'?", ns, s));
     Diagnostic {
         msg: format!("unknown function '{}.{}'", ns, name),
+        code: Some("E0202"),
         span: name_span,
         sm: index.sm.cloned(),
         file: Some(index.file.to_string()),
         help,
+        additional: Vec::new(),
     }
 }
 
@@ -79,9 +83,14 @@ fn resolve_in_stmt(stmt: &mut Stmt, index: &ImportIndex) -> Result<(), Diagnosti
                 resolve_in_expr(a, index)?;
             }
         }
-        StmtKind::Let { value, .. } | StmtKind::Set { value, .. } => {
+        StmtKind::Let { value, .. } | StmtKind::Set { value, .. } | StmtKind::LetValues { value, .. } => {
             resolve_in_expr(value, index)?;
         }
+        StmtKind::ReturnValues(values) => {
+            for v in values {
+                resolve_in_expr(v, index)?;
+            }
+        }
         StmtKind::Run(call) => {
             for a in &mut call.args {
                 resolve_in_expr(a, index)?;
@@ -144,19 +153,7 @@ fn resolve_in_stmt(stmt: &mut Stmt, index: &ImportIndex) -> Result<(), Diagnosti
             for s in try_body { resolve_in_stmt(s, index)?; }
             for s in catch_body { resolve_in_stmt(s, index)?; }
         }
-        StmtKind::Pipe(segments) => {
-            for seg in segments {
-                match &mut seg.node {
-                    crate::ast::PipeSegment::Run(call) | crate::ast::PipeSegment::Sudo(call) => {
-                        for a in &mut call.args { resolve_in_expr(a, index)?; }
-                        for o in &mut call.options { resolve_in_expr(&mut o.value, index)?; }
-                    }
-                    crate::ast::PipeSegment::Block(body) | crate::ast::PipeSegment::EachLine(_, body) => {
-                        for s in body { resolve_in_stmt(s, index)?; }
-                    }
-                }
-            }
-        }
+        StmtKind::Pipe(segments) => resolve_in_pipe_segments(segments, index)?,
         StmtKind::Exec(args) => {
             for a in args { resolve_in_expr(a, index)?; }
         }
@@ -176,7 +173,7 @@ fn resolve_in_stmt(stmt: &mut Stmt, index: &ImportIndex) -> Result<(), Diagnosti
                 resolve_in_stmt(s, index)?;
             }
         }
-        StmtKind::WithCwd { path, body } => {
+        StmtKind::WithCwd { path, body } | StmtKind::WithDeadline { duration: path, body } | StmtKind::WithUmask { mask: path, body } => {
             resolve_in_expr(path, index)?;
             for s in body {
                 resolve_in_stmt(s, index)?;
@@ -203,14 +200,17 @@ fn resolve_in_stmt(stmt: &mut Stmt, index: &ImportIndex) -> Result<(), Diagnosti
                     }
                 }
             }
-            if let Some(crate::ast::RedirectInputTarget::File { path }) = stdin {
+            if let Some(crate::ast::RedirectInputTarget::File { path } | crate::ast::RedirectInputTarget::Text { value: path }) = stdin {
                 resolve_in_expr(path, index)?;
             }
             for s in body {
                 resolve_in_stmt(s, index)?;
             }
         }
-        StmtKind::Subshell { body } | StmtKind::Group { body } => {
+        StmtKind::Subshell { body }
+        | StmtKind::Group { body }
+        | StmtKind::Unchecked { body }
+        | StmtKind::WithLimits { body, .. } => {
             for s in body {
                 resolve_in_stmt(s, index)?;
             }
@@ -231,6 +231,24 @@ fn resolve_in_stmt(stmt: &mut Stmt, index: &ImportIndex) -> Result<(), Diagnosti
     Ok(())
 }
 
+fn resolve_in_pipe_segments(segments: &mut [crate::ast::Spanned<crate::ast::PipeSegment>], index: &ImportIndex) -> Result<(), Diagnostic> {
+    for seg in segments {
+        match &mut seg.node {
+            crate::ast::PipeSegment::Run(call) | crate::ast::PipeSegment::Sudo(call) => {
+                for a in &mut call.args { resolve_in_expr(a, index)?; }
+                for o in &mut call.options { resolve_in_expr(&mut o.value, index)?; }
+                if let Some(crate::ast::RedirectOutputTarget::File { path, .. }) = call.stderr.as_deref_mut().map(|t| &mut t.node) {
+                    resolve_in_expr(path, index)?;
+                }
+            }
+            crate::ast::PipeSegment::Block(body) | crate::ast::PipeSegment::EachLine(_, _, body) => {
+                for s in body { resolve_in_stmt(s, index)?; }
+            }
+        }
+    }
+    Ok(())
+}
+
 // SYNC WITH: debug_assert_expr_resolved below.
 // When adding new ExprKind variants that contain Expr, update both walkers.
 fn resolve_in_expr(expr: &mut Expr, index: &ImportIndex) -> Result<(), Diagnostic> {
@@ -281,15 +299,21 @@ fn resolve_in_expr(expr: &mut Expr, index: &ImportIndex) -> Result<(), Diagnosti
                 }
             }
         }
-        ExprKind::Concat(a, b) | ExprKind::And(a, b) | ExprKind::Or(a, b) | ExprKind::Join { list: a, sep: b } | ExprKind::Index { list: a, index: b } => {
+        ExprKind::Pipeline(segments) => resolve_in_pipe_segments(segments, index)?,
+        ExprKind::Concat(a, b) | ExprKind::Coalesce(a, b) | ExprKind::And(a, b) | ExprKind::Or(a, b) | ExprKind::Join { list: a, sep: b } | ExprKind::Index { list: a, index: b, .. } => {
             resolve_in_expr(a, index)?;
             resolve_in_expr(b, index)?;
         }
+        ExprKind::IfElse { cond, then_expr, else_expr } => {
+            resolve_in_expr(cond, index)?;
+            resolve_in_expr(then_expr, index)?;
+            resolve_in_expr(else_expr, index)?;
+        }
         ExprKind::Arith { left, right, .. } | ExprKind::Compare { left, right, .. } => {
             resolve_in_expr(left, index)?;
             resolve_in_expr(right, index)?;
         }
-        ExprKind::Not(e) | ExprKind::Exists(e) | ExprKind::IsDir(e) | ExprKind::IsFile(e) | ExprKind::IsSymlink(e) | ExprKind::IsExec(e) | ExprKind::IsReadable(e) | ExprKind::IsWritable(e) | ExprKind::IsNonEmpty(e) | ExprKind::BoolStr(e) | ExprKind::Len(e) | ExprKind::Count(e) | ExprKind::Arg(e) | ExprKind::Env(e) | ExprKind::Input(e) | ExprKind::Field { base: e, .. } => {
+        ExprKind::Not(e) | ExprKind::Spread(e) | ExprKind::Exists(e) | ExprKind::IsDir(e) | ExprKind::IsFile(e) | ExprKind::IsSymlink(e) | ExprKind::IsExec(e) | ExprKind::IsReadable(e) | ExprKind::IsWritable(e) | ExprKind::IsNonEmpty(e) | ExprKind::BoolStr(e) | ExprKind::Len(e) | ExprKind::Count(e) | ExprKind::Arg(e) | ExprKind::Env(e) | ExprKind::Input(e) | ExprKind::Field { base: e, .. } => {
             resolve_in_expr(e, index)?;
         }
         ExprKind::List(items) => {
@@ -362,9 +386,14 @@ fn debug_assert_stmt_resolved(stmt: &Stmt) {
                 debug_assert_expr_resolved(a);
             }
         }
-        StmtKind::Let { value, .. } | StmtKind::Set { value, .. } => {
+        StmtKind::Let { value, .. } | StmtKind::Set { value, .. } | StmtKind::LetValues { value, .. } => {
             debug_assert_expr_resolved(value);
         }
+        StmtKind::ReturnValues(values) => {
+            for v in values {
+                debug_assert_expr_resolved(v);
+            }
+        }
         StmtKind::Run(call) => {
             for a in &call.args {
                 debug_assert_expr_resolved(a);
@@ -427,19 +456,7 @@ fn debug_assert_stmt_resolved(stmt: &Stmt) {
             for s in try_body { debug_assert_stmt_resolved(s); }
             for s in catch_body { debug_assert_stmt_resolved(s); }
         }
-        StmtKind::Pipe(segments) => {
-            for seg in segments {
-                match &seg.node {
-                    crate::ast::PipeSegment::Run(call) | crate::ast::PipeSegment::Sudo(call) => {
-                        for a in &call.args { debug_assert_expr_resolved(a); }
-                        for o in &call.options { debug_assert_expr_resolved(&o.value); }
-                    }
-                    crate::ast::PipeSegment::Block(body) | crate::ast::PipeSegment::EachLine(_, body) => {
-                        for s in body { debug_assert_stmt_resolved(s); }
-                    }
-                }
-            }
-        }
+        StmtKind::Pipe(segments) => debug_assert_pipe_segments_resolved(segments),
         StmtKind::Exec(args) => {
             for a in args { debug_assert_expr_resolved(a); }
         }
@@ -459,7 +476,7 @@ fn debug_assert_stmt_resolved(stmt: &Stmt) {
                 debug_assert_stmt_resolved(s);
             }
         }
-        StmtKind::WithCwd { path, body } => {
+        StmtKind::WithCwd { path, body } | StmtKind::WithDeadline { duration: path, body } | StmtKind::WithUmask { mask: path, body } => {
             debug_assert_expr_resolved(path);
             for s in body {
                 debug_assert_stmt_resolved(s);
@@ -486,14 +503,17 @@ fn debug_assert_stmt_resolved(stmt: &Stmt) {
                     }
                 }
             }
-            if let Some(crate::ast::RedirectInputTarget::File { path }) = stdin {
+            if let Some(crate::ast::RedirectInputTarget::File { path } | crate::ast::RedirectInputTarget::Text { value: path }) = stdin {
                 debug_assert_expr_resolved(path);
             }
             for s in body {
                 debug_assert_stmt_resolved(s);
             }
         }
-        StmtKind::Subshell { body } | StmtKind::Group { body } => {
+        StmtKind::Subshell { body }
+        | StmtKind::Group { body }
+        | StmtKind::Unchecked { body }
+        | StmtKind::WithLimits { body, .. } => {
             for s in body {
                 debug_assert_stmt_resolved(s);
             }
@@ -513,6 +533,24 @@ fn debug_assert_stmt_resolved(stmt: &Stmt) {
     }
 }
 
+#[cfg(debug_assertions)]
+fn debug_assert_pipe_segments_resolved(segments: &[crate::ast::Spanned<crate::ast::PipeSegment>]) {
+    for seg in segments {
+        match &seg.node {
+            crate::ast::PipeSegment::Run(call) | crate::ast::PipeSegment::Sudo(call) => {
+                for a in &call.args { debug_assert_expr_resolved(a); }
+                for o in &call.options { debug_assert_expr_resolved(&o.value); }
+                if let Some(crate::ast::RedirectOutputTarget::File { path, .. }) = call.stderr.as_deref().map(|t| &t.node) {
+                    debug_assert_expr_resolved(path);
+                }
+            }
+            crate::ast::PipeSegment::Block(body) | crate::ast::PipeSegment::EachLine(_, _, body) => {
+                for s in body { debug_assert_stmt_resolved(s); }
+            }
+        }
+    }
+}
+
 #[cfg(debug_assertions)]
 // SYNC WITH: resolve_in_expr above.
 fn debug_assert_expr_resolved(expr: &Expr) {
@@ -540,15 +578,21 @@ fn debug_assert_expr_resolved(expr: &Expr) {
                 }
             }
         }
-        ExprKind::Concat(a, b) | ExprKind::And(a, b) | ExprKind::Or(a, b) | ExprKind::Join { list: a, sep: b } | ExprKind::Index { list: a, index: b } => {
+        ExprKind::Pipeline(segments) => debug_assert_pipe_segments_resolved(segments),
+        ExprKind::Concat(a, b) | ExprKind::Coalesce(a, b) | ExprKind::And(a, b) | ExprKind::Or(a, b) | ExprKind::Join { list: a, sep: b } | ExprKind::Index { list: a, index: b, .. } => {
             debug_assert_expr_resolved(a);
             debug_assert_expr_resolved(b);
         }
+        ExprKind::IfElse { cond, then_expr, else_expr } => {
+            debug_assert_expr_resolved(cond);
+            debug_assert_expr_resolved(then_expr);
+            debug_assert_expr_resolved(else_expr);
+        }
         ExprKind::Arith { left, right, .. } | ExprKind::Compare { left, right, .. } => {
             debug_assert_expr_resolved(left);
             debug_assert_expr_resolved(right);
         }
-        ExprKind::Not(e) | ExprKind::Exists(e) | ExprKind::IsDir(e) | ExprKind::IsFile(e) | ExprKind::IsSymlink(e) | ExprKind::IsExec(e) | ExprKind::IsReadable(e) | ExprKind::IsWritable(e) | ExprKind::IsNonEmpty(e) | ExprKind::BoolStr(e) | ExprKind::Len(e) | ExprKind::Count(e) | ExprKind::Arg(e) | ExprKind::Env(e) | ExprKind::Input(e) | ExprKind::Field { base: e, .. } => {
+        ExprKind::Not(e) | ExprKind::Spread(e) | ExprKind::Exists(e) | ExprKind::IsDir(e) | ExprKind::IsFile(e) | ExprKind::IsSymlink(e) | ExprKind::IsExec(e) | ExprKind::IsReadable(e) | ExprKind::IsWritable(e) | ExprKind::IsNonEmpty(e) | ExprKind::BoolStr(e) | ExprKind::Len(e) | ExprKind::Count(e) | ExprKind::Arg(e) | ExprKind::Env(e) | ExprKind::Input(e) | ExprKind::Field { base: e, .. } => {
             debug_assert_expr_resolved(e);
         }
         ExprKind::List(items) => {
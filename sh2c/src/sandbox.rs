@@ -0,0 +1,524 @@
+//! Sandbox profile (`sh2c --sandbox manifest.json [--sandbox-guard]`)
+//!
+//! Collects the external commands a compiled script can start, as far as they
+//! are known statically (the literal head word of every `run`/`capture`/
+//! `spawn`/`exec`/pipeline argv, the command after `sudo`, the tools behind
+//! `mkdirs`/`copy`/`move`/`remove`/`awk()` and `require([...])` entries).
+//!
+//! With the guard enabled, every argv whose head is computed at runtime is
+//! routed through `__sh2_sandbox_exec`, which refuses commands missing from the
+//! manifest. Raw shell (`sh(...)`, `sh { }`, `source(...)`) and computed
+//! `sudo`/`exec` targets cannot be checked and are rejected at compile time.
+
+use crate::error::CompileError;
+use crate::ir::{Cmd, ForIterable, FsOp, Function, RedirectInputTarget, RedirectOutputTarget, Val};
+use crate::target::TargetShell;
+use std::collections::{BTreeSet, HashSet};
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Manifest {
+    /// Statically known command names, sorted.
+    pub commands: BTreeSet<String>,
+    /// Command invocations whose program name is only known at runtime.
+    pub dynamic_exec_sites: usize,
+    /// True when the script contains raw shell code that can run anything.
+    pub raw_shell: bool,
+}
+
+impl Manifest {
+    pub fn to_json(&self, target: TargetShell) -> String {
+        let mut s = String::from("{\n");
+        s.push_str(&format!("  \"target\": {},\n", json_str(&target.to_string())));
+        if self.commands.is_empty() {
+            s.push_str("  \"commands\": [],\n");
+        } else {
+            let items: Vec<String> = self.commands.iter().map(|c| format!("    {}", json_str(c))).collect();
+            s.push_str(&format!("  \"commands\": [\n{}\n  ],\n", items.join(",\n")));
+        }
+        s.push_str(&format!("  \"dynamic_exec_sites\": {},\n", self.dynamic_exec_sites));
+        s.push_str(&format!("  \"raw_shell\": {}\n", self.raw_shell));
+        s.push_str("}\n");
+        s
+    }
+
+    /// Shell functions backing the runtime guard; spliced in after the shebang.
+    pub fn guard_prelude(&self) -> String {
+        let mut s = String::from("__sh2_sandbox_check() {\n");
+        if !self.commands.is_empty() {
+            let pats: Vec<String> = self.commands.iter().map(|c| sh_single_quote(c)).collect();
+            s.push_str(&format!("  case \"$1\" in\n    {}) return 0 ;;\n  esac\n", pats.join("|")));
+        }
+        s.push_str("  printf 'Error: sandbox: command not declared in manifest: %s\\n' \"$1\" >&2\n");
+        s.push_str("  exit 126\n}\n");
+        s.push_str("__sh2_sandbox_exec() { __sh2_sandbox_check \"$1\"; \"$@\"; }\n");
+        s
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn sh_single_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+struct Scanner<'a> {
+    user_funcs: &'a HashSet<String>,
+    guard: bool,
+    manifest: Manifest,
+}
+
+/// Scans the IR, rewriting dynamic command heads when `guard` is set.
+pub fn analyze(funcs: &mut [Function], guard: bool) -> Result<Manifest, CompileError> {
+    let user_funcs: HashSet<String> = funcs.iter().map(|f| f.name.clone()).collect();
+    let mut s = Scanner { user_funcs: &user_funcs, guard, manifest: Manifest::default() };
+    for f in funcs.iter_mut() {
+        s.body(&mut f.commands)?;
+    }
+    Ok(s.manifest)
+}
+
+fn unguardable(what: &str, loc: &Option<String>) -> CompileError {
+    let msg = format!("{} cannot be used with --sandbox-guard: the commands it runs cannot be checked against the manifest", what);
+    match loc {
+        Some(l) => CompileError::coded("E0600", format!("{}: {}", l, msg)),
+        None => CompileError::coded("E0600", msg),
+    }
+}
+
+impl Scanner<'_> {
+    fn add(&mut self, name: &str) {
+        if !name.is_empty() && !name.starts_with("__sh2_") && !self.user_funcs.contains(name) {
+            self.manifest.commands.insert(name.to_string());
+        }
+    }
+
+    fn body(&mut self, cmds: &mut Vec<Cmd>) -> Result<(), CompileError> {
+        let mut i = 0;
+        while i < cmds.len() {
+            let check = self.cmd(&mut cmds[i])?;
+            if let Some(head) = check {
+                cmds.insert(i, Cmd::Call { name: "__sh2_sandbox_check".to_string(), args: vec![head] });
+                i += 1;
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+
+    /// Returns a head value that must be checked before `cmd` runs (for `exec`,
+    /// which cannot go through a wrapper function).
+    fn cmd(&mut self, cmd: &mut Cmd) -> Result<Option<Val>, CompileError> {
+        match cmd {
+            Cmd::Exec { args, stdin, env, cwd, loc, .. } => {
+                self.argv(args, loc)?;
+                for v in stdin.iter_mut().chain(cwd.iter_mut()).chain(env.iter_mut().map(|(_, v)| v)) {
+                    self.val(v)?;
+                }
+            }
+            Cmd::ExecReplace(args, loc) => {
+                for a in args.iter_mut() {
+                    self.val(a)?;
+                }
+                match args.first() {
+                    Some(Val::Literal(head)) => {
+                        let head = head.clone();
+                        self.add(&head);
+                        if head == "sudo" {
+                            self.sudo_target(args, loc)?;
+                        }
+                    }
+                    Some(head) => {
+                        self.manifest.dynamic_exec_sites += 1;
+                        if self.guard {
+                            return Ok(Some(head.clone()));
+                        }
+                    }
+                    None => {}
+                }
+            }
+            Cmd::ServiceStart { pidfile, args, log, loc } => {
+                self.argv(args, loc)?;
+                self.val(pidfile)?;
+                if let Some(v) = log {
+                    self.val(v)?;
+                }
+            }
+            Cmd::Pipe(segs, loc) => {
+                for (args, _, stderr) in segs.iter_mut() {
+                    self.argv(args, loc)?;
+                    if let Some(t) = stderr {
+                        self.redirect_output(t)?;
+                    }
+                }
+            }
+            Cmd::PipeBlocks(segs, _) => {
+                for (body, stderr) in segs.iter_mut() {
+                    self.body(body)?;
+                    if let Some(t) = stderr {
+                        self.redirect_output(t)?;
+                    }
+                }
+            }
+            Cmd::PipeEachLine { producer, body, .. } => {
+                self.boxed(producer)?;
+                self.body(body)?;
+            }
+            Cmd::CapturePipe { pipe, .. } => self.boxed(pipe)?,
+            Cmd::Spawn(inner) => self.boxed(inner)?,
+            Cmd::Require(vals) => {
+                for v in vals.iter_mut() {
+                    if let Val::Literal(name) = v {
+                        let name = name.clone();
+                        self.add(&name);
+                    }
+                    self.val(v)?;
+                }
+            }
+            Cmd::Fs { op, args, .. } => {
+                self.add(match op {
+                    FsOp::Mkdirs => "mkdir",
+                    FsOp::Copy => "cp",
+                    FsOp::Move => "mv",
+                    FsOp::Remove => "rm",
+                });
+                for a in args.iter_mut() {
+                    self.val(a)?;
+                }
+            }
+            Cmd::Raw { cmd: val, loc, .. } => {
+                self.manifest.raw_shell = true;
+                if self.guard {
+                    return Err(unguardable("sh()", loc));
+                }
+                self.val(val)?;
+            }
+            Cmd::RawLine { loc, .. } => {
+                self.manifest.raw_shell = true;
+                if self.guard {
+                    return Err(unguardable("sh { ... }", loc));
+                }
+            }
+            Cmd::Source(path) => {
+                self.manifest.raw_shell = true;
+                if self.guard {
+                    return Err(unguardable("source()", &None));
+                }
+                self.val(path)?;
+            }
+            Cmd::Assign(_, v, _) | Cmd::Print(v) | Cmd::PrintErr(v) | Cmd::Cd(v) | Cmd::ReturnBool(v) => self.val(v)?,
+            Cmd::Log { msg, .. } => self.val(msg)?,
+            Cmd::Return(opt) | Cmd::Exit(opt) | Cmd::Wait(opt) | Cmd::Export { value: opt, .. } => {
+                if let Some(v) = opt {
+                    self.val(v)?;
+                }
+            }
+            Cmd::Call { args, .. } => {
+                for a in args.iter_mut() {
+                    self.val(a)?;
+                }
+            }
+            Cmd::If { cond, then_body, elifs, else_body } => {
+                self.val(cond)?;
+                self.body(then_body)?;
+                for (c, b) in elifs.iter_mut() {
+                    self.val(c)?;
+                    self.body(b)?;
+                }
+                self.body(else_body)?;
+            }
+            Cmd::Case { expr, arms } => {
+                self.val(expr)?;
+                for (_, b) in arms.iter_mut() {
+                    self.body(b)?;
+                }
+            }
+            Cmd::For { iterable, body, .. } => {
+                match iterable {
+                    ForIterable::List(items) => {
+                        for v in items.iter_mut() {
+                            self.val(v)?;
+                        }
+                    }
+                    ForIterable::Range(a, b) => {
+                        self.val(a)?;
+                        self.val(b)?;
+                    }
+                    ForIterable::Find0 { dir, name, type_filter, maxdepth } => {
+                        self.val(dir)?;
+                        for v in [name, type_filter, maxdepth].into_iter().flatten() {
+                            self.val(v)?;
+                        }
+                    }
+                    ForIterable::StdinLines => {}
+                }
+                self.body(body)?;
+            }
+            Cmd::While { cond, body } => {
+                self.val(cond)?;
+                self.body(body)?;
+            }
+            Cmd::ForMap { body, .. }
+            | Cmd::Subshell { body }
+            | Cmd::Group { body }
+            | Cmd::Unchecked { body }
+            | Cmd::Profile { body, .. } => self.body(body)?,
+            Cmd::WithEnv { bindings, body } => {
+                for (_, v) in bindings.iter_mut() {
+                    self.val(v)?;
+                }
+                self.body(body)?;
+            }
+            Cmd::WithUmask { body, .. } | Cmd::WithLimits { body, .. } => self.body(body)?,
+            Cmd::WithLog { path, body, .. }
+            | Cmd::WithCwd { path, body }
+            | Cmd::WithDeadline { duration: path, body, .. } => {
+                self.val(path)?;
+                self.body(body)?;
+            }
+            Cmd::WithRedirect { stdout, stderr, stdin, body } => {
+                for t in stdout.iter_mut().chain(stderr.iter_mut()).flatten() {
+                    self.redirect_output(t)?;
+                }
+                if let Some(RedirectInputTarget::File { path } | RedirectInputTarget::Text { value: path }) = stdin {
+                    self.val(path)?;
+                }
+                self.body(body)?;
+            }
+            Cmd::TryCatch { try_body: a, catch_body: b } | Cmd::AndThen { left: a, right: b } | Cmd::OrElse { left: a, right: b } => {
+                self.body(a)?;
+                self.body(b)?;
+            }
+            Cmd::WaitForPort { host, port, timeout, interval, .. } => {
+                for v in [host, port, timeout, interval] {
+                    self.val(v)?;
+                }
+            }
+            Cmd::ServiceStop { pidfile, grace, .. } => {
+                self.val(pidfile)?;
+                if let Some(v) = grace {
+                    self.val(v)?;
+                }
+            }
+            Cmd::ReplaceInFile { path, pattern, replacement, .. } => {
+                for v in [path, pattern, replacement] {
+                    self.val(v)?;
+                }
+            }
+            Cmd::AppendLineIfMissing { path, line, .. } => {
+                self.val(path)?;
+                self.val(line)?;
+            }
+            Cmd::WriteFile { path, content, .. } => {
+                self.val(path)?;
+                self.val(content)?;
+            }
+            Cmd::SaveEnvfile { path, env } => {
+                self.val(path)?;
+                self.val(env)?;
+            }
+            Cmd::Break | Cmd::Continue | Cmd::Unset(_) | Cmd::Debug { .. } | Cmd::DumpVars { .. } => {}
+        }
+        Ok(None)
+    }
+
+    fn boxed(&mut self, cmd: &mut Cmd) -> Result<(), CompileError> {
+        if let Some(head) = self.cmd(cmd)? {
+            let inner = std::mem::replace(cmd, Cmd::Break);
+            *cmd = Cmd::Group {
+                body: vec![Cmd::Call { name: "__sh2_sandbox_check".to_string(), args: vec![head] }, inner],
+            };
+        }
+        Ok(())
+    }
+
+    fn redirect_output(&mut self, target: &mut RedirectOutputTarget) -> Result<(), CompileError> {
+        if let RedirectOutputTarget::File { path, .. } = target {
+            self.val(path)?;
+        }
+        Ok(())
+    }
+
+    /// Records (or guards) the program started by one argv.
+    fn argv(&mut self, args: &mut Vec<Val>, loc: &Option<String>) -> Result<(), CompileError> {
+        for a in args.iter_mut() {
+            self.val(a)?;
+        }
+        match args.first() {
+            Some(Val::Literal(head)) => {
+                let head = head.clone();
+                self.add(&head);
+                if head == "sudo" {
+                    self.sudo_target(args, loc)?;
+                }
+            }
+            Some(_) => {
+                self.manifest.dynamic_exec_sites += 1;
+                if self.guard {
+                    args.insert(0, Val::Literal("__sh2_sandbox_exec".to_string()));
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Finds the command word after `sudo` and its flags.
+    fn sudo_target(&mut self, args: &[Val], loc: &Option<String>) -> Result<(), CompileError> {
+        let mut i = 1;
+        while let Some(Val::Literal(w)) = args.get(i) {
+            match w.as_str() {
+                "--" => {
+                    i += 1;
+                    break;
+                }
+                "-u" | "-p" => i += 2,
+                w if w.starts_with('-') => i += 1,
+                _ => break,
+            }
+        }
+        match args.get(i) {
+            Some(Val::Literal(name)) => self.add(name),
+            Some(_) => {
+                self.manifest.dynamic_exec_sites += 1;
+                if self.guard {
+                    return Err(unguardable("sudo() with a computed command", loc));
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn val(&mut self, val: &mut Val) -> Result<(), CompileError> {
+        match val {
+            Val::Command(args) | Val::TryRun(args) | Val::Lines0(args) | Val::Spawn { args, .. } => {
+                self.argv(args, &None)?;
+            }
+            Val::CommandPipe(segs) => {
+                for args in segs.iter_mut() {
+                    self.argv(args, &None)?;
+                }
+            }
+            Val::Awk { input, .. } => {
+                self.add("awk");
+                self.val(input)?;
+            }
+            Val::Concat(a, b)
+            | Val::And(a, b)
+            | Val::Or(a, b)
+            | Val::Arith { left: a, right: b, .. }
+            | Val::Compare { left: a, right: b, .. }
+            | Val::Index { list: a, index: b, .. }
+            | Val::Join { list: a, sep: b }
+            | Val::Matches(a, b)
+            | Val::ReadFileOr { path: a, default: b }
+            | Val::FindFiles { dir: a, name: b }
+            | Val::ContainsList { list: a, needle: b }
+            | Val::ContainsSubstring { haystack: a, needle: b }
+            | Val::ContainsLine { file: a, needle: b }
+            | Val::StartsWith { text: a, prefix: b }
+            | Val::Split { s: a, delim: b } => {
+                self.val(a)?;
+                self.val(b)?;
+            }
+            Val::IfElse { cond, then_val, else_val } => {
+                self.val(cond)?;
+                self.val(then_val)?;
+                self.val(else_val)?;
+            }
+            Val::Not(v)
+            | Val::Spread(v)
+            | Val::Exists(v)
+            | Val::IsDir(v)
+            | Val::IsFile(v)
+            | Val::IsSymlink(v)
+            | Val::IsExec(v)
+            | Val::IsReadable(v)
+            | Val::IsWritable(v)
+            | Val::IsNonEmpty(v)
+            | Val::Len(v)
+            | Val::BytesLen(v)
+            | Val::ArgDynamic(v)
+            | Val::Count(v)
+            | Val::Env(v)
+            | Val::BoolStr(v)
+            | Val::Input(v)
+            | Val::InputList(v)
+            | Val::Confirm { prompt: v, .. }
+            | Val::ArgsFlags(v)
+            | Val::ArgsPositionals(v)
+            | Val::LoadEnvfile(v)
+            | Val::JsonKv(v)
+            | Val::Which(v)
+            | Val::ReadFile(v)
+            | Val::Lines(v)
+            | Val::Glob(v)
+            | Val::RawArg(v)
+            | Val::Render { template: v, .. }
+            | Val::Have(v)
+            | Val::ServiceRunning(v)
+            | Val::Capture { value: v, .. }
+            | Val::Wait { pid: v, .. }
+            | Val::WaitAll { pids: v, .. }
+            | Val::WaitAny { pids: v, .. } => self.val(v)?,
+            Val::Call { args, .. } | Val::BoolCall { args, .. } | Val::List(args) | Val::PathJoin(args) => {
+                for a in args.iter_mut() {
+                    self.val(a)?;
+                }
+            }
+            Val::MapLiteral(entries) => {
+                for (_, v) in entries.iter_mut() {
+                    self.val(v)?;
+                }
+            }
+            Val::Find { dir, name, type_filter, maxdepth } => {
+                self.val(dir)?;
+                for v in [name, type_filter, maxdepth].into_iter().flatten() {
+                    self.val(v)?;
+                }
+            }
+            Val::WaitForPort { host, port, timeout, interval } => {
+                for v in [host, port, timeout, interval] {
+                    self.val(v)?;
+                }
+            }
+            Val::Literal(_)
+            | Val::Var(_)
+            | Val::Arg(_)
+            | Val::Bool(_)
+            | Val::Number(_)
+            | Val::Args
+            | Val::Status
+            | Val::Pid
+            | Val::EnvDot(_)
+            | Val::Uid
+            | Val::Ppid
+            | Val::Pwd
+            | Val::SelfPid
+            | Val::Argv0
+            | Val::Argc
+            | Val::ParseArgs
+            | Val::MapIndex { .. }
+            | Val::Home
+            | Val::LastCommand
+            | Val::LastStderr
+            | Val::BoolVar(_)
+            | Val::CalleeVar(_) => {}
+        }
+        Ok(())
+    }
+}
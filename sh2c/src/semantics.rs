@@ -4,11 +4,17 @@
 //! - Declaration before use (undefined variable errors)
 //! - Set requires prior let (undeclared set errors)
 //! - No redeclaration in same scope
+//! - No names reserved by the shell or the generated runtime
+//!
+//! It also warns where Policy A lets a variable be unset at run time: shell code
+//! reading a variable assigned on only some paths, and a `let` reusing a name an
+//! earlier branch may already have set. `case` patterns that an earlier pattern
+//! already covers are flagged as unreachable.
 
 use crate::ast::{self, ExprKind, StmtKind};
 use crate::error::CompileError;
-use crate::span::SourceMap;
-use std::collections::HashSet;
+use crate::span::{SourceMap, Span};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
 /// Options for semantic analysis
@@ -36,6 +42,19 @@ struct BinderContext<'a> {
     #[allow(dead_code)]
     ever_declared: HashSet<String>,
 
+    /// Variables declared on SOME path reaching the current point, with their declaration.
+    /// Disjoint `if`/`case` branches start from the state before the statement.
+    maybe_declared: HashMap<String, Span>,
+
+    /// Block nesting depth; the function body is depth 1
+    depth: usize,
+
+    /// Non-fatal findings, already formatted
+    warnings: Vec<String>,
+
+    /// Function names, which may be used as values (function references)
+    functions: &'a HashSet<String>,
+
     /// Source map for diagnostics
     sm: &'a SourceMap,
     /// File path for diagnostics
@@ -51,11 +70,15 @@ struct BinderContextState {
 }
 
 impl<'a> BinderContext<'a> {
-    fn new(sm: &'a SourceMap, file: &'a str, opts: &'a SemanticOptions) -> Self {
+    fn new(functions: &'a HashSet<String>, sm: &'a SourceMap, file: &'a str, opts: &'a SemanticOptions) -> Self {
         Self {
             definitely_assigned: HashSet::new(),
             declared_in_straight_line: HashSet::new(),
             ever_declared: HashSet::new(),
+            maybe_declared: HashMap::new(),
+            depth: 0,
+            warnings: Vec::new(),
+            functions,
             sm,
             file,
             opts,
@@ -74,27 +97,103 @@ impl<'a> BinderContext<'a> {
         self.declared_in_straight_line = state.declared_in_straight_line;
     }
 
-    fn format_error(&self, msg: &str, span: crate::span::Span) -> String {
-        self.sm.format_diagnostic(self.file, self.opts.diag_base_dir.as_deref(), msg, span)
+    fn format_error(&self, code: &'static str, msg: &str, span: crate::span::Span) -> String {
+        self.sm.format_coded_diagnostic(self.file, self.opts.diag_base_dir.as_deref(), Some(code), msg, span)
+    }
+
+    fn error(&self, code: &'static str, msg: &str, span: crate::span::Span) -> CompileError {
+        CompileError::new(self.format_error(code, msg, span)).with_code(code)
+    }
+
+    /// Records a declaration that passed the redeclaration check, warning when a
+    /// nested one reuses a variable an earlier branch may already have set. A
+    /// top-level `let` is a fresh declaration that overwrites it on every path.
+    fn declare_maybe(&mut self, name: &str, span: Span) {
+        if let Some(earlier) = self.maybe_declared.get(name).filter(|_| self.depth > 1) {
+            let (line, _) = self.sm.line_col(earlier.start);
+            let msg = format!(
+                "variable '{}' shadows an earlier declaration at line {} that may still be set here; both are the same shell variable, so rename this one or declare it once and use `set {} = ...`",
+                name, line, name
+            );
+            self.warnings.push(self.format_error("W0007", &msg, span));
+        }
+        self.maybe_declared.insert(name.to_string(), span);
+    }
+
+    /// Warns when shell code expands a variable that is assigned on only some paths.
+    fn check_shell_text(&mut self, text: &str, span: Span) {
+        let mut seen = HashSet::new();
+        for (name, defaulted) in shell_expansions(text) {
+            if defaulted || self.definitely_assigned.contains(&name) || !seen.insert(name.clone()) {
+                continue;
+            }
+            if let Some(decl) = self.maybe_declared.get(&name) {
+                let (line, _) = self.sm.line_col(decl.start);
+                let msg = format!(
+                    "variable '{}' may be unset here: shell code reads it, but only some paths assign it (line {}); under `set -u` that is an unbound-variable error",
+                    name, line
+                );
+                self.warnings.push(self.format_error("W0006", &msg, span));
+            }
+        }
     }
 }
 
-/// Check semantics for a whole program
-pub fn check_semantics(program: &ast::Program, opts: &SemanticOptions) -> Result<(), CompileError> {
+/// Check semantics for a whole program, returning warnings on success
+pub fn check_semantics(program: &ast::Program, opts: &SemanticOptions) -> Result<Vec<String>, CompileError> {
+    let functions: HashSet<String> =
+        program.functions.iter().map(|f| f.name.clone()).chain(program.externs.iter().map(|e| e.name.clone())).collect();
+    // Top-level lets of `init` blocks are constants every other function can read.
+    let constants: HashSet<String> = program
+        .functions
+        .iter()
+        .filter(|f| program.inits.contains(&f.name))
+        .flat_map(|f| &f.body)
+        .filter_map(|stmt| match &stmt.node {
+            ast::StmtKind::Let { name, .. } => Some(name.node.clone()),
+            _ => None,
+        })
+        .collect();
+    let none = HashSet::new();
+    let mut warnings = Vec::new();
     for func in &program.functions {
         let sm = program.source_maps.get(&func.file)
             .ok_or_else(|| CompileError::new(format!("internal error: missing source map for file {}", func.file)))?;
-        check_function(func, sm, opts)?;
+        let visible = if program.inits.contains(&func.name) { &none } else { &constants };
+        warnings.extend(check_function(func, program.inits.contains(&func.name), &functions, visible, sm, opts)?);
     }
-    Ok(())
+    Ok(warnings)
 }
 
 /// Check semantics for a single function
-fn check_function(func: &ast::Function, sm: &SourceMap, opts: &SemanticOptions) -> Result<(), CompileError> {
-    let mut ctx = BinderContext::new(sm, &func.file, opts);
-
-    // Function parameters are pre-declared
+fn check_function(
+    func: &ast::Function,
+    is_init: bool,
+    functions: &HashSet<String>,
+    constants: &HashSet<String>,
+    sm: &SourceMap,
+    opts: &SemanticOptions,
+) -> Result<Vec<String>, CompileError> {
+    let mut ctx = BinderContext::new(functions, sm, &func.file, opts);
+
+    // Generated names (`init` blocks, import wrappers) are exempt; imported functions
+    // are also checked under their own name in their file.
+    if !is_init
+        && !func.name.starts_with("__imp_")
+        && let Some(why) = crate::names::reserved_function(&func.name)
+    {
+        let msg = format!("Function name '{}' is reserved: {}; choose a different name.", func.name, why);
+        return Err(ctx.error("E0206", &msg, func.span));
+    }
     for param in &func.params {
+        if let Some(why) = crate::names::reserved_variable(param) {
+            let msg = format!("parameter name '{}' of '{}' is reserved: {}", param, crate::names::display(&func.name), why);
+            return Err(ctx.error("E0206", &msg, func.span));
+        }
+    }
+
+    // Function parameters and `init` constants are pre-declared
+    for param in func.params.iter().chain(constants) {
         ctx.declared_in_straight_line.insert(param.clone());
         ctx.definitely_assigned.insert(param.clone());
         ctx.ever_declared.insert(param.clone());
@@ -103,14 +202,27 @@ fn check_function(func: &ast::Function, sm: &SourceMap, opts: &SemanticOptions)
     // Check all statements
     check_block(&func.body, &mut ctx)?;
 
-    Ok(())
+    Ok(ctx.warnings)
+}
+
+/// Rejects a variable name that the shell or the generated runtime already uses.
+fn check_variable_name(name: &str, span: crate::span::Span, ctx: &BinderContext) -> Result<(), CompileError> {
+    match crate::names::reserved_variable(name) {
+        Some(why) => {
+            let msg = format!("variable name '{}' is reserved: {}", name, why);
+            Err(ctx.error("E0206", &msg, span))
+        }
+        None => Ok(()),
+    }
 }
 
 /// Check a block of statements
 fn check_block(stmts: &[ast::Stmt], ctx: &mut BinderContext) -> Result<(), CompileError> {
+    ctx.depth += 1;
     for stmt in stmts {
         check_stmt(stmt, ctx)?;
     }
+    ctx.depth -= 1;
     Ok(())
 }
 
@@ -122,17 +234,17 @@ fn check_function_call(
 ) -> Result<(), CompileError> {
     // Special validation logic:
     if name == "try_run" {
-            return Err(CompileError::new(ctx.format_error(
+            return Err(ctx.error("E0303",
             "try_run() must be bound via let (e.g., let r = try_run(...))",
             span,
-        )));
+        ));
     }
 
     if name == "stdin_lines" || name == "find0" {
-            return Err(CompileError::new(ctx.format_error(
+            return Err(ctx.error("E0303",
             &format!("{}() can only be used as the iterable in a for-loop", name),
             span,
-        )));
+        ));
     }
     
     if name == "write_file" {
@@ -140,10 +252,10 @@ fn check_function_call(
         // If 3rd arg exists, it MUST be a boolean literal
         if args.len() >= 3 {
             if !matches!(args[2].node, ast::ExprKind::Bool(_)) {
-                    return Err(CompileError::new(ctx.format_error(
+                    return Err(ctx.error("E0304",
                     "write_file: append must be boolean literal",
                     args[2].span,
-                )));
+                ));
             }
         }
     }
@@ -174,21 +286,42 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
                 check_expr(value, ctx)?;
             }
 
+            check_variable_name(&name.node, name.span, ctx)?;
+
             // Check for redeclaration on the SAME path
             if ctx.declared_in_straight_line.contains(&name.node) {
                 let msg = format!(
                     "variable '{}' already declared in this scope (on this execution path). Did you mean to use `set {} = ...`?", 
                     name.node, name.node
                 );
-                return Err(CompileError::new(ctx.format_error(&msg, name.span)));
+                return Err(ctx.error("E0201", &msg, name.span));
             }
 
             // Declare the variable
+            ctx.declare_maybe(&name.node, name.span);
             ctx.declared_in_straight_line.insert(name.node.clone());
             ctx.definitely_assigned.insert(name.node.clone());
             ctx.ever_declared.insert(name.node.clone());
         }
 
+        StmtKind::LetValues { names, value, .. } => {
+            check_expr(value, ctx)?;
+            for name in names {
+                check_variable_name(&name.node, name.span, ctx)?;
+                if ctx.declared_in_straight_line.contains(&name.node) {
+                    let msg = format!(
+                        "variable '{}' already declared in this scope (on this execution path). Did you mean to use `set {} = ...`?",
+                        name.node, name.node
+                    );
+                    return Err(ctx.error("E0201", &msg, name.span));
+                }
+                ctx.declare_maybe(&name.node, name.span);
+                ctx.declared_in_straight_line.insert(name.node.clone());
+                ctx.definitely_assigned.insert(name.node.clone());
+                ctx.ever_declared.insert(name.node.clone());
+            }
+        }
+
         StmtKind::Set { target, value } => {
             // Check the RHS expression first
             check_expr(value, ctx)?;
@@ -201,7 +334,7 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
                     // Add hint
                     msg.push_str(&format!(". Did you mean to use `let {} = ...`?", name.node));
                     
-                    return Err(CompileError::new(ctx.format_error(&msg, name.span)));
+                    return Err(ctx.error("E0200", &msg, name.span));
                 }
             }
             // env.X is always allowed (no local declaration needed)
@@ -226,12 +359,15 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
 
             // Save state *before* branches
             let before = ctx.clone_state();
+            let maybe_before = ctx.maybe_declared.clone();
+            let mut maybe_after = maybe_before.clone();
 
             // Check then branch
             // Start with snapshot to isolate branch
             ctx.restore_state(before.clone()); 
             check_block(then_body, ctx)?;
             let after_then = ctx.clone_state();
+            maybe_after.extend(std::mem::replace(&mut ctx.maybe_declared, maybe_before.clone()));
 
             // Check elif branches
             let mut elif_results = Vec::new();
@@ -241,6 +377,7 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
                 check_expr(&elif.cond, ctx)?;
                 check_block(&elif.body, ctx)?;
                 elif_results.push(ctx.clone_state());
+                maybe_after.extend(std::mem::replace(&mut ctx.maybe_declared, maybe_before.clone()));
             }
 
             // Check else branch
@@ -248,6 +385,7 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
                 // Restore snapshot for disjoint branch
                 ctx.restore_state(before.clone());
                 check_block(body, ctx)?;
+                maybe_after.extend(std::mem::take(&mut ctx.maybe_declared));
                 ctx.clone_state()
             } else {
                 // No else = skip branches = returns to before state
@@ -273,6 +411,7 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
                 declared_in_straight_line: merged_path,
                 definitely_assigned: merged_def,
             });
+            ctx.maybe_declared = maybe_after;
         }
 
         StmtKind::While { cond, body } => {
@@ -319,13 +458,15 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
             }
 
             // For-loop var is treated as implicit let
+            check_variable_name(&var.node, var.span, ctx)?;
             if ctx.declared_in_straight_line.contains(&var.node) {
                  let msg = format!(
                     "variable '{}' already declared in this scope (on this execution path). Did you mean to use `set {} = ...`?", 
                     var.node, var.node
                 );
-                return Err(CompileError::new(ctx.format_error(&msg, var.span)));
+                return Err(ctx.error("E0201", &msg, var.span));
             }
+            ctx.declare_maybe(&var.node, var.span);
             ctx.declared_in_straight_line.insert(var.node.clone());
             ctx.definitely_assigned.insert(var.node.clone());
             ctx.ever_declared.insert(var.node.clone());
@@ -352,20 +493,22 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
         StmtKind::ForMap { key_var, val_var, map, body } => {
             // Check that map variable exists
             if !ctx.definitely_assigned.contains(map) {
-                return Err(CompileError::new(ctx.format_error(
+                return Err(ctx.error("E0200",
                     &format!("undefined variable '{}'", map),
                     stmt.span,
-                )));
+                ));
             }
 
             // Declare loop vars
             for var in [key_var, val_var] {
+                check_variable_name(&var.node, var.span, ctx)?;
                 if ctx.declared_in_straight_line.contains(&var.node) {
-                     return Err(CompileError::new(ctx.format_error(
+                     return Err(ctx.error("E0201",
                         &format!("variable '{}' already declared in this scope. Did you mean to use `set {} = ...`?", var.node, var.node),
                         var.span,
-                    )));
+                    ));
                 }
+                ctx.declare_maybe(&var.node, var.span);
                 ctx.declared_in_straight_line.insert(var.node.clone());
                 ctx.definitely_assigned.insert(var.node.clone());
                 ctx.ever_declared.insert(var.node.clone());
@@ -386,19 +529,24 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
 
         StmtKind::Case { expr, arms } => {
             check_expr(expr, ctx)?;
+            check_case_patterns(arms, ctx);
 
             let before = ctx.clone_state();
+            let maybe_before = ctx.maybe_declared.clone();
+            let mut maybe_after = maybe_before.clone();
             let mut arm_results = Vec::new();
 
             for arm in arms {
                 ctx.restore_state(before.clone());
                 check_block(&arm.body, ctx)?;
                 arm_results.push(ctx.clone_state());
+                maybe_after.extend(std::mem::replace(&mut ctx.maybe_declared, maybe_before.clone()));
             }
+            ctx.maybe_declared = maybe_after;
 
             // Merge all arms (case might not match anything without wildcard)
             
-            let has_wildcard = arms.iter().any(|a| a.patterns.iter().any(|p| matches!(p, ast::Pattern::Wildcard)));
+            let has_wildcard = arms.iter().any(|a| a.patterns.iter().any(|p| matches!(p.node, ast::Pattern::Wildcard)));
             
             let mut merged_def: HashSet<String>;
             let mut merged_path: HashSet<String>;
@@ -478,7 +626,7 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
             check_block(body, ctx)?;
         }
 
-        StmtKind::WithCwd { path, body } => {
+        StmtKind::WithCwd { path, body } | StmtKind::WithDeadline { duration: path, body } | StmtKind::WithUmask { mask: path, body } => {
             check_expr(path, ctx)?;
             check_block(body, ctx)?;
         }
@@ -505,7 +653,10 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
             check_block(body, ctx)?;
         }
 
-        StmtKind::Subshell { body } | StmtKind::Group { body } => {
+        StmtKind::Subshell { body }
+        | StmtKind::Group { body }
+        | StmtKind::Unchecked { body }
+        | StmtKind::WithLimits { body, .. } => {
             check_block(body, ctx)?;
         }
 
@@ -521,8 +672,17 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
             check_expr(e, ctx)?;
         }
 
+        StmtKind::ReturnValues(values) => {
+            for v in values {
+                check_expr(v, ctx)?;
+            }
+        }
+
         StmtKind::Sh(e) => {
             check_expr(e, ctx)?;
+            if let ExprKind::Literal(text) = &e.node {
+                ctx.check_shell_text(text, e.span);
+            }
         }
 
         StmtKind::Cd { path } => {
@@ -543,8 +703,11 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
             }
         }
 
-        StmtKind::Call { name, args } => {
+        StmtKind::Call { name, args, options } => {
             check_function_call(name, args, stmt.span, ctx)?;
+            for o in options {
+                check_expr(&o.value, ctx)?;
+            }
         }
 
         StmtKind::QualifiedCall { args, .. } => {
@@ -553,59 +716,14 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
             }
         }
 
-        StmtKind::Pipe(segments) => {
-            for seg in segments {
-                match &seg.node {
-                    ast::PipeSegment::Run(run_call) | ast::PipeSegment::Sudo(run_call) => {
-                        for arg in &run_call.args {
-                            check_expr(arg, ctx)?;
-                        }
-                        for opt in &run_call.options {
-                            check_expr(&opt.value, ctx)?;
-                        }
-                    }
-                    ast::PipeSegment::Block(stmts) => {
-                        check_block(stmts, ctx)?;
-                    }
-                    ast::PipeSegment::EachLine(var, body) => {
-                         // Similar to For loop: Implicit Declaration
-                         if ctx.declared_in_straight_line.contains(&var.node) { 
-                             return Err(CompileError::new(ctx.format_error(
-                                 &format!("variable '{}' already declared in this scope. Did you mean to use `set {} = ...`?", var.node, var.node),
-                                 var.span,
-                              )));
-                        }
-                        ctx.declared_in_straight_line.insert(var.node.clone());
-                        ctx.definitely_assigned.insert(var.node.clone());
-                        ctx.ever_declared.insert(var.node.clone());
-
-                        let before = ctx.clone_state();
-                        
-                        check_block(body, ctx)?;
-                        
-                        // Body execution conditional, but variable declaration persists (lifted)
-                        // Merge with matching logic of `for` loop logic - intersection.
-                        // Since `var` was in `before`, it will be in intersection if `after_body` has it.
-                        let after_body = ctx.clone_state();
-                        
-                        // Wait, previous logic was: "Logic dictates it persists... restore(before)".
-                        // BUT that was wrong. `restore(before)` restores the whole state.
-                        // We must do merging logic like FOR loop.
-                        
-                        let merged_def: HashSet<String> = before.definitely_assigned.intersection(&after_body.definitely_assigned).cloned().collect();
-                        let merged_path: HashSet<String> = before.declared_in_straight_line.intersection(&after_body.declared_in_straight_line).cloned().collect();
-
-                        ctx.restore_state(BinderContextState {
-                            declared_in_straight_line: merged_path,
-                            definitely_assigned: merged_def,
-                        });
-                    }
-                }
-            }
-        }
+        StmtKind::Pipe(segments) => check_pipe_segments(segments, ctx)?,
 
-        StmtKind::ShBlock(_) => {
-            // Raw shell lines, nothing to check
+        StmtKind::ShBlock { lines, bridge } => {
+            // Plain blocks are raw shell lines; only unset reads are flagged.
+            match bridge {
+                Some(bridge) => check_sh_bridge(lines, bridge, stmt.span, ctx)?,
+                None => ctx.check_shell_text(&lines.join("\n"), stmt.span),
+            }
         }
 
         // Terminal statements with no expressions
@@ -617,18 +735,329 @@ fn check_stmt(stmt: &ast::Stmt, ctx: &mut BinderContext) -> Result<(), CompileEr
     Ok(())
 }
 
+/// Check pipeline segments, shared by statement pipelines and `let`-bound pipelines.
+/// `sh use(a) export(b) { ... }`: listed reads must be declared, the lines may only
+/// touch sh2 variables through the lists, and exports are declared afterwards.
+fn check_sh_bridge(
+    lines: &[String],
+    bridge: &ast::ShBridge,
+    span: crate::span::Span,
+    ctx: &mut BinderContext,
+) -> Result<(), CompileError> {
+    for name in &bridge.uses {
+        if !ctx.definitely_assigned.contains(name) {
+            return Err(ctx.error("E0200", &format!("undefined variable '{}'", name), span));
+        }
+    }
+    let is_sh2_var = |name: &str| ctx.ever_declared.contains(name);
+    let listed = |name: &str| bridge.uses.iter().chain(&bridge.exports).any(|n| n == name);
+    for line in lines {
+        if let Some(name) = shell_reads(line).into_iter().find(|n| is_sh2_var(n) && !listed(n)) {
+            let msg = format!("sh block reads sh2 variable '{}' without listing it in use(...)", name);
+            return Err(ctx.error("E0209", &msg, span));
+        }
+        if let Some(name) = shell_assignments(line)
+            .into_iter()
+            .find(|n| is_sh2_var(n) && !bridge.exports.contains(n))
+        {
+            let msg = format!("sh block assigns sh2 variable '{}' without listing it in export(...)", name);
+            return Err(ctx.error("E0209", &msg, span));
+        }
+    }
+    for name in &bridge.exports {
+        ctx.maybe_declared.entry(name.clone()).or_insert(span);
+        ctx.declared_in_straight_line.insert(name.clone());
+        ctx.definitely_assigned.insert(name.clone());
+        ctx.ever_declared.insert(name.clone());
+    }
+    Ok(())
+}
+
+/// A `case` pattern as a sequence of glob symbols.
+#[derive(Clone, PartialEq)]
+enum GlobSym {
+    /// `*`: any run of characters
+    Any,
+    /// `?`: exactly one character
+    One,
+    /// `[...]`: one character from a class (text between the brackets)
+    Class(String),
+    Char(char),
+}
+
+fn glob_syms(pattern: &ast::Pattern) -> Vec<GlobSym> {
+    match pattern {
+        ast::Pattern::Literal(s) => s.chars().map(GlobSym::Char).collect(),
+        ast::Pattern::Glob(s) => {
+            let mut syms = Vec::new();
+            let mut chars = s.chars();
+            while let Some(c) = chars.next() {
+                syms.push(match c {
+                    '*' => GlobSym::Any,
+                    '?' => GlobSym::One,
+                    // The parser only accepts closed classes.
+                    '[' => {
+                        let mut class = String::new();
+                        while let Some(c) = chars.next() {
+                            match c {
+                                ']' if !class.is_empty() && class != "!" && class != "^" => break,
+                                '[' if chars.as_str().starts_with(':') => {
+                                    class.push('[');
+                                    let end = chars.as_str().find(":]").map_or(0, |i| i + 2);
+                                    class.extend(chars.by_ref().take(end));
+                                }
+                                c => class.push(c),
+                            }
+                        }
+                        GlobSym::Class(class)
+                    }
+                    c => GlobSym::Char(c),
+                });
+            }
+            syms
+        }
+        ast::Pattern::Wildcard => vec![GlobSym::Any],
+    }
+}
+
+/// Whether the class text between `[` and `]` matches `c`. `[:name:]` items
+/// count as matching nothing, which only ever hides a warning.
+fn class_matches(class: &str, c: char) -> bool {
+    let (negated, body) = match class.strip_prefix(['!', '^']) {
+        Some(body) => (true, body),
+        None => (false, class),
+    };
+    let items: Vec<char> = body.chars().collect();
+    let mut hit = false;
+    let mut i = 0;
+    while i < items.len() {
+        if items[i] == '[' {
+            if negated {
+                return false;
+            }
+            i += body[i..].find(":]").map_or(items.len(), |j| j + 2);
+            continue;
+        }
+        if items.get(i + 1) == Some(&'-') && i + 2 < items.len() {
+            hit |= (items[i]..=items[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            hit |= items[i] == c;
+            i += 1;
+        }
+    }
+    hit != negated
+}
+
+/// Whether every string `inner` matches is also matched by `outer`. Exact for
+/// literals; for two globs it may miss unusual equivalences (`*?` vs `?*`) but
+/// never reports a cover that does not hold.
+fn glob_covers(outer: &[GlobSym], inner: &[GlobSym]) -> bool {
+    // covers[i][j]: outer[i..] covers inner[j..]
+    let mut covers = vec![vec![false; inner.len() + 1]; outer.len() + 1];
+    covers[outer.len()][inner.len()] = true;
+    for i in (0..outer.len()).rev() {
+        for j in (0..=inner.len()).rev() {
+            let next = inner.get(j);
+            covers[i][j] = match &outer[i] {
+                GlobSym::Any => covers[i + 1][j] || (next.is_some() && covers[i][j + 1]),
+                GlobSym::One => {
+                    matches!(next, Some(GlobSym::One | GlobSym::Class(_) | GlobSym::Char(_))) && covers[i + 1][j + 1]
+                }
+                GlobSym::Class(class) => {
+                    let one = match next {
+                        Some(GlobSym::Char(c)) => class_matches(class, *c),
+                        Some(GlobSym::Class(other)) => other == class,
+                        _ => false,
+                    };
+                    one && covers[i + 1][j + 1]
+                }
+                GlobSym::Char(c) => next == Some(&GlobSym::Char(*c)) && covers[i + 1][j + 1],
+            };
+        }
+    }
+    covers[0][0]
+}
+
+fn display_pattern(pattern: &ast::Pattern) -> String {
+    match pattern {
+        ast::Pattern::Literal(s) if s.contains(crate::parser::GLOB_CHARS) => format!("literal(\"{}\")", s),
+        ast::Pattern::Literal(s) => format!("\"{}\"", s),
+        ast::Pattern::Glob(s) => format!("glob(\"{}\")", s),
+        ast::Pattern::Wildcard => "_".to_string(),
+    }
+}
+
+/// Warns about `case` patterns that can never match because an earlier pattern
+/// (in this arm or a previous one) already matches everything they would.
+fn check_case_patterns(arms: &[ast::CaseArm], ctx: &mut BinderContext) {
+    let mut earlier: Vec<(&ast::Spanned<ast::Pattern>, Vec<GlobSym>)> = Vec::new();
+    for pattern in arms.iter().flat_map(|arm| &arm.patterns) {
+        let syms = glob_syms(&pattern.node);
+        if let Some((first, _)) = earlier.iter().find(|(_, outer)| glob_covers(outer, &syms)) {
+            let (line, _) = ctx.sm.line_col(first.span.start);
+            let msg = match (&first.node, &pattern.node) {
+                (ast::Pattern::Wildcard, _) => {
+                    format!("unreachable case pattern {}: the `_` at line {} already matches everything", display_pattern(&pattern.node), line)
+                }
+                (a, b) if a == b || glob_syms(a) == syms => {
+                    format!("duplicate case pattern {}: it already appears at line {}", display_pattern(b), line)
+                }
+                (a, b) => format!(
+                    "case pattern {} is shadowed by {} at line {} and can never match",
+                    display_pattern(b),
+                    display_pattern(a),
+                    line
+                ),
+            };
+            ctx.warnings.push(ctx.format_error("W0009", &msg, pattern.span));
+        }
+        earlier.push((pattern, syms));
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Variables expanded by a shell line (`$name`, `${name...}`), ignoring text in
+/// single quotes and backslash-escaped dollars.
+fn shell_reads(line: &str) -> Vec<String> {
+    shell_expansions(line).into_iter().map(|(name, _)| name).collect()
+}
+
+/// Like `shell_reads`, also telling whether each expansion supplies a value for
+/// an unset variable (`${name:-...}`, `${name=...}`, `${name:+...}` and friends).
+fn shell_expansions(line: &str) -> Vec<(String, bool)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = Vec::new();
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if !in_single => i += 1,
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            '$' if !in_single => {
+                let braced = chars.get(i + 1) == Some(&'{');
+                let start = if braced { i + 2 } else { i + 1 };
+                if chars.get(start).is_some_and(|c| is_name_start(*c)) {
+                    let end = (start..chars.len()).find(|&j| !is_name_char(chars[j])).unwrap_or(chars.len());
+                    let op: String = chars[end..].iter().take(2).collect();
+                    let defaulted = braced && [":-", ":=", ":+", "-", "=", "+"].iter().any(|p| op.starts_with(p));
+                    out.push((chars[start..end].iter().collect(), defaulted));
+                    i = end - 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Variables assigned at command position (`name=value`, also after `;`, `&&`,
+/// `||`, `|`, `(`, `{`, `then`, `do`, `else`, `export`, `local`) and loop
+/// variables of `for name in`.
+fn shell_assignments(line: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut command_start = true;
+    let mut after_for = false;
+    for word in line.split_whitespace() {
+        let word = word.trim_start_matches(['(', '{']);
+        if after_for {
+            out.push(word.to_string());
+            after_for = false;
+        } else if command_start {
+            if let Some((name, _)) = word.split_once('=')
+                && name.starts_with(is_name_start)
+                && name.chars().all(is_name_char)
+            {
+                out.push(name.to_string());
+                continue;
+            }
+            after_for = word == "for";
+        }
+        command_start = matches!(word, "" | "then" | "do" | "else" | "export" | "local" | "readonly" | "!")
+            || word.ends_with([';', '|', '&']);
+    }
+    out
+}
+
+fn check_pipe_segments(segments: &[ast::Spanned<ast::PipeSegment>], ctx: &mut BinderContext) -> Result<(), CompileError> {
+    for seg in segments {
+        match &seg.node {
+            ast::PipeSegment::Run(run_call) | ast::PipeSegment::Sudo(run_call) => {
+                for arg in &run_call.args {
+                    check_expr(arg, ctx)?;
+                }
+                for opt in &run_call.options {
+                    check_expr(&opt.value, ctx)?;
+                }
+                if let Some(ast::RedirectOutputTarget::File { path, .. }) = run_call.stderr.as_deref().map(|t| &t.node) {
+                    check_expr(path, ctx)?;
+                }
+            }
+            ast::PipeSegment::Block(stmts) => {
+                check_block(stmts, ctx)?;
+            }
+            ast::PipeSegment::EachLine(var, _, body) => {
+                 // Similar to For loop: Implicit Declaration
+                 check_variable_name(&var.node, var.span, ctx)?;
+                 if ctx.declared_in_straight_line.contains(&var.node) { 
+                     return Err(ctx.error("E0201",
+                         &format!("variable '{}' already declared in this scope. Did you mean to use `set {} = ...`?", var.node, var.node),
+                         var.span,
+                      ));
+                }
+                ctx.declare_maybe(&var.node, var.span);
+                ctx.declared_in_straight_line.insert(var.node.clone());
+                ctx.definitely_assigned.insert(var.node.clone());
+                ctx.ever_declared.insert(var.node.clone());
+
+                let before = ctx.clone_state();
+                
+                check_block(body, ctx)?;
+                
+                // Body execution conditional, but variable declaration persists (lifted)
+                // Merge with matching logic of `for` loop logic - intersection.
+                // Since `var` was in `before`, it will be in intersection if `after_body` has it.
+                let after_body = ctx.clone_state();
+                
+                // Wait, previous logic was: "Logic dictates it persists... restore(before)".
+                // BUT that was wrong. `restore(before)` restores the whole state.
+                // We must do merging logic like FOR loop.
+                
+                let merged_def: HashSet<String> = before.definitely_assigned.intersection(&after_body.definitely_assigned).cloned().collect();
+                let merged_path: HashSet<String> = before.declared_in_straight_line.intersection(&after_body.declared_in_straight_line).cloned().collect();
+
+                ctx.restore_state(BinderContextState {
+                    declared_in_straight_line: merged_path,
+                    definitely_assigned: merged_def,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
 fn check_expr(expr: &ast::Expr, ctx: &mut BinderContext) -> Result<(), CompileError> {
     match &expr.node {
         ExprKind::Var(name) => {
-            if !ctx.definitely_assigned.contains(name) {
-                return Err(CompileError::new(ctx.format_error(
+            if !ctx.definitely_assigned.contains(name) && !ctx.functions.contains(name) {
+                return Err(ctx.error("E0200",
                     &format!("undefined variable '{}'", name),
                     expr.span,
-                )));
+                ));
             }
         }
         ExprKind::Literal(_) | ExprKind::Bool(_) | ExprKind::Number(_) => {}
-        ExprKind::Concat(l, r) | ExprKind::And(l, r) | ExprKind::Or(l, r) => {
+        ExprKind::Concat(l, r) | ExprKind::Coalesce(l, r) | ExprKind::And(l, r) | ExprKind::Or(l, r) => {
             check_expr(l, ctx)?;
             check_expr(r, ctx)?;
         }
@@ -636,14 +1065,14 @@ fn check_expr(expr: &ast::Expr, ctx: &mut BinderContext) -> Result<(), CompileEr
             check_expr(left, ctx)?;
             check_expr(right, ctx)?;
         }
-        ExprKind::Not(e) | ExprKind::Exists(e) | ExprKind::IsDir(e) | ExprKind::IsFile(e)
+        ExprKind::Not(e) | ExprKind::Spread(e) | ExprKind::Exists(e) | ExprKind::IsDir(e) | ExprKind::IsFile(e)
         | ExprKind::IsSymlink(e) | ExprKind::IsExec(e) | ExprKind::IsReadable(e)
         | ExprKind::IsWritable(e) | ExprKind::IsNonEmpty(e) | ExprKind::BoolStr(e)
         | ExprKind::Len(e) | ExprKind::Count(e) | ExprKind::Arg(e) | ExprKind::Env(e)
         | ExprKind::Input(e) => {
             check_expr(e, ctx)?;
         }
-        ExprKind::Index { list, index } => {
+        ExprKind::Index { list, index, .. } => {
             check_expr(list, ctx)?;
             check_expr(index, ctx)?;
         }
@@ -654,6 +1083,11 @@ fn check_expr(expr: &ast::Expr, ctx: &mut BinderContext) -> Result<(), CompileEr
             check_expr(list, ctx)?;
             check_expr(sep, ctx)?;
         }
+        ExprKind::IfElse { cond, then_expr, else_expr } => {
+            check_expr(cond, ctx)?;
+            check_expr(then_expr, ctx)?;
+            check_expr(else_expr, ctx)?;
+        }
         ExprKind::List(items) => {
             for item in items {
                 check_expr(item, ctx)?;
@@ -666,10 +1100,10 @@ fn check_expr(expr: &ast::Expr, ctx: &mut BinderContext) -> Result<(), CompileEr
         }
         ExprKind::MapIndex { map, .. } => {
             if !ctx.definitely_assigned.contains(map) {
-                return Err(CompileError::new(ctx.format_error(
+                return Err(ctx.error("E0200",
                     &format!("undefined variable '{}'", map),
                     expr.span,
-                )));
+                ));
             }
         }
         ExprKind::Call { name, args, options: _ } => {
@@ -696,6 +1130,9 @@ fn check_expr(expr: &ast::Expr, ctx: &mut BinderContext) -> Result<(), CompileEr
         }
         ExprKind::Sh { cmd, options } => {
             check_expr(cmd, ctx)?;
+            if let ExprKind::Literal(text) = &cmd.node {
+                ctx.check_shell_text(text, cmd.span);
+            }
             for opt in options {
                 check_expr(&opt.value, ctx)?;
             }
@@ -714,7 +1151,18 @@ fn check_expr(expr: &ast::Expr, ctx: &mut BinderContext) -> Result<(), CompileEr
                 check_expr(&opt.value, ctx)?;
             }
         }
-        ExprKind::Command(_) | ExprKind::CommandPipe(_) | ExprKind::Args
+        ExprKind::Pipeline(segments) => check_pipe_segments(segments, ctx)?,
+        ExprKind::Command(args) => {
+            for a in args {
+                check_expr(a, ctx)?;
+            }
+        }
+        ExprKind::CommandPipe(segments) => {
+            for a in segments.iter().flatten() {
+                check_expr(a, ctx)?;
+            }
+        }
+        ExprKind::Args
         | ExprKind::Status | ExprKind::Pid | ExprKind::Uid | ExprKind::Ppid
         | ExprKind::Pwd | ExprKind::SelfPid | ExprKind::Argv0 | ExprKind::Argc
         | ExprKind::EnvDot(_) | ExprKind::QualifiedCommandWord { .. } => {}
@@ -730,7 +1178,7 @@ fn check_redirect_output(target: &ast::RedirectOutputTarget, ctx: &mut BinderCon
 }
 
 fn check_redirect_input(target: &ast::RedirectInputTarget, ctx: &mut BinderContext) -> Result<(), CompileError> {
-    if let ast::RedirectInputTarget::File { path } = target {
+    if let ast::RedirectInputTarget::File { path } | ast::RedirectInputTarget::Text { value: path } = target {
         check_expr(path, ctx)?;
     }
     Ok(())
@@ -22,23 +22,38 @@ impl Span {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Diagnostic {
     pub msg: String,
+    /// Code from `diag_codes::CODES`; when `None` it is picked from `msg`.
+    pub code: Option<&'static str>,
     pub span: Span,
     pub sm: Option<SourceMap>,
     pub file: Option<String>,
     pub help: Option<String>,
+    /// Further errors reported together with this one, e.g. the rest of the
+    /// syntax errors the parser recovered from.
+    pub additional: Vec<Diagnostic>,
 }
 
 impl Diagnostic {
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
     pub fn format(&self, base: Option<&std::path::Path>) -> String {
         let main = if let (Some(sm), Some(file)) = (&self.sm, &self.file) {
-            sm.format_diagnostic(file, base, &self.msg, self.span)
+            sm.format_coded_diagnostic(file, base, self.code, &self.msg, self.span)
         } else {
-            format!("error: {}", self.msg)
+            format!("error: {}", crate::diag_codes::tagged_with(&crate::names::display_idents(&self.msg), self.code))
         };
-        match &self.help {
+        let mut out = match &self.help {
             Some(help) => format!("{}\nhelp: {}", main, help),
             None => main,
+        };
+        for d in &self.additional {
+            out.push_str("\n\n");
+            out.push_str(&d.format(base));
         }
+        out
     }
 }
 
@@ -98,6 +113,18 @@ impl SourceMap {
     }
 
     pub fn format_diagnostic(&self, file: &str, base: Option<&std::path::Path>, msg: &str, span: Span) -> String {
+        self.format_coded_diagnostic(file, base, None, msg, span)
+    }
+
+    /// Like `format_diagnostic`, tagging the message with `code` instead of classifying it.
+    pub fn format_coded_diagnostic(
+        &self,
+        file: &str,
+        base: Option<&std::path::Path>,
+        code: Option<&str>,
+        msg: &str,
+        span: Span,
+    ) -> String {
         let (start_line, start_col) = self.line_col(span.start);
         let (end_line, _) = self.line_col(span.end);
         let snippet = self.line_snippet(start_line);
@@ -150,7 +177,7 @@ impl SourceMap {
 
         format!(
             "{}:{}:{}: {}\n{}\n{}",
-            display_file, start_line, start_col, msg, snippet, arrow
+            display_file, start_line, start_col, crate::diag_codes::tagged_with(&crate::names::display_idents(msg), code), snippet, arrow
         )
     }
 }
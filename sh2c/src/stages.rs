@@ -0,0 +1,49 @@
+//! The in-memory checks between loading a program and lowering it, shared by
+//! the file driver and the playground so both reject and warn about the same
+//! things.
+
+use crate::ast::Program;
+use crate::error::CompileError;
+use crate::fixes::Suggestion;
+use crate::{deprecation, semantics, stdin_guard, taint, unchecked_status};
+use std::path::Path;
+
+#[derive(Default)]
+pub struct CheckOptions<'a> {
+    pub diag_base_dir: Option<&'a Path>,
+    /// Make tainted `sh()` warnings errors (`--deny-tainted-sh`).
+    pub deny_tainted_sh: bool,
+}
+
+/// Runs semantic analysis, the taint check and the warning passes over
+/// `program`; `suggestions` come from `fixes::collect`, and the error-level
+/// ones fail the check. Returns the warnings in the order `sh2c` prints them,
+/// or the error text.
+pub fn check_program(program: &Program, suggestions: &[Suggestion], opts: &CheckOptions) -> Result<Vec<String>, String> {
+    let base = opts.diag_base_dir;
+    // Semantic analysis: check variable declarations before lowering
+    let semantic_warnings = semantics::check_semantics(program, &semantics::SemanticOptions {
+        diag_base_dir: base.map(Path::to_path_buf),
+    }).map_err(|e| e.to_string())?;
+
+    let mut warnings = taint::check_tainted_sh(program, base);
+    if opts.deny_tainted_sh && !warnings.is_empty() {
+        let msgs: Vec<String> = warnings.into_iter().map(|w| CompileError::new(w).to_string()).collect();
+        return Err(msgs.join("\n"));
+    }
+
+    let (fix_errors, fix_warnings): (Vec<_>, Vec<_>) = suggestions.iter().partition(|s| s.is_error);
+    if !fix_errors.is_empty() {
+        let msgs: Vec<String> = fix_errors
+            .iter()
+            .map(|s| CompileError::new(s.render(program, base)).to_string())
+            .collect();
+        return Err(msgs.join("\n"));
+    }
+    warnings.extend(fix_warnings.iter().map(|s| s.render(program, base)));
+    warnings.extend(semantic_warnings);
+    warnings.extend(deprecation::check_deprecated_calls(program, base));
+    warnings.extend(stdin_guard::check_stdin_reads(program, base));
+    warnings.extend(unchecked_status::check_unchecked_status(program, base));
+    Ok(warnings)
+}
@@ -0,0 +1,78 @@
+//! Warnings for stdin reads that steal an `each_line` loop's input.
+//!
+//! The body of `run(...) | each_line line { ... }` runs with the pipeline's
+//! output on stdin, so an `input()`, `confirm()` or `for x in stdin_lines()`
+//! inside it reads the lines meant for the loop, the classic `while read`
+//! bug. `each_line(fd=3) line` reads the lines on fd 3 instead and leaves
+//! stdin alone, which is what the warning suggests.
+
+use crate::ast::{self, ExprKind, Program, StmtKind};
+use crate::span::Span;
+use crate::warn_walk::{self, Reporter, Visitor};
+use std::path::Path;
+
+struct Checker<'a> {
+    r: Reporter<'a>,
+    /// Inside an `each_line` body whose stdin is the loop's input.
+    in_loop: bool,
+}
+
+/// Returns one formatted warning per stdin read inside an `each_line` body.
+pub fn check_stdin_reads(program: &Program, base: Option<&Path>) -> Vec<String> {
+    warn_walk::check_functions(program, base, |func, r| Checker { r, in_loop: false }.block(&func.body))
+}
+
+impl Checker<'_> {
+    fn read(&mut self, what: &str, span: Span) {
+        if !self.in_loop {
+            return;
+        }
+        let msg = format!(
+            "stdin read inside each_line: {} consumes the lines meant for the loop; \
+             use `each_line(fd=3) line {{ ... }}` to read them on a separate file descriptor",
+            what
+        );
+        self.r.warn("W0005", &msg, span);
+    }
+}
+
+impl Visitor for Checker<'_> {
+    fn stmt(&mut self, stmt: &ast::Stmt) {
+        match &stmt.node {
+            StmtKind::For { iterable: ast::ForIterable::StdinLines, .. } => {
+                self.read("stdin_lines()", stmt.span);
+                warn_walk::walk_stmt(self, stmt);
+            }
+            // A redirected stdin replaces the loop's input for the body.
+            StmtKind::WithRedirect { stdin, .. } => {
+                let in_loop = self.in_loop;
+                self.in_loop &= stdin.is_none();
+                warn_walk::walk_stmt(self, stmt);
+                self.in_loop = in_loop;
+            }
+            _ => warn_walk::walk_stmt(self, stmt),
+        }
+    }
+
+    /// Only the first segment shares the enclosing stdin; the others read the pipe.
+    fn pipe_segments(&mut self, segments: &[ast::Spanned<ast::PipeSegment>]) {
+        let in_loop = self.in_loop;
+        for (i, seg) in segments.iter().enumerate() {
+            self.in_loop = match &seg.node {
+                ast::PipeSegment::EachLine(_, fd, _) => fd.is_none(),
+                _ => in_loop && i == 0,
+            };
+            warn_walk::walk_pipe_segment(self, seg);
+        }
+        self.in_loop = in_loop;
+    }
+
+    fn expr(&mut self, expr: &ast::Expr) {
+        match &expr.node {
+            ExprKind::Input(_) => self.read("input()", expr.span),
+            ExprKind::Confirm { .. } => self.read("confirm()", expr.span),
+            _ => {}
+        }
+        warn_walk::walk_expr(self, expr);
+    }
+}
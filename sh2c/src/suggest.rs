@@ -28,14 +28,15 @@ fn levenshtein(a: &str, b: &str) -> usize {
 /// Return the single best suggestion from `candidates` for `input`,
 /// or `None` if nothing is close enough.
 ///
-/// Threshold: `max(1, min(2, input.len() / 2))`.
+/// Threshold: `input.len() / 3`, clamped to `1..=2`, so short names only
+/// match near-identical candidates (`nope` does not suggest `home`).
 /// Candidates are sorted before scoring for deterministic results.
 pub fn suggest(input: &str, candidates: &[&str]) -> Option<String> {
     if candidates.is_empty() {
         return None;
     }
 
-    let threshold = std::cmp::max(1, std::cmp::min(2, input.len() / 2));
+    let threshold = (input.len() / 3).clamp(1, 2);
 
     let mut sorted: Vec<&str> = candidates.to_vec();
     sorted.sort();
@@ -93,6 +94,12 @@ mod tests {
         assert_eq!(suggest("aa", &["ac", "ab"]), Some("ab".to_string()));
     }
 
+    #[test]
+    fn test_short_input_needs_close_match() {
+        assert_eq!(suggest("nope", &["home", "print"]), None);
+        assert_eq!(suggest("prnt", &["home", "print"]), Some("print".to_string()));
+    }
+
     #[test]
     fn test_near_miss_function() {
         assert_eq!(suggest("gret", &["greet", "sum", "helper"]), Some("greet".to_string()));
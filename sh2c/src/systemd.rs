@@ -0,0 +1,127 @@
+//! `sh2c --emit-systemd`: a `.service` unit written next to the compiled script.
+//!
+//! Settings come from an optional `@service("key=value", ...)` attribute on the
+//! entry function; anything not given gets a default suited to a long-running
+//! script (`Type=simple`, `Restart=on-failure`, `WantedBy=multi-user.target`).
+//! `ExecStart` is the absolute path of the output script unless `exec=` names
+//! where it will be installed.
+
+use crate::ast::{Attribute, Program};
+use std::path::{Path, PathBuf};
+
+/// `@service` keys, in the order they appear in the unit.
+const KEYS: &[&str] = &[
+    "description", "after", "type", "exec", "restart", "restart_sec", "user", "group", "working_dir",
+    "env_file", "wanted_by",
+];
+
+const TYPES: &[&str] = &["simple", "exec", "oneshot", "forking", "notify", "idle"];
+const RESTARTS: &[&str] = &["no", "always", "on-success", "on-failure", "on-abnormal", "on-abort", "on-watchdog"];
+
+/// Checks one `@service` argument; called by the parser so a typo fails even
+/// without `--emit-systemd`.
+pub fn check_arg(arg: &str) -> Result<(), String> {
+    let Some((key, value)) = arg.split_once('=') else {
+        return Err(format!("@service arguments are \"key=value\" strings, got {:?}", arg));
+    };
+    if !KEYS.contains(&key) {
+        return Err(format!("@service has no key '{}' (expected one of: {})", key, KEYS.join(", ")));
+    }
+    if value.is_empty() || value.contains(['\n', '\r']) {
+        return Err(format!("@service {} must be a non-empty single line", key));
+    }
+    let allowed = match key {
+        "type" => TYPES,
+        "restart" => RESTARTS,
+        _ => return Ok(()),
+    };
+    if !allowed.contains(&value) {
+        return Err(format!("@service {}={} is not valid (expected one of: {})", key, value, allowed.join(", ")));
+    }
+    Ok(())
+}
+
+/// `deploy.sh` -> `deploy.service`, in the same directory.
+pub fn unit_path(script: &Path) -> PathBuf {
+    let stem = script.file_stem().unwrap_or(script.as_os_str()).to_string_lossy();
+    script.with_file_name(format!("{}.service", stem))
+}
+
+/// The unit for `script`, whose entry function is `entry`. Fails when another
+/// function carries the `@service` attribute, since it would be ignored.
+pub fn unit(program: &Program, entry: &str, script: &Path) -> Result<String, String> {
+    if let Some(other) = program.functions.iter().find(|f| f.name != entry && f.attr("service").is_some()) {
+        return Err(format!(
+            "@service on '{}' has no effect; put it on the entry function '{}'",
+            other.name, entry
+        ));
+    }
+    let attr = program.functions.iter().find(|f| f.name == entry).and_then(|f| f.attr("service"));
+    let get = |key: &str| attr.and_then(|a| value(a, key));
+
+    let exec = match get("exec") {
+        Some(path) => PathBuf::from(path),
+        None => std::path::absolute(script).unwrap_or_else(|_| script.to_path_buf()),
+    };
+    let name = script.file_stem().unwrap_or(script.as_os_str()).to_string_lossy();
+
+    let source = Path::new(&program.entry_file).file_name().unwrap_or_default().to_string_lossy();
+    let mut out = format!("# Generated by sh2c from {}\n[Unit]\n", source);
+    out.push_str(&format!("Description={}\n", get("description").unwrap_or(&format!("{} (sh2 script)", name))));
+    if let Some(after) = get("after") {
+        out.push_str(&format!("After={}\nWants={}\n", after, after));
+    }
+
+    out.push_str("\n[Service]\n");
+    out.push_str(&format!("Type={}\n", get("type").unwrap_or("simple")));
+    out.push_str(&format!("ExecStart={}\n", exec_arg(&exec.to_string_lossy())));
+    out.push_str(&format!("Restart={}\n", get("restart").unwrap_or("on-failure")));
+    let settings = [
+        ("RestartSec", "restart_sec"),
+        ("User", "user"),
+        ("Group", "group"),
+        ("WorkingDirectory", "working_dir"),
+        ("EnvironmentFile", "env_file"),
+    ];
+    for (setting, key) in settings {
+        if let Some(v) = get(key) {
+            out.push_str(&format!("{}={}\n", setting, v));
+        }
+    }
+
+    out.push_str("\n[Install]\n");
+    out.push_str(&format!("WantedBy={}\n", get("wanted_by").unwrap_or("multi-user.target")));
+    Ok(out)
+}
+
+fn value<'a>(attr: &'a Attribute, key: &str) -> Option<&'a str> {
+    attr.args.iter().rev().find_map(|a| a.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// systemd splits `ExecStart` on whitespace, so a path containing any is quoted.
+fn exec_arg(path: &str) -> String {
+    if path.contains(char::is_whitespace) {
+        format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_keys_and_values() {
+        assert!(check_arg("restart=always").is_ok());
+        assert!(check_arg("restart=sometimes").unwrap_err().contains("restart=sometimes is not valid"));
+        assert!(check_arg("descripton=x").unwrap_err().contains("no key 'descripton'"));
+        assert!(check_arg("user").unwrap_err().contains("\"key=value\""));
+    }
+
+    #[test]
+    fn quotes_paths_with_spaces() {
+        assert_eq!(exec_arg("/opt/my app/run.sh"), "\"/opt/my app/run.sh\"");
+        assert_eq!(unit_path(Path::new("out/deploy.sh")), Path::new("out/deploy.service"));
+    }
+}
@@ -0,0 +1,534 @@
+//! Taint analysis for shell-code sinks
+//!
+//! Tracks values that originate from outside the script (`input()`, `env()`,
+//! `arg()`/`args()`/`argv()`, `parse_args()`, stdin lines) through variables,
+//! string operations and user function parameters, and reports when one
+//! reaches a place where it is parsed as shell code:
+//! - the command string of `sh(...)` (or its `shell=` option)
+//! - the program word of `exec(...)`, or any argument when `exec` runs a
+//!   shell interpreter (`exec("bash", "-c", cmd)`)
+//!
+//! A variable counts as sanitized inside the `then` branch of an `if` whose
+//! condition checks it with `matches(var, re)` or compares it for equality
+//! with a literal.
+
+use crate::ast::{self, ExprKind, LValue, Program, StmtKind};
+use crate::span::{SourceMap, Span};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Origin label of a tainted value, e.g. `"input()"`.
+type Origin = &'static str;
+
+/// Tainted variables in scope at the current program point.
+type Env = HashMap<String, Origin>;
+
+/// Builtins whose result is a boolean or number and therefore never tainted.
+const CLEAN_CALLS: &[&str] = &[
+    "matches",
+    "contains",
+    "contains_line",
+    "starts_with",
+    "have",
+    "service_running",
+    "wait_for_port",
+    "confirm",
+];
+
+/// Shell interpreters whose arguments `exec` hands to a parser.
+const SHELLS: &[&str] = &["sh", "bash", "dash", "ksh", "zsh", "ash", "mksh"];
+
+struct Reporter<'a> {
+    sm: &'a SourceMap,
+    file: &'a str,
+    base: Option<&'a Path>,
+    seen: &'a mut HashSet<(String, usize)>,
+    out: &'a mut Vec<String>,
+}
+
+struct Analyzer<'a> {
+    user_funcs: &'a HashSet<String>,
+    /// Per-function parameter taint observed at call sites.
+    param_taint: &'a mut HashMap<String, Vec<Option<Origin>>>,
+    changed: bool,
+    reporter: Option<Reporter<'a>>,
+}
+
+/// Runs the analysis and returns one formatted diagnostic per finding.
+pub fn check_tainted_sh(program: &Program, base: Option<&Path>) -> Vec<String> {
+    let user_funcs: HashSet<String> = program.functions.iter().map(|f| f.name.clone()).collect();
+    let mut param_taint: HashMap<String, Vec<Option<Origin>>> = program
+        .functions
+        .iter()
+        .map(|f| (f.name.clone(), vec![None; f.params.len()]))
+        .collect();
+
+    // Parameter taint only ever grows, so this reaches a fixpoint.
+    loop {
+        let mut changed = false;
+        for func in &program.functions {
+            let mut a = Analyzer { user_funcs: &user_funcs, param_taint: &mut param_taint, changed: false, reporter: None };
+            a.function(func);
+            changed |= a.changed;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for func in &program.functions {
+        let Some(sm) = program.source_maps.get(&func.file) else { continue };
+        let mut a = Analyzer {
+            user_funcs: &user_funcs,
+            param_taint: &mut param_taint,
+            changed: false,
+            reporter: Some(Reporter { sm, file: &func.file, base, seen: &mut seen, out: &mut out }),
+        };
+        a.function(func);
+    }
+    out
+}
+
+fn merge(into: &mut Env, other: Env) {
+    for (k, v) in other {
+        into.entry(k).or_insert(v);
+    }
+}
+
+/// Variables validated by a condition: `matches(v, re)` or `v == "literal"`,
+/// possibly joined with `&&`.
+fn sanitized_by(cond: &ast::Expr, out: &mut Vec<String>) {
+    match &cond.node {
+        ExprKind::And(l, r) => {
+            sanitized_by(l, out);
+            sanitized_by(r, out);
+        }
+        ExprKind::Call { name, args, .. } if name == "matches" => {
+            if let Some(ExprKind::Var(v)) = args.first().map(|e| &e.node) {
+                out.push(v.clone());
+            }
+        }
+        ExprKind::Compare { left, op: ast::CompareOp::Eq, right } => match (&left.node, &right.node) {
+            (ExprKind::Var(v), ExprKind::Literal(_)) | (ExprKind::Literal(_), ExprKind::Var(v)) => out.push(v.clone()),
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+impl Analyzer<'_> {
+    fn function(&mut self, func: &ast::Function) {
+        let mut env = Env::new();
+        if let Some(taints) = self.param_taint.get(&func.name) {
+            for (p, t) in func.params.iter().zip(taints) {
+                if let Some(origin) = t {
+                    env.insert(p.clone(), *origin);
+                }
+            }
+        }
+        self.block(&func.body, &mut env);
+    }
+
+    fn report(&mut self, span: Span, msg: String) {
+        let Some(r) = self.reporter.as_mut() else { return };
+        if r.seen.insert((r.file.to_string(), span.start)) {
+            r.out.push(r.sm.format_coded_diagnostic(r.file, r.base, Some("W0001"), &msg, span));
+        }
+    }
+
+    fn record_call(&mut self, name: &str, args: &[ast::Expr], env: &Env) {
+        let taints: Vec<Option<Origin>> = args.iter().map(|a| self.taint_of(a, env)).collect();
+        if let Some(slots) = self.param_taint.get_mut(name) {
+            for (slot, t) in slots.iter_mut().zip(taints) {
+                if slot.is_none() && t.is_some() {
+                    *slot = t;
+                    self.changed = true;
+                }
+            }
+        }
+    }
+
+    /// Origin of the taint carried by `expr`, if any.
+    fn taint_of(&self, expr: &ast::Expr, env: &Env) -> Option<Origin> {
+        match &expr.node {
+            ExprKind::Input(_) => Some("input()"),
+            ExprKind::Env(_) | ExprKind::EnvDot(_) => Some("env()"),
+            ExprKind::Arg(_) => Some("arg()"),
+            ExprKind::Args => Some("args()"),
+            ExprKind::Var(name) => env.get(name).copied(),
+            ExprKind::Concat(l, r) | ExprKind::Coalesce(l, r) | ExprKind::Join { list: l, sep: r } => {
+                self.taint_of(l, env).or_else(|| self.taint_of(r, env))
+            }
+            ExprKind::Index { list, .. } | ExprKind::Field { base: list, .. } | ExprKind::Spread(list) => self.taint_of(list, env),
+            ExprKind::IfElse { then_expr, else_expr, .. } => {
+                self.taint_of(then_expr, env).or_else(|| self.taint_of(else_expr, env))
+            }
+            ExprKind::List(items) => items.iter().find_map(|e| self.taint_of(e, env)),
+            ExprKind::MapLiteral(entries) => entries.iter().find_map(|(_, e)| self.taint_of(e, env)),
+            ExprKind::Call { name, args, .. } => match name.as_str() {
+                "argv" => Some("argv()"),
+                "parse_args" => Some("parse_args()"),
+                "input_list" => Some("input_list()"),
+                n if CLEAN_CALLS.contains(&n) || self.user_funcs.contains(n) => None,
+                _ => args.iter().find_map(|e| self.taint_of(e, env)),
+            },
+            _ => None,
+        }
+    }
+
+    /// Visits every sub-expression looking for sinks and user calls.
+    fn scan(&mut self, expr: &ast::Expr, env: &Env) {
+        match &expr.node {
+            ExprKind::Sh { cmd, options } => {
+                if let Some(origin) = self.taint_of(cmd, env) {
+                    self.report(cmd.span, format!(
+                        "tainted value from {} reaches the sh() command string and is parsed as shell code; validate it with matches() or pass it via args=",
+                        origin
+                    ));
+                }
+                for o in options {
+                    if o.name == "shell"
+                        && let Some(origin) = self.taint_of(&o.value, env)
+                    {
+                        self.report(o.value.span, format!("tainted value from {} selects the shell for sh()", origin));
+                    }
+                }
+                self.scan(cmd, env);
+                self.scan_options(options, env);
+            }
+            ExprKind::Call { name, args, options } => {
+                if self.user_funcs.contains(name) {
+                    self.record_call(name, args, env);
+                }
+                self.scan_all(args, env);
+                self.scan_options(options, env);
+            }
+            ExprKind::QualifiedCall { args, resolved_mangled, .. } => {
+                if let Some(name) = resolved_mangled {
+                    self.record_call(name, args, env);
+                }
+                self.scan_all(args, env);
+            }
+            ExprKind::Command(args) | ExprKind::List(args) => self.scan_all(args, env),
+            ExprKind::Sudo { args, options } => {
+                self.scan_all(args, env);
+                self.scan_options(options, env);
+            }
+            ExprKind::CommandPipe(pipeline) => {
+                for e in pipeline.iter().flatten() {
+                    self.scan(e, env);
+                }
+            }
+            ExprKind::Pipeline(segments) => {
+                let mut inner = env.clone();
+                self.pipe_segments(segments, &mut inner);
+            }
+            ExprKind::Run(call) => self.run_call(call, env),
+            ExprKind::Concat(l, r)
+            | ExprKind::Coalesce(l, r)
+            | ExprKind::And(l, r)
+            | ExprKind::Or(l, r)
+            | ExprKind::Join { list: l, sep: r }
+            | ExprKind::Index { list: l, index: r, .. }
+            | ExprKind::Arith { left: l, right: r, .. }
+            | ExprKind::Compare { left: l, right: r, .. } => {
+                self.scan(l, env);
+                self.scan(r, env);
+            }
+            ExprKind::IfElse { cond, then_expr, else_expr } => {
+                self.scan(cond, env);
+                self.scan(then_expr, env);
+                self.scan(else_expr, env);
+            }
+            ExprKind::Not(e)
+            | ExprKind::Spread(e)
+            | ExprKind::Exists(e)
+            | ExprKind::IsDir(e)
+            | ExprKind::IsFile(e)
+            | ExprKind::IsSymlink(e)
+            | ExprKind::IsExec(e)
+            | ExprKind::IsReadable(e)
+            | ExprKind::IsWritable(e)
+            | ExprKind::IsNonEmpty(e)
+            | ExprKind::BoolStr(e)
+            | ExprKind::Len(e)
+            | ExprKind::Count(e)
+            | ExprKind::Arg(e)
+            | ExprKind::Env(e)
+            | ExprKind::Input(e)
+            | ExprKind::Field { base: e, .. } => self.scan(e, env),
+            ExprKind::MapLiteral(entries) => {
+                for (_, v) in entries {
+                    self.scan(v, env);
+                }
+            }
+            ExprKind::Capture { expr: inner, options } => {
+                self.scan(inner, env);
+                self.scan_options(options, env);
+            }
+            ExprKind::Confirm { prompt, default } => {
+                self.scan(prompt, env);
+                if let Some(d) = default {
+                    self.scan(d, env);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn scan_all(&mut self, exprs: &[ast::Expr], env: &Env) {
+        for e in exprs {
+            self.scan(e, env);
+        }
+    }
+
+    fn scan_options(&mut self, options: &[ast::CallOption], env: &Env) {
+        for o in options {
+            self.scan(&o.value, env);
+        }
+    }
+
+    fn run_call(&mut self, call: &ast::RunCall, env: &Env) {
+        self.scan_all(&call.args, env);
+        self.scan_options(&call.options, env);
+        if let Some(ast::RedirectOutputTarget::File { path, .. }) = call.stderr.as_deref().map(|t| &t.node) {
+            self.scan(path, env);
+        }
+    }
+
+    fn pipe_segments(&mut self, segments: &[ast::Spanned<ast::PipeSegment>], env: &mut Env) {
+        for seg in segments {
+            match &seg.node {
+                ast::PipeSegment::Run(call) | ast::PipeSegment::Sudo(call) => self.run_call(call, env),
+                ast::PipeSegment::Block(body) | ast::PipeSegment::EachLine(_, _, body) => {
+                    let mut inner = env.clone();
+                    self.block(body, &mut inner);
+                    merge(env, inner);
+                }
+            }
+        }
+    }
+
+    fn exec(&mut self, args: &[ast::Expr], env: &Env) {
+        let Some(prog) = args.first() else { return };
+        if let Some(origin) = self.taint_of(prog, env) {
+            self.report(prog.span, format!("tainted value from {} names the program run by exec()", origin));
+        } else if matches!(&prog.node, ExprKind::Literal(p) if SHELLS.contains(&p.rsplit('/').next().unwrap_or(p))) {
+            for a in &args[1..] {
+                if let Some(origin) = self.taint_of(a, env) {
+                    self.report(a.span, format!(
+                        "tainted value from {} is passed to a shell by exec() and may be parsed as shell code",
+                        origin
+                    ));
+                }
+            }
+        }
+        self.scan_all(args, env);
+    }
+
+    fn block(&mut self, stmts: &[ast::Stmt], env: &mut Env) {
+        for s in stmts {
+            self.stmt(s, env);
+        }
+    }
+
+    /// Runs a loop body twice so taint assigned late in the body reaches
+    /// uses earlier in the next iteration.
+    fn loop_body(&mut self, body: &[ast::Stmt], env: &mut Env) {
+        for _ in 0..2 {
+            let mut inner = env.clone();
+            self.block(body, &mut inner);
+            merge(env, inner);
+        }
+    }
+
+    fn branch(&mut self, body: &[ast::Stmt], env: &Env, sanitized: &[String]) -> Env {
+        let mut inner = env.clone();
+        for v in sanitized {
+            inner.remove(v);
+        }
+        self.block(body, &mut inner);
+        inner
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt, env: &mut Env) {
+        match &stmt.node {
+            StmtKind::Let { name, value } => {
+                self.scan(value, env);
+                match self.taint_of(value, env) {
+                    Some(origin) => env.insert(name.node.clone(), origin),
+                    None => env.remove(&name.node),
+                };
+            }
+            StmtKind::LetValues { names, value, .. } => {
+                self.scan(value, env);
+                let origin = self.taint_of(value, env);
+                for name in names {
+                    match &origin {
+                        Some(origin) => env.insert(name.node.clone(), *origin),
+                        None => env.remove(&name.node),
+                    };
+                }
+            }
+            StmtKind::ReturnValues(values) => values.iter().for_each(|v| self.scan(v, env)),
+            StmtKind::Set { target, value } => {
+                self.scan(value, env);
+                if let LValue::Var(name) = target {
+                    match self.taint_of(value, env) {
+                        Some(origin) => env.insert(name.node.clone(), origin),
+                        None => env.remove(&name.node),
+                    };
+                }
+            }
+            StmtKind::Run(call) => self.run_call(call, env),
+            StmtKind::Print(e)
+            | StmtKind::PrintErr(e)
+            | StmtKind::Exit(Some(e))
+            | StmtKind::Return(Some(e))
+            | StmtKind::Wait(Some(e))
+            | StmtKind::Sh(e)
+            | StmtKind::Cd { path: e }
+            | StmtKind::Export { value: Some(e), .. }
+            | StmtKind::Source { path: e } => self.scan(e, env),
+            StmtKind::Exec(args) => self.exec(args, env),
+            StmtKind::Call { name, args, options } => {
+                if self.user_funcs.contains(name) {
+                    self.record_call(name, args, env);
+                }
+                self.scan_all(args, env);
+                self.scan_options(options, env);
+            }
+            StmtKind::QualifiedCall { args, resolved_mangled, .. } => {
+                if let Some(name) = resolved_mangled {
+                    self.record_call(name, args, env);
+                }
+                self.scan_all(args, env);
+            }
+            StmtKind::If { cond, then_body, elifs, else_body } => {
+                self.scan(cond, env);
+                let mut sanitized = Vec::new();
+                sanitized_by(cond, &mut sanitized);
+                let mut result = self.branch(then_body, env, &sanitized);
+                for elif in elifs {
+                    self.scan(&elif.cond, env);
+                    let mut sanitized = Vec::new();
+                    sanitized_by(&elif.cond, &mut sanitized);
+                    let out = self.branch(&elif.body, env, &sanitized);
+                    merge(&mut result, out);
+                }
+                match else_body {
+                    Some(body) => {
+                        let out = self.branch(body, env, &[]);
+                        merge(&mut result, out);
+                    }
+                    None => merge(&mut result, env.clone()),
+                }
+                *env = result;
+            }
+            StmtKind::While { cond, body } => {
+                self.scan(cond, env);
+                self.loop_body(body, env);
+            }
+            StmtKind::For { var, iterable, body } => {
+                let origin = match iterable {
+                    ast::ForIterable::List(items) => {
+                        self.scan_all(items, env);
+                        items.iter().find_map(|e| self.taint_of(e, env))
+                    }
+                    ast::ForIterable::Range(start, end) => {
+                        self.scan(start, env);
+                        self.scan(end, env);
+                        None
+                    }
+                    ast::ForIterable::StdinLines => Some("stdin"),
+                    ast::ForIterable::Find0(spec) => {
+                        for e in [&spec.dir, &spec.name, &spec.type_filter, &spec.maxdepth].into_iter().flatten() {
+                            self.scan(e, env);
+                        }
+                        None
+                    }
+                };
+                match origin {
+                    Some(o) => env.insert(var.node.clone(), o),
+                    None => env.remove(&var.node),
+                };
+                self.loop_body(body, env);
+            }
+            StmtKind::ForMap { key_var, val_var, map, body } => {
+                match env.get(map).copied() {
+                    Some(o) => {
+                        env.insert(key_var.node.clone(), o);
+                        env.insert(val_var.node.clone(), o);
+                    }
+                    None => {
+                        env.remove(&key_var.node);
+                        env.remove(&val_var.node);
+                    }
+                }
+                self.loop_body(body, env);
+            }
+            StmtKind::TryCatch { try_body, catch_body } => {
+                let mut result = self.branch(try_body, env, &[]);
+                merge(&mut result, self.branch(catch_body, env, &[]));
+                *env = result;
+            }
+            StmtKind::AndThen { left, right } | StmtKind::OrElse { left, right } => {
+                self.block(left, env);
+                let out = self.branch(right, env, &[]);
+                merge(env, out);
+            }
+            StmtKind::Subshell { body }
+            | StmtKind::Group { body }
+            | StmtKind::Unchecked { body }
+            | StmtKind::WithLimits { body, .. } => {
+                self.block(body, env)
+            }
+            StmtKind::Pipe(segments) => self.pipe_segments(segments, env),
+            StmtKind::WithEnv { bindings, body } => {
+                for (_, v) in bindings {
+                    self.scan(v, env);
+                }
+                self.block(body, env);
+            }
+            StmtKind::WithCwd { path, body }
+            | StmtKind::WithLog { path, body, .. }
+            | StmtKind::WithDeadline { duration: path, body } | StmtKind::WithUmask { mask: path, body } => {
+                self.scan(path, env);
+                self.block(body, env);
+            }
+            StmtKind::WithRedirect { stdout, stderr, stdin, body } => {
+                for t in stdout.iter().chain(stderr.iter()).flatten() {
+                    if let ast::RedirectOutputTarget::File { path, .. } = &t.node {
+                        self.scan(path, env);
+                    }
+                }
+                if let Some(ast::RedirectInputTarget::File { path } | ast::RedirectInputTarget::Text { value: path }) = stdin {
+                    self.scan(path, env);
+                }
+                self.block(body, env);
+            }
+            StmtKind::Spawn { stmt } => self.stmt(stmt, env),
+            StmtKind::Case { expr, arms } => {
+                self.scan(expr, env);
+                let mut result = env.clone();
+                for arm in arms {
+                    let out = self.branch(&arm.body, env, &[]);
+                    merge(&mut result, out);
+                }
+                *env = result;
+            }
+            StmtKind::Unset { name } => {
+                env.remove(name);
+            }
+            StmtKind::ShBlock { .. }
+            | StmtKind::Exit(None)
+            | StmtKind::Return(None)
+            | StmtKind::Wait(None)
+            | StmtKind::Export { value: None, .. }
+            | StmtKind::Break
+            | StmtKind::Continue => {}
+        }
+    }
+}
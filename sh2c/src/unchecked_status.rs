@@ -0,0 +1,97 @@
+//! Warnings for `try_run()` and `capture(..., allow_fail=true)` results whose
+//! status is never looked at.
+//!
+//! Both forms keep the script running when the command fails and record the
+//! exit code in the result's synthesized `__status` field (`r.status`). A
+//! result whose status is never read swallows the failure. `status()` read
+//! while the result is still the most recent one counts as a check too, since
+//! that is how `capture(..., allow_fail=true)` is documented.
+
+use crate::ast::{self, ExprKind, Program, StmtKind};
+use crate::span::Span;
+use crate::warn_walk::{self, Reporter, Visitor};
+use std::path::Path;
+
+struct Binding {
+    name: String,
+    span: Span,
+    what: &'static str,
+    checked: bool,
+}
+
+struct Checker<'a> {
+    r: Reporter<'a>,
+    /// Result bindings of the current function, in source order.
+    bindings: Vec<Binding>,
+}
+
+/// Returns one formatted warning per result binding whose status is never read.
+pub fn check_unchecked_status(program: &Program, base: Option<&Path>) -> Vec<String> {
+    warn_walk::check_functions(program, base, |func, r| {
+        let mut c = Checker { r, bindings: Vec::new() };
+        c.block(&func.body);
+        c.finish();
+    })
+}
+
+/// What kind of result record `value` produces when bound with `let`, if any.
+fn result_kind(value: &ast::Expr) -> Option<&'static str> {
+    match &value.node {
+        ExprKind::Call { name, .. } if name == "try_run" => Some("try_run()"),
+        ExprKind::Capture { options, .. }
+            if options.iter().any(|o| o.name == "allow_fail" && matches!(o.value.node, ExprKind::Bool(true))) =>
+        {
+            Some("capture(..., allow_fail=true)")
+        }
+        _ => None,
+    }
+}
+
+impl Checker<'_> {
+    fn finish(&mut self) {
+        for b in std::mem::take(&mut self.bindings) {
+            if b.checked {
+                continue;
+            }
+            let msg = format!(
+                "status of '{}' is never checked: {} keeps going when the command fails, so the failure is silently ignored; \
+                 read `{}.status` (or `status()` right after the call)",
+                b.name, b.what, b.name
+            );
+            self.r.warn("W0008", &msg, b.span);
+        }
+    }
+}
+
+impl Visitor for Checker<'_> {
+    fn stmt(&mut self, stmt: &ast::Stmt) {
+        warn_walk::walk_stmt(self, stmt);
+        if let StmtKind::Let { name, value } = &stmt.node
+            && let Some(what) = result_kind(value)
+        {
+            self.bindings.push(Binding { name: name.node.clone(), span: name.span, what, checked: false });
+        }
+    }
+
+    fn expr(&mut self, expr: &ast::Expr) {
+        match &expr.node {
+            // `r.status` checks the latest binding of `r`.
+            ExprKind::Field { base, name } => {
+                if let ExprKind::Var(var) = &base.node
+                    && name == "status"
+                    && let Some(b) = self.bindings.iter_mut().rev().find(|b| &b.name == var)
+                {
+                    b.checked = true;
+                }
+            }
+            // `status()` checks the most recent result.
+            ExprKind::Status => {
+                if let Some(b) = self.bindings.last_mut() {
+                    b.checked = true;
+                }
+            }
+            _ => {}
+        }
+        warn_walk::walk_expr(self, expr);
+    }
+}
@@ -0,0 +1,144 @@
+//! `sh2c verify`: compile one script for several targets, run each result under
+//! its shell with the same arguments and stdin, and report where they disagree.
+//!
+//! Only stdout and the exit status are compared. Diagnostics on stderr carry
+//! target-specific wording, so they are kept for the report but never count as
+//! a divergence.
+
+use crate::codegen::TargetShell;
+use crate::driver::{self, CompileOptions, DriverError};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// One compiled script run under one shell.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub target: TargetShell,
+    pub shell: &'static str,
+    /// `None` when the shell was killed by a signal.
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Run {
+    /// `bash (bash)`, `posix (sh)`.
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.target, self.shell)
+    }
+}
+
+/// The shell each target's output is meant for.
+pub fn shell_for(target: TargetShell) -> &'static str {
+    match target {
+        TargetShell::Bash => "bash",
+        TargetShell::Posix => "sh",
+    }
+}
+
+/// `name` as found on `PATH`, if it is there.
+pub fn find_shell(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|exe| exe.is_file())
+}
+
+/// Compiles `script` for `target` and runs it with `args`, feeding `stdin` (or
+/// nothing) to it. Returns `Ok(None)` when the target's shell is not installed.
+pub fn run_target(script: &Path, target: TargetShell, args: &[String], stdin: Option<&Path>) -> Result<Option<Run>, DriverError> {
+    let shell = shell_for(target);
+    let Some(shell_path) = find_shell(shell) else {
+        return Ok(None);
+    };
+    let options = CompileOptions { target, include_diagnostics: true, ..Default::default() };
+    let code = driver::compile_file(script, options)?;
+
+    let io_err = |msg: String| DriverError { code: 1, msg };
+    let tmp = std::env::temp_dir().join(format!("sh2c-verify-{}-{}.sh", std::process::id(), target));
+    fs::write(&tmp, code).map_err(|e| io_err(format!("Failed to write {}: {}", tmp.display(), e)))?;
+
+    let input = match stdin {
+        Some(path) => Stdio::from(
+            fs::File::open(path).map_err(|e| io_err(format!("Failed to read {}: {}", path.display(), e)))?,
+        ),
+        None => Stdio::null(),
+    };
+    let output = Command::new(shell_path).arg(&tmp).args(args).stdin(input).output();
+    let _ = fs::remove_file(&tmp);
+    let output = output.map_err(|e| io_err(format!("Failed to run {}: {}", shell, e)))?;
+
+    Ok(Some(Run {
+        target,
+        shell,
+        status: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }))
+}
+
+/// How each run differs from the first one, one line per difference.
+pub fn divergences(runs: &[Run]) -> Vec<String> {
+    let Some((base, rest)) = runs.split_first() else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for run in rest {
+        if run.status != base.status {
+            out.push(format!(
+                "{}: exit status {}, but {} exited {}",
+                run.label(),
+                describe_status(run.status),
+                base.label(),
+                describe_status(base.status)
+            ));
+        }
+        if run.stdout != base.stdout {
+            out.push(format!("{}: stdout differs from {}: {}", run.label(), base.label(), first_difference(&base.stdout, &run.stdout)));
+        }
+    }
+    out
+}
+
+pub fn describe_status(status: Option<i32>) -> String {
+    status.map_or_else(|| "by signal".to_string(), |code| code.to_string())
+}
+
+/// `line 2: "a" vs "b"`, or which side ran out of lines first.
+fn first_difference(base: &str, other: &str) -> String {
+    let (mut a, mut b) = (base.lines(), other.lines());
+    for line in 1.. {
+        match (a.next(), b.next()) {
+            (Some(x), Some(y)) if x == y => continue,
+            (Some(x), Some(y)) => return format!("line {}: {:?} vs {:?}", line, x, y),
+            (Some(x), None) => return format!("line {}: {:?} is missing", line, x),
+            (None, Some(y)) => return format!("line {}: extra {:?}", line, y),
+            (None, None) => break,
+        }
+    }
+    // Same lines; only a trailing newline differs.
+    "trailing newline".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(target: TargetShell, status: i32, stdout: &str) -> Run {
+        Run { target, shell: shell_for(target), status: Some(status), stdout: stdout.to_string(), stderr: String::new() }
+    }
+
+    #[test]
+    fn reports_status_and_first_differing_line() {
+        let runs = [run(TargetShell::Bash, 0, "a\nb\n"), run(TargetShell::Posix, 1, "a\nc\n")];
+        assert_eq!(
+            divergences(&runs),
+            [
+                "posix (sh): exit status 1, but bash (bash) exited 0",
+                r#"posix (sh): stdout differs from bash (bash): line 2: "b" vs "c""#,
+            ]
+        );
+        assert!(divergences(&[runs[0].clone(), runs[0].clone()]).is_empty());
+    }
+}
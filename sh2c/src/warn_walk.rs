@@ -0,0 +1,238 @@
+//! The function-body walk and per-location reporting shared by the warning
+//! passes (`deprecation`, `stdin_guard`, `unchecked_status`).
+//!
+//! A pass implements [`Visitor`], overriding only the nodes it looks at and
+//! calling the matching `walk_*` function to keep descending, and reports
+//! through the [`Reporter`] that [`check_functions`] hands it.
+
+use crate::ast::{self, ExprKind, Program, StmtKind};
+use crate::span::{SourceMap, Span};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Where a pass's warnings for one function body go.
+pub(crate) struct Reporter<'a> {
+    sm: &'a SourceMap,
+    file: &'a str,
+    base: Option<&'a Path>,
+    seen: &'a mut HashSet<(String, usize)>,
+    out: &'a mut Vec<String>,
+}
+
+impl Reporter<'_> {
+    /// Records a `code` warning at `span` unless that location was already reported.
+    pub(crate) fn warn(&mut self, code: &str, msg: &str, span: Span) {
+        if self.seen.insert((self.file.to_string(), span.start)) {
+            self.out.push(self.sm.format_coded_diagnostic(self.file, self.base, Some(code), msg, span));
+        }
+    }
+}
+
+/// Calls `check` on every function that has a source map, with a reporter
+/// for its file, and returns the warnings in the order they were reported.
+pub(crate) fn check_functions<'p>(
+    program: &'p Program,
+    base: Option<&Path>,
+    mut check: impl FnMut(&'p ast::Function, Reporter<'_>),
+) -> Vec<String> {
+    let mut out = Vec::new();
+    // Imported functions appear once per alias; report each source location once.
+    let mut seen = HashSet::new();
+    for func in &program.functions {
+        let Some(sm) = program.source_maps.get(&func.file) else { continue };
+        check(func, Reporter { sm, file: &func.file, base, seen: &mut seen, out: &mut out });
+    }
+    out
+}
+
+/// Visits every statement and expression in a body. The default methods
+/// descend into all children; an override calls the `walk_*` function to
+/// keep going.
+pub(crate) trait Visitor {
+    fn block(&mut self, stmts: &[ast::Stmt]) {
+        for s in stmts {
+            self.stmt(s);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn pipe_segments(&mut self, segments: &[ast::Spanned<ast::PipeSegment>]) {
+        for seg in segments {
+            walk_pipe_segment(self, seg);
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[ast::Expr]) {
+        for e in exprs {
+            self.expr(e);
+        }
+    }
+
+    fn expr(&mut self, expr: &ast::Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+fn options<V: Visitor + ?Sized>(v: &mut V, options: &[ast::CallOption]) {
+    for o in options {
+        v.expr(&o.value);
+    }
+}
+
+// SYNC WITH: fixes::Collector::stmt (traversal shape).
+pub(crate) fn walk_stmt<V: Visitor + ?Sized>(v: &mut V, stmt: &ast::Stmt) {
+    match &stmt.node {
+        StmtKind::Let { value, .. } | StmtKind::Set { value, .. } | StmtKind::LetValues { value, .. } => v.expr(value),
+        StmtKind::ReturnValues(values) => v.exprs(values),
+        StmtKind::Run(call) => v.exprs(&call.args),
+        StmtKind::Print(e)
+        | StmtKind::PrintErr(e)
+        | StmtKind::Exit(Some(e))
+        | StmtKind::Return(Some(e))
+        | StmtKind::Wait(Some(e))
+        | StmtKind::Sh(e)
+        | StmtKind::Cd { path: e }
+        | StmtKind::Source { path: e }
+        | StmtKind::Export { value: Some(e), .. } => v.expr(e),
+        StmtKind::Exec(args) | StmtKind::QualifiedCall { args, .. } => v.exprs(args),
+        StmtKind::Call { args, options: opts, .. } => {
+            v.exprs(args);
+            options(v, opts);
+        }
+        StmtKind::If { cond, then_body, elifs, else_body } => {
+            v.expr(cond);
+            v.block(then_body);
+            for e in elifs {
+                v.expr(&e.cond);
+                v.block(&e.body);
+            }
+            if let Some(body) = else_body {
+                v.block(body);
+            }
+        }
+        StmtKind::While { cond, body } => {
+            v.expr(cond);
+            v.block(body);
+        }
+        StmtKind::For { iterable, body, .. } => {
+            match iterable {
+                ast::ForIterable::List(items) => v.exprs(items),
+                ast::ForIterable::Range(start, end) => {
+                    v.expr(start);
+                    v.expr(end);
+                }
+                ast::ForIterable::Find0(_) | ast::ForIterable::StdinLines => {}
+            }
+            v.block(body);
+        }
+        StmtKind::ForMap { body, .. }
+        | StmtKind::Subshell { body }
+        | StmtKind::Group { body }
+        | StmtKind::Unchecked { body }
+        | StmtKind::WithLimits { body, .. }
+        | StmtKind::WithEnv { body, .. }
+        | StmtKind::WithRedirect { body, .. } => v.block(body),
+        StmtKind::WithCwd { path, body }
+        | StmtKind::WithLog { path, body, .. }
+        | StmtKind::WithDeadline { duration: path, body }
+        | StmtKind::WithUmask { mask: path, body } => {
+            v.expr(path);
+            v.block(body);
+        }
+        StmtKind::TryCatch { try_body, catch_body } => {
+            v.block(try_body);
+            v.block(catch_body);
+        }
+        StmtKind::AndThen { left, right } | StmtKind::OrElse { left, right } => {
+            v.block(left);
+            v.block(right);
+        }
+        StmtKind::Pipe(segments) => v.pipe_segments(segments),
+        StmtKind::Spawn { stmt } => v.stmt(stmt),
+        StmtKind::Case { expr, arms } => {
+            v.expr(expr);
+            for arm in arms {
+                v.block(&arm.body);
+            }
+        }
+        StmtKind::ShBlock { .. }
+        | StmtKind::Exit(None)
+        | StmtKind::Return(None)
+        | StmtKind::Wait(None)
+        | StmtKind::Export { value: None, .. }
+        | StmtKind::Unset { .. }
+        | StmtKind::Break
+        | StmtKind::Continue => {}
+    }
+}
+
+pub(crate) fn walk_pipe_segment<V: Visitor + ?Sized>(v: &mut V, seg: &ast::Spanned<ast::PipeSegment>) {
+    match &seg.node {
+        ast::PipeSegment::Run(call) | ast::PipeSegment::Sudo(call) => v.exprs(&call.args),
+        ast::PipeSegment::Block(body) | ast::PipeSegment::EachLine(_, _, body) => v.block(body),
+    }
+}
+
+pub(crate) fn walk_expr<V: Visitor + ?Sized>(v: &mut V, expr: &ast::Expr) {
+    match &expr.node {
+        ExprKind::Call { args, options: opts, .. } | ExprKind::Sudo { args, options: opts } => {
+            v.exprs(args);
+            options(v, opts);
+        }
+        ExprKind::Run(call) => v.exprs(&call.args),
+        ExprKind::Pipeline(segments) => v.pipe_segments(segments),
+        ExprKind::QualifiedCall { args, .. } | ExprKind::Command(args) | ExprKind::List(args) => v.exprs(args),
+        ExprKind::CommandPipe(segments) => {
+            for args in segments {
+                v.exprs(args);
+            }
+        }
+        ExprKind::Concat(l, r)
+        | ExprKind::Coalesce(l, r)
+        | ExprKind::And(l, r)
+        | ExprKind::Or(l, r)
+        | ExprKind::Join { list: l, sep: r }
+        | ExprKind::Index { list: l, index: r, .. }
+        | ExprKind::Arith { left: l, right: r, .. }
+        | ExprKind::Compare { left: l, right: r, .. } => {
+            v.expr(l);
+            v.expr(r);
+        }
+        ExprKind::IfElse { cond, then_expr, else_expr } => {
+            v.expr(cond);
+            v.expr(then_expr);
+            v.expr(else_expr);
+        }
+        ExprKind::Not(e)
+        | ExprKind::Spread(e)
+        | ExprKind::Exists(e)
+        | ExprKind::IsDir(e)
+        | ExprKind::IsFile(e)
+        | ExprKind::IsSymlink(e)
+        | ExprKind::IsExec(e)
+        | ExprKind::IsReadable(e)
+        | ExprKind::IsWritable(e)
+        | ExprKind::IsNonEmpty(e)
+        | ExprKind::BoolStr(e)
+        | ExprKind::Len(e)
+        | ExprKind::Count(e)
+        | ExprKind::Arg(e)
+        | ExprKind::Env(e)
+        | ExprKind::Input(e)
+        | ExprKind::Field { base: e, .. }
+        | ExprKind::Confirm { prompt: e, .. } => v.expr(e),
+        ExprKind::Sh { cmd, options: opts } | ExprKind::Capture { expr: cmd, options: opts } => {
+            v.expr(cmd);
+            options(v, opts);
+        }
+        ExprKind::MapLiteral(entries) => {
+            for (_, value) in entries {
+                v.expr(value);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,70 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+#[test]
+fn audit_quoting_reports_sites_with_spans() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_sh2c"));
+    let output = cmd
+        .arg("--audit-quoting")
+        .arg("tests/fixtures/cli_audit_quoting.sh2")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    for expected in [
+        "cli_audit_quoting.sh2:3:5: sh: sh() parses a literal command string as shell code",
+        "cli_audit_quoting.sh2:4:5: sh: sh() command string is built from runtime values",
+        "cli_audit_quoting.sh2:5:15: raw_arg: raw_arg() expands unquoted",
+        "cli_audit_quoting.sh2:6:14: glob: glob() pattern is computed at runtime",
+        "cli_audit_quoting.sh2:9:5: sh-block: sh { ... } pastes 1 line(s) into the script verbatim",
+        "eval: generated function __sh2_split() uses eval",
+        "6 quoting-sensitive sites found",
+    ] {
+        assert!(stdout.contains(expected), "missing {:?} in:\n{}", expected, stdout);
+    }
+    // Compiled output is not printed in audit mode.
+    assert!(!stdout.contains("#!/"), "unexpected script in:\n{}", stdout);
+}
+
+#[test]
+fn audit_quoting_clean_script() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_sh2c"));
+    cmd.arg("--audit-quoting")
+        .arg("tests/fixtures/cli_audit_quoting_clean.sh2")
+        .assert()
+        .success()
+        .stdout(predicate::eq("no quoting-sensitive sites found\n"));
+}
+
+#[test]
+fn audit_quoting_posix_arg_by_index_is_eval_free() {
+    let src = "tests/fixtures/cli_audit_quoting_posix.sh2";
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_sh2c"));
+    cmd.arg("--target=posix")
+        .arg("--audit-quoting")
+        .arg(src)
+        .assert()
+        .success()
+        .stdout(predicate::eq("no quoting-sensitive sites found\n"));
+}
+
+#[test]
+fn audit_quoting_rejects_out_and_other_actions() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_sh2c"));
+    cmd.arg("--audit-quoting")
+        .arg("--out")
+        .arg("x.sh")
+        .arg("tests/fixtures/cli_audit_quoting_clean.sh2")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("error: --audit-quoting cannot be used with --out"));
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_sh2c"));
+    cmd.arg("--audit-quoting")
+        .arg("--check")
+        .arg("tests/fixtures/cli_audit_quoting_clean.sh2")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("multiple action flags specified"));
+}
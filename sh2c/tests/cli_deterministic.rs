@@ -0,0 +1,94 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+
+const MAIN: &str = r#"import "lib/util.sh2"
+
+func main() {
+    let name = "world"
+    greet(name)
+    run("false", allow_fail=true)
+    print(status())
+}
+"#;
+
+const LIB: &str = r#"func greet(who) {
+    run("printf", "hello %s\n", who)
+}
+"#;
+
+fn write_project(root: &Path) {
+    fs::create_dir_all(root.join("lib")).unwrap();
+    fs::write(root.join("main.sh2"), MAIN).unwrap();
+    fs::write(root.join("lib/util.sh2"), LIB).unwrap();
+}
+
+fn compile(cwd: &Path, script: &str, target: &str, extra: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(cwd)
+        .arg("--target")
+        .arg(target)
+        .args(extra)
+        .arg(script)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn output_is_identical_across_directories_and_path_spellings() {
+    let a = tempfile::tempdir().unwrap();
+    let b = tempfile::tempdir().unwrap();
+    write_project(a.path());
+    write_project(&b.path().join("nested/deeper"));
+
+    for target in ["bash", "posix"] {
+        let reference = compile(a.path(), "main.sh2", target, &[]);
+        assert!(reference.contains("__sh2_loc=\"main.sh2:5:5\""), "{}", reference);
+        assert!(reference.contains("lib/util.sh2:2:5"), "{}", reference);
+
+        let abs = a.path().join("main.sh2");
+        let variants = [
+            compile(a.path(), "./main.sh2", target, &[]),
+            compile(a.path(), abs.to_str().unwrap(), target, &[]),
+            compile(b.path(), "nested/deeper/main.sh2", target, &[]),
+            compile(&b.path().join("nested/deeper"), "main.sh2", target, &[]),
+        ];
+        for v in &variants {
+            assert_eq!(v, &reference);
+        }
+        for dir in [a.path(), b.path()] {
+            let dir = fs::canonicalize(dir).unwrap();
+            assert!(!reference.contains(dir.to_str().unwrap()));
+        }
+    }
+}
+
+#[test]
+fn deterministic_flag_verifies_output() {
+    let dir = tempfile::tempdir().unwrap();
+    write_project(dir.path());
+
+    for target in ["bash", "posix"] {
+        let plain = compile(dir.path(), "main.sh2", target, &[]);
+        let checked = compile(dir.path(), "main.sh2", target, &["--deterministic"]);
+        assert_eq!(plain, checked);
+    }
+
+    let out = dir.path().join("main.sh");
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir.path())
+        .args(["--deterministic", "-o", "main.sh", "main.sh2"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(out).unwrap(), compile(dir.path(), "main.sh2", "bash", &[]));
+
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir.path())
+        .args(["--deterministic", "--check", "main.sh2"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("OK\n"));
+}
@@ -0,0 +1,155 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+const DEPLOY: &str = r#"import "lib.sh2"
+
+# push-image - push a container image to a registry
+#
+# Tags the local image and pushes it.
+# Safe to run from CI.
+#
+# Existing tags are never overwritten.
+#
+# --registry URL  Registry to push to
+# --dry-run       Print the commands instead of running them
+func main(image) {
+    let opts = parse_args()
+    print(opts.flags["--registry"])
+    print(opts.flags["--tag"])
+    print(opts.flags["--dry-run"])
+}
+"#;
+
+#[test]
+fn man_page_from_doc_comment_and_flags() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("deploy.sh2"), DEPLOY).unwrap();
+    fs::write(dir.path().join("lib.sh2"), "func helper() {\n    print(\"x\")\n}\n").unwrap();
+
+    let out = sh2c().current_dir(dir.path()).args(["doc", "--man", "deploy.sh2"]).output().unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(
+        String::from_utf8(out.stdout).unwrap(),
+        r#".TH PUSH\-IMAGE 1
+.SH NAME
+push\-image \- push a container image to a registry
+.SH SYNOPSIS
+.B push\-image
+[\fIOPTIONS\fR]
+\fIimage\fR
+.SH DESCRIPTION
+Tags the local image and pushes it. Safe to run from CI.
+.PP
+Existing tags are never overwritten.
+.SH OPTIONS
+.TP
+\fB\-\-registry\fR \fIURL\fR
+Registry to push to
+.TP
+\fB\-\-dry\-run\fR
+Print the commands instead of running them
+.TP
+\fB\-\-tag\fR
+"#
+    );
+}
+
+#[test]
+fn header_comment_and_file_name_are_fallbacks() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("backup.sh2"), "# Copy home to the NAS.\n\nfunc main() {\n    print(\"ok\")\n}\n").unwrap();
+    sh2c()
+        .current_dir(dir.path())
+        .args(["doc", "--man", "-o", "backup.1", "backup.sh2"])
+        .assert()
+        .success()
+        .stdout("");
+    let page = fs::read_to_string(dir.path().join("backup.1")).unwrap();
+    assert_eq!(page, ".TH BACKUP 1\n.SH NAME\nbackup \\- Copy home to the NAS.\n.SH SYNOPSIS\n.B backup\n");
+}
+
+#[test]
+fn doc_rejects_bad_arguments() {
+    for (args, msg) in [
+        (&["doc", "main.sh2"][..], "only --man is supported"),
+        (&["doc", "--man"][..], "doc requires a script"),
+        (&["doc", "--man", "--html", "main.sh2"][..], "unexpected argument for doc: --html"),
+    ] {
+        sh2c().args(args).assert().code(1).stderr(predicates::str::contains(msg));
+    }
+    sh2c().args(["doc", "--man", "missing.sh2"]).assert().code(1).stderr(predicates::str::contains("File not found"));
+}
+
+#[test]
+fn triple_slash_doc_comment_wins_over_hash_comment() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = "# old header\n/// greet - say hello\n///\n/// Prints a greeting.\nfunc main(name) {\n    print(name)\n}\n";
+    fs::write(dir.path().join("greet.sh2"), src).unwrap();
+
+    let out = sh2c().current_dir(dir.path()).args(["doc", "--man", "greet.sh2"]).output().unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let page = String::from_utf8(out.stdout).unwrap();
+    assert!(page.contains(".SH NAME\ngreet \\- say hello\n"), "{}", page);
+    assert!(page.contains(".SH DESCRIPTION\nPrints a greeting.\n"), "{}", page);
+    assert!(!page.contains("old header"), "{}", page);
+}
+
+#[test]
+fn documented_main_answers_help() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = r#"/// push-image - push a container image to a registry
+///
+/// Tags the local image and pushes it.
+/// Safe to run from CI.
+///
+/// --registry URL  Registry to push to
+/// --dry-run       Print the commands instead of running them
+func main(image) {
+    let opts = parse_args()
+    print(image & " " & opts.flags["--tag"])
+}
+"#;
+    fs::write(dir.path().join("deploy.sh2"), src).unwrap();
+    for target in ["bash", "posix"] {
+        sh2c()
+            .current_dir(dir.path())
+            .args(["--target", target, "-o", "deploy.sh", "deploy.sh2"])
+            .assert()
+            .success();
+        let out = std::process::Command::new(dir.path().join("deploy.sh")).arg("--help").output().unwrap();
+        assert!(out.status.success(), "{}: {}", target, String::from_utf8_lossy(&out.stderr));
+        assert_eq!(
+            String::from_utf8(out.stdout).unwrap(),
+            "Usage: push-image [OPTIONS] <image>\n\n\
+             push a container image to a registry\n\n\
+             Tags the local image and pushes it. Safe to run from CI.\n\n\
+             Options:\n  \
+             --registry URL  Registry to push to\n  \
+             --dry-run       Print the commands instead of running them\n  \
+             --tag\n",
+            "{}",
+            target
+        );
+        let out = std::process::Command::new(dir.path().join("deploy.sh")).args(["img", "--tag", "v1"]).output().unwrap();
+        assert_eq!(String::from_utf8(out.stdout).unwrap(), "img v1\n", "{}", target);
+    }
+}
+
+#[test]
+fn help_is_left_to_undocumented_or_help_reading_scripts() {
+    let dir = tempfile::tempdir().unwrap();
+    for src in [
+        "# A hash comment is not enough.\nfunc main() {\n    print(\"ran \" & arg(1))\n}\n",
+        "/// tool - does things\nfunc main() {\n    let opts = parse_args()\n    if opts.flags[\"--help\"] != \"\" {\n        print(\"own help\")\n    }\n    print(\"ran \" & arg(1))\n}\n",
+    ] {
+        fs::write(dir.path().join("tool.sh2"), src).unwrap();
+        sh2c().current_dir(dir.path()).args(["-o", "tool.sh", "tool.sh2"]).assert().success();
+        let out = std::process::Command::new(dir.path().join("tool.sh")).arg("--help").output().unwrap();
+        let stdout = String::from_utf8(out.stdout).unwrap();
+        assert!(stdout.ends_with("ran --help\n") && !stdout.contains("Usage:"), "{}", stdout);
+    }
+}
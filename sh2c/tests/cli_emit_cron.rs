@@ -0,0 +1,120 @@
+#![cfg(unix)]
+
+use assert_cmd::Command;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+/// Compiles a job that prints its first argument and exits with its second,
+/// with logs and the lock kept inside `dir`.
+fn job(dir: &std::path::Path) {
+    let src = format!(
+        "@cron(\"log_dir={d}/logs\", \"lock_file={d}/job.lock\", \"webhook=https://hooks.example/job\")\n\
+         func main() {{\n    print(\"hello \" & arg(1))\n    exit(arg(2))\n}}\n",
+        d = dir.display()
+    );
+    fs::write(dir.join("job.sh2"), src).unwrap();
+    sh2c()
+        .current_dir(dir)
+        .args(["--emit-cron", "15 2 * * *", "-o", "job.sh", "job.sh2"])
+        .assert()
+        .success();
+}
+
+fn todays_log(dir: &std::path::Path) -> String {
+    let entry = fs::read_dir(dir.join("logs")).unwrap().next().unwrap().unwrap();
+    let name = entry.file_name().to_string_lossy().into_owned();
+    assert!(name.starts_with("job-") && name.ends_with(".log"), "{}", name);
+    fs::read_to_string(entry.path()).unwrap()
+}
+
+#[test]
+fn crontab_line_points_at_the_wrapper() {
+    let dir = tempfile::tempdir().unwrap();
+    job(dir.path());
+    let wrapper = dir.path().canonicalize().unwrap().join("job-cron.sh");
+    let crontab = fs::read_to_string(dir.path().join("job.crontab")).unwrap();
+    assert_eq!(crontab, format!("# Generated by sh2c from job.sh2\n15 2 * * * {}\n", wrapper.display()));
+    let mode = fs::metadata(&wrapper).unwrap().permissions().mode();
+    assert!(mode & 0o111 != 0, "wrapper is not executable: {:o}", mode);
+}
+
+#[test]
+fn wrapper_logs_output_and_reports_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    job(dir.path());
+
+    // A stand-in curl records the webhook call.
+    let bin = dir.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    fs::write(bin.join("curl"), format!("#!/bin/sh\nprintf '%s\\n' \"$@\" > {}/curl.args\n", dir.path().display())).unwrap();
+    fs::set_permissions(bin.join("curl"), fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+
+    let ok = std::process::Command::new(dir.path().join("job-cron.sh")).args(["world", "0"]).env("PATH", &path).output().unwrap();
+    assert!(ok.status.success(), "{}", String::from_utf8_lossy(&ok.stderr));
+    assert!(ok.stdout.is_empty(), "output should go to the log");
+    assert!(!dir.path().join("curl.args").exists(), "webhook called for a successful run");
+
+    let failed = std::process::Command::new(dir.path().join("job-cron.sh")).args(["again", "3"]).env("PATH", &path).output().unwrap();
+    assert_eq!(failed.status.code(), Some(3));
+
+    let log = todays_log(dir.path());
+    assert!(log.contains(" job: start\nhello world\n"), "{}", log);
+    assert!(log.contains(" job: exit 0\n") && log.contains("hello again\n") && log.contains(" job: exit 3\n"), "{}", log);
+
+    let curl = fs::read_to_string(dir.path().join("curl.args")).unwrap();
+    assert!(curl.contains("{\"job\":\"job\",\"status\":3,\"host\":"), "{}", curl);
+    assert!(curl.ends_with("https://hooks.example/job\n"), "{}", curl);
+}
+
+#[test]
+fn overlapping_run_is_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    job(dir.path());
+    let wrapper = dir.path().join("job-cron.sh");
+    let lock = dir.path().join("job.lock");
+
+    // Hold the lock the way a still-running job would.
+    let out = if which("flock") {
+        std::process::Command::new("flock").arg(&lock).arg(&wrapper).args(["x", "0"]).output().unwrap()
+    } else {
+        fs::create_dir(dir.path().join("job.lock.d")).unwrap();
+        std::process::Command::new(&wrapper).args(["x", "0"]).output().unwrap()
+    };
+    assert!(out.status.success());
+    let log = todays_log(dir.path());
+    assert!(log.contains(" job: previous run still active, skipped\n"), "{}", log);
+    assert!(!log.contains("hello"), "{}", log);
+}
+
+#[test]
+fn emit_cron_checks_its_schedule_and_attribute() {
+    sh2c()
+        .args(["--emit-cron", "every night", "-o", "job.sh", "job.sh2"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("--emit-cron expects five cron fields"));
+    sh2c()
+        .args(["--emit-cron", "@daily", "job.sh2"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("--emit-cron requires --out or --out-dir"));
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("job.sh2"), "@cron(\"logdir=/tmp\")\nfunc main() {\n}\n").unwrap();
+    let out = sh2c().current_dir(dir.path()).args(["--emit-cron", "@daily", "-o", "job.sh", "job.sh2"]).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("@cron has no key 'logdir'") && stderr.contains("[E0604]"), "{}", stderr);
+}
+
+fn which(cmd: &str) -> bool {
+    std::process::Command::new("sh")
+        .args(["-c", &format!("command -v {} >/dev/null", cmd)])
+        .status()
+        .is_ok_and(|s| s.success())
+}
@@ -0,0 +1,81 @@
+use assert_cmd::Command;
+use std::fs;
+
+const UTIL: &str = r#"func shout(s) {
+    print(s & "!")
+}
+"#;
+
+const SRC: &str = r#"import "util.sh2" as u
+
+func first_field(csv) {
+    for p in split(csv, ",") {
+        print(p)
+        return
+    }
+}
+
+func loud(s) {
+    u.shout(s)
+}
+"#;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+fn project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("util.sh2"), UTIL).unwrap();
+    fs::write(dir.path().join("lib.sh2"), SRC).unwrap();
+    dir
+}
+
+#[test]
+fn library_without_main_is_sourceable() {
+    let dir = project();
+    for (target, shell) in [("bash", "bash"), ("posix", "sh")] {
+        sh2c()
+            .current_dir(dir.path())
+            .args(["--target", target, "--emit-lib", "-o", "lib.sh", "lib.sh2"])
+            .assert()
+            .success();
+        let lib = fs::read_to_string(dir.path().join("lib.sh")).unwrap();
+        assert!(!lib.contains("main \"$@\""), "{}", lib);
+
+        let out = std::process::Command::new(shell)
+            .current_dir(dir.path())
+            .arg("-c")
+            .arg(". ./lib.sh; echo sourced; first_field 'x,y'; loud hey; __imp_u__shout direct")
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "{}: {}", shell, String::from_utf8_lossy(&out.stderr));
+        assert_eq!(String::from_utf8(out.stdout).unwrap(), "sourced\nx\nhey!\ndirect!\n");
+    }
+}
+
+#[test]
+fn library_file_is_not_executable() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = project();
+    sh2c().current_dir(dir.path()).args(["--emit-lib", "-o", "lib.sh", "lib.sh2"]).assert().success();
+    let mode = fs::metadata(dir.path().join("lib.sh")).unwrap().permissions().mode();
+    assert_eq!(mode & 0o111, 0, "library written with exec bit: {:o}", mode);
+}
+
+#[test]
+fn scripts_still_require_main() {
+    let dir = project();
+    sh2c().current_dir(dir.path()).arg("lib.sh2").assert().failure();
+}
+
+#[test]
+fn emit_lib_rejects_script_only_flags() {
+    for flags in [&["--emit-lib", "--check"][..], &["--emit-lib", "--runtime-lib", "rt.sh"], &["--emit-lib", "--sandbox-guard"]] {
+        let dir = project();
+        let out = sh2c().current_dir(dir.path()).args(flags).arg("lib.sh2").output().unwrap();
+        assert_eq!(out.status.code(), Some(1), "{:?}", flags);
+        let stderr = String::from_utf8(out.stderr).unwrap();
+        assert!(stderr.starts_with("error: "), "{:?}: {}", flags, stderr);
+    }
+}
@@ -0,0 +1,53 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+fn emit(format: &str) -> String {
+    let out = sh2c().args(["emit-syntax", format]).output().unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn every_format_lists_keywords_and_builtins() {
+    let vim = emit("vim");
+    assert!(vim.starts_with("\" Vim syntax file\n"), "{}", vim);
+    assert!(vim.contains("syn keyword sh2Keyword func extern let "), "{}", vim);
+    assert!(vim.contains(" each_line") && vim.contains(" try_run "), "{}", vim);
+    assert!(vim.ends_with("let b:current_syntax = \"sh2\"\n"), "{}", vim);
+
+    let queries = emit("tree-sitter-queries");
+    assert!(queries.contains("((identifier) @keyword\n (#any-of? @keyword\n  \"func\" \"extern\""), "{}", queries);
+    assert!(queries.contains("((identifier) @boolean\n (#any-of? @boolean\n  \"true\" \"false\"))\n"), "{}", queries);
+
+    let grammar: serde_json::Value = serde_json::from_str(&emit("tmLanguage")).unwrap();
+    let keywords = grammar["repository"]["keywords"]["patterns"][0]["match"].as_str().unwrap();
+    assert!(keywords.contains("|pipe|"), "{}", keywords);
+}
+
+#[test]
+fn output_file() {
+    let dir = tempfile::tempdir().unwrap();
+    sh2c()
+        .current_dir(dir.path())
+        .args(["emit-syntax", "-o", "sh2.vim", "vim"])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(fs::read_to_string(dir.path().join("sh2.vim")).unwrap(), emit("vim"));
+}
+
+#[test]
+fn emit_syntax_rejects_bad_arguments() {
+    for (args, msg) in [
+        (&["emit-syntax"][..], "emit-syntax requires a format: vim, tmLanguage, tree-sitter-queries"),
+        (&["emit-syntax", "emacs"][..], "unexpected argument for emit-syntax: emacs"),
+        (&["emit-syntax", "vim", "vim"][..], "unexpected argument for emit-syntax: vim"),
+        (&["emit-syntax", "vim", "-o"][..], "-o requires an argument"),
+    ] {
+        sh2c().args(args).assert().code(1).stderr(predicates::str::contains(msg));
+    }
+}
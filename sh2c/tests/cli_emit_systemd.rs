@@ -0,0 +1,111 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+const WORKER: &str = r#"@service("description=Queue worker", "after=network-online.target", "restart=always", "restart_sec=5", "user=worker", "env_file=-/etc/default/worker")
+func main() {
+    print("working")
+}
+"#;
+
+#[test]
+fn unit_is_written_next_to_the_script() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("worker.sh2"), WORKER).unwrap();
+    sh2c()
+        .current_dir(dir.path())
+        .args(["--emit-systemd", "-o", "worker.sh", "worker.sh2"])
+        .assert()
+        .success();
+
+    let script = dir.path().canonicalize().unwrap().join("worker.sh");
+    let unit = fs::read_to_string(dir.path().join("worker.service")).unwrap();
+    assert_eq!(
+        unit,
+        format!(
+            "# Generated by sh2c from worker.sh2\n\
+             [Unit]\n\
+             Description=Queue worker\n\
+             After=network-online.target\n\
+             Wants=network-online.target\n\
+             \n\
+             [Service]\n\
+             Type=simple\n\
+             ExecStart={}\n\
+             Restart=always\n\
+             RestartSec=5\n\
+             User=worker\n\
+             EnvironmentFile=-/etc/default/worker\n\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n",
+            script.display()
+        )
+    );
+}
+
+#[test]
+fn defaults_without_attribute_and_exec_override() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.sh2"), "func main() {\n    print(\"a\")\n}\n").unwrap();
+    fs::write(
+        dir.path().join("b.sh2"),
+        "@service(\"exec=/usr/local/bin/b\", \"type=oneshot\", \"restart=no\")\nfunc main() {\n    print(\"b\")\n}\n",
+    )
+    .unwrap();
+    sh2c()
+        .current_dir(dir.path())
+        .args(["--emit-systemd", "--out-dir", "out", "a.sh2", "b.sh2"])
+        .assert()
+        .success();
+
+    let a = fs::read_to_string(dir.path().join("out/a.service")).unwrap();
+    assert!(a.contains("Description=a (sh2 script)\n"), "{}", a);
+    assert!(a.contains("Type=simple\n") && a.contains("Restart=on-failure\n"), "{}", a);
+    assert!(a.contains("/out/a.sh\n") && !a.contains("After="), "{}", a);
+
+    let b = fs::read_to_string(dir.path().join("out/b.service")).unwrap();
+    assert!(b.contains("Type=oneshot\nExecStart=/usr/local/bin/b\nRestart=no\n"), "{}", b);
+}
+
+#[test]
+fn service_attribute_is_checked() {
+    let dir = tempfile::tempdir().unwrap();
+    let cases = [
+        ("@service(\"restart=sometimes\")\nfunc main() {\n}\n", "@service restart=sometimes is not valid"),
+        ("@service(\"usr=x\")\nfunc main() {\n}\n", "@service has no key 'usr'"),
+        (
+            "@service(\"user=x\")\nfunc helper() {\n}\nfunc main() {\n    helper()\n}\n",
+            "@service on 'helper' has no effect; put it on the entry function 'main'",
+        ),
+    ];
+    for (src, msg) in cases {
+        fs::write(dir.path().join("main.sh2"), src).unwrap();
+        let out = sh2c()
+            .current_dir(dir.path())
+            .args(["--emit-systemd", "-o", "main.sh", "main.sh2"])
+            .output()
+            .unwrap();
+        assert_eq!(out.status.code(), Some(2), "{}", src);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(stderr.contains(msg) && stderr.contains("[E0603]"), "{}", stderr);
+        assert!(!dir.path().join("main.service").exists());
+    }
+}
+
+#[test]
+fn emit_systemd_needs_an_output_script() {
+    sh2c()
+        .args(["--emit-systemd", "main.sh2"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("--emit-systemd requires --out or --out-dir"));
+    sh2c()
+        .args(["--emit-systemd", "--emit-lib", "-o", "lib.sh", "main.sh2"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("--emit-systemd only works when emitting scripts"));
+}
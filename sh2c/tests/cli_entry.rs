@@ -0,0 +1,86 @@
+use assert_cmd::Command;
+use std::fs;
+
+const SRC: &str = r#"extern func notify(msg)
+
+func backup(dest, mode) {
+    print("backup to " & dest & " (" & mode & ")")
+}
+
+func restore(src) {
+    print("restore from " & src)
+}
+
+func main() {
+    print("usage: backup.sh or restore.sh")
+}
+"#;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+fn project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("tools.sh2"), SRC).unwrap();
+    dir
+}
+
+#[test]
+fn entry_function_receives_script_arguments() {
+    let dir = project();
+    for target in ["bash", "posix"] {
+        for (entry, args, expected) in [
+            ("backup", &["/mnt/a b", "full"][..], "backup to /mnt/a b (full)\n"),
+            ("restore", &["/srv"][..], "restore from /srv\n"),
+        ] {
+            sh2c()
+                .current_dir(dir.path())
+                .args(["--target", target, "--entry", entry, "-o", "tool.sh", "tools.sh2"])
+                .assert()
+                .success();
+            let out = std::process::Command::new(dir.path().join("tool.sh")).args(args).output().unwrap();
+            assert!(out.status.success(), "{} {}: {}", target, entry, String::from_utf8_lossy(&out.stderr));
+            assert_eq!(String::from_utf8(out.stdout).unwrap(), expected, "{} {}", target, entry);
+        }
+    }
+}
+
+#[test]
+fn main_is_optional_with_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("t.sh2"), "func go() {\n    print(\"go\")\n}\n").unwrap();
+    let out = sh2c().current_dir(dir.path()).args(["--entry", "go", "t.sh2"]).output().unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert!(String::from_utf8(out.stdout).unwrap().ends_with("\ngo \"$@\"\n"));
+}
+
+#[test]
+fn unknown_or_extern_entry_is_rejected() {
+    let dir = project();
+    for (entry, msg) in [
+        ("cleanup", "--entry cleanup: no such function; define `func cleanup(...)`. [E0102]"),
+        ("notify", "--entry notify: an extern function cannot be the entry point"),
+    ] {
+        sh2c()
+            .current_dir(dir.path())
+            .args(["--entry", entry, "tools.sh2"])
+            .assert()
+            .code(2)
+            .stderr(predicates::str::contains(msg));
+    }
+}
+
+#[test]
+fn entry_flag_errors() {
+    sh2c()
+        .args(["tools.sh2", "--entry"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("error: --entry requires a function name"));
+    sh2c()
+        .args(["--emit-lib", "--entry", "backup", "tools.sh2"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("error: --emit-lib cannot be used with --entry"));
+}
@@ -0,0 +1,58 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+#[test]
+fn diagnostics_carry_a_code_that_explain_describes() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("main.sh2");
+    fs::write(&script, "func main() {\n    print(missing)\n}\n").unwrap();
+
+    let out = sh2c().arg("--check").arg(&script).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("undefined variable 'missing' [E0200]"), "{}", stderr);
+
+    let explain = sh2c().args(["explain", "E0200"]).output().unwrap();
+    assert!(explain.status.success());
+    let text = String::from_utf8(explain.stdout).unwrap();
+    assert!(text.starts_with("E0200: undefined variable\n\n"), "{}", text);
+}
+
+#[test]
+fn explain_rejects_unknown_or_missing_codes() {
+    let out = sh2c().args(["explain", "E9999"]).output().unwrap();
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("unknown error code: E9999"));
+
+    let out = sh2c().arg("explain").output().unwrap();
+    assert_eq!(out.status.code(), Some(1));
+}
+
+#[test]
+fn every_code_has_an_explanation() {
+    for c in sh2c::diag_codes::CODES {
+        let out = sh2c().args(["explain", c.code]).output().unwrap();
+        assert!(out.status.success(), "{}", c.code);
+        assert!(!c.explanation.trim().is_empty(), "{}", c.code);
+    }
+}
+
+#[test]
+fn errors_keep_the_code_their_site_set() {
+    let src = "func main() {\n    print(\"open)\n}\n";
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let err = sh2c::lexer::lex(&sm, "main.sh2").unwrap_err();
+    assert_eq!(err.code, Some("E0001"));
+
+    // The stored code wins even when the message reads like another one.
+    let err = err.with_code("E0004");
+    assert!(err.format(None).contains("[E0004]"), "{}", err.format(None));
+
+    let err = sh2c::error::CompileError::unsupported("lines()", sh2c::target::TargetShell::Posix);
+    assert_eq!(err.code, Some("E0400"));
+    assert!(err.message.ends_with("[E0400]"), "{}", err.message);
+}
@@ -0,0 +1,130 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+const MAIN: &str = r#"import "lib.sh2" as lib
+import "lib.sh2" as util
+
+func main() {
+    let f = arg(1)
+    run("rm", "-f", f)
+    run("rm", "--", f)
+    print(pwd())
+    util.hello()
+}
+"#;
+
+fn project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("lib.sh2"), "func hello() {\n    print(\"hi\")\n}\n").unwrap();
+    fs::write(dir.path().join("main.sh2"), MAIN).unwrap();
+    dir
+}
+
+#[test]
+fn check_reports_fixable_warnings() {
+    let dir = project();
+    let out = sh2c().current_dir(dir.path()).args(["--check", "main.sh2"]).output().unwrap();
+    assert!(out.status.success());
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("main.sh2:6:21: rm receives `f` before any \"--\""), "{}", stderr);
+    assert!(stderr.contains("[W0003]"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:1:1: unused import alias 'lib' [W0002]"), "{}", stderr);
+    assert!(stderr.contains("help: remove the import (sh2c --fix applies this)"), "{}", stderr);
+    // The `--` already present on line 7 is respected.
+    assert!(!stderr.contains("main.sh2:7:"), "{}", stderr);
+}
+
+#[test]
+fn values_computed_by_the_script_need_no_separator() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = r#"func main() {
+    let tmp = "/tmp/build"
+    for f in ["a.o", "b.o"] {
+        run("rm", "-f", tmp & "/" & f)
+    }
+    let dest = tmp
+    if arg(1) == "x" {
+        set dest = env.HOME & "/out"
+    }
+    run("mkdir", "-p", dest)
+}
+"#;
+    fs::write(dir.path().join("main.sh2"), src).unwrap();
+    let out = sh2c().current_dir(dir.path()).args(["--check", "main.sh2"]).output().unwrap();
+    assert!(out.status.success());
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    // Only the mkdir sees input: `dest` is set from `env.HOME` on one path.
+    assert!(!stderr.contains("main.sh2:4:"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:10:24: mkdir receives `dest` before any \"--\""), "{}", stderr);
+    assert_eq!(stderr.matches("[W0003]").count(), 1, "{}", stderr);
+}
+
+#[test]
+fn posix_only_builtins_are_errors_with_a_rewrite() {
+    let dir = project();
+    let out = sh2c()
+        .current_dir(dir.path())
+        .args(["--target", "posix", "--check", "main.sh2"])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("main.sh2:8:11: pwd() is not supported in POSIX sh target [E0400]"), "{}", stderr);
+    assert!(stderr.contains("help: use `env.PWD`"), "{}", stderr);
+}
+
+#[test]
+fn fix_rewrites_the_source_and_is_idempotent() {
+    let dir = project();
+    let out = sh2c()
+        .current_dir(dir.path())
+        .args(["--target", "posix", "--fix", "main.sh2"])
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), "main.sh2: applied 3 fixes\n");
+
+    let fixed = fs::read_to_string(dir.path().join("main.sh2")).unwrap();
+    assert_eq!(
+        fixed,
+        r#"import "lib.sh2" as util
+
+func main() {
+    let f = arg(1)
+    run("rm", "-f", "--", f)
+    run("rm", "--", f)
+    print(env.PWD)
+    util.hello()
+}
+"#
+    );
+
+    let again = sh2c()
+        .current_dir(dir.path())
+        .args(["--target", "posix", "--fix", "main.sh2"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(again.stdout).unwrap(), "no fixes to apply\n");
+
+    let check = sh2c()
+        .current_dir(dir.path())
+        .args(["--target", "posix", "--check", "main.sh2"])
+        .output()
+        .unwrap();
+    assert!(check.status.success());
+    assert!(check.stderr.is_empty(), "{}", String::from_utf8_lossy(&check.stderr));
+}
+
+#[test]
+fn fix_rejects_out_and_other_actions() {
+    let dir = project();
+    let out = sh2c().current_dir(dir.path()).args(["--fix", "-o", "x.sh", "main.sh2"]).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    let out = sh2c().current_dir(dir.path()).args(["--fix", "--check", "main.sh2"]).output().unwrap();
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(fs::read_to_string(dir.path().join("main.sh2")).unwrap(), MAIN);
+}
@@ -0,0 +1,71 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+fn summary() -> serde_json::Value {
+    let out = sh2c().args(["introspect", "--json"]).output().unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    serde_json::from_slice(&out.stdout).unwrap()
+}
+
+fn names(list: &serde_json::Value) -> Vec<&str> {
+    list.as_array().unwrap().iter().map(|v| v["name"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn summary_lists_keywords_and_reserved_words() {
+    let json = summary();
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    let keywords: Vec<&str> = json["keywords"].as_array().unwrap().iter().map(|v| v.as_str().unwrap()).collect();
+    assert_eq!(keywords, sh2c::lang_spec::KEYWORDS);
+    let reserved: Vec<&str> = json["reserved_words"].as_array().unwrap().iter().map(|v| v.as_str().unwrap()).collect();
+    assert_eq!(reserved, sh2c::lexer::keywords());
+    assert!(reserved.contains(&"print") && reserved.contains(&"each_line"));
+    assert_eq!(names(&json["attributes"]), ["deprecated", "service", "cron", "cache", "pure", "inline"]);
+}
+
+#[test]
+fn statements_follow_the_grammar() {
+    let ebnf = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../artifacts/grammar/sh2.ebnf")).unwrap();
+    let rule = ebnf.split("\nstatement =").nth(1).unwrap().split(';').next().unwrap();
+    let alternatives: Vec<&str> = rule
+        .split('|')
+        .map(|alt| alt.trim())
+        .map(|alt| alt.strip_suffix("_stmt").unwrap_or(alt))
+        .collect();
+    assert_eq!(names(&summary()["statements"]), alternatives);
+}
+
+#[test]
+fn builtin_arities_match_the_compiler() {
+    let json = summary();
+    let builtins = json["builtins"].as_array().unwrap();
+    let arity = |name: &str| {
+        let b = builtins.iter().find(|b| b["name"] == name).unwrap_or_else(|| panic!("no builtin {}", name));
+        (b["min_args"].as_u64().unwrap() as usize, b["max_args"].as_u64().map(|m| m as usize))
+    };
+    assert_eq!(arity("run"), (1, None));
+    assert_eq!(arity("write_file"), (2, Some(3)));
+
+    // One argument too many is a compile error for fixed-arity builtins.
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["split", "matches", "read_file", "replace", "home", "which"] {
+        let (_, max) = arity(name);
+        let args = vec!["\"x\""; max.unwrap() + 1].join(", ");
+        fs::write(dir.path().join("main.sh2"), format!("func main() {{\n    let v = {}({})\n}}\n", name, args)).unwrap();
+        let out = sh2c().current_dir(dir.path()).args(["--check", "main.sh2"]).output().unwrap();
+        assert_eq!(out.status.code(), Some(2), "{}({}) compiled", name, args);
+    }
+}
+
+#[test]
+fn introspect_requires_json() {
+    sh2c()
+        .args(["introspect"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("introspect requires --json"));
+}
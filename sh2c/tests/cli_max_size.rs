@@ -0,0 +1,83 @@
+use assert_cmd::Command;
+use std::fs;
+
+const SRC: &str = r#"func greet(name) {
+    print("hello " & name)
+}
+
+func main() {
+    let parts = split("a,b", ",")
+    for p in parts {
+        greet(p)
+    }
+}
+"#;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+fn project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("main.sh2"), SRC).unwrap();
+    dir
+}
+
+#[test]
+fn over_budget_fails_with_breakdown() {
+    let dir = project();
+    let out = sh2c()
+        .current_dir(dir.path())
+        .args(["--max-size", "200", "-o", "main.sh", "main.sh2"])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    assert!(!dir.path().join("main.sh").exists(), "output written despite the budget");
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("--max-size: script is "), "{}", stderr);
+    assert!(stderr.contains("over the budget of 200 bytes [E0602]"), "{}", stderr);
+    for row in ["  prelude ", "  main() ", "  greet() "] {
+        assert!(stderr.contains(row), "missing {:?} in:\n{}", row, stderr);
+    }
+}
+
+#[test]
+fn breakdown_adds_up_to_the_script() {
+    let dir = project();
+    let script = sh2c().current_dir(dir.path()).arg("main.sh2").output().unwrap().stdout;
+    let out = sh2c()
+        .current_dir(dir.path())
+        .args(["--warn-size", "1", "main.sh2"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(out.stdout, script, "--warn-size changed the output");
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.starts_with(&format!("warning: --warn-size: script is {} bytes", script.len())), "{}", stderr);
+    let sum: usize = stderr
+        .lines()
+        .skip(1)
+        .map(|l| l.split_whitespace().nth(1).unwrap().parse::<usize>().unwrap())
+        .sum();
+    assert_eq!(sum, script.len(), "{}", stderr);
+}
+
+#[test]
+fn within_budget_is_silent() {
+    let dir = project();
+    sh2c()
+        .current_dir(dir.path())
+        .args(["--max-size", "64K", "--warn-size", "1M", "--minify", "main.sh2"])
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn bad_size_is_a_usage_error() {
+    sh2c()
+        .args(["--max-size", "16KB", "main.sh2"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("error: --max-size expects a byte count such as 16384 or 16K, got '16KB'"));
+}
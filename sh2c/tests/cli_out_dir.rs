@@ -0,0 +1,96 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+
+const UTIL: &str = r#"func shout(s) {
+    print(s & "!")
+}
+"#;
+
+fn tool(word: &str) -> String {
+    format!("import \"lib/util.sh2\" as u\n\nfunc main() {{\n    u.shout(\"{}\")\n}}\n", word)
+}
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+fn project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir_all(src.join("lib")).unwrap();
+    fs::write(src.join("lib/util.sh2"), UTIL).unwrap();
+    fs::write(src.join("deploy.sh2"), tool("deploy")).unwrap();
+    fs::write(src.join("backup.sh2"), tool("backup")).unwrap();
+    dir
+}
+
+fn run(script: &Path) -> String {
+    let out = std::process::Command::new(script).output().unwrap();
+    assert!(out.status.success(), "{}: {}", script.display(), String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn compiles_each_input_into_out_dir() {
+    let dir = project();
+    sh2c()
+        .current_dir(dir.path())
+        .args(["--out-dir", "build", "src/deploy.sh2", "src/backup.sh2"])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(run(&dir.path().join("build/deploy.sh")), "deploy!\n");
+    assert_eq!(run(&dir.path().join("build/backup.sh")), "backup!\n");
+
+    // Same bytes as compiling one at a time.
+    let single = sh2c().current_dir(dir.path()).arg("src/deploy.sh2").output().unwrap().stdout;
+    assert_eq!(fs::read(dir.path().join("build/deploy.sh")).unwrap(), single);
+}
+
+#[test]
+fn directory_input_takes_its_sh2_files() {
+    let dir = project();
+    sh2c().current_dir(dir.path()).args(["src", "--out-dir", "build"]).assert().success();
+    let mut names: Vec<_> = fs::read_dir(dir.path().join("build"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["backup.sh", "deploy.sh"]);
+}
+
+#[test]
+fn failures_are_reported_without_stopping_the_build() {
+    let dir = project();
+    fs::write(dir.path().join("src/broken.sh2"), "func main() {\n    print(x)\n}\n").unwrap();
+    sh2c()
+        .current_dir(dir.path())
+        .args(["--out-dir", "build", "src"])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("undefined variable 'x'"))
+        .stderr(predicates::str::contains("error: 1 of 3 input(s) failed to compile"));
+    assert!(dir.path().join("build/backup.sh").exists());
+    assert!(dir.path().join("build/deploy.sh").exists());
+    assert!(!dir.path().join("build/broken.sh").exists());
+}
+
+#[test]
+fn multiple_inputs_need_out_dir() {
+    let dir = project();
+    for (args, msg) in [
+        (&["src/deploy.sh2", "src/backup.sh2"][..], "use --out-dir to compile several"),
+        (&["src"][..], "error: src is a directory; use --out-dir"),
+        (&["--out-dir", "build", "-o", "x.sh", "src"][..], "error: --out-dir cannot be used with --out"),
+        (&["--out-dir", "build", "--check", "src"][..], "error: --out-dir only works when emitting scripts or libraries"),
+        (&["--out-dir", "build", "src/deploy.sh2", "src/lib/../deploy.sh2"][..], "would both be written to"),
+    ] {
+        sh2c()
+            .current_dir(dir.path())
+            .args(args)
+            .assert()
+            .code(1)
+            .stderr(predicates::str::contains(msg));
+    }
+}
@@ -0,0 +1,109 @@
+use assert_cmd::Command;
+use std::fs;
+
+const SRC: &str = r#"func main() {
+    let parts = split("a,b", ",")
+    for p in parts {
+        print(p)
+    }
+    # a comment the script does not need
+
+    print(before("hi there", " "))
+}
+"#;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+fn project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("main.sh2"), SRC).unwrap();
+    dir
+}
+
+#[test]
+fn report_lists_helpers_and_their_cause() {
+    let dir = project();
+    let out = sh2c()
+        .current_dir(dir.path())
+        .args(["--emit-prelude-report", "main.sh2"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.starts_with("prelude for bash target: "), "{}", stdout);
+    assert!(stdout.contains("  always: __sh2_check ("), "{}", stdout);
+    assert!(stdout.contains("  split: __sh2_split ("), "{}", stdout);
+    assert!(stdout.contains("), first needed at main.sh2:2:5\n"), "{}", stdout);
+    // print() carries no location of its own, so the function is named instead.
+    assert!(stdout.contains("  before: __sh2_before (") && stdout.contains("first needed in main()"), "{}", stdout);
+    assert!(!stdout.contains("#!/"), "script printed in report mode:\n{}", stdout);
+}
+
+#[test]
+fn report_is_an_action_flag() {
+    sh2c()
+        .args(["--emit-prelude-report", "--check", "main.sh2"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("multiple action flags specified"));
+}
+
+#[test]
+fn minified_script_is_smaller_and_equivalent() {
+    let dir = project();
+    for target in ["bash", "posix"] {
+        let plain = sh2c().current_dir(dir.path()).args(["--target", target, "main.sh2"]).output().unwrap();
+        let min = sh2c()
+            .current_dir(dir.path())
+            .args(["--target", target, "--minify", "-o", "min.sh", "main.sh2"])
+            .output()
+            .unwrap();
+        assert!(plain.status.success() && min.status.success());
+        let script = fs::read_to_string(dir.path().join("min.sh")).unwrap();
+        assert!(script.len() < plain.stdout.len(), "{} output did not shrink", target);
+        assert!(!script.contains("__sh2_"), "internal names left in {} output:\n{}", target, script);
+        assert!(!script.lines().any(|l| l.trim().is_empty()), "blank lines left in {} output", target);
+
+        let run = std::process::Command::new(dir.path().join("min.sh")).output().unwrap();
+        assert!(run.status.success(), "{}: {}", target, String::from_utf8_lossy(&run.stderr));
+        assert_eq!(String::from_utf8(run.stdout).unwrap(), "a\nb\nhi\n");
+    }
+}
+
+#[test]
+fn minify_keeps_names_the_runtime_library_defines() {
+    let dir = project();
+    sh2c()
+        .current_dir(dir.path())
+        .args(["--minify", "--runtime-lib", "rt.sh", "-o", "min.sh", "main.sh2"])
+        .assert()
+        .success();
+    let script = fs::read_to_string(dir.path().join("min.sh")).unwrap();
+    assert!(script.contains("__sh2_split "), "{}", script);
+    let run = std::process::Command::new(dir.path().join("min.sh")).output().unwrap();
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), "a\nb\nhi\n");
+}
+
+#[test]
+fn minify_leaves_user_strings_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = "func main() {\n    let msg = \"keep __sh2_check and $__sh2_status here\"\n    print(msg)\n    print(\"__sh2_split\")\n    sh(\"echo '__sh2_x y'\")\n}\n";
+    fs::write(dir.path().join("main.sh2"), src).unwrap();
+    for target in ["bash", "posix"] {
+        sh2c()
+            .current_dir(dir.path())
+            .args(["--target", target, "--minify", "-o", "min.sh", "main.sh2"])
+            .assert()
+            .success();
+        let run = std::process::Command::new(dir.path().join("min.sh")).output().unwrap();
+        assert!(run.status.success(), "{}: {}", target, String::from_utf8_lossy(&run.stderr));
+        assert_eq!(
+            String::from_utf8(run.stdout).unwrap(),
+            "keep __sh2_check and $__sh2_status here\n__sh2_split\n__sh2_x y\n",
+            "{}",
+            target
+        );
+    }
+}
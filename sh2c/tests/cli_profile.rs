@@ -0,0 +1,83 @@
+#![cfg(unix)]
+
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+const SRC: &str = r#"func nap(n) {
+    run("sleep", n)
+    print("slept " & n)
+}
+func main() {
+    let x = 1
+    nap("0.2")
+    for i in [1, 2] {
+        nap("0.1")
+    }
+    if x == 1 {
+        return
+    }
+}
+"#;
+
+/// The report lines after the header, as (ms, runs, location).
+fn report(stderr: &str) -> Vec<(f64, u32, String)> {
+    let mut lines = stderr.lines();
+    assert_eq!(lines.next(), Some("profile: slowest lines (total time, runs)"), "{}", stderr);
+    lines
+        .map(|l| {
+            let f: Vec<&str> = l.split_whitespace().collect();
+            assert_eq!(f[1], "ms", "{}", l);
+            (f[0].parse().unwrap(), f[2].trim_end_matches('x').parse().unwrap(), f[3].to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn profile_reports_slowest_lines_at_exit() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("main.sh2"), SRC).unwrap();
+    for (target, shell) in [("bash", "bash"), ("posix", "sh")] {
+        sh2c().current_dir(dir.path()).args(["--profile", "--target", target, "-o", "main.sh", "main.sh2"]).assert().success();
+        let out = std::process::Command::new(shell)
+            .arg("main.sh")
+            .current_dir(dir.path())
+            .env("TMPDIR", dir.path())
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "slept 0.2\nslept 0.1\nslept 0.1\n");
+
+        let rows = report(&String::from_utf8_lossy(&out.stderr));
+        let locs: Vec<&str> = rows.iter().map(|r| r.2.as_str()).collect();
+        assert_eq!(locs[0], "main.sh2:2:5", "{:?}", rows);
+        assert_eq!(rows[0].1, 3);
+        assert!(rows[0].0 >= 350.0, "{:?}", rows);
+        for loc in ["main.sh2:3:5", "main.sh2:6:5", "main.sh2:7:5", "main.sh2:8:5"] {
+            assert!(locs.contains(&loc), "{} missing from {:?}", loc, rows);
+        }
+        // The `if` returns before its time is recorded.
+        assert!(!locs.contains(&"main.sh2:11:5"), "{:?}", rows);
+        // The log is removed once reported.
+        assert!(!fs::read_dir(dir.path()).unwrap().any(|e| e.unwrap().file_name().to_string_lossy().starts_with("sh2-profile.")));
+    }
+}
+
+#[test]
+fn profile_keeps_exit_status_and_is_off_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("main.sh2"), "func main() {\n    print(\"a\")\n    exit(3)\n}\n").unwrap();
+    sh2c().current_dir(dir.path()).args(["--profile", "-o", "main.sh", "main.sh2"]).assert().success();
+    let out = std::process::Command::new("bash").arg("main.sh").current_dir(dir.path()).output().unwrap();
+    assert_eq!(out.status.code(), Some(3));
+    let rows = report(&String::from_utf8_lossy(&out.stderr));
+    assert_eq!(rows.iter().map(|r| r.2.as_str()).collect::<Vec<_>>(), ["main.sh2:2:5"]);
+
+    let plain = sh2c().current_dir(dir.path()).arg("main.sh2").output().unwrap();
+    assert!(!String::from_utf8_lossy(&plain.stdout).contains("__sh2_prof"));
+    let lib = sh2c().current_dir(dir.path()).args(["--profile", "--emit-lib", "main.sh2"]).output().unwrap();
+    assert!(!String::from_utf8_lossy(&lib.stdout).contains("__sh2_prof"));
+}
@@ -0,0 +1,82 @@
+use assert_cmd::Command;
+use sh2c::codegen::{emit_runtime_lib, TargetShell};
+use std::fs;
+use std::path::Path;
+
+const SRC: &str = r#"func main() {
+    let parts = split("a,b", ",")
+    for p in parts {
+        print(p)
+    }
+    print(trim("  hi  "))
+}
+"#;
+
+fn compile(dir: &Path, target: &str) {
+    fs::write(dir.join("main.sh2"), SRC).unwrap();
+    fs::create_dir(dir.join("bin")).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir)
+        .args(["--target", target, "--runtime-lib=sh2rt.sh", "-o", "bin/main.sh", "main.sh2"])
+        .assert()
+        .success();
+}
+
+fn run(script: &Path) -> std::process::Output {
+    std::process::Command::new(script).output().unwrap()
+}
+
+#[test]
+fn script_sources_the_shared_runtime() {
+    for target in ["bash", "posix"] {
+        let dir = tempfile::tempdir().unwrap();
+        compile(dir.path(), target);
+
+        let script = fs::read_to_string(dir.path().join("bin/main.sh")).unwrap();
+        let lib = fs::read_to_string(dir.path().join("bin/sh2rt.sh")).unwrap();
+        assert!(!script.contains("__sh2_split()"), "helper inlined for {}:\n{}", target, script);
+        assert!(lib.contains("__sh2_split()"), "helper missing from {} runtime", target);
+        assert!(lib.contains("__sh2_confirm()"), "unused helpers belong in the {} runtime too", target);
+
+        // Run from elsewhere: the relative library path follows the script.
+        let out = run(&dir.path().join("bin/main.sh"));
+        assert!(out.status.success(), "{}: {}", target, String::from_utf8_lossy(&out.stderr));
+        assert_eq!(String::from_utf8(out.stdout).unwrap(), "a\nb\nhi\n");
+    }
+}
+
+#[test]
+fn version_mismatch_and_missing_runtime_fail() {
+    let dir = tempfile::tempdir().unwrap();
+    compile(dir.path(), "posix");
+    let lib_path = dir.path().join("bin/sh2rt.sh");
+    let script = dir.path().join("bin/main.sh");
+
+    let lib = fs::read_to_string(&lib_path).unwrap();
+    let stale: String = lib
+        .lines()
+        .map(|l| if l.starts_with("__sh2_rt_version=") { "__sh2_rt_version='sh2c 0.0.1 (posix)'" } else { l })
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(&lib_path, stale).unwrap();
+    let out = run(&script);
+    assert_eq!(out.status.code(), Some(127));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("is sh2 runtime sh2c 0.0.1 (posix), this script needs sh2c "), "{}", stderr);
+
+    fs::remove_file(&lib_path).unwrap();
+    let out = run(&script);
+    assert_eq!(out.status.code(), Some(127));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("sh2 runtime library not found"), "{}", stderr);
+}
+
+#[test]
+fn runtime_is_valid_shell_without_global_setup() {
+    for (target, shell) in [(TargetShell::Bash, "bash"), (TargetShell::Posix, "sh")] {
+        let lib = emit_runtime_lib(target);
+        let out = std::process::Command::new(shell).arg("-n").arg("-c").arg(&lib).output().unwrap();
+        assert!(out.status.success(), "{}: {}", shell, String::from_utf8_lossy(&out.stderr));
+        assert!(!lib.contains("\ntrap "), "{} runtime must not install traps", target);
+    }
+}
@@ -0,0 +1,121 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+
+const SCRIPT: &str = r#"func helper() {
+    print("h")
+}
+
+func main() {
+    require(["curl"])
+    run("echo", "static")
+    let tool = arg(1)
+    run(tool, "dyn")
+    let v = capture(run("printf", "%s", "x") | run("tr", "x", "y"))
+    print(v)
+    sudo("systemctl", "restart", "nginx", user="root", allow_fail=true)
+    helper()
+}
+"#;
+
+#[test]
+fn sandbox_writes_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("s.sh2");
+    let manifest = dir.path().join("m.json");
+    fs::write(&src, SCRIPT).unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .arg("--check")
+        .arg("--sandbox")
+        .arg(&manifest)
+        .arg(&src)
+        .assert()
+        .success();
+
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&manifest).unwrap()).unwrap();
+    assert_eq!(json["target"], "bash");
+    assert_eq!(
+        json["commands"],
+        serde_json::json!(["curl", "echo", "printf", "sudo", "systemctl", "tr"])
+    );
+    assert_eq!(json["dynamic_exec_sites"], 1);
+    assert_eq!(json["raw_shell"], false);
+}
+
+#[test]
+fn sandbox_manifest_flags_raw_shell() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("r.sh2");
+    let manifest = dir.path().join("m.json");
+    fs::write(&src, "func main() {\n    sh(\"ls\")\n}\n").unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .arg("--sandbox")
+        .arg(&manifest)
+        .arg(&src)
+        .assert()
+        .success();
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&manifest).unwrap()).unwrap();
+    assert_eq!(json["commands"], serde_json::json!([]));
+    assert_eq!(json["raw_shell"], true);
+
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .arg("--sandbox")
+        .arg(&manifest)
+        .arg("--sandbox-guard")
+        .arg(&src)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("sh() cannot be used with --sandbox-guard"));
+}
+
+#[test]
+fn sandbox_guard_refuses_undeclared_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("g.sh2");
+    let manifest = dir.path().join("m.json");
+    fs::write(&src, "func main() {\n    run(\"true\")\n    let tool = arg(1)\n    run(tool, \"ran\")\n    print(\"done\")\n}\n").unwrap();
+
+    for target in ["bash", "posix"] {
+        let out = dir.path().join(format!("g_{}.sh", target));
+        Command::new(env!("CARGO_BIN_EXE_sh2c"))
+            .arg("--target")
+            .arg(target)
+            .arg("--sandbox")
+            .arg(&manifest)
+            .arg("--sandbox-guard")
+            .arg("-o")
+            .arg(&out)
+            .arg(&src)
+            .assert()
+            .success();
+        let shell = if target == "bash" { "bash" } else { "sh" };
+
+        // `true` is declared, so computing it at runtime is allowed.
+        Command::new(shell)
+            .arg(&out)
+            .arg("true")
+            .assert()
+            .success()
+            .stdout("done\n");
+
+        Command::new(shell)
+            .arg(&out)
+            .arg("echo")
+            .assert()
+            .code(126)
+            .stdout("")
+            .stderr(predicate::str::contains("Error: sandbox: command not declared in manifest: echo"));
+    }
+}
+
+#[test]
+fn sandbox_guard_requires_manifest() {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .arg("--sandbox-guard")
+        .arg("tests/fixtures/cli_audit_quoting_clean.sh2")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("error: --sandbox-guard requires --sandbox"));
+}
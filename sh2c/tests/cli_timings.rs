@@ -0,0 +1,40 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+#[test]
+fn timings_reports_each_stage_on_stderr() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("main.sh2");
+    fs::write(&script, "func main() {\n    print(\"hi\")\n}\n").unwrap();
+
+    let plain = sh2c().arg(&script).output().unwrap();
+    let timed = sh2c().arg("--timings").arg(&script).output().unwrap();
+    assert!(timed.status.success());
+    // The compiled script is unchanged; the report goes to stderr only.
+    assert_eq!(timed.stdout, plain.stdout);
+
+    let stderr = String::from_utf8(timed.stderr).unwrap();
+    assert!(stderr.starts_with("timings:\n"), "{}", stderr);
+    for stage in ["lex", "parse", "check", "lower", "codegen", "total"] {
+        let line = stderr
+            .lines()
+            .find(|l| l.split_whitespace().next() == Some(stage))
+            .unwrap_or_else(|| panic!("missing {} in:\n{}", stage, stderr));
+        assert!(line.trim_end().ends_with(" ms"), "{}", line);
+    }
+}
+
+#[test]
+fn timings_are_not_printed_when_compilation_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("bad.sh2");
+    fs::write(&script, "func main() {\n    let = 1\n}\n").unwrap();
+
+    let out = sh2c().arg("--timings").arg(&script).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    assert!(!String::from_utf8_lossy(&out.stderr).contains("timings:"));
+}
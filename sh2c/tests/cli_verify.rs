@@ -0,0 +1,63 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+fn project(src: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("main.sh2"), src).unwrap();
+    dir
+}
+
+#[test]
+fn portable_script_agrees_across_targets() {
+    let dir = project(
+        "func main() {\n    for line in stdin_lines() {\n        print(arg(1) & \": \" & line)\n    }\n    exit(3)\n}\n",
+    );
+    fs::write(dir.path().join("smoke.txt"), "one\ntwo\n").unwrap();
+    let out = sh2c()
+        .current_dir(dir.path())
+        .args(["verify", "--stdin", "smoke.txt", "main.sh2", "--", "x y"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert_eq!(out.status.code(), Some(0), "{}", stdout);
+    assert!(stdout.contains("bash (bash): exit 3, 2 line(s) of output\n"), "{}", stdout);
+    assert!(stdout.contains("posix (sh): exit 3, 2 line(s) of output\n"), "{}", stdout);
+    assert!(stdout.ends_with("verify: 2 run(s) agree\n"), "{}", stdout);
+}
+
+#[test]
+fn shell_specific_output_is_reported() {
+    let dir = project("func main() {\n    sh(\"echo ${BASH_VERSION:+bash}\")\n}\n");
+    let out = sh2c().current_dir(dir.path()).args(["verify", "main.sh2"]).output().unwrap();
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert_eq!(out.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains(r#"diverges: posix (sh): stdout differs from bash (bash): line 1: "bash" vs """#),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn single_target_and_compile_errors() {
+    let dir = project("func main() {\n    print(missing)\n}\n");
+    let out = sh2c().current_dir(dir.path()).args(["verify", "--targets", "posix", "main.sh2"]).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "posix (sh): does not compile\n");
+    assert!(String::from_utf8_lossy(&out.stderr).contains("[E0200]"));
+}
+
+#[test]
+fn verify_rejects_bad_arguments() {
+    for (args, msg) in [
+        (&["verify"][..], "verify requires a script"),
+        (&["verify", "--targets", "zsh", "main.sh2"][..], "Invalid target: zsh"),
+        (&["verify", "a.sh2", "b.sh2"][..], "unexpected argument for verify: b.sh2"),
+    ] {
+        sh2c().args(args).assert().code(1).stderr(predicates::str::contains(msg));
+    }
+}
@@ -0,0 +1,69 @@
+use sh2c::codegen::{self, TargetShell};
+use sh2c::{lexer, lower, parser};
+
+const SRC: &str = r#"
+func first(csv) {
+    let parts = split(csv, ",")
+    for p in parts {
+        print(p)
+        return
+    }
+}
+
+func shout(s) {
+    print(s & "!")
+}
+
+func main() {
+    shout("hi")
+}
+"#;
+
+fn library(src: &str, target: TargetShell) -> Result<String, String> {
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = lexer::lex(&sm, src).map_err(|d| d.format(None))?;
+    let mut program = parser::parse(&tokens, &sm, "lib.sh2").map_err(|d| d.format(None))?;
+    program.source_maps.insert("lib.sh2".to_string(), sm);
+    let opts = lower::LowerOptions { include_diagnostics: true, diag_base_dir: None, target, debug: false, profile: false };
+    let ir = lower::lower_with_options(program, &opts).map_err(|e| e.message)?;
+    codegen::emit_library(&ir, codegen::CodegenOptions { target, include_diagnostics: true }).map_err(|e| e.message)
+}
+
+fn run_sourced(shell: &str, lib: &str, body: &str) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lib.sh");
+    std::fs::write(&path, lib).unwrap();
+    let script = format!(". '{}'\n{}", path.display(), body);
+    let out = std::process::Command::new(shell).arg("-c").arg(script).output().unwrap();
+    assert!(out.status.success(), "{}: {}", shell, String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn library_defines_functions_without_running_anything() {
+    let lib = library(SRC, TargetShell::Bash).unwrap();
+    assert!(!lib.contains("main \"$@\""), "{}", lib);
+    assert!(!lib.contains("trap "), "{}", lib);
+    // Only functions that need a helper group ask for it.
+    assert!(lib.contains("first() {\n  __sh2_lib_load check split\n"), "{}", lib);
+    assert!(lib.contains("shout() {\n  __sh2_lib_load check\n"), "{}", lib);
+}
+
+#[test]
+fn helpers_are_defined_on_first_use() {
+    for (target, shell, probe) in [
+        (TargetShell::Bash, "bash", "declare -F __sh2_split >/dev/null && echo defined || echo absent"),
+        (TargetShell::Posix, "sh", "command -v __sh2_split >/dev/null && echo defined || echo absent"),
+    ] {
+        let lib = library(SRC, target).unwrap();
+        let body = format!("{probe}\nshout hey\n{probe}\nfirst 'a,b'\n{probe}\n", probe = probe);
+        assert_eq!(run_sourced(shell, &lib, &body), "absent\nhey!\nabsent\na\ndefined\n", "{}", shell);
+    }
+}
+
+#[test]
+fn library_rejects_parse_args() {
+    let src = "func opts() {\n    let a = parse_args()\n}\nfunc main() {\n    opts()\n}\n";
+    let err = library(src, TargetShell::Bash).unwrap_err();
+    assert!(err.contains("parse_args() in library function 'opts'"), "{}", err);
+}
@@ -0,0 +1,106 @@
+use assert_cmd::Command;
+use sh2c::codegen::{self, CodegenOptions, TargetShell};
+use sh2c::{lexer, lower, parser};
+use std::fs;
+
+const SRC: &str = r#"
+func greet(name) {
+    print("hello " & name)
+}
+
+func main() {
+    let parts = split("a,b", ",")
+    for p in parts {
+        greet(p)
+    }
+    let opts = parse_args()
+}
+"#;
+
+fn lowered(target: TargetShell) -> Vec<sh2c::ir::Function> {
+    let sm = sh2c::span::SourceMap::new(SRC.to_string());
+    let tokens = lexer::lex(&sm, SRC).unwrap();
+    let mut program = parser::parse(&tokens, &sm, "main.sh2").unwrap();
+    program.source_maps.insert("main.sh2".to_string(), sm);
+    let opts = lower::LowerOptions { include_diagnostics: true, diag_base_dir: None, target, debug: false, profile: false };
+    lower::lower_with_options(program, &opts).unwrap()
+}
+
+#[test]
+fn writer_output_matches_in_memory_output() {
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let ir = lowered(target);
+        let opts = CodegenOptions { target, include_diagnostics: true };
+        let mut streamed = Vec::new();
+        codegen::emit_to_writer(&mut streamed, &ir, opts).unwrap();
+        let built = codegen::emit_with_options_checked(&ir, opts).unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), built, "{} output differs", target);
+    }
+}
+
+#[test]
+fn write_errors_are_io_errors() {
+    struct Full;
+    impl std::io::Write for Full {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::StorageFull, "disk full"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let ir = lowered(TargetShell::Bash);
+    let err = codegen::emit_to_writer(&mut Full, &ir, CodegenOptions::default()).unwrap_err();
+    assert!(matches!(err, codegen::EmitError::Io(_)), "{}", err);
+}
+
+#[test]
+fn cli_streams_to_file_without_leftovers() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("main.sh2"), SRC).unwrap();
+    let stdout = Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir.path())
+        .arg("main.sh2")
+        .output()
+        .unwrap()
+        .stdout;
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir.path())
+        .args(["-o", "main.sh", "main.sh2"])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(fs::read(dir.path().join("main.sh")).unwrap(), stdout);
+    let mut names: Vec<_> = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+    names.sort();
+    assert_eq!(names, ["main.sh", "main.sh2"]);
+}
+
+#[test]
+fn cli_reports_unwritable_output() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("main.sh2"), SRC).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir.path())
+        .args(["-o", "missing/main.sh", "main.sh2"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("Failed to write to missing/main.sh"));
+}
+
+#[test]
+fn cli_prints_nothing_when_codegen_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("main.sh2"),
+        "func main() {\n    print(\"start\")\n    let r = capture(run(\"x\"), allow_fail=true) & \"a\"\n}\n",
+    )
+    .unwrap();
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir.path())
+        .arg("main.sh2")
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr(predicates::str::contains("[E0303]"));
+}
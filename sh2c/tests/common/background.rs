@@ -0,0 +1,47 @@
+//! Helpers for fixtures that start background jobs.
+//!
+//! A job that is left running keeps the script's stdout open, so `output()`
+//! blocks until it ends; `run_fixture` turns that wait into a failure.
+
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+/// Compiles `tests/fixtures/<name>.sh2` with the `sh2c` binary; returns the
+/// script, or sh2c's stderr.
+pub fn compile_fixture(name: &str, target: &str) -> Result<String, String> {
+    let fixture_path = format!("tests/fixtures/{}.sh2", name);
+    let output = Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .args(["--target", target, &fixture_path])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .map_err(|e| format!("Failed to run sh2c: {}", e))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
+
+/// `(target, shell)` pairs to run under: bash, plus dash when it is installed.
+pub fn shells() -> Vec<(&'static str, &'static str)> {
+    let mut shells = vec![("bash", "bash")];
+    if Command::new("dash").args(["-c", "exit 0"]).status().is_ok() {
+        shells.push(("posix", "dash"));
+    }
+    shells
+}
+
+/// Runs the compiled fixture, failing if it took long enough to have waited for a `sleep 30`.
+pub fn run_fixture(name: &str, target: &str, shell: &str) -> Output {
+    let script = compile_fixture(name, target).expect("compile failed");
+    let start = Instant::now();
+    let output = Command::new(shell).args(["-c", &script]).output().expect("run failed");
+    assert!(
+        start.elapsed() < Duration::from_secs(10),
+        "{} ({}) left a background process running",
+        name,
+        shell
+    );
+    output
+}
@@ -17,6 +17,8 @@ pub fn try_compile_to_shell(src: &str, target: TargetShell) -> Result<String, St
         include_diagnostics: true,
         diag_base_dir: Some(std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))),
         target,
+        debug: false,
+        profile: false,
     };
 
     // Use formatted diagnostics for better error messages
@@ -37,6 +39,8 @@ pub fn try_compile_path_to_shell(path: &Path, target: TargetShell) -> Result<Str
         include_diagnostics: true,
         diag_base_dir: Some(std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))),
         target,
+        debug: false,
+        profile: false,
     };
     
     let ir = lower::lower_with_options(program, &opts).map_err(|e| e.message)?;
@@ -66,7 +70,32 @@ pub fn compile_to_shell(src: &str, target: TargetShell) -> String {
         include_diagnostics: true,
         diag_base_dir: Some(std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))),
         target,
+        debug: false,
+        profile: false,
     };
     let ir = lower::lower_with_options(program, &opts).expect("Lowering failed");
     codegen::emit_with_options(&ir, codegen::CodegenOptions { target, include_diagnostics: true }).expect("Codegen failed")
 }
+
+/// Parses `src` and returns the taint analysis diagnostics (no lowering).
+pub fn taint_warnings(src: &str) -> Vec<String> {
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = lexer::lex(&sm, src).unwrap_or_else(|d| panic!("{}", d.format(None)));
+    let mut program = parser::parse(&tokens, &sm, "inline_test").unwrap_or_else(|d| panic!("{}", d.format(None)));
+    program.source_maps.insert("inline_test".to_string(), sm);
+    sh2c::taint::check_tainted_sh(&program, None)
+}
+
+/// Runs `sh2c --check` on `src` saved as `main.sh2`; asserts it succeeds and
+/// returns its stderr, where the warnings go.
+pub fn check_warnings(src: &str) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("main.sh2"), src).unwrap();
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir.path())
+        .args(["--check", "main.sh2"])
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stderr).unwrap()
+}
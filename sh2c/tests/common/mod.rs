@@ -15,6 +15,9 @@ pub use strip_spans::*;
 mod compile;
 pub use compile::*;
 
+mod background;
+pub use background::*;
+
 fn crate_root() -> PathBuf {
     Path::new(env!("CARGO_MANIFEST_DIR")).to_path_buf()
 }
@@ -416,28 +419,33 @@ pub fn compile_and_run_err(fixture_name: &str, target: TargetShell) -> (String,
     (stdout, stderr)
 }
 
-pub fn run_test_in_targets(name: &str, src: &str, expected_stdout: &str) {
+/// Compiles and runs `src` for bash and then POSIX sh, panicking if either run
+/// fails; returns `(script, stdout, stderr)` for each target in that order.
+pub fn run_in_targets(src: &str) -> Vec<(String, String, String)> {
     let targets = [
         (TargetShell::Bash, "bash"),
         (TargetShell::Posix, "sh"),
     ];
 
-    for (target, shell_bin) in targets {
-        // Compile
-        // Note: compile_to_shell panics on error, which is fine for tests expected to pass
-        let shell_script = compile_to_shell(src, target);
-
-        // Execute
-        // We use run_shell_script which handles temp dir creation
-        let (stdout, stderr, status) = run_shell_script(&shell_script, shell_bin, &[], &[], None, None);
-
-        if status != Some(0) {
-            panic!(
-                "Execution failed for {} ({:?})\nStatus: {:?}\nStdout: {}\nStderr: {}\nScript:\n{}",
-                name, target, status, stdout, stderr, shell_script
-            );
-        }
+    targets
+        .into_iter()
+        .map(|(target, shell_bin)| {
+            // Note: compile_to_shell panics on error, which is fine for tests expected to pass
+            let shell_script = compile_to_shell(src, target);
+            let (stdout, stderr, status) = run_shell_script(&shell_script, shell_bin, &[], &[], None, None);
+            if status != Some(0) {
+                panic!(
+                    "Execution failed ({:?})\nStatus: {:?}\nStdout: {}\nStderr: {}\nScript:\n{}",
+                    target, status, stdout, stderr, shell_script
+                );
+            }
+            (shell_script, stdout, stderr)
+        })
+        .collect()
+}
 
+pub fn run_test_in_targets(name: &str, src: &str, expected_stdout: &str) {
+    for ((_, stdout, _), target) in run_in_targets(src).into_iter().zip([TargetShell::Bash, TargetShell::Posix]) {
         assert_eq!(
             stdout.trim(),
             expected_stdout.trim(),
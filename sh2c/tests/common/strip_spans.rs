@@ -8,11 +8,28 @@ pub fn strip_spans_program(p: &mut ast::Program) {
     for f in &mut p.functions {
         strip_spans_fn(f);
     }
+    for e in &mut p.externs {
+        e.span = Span::new(0, 0);
+        e.file = String::new();
+    }
+    for s in &mut p.top_level {
+        strip_spans_stmt(s);
+    }
+    for h in &mut p.hooks {
+        h.span = Span::new(0, 0);
+        h.file = String::new();
+        for s in &mut h.body {
+            strip_spans_stmt(s);
+        }
+    }
 }
 
 pub fn strip_spans_fn(f: &mut ast::Function) {
     f.span = Span::new(0, 0);
     f.file = String::new(); // Clear file path for comparison
+    for a in &mut f.attrs {
+        a.span = Span::new(0, 0);
+    }
     for s in &mut f.body {
         strip_spans_stmt(s);
     }
@@ -84,7 +101,7 @@ pub fn strip_spans_stmt(s: &mut ast::Stmt) {
                     ast::PipeSegment::Run(call) => strip_spans_run_call(call),
                     ast::PipeSegment::Sudo(call) => strip_spans_run_call(call),
                     ast::PipeSegment::Block(stmts) => for s in stmts { strip_spans_stmt(s); },
-                    ast::PipeSegment::EachLine(ident, body) => {
+                    ast::PipeSegment::EachLine(ident, _, body) => {
                         ident.span = Span::new(0, 0);
                         for s in body { strip_spans_stmt(s); }
                     }
@@ -92,6 +109,11 @@ pub fn strip_spans_stmt(s: &mut ast::Stmt) {
             }
         }
         ast::StmtKind::Return(Some(e)) => strip_spans_expr(e),
+        ast::StmtKind::ReturnValues(values) => for v in values { strip_spans_expr(v); },
+        ast::StmtKind::LetValues { names, value, .. } => {
+            for n in names { n.span = Span::new(0, 0); }
+            strip_spans_expr(value)
+        }
         ast::StmtKind::Exit(Some(e)) => strip_spans_expr(e),
         ast::StmtKind::Cd { path } => strip_spans_expr(path),
         ast::StmtKind::Export { value: Some(v), .. } => strip_spans_expr(v),
@@ -109,7 +131,10 @@ pub fn strip_spans_stmt(s: &mut ast::Stmt) {
              for (_, v) in bindings { strip_spans_expr(v); }
              for s in body { strip_spans_stmt(s); }
         }
-        ast::StmtKind::WithCwd { path, body } => {
+        ast::StmtKind::WithLimits { body, .. } => {
+             for s in body { strip_spans_stmt(s); }
+        }
+        ast::StmtKind::WithCwd { path, body } | ast::StmtKind::WithUmask { mask: path, body } => {
              strip_spans_expr(path);
              for s in body { strip_spans_stmt(s); }
         }
@@ -149,6 +174,7 @@ pub fn strip_spans_stmt(s: &mut ast::Stmt) {
         ast::StmtKind::Case { expr, arms } => {
             strip_spans_expr(expr);
             for arm in arms {
+                for p in &mut arm.patterns { p.span = Span::new(0, 0); }
                 for s in &mut arm.body { strip_spans_stmt(s); }
             }
         },
@@ -167,6 +193,7 @@ pub fn strip_spans_expr(e: &mut ast::Expr) {
         ast::ExprKind::And(l, r) => { strip_spans_expr(l); strip_spans_expr(r); },
         ast::ExprKind::Or(l, r) => { strip_spans_expr(l); strip_spans_expr(r); },
         ast::ExprKind::Not(e) => strip_spans_expr(e),
+        ast::ExprKind::Spread(e) => strip_spans_expr(e),
         ast::ExprKind::Exists(e) => strip_spans_expr(e),
         ast::ExprKind::IsDir(e) => strip_spans_expr(e),
         ast::ExprKind::IsFile(e) => strip_spans_expr(e),
@@ -177,7 +204,7 @@ pub fn strip_spans_expr(e: &mut ast::Expr) {
         ast::ExprKind::IsNonEmpty(e) => strip_spans_expr(e),
         ast::ExprKind::BoolStr(e) => strip_spans_expr(e),
         ast::ExprKind::Len(e) => strip_spans_expr(e),
-        ast::ExprKind::Index { list, index } => { strip_spans_expr(list); strip_spans_expr(index); },
+        ast::ExprKind::Index { list, index, .. } => { strip_spans_expr(list); strip_spans_expr(index); },
         ast::ExprKind::Field { base, .. } => strip_spans_expr(base),
         ast::ExprKind::Join { list, sep } => { strip_spans_expr(list); strip_spans_expr(sep); },
         ast::ExprKind::Count(e) => strip_spans_expr(e),
@@ -200,4 +227,10 @@ pub fn strip_spans_run_call(c: &mut ast::RunCall) {
          o.span = Span::new(0, 0);
          strip_spans_expr(&mut o.value);
      }
+     if let Some(t) = &mut c.stderr {
+         t.span = Span::new(0, 0);
+         if let ast::RedirectOutputTarget::File { path, .. } = &mut t.node {
+             strip_spans_expr(path);
+         }
+     }
 }
@@ -20,3 +20,25 @@ fn parse_err_eof_after_keyword() {
 fn parse_err_unterminated_string() {
     assert_parse_error_matches_snapshot("parse_err_unterminated_string");
 }
+
+#[test]
+fn parse_err_recovered_multiple() {
+    assert_parse_error_matches_snapshot("parse_err_recovered_multiple");
+}
+
+#[test]
+fn parse_recovering_keeps_statements_around_errors() {
+    let src = include_str!("fixtures/parse_err_recovered_multiple.sh2");
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, "test").unwrap();
+    let (program, errors) = sh2c::parser::parse_recovering(&tokens, &sm, "test");
+
+    let lines: Vec<usize> = errors.iter().map(|e| sm.line_col(e.span.start).0).collect();
+    assert_eq!(lines, vec![2, 8, 10, 15]);
+
+    let names: Vec<&str> = program.functions.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["helper", "main"]);
+    // helper keeps `print("ok")`; main keeps `run("ls")`, the `if` and `helper()`.
+    assert_eq!(program.functions[0].body.len(), 1);
+    assert_eq!(program.functions[1].body.len(), 3);
+}
@@ -31,3 +31,15 @@ fn exec_runtime_fail_fast_posix() {
     // Test that failing commands stop execution in POSIX mode too
     assert_exec_matches_fixture_target("runtime_fail_fast", TargetShell::Posix);
 }
+
+#[test]
+fn exec_runtime_stack_trace() {
+    // Errors inside called functions list each caller and its call site.
+    assert_exec_matches_fixture_target("runtime_stack_trace", TargetShell::Bash);
+    assert_exec_matches_fixture_target("runtime_stack_trace", TargetShell::Posix);
+}
+
+#[test]
+fn codegen_runtime_stack_trace() {
+    assert_codegen_matches_snapshot("runtime_stack_trace");
+}
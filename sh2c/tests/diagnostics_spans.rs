@@ -54,6 +54,17 @@ fn diag_span_multiline_expr() {
 fn unknown_function_is_error() {
     assert_diag_output("unknown_function_is_error.sh2");
 }
+
+#[test]
+fn unknown_function_suggests_user_func() {
+    assert_diag_output("unknown_function_suggests_user_func.sh2");
+}
+
+#[test]
+fn unknown_function_suggests_builtin() {
+    assert_diag_output("unknown_function_suggests_builtin.sh2");
+}
+
 #[test]
 fn diag_span_let_redecl() {
     assert_diag_output("diag_span_let_redecl.sh2");
@@ -160,11 +160,15 @@ fn feature_matrix_evidence_exists() {
     // Gather all test files
     let sh2c_tests = manifest_dir.join("tests");
     let sh2do_tests = repo_root.join("sh2do/tests");
+    let build_tests = repo_root.join("sh2c-build/tests");
+    let macro_tests = repo_root.join("sh2c-macros/tests");
     let fixtures_dir = manifest_dir.join("tests/fixtures");
     
     let mut all_rs: HashSet<String> = HashSet::new();
     all_rs.extend(gather_files_with_suffix(&sh2c_tests, ".rs"));
     all_rs.extend(gather_files_with_suffix(&sh2do_tests, ".rs"));
+    all_rs.extend(gather_files_with_suffix(&build_tests, ".rs"));
+    all_rs.extend(gather_files_with_suffix(&macro_tests, ".rs"));
     
     let all_sh2 = gather_files_with_suffix(&fixtures_dir, ".sh2");
     
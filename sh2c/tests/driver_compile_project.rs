@@ -0,0 +1,67 @@
+use sh2c::driver::{self, CompileOptions};
+use std::fs;
+use std::path::PathBuf;
+
+fn project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("lib")).unwrap();
+    fs::write(dir.path().join("lib/util.sh2"), "func shout(s) {\n    print(s & \"!\")\n}\n").unwrap();
+    fs::write(dir.path().join("lib/more.sh2"), "import \"util.sh2\" as u\nfunc twice(s) {\n    u.shout(s)\n    u.shout(s)\n}\n").unwrap();
+    fs::write(dir.path().join("one.sh2"), "import \"lib/util.sh2\" as u\nfunc main() {\n    u.shout(\"one\")\n}\n").unwrap();
+    fs::write(dir.path().join("two.sh2"), "import \"lib/more.sh2\" as m\nfunc main() {\n    m.twice(\"two\")\n}\n").unwrap();
+    fs::write(dir.path().join("bad.sh2"), "import \"lib/util.sh2\" as u\nfunc main() {\n    print(nope)\n}\n").unwrap();
+    dir
+}
+
+#[test]
+fn artifacts_describe_each_input() {
+    let dir = project();
+    let root = fs::canonicalize(dir.path()).unwrap();
+    let inputs: Vec<PathBuf> = ["one.sh2", "two.sh2", "bad.sh2"].iter().map(|f| dir.path().join(f)).collect();
+    let options = CompileOptions { out_dir: Some(dir.path().join("build")), ..Default::default() };
+    let artifacts = driver::compile_project(&inputs, &options);
+    assert_eq!(artifacts.len(), 3);
+
+    let [one, two, bad] = &artifacts[..] else { unreachable!() };
+    assert!(one.ok() && two.ok());
+    assert_eq!(one.script, None);
+    assert_eq!(one.entry, inputs[0]);
+    assert_eq!(one.output.as_deref(), Some(dir.path().join("build/one.sh").as_path()));
+    assert_eq!(one.size as u64, fs::metadata(dir.path().join("build/one.sh")).unwrap().len());
+    assert_eq!(one.imports, [root.join("lib/util.sh2")]);
+    assert_eq!(two.imports, [root.join("lib/more.sh2"), root.join("lib/util.sh2")]);
+
+    let err = bad.error.as_ref().expect("bad.sh2 should fail");
+    assert_eq!(err.code, 2);
+    assert!(err.msg.contains("undefined variable 'nope'"), "{}", err.msg);
+    assert_eq!(bad.output, None);
+    assert_eq!(bad.size, 0);
+    assert_eq!(bad.imports, [root.join("lib/util.sh2")]);
+    assert!(!dir.path().join("build/bad.sh").exists());
+}
+
+#[test]
+fn without_out_dir_nothing_is_written() {
+    let dir = project();
+    let input = dir.path().join("one.sh2");
+    let artifacts = driver::compile_project(std::slice::from_ref(&input), &CompileOptions::default());
+    assert!(artifacts[0].ok());
+    assert_eq!(artifacts[0].output, None);
+    let single = driver::compile_file(&input, CompileOptions::default()).ok().unwrap();
+    assert_eq!(artifacts[0].size, single.len());
+    assert_eq!(artifacts[0].script.as_deref(), Some(single.as_str()));
+    assert!(!dir.path().join("one.sh").exists());
+}
+
+#[test]
+fn clashing_outputs_are_reported() {
+    let dir = project();
+    fs::write(dir.path().join("lib/one.sh2"), "func main() {\n    print(\"lib\")\n}\n").unwrap();
+    let inputs = [dir.path().join("one.sh2"), dir.path().join("lib/one.sh2")];
+    let options = CompileOptions { out_dir: Some(dir.path().join("build")), ..Default::default() };
+    let artifacts = driver::compile_project(&inputs, &options);
+    assert!(artifacts[0].ok());
+    let err = artifacts[1].error.as_ref().unwrap();
+    assert_eq!(err.code, 1);
+    assert!(err.msg.contains("would overwrite"), "{}", err.msg);
+}
@@ -49,6 +49,23 @@ fn test_textmate_grammar_valid() {
 }
 
 
+#[test]
+fn test_textmate_grammar_is_generated() {
+    let path = workspace_root()
+        .join("editors/vscode/syntaxes/sh2.tmLanguage.json");
+
+    let content = fs::read_to_string(&path)
+        .expect("Failed to read editors/vscode/syntaxes/sh2.tmLanguage.json");
+
+    // Regenerate with: sh2c emit-syntax tmLanguage -o editors/vscode/syntaxes/sh2.tmLanguage.json
+    assert_eq!(
+        content,
+        sh2c::highlight::tm_language(),
+        "sh2.tmLanguage.json is out of date with lang_spec"
+    );
+}
+
+
 #[test]
 fn test_ebnf_grammar_exists() {
     let path = workspace_root()
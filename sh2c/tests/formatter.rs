@@ -14,7 +14,14 @@ fn test_formatter_suites() {
         "fmt_redirect_heredoc",
         "fmt_chain_indent",
         "fmt_capture_pipe",
-        "fmt_misc_exprs"
+        "fmt_misc_exprs",
+        "fmt_attributes",
+        "fmt_sh_bridge",
+        "fmt_return_values",
+        "fmt_hooks",
+        "fmt_init",
+        "fmt_script",
+        "fmt_doc_comments"
     ];
     for case in cases {
         eprintln!("Testing formatter case: {}", case);
@@ -0,0 +1,46 @@
+use sh2c::codegen::TargetShell;
+use sh2c::driver::{self, CompileOptions};
+use sh2c::playground::compile_str;
+use std::fs;
+
+const HELLO: &str = "func main() {\n    let name = \"world\"\n    print($\"hello {name}\")\n}\n";
+
+#[test]
+fn matches_the_file_compiler() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("main.sh2");
+    fs::write(&path, HELLO).unwrap();
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let expected = driver::compile_file(&path, CompileOptions { target, ..Default::default() }).unwrap();
+        assert_eq!(compile_str(HELLO, target).unwrap(), expected);
+    }
+}
+
+#[test]
+fn errors_name_the_playground_file() {
+    let err = compile_str("func main() {\n    print(missing)\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("main.sh2:2:"), "{}", err);
+
+    let err = compile_str("func main() {\n    let x = \n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("main.sh2:"), "{}", err);
+}
+
+#[test]
+fn imports_are_not_read_from_disk() {
+    let err = compile_str("import \"lib.sh2\"\nfunc main() {\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("Failed to resolve path lib.sh2"), "{}", err);
+}
+
+#[test]
+fn rejects_what_the_file_compiler_rejects() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("main.sh2");
+    for src in [
+        "func main() {\n    print(missing)\n}\n",
+        "func main() {\n    let x = 1\n    let x = 2\n    print(x)\n}\n",
+    ] {
+        fs::write(&path, src).unwrap();
+        let expected = driver::compile_file(&path, CompileOptions::default()).unwrap_err().msg;
+        assert_eq!(compile_str(src, TargetShell::Bash).unwrap_err(), expected);
+    }
+}
@@ -8,3 +8,17 @@ fn test_arg_dynamic_index_posix() {
     // This ensures no "unsupported" error is raised and logic works.
     assert_exec_matches_fixture_target("arg_dynamic_index", TargetShell::Posix);
 }
+
+#[test]
+fn test_arg_dynamic_index_posix_helper_has_no_eval() {
+    let src = r#"
+        func main() {
+            let i = 1
+            print(arg(i))
+        }
+    "#;
+    let sh = common::try_compile_to_shell(src, TargetShell::Posix).expect("compile");
+    let helper = sh.lines().find(|l| l.starts_with("__sh2_arg_by_index()")).expect("helper emitted");
+    assert!(!helper.contains("eval"), "helper still uses eval: {}", helper);
+    assert!(helper.contains("shift $((idx - 1))"), "unexpected helper: {}", helper);
+}
@@ -21,6 +21,8 @@ fn test_arg_zero_lowers_to_dynamic() {
         include_diagnostics: false,
         diag_base_dir: None,
         target: sh2c::codegen::TargetShell::Bash,
+        debug: false,
+        profile: false,
     };
     
     let mut prog_with_maps = program;
@@ -73,6 +75,8 @@ fn test_arg_one_lowers_to_fast_path() {
         include_diagnostics: false,
         diag_base_dir: None,
         target: sh2c::codegen::TargetShell::Bash,
+        debug: false,
+        profile: false,
     };
     
     let mut prog_with_maps = program;
@@ -31,7 +31,7 @@ fn parse_argc_argv0_basic() {
     // main calls f(...)
     assert_eq!(func_main.body.len(), 1);
     if let Stmt {
-        node: StmtKind::Call { name, args },
+        node: StmtKind::Call { name, args, .. },
         ..
     } = &func_main.body[0]
     {
@@ -0,0 +1,36 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_awk_basic() {
+    assert_exec_matches_fixture_target("awk_basic", TargetShell::Bash);
+    assert_exec_matches_fixture_target("awk_basic", TargetShell::Posix);
+}
+
+#[test]
+fn codegen_awk_basic() {
+    assert_codegen_matches_snapshot("awk_basic");
+}
+
+#[test]
+fn awk_program_is_validated() {
+    let cases = [
+        (r#"awk(p, "x")"#, "awk() program must be a plain string literal"),
+        (r#"awk("{ print $1 ", "x")"#, "awk() program has an unclosed '{'"),
+        (r#"awk("]", "x")"#, "awk() program has an unmatched ']'"),
+        (r#"awk("{ print \"a }", "x")"#, "awk() program has an unterminated string"),
+        (r#"awk("$1 ~ /ab", "x")"#, "awk() program has an unterminated regular expression"),
+        (r#"awk("{ print }")"#, "awk() requires exactly 2 arguments (program, input)"),
+    ];
+    for (call, expected) in cases {
+        let src = format!("func main() {{\n    let p = \"x\"\n    print({})\n}}\n", call);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).expect_err(call);
+        assert!(err.contains(expected), "{}: {}", call, err);
+    }
+}
+
+#[test]
+fn awk_division_is_not_a_regex() {
+    let src = "func main() {\n    print(awk(\"{ print $1 / 2 } # halve\", \"8\"))\n}\n";
+    run_test_in_targets("awk_division", src, "4");
+}
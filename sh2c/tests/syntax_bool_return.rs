@@ -0,0 +1,93 @@
+mod common;
+use common::*;
+
+#[test]
+fn bool_functions_are_tested_by_exit_status() {
+    let src = r#"func is_ready(path) -> bool {
+    if is_file(path) {
+        return true
+    }
+    return exists(path & ".ready")
+}
+func both(a, b) -> bool {
+    return is_ready(a) && is_ready(b)
+}
+func never() -> bool {
+    let x = "1"
+}
+func main() {
+    if is_ready("/etc/passwd") {
+        print("ready")
+    }
+    if !is_ready("/nope") {
+        print("not ready")
+    }
+    let ok = both("/etc/passwd", "/nope")
+    if !ok {
+        print("not both")
+    }
+    while never() {
+        print("loop")
+    }
+    print(bool_str(is_ready("/etc/passwd")))
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        assert!(script.contains("if is_ready '/etc/passwd'; then"), "{}", script);
+        assert!(!script.contains("printf '%s' 1"), "{}", script);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "ready\nnot ready\nnot both\ntrue\n");
+    }
+}
+
+#[test]
+fn error_traces_stay_correct_after_a_condition_call() {
+    let src = r#"func ok() -> bool {
+    return true
+}
+func boom() {
+    run("false")
+}
+func main() {
+    if ok() {
+        print("ok")
+    }
+    boom()
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Posix);
+    let (stdout, stderr, code) = run_shell_script(&script, "sh", &[], &[], None, None);
+    assert_eq!(code, Some(1));
+    assert_eq!(stdout, "ok\n");
+    assert!(stderr.ends_with("  boom at inline_test:5:5\n  main at inline_test:11:5\n"), "{}", stderr);
+}
+
+#[test]
+fn bool_declarations_are_checked() {
+    for (src, msg) in [
+        ("func f() -> int {\n    return 1\n}\nfunc main() {\n}\n", "Expected `bool` after `->`"),
+        (
+            "func f() -> bool {\n    return\n}\nfunc main() {\n    if f() {\n        print(\"y\")\n    }\n}\n",
+            "`return` in a `-> bool` function needs a value",
+        ),
+        (
+            "func f() -> bool {\n    return true\n}\nfunc main() {\n    f()\n}\n",
+            "function 'f' returns a bool; use it in a condition",
+        ),
+        (
+            "func f() -> bool {\n    return true, false\n}\nfunc main() {\n}\n",
+            "a `-> bool` function cannot return several values",
+        ),
+        (
+            "@cache\nfunc f() -> bool {\n    return true\n}\nfunc main() {\n}\n",
+            "@cache function 'f' cannot be declared `-> bool`",
+        ),
+        ("@pure\nfunc f() -> bool {\n    return true\n}\nfunc main() {\n}\n", "@pure function 'f' cannot use `-> bool`"),
+    ] {
+        let err = try_compile_to_shell(src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}", err);
+    }
+}
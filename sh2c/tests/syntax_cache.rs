@@ -0,0 +1,92 @@
+mod common;
+use common::*;
+
+#[test]
+fn cached_function_runs_once_per_argument_list() {
+    let src = r#"@cache
+func version(tool) {
+    run("sh", "-c", "echo probe >&2")
+    return tool & "-1.2"
+}
+func main() {
+    let a = version("git")
+    for i in [1, 2] {
+        print(version("git") & " " & version("tar"))
+    }
+    if version("git") == a {
+        print("same")
+    }
+}
+"#;
+    for (_, stdout, stderr) in run_in_targets(src) {
+        assert_eq!(stdout, "git-1.2 tar-1.2\ngit-1.2 tar-1.2\nsame\n");
+        assert_eq!(stderr, "probe\nprobe\n");
+    }
+}
+
+#[test]
+fn statement_calls_replay_output_exactly() {
+    let src = r#"@cache
+func banner(name) {
+    run("sh", "-c", "echo probe >&2")
+    print("== " & name & " ==")
+    print("")
+}
+func main() {
+    banner("a b")
+    banner("a b")
+    banner("a")
+}
+"#;
+    for (_, stdout, stderr) in run_in_targets(src) {
+        assert_eq!(stdout, "== a b ==\n\n== a b ==\n\n== a ==\n\n");
+        assert_eq!(stderr, "probe\nprobe\n");
+    }
+}
+
+#[test]
+fn failed_calls_are_not_cached() {
+    let src = r#"@cache
+func flaky(x) {
+    run("sh", "-c", "echo try >&2; exit 3")
+    return x
+}
+func main() {
+    try {
+        let v = flaky("1")
+    } catch {
+        print("first " & status())
+    }
+    try {
+        let v = flaky("1")
+    } catch {
+        print("second " & status())
+    }
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    let (stdout, stderr, code) = run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(stdout, "first 3\nsecond 3\n");
+    assert_eq!(stderr.matches("try\n").count(), 2, "{}", stderr);
+}
+
+#[test]
+fn cache_attribute_checks() {
+    let err = try_compile_to_shell("@cache(\"1m\")\nfunc f() {\n    return \"x\"\n}\nfunc main() {\n    print(f())\n}\n", TargetShell::Bash)
+        .unwrap_err();
+    assert!(err.contains("@cache"), "{}", err);
+
+    let plain = compile_to_shell("func f() {\n    return \"x\"\n}\nfunc main() {\n    print(f())\n}\n", TargetShell::Bash);
+    assert!(!plain.contains("__sh2_cache"), "{}", plain);
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("lib.sh2"), "@cache\nfunc f() {\n    return \"x\"\n}\n").unwrap();
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir.path())
+        .args(["--emit-lib", "lib.sh2"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("@cache on library function 'f'"));
+}
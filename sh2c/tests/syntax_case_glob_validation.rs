@@ -0,0 +1,64 @@
+mod common;
+use common::*;
+
+#[test]
+fn glob_classes_and_literal_patterns_match_on_both_targets() {
+    let src = r#"func main() {
+    for x in ["file7.txt", "fileA.txt", "a*b", "a-b", "Z", "q"] {
+        case x {
+            glob("file[0-9].txt") => { print(x & " digit") }
+            glob("file[!0-9].txt") => { print(x & " nondigit") }
+            literal("a*b") => { print(x & " star") }
+            glob("a[*]b") | glob("a?b") => { print(x & " a_b") }
+            glob("[[:upper:]]") => { print(x & " upper") }
+            glob("[^[:upper:]]") => { print(x & " lower") }
+            _ => { print(x & " none") }
+        }
+    }
+}
+"#;
+    for (target, shell) in [(TargetShell::Bash, "bash"), (TargetShell::Posix, "sh")] {
+        let script = compile_to_shell(src, target);
+        assert!(script.contains("'file'[0-9]'.txt')"), "{}", script);
+        assert!(script.contains("[![:upper:]])"), "{}", script);
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{}: {}", shell, stderr);
+        assert_eq!(
+            stdout,
+            "file7.txt digit\nfileA.txt nondigit\na*b star\na-b a_b\nZ upper\nq lower\n",
+            "{}",
+            shell
+        );
+    }
+}
+
+#[test]
+fn malformed_globs_are_rejected() {
+    for (pattern, msg) in [
+        ("glob(\"file[0-9.txt\")", "glob pattern \"file[0-9.txt\" has an unclosed `[`"),
+        ("glob(\"a]b\")", "glob pattern \"a]b\" has a `]` without a matching `[`"),
+        ("glob(\"x[]\")", "has an empty `[]` class"),
+        ("glob(\"*.txt \")", "glob pattern \"*.txt \" starts or ends with whitespace"),
+        ("glob(\"[a b]\")", "uses ' ' inside `[...]`"),
+        ("glob(\"[[:alpha]]\")", "has a malformed `[:class:]`"),
+        (
+            "\"*.txt\"",
+            "string case pattern \"*.txt\" contains glob characters that match literally; write glob(\"*.txt\") to match it as a pattern or literal(\"*.txt\") to match it exactly [E0104]",
+        ),
+    ] {
+        let src = format!("func main() {{\n    case arg(1) {{\n        {} => {{ print(\"x\") }}\n        _ => {{ print(\"y\") }}\n    }}\n}}\n", pattern);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", pattern, err);
+        assert!(err.contains("inline_test:3:"), "{}", err);
+    }
+}
+
+#[test]
+fn literal_patterns_round_trip_through_the_formatter() {
+    let src = "func main() {\n    case arg(1) {\n        literal(\"a*b\") | \"plain\" | literal(\"c\") => {\n            print(\"x\")\n        }\n    }\n}\n";
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    let formatted = sh2c::formatter::format_program(&program);
+    assert!(formatted.contains("literal(\"a*b\") | \"plain\" | \"c\" => {"), "{}", formatted);
+}
@@ -0,0 +1,55 @@
+mod common;
+use common::*;
+
+#[test]
+fn shadowed_patterns_warn_at_the_pattern() {
+    let stderr = check_warnings(
+        r#"func main() {
+    case arg(1) {
+        "start" | "run" => { print("a") }
+        glob("st*") => { print("b") }
+        "stop" | "run" => { print("c") }
+        glob("*.log") | glob("s?art") => { print("d") }
+        glob("app.*.log") | glob("*.log") => { print("e") }
+        _ => { print("f") }
+        "late" | _ => { print("g") }
+    }
+    case arg(2) {
+        glob("v[0-9]*") => { print("v") }
+        "v1.0" | glob("v2[.]*") | glob("v[!0-9]") => { print("w") }
+    }
+}
+"#,
+    );
+    assert!(stderr.contains("warning: main.sh2:5:9: case pattern \"stop\" is shadowed by glob(\"st*\") at line 4 and can never match [W0009]"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:5:18: duplicate case pattern \"run\": it already appears at line 3 [W0009]"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:7:9: case pattern glob(\"app.*.log\") is shadowed by glob(\"*.log\") at line 6"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:7:29: duplicate case pattern glob(\"*.log\"): it already appears at line 6"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:9:9: unreachable case pattern \"late\": the `_` at line 8 already matches everything"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:9:18: unreachable case pattern _"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:13:9: case pattern \"v1.0\" is shadowed by glob(\"v[0-9]*\") at line 12"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:13:18: case pattern glob(\"v2[.]*\") is shadowed by glob(\"v[0-9]*\") at line 12"), "{}", stderr);
+    assert_eq!(stderr.matches("[W0009]").count(), 8, "{}", stderr);
+}
+
+#[test]
+fn distinct_patterns_do_not_warn() {
+    let stderr = check_warnings(
+        r#"func main() {
+    case arg(1) {
+        glob("app.*.log") => { print("app") }
+        glob("*.log") => { print("log") }
+        glob("s?art") => { print("s") }
+        glob("st*") => { print("st") }
+        literal("a*") => { print("literal star") }
+        glob("file[0-9]") => { print("digit") }
+        glob("file[a-z]") => { print("letter") }
+        glob("a*") => { print("a") }
+        "" => { print("empty") }
+        _ => { print("other") }
+    }
+}
+"#,
+    );
+    assert_eq!(stderr, "");
+}
@@ -0,0 +1,55 @@
+mod common;
+use common::*;
+
+#[test]
+fn coalesce_chains_to_the_first_non_empty_value() {
+    let src = r#"func main() {
+    let name = arg(1) ?? env.SH2_TEST_NAME ?? "anon"
+    print("hi " & name)
+    print("x" & (arg(2) ?? "y") & "z")
+    if arg(1) ?? "n" == "n" {
+        print("no arg")
+    }
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "hi anon\nxyz\nno arg\n", "{:?}", target);
+        let env = [("SH2_TEST_NAME", "bob")];
+        let (stdout, _, _) = run_shell_script(&script, shell, &env, &[], None, None);
+        assert_eq!(stdout, "hi bob\nxyz\nno arg\n", "{:?}", target);
+        let (stdout, _, _) = run_shell_script(&script, shell, &env, &["al", "b"], None, None);
+        assert_eq!(stdout, "hi al\nxbz\n", "{:?}", target);
+    }
+
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
+
+#[test]
+fn literal_operands_fold_at_compile_time() {
+    let src = r#"func main() {
+    let x = arg(1)
+    print("" ?? "lit")
+    print("fixed" ?? x)
+    print(x ?? "")
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    assert!(!script.contains("__sh2_coalesce"), "{}", script);
+    let (stdout, stderr, code) = run_shell_script(&script, "bash", &[], &["v"], None, None);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(stdout, "lit\nfixed\nv\n");
+}
+
+#[test]
+fn lone_question_mark_suggests_coalesce() {
+    let err = try_compile_to_shell("func main() {\n    let x = arg(1) ? \"a\"\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("Unexpected character: ?"), "{}", err);
+    assert!(err.contains("help: use `a ?? b` for a fallback value"), "{}", err);
+}
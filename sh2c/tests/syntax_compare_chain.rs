@@ -0,0 +1,54 @@
+mod common;
+use common::*;
+
+#[test]
+fn chained_comparisons_check_every_pair() {
+    let src = r#"@pure
+func grade(n) {
+    return if 90 <= n <= 100 { "A" } elif 0 <= n < 90 { "B" } else { "?" }
+}
+
+func main() {
+    let x = arg(1)
+    if 0 <= x <= 100 {
+        print("in")
+    } else {
+        print("out")
+    }
+    let ok = 1 < x < len(arg(2)) + 5 <= 50
+    print(bool_str(ok))
+    print(grade(95) & grade(5) & grade(200))
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        assert!(!script.contains("grade"), "{}", script);
+        assert_eq!(script.matches("__sh2_len").count(), 2, "{}", script);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        for (args, expected) in [
+            (["3", "abcdef"], "in\ntrue\nAB?\n"),
+            (["50", "abc"], "in\nfalse\nAB?\n"),
+            (["-1", "abc"], "out\nfalse\nAB?\n"),
+            (["101", "abc"], "out\nfalse\nAB?\n"),
+        ] {
+            let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &args, None, None);
+            assert_eq!(code, Some(0), "{:?} {:?}: {}", target, args, stderr);
+            assert_eq!(stdout, expected, "{:?} {:?}", target, args);
+        }
+    }
+
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
+
+#[test]
+fn equality_does_not_chain() {
+    for cond in ["a == b == c", "a < b == c", "a == b < c"] {
+        let src = format!("func main() {{\n    let a = 1\n    let b = 1\n    let c = 1\n    if {} {{\n        print(\"x\")\n    }}\n}}\n", cond);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains("only `<`, `<=`, `>` and `>=` can be chained"), "{}: {}", cond, err);
+        assert!(err.contains("help: join the comparisons with `&&`"), "{}: {}", cond, err);
+    }
+}
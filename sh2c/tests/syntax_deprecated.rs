@@ -0,0 +1,74 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+const LIB: &str = r#"@deprecated("use greet()")
+func hello() {
+    greet()
+}
+
+@deprecated
+func shim() {
+    hello()
+}
+
+func greet() {
+    print("hi")
+}
+"#;
+
+fn check(dir: &tempfile::TempDir, main: &str) -> std::process::Output {
+    fs::write(dir.path().join("lib.sh2"), LIB).unwrap();
+    fs::write(dir.path().join("main.sh2"), main).unwrap();
+    sh2c().current_dir(dir.path()).args(["--check", "main.sh2"]).output().unwrap()
+}
+
+#[test]
+fn deprecated_calls_warn_at_each_call_site() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = check(
+        &dir,
+        "import \"lib.sh2\" as util\nimport \"lib.sh2\"\n\nfunc main() {\n    util.hello()\n    let x = hello()\n    shim()\n}\n",
+    );
+    assert!(out.status.success());
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("warning: main.sh2:5:5: call to deprecated function 'util.hello': use greet() [W0004]"), "{}", stderr);
+    assert!(stderr.contains("warning: main.sh2:6:13: call to deprecated function 'hello': use greet() [W0004]"), "{}", stderr);
+    assert!(stderr.contains("warning: main.sh2:7:5: call to deprecated function 'shim' [W0004]\n"), "{}", stderr);
+    // `shim` calling `hello` inside the library is not the caller's problem.
+    assert!(!stderr.contains("lib.sh2"), "{}", stderr);
+}
+
+#[test]
+fn deprecated_functions_still_compile_and_run() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("lib.sh2"), LIB).unwrap();
+    fs::write(dir.path().join("main.sh2"), "import \"lib.sh2\"\n\nfunc main() {\n    hello()\n}\n").unwrap();
+    let out = sh2c().current_dir(dir.path()).arg("main.sh2").output().unwrap();
+    assert!(out.status.success());
+    let script = dir.path().join("main.sh");
+    fs::write(&script, out.stdout).unwrap();
+    let run = std::process::Command::new("bash").arg(&script).output().unwrap();
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), "hi\n");
+}
+
+#[test]
+fn attribute_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let cases = [
+        ("@frobnicate\nfunc main() {}\n", "unknown attribute '@frobnicate'"),
+        ("@deprecated(\"a\", \"b\")\nfunc main() {}\n", "@deprecated takes at most 1 argument(s), got 2"),
+        ("@deprecated(x)\nfunc main() {}\n", "Attribute arguments must be string literals"),
+        ("@deprecated\n@deprecated\nfunc main() {}\n", "duplicate attribute '@deprecated'"),
+        ("@deprecated\nimport \"lib.sh2\"\nfunc main() {}\n", "Expected 'func' after attribute"),
+    ];
+    for (src, expected) in cases {
+        let out = check(&dir, src);
+        assert_eq!(out.status.code(), Some(2), "{}", src);
+        let stderr = String::from_utf8(out.stderr).unwrap();
+        assert!(stderr.contains(expected), "{}: {}", src, stderr);
+    }
+}
@@ -0,0 +1,70 @@
+mod common;
+use common::*;
+
+#[test]
+fn split_once_destructures_on_both_targets() {
+    let src = r#"func main() {
+    let (key, value) = split_once(arg(1), ": ")
+    print(key & "|" & value & "|")
+    let (head, tail) = split_once("a=b", "=")
+    let (ab, x) = split_once(head & tail & "-x", "-")
+    print(x & "," & ab)
+    let (a, b) = ["1", "2"]
+    let (a2, b2) = [b, a]
+    print(a2 & b2)
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &["name: sh2: lang"], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "name|sh2: lang|\nx,ab\n21\n", "{:?}", target);
+        let (stdout, _, _) = run_shell_script(&script, shell, &[], &["novalue"], None, None);
+        assert_eq!(stdout, "novalue||\nx,ab\n21\n", "{:?}", target);
+    }
+
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
+
+#[test]
+fn runtime_lists_are_checked_for_arity() {
+    let src = r#"func pair() {
+    return "l", "r"
+}
+
+func main() {
+    let (user, host) = split(arg(1), "@")
+    print(host & " " & user)
+    let (l, r) = pair()
+    print(l & r)
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    let (stdout, stderr, code) = run_shell_script(&script, "bash", &[], &["me@example.com"], None, None);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(stdout, "example.com me\nlr\n");
+    let (stdout, stderr, code) = run_shell_script(&script, "bash", &[], &["a@b@c"], None, None);
+    assert_eq!(code, Some(1));
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("Error: let (user, host): expected 2 values, got 3"), "{}", stderr);
+}
+
+#[test]
+fn destructuring_errors() {
+    for (stmt, msg) in [
+        ("let (a, b) = split_once(\"x\", \"=\", \"y\")", "split_once() requires exactly 2 arguments"),
+        ("let (a, b, c) = split_once(\"x\", \"=\")", "split_once() yields 2 values, but 3 names are given"),
+        ("let (a, b) = [\"1\", \"2\", \"3\"]", "list has 3 items, but 2 names are given"),
+        ("let (a, b) = \"ab\"", "`let (a, b)` needs a list, split_once(...) or a function returning 2 values"),
+        ("let (a) = [\"1\"]", "Expected at least two names in `let (...)`"),
+        ("print(split_once(\"a=b\", \"=\"))", "split_once() yields two values and must be bound via let"),
+    ] {
+        let src = format!("func main() {{\n    {}\n}}\n", stmt);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", stmt, err);
+    }
+}
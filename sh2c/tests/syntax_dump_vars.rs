@@ -0,0 +1,54 @@
+mod common;
+use common::*;
+
+fn dump(src: &str, target: TargetShell) -> (String, String) {
+    let script = compile_to_shell(src, target);
+    let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+    let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+    assert_eq!(code, Some(0), "{}", stderr);
+    (stdout, stderr)
+}
+
+#[test]
+fn dump_vars_lists_params_and_lets_in_order() {
+    let src = r#"func show(name, n) {
+    let items = ["a", "b c"]
+    let m = {"k": "v"}
+    let greeting = "hi " & name
+    dump_vars()
+    let later = 1
+}
+func main() {
+    show("bob", 3)
+    for i in [1, 2] { print(i) }
+    dump_vars()
+}
+"#;
+    let (stdout, stderr) = dump(src, TargetShell::Bash);
+    assert_eq!(stdout, "1\n2\n");
+    let stderr: Vec<&str> = stderr.lines().collect();
+    assert!(stderr[0].starts_with("dump_vars at ") && stderr[0].ends_with(":5:5"), "{:?}", stderr);
+    assert_eq!(
+        stderr[1..6],
+        ["  name      bob", "  n         3", "  items     [a, b c]", "  m         {k: v}", "  greeting  hi bob"]
+    );
+    assert!(stderr[6].ends_with(":11:5"), "{:?}", stderr);
+    assert_eq!(stderr[7..], ["  i  2"]);
+}
+
+#[test]
+fn dump_vars_posix_and_block_scoped_lets() {
+    let src = "func main() {\n    let a = \"one two\"\n    if true {\n        let b = 2\n    }\n    dump_vars()\n}\n";
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let (_, stderr) = dump(src, target);
+        assert!(stderr.ends_with("\n  a  one two\n  b  2\n"), "{}", stderr);
+    }
+}
+
+#[test]
+fn dump_vars_takes_no_arguments() {
+    let err = try_compile_to_shell("func main() {\n    dump_vars(1)\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("dump_vars() takes no arguments"), "{}", err);
+    let err = try_compile_to_shell("func main() {\n    let v = dump_vars()\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("is a statement"), "{}", err);
+}
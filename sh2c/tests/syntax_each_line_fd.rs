@@ -0,0 +1,70 @@
+mod common;
+use common::*;
+
+#[test]
+fn each_line_fd_leaves_stdin_to_the_body() {
+    let src = r#"func main() {
+    run("printf", "a\nb\n") | each_line(fd=3) f {
+        let answer = input("")
+        print(f & "=" & answer)
+    }
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    assert!(script.contains("read -r -u 3 "), "{}", script);
+    assert!(script.contains("done 3< <("), "{}", script);
+    let (stdout, stderr, code) = run_bash_script_with_stdin(&script, &[], &[], "yes\nno\n");
+    assert_eq!(code, 0, "{}", stderr);
+    assert_eq!(stdout, "a=yes\nb=no\n");
+}
+
+#[test]
+fn stdin_reads_inside_each_line_warn() {
+    let stderr = check_warnings(
+        "func main() {\n    run(\"ls\") | each_line f {\n        let a = input(\"? \")\n        if confirm(\"ok?\") {\n            print(a)\n        }\n        for l in stdin_lines() {\n            print(l)\n        }\n    }\n}\n",
+    );
+    assert!(stderr.contains("warning: main.sh2:3:17: stdin read inside each_line: input() consumes the lines meant for the loop; use `each_line(fd=3) line { ... }` to read them on a separate file descriptor [W0005]"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:4:12: stdin read inside each_line: confirm()"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:7:9: stdin read inside each_line: stdin_lines()"), "{}", stderr);
+}
+
+#[test]
+fn stdin_reads_with_their_own_input_do_not_warn() {
+    let stderr = check_warnings(
+        r#"func main() {
+    let name = input("name? ")
+    run("ls") | each_line(fd=3) f {
+        print(input("? "))
+    }
+    run("ls") | each_line h {
+        run("cat") | each_line g {
+            print(g)
+        }
+        with redirect { stdin: file("/dev/tty") } {
+            print(input("? "))
+        }
+    }
+}
+"#,
+    );
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn each_line_fd_is_checked_and_formatted() {
+    for (head, msg) in [
+        ("each_line(fd=2) f", "each_line fd must be an integer from 3 to 9"),
+        ("each_line(3) f", "each_line accepts only an fd option"),
+        ("each_line(fd=3, x=1) f", "each_line accepts only an fd option"),
+    ] {
+        let src = format!("func main() {{\n    run(\"ls\") | {} {{\n        print(f)\n    }}\n}}\n", head);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", head, err);
+    }
+
+    let src = "func main() {\n    run(\"ls\") | each_line(fd=4) f {\n        print(f)\n    }\n}\n";
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
@@ -0,0 +1,80 @@
+mod common;
+use common::*;
+
+#[test]
+fn escapes_decode_the_same_in_every_string_form() {
+    let src = r#"func main() {
+    let name = "x"
+    print("a\tb|caf\u{e9}|\u{1F600}")
+    print("\e[1mbold\e[0m")
+    print("cr\rlf")
+    print("keep \\$HOME \$HOME \{\}")
+    print($"{name}\t\u{e9}\{\}")
+    print("""tri\tple \u{e9}""")
+    print(r"C:\temp\data \n")
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        assert!(!script.contains('\x1b') && !script.contains('\r'), "{:?}: {}", target, script);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(
+            stdout,
+            "a\tb|café|😀\n\x1b[1mbold\x1b[0m\ncr\rlf\nkeep \\$HOME $HOME {}\nx\té{}\ntri\tple é\nC:\\temp\\data \\n\n",
+            "{:?}",
+            target
+        );
+    }
+
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    let formatted = sh2c::formatter::format_program(&program);
+    assert!(formatted.contains(r#"print("\e[1mbold\e[0m")"#), "{}", formatted);
+    let sm = sh2c::span::SourceMap::new(formatted.clone());
+    let tokens = sh2c::lexer::lex(&sm, &formatted).unwrap();
+    let reparsed = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&reparsed), formatted);
+}
+
+#[test]
+fn invalid_escapes_are_errors() {
+    for (body, msg) in [
+        (r#"print("C:\data")"#, "Unknown escape sequence `\\d` in string"),
+        (r#"print($"{1}\q")"#, "Unknown escape sequence `\\q` in string"),
+        (r#"print("""\x41""")"#, "Unknown escape sequence `\\x` in string"),
+        (r#"print("a\0b")"#, "shell strings cannot hold NUL bytes"),
+        (r#"print("\u{0}")"#, "shell strings cannot hold NUL bytes"),
+        (r#"print("\u41")"#, "Invalid unicode escape: expected `\\u{...}`"),
+        (r#"print("\u{1234567}")"#, "Invalid unicode escape: expected `\\u{...}`"),
+        (r#"print("\u{d800}")"#, "`\\u{d800}` is not a Unicode scalar value"),
+    ] {
+        let src = format!("func main() {{\n    {}\n}}\n", body);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", body, err);
+        assert!(err.contains("[E0004]"), "{}: {}", body, err);
+    }
+    let err = try_compile_to_shell("func main() {\n    print(\"\\d\")\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("help:") && err.contains("raw string r\"...\""), "{}", err);
+}
+
+#[test]
+fn spellings_from_before_strict_escapes() {
+    let fixture = |name: &str| repo_root().join(format!("sh2c/tests/fixtures/{}.sh2", name));
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_path_to_shell(&fixture("string_escape_old_backslash_dollar"), target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "literal: \\$name\nliteral2: \\${name}\n", "{:?}", target);
+    }
+    for (name, msg) in [
+        ("string_escape_old_unknown_error", "3:12: Unknown escape sequence `\\d` in string [E0004]"),
+        ("string_escape_old_nul_error", "3:11: `\\0` is not allowed in strings: shell strings cannot hold NUL bytes [E0004]"),
+    ] {
+        let err = try_compile_path_to_shell(&fixture(name), TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", name, err);
+    }
+}
@@ -0,0 +1,62 @@
+use assert_cmd::Command;
+use std::fs;
+
+fn sh2c() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+}
+
+const HELPERS_SH: &str = "greet() { printf 'hello %s\\n' \"$1\"; }\njq_get() { printf '%s:%s' \"$1\" \"$2\"; }\n";
+
+const JSON_SH2: &str = "extern func jq_get(blob, path)\n\nfunc field(blob) {\n    return jq_get(blob, \".name\")\n}\n";
+
+fn compile(dir: &tempfile::TempDir, main: &str) -> std::process::Output {
+    fs::write(dir.path().join("json.sh2"), JSON_SH2).unwrap();
+    fs::write(dir.path().join("main.sh2"), main).unwrap();
+    sh2c().current_dir(dir.path()).args(["-o", "main.sh", "main.sh2"]).output().unwrap()
+}
+
+#[test]
+fn extern_calls_run_the_sourced_shell_function() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("helpers.sh"), HELPERS_SH).unwrap();
+    let out = compile(
+        &dir,
+        "import \"json.sh2\"\nextern func greet(name)\n\nfunc main() {\n    source(\"./helpers.sh\")\n    greet(\"world\")\n    let v = jq_get(\"{}\", \".a\")\n    print(v)\n    print(field(\"x\"))\n}\n",
+    );
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let script = fs::read_to_string(dir.path().join("main.sh")).unwrap();
+    assert!(!script.contains("greet()"), "extern must not be defined:\n{}", script);
+
+    let run = std::process::Command::new("bash").current_dir(dir.path()).arg("main.sh").output().unwrap();
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), "hello world\n{}:.a\nx:.name\n");
+}
+
+#[test]
+fn extern_call_arity_is_checked() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = compile(&dir, "import \"json.sh2\"\n\nfunc main() {\n    let v = jq_get(\"{}\")\n}\n");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("main.sh2:4:13: extern func 'jq_get' takes 2 argument(s), got 1"), "{}", stderr);
+
+    let out = compile(&dir, "extern func greet(name)\n\nfunc main() {\n    greet()\n}\n");
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("extern func 'greet' takes 1 argument(s), got 0"), "{}", stderr);
+}
+
+#[test]
+fn extern_declaration_errors() {
+    for (main, expected) in [
+        ("extern func greet(name) {\n}\nfunc main() {\n}\n", "extern func 'greet' cannot have a body"),
+        ("extern func greet(name)\nfunc greet(name) {\n}\nfunc main() {\n}\n", "extern func 'greet' is also defined in"),
+        ("extern func split(s)\nfunc main() {\n}\n", "extern func 'split' has the name of a builtin"),
+        ("import \"json.sh2\"\nextern func jq_get(blob)\nfunc main() {\n}\n", "extern func 'jq_get' is declared with 1 parameter(s) here and 2 in"),
+    ] {
+        let dir = tempfile::tempdir().unwrap();
+        let out = compile(&dir, main);
+        assert!(!out.status.success(), "{}", main);
+        let stderr = String::from_utf8(out.stderr).unwrap();
+        assert!(stderr.contains(expected), "{}\n{}", main, stderr);
+    }
+}
@@ -0,0 +1,73 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_replace_in_file_is_literal_and_idempotent() {
+    let src = r#"
+func main() {
+    write_file("app.conf", "host=a.b\nport=80\nname=x.y")
+    run("chmod", "640", "app.conf")
+    replace_in_file("app.conf", ".", "[dot]", backup=true)
+    replace_in_file("app.conf", "port=80", "port=\\&8080")
+    replace_in_file("app.conf", "missing", "zzz", backup=true)
+    run("sh", "-c", "cat app.conf; echo; echo ---; cat app.conf.bak; echo; ls -l app.conf | cut -c1-10")
+}
+"#;
+    run_test_in_targets(
+        "replace_in_file_literal",
+        src,
+        "host=a[dot]b\nport=\\&8080\nname=x[dot]y\n---\nhost=a.b\nport=80\nname=x.y\n-rw-r-----",
+    );
+}
+
+#[test]
+fn exec_replace_in_file_missing_file_fails() {
+    let src = r#"
+func main() {
+    replace_in_file("nope.conf", "a", "b")
+    print("unreachable")
+}
+"#;
+    for (target, shell) in [(TargetShell::Bash, "bash"), (TargetShell::Posix, "sh")] {
+        let script = compile_to_shell(src, target);
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(stdout, "");
+        assert!(stderr.contains("replace_in_file: nope.conf: no such file"), "stderr: {}", stderr);
+        assert_ne!(code, Some(0));
+    }
+}
+
+#[test]
+fn exec_append_line_if_missing() {
+    let src = r#"
+func main() {
+    write_file("hosts", "127.0.0.1 localhost")
+    append_line_if_missing("hosts", "10.0.0.1 db")
+    append_line_if_missing("hosts", "10.0.0.1 db", backup=true)
+    append_line_if_missing("hosts", "127.0.0.1", backup=true)
+    append_line_if_missing("new.list", "-v")
+    run("sh", "-c", "cat hosts; echo ---; cat hosts.bak; echo ---; cat new.list")
+}
+"#;
+    run_test_in_targets(
+        "append_line_if_missing",
+        src,
+        "127.0.0.1 localhost\n10.0.0.1 db\n127.0.0.1\n---\n127.0.0.1 localhost\n10.0.0.1 db\n---\n-v",
+    );
+}
+
+#[test]
+fn compile_file_edit_rejects_unknown_option_and_expression_use() {
+    let cases = [
+        ("replace_in_file(\"f\", \"a\", \"b\", inplace=true)", "Unknown option \"inplace\" for replace_in_file()"),
+        ("append_line_if_missing(\"f\")", "append_line_if_missing() requires exactly 2 arguments"),
+        ("let x = replace_in_file(\"f\", \"a\", \"b\")", "replace_in_file() is a statement, not an expression"),
+    ];
+    for (stmt, expected) in cases {
+        let src = format!("func main() {{\n    {}\n}}\n", stmt);
+        match try_compile_to_shell(&src, TargetShell::Bash) {
+            Err(msg) => assert!(msg.contains(expected), "Unexpected error for {}: {}", stmt, msg),
+            Ok(_) => panic!("Expected compilation failure for {}", stmt),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+mod common;
+use common::*;
+
+const SETUP: &str = r#"
+    run("mkdir", "-p", "--", "src/sub", "sp ace", "-odd")
+    run("touch", "--", "src/a.log", "src/b.txt", "src/sub/c.log", "sp ace/d.log", "-odd/e.log")
+"#;
+
+#[test]
+fn exec_find_let_and_for() {
+    let src = format!(r#"
+func main() {{
+{SETUP}
+    let logs = find("src", name="*.log")
+    for a in logs {{ print("L " & a) }}
+    for b in find(".", name="*.log", type="f", max_depth=2) {{ print("S " & b) }}
+    for d in (find("src", type="d"), "extra") {{ print("D " & d) }}
+    for o in find("-odd") {{ print("O " & o) }}
+    for m in find("missing") {{ print("never") }}
+}}
+"#);
+    run_test_in_targets(
+        "find_let_and_for",
+        &src,
+        "L src/a.log\nL src/sub/c.log\nS ./-odd/e.log\nS ./sp ace/d.log\nS ./src/a.log\nD src/sub\nD extra\nO ./-odd/e.log",
+    );
+}
+
+#[test]
+fn exec_find_newline_in_filename_bash() {
+    let src = r#"
+func main() {
+    run("mkdir", "d")
+    run("touch", "d/line\nbreak", "d/plain")
+    let files = find("d", type="f")
+    print("count=" & count(files))
+    for f in find("d", type="f") {
+        print("[" & f & "]")
+    }
+}
+"#;
+    run_test_bash_only("find_newline_name", src, "count=2\n[d/line\nbreak]\n[d/plain]");
+}
+
+#[test]
+fn codegen_find_for_loop_streams_on_bash() {
+    let src = r#"
+func main() {
+    for f in find("src", name="*.rs") {
+        print(f)
+    }
+}
+"#;
+    let bash = compile_to_shell(src, TargetShell::Bash);
+    assert!(bash.contains("while IFS= read -r -d ''"), "bash output:\n{}", bash);
+    assert!(!bash.contains("__sh2_find "), "bash output:\n{}", bash);
+}
+
+#[test]
+fn compile_find_rejects_bad_options() {
+    let cases = [
+        ("find(\".\", type=\"x\")", "find() type must be literal \"f\" (files) or \"d\" (directories)"),
+        ("find(\".\", max_depth=\"2\")", "find() max_depth must be a non-negative integer literal"),
+        ("find(\".\", maxdepth=2)", "Unknown argument 'maxdepth'. Supported: name, type, max_depth"),
+        ("find(\".\", name=\"a\", name=\"b\")", "Duplicate argument 'name'"),
+        ("find(\"a\", \"b\")", "find() takes at most 1 positional argument"),
+    ];
+    for (call, expected) in cases {
+        let src = format!("func main() {{\n    let x = {}\n}}\n", call);
+        match try_compile_to_shell(&src, TargetShell::Bash) {
+            Err(msg) => assert!(msg.contains(expected), "Unexpected error for {}: {}", call, msg),
+            Ok(_) => panic!("Expected compilation failure for {}", call),
+        }
+    }
+}
+
+#[test]
+fn compile_find_as_string_is_rejected() {
+    let src = r#"
+func main() {
+    print(find("."))
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("find() returns a list"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for find() used as a string"),
+    }
+}
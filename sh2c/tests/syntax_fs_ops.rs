@@ -0,0 +1,82 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_fs_ops_basic() {
+    let src = r#"
+func main() {
+    mkdirs("a b/c")
+    mkdirs("a b/c")
+    write_file("a b/c/f.txt", "hi")
+    copy("a b", "copy dir", recursive=true)
+    copy("a b/c/f.txt", "-dash.txt")
+    move("-dash.txt", "moved.txt", force=true)
+    remove("a b", recursive=true)
+    remove("nothing", force=true)
+    run("sh", "-c", "find . -name '*.txt' | sort; test -e 'a b' || echo removed")
+}
+"#;
+    run_test_in_targets("fs_ops_basic", src, "./copy dir/c/f.txt\n./moved.txt\nremoved");
+}
+
+#[test]
+fn exec_fs_op_failure_is_status_checked() {
+    let src = r#"
+func main() {
+    remove("nothing")
+    print("unreachable")
+}
+"#;
+    for (target, shell) in [(TargetShell::Bash, "bash"), (TargetShell::Posix, "sh")] {
+        let script = compile_to_shell(src, target);
+        let (stdout, _stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(stdout, "");
+        assert_ne!(code, Some(0));
+    }
+}
+
+#[test]
+fn codegen_fs_ops_emit_option_terminator() {
+    let src = r#"
+func main() {
+    let p = "-x"
+    copy(p, "dst", recursive=true, force=true)
+    remove(p)
+}
+"#;
+    let out = compile_to_shell(src, TargetShell::Posix);
+    assert!(out.contains("cp -R -f -- \"$p\" 'dst'"), "posix output:\n{}", out);
+    assert!(out.contains("rm -- \"$p\""), "posix output:\n{}", out);
+}
+
+#[test]
+fn exec_user_function_shadows_fs_builtin() {
+    let src = r#"
+func copy(a, b) {
+    print("custom " & a & " " & b)
+}
+
+func main() {
+    copy("x", "y")
+}
+"#;
+    run_test_in_targets("fs_ops_user_shadow", src, "custom x y");
+}
+
+#[test]
+fn compile_fs_ops_reject_bad_usage() {
+    let cases = [
+        ("move(\"a\", \"b\", recursive=true)", "Unknown option \"recursive\" for move(); supported options are force"),
+        ("mkdirs(\"a\", force=true)", "mkdirs() takes no options; got \"force\""),
+        ("remove(\"a\", recursive=\"yes\")", "remove: recursive must be boolean literal"),
+        ("copy(\"a\")", "copy() requires exactly 2 arguments (src, dst)"),
+        ("let x = mkdirs(\"a\")", "mkdirs() is a statement, not an expression"),
+    ];
+    for (stmt, expected) in cases {
+        let src = format!("func main() {{\n    {}\n}}\n", stmt);
+        match try_compile_to_shell(&src, TargetShell::Bash) {
+            Err(msg) => assert!(msg.contains(expected), "Unexpected error for {}: {}", stmt, msg),
+            Ok(_) => panic!("Expected compilation failure for {}", stmt),
+        }
+    }
+}
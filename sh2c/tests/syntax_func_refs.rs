@@ -0,0 +1,102 @@
+mod common;
+use common::*;
+
+#[test]
+fn known_references_call_the_function_directly() {
+    let src = r#"func shout(msg) {
+    return msg & "!"
+}
+func note(msg) {
+    print("note: " & msg)
+}
+func is_even(n) -> bool {
+    return n % 2 == 0
+}
+func main() {
+    let up = shout
+    print(up("hi"))
+    let cb = note
+    cb("saved")
+    let even = is_even
+    if even(4) {
+        print("4 is even")
+    }
+    set cb = up
+    print(cb("again"))
+    print(cb)
+}
+"#;
+    for (script, stdout, _) in run_in_targets(src) {
+        assert_eq!(stdout, "hi!\nnote: saved\n4 is even\nagain!\nshout\n");
+        assert!(script.contains("\"$( shout 'hi' )\""), "{}", script);
+        assert!(script.contains("if is_even \"4\"; then"), "{}", script);
+    }
+}
+
+#[test]
+fn callbacks_passed_as_arguments() {
+    let src = r#"func on_error(msg) {
+    print("error: " & msg)
+}
+func tag(s) {
+    return "[" & s & "]"
+}
+func apply(cb, x) {
+    cb(x)
+}
+func twice(f, x) {
+    return f(x) & f(x)
+}
+func main() {
+    apply(on_error, "disk full")
+    print(twice(tag, "a"))
+}
+"#;
+    for (script, stdout, _) in run_in_targets(src) {
+        assert_eq!(stdout, "error: disk full\n[a][a]\n");
+        assert!(script.contains("\"$cb\" \"$x\""), "{}", script);
+    }
+}
+
+#[test]
+fn posix_error_traces_pop_callback_frames() {
+    let src = r#"func hello(x) {
+    print(x)
+}
+func apply(cb, x) {
+    cb(x)
+}
+func boom() {
+    run("false")
+}
+func main() {
+    apply(hello, "hi")
+    boom()
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Posix);
+    let (stdout, stderr, code) = run_shell_script(&script, "sh", &[], &[], None, None);
+    assert_eq!(code, Some(1));
+    assert_eq!(stdout, "hi\n");
+    assert!(stderr.ends_with("  boom at inline_test:8:5\n  main at inline_test:12:5\n"), "{}", stderr);
+}
+
+#[test]
+fn references_are_checked() {
+    let err = try_compile_to_shell(
+        "func hello(x) {\n    print(x)\n}\nfunc main() {\n    let h = hello\n    h(\"a\", \"b\")\n}\n",
+        TargetShell::Bash,
+    )
+    .unwrap_err();
+    assert!(err.contains("'h' refers to function 'hello', which takes 1 argument(s), got 2"), "{}", err);
+    assert!(err.contains("[E0300]"), "{}", err);
+
+    let err = try_compile_to_shell("func main() {\n    let h = helo\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("undefined variable 'helo'"), "{}", err);
+
+    // A variable wins over a function of the same name.
+    let src = "func name() {\n    print(\"f\")\n}\nfunc main() {\n    let name = \"v\"\n    print(name)\n}\n";
+    let script = compile_to_shell(src, TargetShell::Bash);
+    let (stdout, _, _) = run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_eq!(stdout, "v\n");
+}
@@ -1,5 +1,5 @@
 mod common;
-use common::{assert_codegen_matches_snapshot, assert_exec_matches_fixture, parse_fixture};
+use common::{assert_codegen_matches_snapshot, assert_exec_matches_fixture, parse_fixture, try_compile_to_shell, TargetShell};
 use sh2c::ast::{Expr, ExprKind, Stmt, StmtKind};
 
 #[test]
@@ -138,3 +138,19 @@ fn exec_return_status() {
 fn codegen_multi_run() {
     assert_codegen_matches_snapshot("multi_run");
 }
+
+#[test]
+fn duplicate_function_names_both_sites() {
+    let src = "func f() {\n    print(\"1\")\n}\nfunc f() {\n    print(\"2\")\n}\nfunc main() {\n    f()\n}\n";
+    let err = try_compile_to_shell(src, TargetShell::Bash).unwrap_err();
+    assert!(err.contains("inline_test:4:1: Function 'f' is already defined in this file [E0205]"), "{}", err);
+    assert!(err.contains("inline_test:1:1: 'f' was first defined here [E0205]"), "{}", err);
+}
+
+#[test]
+fn duplicate_parameter_names_both_sites() {
+    let src = "func copy(src, dst, src) {\n    print(src)\n}\nfunc main() {\n    copy(\"a\", \"b\", \"c\")\n}\n";
+    let err = try_compile_to_shell(src, TargetShell::Bash).unwrap_err();
+    assert!(err.contains("inline_test:1:21: duplicate parameter 'src' in function 'copy' [E0210]"), "{}", err);
+    assert!(err.contains("inline_test:1:11: parameter 'src' was first declared here [E0210]"), "{}", err);
+}
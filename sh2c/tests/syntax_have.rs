@@ -0,0 +1,62 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_have_in_conditions() {
+    let src = r#"
+func main() {
+    if have("sh") {
+        print("have sh")
+    }
+    if !have("sh2-no-such-command") {
+        print("missing")
+    }
+    if have("sh2-no-such-command") || have("sh") {
+        print("either")
+    }
+    print(have("sh2-no-such-command"))
+    let found = have("sh2-no-such-command")
+    if found {
+        print("wrong")
+    } else {
+        print("bound false")
+    }
+}
+"#;
+    run_test_in_targets("have_in_conditions", src, "have sh\nmissing\neither\nfalse\nbound false");
+}
+
+#[test]
+fn exec_have_caches_probe_results() {
+    let src = r#"
+func main() {
+    run("sh", "-c", "mkdir bin && printf '#!/bin/sh\n' > bin/tool && chmod +x bin/tool")
+    if have("./bin/tool") {
+        print("first")
+    }
+    run("rm", "bin/tool")
+    if have("./bin/tool") {
+        print("cached")
+    }
+    if !have("./bin/gone") {
+        print("gone")
+    }
+}
+"#;
+    run_test_in_targets("have_caches_results", src, "first\ncached\ngone");
+}
+
+#[test]
+fn compile_have_requires_one_argument() {
+    let src = r#"
+func main() {
+    if have("a", "b") {
+        print("x")
+    }
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("have() requires exactly 1 argument"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for have() with two arguments"),
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+use common::*;
+
+#[test]
+fn hooks_run_around_their_functions() {
+    let src = r#"func deploy(stage) {
+    print("deploying " & stage)
+    if stage == "prod" {
+        return "careful"
+    }
+    return "ok"
+}
+func cleanup() {
+    print("cleaning")
+}
+func main() {
+    print(deploy("dev"))
+    print(deploy("prod"))
+    cleanup()
+}
+on_enter(deploy, cleanup) {
+    print("audit: enter")
+}
+on_exit(deploy) {
+    print("audit: exit")
+}
+on_exit(*) {
+    print("outer exit")
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(
+            stdout,
+            "audit: enter\ndeploying dev\nouter exit\naudit: exit\nok\n\
+             audit: enter\ndeploying prod\nouter exit\naudit: exit\ncareful\n\
+             audit: enter\ncleaning\nouter exit\nouter exit\n",
+            "{:?}",
+            target
+        );
+    }
+}
+
+#[test]
+fn hooks_are_checked() {
+    for (src, msg) in [
+        ("func main() {\n}\non_enter(deploy) {\n    print(\"x\")\n}\n", "unknown function 'deploy' in on_enter(...)"),
+        (
+            "func main() {\n}\non_exit(main) {\n    if true {\n        return\n    }\n}\n",
+            "`return` is not allowed in an on_exit hook",
+        ),
+        ("func main() {\n}\non_enter() {\n}\n", "Expected a function name or `*` in hook targets"),
+    ] {
+        let err = try_compile_to_shell(src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}", err);
+    }
+    let err = try_compile_to_shell("func main() {\n}\non_enter(mian) {\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("[E0202]"), "{}", err);
+}
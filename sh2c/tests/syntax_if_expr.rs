@@ -0,0 +1,67 @@
+mod common;
+use common::*;
+
+#[test]
+fn if_expressions_select_a_value() {
+    let src = r#"@pure
+func plural(n) {
+    return if n == 1 { "" } else { "s" }
+}
+
+func main() {
+    let n = arg(1)
+    let label = if n == 1 { "file" } else { "files" }
+    print(n & " " & label)
+    print(if n > 10 { "big" } elif n > 2 { "medium" } else { "small" })
+    let ok = if n > 2 { n < 5 } else { false }
+    if ok {
+        print("in range")
+    }
+    print("x" & if n > 2 { n * 2 } else { 0 })
+    print("file" & plural(2))
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        assert!(!script.contains("plural"), "{}", script);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &["3"], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "3 files\nmedium\nin range\nx6\nfiles\n", "{:?}", target);
+        let (stdout, _, _) = run_shell_script(&script, shell, &[], &["1"], None, None);
+        assert_eq!(stdout, "1 file\nsmall\nx0\nfiles\n", "{:?}", target);
+    }
+
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
+
+#[test]
+fn only_the_chosen_branch_runs() {
+    let src = r#"func main() {
+    let v = if true { "kept" } else { capture(run("sh", "-c", "echo ran >&2; echo other")) }
+    print(v)
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    let (stdout, stderr, code) = run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(stdout, "kept\n");
+    assert!(!stderr.contains("ran"), "{}", stderr);
+}
+
+#[test]
+fn if_expressions_are_checked() {
+    for (body, msg) in [
+        ("let v = if true { \"a\" }", "An `if` used as a value needs an `else` branch"),
+        ("let v = if true { \"a\" \"b\" } else { \"c\" }", "branches hold a single expression"),
+        ("let v = if true { [\"a\"] } else { [\"b\"] }", "if expression branches must be strings, numbers or booleans"),
+        ("let v = if true { missing } else { \"b\" }", "undefined variable 'missing'"),
+    ] {
+        let src = format!("func main() {{\n    {}\n    print(v)\n}}\n", body);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", body, err);
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+use common::*;
+use std::fs;
+
+const BASE: &str = r#"init {
+    print("base init")
+    let BASE_URL = "https://example.com"
+}
+func url(path) {
+    return BASE_URL & "/" & path
+}
+"#;
+
+const API: &str = r#"import "base.sh2" as base
+init {
+    print("api init")
+}
+func fetch(path) {
+    print("GET " & base.url(path))
+}
+"#;
+
+const MAIN: &str = r#"import "base.sh2" as base
+import "api.sh2" as api
+init {
+    if !have("sh") {
+        print_err("sh is required")
+        exit(1)
+    }
+    let RETRIES = 3
+}
+func main() {
+    print("main")
+    api.fetch("status")
+    print(RETRIES)
+}
+"#;
+
+#[test]
+fn init_blocks_run_once_in_import_order() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("base.sh2"), BASE).unwrap();
+    fs::write(dir.path().join("api.sh2"), API).unwrap();
+    fs::write(dir.path().join("main.sh2"), MAIN).unwrap();
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_path_to_shell(&dir.path().join("main.sh2"), target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "base init\napi init\nmain\nGET https://example.com/status\n3\n");
+    }
+}
+
+#[test]
+fn init_blocks_are_checked() {
+    for (src, msg) in [
+        ("init {\n}\ninit {\n}\nfunc main() {\n}\n", "Only one `init { ... }` block is allowed per file"),
+        (
+            "init {\n    let NAME = \"a\"\n}\nfunc main() {\n    let NAME = \"b\"\n}\n",
+            "already declared in this scope",
+        ),
+        ("init {\n    print(later)\n}\nfunc main() {\n    let later = \"x\"\n}\n", "undefined variable 'later'"),
+    ] {
+        let err = try_compile_to_shell(src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}", err);
+    }
+}
@@ -0,0 +1,90 @@
+mod common;
+use common::*;
+
+#[test]
+fn small_functions_are_inlined_at_let_sites() {
+    let src = r#"func double(x) {
+    return x * 2
+}
+func tag(a) {
+    let t = a & "x"
+    return t & "!"
+}
+func main() {
+    let t = "1"
+    let v = tag(t)
+    let d = double(21)
+    print(v & " " & t & " " & d)
+    print(double(3))
+}
+"#;
+    for (script, stdout, _) in run_in_targets(src) {
+        assert_eq!(stdout, "1x! 1 42\n6\n");
+        assert!(script.contains("__sh2_inl_tag_t=\"$t\"'x'"), "{}", script);
+        assert!(!script.contains("$( tag") && !script.contains("$( double \"21\""), "{}", script);
+        // Calls inside other expressions are left alone.
+        assert!(script.contains("$( double \"3\" )"), "{}", script);
+    }
+}
+
+#[test]
+fn results_that_may_end_in_a_newline_need_the_attribute() {
+    let src = r#"func greet(name) {
+    return "hi " & name
+}
+@inline
+func greet2(name) {
+    return "hi " & name
+}
+func main() {
+    let a = greet("bob")
+    let b = greet2("ann" & "!")
+    print(a)
+    print(b)
+}
+"#;
+    for (script, stdout, _) in run_in_targets(src) {
+        assert_eq!(stdout, "hi bob\nhi ann!\n");
+        assert!(script.contains("$( greet 'bob' )"), "{}", script);
+        assert!(script.contains("__sh2_inl_greet2_name='ann''!'"), "{}", script);
+        assert!(!script.contains("$( greet2"), "{}", script);
+    }
+}
+
+#[test]
+fn failures_inside_inlined_code_still_stop_the_script() {
+    let src = "func ratio(a, b) {\n    return a / b\n}\nfunc main() {\n    let z = 0\n    let r = ratio(4, z)\n    print(\"after \" & r)\n}\n";
+    let script = compile_to_shell(src, TargetShell::Bash);
+    assert!(!script.contains("$( ratio"), "{}", script);
+    let (stdout, _, code) = run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_ne!(code, Some(0));
+    assert_eq!(stdout, "");
+}
+
+#[test]
+fn inline_attribute_checks() {
+    let err = try_compile_to_shell(
+        "@inline\nfunc f(x) {\n    print(x)\n    return x\n}\nfunc main() {\n    let v = f(\"a\")\n}\n",
+        TargetShell::Bash,
+    )
+    .unwrap_err();
+    assert!(err.contains("@inline function 'f' can only contain lets") && err.contains(":3:5"), "{}", err);
+    assert!(err.contains("[E0606]"), "{}", err);
+
+    let err = try_compile_to_shell(
+        "@inline\n@cache\nfunc f(x) {\n    return x\n}\nfunc main() {\n    let v = f(\"a\")\n}\n",
+        TargetShell::Bash,
+    )
+    .unwrap_err();
+    assert!(err.contains("cannot also be @cache"), "{}", err);
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("p.sh2"), "func one() {\n    return 1\n}\nfunc main() {\n    let v = one()\n    print(v)\n}\n").unwrap();
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .current_dir(dir.path())
+        .args(["--profile", "p.sh2"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stdout).contains("v=\"$( one  )\""));
+}
@@ -18,12 +18,28 @@ fn exec_confirm_invalid_then_no_bash() {
 }
 
 #[test]
-fn compile_panic_input_posix_unsupported() {
-    assert_codegen_panics_target(
-        "input_basic",
-        TargetShell::Posix,
-        "input(...) is not supported in POSIX sh target",
-    );
+fn exec_input_basic_posix() {
+    assert_exec_matches_fixture_target("input_basic", TargetShell::Posix);
 }
 
-// confirm() now works in POSIX thanks to __sh2_confirm helper using tr for case conversion
+#[test]
+fn exec_confirm_yes_posix() {
+    assert_exec_matches_fixture_target("confirm_yes", TargetShell::Posix);
+}
+
+#[test]
+fn exec_confirm_invalid_then_no_posix() {
+    assert_exec_matches_fixture_target("confirm_invalid_then_no", TargetShell::Posix);
+}
+
+#[test]
+fn codegen_confirm_helper_has_no_external_case_folding() {
+    let src = r#"
+func main() {
+    if confirm("Go?") { print("y") }
+}
+"#;
+    let sh = compile_to_shell(src, TargetShell::Posix);
+    assert!(sh.contains("[Yy]|[Yy][Ee][Ss])"), "posix output:\n{}", sh);
+    assert!(!sh.contains("tr '[:upper:]'"), "posix output:\n{}", sh);
+}
@@ -0,0 +1,50 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_input_list_reads_stdin_until_eof() {
+    let src = r#"
+func main() {
+    let hosts = input_list("Enter hosts (empty line to finish)")
+    for h in hosts {
+        print("host: " & h)
+    }
+    print("done")
+}
+"#;
+    for (target, shell) in [(TargetShell::Bash, "bash"), (TargetShell::Posix, "sh")] {
+        let script = compile_to_shell(src, target);
+        let (stdout, stderr, status) =
+            run_shell_script(&script, shell, &[], &[], Some("web 1\n\ndb-2\n  spaced  \nlast"), None);
+        assert_eq!(status, Some(0), "{:?}: stderr was {}", target, stderr);
+        assert_eq!(stdout, "host: web 1\nhost: db-2\nhost:   spaced  \nhost: last\ndone\n", "{:?}", target);
+        assert!(!stderr.contains("Enter hosts"), "{:?}: prompt shown without a terminal", target);
+    }
+}
+
+#[test]
+fn exec_input_list_empty_stdin_bash() {
+    let src = r#"
+func main() {
+    let items = input_list("Items:")
+    print("count=" & count(items))
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    let (stdout, _stderr, status) = run_shell_script(&script, "bash", &[], &[], Some(""), None);
+    assert_eq!(status, Some(0));
+    assert_eq!(stdout, "count=0\n");
+}
+
+#[test]
+fn compile_input_list_requires_let() {
+    let src = r#"
+func main() {
+    print(input_list("Items:"))
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("input_list() can only be used in 'let' assignments"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for input_list() outside let"),
+    }
+}
@@ -0,0 +1,53 @@
+mod common;
+use common::*;
+
+#[test]
+fn last_command_and_stderr_in_catch() {
+    let src = r#"func main() {
+    try {
+        run("sh", "-c", "echo 'disk full' >&2; exit 4")
+    } catch {
+        print($"failed: {last_command()}")
+        print($"stderr: {last_stderr()}")
+    }
+    run("printf", "a b") | run("tr", "a-z", "A-Z")
+    print("")
+    print($"cmd: {last_command()}")
+    print($"err: [{last_stderr()}]")
+}
+"#;
+    run_test_in_targets(
+        "last_command_catch",
+        src,
+        "failed: sh -c echo 'disk full' >&2; exit 4\nstderr: disk full\nA B\ncmd: printf a b | tr a-z A-Z\nerr: []",
+    );
+}
+
+#[test]
+fn last_stderr_with_allow_fail() {
+    let src = r#"func main() {
+    run("sh", "-c", "echo out; echo warn >&2; exit 2", allow_fail=true)
+    print($"{status()} {last_stderr()}")
+}
+"#;
+    run_test_in_targets("last_stderr_allow_fail", src, "out\n2 warn");
+}
+
+#[test]
+fn tracking_only_when_used() {
+    let plain = try_compile_to_shell("func main() {\n    run(\"true\")\n}\n", TargetShell::Bash).unwrap();
+    assert!(!plain.contains("__sh2_last_cmd") && !plain.contains("__sh2_last_stderr"), "{}", plain);
+
+    let cmd_only = try_compile_to_shell(
+        "func main() {\n    run(\"true\")\n    print(last_command())\n}\n",
+        TargetShell::Posix,
+    )
+    .unwrap();
+    assert!(cmd_only.contains("__sh2_last_cmd=") && !cmd_only.contains("__sh2_last_stderr"), "{}", cmd_only);
+}
+
+#[test]
+fn last_command_takes_no_arguments() {
+    let err = try_compile_to_shell("func main() {\n    print(last_stderr(1))\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("last_stderr() takes no arguments"), "{}", err);
+}
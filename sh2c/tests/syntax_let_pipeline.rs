@@ -0,0 +1,57 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_let_pipeline_captures_stdout() {
+    let src = r#"
+func main() {
+    let out = run("printf", "a\nb\nc\n") | run("grep", "b")
+    print("got=" & out)
+    let blk = run("echo", "blk") | { run("tr", "a-z", "A-Z") }
+    print("blk=" & blk)
+}
+"#;
+    run_test_in_targets("let_pipeline_captures_stdout", src, "got=b\nblk=BLK");
+}
+
+#[test]
+fn exec_let_pipeline_allow_fail_per_segment() {
+    let src = r#"
+func main() {
+    let up = run("sh", "-c", "echo hi; exit 3", allow_fail=true) | run("tr", "a-z", "A-Z")
+    print("up=" & up & " status=" & status())
+    let last = run("echo", "x") | run("sh", "-c", "cat; exit 4", allow_fail=true)
+    print("last=" & last & " status=" & status())
+}
+"#;
+    run_test_in_targets("let_pipeline_allow_fail", src, "up=HI status=0\nlast=x status=4");
+}
+
+#[test]
+fn exec_let_pipeline_upstream_failure_is_fatal() {
+    let src = r#"
+func main() {
+    let out = run("sh", "-c", "echo partial; exit 5") | run("cat")
+    print("unreachable " & out)
+}
+"#;
+    for (target, shell) in [(TargetShell::Bash, "bash"), (TargetShell::Posix, "sh")] {
+        let script = compile_to_shell(src, target);
+        let (stdout, _stderr, status) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(status, Some(5), "{:?}: unexpected status (stdout: {})", target, stdout);
+        assert!(!stdout.contains("unreachable"), "{:?}: pipeline failure was ignored", target);
+    }
+}
+
+#[test]
+fn compile_let_pipeline_rejects_each_line() {
+    let src = r#"
+func main() {
+    let out = run("ls") | each_line f { print(f) }
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("each_line cannot be used in a let-bound pipeline"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for each_line in let pipeline"),
+    }
+}
@@ -0,0 +1,118 @@
+mod common;
+use common::*;
+
+#[test]
+fn with_nice_and_ulimit_scope_the_body() {
+    let src = r#"func main() {
+    with nice(5) {
+        run("sh", "-c", "cut -d' ' -f19 /proc/$$/stat")
+    }
+    run("sh", "-c", "cut -d' ' -f19 /proc/$$/stat")
+    with ulimit(nofile=64, core=0) {
+        run("sh", "-c", "ulimit -n; ulimit -c")
+    }
+    run("sh", "-c", "ulimit -n")
+}
+"#;
+    let outside = std::process::Command::new("sh").args(["-c", "ulimit -n"]).output().unwrap();
+    let outside = String::from_utf8(outside.stdout).unwrap();
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, format!("5\n0\n64\n0\n{}", outside), "{:?}", target);
+    }
+}
+
+#[test]
+fn ulimit_sizes_are_bytes_in_each_targets_units() {
+    let src = r#"func main() {
+    with ulimit(fsize=1Mi, stack=8Mi, vmem="unlimited") {
+        print("x")
+    }
+}
+"#;
+    let bash = compile_to_shell(src, TargetShell::Bash);
+    assert!(bash.contains("ulimit -f 1024 ||"), "{}", bash);
+    assert!(bash.contains("ulimit -s 8192 ||"), "{}", bash);
+    assert!(bash.contains("ulimit -v unlimited ||"), "{}", bash);
+    let posix = compile_to_shell(src, TargetShell::Posix);
+    assert!(posix.contains("ulimit -f 2048 ||"), "{}", posix);
+    assert!(posix.contains("ulimit -s 8192 ||"), "{}", posix);
+}
+
+#[test]
+fn missing_priority_tools_only_warn() {
+    let src = r#"func main() {
+    with ionice("best-effort", 7) {
+        print("body")
+    }
+    print("after")
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        assert!(script.contains("ionice -c 2 -n 7 -p"), "{}", script);
+        let shell = if target == TargetShell::Bash { "/bin/bash" } else { "/bin/sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[("PATH", "/nonexistent")], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "body\nafter\n", "{:?}", target);
+        assert!(stderr.contains("warning: ionice not found; running without ionice(...)"), "{}", stderr);
+    }
+}
+
+#[test]
+fn a_refused_limit_fails_the_block() {
+    let src = r#"func main() {
+    with ulimit(nofile=999999999) {
+        print("not reached")
+    }
+    print("not reached either")
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    let (stdout, _, code) = run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_ne!(code, Some(0));
+    assert_eq!(stdout, "");
+}
+
+#[test]
+fn limit_blocks_format_round_trip() {
+    let src = r#"func main() {
+    with nice(-5) {
+        print("a")
+    }
+    with ionice("idle") {
+        print("b")
+    }
+    with ulimit(nofile=1024, cpu="unlimited") {
+        print("c")
+    }
+}
+"#;
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
+
+#[test]
+fn limit_settings_are_checked() {
+    for (setting, msg) in [
+        ("nice(20)", "nice() value must be an integer from -20 to 19"),
+        ("nice()", "nice() requires exactly one argument"),
+        ("ionice(\"low\")", "ionice() class must be \"idle\", \"best-effort\" or \"realtime\""),
+        ("ionice(\"idle\", 3)", "the \"idle\" I/O class takes no level"),
+        ("ionice(\"best-effort\", 8)", "ionice() level must be an integer from 0 to 7"),
+        ("ulimit()", "ulimit() requires at least one limit"),
+        ("ulimit(1024)", "ulimit() does not accept positional arguments"),
+        ("ulimit(files=10)", "unknown option 'files' for ulimit()"),
+        ("ulimit(cpu=1, cpu=2)", "ulimit() option 'cpu' is specified more than once"),
+        ("ulimit(cpu=\"lots\")", "ulimit() option 'cpu' must be a non-negative integer or \"unlimited\""),
+    ] {
+        let src = format!("func main() {{\n    with {} {{\n        print(\"x\")\n    }}\n}}\n", setting);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", setting, err);
+    }
+}
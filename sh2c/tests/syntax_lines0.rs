@@ -0,0 +1,74 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_lines0_from_command_keeps_newlines_and_spaces() {
+    let src = r#"
+func main() {
+    let recs = lines0(run("sh", "-c", "printf 'a b\\0line\\nbreak\\0tail'"))
+    print("count=" & count(recs))
+    for r in recs {
+        print("[" & r & "]")
+    }
+}
+"#;
+    run_test_bash_only("lines0_command", src, "count=3\n[a b]\n[line\nbreak]\n[tail]");
+}
+
+#[test]
+fn exec_split0_from_file_in_for_loop() {
+    let src = r#"
+func main() {
+    run("sh", "-c", "printf 'x\\0y y\\0' > data.bin")
+    for r in (split0(read_file("data.bin")), "end") {
+        print("<" & r & ">")
+    }
+}
+"#;
+    run_test_bash_only("split0_file", src, "<x>\n<y y>\n<end>");
+}
+
+#[test]
+fn exec_lines0_producer_failure_stops_script() {
+    let src = r#"
+func main() {
+    for r in lines0(run("false")) {
+        print("never")
+    }
+    print("unreachable")
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    let (stdout, _stderr, code) = run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_eq!(stdout, "");
+    assert_ne!(code, Some(0));
+}
+
+#[test]
+fn compile_lines0_rejects_string_source() {
+    let src = r#"
+func main() {
+    let out = capture(run("find", ".", "-print0"))
+    let files = lines0(out)
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("shell strings cannot contain NUL bytes"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for lines0() over a string"),
+    }
+}
+
+#[test]
+fn compile_lines0_posix_unsupported() {
+    let src = r#"
+func main() {
+    for f in lines0(run("find", ".", "-print0")) {
+        print(f)
+    }
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Posix) {
+        Err(msg) => assert!(msg.contains("lines0()/split0() (NUL-delimited read) is not supported in posix target"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for lines0() on POSIX"),
+    }
+}
@@ -8,9 +8,9 @@ fn parse_list_args_utils() {
     assert_eq!(program.functions.len(), 2);
 
     let test_fn = &program.functions[0];
-    assert_eq!(test_fn.name, "test");
+    assert_eq!(test_fn.name, "show_args");
 
-    // In test(): print(argc())
+    // In show_args(): print(argc())
     assert!(matches!(
         test_fn.body[0],
         Stmt {
@@ -0,0 +1,65 @@
+mod common;
+use common::*;
+
+#[test]
+fn trailing_commas_and_newline_separators() {
+    let src = r#"func join3(
+    a,
+    b,
+    c,
+) {
+    return a & b & c
+}
+func main() {
+    let xs = [
+        "a",
+        "b",
+    ]
+    let ys = [
+        "c"
+        "d" & "e"
+    ]
+    let m = {
+        "k": "v"
+        "n": "w",
+    }
+    print(join3(
+        xs[0]
+        xs[1]
+        ys[1],
+    ))
+    print(m["n"],)
+    for v in (
+        "p"
+        "q",
+    ) {
+        print(v)
+    }
+    lib.greet(
+        "x",
+    )
+}
+"#;
+    let lib = "func greet(name) {\n    print(\"hi \" & name)\n}\n";
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("main.sh2"), format!("import \"lib.sh2\" as lib\n{}", src)).unwrap();
+    std::fs::write(dir.path().join("lib.sh2"), lib).unwrap();
+    // Lists are bash-only.
+    let script = compile_path_to_shell(&dir.path().join("main.sh2"), TargetShell::Bash);
+    let (stdout, stderr, code) = run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(stdout, "abde\nw\np\nq\nhi x\n");
+}
+
+#[test]
+fn unclosed_list_still_reports_the_missing_bracket() {
+    for (src, msg) in [
+        ("func main() {\n    let xs = [1, 2\n    print(xs)\n}\n", "Expected RBracket, got Print"),
+        ("func main() {\n    let xs = [\"a\" \"b\"]\n}\n", "Expected RBracket, got String(\"b\")"),
+        ("func main() {\n    let xs = [,]\n}\n", "Expected expression, got Comma"),
+        ("func main() {\n    print(\"a\",,)\n}\n", "Expected RParen, got Comma"),
+    ] {
+        let err = try_compile_to_shell(src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}", err);
+    }
+}
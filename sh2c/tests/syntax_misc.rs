@@ -13,3 +13,36 @@ fn codegen_nothing_panics() {
         "No entrypoint: define `func main()`.",
     );
 }
+
+#[test]
+fn shebang_line_is_skipped_without_shifting_lines() {
+    let src = "#!/usr/bin/env sh2do\nfunc main() {\n    print(missing)\n}\n";
+    let err = common::try_compile_to_shell(src, sh2c::codegen::TargetShell::Bash).unwrap_err();
+    assert!(err.contains("inline_test:3:11: undefined variable 'missing'"), "{}", err);
+
+    let script = common::compile_to_shell("#!/usr/bin/env sh2do\nprint(\"ok\")\n", sh2c::codegen::TargetShell::Bash);
+    let (stdout, _, code) = common::run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_eq!((stdout.as_str(), code), ("ok\n", Some(0)));
+}
+
+#[test]
+fn block_comments_nest_without_shifting_lines() {
+    let src = "/* a /* nested\n   comment */\n   still out */\nfunc main() {\n    // line comment\n    print(missing)\n}\n";
+    let err = common::try_compile_to_shell(src, sh2c::codegen::TargetShell::Bash).unwrap_err();
+    assert!(err.contains("inline_test:6:11: undefined variable 'missing'"), "{}", err);
+
+    let err = common::try_compile_to_shell("/* a /* b */\nfunc main() {\n}\n", sh2c::codegen::TargetShell::Bash)
+        .unwrap_err();
+    assert!(err.contains("Unterminated block comment"), "{}", err);
+    assert!(err.contains("[E0003]"), "{}", err);
+}
+
+#[test]
+fn doc_comments_attach_to_the_next_item() {
+    let src = "/// Says hi.\n///\n/// Twice.\nfunc hi() {\n    print(\"hi\")\n}\n//// not a doc comment\nfunc main() {\n    hi()\n}\n";
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(program.functions[0].doc, ["Says hi.", "", "Twice."]);
+    assert!(program.functions[1].doc.is_empty());
+}
@@ -0,0 +1,49 @@
+mod common;
+use common::*;
+
+#[test]
+fn hex_underscores_and_size_suffixes() {
+    let src = r#"func main() {
+    print(0xFF)
+    print(0x7f_ff)
+    print(1_000_000)
+    print(10M)
+    print(4Ki)
+    print(2Gi + 1)
+    let size = 5000
+    if size < 4K {
+        print("small")
+    } else {
+        print("big")
+    }
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "255\n32767\n1000000\n10000000\n4096\n2147483649\nbig\n", "{:?}", target);
+    }
+}
+
+#[test]
+fn invalid_number_literals_are_errors() {
+    for (expr, msg) in [
+        ("10mb", "Unknown number suffix `mb` in `10mb`"),
+        ("1__000", "Invalid number literal `1__000`: `_` may only separate digits"),
+        ("1000_", "Invalid number literal `1000_`: `_` may only separate digits"),
+        ("0x", "Invalid number literal `0x`"),
+        ("0xFG", "Invalid number literal `0xFG`: expected hex digits after `0x`"),
+        ("9223372036854775808", "is too large for shell arithmetic"),
+        ("10000000T", "Number literal `10000000T` is too large for shell arithmetic"),
+    ] {
+        let src = format!("func main() {{\n    print({})\n}}\n", expr);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", expr, err);
+        assert!(err.contains("[E0005]"), "{}: {}", expr, err);
+    }
+    assert!(try_compile_to_shell("func main() {\n    print(8000000Ti)\n}\n", TargetShell::Bash).is_ok());
+    let err = try_compile_to_shell("func main() {\n    print(3kb)\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("help: size suffixes are K, M, G, T"), "{}", err);
+}
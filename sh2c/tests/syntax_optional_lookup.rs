@@ -0,0 +1,49 @@
+mod common;
+use common::*;
+
+#[test]
+fn optional_lookups_read_missing_entries_as_empty() {
+    let src = r#"func main() {
+    let cfg = { "host": "localhost", "log-level": "debug" }
+    let xs = ["a", "b"]
+    let i = 1
+    print("[" & cfg?.host & "|" & cfg?.port & "]")
+    print("[" & cfg?["log-level"] & "|" & cfg?.nope & "]")
+    print("[" & xs?[i] & "|" & xs?[5] & "]")
+    print(cfg?.port ?? "8080")
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    assert!(script.contains("\"${cfg['port']:-}\""), "{}", script);
+    assert!(script.contains("\"${xs[5]:-}\""), "{}", script);
+    let (stdout, stderr, code) = run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(stdout, "[localhost|]\n[debug|]\n[b|]\n8080\n");
+
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
+
+#[test]
+fn plain_lookups_stay_strict() {
+    let src = "func main() {\n    let m = { \"k\": \"v\" }\n    print(m[\"k\"])\n}\n";
+    let script = compile_to_shell(src, TargetShell::Bash);
+    assert!(script.contains("\"${m['k']}\""), "{}", script);
+}
+
+#[test]
+fn optional_lookup_errors() {
+    for (expr, msg) in [
+        ("capture(run(\"echo\"))?.x", "Expected a map variable before `?.`"),
+        ("m?.1", "Expected a key name after `?.`"),
+    ] {
+        let src = format!("func main() {{\n    let m = {{ \"k\": \"v\" }}\n    print({})\n}}\n", expr);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", expr, err);
+    }
+    let err = try_compile_to_shell("func main() {\n    let m = { \"k\": \"v\" }\n    print(m?.k)\n}\n", TargetShell::Posix)
+        .unwrap_err();
+    assert!(err.contains("map/dict is only supported in Bash target"), "{}", err);
+}
@@ -1,5 +1,5 @@
 mod common;
-use common::{assert_codegen_matches_snapshot, assert_exec_matches_fixture, parse_fixture};
+use common::{assert_codegen_matches_snapshot, assert_exec_matches_fixture, parse_fixture, try_compile_to_shell, TargetShell};
 use sh2c::ast::{Stmt, StmtKind};
 
 #[test]
@@ -34,3 +34,26 @@ fn codegen_capture_pipe() {
 fn exec_pipe_basic() {
     assert_exec_matches_fixture("pipe_basic");
 }
+
+#[test]
+fn codegen_posix_pipe_fds_are_literal() {
+    let src = r#"
+        func main() {
+            run("printf", "b\na\n") | run("sort") | run("head", "-n", "1")
+        }
+    "#;
+    let sh = try_compile_to_shell(src, TargetShell::Posix).expect("compile");
+    assert!(sh.contains("exec 3<>\"${__sh2_base}_0\";"), "missing fd 3 setup:\n{}", sh);
+    assert!(sh.contains("exec 4<>\"${__sh2_base}_1\";"), "missing fd 4 setup:\n{}", sh);
+    assert!(sh.contains("( exec 3>&- 4>&-; "), "children must close keepalive fds:\n{}", sh);
+    assert!(!sh.contains("__sh2_fds"), "runtime fd list should be gone:\n{}", sh);
+}
+
+#[test]
+fn codegen_posix_pipe_too_many_stages() {
+    let stage = r#"run("cat")"#;
+    let src = format!("func main() {{\n    {}\n}}\n", vec![stage; 9].join(" | "));
+    let err = try_compile_to_shell(&src, TargetShell::Posix).unwrap_err();
+    assert!(err.contains("a pipeline longer than 8 stages (got 9)"), "unexpected error: {}", err);
+    assert!(try_compile_to_shell(&src, TargetShell::Bash).is_ok());
+}
@@ -0,0 +1,62 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_pipe_segment_stderr_to_files() {
+    let src = r#"
+func main() {
+    run("sh", "-c", "echo out; echo err-a >&2") stderr=file("a.err") | run("sh", "-c", "cat; echo err-b >&2") stderr=file("b.err")
+    run("echo", "x") | run("sh", "-c", "cat >/dev/null; echo more-b >&2") stderr=file("b.err", append=true)
+    print(read_file("a.err"))
+    print(read_file("b.err"))
+}
+"#;
+    run_test_in_targets("pipe_segment_stderr_files", src, "out\nerr-a\nerr-b\nmore-b");
+}
+
+#[test]
+fn exec_pipe_segment_stderr_to_stdout() {
+    let src = r#"
+func main() {
+    run("sh", "-c", "echo merged >&2") stderr=to_stdout() | run("tr", "a-z", "A-Z")
+}
+"#;
+    run_test_in_targets("pipe_segment_stderr_to_stdout", src, "MERGED");
+}
+
+#[test]
+fn exec_pipe_blocks_segment_stderr() {
+    let src = r#"
+func main() {
+    pipe { print("blk") } | run("sh", "-c", "cat; echo e3 >&2", allow_fail=true) stderr=file("c.err")
+    print(read_file("c.err"))
+}
+"#;
+    run_test_in_targets("pipe_blocks_segment_stderr", src, "blk\ne3");
+}
+
+#[test]
+fn compile_segment_stderr_requires_pipeline() {
+    let src = r#"
+func main() {
+    run("ls") stderr=file("ls.err")
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("stderr= is only supported on pipeline segments"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for stderr= on a single run()"),
+    }
+}
+
+#[test]
+fn compile_segment_stderr_rejects_cross_stream_to_stderr() {
+    let src = r#"
+func main() {
+    run("ls") stderr=to_stderr() | run("cat")
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("supports only file(...) or to_stdout()"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for stderr=to_stderr()"),
+    }
+}
@@ -0,0 +1,85 @@
+mod common;
+use common::*;
+
+const SLUG: &str = r#"@pure
+func slug(name, n) {
+    let base = replace(trim(name), " ", "-")
+    if n > 1 {
+        return base & "-" & n
+    }
+    return base
+}
+@pure
+func fact(n) {
+    if n <= 1 {
+        return 1
+    }
+    return n * fact(n - 1)
+}
+"#;
+
+#[test]
+fn literal_calls_are_folded_away() {
+    let src = format!(
+        "{}func main() {{\n    print(slug(\"  my app \", 3))\n    print(fact(10) + 1)\n    print(\"n=\" & fact(3))\n}}\n",
+        SLUG
+    );
+    for (script, stdout, _) in run_in_targets(&src) {
+        assert_eq!(stdout, "my-app-3\n3628801\nn=6\n");
+        assert!(!script.contains("slug") && !script.contains("fact"), "{}", script);
+        assert!(!script.contains("__sh2_replace"), "{}", script);
+    }
+}
+
+#[test]
+fn runtime_arguments_still_call_the_function() {
+    let src = format!(
+        "{}func main() {{\n    let name = \"x y\"\n    print(slug(name, 1))\n    print(slug(\"a b\", 2))\n}}\n",
+        SLUG
+    );
+    for (script, stdout, _) in run_in_targets(&src) {
+        assert_eq!(stdout, "x-y\na-b-2\n");
+        assert!(script.contains("slug()"), "{}", script);
+        assert!(!script.contains("fact"), "{}", script);
+    }
+}
+
+#[test]
+fn unmodelled_cases_are_left_for_runtime() {
+    // A backslash reaches awk, and "1" + 1 is concatenation in sh2.
+    let src = r#"@pure
+func tidy(s) {
+    return trim(s)
+}
+@pure
+func inc(s) {
+    return "1" + s
+}
+func main() {
+    print(tidy(" a\\tb "))
+    print(inc(1))
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    assert!(script.contains("tidy()"), "{}", script);
+    assert!(!script.contains("inc()"), "{}", script);
+    let (stdout, _, code) = run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "a\tb\n11\n");
+}
+
+#[test]
+fn pure_bodies_are_checked() {
+    for (body, msg) in [
+        ("    print(x)\n    return x", "@pure function 'f' cannot use this statement"),
+        ("    return g(x)", "@pure function 'f' cannot use 'g()'"),
+        ("    return env.HOME", "@pure function 'f' cannot use this expression"),
+    ] {
+        let src = format!(
+            "@pure\nfunc f(x) {{\n{}\n}}\nfunc g(x) {{\n    return x\n}}\nfunc main() {{\n    print(f(\"a\"))\n}}\n",
+            body
+        );
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg) && err.contains("[E0605]"), "{}", err);
+    }
+}
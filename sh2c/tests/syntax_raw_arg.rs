@@ -0,0 +1,65 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_raw_arg_splits_words() {
+    let src = r#"
+func main() {
+    let flags = "-a   -b"
+    run("printf", "[%s]", raw_arg(flags, allow_split=true), "c d")
+    print("")
+    run("printf", "[%s]", raw_arg("x y", allow_split=true))
+    print("")
+    let out = capture(run("printf", "<%s>", raw_arg(flags, allow_split=true)))
+    print(out)
+}
+"#;
+    run_test_in_targets("raw_arg_splits_words", src, "[-a][-b][c d]\n[x][y]\n<-a><-b>");
+}
+
+#[test]
+fn exec_raw_arg_empty_value_drops_argument() {
+    let src = r#"
+func main() {
+    let flags = ""
+    run("sh", "-c", "echo $#", "sh", raw_arg(flags, allow_split=true), "only")
+}
+"#;
+    run_test_in_targets("raw_arg_empty_value", src, "1");
+}
+
+#[test]
+fn codegen_raw_arg_is_unquoted() {
+    let src = r#"
+func main() {
+    let flags = "-v"
+    run("ls", raw_arg(flags, allow_split=true), flags)
+}
+"#;
+    let bash = compile_to_shell(src, TargetShell::Bash);
+    assert!(bash.contains("'ls' $flags \"$flags\""), "bash output:\n{}", bash);
+}
+
+#[test]
+fn compile_raw_arg_requires_acknowledgment() {
+    for call in ["raw_arg(flags)", "raw_arg(flags, allow_split=false)"] {
+        let src = format!("func main() {{\n    let flags = \"-v\"\n    run(\"ls\", {})\n}}\n", call);
+        match try_compile_to_shell(&src, TargetShell::Bash) {
+            Err(msg) => assert!(msg.contains("acknowledge this with raw_arg(value, allow_split=true)"), "Unexpected error: {}", msg),
+            Ok(_) => panic!("Expected compilation failure for {}", call),
+        }
+    }
+}
+
+#[test]
+fn compile_raw_arg_rejected_outside_command_args() {
+    let src = r#"
+func main() {
+    let flags = raw_arg("-a -b", allow_split=true)
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("raw_arg() can only be used as a direct argument of run(...) or sudo(...)"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for raw_arg() outside run()"),
+    }
+}
@@ -19,3 +19,61 @@ fn test_read_file_trailing_newline() {
 fn test_read_file_missing_error() {
     common::assert_exec_matches_fixture("read_file_missing_error");
 }
+
+#[test]
+fn exec_read_file_default_when_missing() {
+    let src = r#"
+func main() {
+    let cfg = read_file("missing.conf", default="port=80")
+    print("[" & cfg & "]")
+    let none = read_file("missing.conf", allow_missing=true)
+    print("[" & none & "]")
+    write_file("present.conf", "port=8080")
+    print("[" & read_file("present.conf", default="port=80") & "]")
+    for l in lines(read_file("missing.conf", default="only")) {
+        print("line " & l)
+    }
+}
+"#;
+    common::run_test_in_targets("read_file_default", src, "[port=80]\n[]\n[port=8080]\nline only");
+}
+
+#[test]
+fn exec_read_file_default_still_fails_on_unreadable_path() {
+    let src = r#"
+func main() {
+    let c = read_file(".", allow_missing=true)
+    print("unreachable")
+}
+"#;
+    let script = common::compile_to_shell(src, common::TargetShell::Bash);
+    let (stdout, _stderr, code) = common::run_shell_script(&script, "bash", &[], &[], None, None);
+    assert_eq!(stdout, "");
+    assert_ne!(code, Some(0));
+}
+
+#[test]
+fn compile_read_file_default_conflicts_with_allow_missing_false() {
+    let src = r#"
+func main() {
+    let c = read_file("x", default="", allow_missing=false)
+}
+"#;
+    match common::try_compile_to_shell(src, common::TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("default= implies allow_missing=true"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for conflicting read_file() options"),
+    }
+}
+
+#[test]
+fn compile_read_file_unknown_option() {
+    let src = r#"
+func main() {
+    let c = read_file("x", fallback="")
+}
+"#;
+    match common::try_compile_to_shell(src, common::TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("Unknown option \"fallback\" for read_file()"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for unknown read_file() option"),
+    }
+}
@@ -0,0 +1,93 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_render_basic() {
+    let src = r#"
+func main() {
+    let vars = { "name": "World", "greeting": "Hello" }
+    print(render("{{greeting}}, {{ name }}! {{missing}}", vars))
+}
+"#;
+    run_test_bash_only("render_basic", src, "Hello, World! {{missing}}");
+}
+
+#[test]
+fn exec_render_preserves_backslashes_and_newlines() {
+    let src = r#"
+func main() {
+    let vars = { "path": "C:\\dir\\n", "name": "x" }
+    let tpl = "a\n{{path}}\n{{name}}"
+    print(render(tpl, vars))
+}
+"#;
+    run_test_bash_only("render_backslashes", src, "a\nC:\\dir\\n\nx");
+}
+
+#[test]
+fn exec_render_escape_html() {
+    let src = r#"
+func main() {
+    let vars = { "v": "<a href=\"x\">&'</a>" }
+    print(render("<p>{{v}}</p>", vars, escape="html"))
+}
+"#;
+    run_test_bash_only(
+        "render_escape_html",
+        src,
+        "<p>&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;</p>",
+    );
+}
+
+#[test]
+fn exec_render_escape_shell() {
+    let src = r#"
+func main() {
+    let vars = { "v": "it's $HOME" }
+    let cmd = render("printf '%s' {{v}}", vars, escape="shell")
+    sh(cmd)
+}
+"#;
+    run_test_bash_only("render_escape_shell", src, "it's $HOME");
+}
+
+#[test]
+fn compile_render_rejects_non_map_argument() {
+    let src = r#"
+func main() {
+    print(render("{{a}}", "a=1"))
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("render() map must be a map variable"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for render() with a string map"),
+    }
+}
+
+#[test]
+fn compile_render_rejects_unknown_escape() {
+    let src = r#"
+func main() {
+    let vars = { "a": "1" }
+    print(render("{{a}}", vars, escape="url"))
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("render() escape must be literal"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for escape=\"url\""),
+    }
+}
+
+#[test]
+fn compile_render_posix_is_error() {
+    let src = r#"
+func main() {
+    let vars = { "a": "1" }
+    print(render("{{a}}", vars))
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Posix) {
+        Err(msg) => assert!(msg.contains("map/dict is only supported in Bash target"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for render() on POSIX"),
+    }
+}
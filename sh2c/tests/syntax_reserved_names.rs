@@ -0,0 +1,60 @@
+//! Names taken by the shell or the generated runtime are rejected.
+
+mod common;
+use common::{compile_to_shell, repo_root, try_compile_to_shell, TargetShell};
+
+fn err(src: &str) -> String {
+    try_compile_to_shell(src, TargetShell::Bash).unwrap_err()
+}
+
+#[test]
+fn runtime_prefix_is_out_of_reach() {
+    // Identifiers cannot start with `_`, so no source name reaches `__sh2_`.
+    for src in [
+        "func __sh2_check() {\n    print(\"x\")\n}\nfunc main() {\n}\n",
+        "func main() {\n    let __sh2_status = 1\n}\n",
+    ] {
+        assert!(try_compile_to_shell(src, TargetShell::Bash).is_err(), "{}", src);
+    }
+}
+
+#[test]
+fn shell_builtins_cannot_be_function_names() {
+    for name in ["test", "local", "printf", "read", "trap"] {
+        let e = err(&format!("func {}() {{\n    print(\"x\")\n}}\nfunc main() {{\n    {}()\n}}\n", name, name));
+        assert!(e.contains(&format!("Function name '{}' is reserved: it is a shell builtin the generated script relies on", name)), "{}", e);
+    }
+}
+
+#[test]
+fn programs_that_defined_builtins_now_fail() {
+    // These fixtures keep the spellings of two older fixtures that shadowed builtins.
+    for (name, line) in [("reserved_function_echo_error", "2:1"), ("reserved_function_test_error", "1:1")] {
+        let path = repo_root().join(format!("sh2c/tests/fixtures/{}.sh2", name));
+        let e = sh2c::driver::compile_file(&path, Default::default()).unwrap_err().msg;
+        let builtin = if name.contains("echo") { "echo" } else { "test" };
+        let expected = format!(
+            "{}.sh2:{}: Function name '{}' is reserved: it is a shell builtin the generated script relies on; choose a different name. [E0206]",
+            name, line, builtin
+        );
+        assert!(e.contains(&expected), "{}", e);
+    }
+}
+
+#[test]
+fn shell_variables_need_explicit_intent() {
+    let e = err("func main() {\n    let IFS = \",\"\n}\n");
+    assert!(e.contains("inline_test:2:9: variable name 'IFS' is reserved: the shell itself uses it; to change it on purpose, use `set env.IFS = ...` or `with env { IFS: ... }` [E0206]"), "{}", e);
+
+    let e = err("func build(PATH) {\n    print(PATH)\n}\nfunc main() {\n    build(\"/bin\")\n}\n");
+    assert!(e.contains("parameter name 'PATH' of 'build' is reserved"), "{}", e);
+
+    let e = err("func main() {\n    run(\"ls\") | each_line PWD {\n        print(PWD)\n    }\n}\n");
+    assert!(e.contains("variable name 'PWD' is reserved"), "{}", e);
+
+    // Saying so explicitly is fine, and ordinary names that merely look alike are too.
+    compile_to_shell(
+        "func main() {\n    set env.PATH = \"/usr/bin\"\n    with env { IFS: \",\" } {\n        run(\"true\")\n    }\n    let path = env.PATH\n    let Path = path\n    let testing = Path\n    print(testing)\n}\n",
+        TargetShell::Bash,
+    );
+}
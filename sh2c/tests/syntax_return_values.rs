@@ -0,0 +1,86 @@
+mod common;
+use common::*;
+
+const SPLIT_HOST: &str = r#"func split_host(addr) {
+    let port = after(addr, ":")
+    if port == "" {
+        return addr, "22"
+    }
+    return before(addr, ":"), port
+}
+"#;
+
+#[test]
+fn values_are_bound_without_a_subshell() {
+    let src = format!(
+        r#"{}func main() {{
+    let host, port = split_host("example.com:2222")
+    print(host & " " & port)
+    for a in ["a", "b:22"] {{
+        let h, p = split_host(a)
+        print(h & "/" & p)
+    }}
+}}
+"#,
+        SPLIT_HOST
+    );
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(&src, target);
+        assert!(!script.contains("$( split_host"), "{}", script);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "example.com 2222\na/22\nb/22\n");
+    }
+}
+
+#[test]
+fn targets_may_share_names_with_the_callee() {
+    let src = r#"func swap(a, b) {
+    return b, a
+}
+func main() {
+    let a, b = swap("1", "2")
+    print(a & b)
+    set a = "x"
+    let c, d = swap(a, b)
+    print(c & d)
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "21\n1x\n");
+    }
+}
+
+#[test]
+fn return_counts_are_checked() {
+    for (src, msg) in [
+        (
+            "func f() {\n    if true {\n        return \"a\", \"b\"\n    }\n    return \"a\"\n}\nfunc main() {\n    let x, y = f()\n}\n",
+            "function 'f' returns 2 values elsewhere",
+        ),
+        (
+            "func f() {\n    return \"a\", \"b\"\n}\nfunc main() {\n    print(f())\n}\n",
+            "call it as `let a, b = f(...)`",
+        ),
+        (
+            "func f() {\n    return \"a\", \"b\"\n}\nfunc main() {\n    let x, y, z = f()\n}\n",
+            "function 'f' returns 2 values, but 3 names are given",
+        ),
+        (
+            "func f() {\n    return \"a\"\n}\nfunc main() {\n    let x, y = f()\n}\n",
+            "function 'f' does not return multiple values",
+        ),
+        (
+            "@cache\nfunc f() {\n    return \"a\", \"b\"\n}\nfunc main() {\n    let x, y = f()\n}\n",
+            "@cache function 'f' cannot return multiple values",
+        ),
+    ] {
+        let err = try_compile_to_shell(src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}", err);
+    }
+}
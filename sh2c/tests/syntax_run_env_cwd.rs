@@ -0,0 +1,82 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_run_env_option() {
+    let src = r#"
+func main() {
+    let who = "w o"
+    run("sh", "-c", "echo \"$GREETING, $WHO\"", env={"GREETING": "hi", "WHO": who})
+    run("sh", "-c", "echo \"[${GREETING:-unset}]\"")
+}
+"#;
+    run_test_in_targets("run_env_option", src, "hi, w o\n[unset]");
+}
+
+#[test]
+fn exec_run_cwd_option_does_not_leak() {
+    let src = r#"
+func main() {
+    run("mkdir", "-p", "sub/inner")
+    run("sh", "-c", "basename \"$PWD\"", cwd="sub/inner")
+    run("sh", "-c", "echo $A; ls", env={"A": "1"}, cwd="sub")
+    run("sh", "-c", "test -d sub && echo still-outside")
+}
+"#;
+    run_test_in_targets("run_cwd_option", src, "inner\n1\ninner\nstill-outside");
+}
+
+#[test]
+fn exec_run_cwd_allow_fail_and_missing_dir() {
+    let src = r#"
+func main() {
+    run("mkdir", "sub")
+    run("false", cwd="sub", allow_fail=true)
+    print("status=" & status())
+    run("true", cwd="missing", allow_fail=true)
+    if status() != 0 {
+        print("missing dir failed")
+    }
+}
+"#;
+    run_test_in_targets("run_cwd_allow_fail", src, "status=1\nmissing dir failed");
+}
+
+#[test]
+fn compile_run_env_requires_map_literal() {
+    let src = r#"
+func main() {
+    run("make", env="A=1")
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("run() env= expects a map literal"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for env=\"A=1\""),
+    }
+}
+
+#[test]
+fn compile_run_env_rejects_invalid_name() {
+    let src = r#"
+func main() {
+    run("make", env={"BAD-NAME": "1"})
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("is not a valid environment variable name"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for env key BAD-NAME"),
+    }
+}
+
+#[test]
+fn compile_run_cwd_rejected_in_pipeline() {
+    let src = r#"
+func main() {
+    run("ls", cwd="/tmp") | run("wc", "-l")
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("cwd= is only supported on a standalone run(...) statement"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for cwd= in a pipeline"),
+    }
+}
@@ -0,0 +1,98 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_run_stdin_text() {
+    let src = r#"
+func main() {
+    let data = "alpha\nbeta x\n$HOME 'q' \\n"
+    run("grep", "x", stdin=text(data))
+    run("tail", "-n", "1", stdin=text(data))
+    run("wc", "-l", stdin=text("one"))
+}
+"#;
+    run_test_in_targets("run_stdin_text", src, "beta x\n$HOME 'q' \\n\n1");
+}
+
+#[test]
+fn exec_run_stdin_text_allow_fail_keeps_status() {
+    let src = r#"
+func main() {
+    run("grep", "zzz", stdin=text("abc"), allow_fail=true)
+    print("status=" & status())
+}
+"#;
+    run_test_in_targets("run_stdin_text_allow_fail", src, "status=1");
+}
+
+#[test]
+fn exec_with_redirect_stdin_text() {
+    let src = r#"
+func main() {
+    let data = "first\nsecond"
+    with redirect { stdin: text(data) } {
+        run("sed", "-n", "2p")
+    }
+}
+"#;
+    run_test_in_targets("with_redirect_stdin_text", src, "second");
+}
+
+#[test]
+fn exec_with_redirect_stdin_text_failure_is_fatal() {
+    let src = r#"
+func main() {
+    with redirect { stdin: text("abc") } {
+        run("grep", "nomatch")
+    }
+    print("unreachable")
+}
+"#;
+    for (target, shell) in [(TargetShell::Bash, "bash"), (TargetShell::Posix, "sh")] {
+        let script = compile_to_shell(src, target);
+        let (stdout, _stderr, status) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(status, Some(1), "{:?}: unexpected status", target);
+        assert!(!stdout.contains("unreachable"), "{:?}: failure inside block was ignored", target);
+    }
+}
+
+#[test]
+fn codegen_run_stdin_text_avoids_temp_files() {
+    let src = r#"
+func main() {
+    let v = "x"
+    run("cat", stdin=text(v))
+}
+"#;
+    let bash = compile_to_shell(src, TargetShell::Bash);
+    assert!(bash.contains("'cat' <<< \"$v\""), "bash output:\n{}", bash);
+    let posix = compile_to_shell(src, TargetShell::Posix);
+    assert!(posix.contains("printf '%s\\n' \"$v\" | 'cat'"), "posix output:\n{}", posix);
+    assert!(!posix.contains("__sh2_tmpfile"), "posix output:\n{}", posix);
+}
+
+#[test]
+fn compile_run_stdin_requires_text() {
+    let src = r#"
+func main() {
+    run("cat", stdin="raw")
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("run() stdin= expects text(expr)"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for stdin=\"raw\""),
+    }
+}
+
+#[test]
+fn compile_run_stdin_rejected_in_pipeline() {
+    let src = r#"
+func main() {
+    run("cat", stdin=text("a")) | run("wc", "-l")
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("stdin= is only supported on a standalone run(...) statement"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for stdin= in a pipeline"),
+    }
+}
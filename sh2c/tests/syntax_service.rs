@@ -0,0 +1,54 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_service_start_stop_lifecycle() {
+    let src = r#"
+func main() {
+    service_start("svc.pid", run("sh", "-c", "echo started; echo oops >&2; exec sleep 30"), "svc.log")
+    if service_running("svc.pid") {
+        print("running")
+    }
+    run("sh", "-c", "until grep -q oops svc.log; do sleep 0.1; done")
+    service_stop("svc.pid")
+    if !service_running("svc.pid") && !exists("svc.pid") {
+        print("stopped")
+    }
+    print(read_file("svc.log"))
+    service_stop("svc.pid")
+    print("stop is idempotent")
+}
+"#;
+    run_test_in_targets("service_lifecycle", src, "running\nstopped\nstarted\noops\nstop is idempotent");
+}
+
+#[test]
+fn exec_service_start_refuses_running_service() {
+    let src = r#"
+func main() {
+    service_start("svc.pid", run("sleep", "3"))
+    service_start("svc.pid", run("sleep", "3"))
+    print("unreachable")
+}
+"#;
+    for (target, shell) in [(TargetShell::Bash, "bash"), (TargetShell::Posix, "sh")] {
+        let script = compile_to_shell(src, target);
+        let (stdout, stderr, status) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(status, Some(1), "{:?}: unexpected status", target);
+        assert!(stderr.contains("service already running"), "{:?}: stderr was {}", target, stderr);
+        assert!(!stdout.contains("unreachable"), "{:?}: second start was not fatal", target);
+    }
+}
+
+#[test]
+fn compile_service_start_requires_run() {
+    let src = r#"
+func main() {
+    service_start("svc.pid", "sleep 30")
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("service_start() only accepts run(...) as the command"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for a non-run service command"),
+    }
+}
@@ -49,3 +49,51 @@ fn test_sh_block_fail_fast() {
     assert_ne!(status, Some(0), "Posix should have failed fast");
     assert!(!stdout.contains("should_not_print"));
 }
+
+#[test]
+fn test_sh_block_use_export_bridge() {
+    let src = r#"
+func main() {
+    let dir = "a b"
+    let label = "old"
+    sh use(dir) export(n, label) {
+        "n=$(printf '%s' \"$dir\" | wc -c | tr -d ' ')",
+        "label='from shell'"
+    }
+    print(dir & "|" & n & "|" & label)
+}
+"#;
+    run_test_in_targets("sh_block_use_export_bridge", src, "a b|3|from shell");
+}
+
+#[test]
+fn test_sh_block_bridge_rejects_unlisted_variables() {
+    let cases = [
+        (r#"sh use() { "ls $dir" }"#, "sh block reads sh2 variable 'dir' without listing it in use(...)"),
+        (r#"sh use() { "ls ${dir}/x" }"#, "sh block reads sh2 variable 'dir'"),
+        (r#"sh use(dir) { "dir=/" }"#, "sh block assigns sh2 variable 'dir' without listing it in export(...)"),
+        (r#"sh use() { "for dir in a b; do :; done" }"#, "sh block assigns sh2 variable 'dir'"),
+        (r#"sh use(missing) { "true" }"#, "undefined variable 'missing'"),
+        (r#"sh use(dir, dir) { "true" }"#, "'dir' is listed twice in sh use(...)"),
+    ];
+    for (block, expected) in cases {
+        let src = format!("func main() {{\n    let dir = \"/tmp\"\n    {}\n}}\n", block);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).expect_err(block);
+        assert!(err.contains(expected), "{}: {}", block, err);
+    }
+}
+
+#[test]
+fn test_sh_block_bridge_ignores_quoted_and_unrelated_names() {
+    // Single-quoted and escaped `$dir` are literal text; `$HOME` is not an sh2 variable;
+    // a plain `sh { ... }` block is not checked at all.
+    let src = r#"
+func main() {
+    let dir = "/tmp"
+    sh use() { "echo '$dir' \\$dir \"$HOME\" >/dev/null" }
+    sh { "echo \"$dir\" >/dev/null" }
+    print("ok")
+}
+"#;
+    run_test_in_targets("sh_block_bridge_quoted", src, "ok");
+}
@@ -0,0 +1,48 @@
+mod common;
+use common::*;
+
+fn error_for(body: &str) -> String {
+    let src = format!("func main() {{\n    {}\n}}\n", body);
+    try_compile_to_shell(&src, TargetShell::Bash).unwrap_err()
+}
+
+#[test]
+fn shell_variables_point_at_sh2_spelling() {
+    for (body, msg, help) in [
+        ("print($HOME)", "`$HOME` is shell syntax", "or `env.HOME` for an environment variable"),
+        ("print(${HOME})", "`${HOME}` is shell syntax", "use `HOME` for an sh2 variable"),
+        ("print($1)", "`$1` is shell syntax", "use `arg(1)`"),
+        ("let x = `ls`", "Backticks are shell syntax", "capture(run("),
+        ("print('hi')", "Single quotes are shell syntax", "sh2 strings are never interpolated"),
+    ] {
+        let err = error_for(body);
+        assert!(err.contains(msg), "{}", err);
+        assert!(err.contains(&format!("help: {}", help)) || err.contains(help), "{}", err);
+        assert!(err.contains("[E0103]"), "{}", err);
+    }
+}
+
+#[test]
+fn shell_command_lines_are_translated() {
+    for (body, msg, help) in [
+        ("echo \"hi\" $USER", "`echo \"hi\" $USER` is shell syntax; sh2 prints with print(...)", "print(\"hi\" & \" \" & env.USER)"),
+        ("echo hello world", "sh2 prints with print(...)", "print(\"hello world\")"),
+        ("ls -la && echo ok # list", "`ls -la` is shell syntax", "run(\"ls\", \"-la\") && print(\"ok\")"),
+        ("cat /etc/hosts | grep -v x", "`cat /etc/hosts` is shell syntax", "run(\"cat\", \"/etc/hosts\") | run(\"grep\", \"-v\", \"x\")"),
+        ("mkdir -p \"a b\" || true", "sh2 runs commands with run(...)", "run(\"mkdir\", \"-p\", \"a b\") || run(\"true\")"),
+        ("make", "`make` is shell syntax", "write `run(\"make\")` to run the command, or `make()` to call a function"),
+    ] {
+        let err = error_for(body);
+        assert!(err.contains(msg), "{}", err);
+        assert!(err.contains(&format!("help: write it as: {}", help)) || err.contains(help), "{}", err);
+    }
+}
+
+#[test]
+fn other_mistakes_keep_their_errors() {
+    assert!(error_for("x == 1").contains("Expected Equals, got EqEq"));
+    assert!(error_for("let y = $ HOME").contains("Expected LParen"));
+    // `$(...)` and `$"..."` are sh2 syntax.
+    let script = compile_to_shell("func main() {\n    let u = $(whoami)\n    print($\"{u}\")\n}\n", TargetShell::Bash);
+    assert!(script.contains("whoami"), "{}", script);
+}
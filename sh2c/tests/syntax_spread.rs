@@ -0,0 +1,66 @@
+mod common;
+use common::*;
+
+#[test]
+fn spread_passes_list_items_as_separate_words() {
+    let src = r#"func show(a, b, c) {
+    print(a & "|" & b & "|" & c)
+}
+
+func main() {
+    let xs = ["one", "two words"]
+    run("printf", "<%s>\n", ...xs)
+    let all = ["zero", ...xs, "three"]
+    print(count(all))
+    for x in all {
+        print(x)
+    }
+    show("a", ...xs)
+    let parts = [...split("p,q", ","), "r"]
+    print(count(parts) & " " & parts[2])
+    print(count([...args, "x"]))
+}
+"#;
+    let script = compile_to_shell(src, TargetShell::Bash);
+    let (stdout, stderr, code) = run_shell_script(&script, "bash", &[], &["u v", "w"], None, None);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(
+        stdout,
+        "<one>\n<two words>\n4\nzero\none\ntwo words\nthree\na|one|two words\n3 r\n3\n"
+    );
+
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
+
+#[test]
+fn spreading_args_works_on_both_targets() {
+    let src = r#"func main() {
+    run("printf", "[%s]\n", "first", ...args)
+    run("printf", "[%s]\n", ...["x", "y z"])
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &["a", "b c"], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "[first]\n[a]\n[b c]\n[x]\n[y z]\n", "{:?}", target);
+    }
+}
+
+#[test]
+fn misplaced_spreads_are_errors() {
+    for (body, target, msg) in [
+        ("let xs = [\"a\"]\n    print(\"a\" & ...xs)", TargetShell::Bash, "`...` can only be used to spread a list into call arguments or list items"),
+        ("let s = \"a\"\n    run(\"echo\", ...s)", TargetShell::Bash, "`...` needs a list, but 's' is not a list"),
+        ("run(\"echo\", ...env.HOME)", TargetShell::Bash, "`...` needs a list"),
+        ("let xs = [\"a\"]\n    run(\"echo\", ...xs)", TargetShell::Posix, "spreading a list variable is not supported in POSIX sh"),
+    ] {
+        let src = format!("func main() {{\n    {}\n}}\n", body);
+        let err = try_compile_to_shell(&src, target).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", body, err);
+    }
+}
@@ -0,0 +1,14 @@
+mod common;
+use common::*;
+use sh2c::codegen::TargetShell;
+
+#[test]
+fn codegen_status_after_static_assign() {
+    assert_codegen_matches_snapshot("status_after_static_assign");
+}
+
+#[test]
+fn exec_status_after_static_assign() {
+    assert_exec_matches_fixture_target("status_after_static_assign", TargetShell::Bash);
+    assert_exec_matches_fixture_target("status_after_static_assign", TargetShell::Posix);
+}
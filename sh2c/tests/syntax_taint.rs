@@ -0,0 +1,135 @@
+mod common;
+use assert_cmd::Command;
+use common::*;
+use predicates::prelude::*;
+
+#[test]
+fn taint_flows_from_sources_into_sh() {
+    let src = r#"
+func main() {
+    let name = input("name? ")
+    let greeting = "hello " & name
+    sh("echo " & greeting)
+    sh("ls " & env("HOME"))
+    sh("cat " & arg(1))
+}
+"#;
+    let w = taint_warnings(src);
+    assert_eq!(w.len(), 3, "{:#?}", w);
+    assert!(w[0].contains("inline_test:5:8: tainted value from input() reaches the sh() command string"), "{}", w[0]);
+    assert!(w[1].contains("tainted value from env()"), "{}", w[1]);
+    assert!(w[2].contains("tainted value from arg()"), "{}", w[2]);
+}
+
+#[test]
+fn taint_clean_values_and_reassignment() {
+    let src = r#"
+func main() {
+    let x = input("x? ")
+    let n = len(x)
+    sh("echo " & n)
+    set x = "fixed"
+    sh("echo " & x)
+    sh("printf '%s' \"$1\"", args=args())
+}
+"#;
+    assert!(taint_warnings(src).is_empty());
+}
+
+#[test]
+fn taint_sanitized_by_matches_or_equality_guard() {
+    let src = r#"
+func main() {
+    let x = input("x? ")
+    if matches(x, "^[a-z]+$") {
+        sh("echo " & x)
+    }
+    if x == "yes" {
+        sh("echo " & x)
+    } else {
+        sh("echo other " & x)
+    }
+    sh("echo after " & x)
+}
+"#;
+    let w = taint_warnings(src);
+    assert_eq!(w.len(), 2, "{:#?}", w);
+    assert!(w[0].contains("inline_test:10:12:"), "{}", w[0]);
+    assert!(w[1].contains("inline_test:12:8:"), "{}", w[1]);
+}
+
+#[test]
+fn taint_crosses_function_parameters_and_branches() {
+    let src = r#"
+func show(c) {
+    sh("echo " & c)
+}
+
+func main() {
+    let v = "safe"
+    if exists("/nonexistent") {
+        set v = env("USER")
+    }
+    for line in stdin_lines() {
+        sh("echo " & line)
+    }
+    show(v)
+}
+"#;
+    let w = taint_warnings(src);
+    assert_eq!(w.len(), 2, "{:#?}", w);
+    assert!(w[0].contains("inline_test:3:8: tainted value from env()"), "{}", w[0]);
+    assert!(w[1].contains("tainted value from stdin"), "{}", w[1]);
+}
+
+#[test]
+fn taint_exec_program_and_shell_arguments() {
+    let src = r#"
+func main() {
+    let p = arg(1)
+    exec("bash", "-c", "echo " & p)
+}
+"#;
+    let w = taint_warnings(src);
+    assert_eq!(w.len(), 1, "{:#?}", w);
+    assert!(w[0].contains("is passed to a shell by exec()"), "{}", w[0]);
+
+    let src = r#"
+func main() {
+    exec(env("EDITOR"), "notes.txt")
+}
+"#;
+    let w = taint_warnings(src);
+    assert_eq!(w.len(), 1, "{:#?}", w);
+    assert!(w[0].contains("tainted value from env() names the program run by exec()"), "{}", w[0]);
+
+    let src = r#"
+func main() {
+    exec("printf", "%s\n", arg(1))
+}
+"#;
+    assert!(taint_warnings(src).is_empty());
+}
+
+#[test]
+fn cli_warns_by_default_and_denies_with_flag() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("t.sh2");
+    std::fs::write(&script, "func main() {\n    sh(\"echo \" & arg(1))\n}\n").unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .arg("--check")
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout("OK\n")
+        .stderr(predicate::str::contains("warning: t.sh2:2:8: tainted value from arg()"));
+
+    Command::new(env!("CARGO_BIN_EXE_sh2c"))
+        .arg("--deny-tainted-sh")
+        .arg(&script)
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr(predicate::str::contains("compile error: t.sh2:2:8: tainted value from arg()"));
+}
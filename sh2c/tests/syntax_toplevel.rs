@@ -2,35 +2,33 @@ mod common;
 use common::*;
 
 #[test]
-fn compile_toplevel_print_fails() {
-    assert_codegen_panics(
-        "toplevel_print",
-        "Top-level statements are not allowed",
-    );
+fn compile_toplevel_print_implicit_main() {
+    assert_codegen_matches_snapshot("toplevel_print");
+    assert_codegen_matches_snapshot_target("toplevel_print", TargetShell::Posix);
+    assert_exec_matches_fixture("toplevel_print");
 }
 
 #[test]
-fn compile_toplevel_let_fails() {
-    assert_codegen_panics(
-        "toplevel_let_and_run",
-        "Top-level statements are not allowed",
-    );
+fn compile_toplevel_let_implicit_main() {
+    assert_codegen_matches_snapshot("toplevel_let_and_run");
+    assert_codegen_matches_snapshot_target("toplevel_let_and_run", TargetShell::Posix);
+    assert_exec_matches_fixture("toplevel_let_and_run");
 }
 
 #[test]
 fn compile_toplevel_with_explicit_main_fails() {
     assert_codegen_panics(
         "toplevel_with_explicit_main_is_error",
-        "Top-level statements are not allowed",
+        "Top-level statements are not allowed in a file that defines func main()",
     );
 }
 
 #[test]
 fn compile_imported_module_has_toplevel_fails() {
-    // Parser now catches this before loader
+    // Only the entry file may be script-style; the loader rejects the import.
     assert_codegen_panics(
         "imports/module_has_toplevel_is_error/main",
-        "Top-level statements are not allowed",
+        "Top-level statements are not allowed in an imported module",
     );
 }
 
@@ -44,3 +42,27 @@ fn compile_minimal_main_works() {
     assert_codegen_matches_snapshot("toplevel_minimal_main_ok");
     assert_exec_matches_fixture("toplevel_minimal_main_ok");
 }
+
+#[test]
+fn script_style_file_calls_its_functions() {
+    let src = r#"func greet(name) {
+    print("hello " & name)
+}
+init {
+    let GREETING = "start"
+}
+print(GREETING)
+let who = "world"
+greet(who)
+on_exit(main) {
+    print("done")
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "start\nhello world\ndone\n");
+    }
+}
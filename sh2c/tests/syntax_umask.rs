@@ -0,0 +1,86 @@
+mod common;
+use common::*;
+
+#[test]
+fn with_umask_applies_to_the_body_and_restores_the_previous_mask() {
+    let src = r#"func main() {
+    let dir = env.WORK
+    with umask(077) {
+        write_file(dir & "/secret", "x")
+        let inner = capture(run("sh", "-c", "umask"))
+        print(inner)
+        with umask("0027") {
+            print(capture(run("sh", "-c", "umask")))
+        }
+    }
+    print(inner)
+    print(capture(run("sh", "-c", "umask")))
+    run("sh", "-c", "ls -l \"$1\" | cut -c1-10", "ls", dir & "/secret")
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        assert!(script.contains("umask 077"), "{}", script);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().to_str().unwrap();
+        let wrapped = script.replacen("\n", "\numask 022\n", 1);
+        let (stdout, stderr, code) = run_shell_script(&wrapped, shell, &[("WORK", work)], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "0077\n0027\n0077\n0022\n-rw-------\n", "{:?}", target);
+    }
+}
+
+#[test]
+fn a_failing_body_inside_try_restores_the_mask_before_catch() {
+    let src = r#"func main() {
+    try {
+        with umask(077) {
+            run("false")
+            print("not reached")
+        }
+    } catch {
+        print("caught " & status())
+    }
+    print(capture(run("sh", "-c", "umask")))
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target).replacen("\n", "\numask 022\n", 1);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "caught 1\n0022\n", "{:?}", target);
+    }
+}
+
+#[test]
+fn with_umask_formats_round_trip() {
+    let src = r#"func main() {
+    with umask("0077") {
+        print("private")
+    }
+}
+"#;
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
+
+#[test]
+fn with_umask_is_checked() {
+    for (body, msg) in [
+        ("with umask(\"u=rwx\") {\n        print(\"x\")\n    }", "umask must be an octal literal such as 022"),
+        ("with umask(0800) {\n        print(\"x\")\n    }", "umask must be an octal literal"),
+        ("let m = \"022\"\n    with umask(m) {\n        print(\"x\")\n    }", "umask must be an octal literal"),
+        ("with umask(077) {\n        return\n    }", "cannot be used in a `with umask(...)` block"),
+        ("for x in [\"a\"] {\n        with umask(077) {\n            if x == \"a\" {\n                break\n            }\n        }\n    }", "cannot be used in a `with umask(...)` block"),
+    ] {
+        let src = format!("func main() {{\n    {}\n}}\n", body);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains(msg), "{}: {}", body, err);
+    }
+    let inner_loop = "func main() {\n    with umask(077) {\n        for x in [\"a\"] {\n            break\n        }\n    }\n}\n";
+    assert!(try_compile_to_shell(inner_loop, TargetShell::Bash).is_ok());
+}
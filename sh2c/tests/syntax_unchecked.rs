@@ -0,0 +1,53 @@
+mod common;
+use common::*;
+
+#[test]
+fn exec_unchecked_basic() {
+    assert_exec_matches_fixture_target("unchecked_basic", TargetShell::Bash);
+    assert_exec_matches_fixture_target("unchecked_basic", TargetShell::Posix);
+}
+
+#[test]
+fn codegen_unchecked_basic() {
+    assert_codegen_matches_snapshot("unchecked_basic");
+}
+
+#[test]
+fn unchecked_covers_nested_blocks() {
+    let src = r#"func main() {
+    unchecked {
+        for f in ["a", "b"] {
+            run("sh", "-c", "exit 3")
+            print($"{f}: {status()}")
+        }
+    }
+    print("done")
+}
+"#;
+    run_test_in_targets("unchecked_nested", src, "a: 3\nb: 3\ndone");
+}
+
+#[test]
+fn run_check_false_is_allow_fail() {
+    let src = r#"func main() {
+    run("sh", "-c", "exit 6", check=false)
+    print($"status: {status()}")
+    run("true", check=true)
+}
+"#;
+    run_test_in_targets("run_check_false", src, "status: 6");
+}
+
+#[test]
+fn check_option_is_validated() {
+    let cases = [
+        (r#"run("true", check=maybe)"#, "check must be true or false"),
+        (r#"run("true", check=false, allow_fail=true)"#, "check and allow_fail cannot both be given"),
+        (r#"run("true", allow_fail=true, check=false)"#, "check and allow_fail cannot both be given"),
+    ];
+    for (call, expected) in cases {
+        let src = format!("func main() {{\n    let maybe = true\n    {}\n}}\n", call);
+        let err = try_compile_to_shell(&src, TargetShell::Bash).expect_err(call);
+        assert!(err.contains(expected), "{}: {}", call, err);
+    }
+}
@@ -0,0 +1,57 @@
+mod common;
+use common::*;
+
+#[test]
+fn ignored_result_status_warns() {
+    let stderr = check_warnings(
+        r#"func main() {
+    let r = try_run("make")
+    print(r.stdout)
+    let out = capture(run("ls", "missing"), allow_fail=true)
+    print(out)
+    if true {
+        let s = try_run("true")
+        print(s.stderr)
+    }
+    let t = try_run("false")
+    let u = try_run("true")
+    print(t.stderr & u.status)
+}
+"#,
+    );
+    assert!(stderr.contains("warning: main.sh2:2:9: status of 'r' is never checked: try_run() keeps going when the command fails, so the failure is silently ignored; read `r.status` (or `status()` right after the call) [W0008]"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:4:9: status of 'out' is never checked: capture(..., allow_fail=true) keeps going"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:7:13: status of 's' is never checked"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:10:9: status of 't' is never checked"), "{}", stderr);
+    assert_eq!(stderr.matches("[W0008]").count(), 4, "{}", stderr);
+}
+
+#[test]
+fn inspected_result_status_does_not_warn() {
+    let stderr = check_warnings(
+        r#"func main() {
+    let r = try_run("make")
+    if r.status != 0 {
+        print_err(r.stderr)
+    }
+    let out = capture(run("ls", "missing"), allow_fail=true)
+    if status() != 0 {
+        print("ls failed with status " & status())
+    }
+    let n = capture(run("wc", "-l", allow_fail=true))
+    print(n & " " & n.status)
+    let plain = capture(run("true"))
+    print(out & plain)
+}
+"#,
+    );
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn status_check_is_not_required_to_compile() {
+    let script = compile_to_shell("func main() {\n    let r = try_run(\"false\")\n    print(\"done\")\n}\n", TargetShell::Bash);
+    let (stdout, _, code) = run_bash_script(&script, &[], &[]);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "done\n");
+}
@@ -0,0 +1,56 @@
+mod common;
+use common::*;
+
+#[test]
+fn unicode_identifiers_compile_to_ascii_names() {
+    let src = r#"func grüße(名前) {
+    return "Hallo " & 名前
+}
+
+func main() {
+    let größe = "groß"
+    let 名前 = "世界"
+    print(grüße(名前))
+    print(größe)
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        assert!(script.contains("gr__U0000F6__U0000DFe="), "{}", script);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "Hallo 世界\ngroß\n");
+    }
+
+    let err = try_compile_to_shell("func main() {\n    print(größe)\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("undefined variable 'größe'"), "{}", err);
+
+    let sm = sh2c::span::SourceMap::new(src.to_string());
+    let tokens = sh2c::lexer::lex(&sm, src).unwrap();
+    let program = sh2c::parser::parse(&tokens, &sm, "inline_test").unwrap();
+    assert_eq!(sh2c::formatter::format_program(&program), src);
+}
+
+#[test]
+fn len_counts_characters_and_bytes_len_bytes() {
+    let src = r#"func main() {
+    let s = "größe"
+    print(len(s))
+    print(bytes_len(s))
+    print(len(""))
+    print(len("a\nb\n"))
+    print(len("日本") + 1)
+}
+"#;
+    for target in [TargetShell::Bash, TargetShell::Posix] {
+        let script = compile_to_shell(src, target);
+        let shell = if target == TargetShell::Bash { "bash" } else { "sh" };
+        let (stdout, stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(code, Some(0), "{:?}: {}", target, stderr);
+        assert_eq!(stdout, "5\n7\n0\n4\n3\n", "{:?}", target);
+    }
+
+    let err = try_compile_to_shell("func main() {\n    print(bytes_len())\n}\n", TargetShell::Bash).unwrap_err();
+    assert!(err.contains("bytes_len() requires exactly 1 argument"), "{}", err);
+}
@@ -241,7 +241,7 @@ fn test_binder_err_case_no_wildcard() {
 #[test]
 fn test_parse_err_top_level_shim() {
     let (_, stderr) = compile_to_bash_test("fixtures/parse_err_top_level_shim.sh2");
-    assert!(stderr.contains("Top-level statements are not allowed"));
+    assert!(stderr.contains("Unexpected token: EachLine"));
 }
 
 #[test]
@@ -269,3 +269,14 @@ fn test_for_loop_zero_iter_preserves() {
     let (stdout, _, _) = run_bash_script(&script, &[], &[]);
     assert_eq!(stdout.trim(), "after: 'preserved'");
 }
+
+#[test]
+fn test_undeclared_in_capture_args_is_error() {
+    for src in [
+        r#"func main(){ let o = capture(run("echo", b)) print(o) }"#,
+        r#"func main(){ let o = capture(run("echo", "x") | run("grep", b), allow_fail=true) print(o) }"#,
+    ] {
+        let err = try_compile_to_shell(src, TargetShell::Bash).unwrap_err();
+        assert!(err.contains("undefined variable 'b'"), "{}", err);
+    }
+}
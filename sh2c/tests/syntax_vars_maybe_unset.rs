@@ -0,0 +1,89 @@
+//! Warnings for variables that Policy A accepts but that may be unset at run time.
+
+mod common;
+use common::*;
+
+#[test]
+fn shell_reads_of_partially_assigned_variables_warn() {
+    let stderr = check_warnings(
+        r#"func main() {
+    if arg(1) == "v" {
+        let level = "debug"
+    }
+    sh("tool --log $level")
+    sh {
+        "echo ${level:-none} '$level'",
+        "echo ${level}",
+    }
+    let out = capture(sh("echo \"$level\""), allow_fail=true)
+    print(out)
+}
+"#,
+    );
+    assert!(stderr.contains("warning: main.sh2:5:8: variable 'level' may be unset here: shell code reads it, but only some paths assign it (line 3); under `set -u` that is an unbound-variable error [W0006]"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:6:5: variable 'level' may be unset here"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:10:26: variable 'level' may be unset here"), "{}", stderr);
+    assert_eq!(stderr.matches("[W0006]").count(), 3, "{}", stderr);
+}
+
+#[test]
+fn nested_let_shadowing_an_earlier_branch_warns() {
+    let stderr = check_warnings(
+        r#"func main() {
+    if arg(1) == "c" {
+        let path = "/var/cache"
+        print(path)
+    }
+    for f in ["a", "b"] {
+        let path = f
+        print(path)
+    }
+    while false {
+        run("ls") | each_line path {
+            print(path)
+        }
+    }
+}
+"#,
+    );
+    assert!(stderr.contains("warning: main.sh2:7:13: variable 'path' shadows an earlier declaration at line 3 that may still be set here; both are the same shell variable, so rename this one or declare it once and use `set path = ...` [W0007]"), "{}", stderr);
+    assert!(stderr.contains("main.sh2:11:31: variable 'path' shadows an earlier declaration at line 7"), "{}", stderr);
+}
+
+#[test]
+fn definite_and_disjoint_declarations_do_not_warn() {
+    let stderr = check_warnings(
+        r#"func main() {
+    if arg(1) == "a" {
+        let mode = "a"
+    } else {
+        let mode = "b"
+    }
+    sh("echo $mode")
+    if arg(2) == "x" {
+        let y = 1
+        print(y)
+    } elif arg(2) == "z" {
+        let y = 2
+        print(y)
+    }
+    let y = 100
+    sh("echo $y $HOME")
+    for i in [1, 2] {
+        let tmp = i
+        print(tmp)
+    }
+    case arg(3) {
+        "q" => { let c = 1
+            print(c) }
+        _ => { let c = 2
+            print(c) }
+    }
+    sh use(mode) {
+        "echo \"$mode\"",
+    }
+}
+"#,
+    );
+    assert_eq!(stderr, "");
+}
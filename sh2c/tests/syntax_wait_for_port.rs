@@ -0,0 +1,81 @@
+mod common;
+use common::*;
+use std::net::TcpListener;
+
+fn has_nc() -> bool {
+    std::process::Command::new("sh")
+        .arg("-c")
+        .arg("command -v nc")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+fn targets() -> Vec<(TargetShell, &'static str)> {
+    let mut t = vec![(TargetShell::Bash, "bash")];
+    if has_nc() {
+        t.push((TargetShell::Posix, "sh"));
+    } else {
+        eprintln!("Skipping POSIX wait_for_port checks: nc not found");
+    }
+    t
+}
+
+#[test]
+fn exec_wait_for_port_open_listener() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port().to_string();
+    let src = r#"
+func main() {
+    wait_for_port("127.0.0.1", env.SH2_TEST_PORT, timeout=5)
+    if wait_for_port("127.0.0.1", env.SH2_TEST_PORT, timeout=0) {
+        print("open")
+    }
+}
+"#;
+    for (target, shell) in targets() {
+        let script = compile_to_shell(src, target);
+        let (stdout, stderr, status) =
+            run_shell_script(&script, shell, &[("SH2_TEST_PORT", &port)], &[], None, None);
+        assert_eq!(status, Some(0), "{:?}: stderr was {}", target, stderr);
+        assert_eq!(stdout.trim(), "open", "{:?}", target);
+    }
+}
+
+#[test]
+fn exec_wait_for_port_timeout() {
+    let port = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port().to_string()
+    };
+    let src = r#"
+func main() {
+    if !wait_for_port("127.0.0.1", env.SH2_TEST_PORT, timeout=0) {
+        print("closed")
+    }
+    wait_for_port("127.0.0.1", env.SH2_TEST_PORT, timeout=1, interval=1)
+    print("unreachable")
+}
+"#;
+    for (target, shell) in targets() {
+        let script = compile_to_shell(src, target);
+        let (stdout, stderr, status) =
+            run_shell_script(&script, shell, &[("SH2_TEST_PORT", &port)], &[], None, None);
+        assert_eq!(status, Some(1), "{:?}: stderr was {}", target, stderr);
+        assert_eq!(stdout.trim(), "closed", "{:?}", target);
+        assert!(stderr.contains("wait_for_port: timed out after 1s"), "{:?}: stderr was {}", target, stderr);
+    }
+}
+
+#[test]
+fn compile_wait_for_port_rejects_unknown_option() {
+    let src = r#"
+func main() {
+    wait_for_port("localhost", 80, retries=3)
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("Unknown option \"retries\" for wait_for_port()"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for retries="),
+    }
+}
@@ -28,3 +28,57 @@ fn exec_write_file_error_is_dir_posix() {
     let (_stdout, stderr) = compile_and_run_err("write_file_error_is_dir", TargetShell::Posix);
     assert!(stderr.contains("Is a directory") || stderr.contains("directory"));
 }
+
+#[test]
+fn exec_write_file_atomic_and_mode() {
+    let src = r#"
+func main() {
+    run("mkdir", "cfg")
+    write_file("cfg/app.conf", "old", atomic=true)
+    write_file("cfg/app.conf", "a=1", atomic=true, mode=0640)
+    write_file("plain.txt", "p", mode="0600")
+    run("sh", "-c", "ls -l cfg/app.conf plain.txt | cut -c1-10; cat cfg/app.conf; echo; ls -A cfg")
+}
+"#;
+    run_test_in_targets("write_file_atomic", src, "-rw-r-----\n-rw-------\na=1\napp.conf");
+}
+
+#[test]
+fn exec_write_file_atomic_missing_dir_fails() {
+    let src = r#"
+func main() {
+    write_file("missing/app.conf", "new", atomic=true)
+    print("unreachable")
+}
+"#;
+    for (target, shell) in [(TargetShell::Bash, "bash"), (TargetShell::Posix, "sh")] {
+        let script = compile_to_shell(src, target);
+        let (stdout, _stderr, code) = run_shell_script(&script, shell, &[], &[], None, None);
+        assert_eq!(stdout, "");
+        assert_ne!(code, Some(0));
+    }
+}
+
+#[test]
+fn compile_write_file_atomic_rejects_append() {
+    let src = r#"
+func main() {
+    write_file("log.txt", "x", true, atomic=true)
+}
+"#;
+    match try_compile_to_shell(src, TargetShell::Bash) {
+        Err(msg) => assert!(msg.contains("cannot be combined with append"), "Unexpected error: {}", msg),
+        Ok(_) => panic!("Expected compilation failure for atomic append"),
+    }
+}
+
+#[test]
+fn compile_write_file_rejects_bad_mode() {
+    for mode in ["\"rw\"", "99", "\"01777x\""] {
+        let src = format!("func main() {{\n    write_file(\"f\", \"x\", mode={})\n}}\n", mode);
+        match try_compile_to_shell(&src, TargetShell::Bash) {
+            Err(msg) => assert!(msg.contains("mode must be an octal permission literal"), "Unexpected error: {}", msg),
+            Ok(_) => panic!("Expected compilation failure for mode={}", mode),
+        }
+    }
+}
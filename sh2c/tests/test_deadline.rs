@@ -0,0 +1,52 @@
+//! Integration tests for `with deadline(...)` blocks.
+//!
+//! Everything started inside the block writes to the script's stdout, so a
+//! process that survives the deadline keeps `output()` blocked; the
+//! elapsed-time check below catches it.
+
+mod common;
+use common::*;
+use std::process::Command;
+
+#[test]
+fn test_deadline_block_within_time() {
+    for (target, shell) in shells() {
+        let output = run_fixture("deadline_fast", target, shell);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("block done\nafter: 0"), "{}: {}", shell, stdout);
+        assert_eq!(output.status.code(), Some(0), "{}", shell);
+    }
+}
+
+#[test]
+fn test_deadline_kills_block_and_its_children() {
+    for (target, shell) in shells() {
+        let output = run_fixture("deadline_exceeded", target, shell);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!stdout.contains("should not reach here"), "{}: {}", shell, stdout);
+        assert!(stdout.contains("caught: 124"), "{}: {}", shell, stdout);
+        assert!(stderr.contains("deadline of 1s exceeded"), "{}: {}", shell, stderr);
+        assert_eq!(output.status.code(), Some(0), "{}", shell);
+    }
+}
+
+#[test]
+fn test_deadline_aborts_with_124() {
+    for (target, shell) in shells() {
+        let output = run_fixture("deadline_abort", target, shell);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(!stdout.contains("should not reach here"), "{}: {}", shell, stdout);
+        assert_eq!(output.status.code(), Some(124), "{}", shell);
+    }
+}
+
+#[test]
+fn test_deadline_rejects_bad_literal() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bad.sh2");
+    std::fs::write(&path, "func main() {\n    with deadline(\"5 minutes\") {\n        print(\"x\")\n    }\n}\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_sh2c")).arg(&path).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid deadline \"5 minutes\""));
+}
@@ -0,0 +1,65 @@
+//! Integration tests for wait_any(pids), the wait_all() failure report, and
+//! the exit trap that kills spawned jobs.
+//!
+//! A background `sleep 30` that is left running keeps the script's stdout
+//! open, so `output()` would block until it ends; the elapsed-time checks
+//! below catch jobs that were not killed.
+
+mod common;
+use common::*;
+use std::process::Command;
+
+#[test]
+fn test_wait_any_returns_first_and_kills_the_rest() {
+    for (target, shell) in shells() {
+        let output = run_fixture("wait_any_first", target, shell);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("first done: 4"), "{}: {}", shell, stdout);
+        assert!(stdout.contains("status: 4"), "{}: {}", shell, stdout);
+        assert_eq!(output.status.code(), Some(0), "{}", shell);
+    }
+}
+
+#[test]
+fn test_wait_any_default_aborts() {
+    for (target, shell) in shells() {
+        let output = run_fixture("wait_any_abort", target, shell);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(!stdout.contains("should not reach here"), "{}: {}", shell, stdout);
+        assert_eq!(output.status.code(), Some(2), "{}", shell);
+    }
+}
+
+#[test]
+fn test_wait_all_reports_every_status() {
+    for (target, shell) in shells() {
+        let output = run_fixture("wait_all_report", target, shell);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("wait_all: 2 job(s) failed; exit statuses in list order: 0 3 1"),
+            "{}: {}",
+            shell,
+            stderr
+        );
+        assert_eq!(output.status.code(), Some(3), "{}", shell);
+    }
+}
+
+#[test]
+fn test_unwaited_spawn_is_killed_on_exit() {
+    for (target, shell) in shells() {
+        let output = run_fixture("spawn_orphan_killed", target, shell);
+        assert!(String::from_utf8_lossy(&output.stdout).contains("started"));
+        assert_eq!(output.status.code(), Some(0), "{}", shell);
+    }
+}
+
+#[test]
+fn test_wait_any_rejects_empty_list() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("empty.sh2");
+    std::fs::write(&path, "func main() {\n    let rc = wait_any([])\n}\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_sh2c")).arg(&path).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("wait_any() needs at least one PID"));
+}
@@ -0,0 +1,89 @@
+//! Defaults for `--target` and `--shell` taken from the environment and from
+//! an optional config file, so a POSIX-only machine can say `posix` once.
+//!
+//! Precedence, highest first: command-line flags, `SH2DO_TARGET` /
+//! `SH2DO_SHELL`, then `$XDG_CONFIG_HOME/sh2do/config.toml` (or
+//! `~/.config/sh2do/config.toml`). The file holds plain `key = "value"` lines:
+//!
+//! ```toml
+//! # ~/.config/sh2do/config.toml
+//! target = "posix"
+//! shell = "sh"
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const TARGETS: &[&str] = &["bash", "posix"];
+const SHELLS: &[&str] = &["bash", "sh"];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Defaults {
+    pub target: Option<String>,
+    pub shell: Option<String>,
+}
+
+/// Reads the config file (if any) and overlays the environment variables.
+pub fn load() -> Result<Defaults, String> {
+    let mut defaults = match config_path() {
+        Some(path) if path.is_file() => {
+            let text = fs::read_to_string(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            parse_file(&text).map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+        _ => Defaults::default(),
+    };
+    if let Some(v) = env_value("SH2DO_TARGET") {
+        defaults.target = Some(check("SH2DO_TARGET", "target", &v, TARGETS)?);
+    }
+    if let Some(v) = env_value("SH2DO_SHELL") {
+        defaults.shell = Some(check("SH2DO_SHELL", "shell", &v, SHELLS)?);
+    }
+    Ok(defaults)
+}
+
+fn env_value(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn config_path() -> Option<PathBuf> {
+    let base = env_value("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env_value("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("sh2do").join("config.toml"))
+}
+
+fn check(origin: &str, what: &str, value: &str, allowed: &[&str]) -> Result<String, String> {
+    if allowed.contains(&value) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("{}: invalid {} '{}' (expected {})", origin, what, value, allowed.join(" or ")))
+    }
+}
+
+/// Parses the `key = "value"` subset of TOML the config file uses.
+pub fn parse_file(text: &str) -> Result<Defaults, String> {
+    let mut defaults = Defaults::default();
+    for (n, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let origin = format!("line {}", n + 1);
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("{}: expected key = \"value\"", origin));
+        };
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .ok_or_else(|| format!("{}: value must be a quoted string", origin))?;
+        match key.trim() {
+            "target" => defaults.target = Some(check(&origin, "target", value, TARGETS)?),
+            "shell" => defaults.shell = Some(check(&origin, "shell", value, SHELLS)?),
+            other => return Err(format!("{}: unknown key '{}' (expected target or shell)", origin, other)),
+        }
+    }
+    Ok(defaults)
+}
@@ -0,0 +1,41 @@
+//! `--container <image>`: run a generated script inside a throwaway container.
+//!
+//! The script is bind-mounted read-only at [`SCRIPT_PATH`] and run with the
+//! image's own `bash` or `sh`, so a script meant for alpine or busybox can be
+//! tried against the real thing without leaving the host.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where the script appears inside the container.
+pub const SCRIPT_PATH: &str = "/sh2do/script.sh";
+
+/// The container engine to use: `SH2DO_CONTAINER_RUNTIME` if set, otherwise
+/// `docker` or `podman`, whichever is found on `PATH` first.
+pub fn detect_runtime() -> Result<PathBuf, String> {
+    if let Some(runtime) = std::env::var_os("SH2DO_CONTAINER_RUNTIME").filter(|v| !v.is_empty()) {
+        return Ok(PathBuf::from(runtime));
+    }
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    for name in ["docker", "podman"] {
+        for dir in std::env::split_paths(&path) {
+            let exe = dir.join(name).with_extension(std::env::consts::EXE_EXTENSION);
+            if exe.is_file() {
+                return Ok(exe);
+            }
+        }
+    }
+    Err("--container needs docker or podman on PATH (or SH2DO_CONTAINER_RUNTIME)".to_string())
+}
+
+/// `<runtime> run --rm -i -v <script>:/sh2do/script.sh:ro <image> <shell> /sh2do/script.sh`;
+/// the caller appends the script's arguments.
+pub fn run_command(runtime: &Path, image: &str, shell: &str, script: &Path) -> Command {
+    let mut cmd = Command::new(runtime);
+    cmd.args(["run", "--rm", "-i", "-v"])
+        .arg(format!("{}:{}:ro", script.display(), SCRIPT_PATH))
+        .arg(image)
+        .arg(shell)
+        .arg(SCRIPT_PATH);
+    cmd
+}
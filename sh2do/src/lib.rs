@@ -1,7 +1,12 @@
 use std::process::ExitCode;
 
+pub mod config;
+pub mod container;
+pub mod platform;
+pub mod remote;
+
 pub mod exit_code {
-    use std::process::ExitCode;
+    use std::process::{ExitCode, ExitStatus};
 
     pub fn from_i32(code: i32) -> ExitCode {
         if code >= 0 && code <= 255 {
@@ -10,6 +15,103 @@ pub mod exit_code {
             ExitCode::from(1)
         }
     }
+
+    /// The status a shell reports for a child killed by `signal`: 128+N.
+    pub fn from_signal(signal: i32) -> ExitCode {
+        from_i32(128 + signal)
+    }
+
+    /// The status sh2do exits with for a finished script, without remapping.
+    pub fn from_status(status: ExitStatus) -> ExitCode {
+        match (status.code(), signal(status)) {
+            (Some(code), _) => from_i32(code),
+            (None, Some(signal)) => from_signal(signal),
+            (None, None) => ExitCode::from(1),
+        }
+    }
+
+    #[cfg(unix)]
+    fn signal(status: ExitStatus) -> Option<i32> {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    }
+
+    #[cfg(not(unix))]
+    fn signal(_status: ExitStatus) -> Option<i32> {
+        None
+    }
+
+    /// One rule of `--exit-code-map`: keep the natural status, or use a fixed one.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Rule {
+        Raw,
+        Fixed(u8),
+    }
+
+    /// `--exit-code-map compile=N,runtime=N,signal=N`, each rule `raw` by default:
+    /// sh2c's code for compile errors, the script's own non-zero status, and
+    /// 128+N for a script killed by signal N.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct ExitCodeMap {
+        pub compile: Rule,
+        pub runtime: Rule,
+        pub signal: Rule,
+    }
+
+    impl Default for ExitCodeMap {
+        fn default() -> Self {
+            Self { compile: Rule::Raw, runtime: Rule::Raw, signal: Rule::Raw }
+        }
+    }
+
+    impl ExitCodeMap {
+        pub fn parse(spec: &str) -> Result<Self, String> {
+            let mut map = Self::default();
+            for item in spec.split(',') {
+                let (key, value) = item
+                    .split_once('=')
+                    .ok_or_else(|| format!("--exit-code-map: expected key=value, got '{}'", item))?;
+                let rule = match value {
+                    "raw" => Rule::Raw,
+                    n => Rule::Fixed(n.parse().map_err(|_| {
+                        format!("--exit-code-map: {} must be raw or 0-255, got '{}'", key, n)
+                    })?),
+                };
+                match key {
+                    "compile" => map.compile = rule,
+                    "runtime" => map.runtime = rule,
+                    "signal" => map.signal = rule,
+                    _ => {
+                        return Err(format!(
+                            "--exit-code-map: unknown key '{}' (expected compile, runtime or signal)",
+                            key
+                        ));
+                    }
+                }
+            }
+            Ok(map)
+        }
+
+        /// Exit status for a failed compile that sh2c reported with `driver_code`.
+        pub fn compile(&self, driver_code: i32) -> ExitCode {
+            match self.compile {
+                Rule::Raw => super::from_driver_code(driver_code),
+                Rule::Fixed(n) => ExitCode::from(n),
+            }
+        }
+
+        /// Exit status for a script that ran; success always stays 0.
+        pub fn runtime(&self, status: ExitStatus) -> ExitCode {
+            if status.success() {
+                return ExitCode::SUCCESS;
+            }
+            let rule = if status.code().is_some() { self.runtime } else { self.signal };
+            match rule {
+                Rule::Raw => from_status(status),
+                Rule::Fixed(n) => ExitCode::from(n),
+            }
+        }
+    }
 }
 
 pub fn from_driver_code(code: i32) -> ExitCode {
@@ -1,31 +1,54 @@
 use std::env;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitCode};
+use std::process::{ExitCode, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
 
 use tempfile::NamedTempFile;
 use sh2c::driver::{self, CompileOptions};
 use sh2c::codegen::TargetShell;
 
 // Use internal library components (defined in lib.rs)
-use sh2do::from_driver_code;
-use sh2do::exit_code;
+use sh2do::exit_code::ExitCodeMap;
+use sh2do::config;
+use sh2do::container;
+use sh2do::platform;
+use sh2do::remote;
 
 const HELP_TEXT: &str = "\
 Usage: sh2do [flags] <file.sh2> [flags] -- [args...]
        sh2do [flags] '<snippet>' [flags] -- [args...]
        sh2do -
+       sh2do -c '<snippet>' [name [args...]]
+       sh2do -e '<stmt>' [-e '<stmt>'...] [flags] -- [args...]
 
 Compile and execute sh2 snippets or files.
 
 Flags:
-  -e, --emit     Emit compiled script (valid for file mode) and run it
+  -c <snippet>   Run <snippet> like sh -c; operands after it are $0 and args
+  -e <stmt>      Add a statement to the snippet (repeatable, kept in order)
+  --emit         Emit compiled script (valid for file mode) and run it
   -o <path>      Explicit output path (implies run unless --no-exec)
   --emit-sh      Compile and emit shell to stdout, do not execute
   --no-exec      Alias of --emit-sh
   --target <t>   Target shell: bash (default) or posix
   --shell <s>    Override runtime shell (bash or sh)
+  --keep-temp[=<dir>]
+                 Keep the generated script (in <dir> if given) and print its path
+  --show-path    Print the generated script's path before running it
+  --stats        Print compile time, script size, run time and exit status
+  --profile      Time each statement and print the slowest lines at exit
+  --debug        Pause at breakpoint() statements (or before the first
+                 statement if there are none) with a debugger prompt
+  --break <file:line>
+                 Also pause at this line (repeatable; implies --debug)
+  --ssh <dest>   Run the script on <dest> (user@host) over ssh instead of locally
+  --container <image>
+                 Run the script in a throwaway docker/podman container of <image>
+  --exit-code-map <spec>
+                 Remap exit statuses: compile=N,runtime=N,signal=N (each N
+                 is 0-255 or raw, the default)
   -h, --help     Show this help and exit
   -V, --version  Print version information and exit
 
@@ -35,16 +58,25 @@ Snippet input:
   -              Read snippet from stdin
 
 Arguments:
-  Everything after -- is passed to the executed script
+  Everything after -- is passed to the executed script. In file mode, so are
+  operands after <file.sh2>, which lets a file start with #!/usr/bin/env sh2do;
+  put arguments that start with '-' after --
+
+Defaults:
+  SH2DO_TARGET and SH2DO_SHELL, then target = \"...\" / shell = \"...\" lines in
+  $XDG_CONFIG_HOME/sh2do/config.toml (~/.config/sh2do/config.toml), apply when
+  --target is not given
 
 Exit codes:
   Compile error: exits with sh2c's code
   Runtime error: exits with script's code
+  Killed by signal N: exits with 128+N
 
 Examples:
   sh2do script.sh2
   sh2do 'print(\"hi\")'
   sh2do 'run(\"ls\")'
+  sh2do -e 'let n = arg(1)' -e 'print(n)' -- world
 ";
 
 fn main() -> ExitCode {
@@ -62,9 +94,21 @@ struct ParsedArgs {
     snippet_arg: String,
     target: Option<String>,
     shell: Option<String>,
-    emit_and_run: bool, // --emit / -e
+    /// Snippet given with `-c`, or the `-e` statements joined in order.
+    inline: Option<String>,
+    emit_and_run: bool, // --emit
     out_path: Option<String>, // -o
     emit_stdout: bool, // --emit-sh / --no-exec
+    keep_temp: bool, // --keep-temp[=<dir>]
+    keep_dir: Option<PathBuf>,
+    show_path: bool, // --show-path
+    exit_map: ExitCodeMap, // --exit-code-map
+    stats: bool, // --stats
+    ssh: Option<String>, // --ssh
+    container: Option<String>, // --container
+    profile: bool, // --profile
+    debug: bool, // --debug
+    breaks: Vec<String>, // --break
     passthrough: Vec<String>,
 }
 
@@ -89,15 +133,30 @@ fn run() -> Result<ExitCode, String> {
 
     let mut parsed = ParsedArgs {
         snippet_arg: String::new(),
+        inline: None,
         target: None,
         shell: None,
         emit_and_run: false,
         out_path: None,
         emit_stdout: false,
+        keep_temp: false,
+        keep_dir: None,
+        show_path: false,
+        exit_map: ExitCodeMap::default(),
+        stats: false,
+        ssh: None,
+        container: None,
+        profile: false,
+        debug: false,
+        breaks: Vec::new(),
         passthrough,
     };
 
     let mut snippet_found = false;
+    // Operands after the file, as when a `#!/usr/bin/env sh2do` script is run.
+    let mut operands: Vec<String> = Vec::new();
+    let mut command = None;
+    let mut stmts: Vec<String> = Vec::new();
     let mut i = 0;
     while i < pre_args.len() {
         let arg = &pre_args[i];
@@ -124,12 +183,86 @@ fn run() -> Result<ExitCode, String> {
             } else {
                 return Err("-o requires a value".to_string());
             }
-        } else if arg == "--emit" || arg == "-e" {
+        } else if arg == "-c" {
+            let Some(code) = pre_args.get(i + 1) else {
+                return Err("-c requires a value".to_string());
+            };
+            command = Some(code.clone());
+            // As with `sh -c`, the operands that follow name the script ($0,
+            // which sh2do cannot set) and supply its arguments.
+            let mut operands: Vec<String> = pre_args[i + 2..].iter().skip(1).cloned().collect();
+            operands.append(&mut parsed.passthrough);
+            parsed.passthrough = operands;
+            break;
+        } else if arg == "-e" {
+            let Some(stmt) = pre_args.get(i + 1) else {
+                return Err("-e requires a value".to_string());
+            };
+            // `-e` used to be short for `--emit`; catch the old `-e script.sh2`.
+            if stmt.ends_with(".sh2") || Path::new(stmt).is_file() {
+                return Err(format!("-e now adds a statement; use --emit to emit and run {}", stmt));
+            }
+            stmts.push(stmt.clone());
+            i += 2;
+        } else if arg == "--emit" {
             parsed.emit_and_run = true;
             i += 1;
         } else if arg == "--emit-sh" || arg == "--no-exec" {
             parsed.emit_stdout = true;
             i += 1;
+        } else if arg == "--keep-temp" {
+            parsed.keep_temp = true;
+            i += 1;
+        } else if let Some(dir) = arg.strip_prefix("--keep-temp=") {
+            if dir.is_empty() {
+                return Err("--keep-temp= requires a directory".to_string());
+            }
+            parsed.keep_temp = true;
+            parsed.keep_dir = Some(PathBuf::from(dir));
+            i += 1;
+        } else if arg == "--show-path" {
+            parsed.show_path = true;
+            i += 1;
+        } else if arg == "--stats" {
+            parsed.stats = true;
+            i += 1;
+        } else if arg == "--profile" {
+            parsed.profile = true;
+            i += 1;
+        } else if arg == "--debug" {
+            parsed.debug = true;
+            i += 1;
+        } else if arg == "--break" {
+            let spot = match pre_args.get(i + 1) {
+                Some(spot) if spot.rsplit_once(':').is_some_and(|(file, line)| !file.is_empty() && line.parse::<u32>().is_ok()) => spot,
+                _ => return Err("--break requires a position such as deploy.sh2:12".to_string()),
+            };
+            parsed.breaks.push(spot.clone());
+            parsed.debug = true;
+            i += 2;
+        } else if arg == "--ssh" {
+            let Some(dest) = pre_args.get(i + 1) else {
+                return Err("--ssh requires a destination such as user@host".to_string());
+            };
+            parsed.ssh = Some(dest.clone());
+            i += 2;
+        } else if arg == "--container" {
+            let image = match pre_args.get(i + 1) {
+                Some(image) if !image.starts_with('-') => image,
+                _ => return Err("--container requires an image such as alpine:3.20".to_string()),
+            };
+            parsed.container = Some(image.clone());
+            i += 2;
+        } else if arg == "--exit-code-map" || arg.starts_with("--exit-code-map=") {
+            let spec = match arg.strip_prefix("--exit-code-map=") {
+                Some(spec) => spec,
+                None => {
+                    i += 1;
+                    pre_args.get(i).ok_or("--exit-code-map requires a value")?
+                }
+            };
+            parsed.exit_map = ExitCodeMap::parse(spec)?;
+            i += 1;
         } else if arg.starts_with("-") && arg != "-" {
             // Unknown flag
             return Err(format!("unexpected argument: {}", arg));
@@ -140,12 +273,21 @@ fn run() -> Result<ExitCode, String> {
                 snippet_found = true;
                 i += 1;
             } else {
-                return Err(format!("unexpected argument: {}", arg));
+                operands.push(arg.clone());
+                i += 1;
             }
         }
     }
 
-    if !snippet_found {
+    if command.is_some() && !stmts.is_empty() {
+        return Err("-c cannot be combined with -e".to_string());
+    }
+    if command.is_some() || !stmts.is_empty() {
+        if snippet_found {
+            return Err(format!("unexpected argument: {}", parsed.snippet_arg));
+        }
+        parsed.inline = command.or_else(|| Some(stmts.join("\n")));
+    } else if !snippet_found {
         return Err("missing sh2 snippet or file argument (or '-')".to_string());
     }
 
@@ -164,13 +306,45 @@ fn run() -> Result<ExitCode, String> {
         return Err(format!("Path is a directory, expected file: {}", parsed.snippet_arg));
     }
     
-    let is_file_mode = parsed.snippet_arg != "-" && (is_file_ext || is_existing_file);
+    let is_file_mode = parsed.inline.is_none() && parsed.snippet_arg != "-" && (is_file_ext || is_existing_file);
+    if let Some(first) = operands.first() {
+        if !is_file_mode {
+            return Err(format!("unexpected argument: {}", first));
+        }
+        operands.append(&mut parsed.passthrough);
+        parsed.passthrough = operands;
+    }
     
     // Validation: --emit is file-mode only
     if parsed.emit_and_run && !is_file_mode {
         return Err("--emit is only valid when running a file; for inline, use --emit-sh > out.sh".to_string());
     }
 
+    // The script never exists on the remote side as a file, so there is no
+    // path to keep or show there.
+    if parsed.ssh.is_some() {
+        if parsed.emit_stdout {
+            return Err("--ssh cannot be combined with --emit-sh; the script would not run".to_string());
+        }
+        if parsed.keep_temp || parsed.show_path {
+            return Err("--ssh cannot be combined with --keep-temp or --show-path; use -o to keep a local copy".to_string());
+        }
+    }
+
+    // The debugger prompt reads from the local terminal.
+    if parsed.debug && (parsed.ssh.is_some() || parsed.container.is_some()) {
+        return Err("--debug cannot be combined with --ssh or --container".to_string());
+    }
+
+    if parsed.container.is_some() {
+        if parsed.ssh.is_some() {
+            return Err("--container cannot be combined with --ssh".to_string());
+        }
+        if parsed.emit_stdout {
+            return Err("--container cannot be combined with --emit-sh; the script would not run".to_string());
+        }
+    }
+
     // Prepare Source
     let (src_path, _temp_src) = if is_file_mode {
         let p = Path::new(&parsed.snippet_arg);
@@ -188,13 +362,25 @@ fn run() -> Result<ExitCode, String> {
         (p.to_path_buf(), None)
     } else {
         // Inline mode
-        let content = read_snippet(parsed.snippet_arg.clone())?;
+        let content = match parsed.inline.take() {
+            Some(code) => platform::normalize_newlines(&code),
+            None => read_snippet(parsed.snippet_arg.clone())?,
+        };
         let wrapped = wrap_snippet(&content);
         let t = NamedTempFile::new().map_err(|e| format!("failed to create temp file: {}", e))?;
         fs::write(t.path(), wrapped).map_err(|e| format!("failed to write snippet: {}", e))?;
         (t.path().to_path_buf(), Some(t))
     };
     
+    // SH2DO_TARGET / SH2DO_SHELL and the config file fill in what the command
+    // line leaves out. The runtime shell follows the target, so a default
+    // shell is only used when the target is not given on the command line.
+    if parsed.target.is_none() {
+        let defaults = config::load()?;
+        parsed.target = defaults.target;
+        parsed.shell = parsed.shell.or(defaults.shell);
+    }
+
     // Check Target Shell mismatch
     // Default runtime: target=bash => bash, target=posix => sh
     let target_enum = match parsed.target.as_deref() {
@@ -228,7 +414,10 @@ fn run() -> Result<ExitCode, String> {
     // If --emit (and file mode): use <src_stem>.sh
     // Else: temp file
     
-    let (out_path, _temp_out) = if parsed.emit_stdout {
+    let (out_path, temp_out) = if parsed.emit_stdout {
+        (None, None)
+    } else if parsed.ssh.is_some() && parsed.out_path.is_none() && !parsed.emit_and_run {
+        // Streamed to ssh from memory; nothing to write locally.
         (None, None)
     } else if let Some(o) = parsed.out_path {
         (Some(PathBuf::from(o)), None)
@@ -239,8 +428,17 @@ fn run() -> Result<ExitCode, String> {
         p.push(format!("{}.sh", stem.to_string_lossy()));
         (Some(p), None)
     } else {
-        // temp file
-        let t = NamedTempFile::new().map_err(|e| format!("failed to create output temp file: {}", e))?;
+        // temp file, named so a kept script is recognisable
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("sh2do-").suffix(".sh");
+        let t = match &parsed.keep_dir {
+            Some(dir) => builder
+                .tempfile_in(dir)
+                .map_err(|e| format!("failed to create output file in {}: {}", dir.display(), e))?,
+            None => builder
+                .tempfile()
+                .map_err(|e| format!("failed to create output temp file: {}", e))?,
+        };
         (Some(t.path().to_path_buf()), Some(t))
     };
     
@@ -250,54 +448,163 @@ fn run() -> Result<ExitCode, String> {
         include_diagnostics: true,
         out_path: out_path.clone(),
         chmod_x: true, // sh2do is a runner, so we want +x
+        debug: parsed.debug,
+        profile: parsed.profile,
         ..Default::default()
     };
     
-    // driver::compile_file returns Result<String, DriverError>
-    let generated_code = match driver::compile_file(&src_path, options) {
-        Ok(s) => s,
+    let mut stats = RunStats::default();
+    let generated_code = match driver::compile_file_timed(&src_path, options) {
+        Ok((s, _, timings)) => {
+            stats.compile = Some(timings.total);
+            stats.script_bytes = Some(s.len());
+            s
+        }
         Err(e) => {
             eprintln!("{}", e.msg);
-            return Ok(from_driver_code(e.code));
+            return Ok(parsed.exit_map.compile(e.code));
         }
     };
 
     if parsed.emit_stdout {
         print!("{}", generated_code);
+        if parsed.stats {
+            eprint!("{}", stats.report());
+        }
         return Ok(ExitCode::SUCCESS);
     }
     
+    if let Some(dest) = &parsed.ssh {
+        let status = run_remote(dest, &runtime_shell_bin, &parsed.passthrough, &generated_code, &mut stats)?;
+        if parsed.stats {
+            eprint!("{}", stats.report());
+        }
+        // ssh exits with the remote script's status, or 255 if it failed itself.
+        return Ok(parsed.exit_map.runtime(status));
+    }
+
     // Execution
     let script_path = out_path.unwrap(); // Must exist if not emit_stdout
+    if parsed.show_path {
+        eprintln!("sh2do: script: {}", script_path.display());
+    }
+    // A kept temp script outlives sh2do for post-mortem debugging.
+    let kept = parsed.keep_temp && temp_out.is_some();
+    if kept && let Some(t) = temp_out {
+        t.keep().map_err(|e| format!("failed to keep {}: {}", script_path.display(), e))?;
+    }
     
-    let mut cmd = Command::new(&runtime_shell_bin);
-    // bash -- <out_path> <args...>
-    // Safety: Inject -- before script path to protect against script path starting with -
-    cmd.arg("--");
-    cmd.arg(&script_path);
-    
+    // bash -- <out_path> <args...>, via Git for Windows or WSL on Windows, or
+    // the image's own shell with --container
+    let mut cmd = match &parsed.container {
+        Some(image) => {
+            let runtime = container::detect_runtime()?;
+            let script_path = fs::canonicalize(&script_path).unwrap_or_else(|_| script_path.clone());
+            container::run_command(&runtime, image, &runtime_shell_bin, &script_path)
+        }
+        None => {
+            let host = platform::detect()?;
+            platform::shell_command(&host, &runtime_shell_bin, &script_path)
+        }
+    };
+
     if !parsed.passthrough.is_empty() {
         cmd.args(&parsed.passthrough);
     }
+    if !parsed.breaks.is_empty() {
+        cmd.env("SH2_BREAK", parsed.breaks.join(" "));
+    }
     
     cmd.stdout(std::process::Stdio::inherit())
        .stderr(std::process::Stdio::inherit());
        
+    let started = Instant::now();
     let status = cmd.status()
-        .map_err(|e| format!("failed to execute {}: {}", runtime_shell_bin, e))?;
+        .map_err(|e| format!("failed to execute {}: {}", cmd.get_program().to_string_lossy(), e))?;
+    stats.run = Some(started.elapsed());
+    stats.status = Some(status);
+    if parsed.stats {
+        eprint!("{}", stats.report());
+    }
+    if kept {
+        eprintln!("sh2do: kept {}", script_path.display());
+    }
+
+    // Signals map to 128+N, like a shell reports them, unless remapped.
+    Ok(parsed.exit_map.runtime(status))
+}
+/// Pipes `script` into `<shell> -s` on `dest` and waits for ssh to exit.
+fn run_remote(dest: &str, shell: &str, args: &[String], script: &str, stats: &mut RunStats) -> Result<ExitStatus, String> {
+    let mut cmd = remote::ssh_command(dest, shell, args);
+    cmd.stdin(Stdio::piped()).stdout(Stdio::inherit()).stderr(Stdio::inherit());
+
+    let started = Instant::now();
+    let mut child = cmd.spawn().map_err(|e| format!("failed to execute ssh: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A remote side that exits early closes the pipe; its status says why.
+        match stdin.write_all(script.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                return Err(format!("failed to send script to ssh: {}", e));
+            }
+            _ => {}
+        }
+    }
+    let status = child.wait().map_err(|e| format!("failed to wait for ssh: {}", e))?;
+    stats.run = Some(started.elapsed());
+    stats.status = Some(status);
+    Ok(status)
+}
 
-    // Use robust status code mapping (defaults to 1 if None or out of range)
-    Ok(exit_code::from_i32(status.code().unwrap_or(1)))
+/// What `--stats` prints to stderr once sh2do is done.
+#[derive(Default)]
+struct RunStats {
+    compile: Option<Duration>,
+    script_bytes: Option<usize>,
+    run: Option<Duration>,
+    status: Option<ExitStatus>,
 }
+
+impl RunStats {
+    fn report(&self) -> String {
+        let ms = |d: Duration| format!("{:.3} ms", d.as_secs_f64() * 1000.0);
+        let mut out = String::from("stats:\n");
+        let rows = [
+            ("compile", self.compile.map(ms)),
+            ("script", self.script_bytes.map(|n| format!("{} bytes", n))),
+            ("run", self.run.map(ms)),
+            ("exit", self.status.map(describe_status)),
+        ];
+        for (name, value) in rows {
+            if let Some(value) = value {
+                out.push_str(&format!("  {:<8}{:>16}\n", name, value));
+            }
+        }
+        out
+    }
+}
+
+fn describe_status(status: ExitStatus) -> String {
+    match status.code() {
+        Some(code) => code.to_string(),
+        None => {
+            #[cfg(unix)]
+            if let Some(sig) = std::os::unix::process::ExitStatusExt::signal(&status) {
+                return format!("signal {}", sig);
+            }
+            "unknown".to_string()
+        }
+    }
+}
+
 fn read_snippet(arg: String) -> Result<String, String> {
     if arg == "-" {
         let mut buf = String::new();
         io::stdin()
             .read_to_string(&mut buf)
             .map_err(|e| e.to_string())?;
-        Ok(buf)
+        Ok(platform::normalize_newlines(&buf))
     } else {
-        Ok(arg)
+        Ok(platform::normalize_newlines(&arg))
     }
 }
 
@@ -0,0 +1,102 @@
+//! Finding the shell that runs a generated script. On Unix that is `bash` or
+//! `sh` from `PATH`. On Windows it is Git for Windows' bash (MSYS paths such
+//! as `/c/Users/...`) or, failing that, WSL (`/mnt/c/Users/...`); both need the
+//! script path rewritten into their own spelling.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Host {
+    /// `bash` / `sh` found on `PATH`.
+    Native,
+    /// Git for Windows; `bin_dir` holds `bash.exe` and `sh.exe`.
+    GitBash { bin_dir: PathBuf },
+    /// The WSL launcher, `wsl.exe`.
+    Wsl { exe: PathBuf },
+}
+
+/// The host to run scripts with on this machine.
+#[cfg(not(windows))]
+pub fn detect() -> Result<Host, String> {
+    Ok(Host::Native)
+}
+
+/// The host to run scripts with on this machine: Git for Windows is preferred
+/// because it shares the Windows filesystem view; WSL is the fallback.
+#[cfg(windows)]
+pub fn detect() -> Result<Host, String> {
+    let env_dir = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let git_roots = [
+        env_dir("ProgramW6432"),
+        env_dir("ProgramFiles"),
+        env_dir("ProgramFiles(x86)"),
+        env_dir("LOCALAPPDATA").map(|d| d.join("Programs")),
+    ];
+    for root in git_roots.into_iter().flatten() {
+        let bin_dir = root.join("Git").join("bin");
+        if bin_dir.join("bash.exe").is_file() {
+            return Ok(Host::GitBash { bin_dir });
+        }
+    }
+    if let Some(root) = env_dir("SystemRoot") {
+        let exe = root.join("System32").join("wsl.exe");
+        if exe.is_file() {
+            return Ok(Host::Wsl { exe });
+        }
+    }
+    Err("no bash found: install Git for Windows or WSL to run sh2 scripts on Windows".to_string())
+}
+
+/// A command that runs `script` with `shell` (`bash` or `sh`) on `host`; the
+/// caller appends the script's arguments.
+pub fn shell_command(host: &Host, shell: &str, script: &Path) -> Command {
+    let script = script.to_string_lossy();
+    let (mut cmd, script) = match host {
+        Host::Native => (Command::new(shell), script.into_owned()),
+        Host::GitBash { bin_dir } => (Command::new(bin_dir.join(format!("{}.exe", shell))), msys_path(&script)),
+        Host::Wsl { exe } => {
+            let mut cmd = Command::new(exe);
+            cmd.args(["-e", shell]);
+            (cmd, wsl_path(&script))
+        }
+    };
+    // Safety: `--` keeps a script path starting with `-` from being read as an option.
+    cmd.arg("--").arg(script);
+    cmd
+}
+
+/// Splits `C:\dir\file` into the lowercase drive letter and the rest with
+/// forward slashes; `None` for paths without a drive letter.
+fn split_drive(path: &str) -> Option<(char, String)> {
+    let path = path.strip_prefix(r"\\?\").unwrap_or(path);
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    if chars.next() != Some(':') {
+        return None;
+    }
+    let rest = chars.as_str().replace('\\', "/");
+    Some((drive.to_ascii_lowercase(), rest.trim_start_matches('/').to_string()))
+}
+
+/// `C:\Users\me\x.sh` as Git for Windows' bash spells it: `/c/Users/me/x.sh`.
+pub fn msys_path(path: &str) -> String {
+    match split_drive(path) {
+        Some((drive, rest)) => format!("/{}/{}", drive, rest),
+        None => path.replace('\\', "/"),
+    }
+}
+
+/// `C:\Users\me\x.sh` as WSL spells it: `/mnt/c/Users/me/x.sh`.
+pub fn wsl_path(path: &str) -> String {
+    match split_drive(path) {
+        Some((drive, rest)) => format!("/mnt/{}/{}", drive, rest),
+        None => path.replace('\\', "/"),
+    }
+}
+
+/// Windows line endings in a snippet (from PowerShell or a CRLF file piped to
+/// `sh2do -`) would otherwise reach the lexer as stray `\r` characters.
+pub fn normalize_newlines(snippet: &str) -> String {
+    snippet.replace("\r\n", "\n").replace('\r', "\n")
+}
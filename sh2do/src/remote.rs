@@ -0,0 +1,39 @@
+//! `--ssh <destination>`: run a locally compiled script on another machine.
+//!
+//! The script travels over ssh's stdin to `<shell> -s` on the remote side, so
+//! nothing is copied to the remote filesystem. ssh joins its command operands
+//! into one string for the remote login shell to parse, so every script
+//! argument is single-quoted here rather than passed through as-is.
+
+use std::process::Command;
+
+/// `ssh -- <destination> '<shell> -s -- <args...>'`, with the script to be
+/// written to the command's stdin.
+pub fn ssh_command(destination: &str, shell: &str, args: &[String]) -> Command {
+    let mut remote = format!("{} -s --", shell);
+    for arg in args {
+        remote.push(' ');
+        remote.push_str(&quote(arg));
+    }
+    let mut cmd = Command::new("ssh");
+    // `--` keeps a destination starting with `-` from being read as an option.
+    cmd.arg("--").arg(destination).arg(remote);
+    cmd
+}
+
+/// Quotes `arg` for a POSIX shell: `it's` becomes `'it'\''s'`.
+pub fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_every_argument() {
+        let cmd = ssh_command("me@box", "bash", &["a b".to_string(), "it's".to_string(), "$HOME".to_string()]);
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(args, ["--", "me@box", r#"bash -s -- 'a b' 'it'\''s' '$HOME'"#]);
+    }
+}
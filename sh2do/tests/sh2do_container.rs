@@ -0,0 +1,91 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+
+/// A stand-in container engine that logs its arguments and runs the mounted
+/// script on the host with the requested shell.
+const FAKE_ENGINE: &str = r#"#!/bin/sh
+printf '%s\n' "$@" > "$(dirname "$0")/argv"
+shift 4
+src=${1%%:/sh2do/script.sh:ro}
+shell=$3
+shift 4
+exec "$shell" "$src" "$@"
+"#;
+
+fn sh2do_in_fake_container(args: &[&str]) -> (std::process::Output, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = dir.path().join("engine");
+    fs::write(&engine, FAKE_ENGINE).unwrap();
+    fs::set_permissions(&engine, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sh2do"))
+        .args(args)
+        .env("SH2DO_CONTAINER_RUNTIME", &engine)
+        .output()
+        .expect("Failed to run sh2do");
+    let argv = fs::read_to_string(dir.path().join("argv")).unwrap_or_default();
+    (output, argv)
+}
+
+#[test]
+fn test_container_mounts_script_and_passes_args() {
+    let (output, argv) = sh2do_in_fake_container(&[
+        "--container",
+        "alpine:3.20",
+        "--target",
+        "posix",
+        r#"print(arg(1)); exit(5)"#,
+        "--",
+        "a b",
+    ]);
+    assert_eq!(output.status.code(), Some(5), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\n");
+
+    let argv: Vec<&str> = argv.lines().collect();
+    assert_eq!(argv[..4], ["run", "--rm", "-i", "-v"], "{:?}", argv);
+    assert!(argv[4].starts_with('/') && argv[4].ends_with(".sh:/sh2do/script.sh:ro"), "{:?}", argv);
+    assert_eq!(argv[5..], ["alpine:3.20", "sh", "/sh2do/script.sh", "a b"], "{:?}", argv);
+}
+
+#[test]
+fn test_container_mounts_relative_output_by_absolute_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = dir.path().join("engine");
+    fs::write(&engine, FAKE_ENGINE).unwrap();
+    fs::set_permissions(&engine, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sh2do"))
+        .current_dir(dir.path())
+        .args(["--container", "debian", "-o", "out.sh", r#"print("hi")"#])
+        .env("SH2DO_CONTAINER_RUNTIME", &engine)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+    let argv = fs::read_to_string(dir.path().join("argv")).unwrap();
+    let mount = argv.lines().nth(4).unwrap();
+    assert!(mount.starts_with('/') && mount.ends_with("/out.sh:/sh2do/script.sh:ro"), "{}", mount);
+}
+
+#[test]
+fn test_container_rejects_conflicting_flags() {
+    for (flag, value) in [("--emit-sh", None), ("--ssh", Some("web1"))] {
+        let mut args = vec!["--container", "alpine", flag];
+        args.extend(value);
+        args.push(r#"print("hi")"#);
+        let (output, argv) = sh2do_in_fake_container(&args);
+        assert_eq!(output.status.code(), Some(1), "{}", flag);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--container cannot be combined with"), "{}", flag);
+        assert!(argv.is_empty(), "engine ran with {}", flag);
+    }
+}
+
+#[test]
+fn test_container_requires_image() {
+    let (output, _) = sh2do_in_fake_container(&["--container", "--emit-sh", r#"print("hi")"#]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--container requires an image"));
+}
@@ -0,0 +1,52 @@
+use std::process::Command;
+
+fn sh2do() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2do"))
+}
+
+fn run(args: &[&str]) -> (String, String, Option<i32>) {
+    let output = sh2do().args(args).output().expect("Failed to run sh2do");
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+        output.status.code(),
+    )
+}
+
+#[test]
+fn test_dash_c_takes_operands_like_sh() {
+    // The first operand stands in for $0; the rest are the script's arguments.
+    let (stdout, stderr, code) = run(&["-c", r#"print(argc()); print(arg(1))"#, "name", "one", "two"]);
+    assert_eq!(code, Some(0), "stderr: {}", stderr);
+    assert_eq!(stdout, "2\none\n");
+}
+
+#[test]
+fn test_dash_c_with_posix_target_and_exit_status() {
+    let (stdout, _, code) = run(&["--target", "posix", "-c", r#"print("hi"); exit(3)"#]);
+    assert_eq!(stdout, "hi\n");
+    assert_eq!(code, Some(3));
+}
+
+#[test]
+fn test_multiple_dash_e_run_in_order() {
+    let (stdout, stderr, code) = run(&["-e", "let who = arg(1)", "-e", r#"print("a " & who)"#, "-e", r#"print("b")"#, "--", "x"]);
+    assert_eq!(code, Some(0), "stderr: {}", stderr);
+    assert_eq!(stdout, "a x\nb\n");
+}
+
+#[test]
+fn test_dash_c_and_dash_e_errors() {
+    for (args, expected) in [
+        (&["-c"][..], "-c requires a value"),
+        (&["-e"][..], "-e requires a value"),
+        (&["-e", "print(1)", "-c", "print(2)"][..], "-c cannot be combined with -e"),
+        (&["-e", "print(1)", "script.sh2"][..], "unexpected argument: script.sh2"),
+        (&["-e", "script.sh2"][..], "-e now adds a statement; use --emit to emit and run script.sh2"),
+        (&["-e", "Cargo.toml"][..], "-e now adds a statement; use --emit"),
+    ] {
+        let (_, stderr, code) = run(args);
+        assert_eq!(code, Some(1), "{:?}", args);
+        assert!(stderr.contains(expected), "{:?}: {}", args, stderr);
+    }
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use std::process::Command;
+
+const SCRIPT: &str = "func main() {\n    let x = \"hi\"\n    breakpoint()\n    print(x)\n    print(\"done\")\n}\n";
+
+/// Runs sh2do on `script.sh2` (containing `src`) with `commands` as the debugger input.
+fn debug_run(src: &str, commands: &str, args: &[&str]) -> std::process::Output {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("script.sh2"), src).unwrap();
+    fs::write(dir.path().join("commands"), commands).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_sh2do"))
+        .current_dir(dir.path())
+        .args(args)
+        .arg("script.sh2")
+        .env("SH2_DEBUG_INPUT", dir.path().join("commands"))
+        .env_remove("SH2_BREAK")
+        .output()
+        .expect("Failed to run sh2do")
+}
+
+#[test]
+fn test_breakpoint_shows_vars_and_steps() {
+    for target in ["bash", "posix"] {
+        let out = debug_run(SCRIPT, "v\np x\np nope\ns\nc\n", &["--debug", "--target", target]);
+        assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "hi\ndone\n");
+        assert_eq!(
+            String::from_utf8_lossy(&out.stderr),
+            "[debug] script.sh2:3:5: breakpoint()\n\
+             (debug) x=hi\n\
+             (debug) x=hi\n\
+             (debug) nope is not set\n\
+             (debug) [debug] script.sh2:4:5: print(x)\n\
+             (debug) ",
+            "target {}",
+            target
+        );
+    }
+}
+
+#[test]
+fn test_break_at_line_and_continue() {
+    let out = debug_run(SCRIPT, "c\nc\n", &["--break", "script.sh2:5"]);
+    assert!(out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("[debug] script.sh2:3:5: breakpoint()\n"), "{}", stderr);
+    assert!(stderr.contains("[debug] script.sh2:5:5: print(\"done\")\n"), "{}", stderr);
+    assert!(!stderr.contains("script.sh2:4:5"), "{}", stderr);
+}
+
+#[test]
+fn test_steps_from_the_start_without_breakpoints() {
+    let src = "func main() {\n    let n = 1\n    print(n)\n}\n";
+    let out = debug_run(src, "", &["--debug"]);
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "1\n");
+    // End of input lets the script finish without stopping again.
+    assert_eq!(String::from_utf8_lossy(&out.stderr), "[debug] script.sh2:2:5: let n = 1\n(debug) \n");
+}
+
+#[test]
+fn test_quit_stops_the_script() {
+    let out = debug_run(SCRIPT, "q\n", &["--debug"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(out.stdout.is_empty());
+}
+
+#[test]
+fn test_breakpoint_is_ignored_without_debug() {
+    let out = debug_run(SCRIPT, "q\n", &[]);
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "hi\ndone\n");
+    assert!(out.stderr.is_empty(), "{}", String::from_utf8_lossy(&out.stderr));
+}
+
+#[test]
+fn test_debug_rejects_bad_arguments() {
+    let out = debug_run(SCRIPT, "", &["--debug", "--ssh", "web1"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--debug cannot be combined with --ssh or --container"));
+
+    let out = debug_run(SCRIPT, "", &["--break", "script.sh2"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--break requires a position"));
+
+    let out = debug_run("func main() {\n    breakpoint(1)\n}\n", "", &["--debug"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("breakpoint() takes no arguments"));
+}
@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// sh2do with a private config home and no inherited defaults.
+fn sh2do(config_home: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_sh2do"));
+    cmd.env("XDG_CONFIG_HOME", config_home)
+        .env_remove("SH2DO_TARGET")
+        .env_remove("SH2DO_SHELL");
+    cmd
+}
+
+fn write_config(config_home: &Path, text: &str) {
+    fs::create_dir_all(config_home.join("sh2do")).unwrap();
+    fs::write(config_home.join("sh2do/config.toml"), text).unwrap();
+}
+
+fn shebang(output: &std::process::Output) -> String {
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or("").to_string()
+}
+
+#[test]
+fn test_env_target_default() {
+    let home = tempfile::tempdir().unwrap();
+    let output = sh2do(home.path())
+        .env("SH2DO_TARGET", "posix")
+        .args(["--emit-sh", r#"print("x")"#])
+        .output()
+        .unwrap();
+    assert_eq!(shebang(&output), "#!/bin/sh");
+}
+
+#[test]
+fn test_config_file_default_and_precedence() {
+    let home = tempfile::tempdir().unwrap();
+    write_config(home.path(), "# use sh everywhere\ntarget = \"posix\"\nshell = \"sh\"\n");
+
+    let output = sh2do(home.path()).args(["--emit-sh", r#"print("x")"#]).output().unwrap();
+    assert_eq!(shebang(&output), "#!/bin/sh");
+
+    // The environment beats the file, and the command line beats both.
+    let output = sh2do(home.path())
+        .env("SH2DO_TARGET", "bash")
+        .env("SH2DO_SHELL", "bash")
+        .args(["--emit-sh", r#"print("x")"#])
+        .output()
+        .unwrap();
+    assert_eq!(shebang(&output), "#!/usr/bin/env bash");
+
+    // An explicit --target also drops the configured shell, which follows the target.
+    let output = sh2do(home.path()).args(["--target", "bash", r#"print("ran")"#]).output().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ran\n");
+}
+
+#[test]
+fn test_invalid_defaults_are_reported() {
+    let home = tempfile::tempdir().unwrap();
+    let output = sh2do(home.path()).env("SH2DO_SHELL", "zsh").arg(r#"print("x")"#).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("SH2DO_SHELL: invalid shell 'zsh' (expected bash or sh)"));
+
+    write_config(home.path(), "target = \"posix\"\ncolor = \"on\"\n");
+    let output = sh2do(home.path()).arg(r#"print("x")"#).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("config.toml: line 2: unknown key 'color'"), "{}", stderr);
+}
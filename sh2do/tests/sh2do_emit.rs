@@ -131,7 +131,7 @@ fn test_emit_sh_compile_error() {
 
     // Should have error in stderr
     assert!(
-        stderr.contains("Expected") || stderr.contains("error"),
+        stderr.contains("Expected") || stderr.contains("error") || stderr.contains("shell syntax"),
         "Expected compile error in stderr, got: {}",
         stderr
     );
@@ -30,3 +30,43 @@ fn driver_code_zero_is_mapped_to_compile_error_2() {
     assert_eq!(from_driver_code(2), ExitCode::from(2));
     assert_eq!(from_driver_code(256), ExitCode::from(1));
 }
+
+#[test]
+#[cfg(unix)]
+fn signal_exits_map_to_128_plus_n() {
+    use std::os::unix::process::ExitStatusExt;
+    use sh2do::exit_code::{from_signal, from_status};
+    assert_eq!(from_signal(15), ExitCode::from(143));
+    // Raw wait status: low 7 bits hold the signal, the exit code sits above them.
+    assert_eq!(from_status(std::process::ExitStatus::from_raw(9)), ExitCode::from(137));
+    assert_eq!(from_status(std::process::ExitStatus::from_raw(3 << 8)), ExitCode::from(3));
+}
+
+#[test]
+#[cfg(unix)]
+fn exit_code_map_parses_and_applies_rules() {
+    use std::os::unix::process::ExitStatusExt;
+    use sh2do::exit_code::{ExitCodeMap, Rule};
+    let map = ExitCodeMap::parse("compile=125,signal=raw,runtime=1").unwrap();
+    assert_eq!(map, ExitCodeMap { compile: Rule::Fixed(125), runtime: Rule::Fixed(1), signal: Rule::Raw });
+    assert_eq!(map.compile(2), ExitCode::from(125));
+    assert_eq!(map.runtime(std::process::ExitStatus::from_raw(7 << 8)), ExitCode::from(1));
+    assert_eq!(map.runtime(std::process::ExitStatus::from_raw(15)), ExitCode::from(143));
+    assert_eq!(map.runtime(std::process::ExitStatus::from_raw(0)), ExitCode::SUCCESS);
+
+    assert!(ExitCodeMap::parse("compile=256").unwrap_err().contains("compile must be raw or 0-255"));
+    assert!(ExitCodeMap::parse("exit=1").unwrap_err().contains("unknown key 'exit'"));
+    assert!(ExitCodeMap::parse("compile").unwrap_err().contains("expected key=value"));
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_exit_code_map_separates_compile_and_runtime_failures() {
+    let run = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_sh2do")).args(args).output().unwrap().status.code()
+    };
+    assert_eq!(run(&["sh(\"kill -TERM $PPID\")"]), Some(143));
+    assert_eq!(run(&["--exit-code-map", "signal=99", "sh(\"kill -TERM $PPID\")"]), Some(99));
+    assert_eq!(run(&["--exit-code-map=compile=125", "print("]), Some(125));
+    assert_eq!(run(&["--exit-code-map=compile=125", "exit(2)"]), Some(2));
+}
@@ -378,3 +378,27 @@ fn compile_error_exit_code_is_2() {
     // Compile error should be 2
     assert_eq!(output.status.code(), Some(2));
 }
+
+#[test]
+fn test_shebang_script_runs_directly_with_args() {
+    use std::os::unix::fs::PermissionsExt;
+    let tmp = TempDir::new().unwrap();
+    let file = tmp.path().join("greet.sh2");
+    fs::write(&file, "#!/usr/bin/env sh2do\nprint(\"hello \" & arg(1) & \" \" & arg(2))\n").unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let bin_dir = std::path::Path::new(&sh2do_path()).parent().unwrap().to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(&file)
+        .args(["big", "world"])
+        .env("PATH", path)
+        .output()
+        .expect("Failed to run script");
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hello big world\n");
+
+    // Operands only follow files; after an inline snippet they are still an error.
+    let output = Command::new(sh2do_path()).args(["print(1)", "extra"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unexpected argument: extra"));
+}
@@ -0,0 +1,51 @@
+use std::fs;
+use std::process::Command;
+
+fn sh2do() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sh2do"))
+}
+
+fn stderr_path(stderr: &str, prefix: &str) -> String {
+    stderr
+        .lines()
+        .find_map(|l| l.strip_prefix(prefix))
+        .unwrap_or_else(|| panic!("no '{}' line in: {}", prefix, stderr))
+        .to_string()
+}
+
+#[test]
+fn test_keep_temp_in_dir_survives_failed_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let keep = format!("--keep-temp={}", dir.path().display());
+    let output = sh2do().args([&keep, r#"print("hi"); exit(4)"#]).output().unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let kept = stderr_path(&stderr, "sh2do: kept ");
+    assert!(kept.starts_with(&dir.path().display().to_string()), "{}", kept);
+    assert!(kept.ends_with(".sh"), "{}", kept);
+    let script = fs::read_to_string(&kept).unwrap();
+    assert!(script.contains("exit"), "{}", script);
+
+    // The kept script reproduces the run on its own.
+    let rerun = Command::new("bash").arg(&kept).output().unwrap();
+    assert_eq!(rerun.status.code(), Some(4));
+}
+
+#[test]
+fn test_show_path_without_keep_removes_script() {
+    let output = sh2do().args(["--show-path", r#"print("x")"#]).output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let path = stderr_path(&stderr, "sh2do: script: ");
+    assert!(!stderr.contains("sh2do: kept"), "{}", stderr);
+    assert!(!std::path::Path::new(&path).exists(), "temp script left behind: {}", path);
+}
+
+#[test]
+fn test_keep_temp_requires_dir_after_equals() {
+    let output = sh2do().args(["--keep-temp=", r#"print("x")"#]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--keep-temp= requires a directory"));
+}
@@ -0,0 +1,69 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+
+/// A stand-in `ssh` that logs its arguments and runs the remote command locally.
+const FAKE_SSH: &str = r#"#!/bin/sh
+printf '%s\n' "$@" > "$(dirname "$0")/argv"
+[ "$1" = "--" ] && shift
+shift
+exec sh -c "$1"
+"#;
+
+fn sh2do_via_fake_ssh(args: &[&str]) -> (std::process::Output, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let ssh = dir.path().join("ssh");
+    fs::write(&ssh, FAKE_SSH).unwrap();
+    fs::set_permissions(&ssh, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let path = format!("{}:{}", dir.path().display(), std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(env!("CARGO_BIN_EXE_sh2do"))
+        .args(args)
+        .env("PATH", path)
+        .output()
+        .expect("Failed to run sh2do");
+    let argv = fs::read_to_string(dir.path().join("argv")).unwrap_or_default();
+    (output, argv)
+}
+
+#[test]
+fn test_ssh_streams_script_and_quotes_args() {
+    let (output, argv) = sh2do_via_fake_ssh(&[
+        "--ssh",
+        "deploy@web1",
+        r#"print(arg(1)); print(arg(2)); exit(4)"#,
+        "--",
+        "a b",
+        "it's $HOME",
+    ]);
+    assert_eq!(output.status.code(), Some(4), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\nit's $HOME\n");
+    assert_eq!(argv, "--\ndeploy@web1\nbash -s -- 'a b' 'it'\\''s $HOME'\n");
+}
+
+#[test]
+fn test_ssh_uses_posix_shell_for_posix_target() {
+    let (output, argv) = sh2do_via_fake_ssh(&["--ssh", "web1", "--target", "posix", r#"print("hi")"#]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+    assert!(argv.ends_with("\nsh -s --\n"), "{}", argv);
+}
+
+#[test]
+fn test_ssh_rejects_local_only_flags() {
+    for flag in ["--emit-sh", "--keep-temp", "--show-path"] {
+        let (output, argv) = sh2do_via_fake_ssh(&["--ssh", "web1", flag, r#"print("hi")"#]);
+        assert_eq!(output.status.code(), Some(1), "{}", flag);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--ssh cannot be combined with"), "{}", flag);
+        assert!(argv.is_empty(), "ssh ran with {}", flag);
+    }
+}
+
+#[test]
+fn test_ssh_requires_destination() {
+    let (output, _) = sh2do_via_fake_ssh(&[r#"print("hi")"#, "--ssh"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--ssh requires a destination"));
+}
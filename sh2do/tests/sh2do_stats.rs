@@ -0,0 +1,42 @@
+use std::process::Command;
+
+fn sh2do(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_sh2do")).args(args).output().expect("Failed to run sh2do")
+}
+
+fn stat<'a>(stderr: &'a str, name: &str) -> Option<&'a str> {
+    stderr
+        .lines()
+        .find(|l| l.trim_start().starts_with(&format!("{} ", name)))
+        .map(|l| l.trim_start()[name.len()..].trim())
+}
+
+#[test]
+fn test_stats_after_run() {
+    let output = sh2do(&["--stats", r#"print("hi"); exit(3)"#]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("stats:\n"), "{}", stderr);
+    assert!(stat(&stderr, "compile").is_some_and(|v| v.ends_with(" ms")), "{}", stderr);
+    assert!(stat(&stderr, "script").is_some_and(|v| v.ends_with(" bytes")), "{}", stderr);
+    assert!(stat(&stderr, "run").is_some_and(|v| v.ends_with(" ms")), "{}", stderr);
+    assert_eq!(stat(&stderr, "exit"), Some("3"), "{}", stderr);
+}
+
+#[test]
+fn test_stats_with_emit_sh_has_no_run() {
+    let output = sh2do(&["--stats", "--emit-sh", r#"print("hi")"#]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let bytes = format!("{} bytes", output.stdout.len());
+    assert_eq!(stat(&stderr, "script"), Some(bytes.as_str()), "{}", stderr);
+    assert_eq!(stat(&stderr, "run"), None, "{}", stderr);
+}
+
+#[test]
+fn test_no_stats_by_default() {
+    let output = sh2do(&[r#"print("hi")"#]);
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,44 @@
+use std::path::Path;
+use std::process::Command;
+
+use sh2do::platform::{msys_path, normalize_newlines, shell_command, wsl_path, Host};
+
+#[test]
+fn windows_paths_are_translated_for_git_bash_and_wsl() {
+    let temp = r"C:\Users\me\AppData\Local\Temp\sh2do-ab12.sh";
+    assert_eq!(msys_path(temp), "/c/Users/me/AppData/Local/Temp/sh2do-ab12.sh");
+    assert_eq!(wsl_path(temp), "/mnt/c/Users/me/AppData/Local/Temp/sh2do-ab12.sh");
+    assert_eq!(msys_path(r"\\?\D:\work\x.sh"), "/d/work/x.sh");
+    assert_eq!(wsl_path("D:/work/x.sh"), "/mnt/d/work/x.sh");
+    // Relative and Unix paths only get their separators normalized.
+    assert_eq!(msys_path(r"out\x.sh"), "out/x.sh");
+    assert_eq!(wsl_path("/tmp/x.sh"), "/tmp/x.sh");
+}
+
+#[test]
+fn shell_command_uses_the_host_spelling() {
+    let script = Path::new(r"C:\Temp\s.sh");
+    let args = |cmd: &Command| cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect::<Vec<_>>();
+
+    let git = shell_command(&Host::GitBash { bin_dir: r"C:\Program Files\Git\bin".into() }, "sh", script);
+    assert!(git.get_program().to_string_lossy().ends_with("sh.exe"));
+    assert_eq!(args(&git), ["--", "/c/Temp/s.sh"]);
+
+    let wsl = shell_command(&Host::Wsl { exe: r"C:\Windows\System32\wsl.exe".into() }, "bash", script);
+    assert_eq!(args(&wsl), ["-e", "bash", "--", "/mnt/c/Temp/s.sh"]);
+
+    let native = shell_command(&Host::Native, "bash", Path::new("/tmp/s.sh"));
+    assert_eq!(native.get_program(), "bash");
+    assert_eq!(args(&native), ["--", "/tmp/s.sh"]);
+}
+
+#[test]
+fn crlf_snippets_run() {
+    assert_eq!(normalize_newlines("a\r\nb\rc\n"), "a\nb\nc\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_sh2do"))
+        .arg("print(\"a\")\r\nprint(\"b\")\r")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\nb\n");
+}